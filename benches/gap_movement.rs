@@ -0,0 +1,55 @@
+#[macro_use]
+extern crate criterion;
+extern crate gap_buffer;
+
+use criterion::{BatchSize, Criterion};
+use gap_buffer::GapBuffer;
+
+const LINE: &str = "The quick brown fox jumped over the lazy dog.\n";
+
+fn large_buffer(lines: usize) -> GapBuffer {
+    GapBuffer::from(LINE.repeat(lines))
+}
+
+fn jump_between_ends(criterion: &mut Criterion) {
+    let mut group = criterion.benchmark_group("jump between ends");
+
+    for lines in [1_000, 10_000, 100_000].iter() {
+        group.bench_function(format!("{} lines", lines), |bencher| {
+            bencher.iter_batched(
+                || large_buffer(*lines),
+                |mut buffer| {
+                    for _ in 0..10 {
+                        buffer.set_point(0);
+                        buffer.insert(b'a');
+                        let end = buffer.len();
+                        buffer.set_point(end);
+                        buffer.insert(b'z');
+                    }
+                    buffer
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn jump_to_middle(criterion: &mut Criterion) {
+    criterion.bench_function("jump to middle of 100000 lines", |bencher| {
+        bencher.iter_batched(
+            || large_buffer(100_000),
+            |mut buffer| {
+                let middle = buffer.len() / 2;
+                buffer.set_point(middle);
+                buffer.insert(b'm');
+                buffer
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, jump_between_ends, jump_to_middle);
+criterion_main!(benches);
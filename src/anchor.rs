@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use GapBuffer;
+
+/// Affinity decides which way an anchor moves when text is inserted exactly at it.
+/// - `Left`: Stay before the inserted text.
+/// - `Right`: Move after the inserted text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Affinity {
+    #[default]
+    Left,
+    Right,
+}
+
+/// AnchorId identifies a position registered with `GapBuffer::create_anchor()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnchorId(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Anchor {
+    offset: usize,
+    affinity: Affinity,
+}
+
+/// Anchors holds the positions that are moved to follow the text around them as it changes.
+#[derive(Debug, Default)]
+pub(crate) struct Anchors {
+    anchors: HashMap<AnchorId, Anchor>,
+    next_id: usize,
+}
+
+impl Anchors {
+    /// update() moves every anchor after the text in `removed` is replaced by `inserted_length` bytes.
+    /// Anchors inside the removed text collapse to its start before the insertion is applied.
+    pub(crate) fn update(&mut self, removed: Range<usize>, inserted_length: usize) {
+        for anchor in self.anchors.values_mut() {
+            anchor.offset = shift_offset(anchor.offset, anchor.affinity, removed.clone(), inserted_length);
+        }
+    }
+}
+
+/// shift_offset() returns where `offset` moves to when the text in `removed` is replaced by
+/// `inserted_length` bytes.
+pub fn shift_offset(offset: usize, affinity: Affinity, removed: Range<usize>, inserted_length: usize) -> usize {
+    if offset < removed.start {
+        offset
+    } else if offset > removed.end {
+        offset - (removed.end - removed.start) + inserted_length
+    } else {
+        match affinity {
+            Affinity::Left => removed.start,
+            Affinity::Right => removed.start + inserted_length,
+        }
+    }
+}
+
+/// Anchors are positions that stay on the same text as the buffer changes. They are kept as
+/// offsets into the whole text, so narrowing the buffer does not lose those it hides; an anchor
+/// outside the accessible text is reported at the nearer end of it.
+impl GapBuffer {
+    /// create_anchor() registers a position at `offset` that moves with the text around it.
+    /// It will panic if `offset` is greater than the buffer length.
+    pub fn create_anchor(&mut self, offset: usize, affinity: Affinity) -> AnchorId {
+        if offset > self.len() {
+            panic!("Index out of bounds. The length is {} but the index is {}.", self.len(), offset)
+        }
+
+        let id = AnchorId(self.anchors.next_id);
+        self.anchors.next_id += 1;
+        let offset = offset + self.hidden_before();
+        self.anchors.anchors.insert(id, Anchor { offset, affinity });
+
+        id
+    }
+
+    /// anchor_offset() returns the current position of an anchor, or `None` if it was removed.
+    pub fn anchor_offset(&self, id: AnchorId) -> Option<usize> {
+        self.anchors.anchors.get(&id).map(|anchor| self.accessible_offset(anchor.offset))
+    }
+
+    pub fn anchor_affinity(&self, id: AnchorId) -> Option<Affinity> {
+        self.anchors.anchors.get(&id).map(|anchor| anchor.affinity)
+    }
+
+    /// move_anchor() sets the position of an anchor and returns false if it was removed.
+    /// It will panic if `offset` is greater than the buffer length.
+    pub fn move_anchor(&mut self, id: AnchorId, offset: usize) -> bool {
+        if offset > self.len() {
+            panic!("Index out of bounds. The length is {} but the index is {}.", self.len(), offset)
+        }
+
+        let offset = offset + self.hidden_before();
+        match self.anchors.anchors.get_mut(&id) {
+            Some(anchor) => {
+                anchor.offset = offset;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// remove_anchor() stops tracking an anchor and returns its last position.
+    pub fn remove_anchor(&mut self, id: AnchorId) -> Option<usize> {
+        let anchor = self.anchors.anchors.remove(&id)?;
+        Some(self.accessible_offset(anchor.offset))
+    }
+
+    /// accessible_offset() turns an offset into the whole text into one into the accessible text.
+    fn accessible_offset(&self, offset: usize) -> usize {
+        let before = self.hidden_before();
+        offset.clamp(before, before + self.len()) - before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor::Affinity;
+    use GapBuffer;
+
+    const TEST_STRING: &str = "The quick brown fox";
+
+    #[test]
+    fn anchors_follow_insertions() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let before = buffer.create_anchor(2, Affinity::Left);
+        let left = buffer.create_anchor(4, Affinity::Left);
+        let right = buffer.create_anchor(4, Affinity::Right);
+        let after = buffer.create_anchor(10, Affinity::Left);
+
+        buffer.set_point(4);
+        buffer.insert_str("very ");
+
+        assert_eq!(buffer.anchor_offset(before), Some(2));
+        assert_eq!(buffer.anchor_offset(left), Some(4));
+        assert_eq!(buffer.anchor_offset(right), Some(9));
+        assert_eq!(buffer.anchor_offset(after), Some(15));
+    }
+
+    #[test]
+    fn anchors_follow_removals() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let start = buffer.create_anchor(4, Affinity::Right);
+        let inside = buffer.create_anchor(7, Affinity::Right);
+        let end = buffer.create_anchor(10, Affinity::Left);
+        let after = buffer.create_anchor(16, Affinity::Left);
+
+        buffer.remove_bytes(4..10);
+
+        assert_eq!(buffer.anchor_offset(start), Some(4));
+        assert_eq!(buffer.anchor_offset(inside), Some(4));
+        assert_eq!(buffer.anchor_offset(end), Some(4));
+        assert_eq!(buffer.anchor_offset(after), Some(10));
+    }
+
+    #[test]
+    fn anchors_follow_replacements() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let left = buffer.create_anchor(6, Affinity::Left);
+        let right = buffer.create_anchor(6, Affinity::Right);
+        let after = buffer.create_anchor(16, Affinity::Right);
+
+        buffer.replace_range(4..9, "sluggish");
+
+        assert_eq!(buffer.anchor_offset(left), Some(4));
+        assert_eq!(buffer.anchor_offset(right), Some(12));
+        assert_eq!(buffer.anchor_offset(after), Some(19));
+    }
+
+    #[test]
+    fn anchors_follow_undo() {
+        let mut history = ::undo::History::new(GapBuffer::from(TEST_STRING.to_string()));
+        let anchor = history.create_anchor(10, Affinity::Left);
+
+        history.remove_bytes(0..4);
+        history.undo();
+
+        assert_eq!(history.anchor_offset(anchor), Some(10));
+    }
+
+    #[test]
+    fn move_and_remove_anchors() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let anchor = buffer.create_anchor(0, Affinity::Right);
+
+        assert!(buffer.move_anchor(anchor, 19));
+        assert_eq!(buffer.anchor_affinity(anchor), Some(Affinity::Right));
+        assert_eq!(buffer.remove_anchor(anchor), Some(19));
+        assert_eq!(buffer.anchor_offset(anchor), None);
+        assert!(!buffer.move_anchor(anchor, 0));
+    }
+}
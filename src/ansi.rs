@@ -0,0 +1,325 @@
+use std::str;
+
+use keymap::{Key, KeyCode};
+use theme::{ansi_color_name, Color, Face};
+
+/// AnsiEvent is a piece of what a program writes to a terminal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsiEvent {
+    /// Text to draw at the cursor, with no control characters in it.
+    Text(String),
+    /// A control character such as `\r`, `\n`, `\x08` or `\t`.
+    Control(char),
+    /// A control sequence, `ESC [` then numeric parameters and the character saying what to do,
+    /// such as `m` to set the colors of the text that follows. A parameter left out is 0.
+    /// `private` sequences, which start `ESC [ ?` or similar, set terminal modes.
+    Csi { private: bool, params: Vec<u16>, action: char },
+}
+
+/// State is where an `AnsiParser` is in the sequence it is reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum State {
+    #[default]
+    Text,
+    Escape,
+    Csi,
+    /// An operating system command, such as setting the window title, which is skipped.
+    Osc,
+    OscEscape,
+    /// The character set of an `ESC (` sequence, which is skipped.
+    Charset,
+}
+
+/// AnsiParser turns what a program writes to a terminal into text and the control characters and
+/// escape sequences mixed in with it. Input can be split anywhere, even inside a character or a
+/// sequence, and what is left over is kept for the next call. Text that is not valid UTF-8 is
+/// replaced with `U+FFFD REPLACEMENT CHARACTER`.
+#[derive(Debug, Default)]
+pub struct AnsiParser {
+    state: State,
+    params: String,
+    text: String,
+    pending: Vec<u8>,
+}
+
+impl AnsiParser {
+    pub fn new() -> AnsiParser {
+        AnsiParser::default()
+    }
+
+    /// feed() parses `bytes` and returns the events they complete.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<AnsiEvent> {
+        self.pending.extend_from_slice(bytes);
+        let pending = std::mem::take(&mut self.pending);
+        let mut events = Vec::new();
+        let mut rest = pending.as_slice();
+
+        loop {
+            match str::from_utf8(rest) {
+                Ok(text) => {
+                    self.advance_str(text, &mut events);
+                    break;
+                }
+                Err(error) => {
+                    let (valid, after) = rest.split_at(error.valid_up_to());
+                    self.advance_str(str::from_utf8(valid).unwrap(), &mut events);
+                    match error.error_len() {
+                        Some(length) => {
+                            self.advance('\u{FFFD}', &mut events);
+                            rest = &after[length..];
+                        }
+                        None => {
+                            self.pending = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        self.flush_text(&mut events);
+        events
+    }
+
+    fn advance_str(&mut self, text: &str, events: &mut Vec<AnsiEvent>) {
+        for character in text.chars() {
+            self.advance(character, events);
+        }
+    }
+
+    fn advance(&mut self, character: char, events: &mut Vec<AnsiEvent>) {
+        match (self.state, character) {
+            (State::Osc, '\x07') => self.state = State::Text,
+            (State::Osc, '\x1b') => self.state = State::OscEscape,
+            (State::Osc, _) => {}
+            (State::OscEscape, _) => self.state = State::Text,
+            (State::Charset, _) => self.state = State::Text,
+            (_, '\x1b') => {
+                self.flush_text(events);
+                self.state = State::Escape;
+            }
+            (State::Escape, '[') => {
+                self.params.clear();
+                self.state = State::Csi;
+            }
+            (State::Escape, ']') => self.state = State::Osc,
+            (State::Escape, '(') | (State::Escape, ')') | (State::Escape, '*') | (State::Escape, '+') => {
+                self.state = State::Charset
+            }
+            // The other escape sequences, such as saving the cursor, are not followed.
+            (State::Escape, _) => self.state = State::Text,
+            (State::Csi, '0'..='9') | (State::Csi, ';') | (State::Csi, '?') | (State::Csi, '>') | (State::Csi, '=') => {
+                self.params.push(character)
+            }
+            (State::Csi, '\x40'..='\x7e') => {
+                let private = self.params.starts_with(['?', '>', '=']);
+                let params = self
+                    .params
+                    .trim_start_matches(['?', '>', '='])
+                    .split(';')
+                    .map(|param| param.parse().unwrap_or(0))
+                    .collect();
+                events.push(AnsiEvent::Csi { private, params, action: character });
+                self.state = State::Text;
+            }
+            (_, control) if control < ' ' || control == '\x7f' => {
+                self.flush_text(events);
+                if control != '\x7f' {
+                    events.push(AnsiEvent::Control(control));
+                }
+            }
+            // Intermediate characters of a control sequence are not needed.
+            (State::Csi, _) => {}
+            (State::Text, _) => self.text.push(character),
+        }
+    }
+
+    fn flush_text(&mut self, events: &mut Vec<AnsiEvent>) {
+        if !self.text.is_empty() {
+            events.push(AnsiEvent::Text(std::mem::take(&mut self.text)));
+        }
+    }
+}
+
+/// apply_sgr() changes `face` as the parameters of a Select Graphic Rendition sequence,
+/// `ESC [ ... m`, say: attributes, the 8 colors and their bright versions, 256-color palette
+/// indexes and true colors. No parameters reset the face.
+pub fn apply_sgr(face: &mut Face, params: &[u16]) {
+    let mut params = params.iter().copied();
+
+    while let Some(param) = params.next() {
+        match param {
+            0 => *face = Face::default(),
+            1 => face.bold = true,
+            2 => face.dim = true,
+            3 => face.italic = true,
+            4 => face.underline = true,
+            7 => face.reverse = true,
+            22 => (face.bold, face.dim) = (false, false),
+            23 => face.italic = false,
+            24 => face.underline = false,
+            27 => face.reverse = false,
+            30..=37 => face.foreground = Color::Indexed((param - 30) as u8),
+            38 => face.foreground = extended_color(&mut params).unwrap_or(face.foreground),
+            39 => face.foreground = Color::Default,
+            40..=47 => face.background = Color::Indexed((param - 40) as u8),
+            48 => face.background = extended_color(&mut params).unwrap_or(face.background),
+            49 => face.background = Color::Default,
+            90..=97 => face.foreground = Color::Indexed((param - 90 + 8) as u8),
+            100..=107 => face.background = Color::Indexed((param - 100 + 8) as u8),
+            _ => {}
+        }
+    }
+}
+
+/// extended_color() reads the color after a `38` or `48`: `5;index` or `2;red;green;blue`.
+fn extended_color<I: Iterator<Item = u16>>(params: &mut I) -> Option<Color> {
+    let channel = |value: Option<u16>| value.map(|value| value.min(255) as u8);
+
+    match params.next()? {
+        5 => channel(params.next()).map(Color::Indexed),
+        2 => Some(Color::Rgb(channel(params.next())?, channel(params.next())?, channel(params.next())?)),
+        _ => None,
+    }
+}
+
+/// face_styles() names the styles a span drawn in `face` is marked with, one for each color and
+/// attribute, which `Theme::overlay()` draws as the face again unless the theme sets them.
+pub fn face_styles(face: &Face) -> Vec<String> {
+    let mut styles = Vec::new();
+    let mut color = |layer: &str, color: Color| match color {
+        Color::Default => {}
+        Color::Indexed(index) => styles.push(format!("ansi.{}.{}", layer, ansi_color_name(index))),
+        Color::Rgb(r, g, b) => styles.push(format!("ansi.{}.#{:02x}{:02x}{:02x}", layer, r, g, b)),
+    };
+    color("foreground", face.foreground);
+    color("background", face.background);
+
+    let attributes = [
+        (face.bold, "ansi.bold"),
+        (face.dim, "ansi.dim"),
+        (face.italic, "ansi.italic"),
+        (face.underline, "ansi.underline"),
+        (face.reverse, "ansi.reverse"),
+    ];
+    styles.extend(attributes.iter().filter(|(on, _)| *on).map(|(_, style)| style.to_string()));
+    styles
+}
+
+/// encode_key() is what a terminal sends a program when `key` is pressed, or None if it sends
+/// nothing.
+pub fn encode_key(key: Key) -> Option<Vec<u8>> {
+    let sequence: &[u8] = match key.code {
+        KeyCode::Char(character) if key.modifiers.control => {
+            let control = match character {
+                '@' | ' ' => 0,
+                'a'..='z' => character as u8 - b'a' + 1,
+                '[' => 0x1b,
+                '\\' => 0x1c,
+                ']' => 0x1d,
+                '_' | '/' => 0x1f,
+                '?' => 0x7f,
+                _ => return None,
+            };
+            return Some(with_meta(key, vec![control]));
+        }
+        KeyCode::Char(character) => return Some(with_meta(key, character.to_string().into_bytes())),
+        KeyCode::Enter => b"\r",
+        KeyCode::Tab if key.modifiers.shift => b"\x1b[Z",
+        KeyCode::Tab => b"\t",
+        KeyCode::Backspace => b"\x7f",
+        KeyCode::Escape => b"\x1b",
+        KeyCode::Up => b"\x1b[A",
+        KeyCode::Down => b"\x1b[B",
+        KeyCode::Right => b"\x1b[C",
+        KeyCode::Left => b"\x1b[D",
+        KeyCode::Home => b"\x1b[H",
+        KeyCode::End => b"\x1b[F",
+        KeyCode::Insert => b"\x1b[2~",
+        KeyCode::Delete => b"\x1b[3~",
+        KeyCode::PageUp => b"\x1b[5~",
+        KeyCode::PageDown => b"\x1b[6~",
+        KeyCode::F(_) => return None,
+    };
+
+    Some(with_meta(key, sequence.to_vec()))
+}
+
+/// with_meta() is `bytes` sent with meta held down, which terminals send as escape first.
+fn with_meta(key: Key, mut bytes: Vec<u8>) -> Vec<u8> {
+    if key.modifiers.meta {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use ansi::{apply_sgr, encode_key, face_styles, AnsiEvent, AnsiParser};
+    use keymap::Key;
+    use theme::{Color, Face};
+
+    #[test]
+    fn split_output_into_text_and_sequences() {
+        let mut parser = AnsiParser::new();
+
+        let events = parser.feed(b"\x1b]0;title\x07ls\r\n\x1b[1;31mred\x1b[0m\x1b[?25l\x1b(Bok");
+        assert_eq!(
+            events,
+            vec![
+                AnsiEvent::Text("ls".to_string()),
+                AnsiEvent::Control('\r'),
+                AnsiEvent::Control('\n'),
+                AnsiEvent::Csi { private: false, params: vec![1, 31], action: 'm' },
+                AnsiEvent::Text("red".to_string()),
+                AnsiEvent::Csi { private: false, params: vec![0], action: 'm' },
+                AnsiEvent::Csi { private: true, params: vec![25], action: 'l' },
+                AnsiEvent::Text("ok".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn sequences_and_characters_can_be_split_across_reads() {
+        let mut parser = AnsiParser::new();
+
+        assert_eq!(parser.feed(b"caf\xC3"), vec![AnsiEvent::Text("caf".to_string())]);
+        assert_eq!(parser.feed(b"\xA9 \x1b["), vec![AnsiEvent::Text("\u{e9} ".to_string())]);
+        assert_eq!(parser.feed(b"2K\xFFx"), vec![
+            AnsiEvent::Csi { private: false, params: vec![2], action: 'K' },
+            AnsiEvent::Text("\u{FFFD}x".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn graphic_renditions_become_faces_and_styles() {
+        let mut face = Face::default();
+
+        apply_sgr(&mut face, &[1, 32, 48, 5, 196]);
+        assert_eq!((face.bold, face.foreground, face.background), (true, Color::Indexed(2), Color::Indexed(196)));
+        assert_eq!(face_styles(&face), vec!["ansi.foreground.green", "ansi.background.196", "ansi.bold"]);
+        apply_sgr(&mut face, &[22, 39, 38, 2, 255, 128, 0]);
+        assert_eq!(face_styles(&face), vec!["ansi.foreground.#ff8000", "ansi.background.196"]);
+        apply_sgr(&mut face, &[104]);
+        assert_eq!(face.background, Color::Indexed(12));
+        apply_sgr(&mut face, &[0]);
+        assert_eq!(face, Face::default());
+    }
+
+    #[test]
+    fn keys_are_sent_as_a_terminal_sends_them() {
+        let test_cases = [
+            ("a character", "a", Some(b"a".to_vec())),
+            ("a control key", "C-c", Some(vec![3])),
+            ("a meta key", "M-b", Some(b"\x1bb".to_vec())),
+            ("return", "RET", Some(b"\r".to_vec())),
+            ("an arrow", "<up>", Some(b"\x1b[A".to_vec())),
+            ("backspace", "DEL", Some(vec![0x7f])),
+            ("a function key", "<f5>", None),
+        ];
+
+        for (name, key, expected) in test_cases.iter() {
+            assert_eq!(encode_key(Key::parse(key).unwrap()), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+}
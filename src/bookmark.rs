@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anchor::AnchorId;
+use buffer_manager::{BufferId, BufferManager};
+use delta::{Position, PositionEncoding};
+
+/// Bookmark is a named place to come back to.
+/// - `path`: The file of the buffer it is in, if it has one.
+/// - `position`: Its line and byte column, as last known. This is where it is in its file once
+///   its buffer is gone, as after loading bookmarks saved in another session.
+/// - `anchor`: The buffer it is in and the anchor following its place there, while the buffer
+///   is open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub path: Option<PathBuf>,
+    pub position: Position,
+    anchor: Option<(BufferId, AnchorId)>,
+}
+
+impl Bookmark {
+    /// anchor() returns the buffer the bookmark is in and the anchor at its place there, if the
+    /// buffer is still open.
+    pub fn anchor(&self, buffers: &BufferManager) -> Option<(BufferId, AnchorId)> {
+        let (id, anchor) = self.anchor?;
+        buffers.get(id)?.anchor_offset(anchor)?;
+
+        Some((id, anchor))
+    }
+
+    /// position() returns the line and byte column of the bookmark, following its anchor while
+    /// its buffer is open.
+    pub fn position(&self, buffers: &BufferManager) -> Position {
+        match self.anchor(buffers) {
+            Some((id, anchor)) => {
+                let buffer = buffers.get(id).unwrap();
+                buffer.offset_to_position(buffer.anchor_offset(anchor).unwrap(), PositionEncoding::Utf8)
+            }
+            None => self.position,
+        }
+    }
+}
+
+/// Bookmarks keeps bookmarks by name.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    bookmarks: BTreeMap<String, Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Bookmarks {
+        Bookmarks::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+
+    /// set() bookmarks the place held by `anchor` in buffer `id`, which is at `position` in the
+    /// file at `path`, as `name`. Returns the bookmark it replaces, if there was one.
+    pub fn set(&mut self, name: &str, id: BufferId, anchor: AnchorId, path: Option<PathBuf>, position: Position) -> Option<Bookmark> {
+        let bookmark = Bookmark {
+            path,
+            position,
+            anchor: Some((id, anchor)),
+        };
+
+        self.bookmarks.insert(name.to_string(), bookmark)
+    }
+
+    /// attach() follows the place of bookmark `name` with `anchor` in buffer `id`, as when its
+    /// file has been visited again. Returns false if there is no such bookmark.
+    pub fn attach(&mut self, name: &str, id: BufferId, anchor: AnchorId) -> bool {
+        match self.bookmarks.get_mut(name) {
+            Some(bookmark) => {
+                bookmark.anchor = Some((id, anchor));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.get(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Bookmark> {
+        self.bookmarks.remove(name)
+    }
+
+    /// iter() visits the bookmarks in alphabetical order of their names.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Bookmark)> {
+        self.bookmarks.iter().map(|(name, bookmark)| (name.as_str(), bookmark))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.bookmarks.keys().map(|name| name.as_str())
+    }
+
+    /// save() writes the bookmarks in files to `path`, one per line as the name, its line, its
+    /// byte column and its file, separated by tabs. Positions are taken from `buffers` for
+    /// bookmarks whose buffer is open.
+    pub fn save(&self, buffers: &BufferManager, path: &Path) -> io::Result<()> {
+        let mut contents = String::new();
+        for (name, bookmark) in self.bookmarks.iter() {
+            if let Some(file) = bookmark.path.as_ref() {
+                let position = bookmark.position(buffers);
+                contents.push_str(&format!("{}\t{}\t{}\t{}\n", name, position.line, position.character, file.display()));
+            }
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// load() reads bookmarks written by `save()`, replacing any already set with the same name.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let contents = fs::read_to_string(path)?;
+
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("Invalid bookmark: {:?}", line));
+            let fields: Vec<&str> = line.splitn(4, '\t').collect();
+            let (name, line, character, file) = match fields[..] {
+                [name, line, character, file] => (name, line, character, file),
+                _ => return Err(invalid()),
+            };
+            let position = Position {
+                line: line.parse().map_err(|_| invalid())?,
+                character: character.parse().map_err(|_| invalid())?,
+            };
+
+            let bookmark = Bookmark {
+                path: Some(PathBuf::from(file)),
+                position,
+                anchor: None,
+            };
+            self.bookmarks.insert(name.to_string(), bookmark);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use anchor::Affinity;
+    use bookmark::Bookmarks;
+    use buffer::Buffer;
+    use buffer_manager::BufferManager;
+    use delta::Position;
+
+    #[test]
+    fn bookmarks_follow_edits_and_are_saved() {
+        let path = env::temp_dir().join(format!("one-bookmarks-{}.txt", std::process::id()));
+        let mut buffers = BufferManager::new();
+        let id = buffers.add(Buffer::visiting("/work/notes.txt", "first\nsecond\n".to_string()));
+        let scratch = buffers.create("*scratch*");
+        let buffer = buffers.get_mut(id).unwrap();
+        let anchor = buffer.create_anchor(8, Affinity::Left);
+
+        let mut bookmarks = Bookmarks::new();
+        let position = Position { line: 1, character: 2 };
+        assert!(bookmarks.set("notes", id, anchor, Some(PathBuf::from("/work/notes.txt")), position).is_none());
+        let anchor = buffers.get_mut(scratch).unwrap().create_anchor(0, Affinity::Left);
+        bookmarks.set("scratch", scratch, anchor, None, Position { line: 0, character: 0 });
+
+        let buffer = buffers.get_mut(id).unwrap();
+        buffer.set_point(0);
+        buffer.insert_str("zeroth\n");
+        assert_eq!(bookmarks.get("notes").unwrap().position(&buffers), Position { line: 2, character: 2 });
+
+        bookmarks.save(&buffers, &path).unwrap();
+        buffers.kill(id);
+        assert!(bookmarks.get("notes").unwrap().anchor(&buffers).is_none());
+        let mut loaded = Bookmarks::new();
+        loaded.load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["notes"]);
+        let notes = loaded.get("notes").unwrap();
+        assert_eq!((notes.path.as_deref(), notes.position(&buffers)), (Some(PathBuf::from("/work/notes.txt").as_path()), Position { line: 2, character: 2 }));
+    }
+
+    #[test]
+    fn load_rejects_invalid_bookmarks() {
+        let path = env::temp_dir().join(format!("one-bad-bookmarks-{}.txt", std::process::id()));
+        fs::write(&path, "broken\tline\n").unwrap();
+
+        let result = Bookmarks::new().load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use GapBuffer;
+
+/// The pairs of brackets that are matched, as their opening and closing characters.
+pub const BRACKETS: &[(u8, u8)] = &[(b'(', b')'), (b'[', b']'), (b'{', b'}')];
+/// The quotes that are matched, each closed by the same character.
+pub const QUOTES: &[u8] = b"\"'`";
+/// The character that stops the quote after it from opening or closing a string.
+const ESCAPE: u8 = b'\\';
+
+/// Delimiter matching. Every delimiter is ASCII, so the buffer is scanned by bytes.
+impl GapBuffer {
+    /// delimiter_near() returns the offset of the bracket or quote at `offset`, or else of the
+    /// closing bracket or quote just before it, the one a point after it is taken to be on.
+    pub fn delimiter_near(&self, offset: usize) -> Option<usize> {
+        let is_delimiter = |byte: u8| QUOTES.contains(&byte) || BRACKETS.iter().any(|(open, close)| byte == *open || byte == *close);
+        let is_closing = |byte: u8| QUOTES.contains(&byte) || BRACKETS.iter().any(|(_, close)| byte == *close);
+
+        match self.byte(offset) {
+            Some(byte) if is_delimiter(byte) => Some(offset),
+            _ => offset.checked_sub(1).filter(|before| self.byte(*before).is_some_and(is_closing)),
+        }
+    }
+
+    /// matching_delimiter() returns the offset of the delimiter matching the bracket or quote at
+    /// `offset`. Brackets nest, and are matched across lines. `skip` reports whether an offset is
+    /// in a string or comment: only brackets on the same side of that as the one at `offset` are
+    /// counted, so a bracket in code passes over those in strings, and one in a string is matched
+    /// within it. Quotes are matched on their own line, pairing the unescaped ones in order.
+    pub fn matching_delimiter(&self, offset: usize, skip: &mut dyn FnMut(usize) -> bool) -> Option<usize> {
+        let byte = self.byte(offset)?;
+        if QUOTES.contains(&byte) {
+            return self.matching_quote(offset, byte);
+        }
+
+        let inside = skip(offset);
+        if let Some((open, close)) = BRACKETS.iter().find(|(open, _)| *open == byte) {
+            let mut depth = 0;
+            for index in offset + 1..self.len() {
+                match self.byte(index) {
+                    Some(found) if found == *open && skip(index) == inside => depth += 1,
+                    Some(found) if found == *close && skip(index) == inside => {
+                        if depth == 0 {
+                            return Some(index);
+                        }
+                        depth -= 1;
+                    }
+                    _ => {}
+                }
+            }
+        } else if let Some((open, close)) = BRACKETS.iter().find(|(_, close)| *close == byte) {
+            let mut depth = 0;
+            for index in (0..offset).rev() {
+                match self.byte(index) {
+                    Some(found) if found == *close && skip(index) == inside => depth += 1,
+                    Some(found) if found == *open && skip(index) == inside => {
+                        if depth == 0 {
+                            return Some(index);
+                        }
+                        depth -= 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// matching_quote() pairs the unescaped `quote`s on the line of `offset` from its start, and
+    /// returns the other one of the pair the quote at `offset` is in.
+    fn matching_quote(&self, offset: usize, quote: u8) -> Option<usize> {
+        let (line, _) = self.offset_to_line_col(offset);
+        let start = self.line_to_offset(line)?;
+        let end = self.line_end_offset(line)?;
+
+        let mut quotes = Vec::new();
+        let mut escaped = false;
+        for index in start..end {
+            match self.byte(index) {
+                Some(ESCAPE) => escaped = !escaped,
+                Some(byte) => {
+                    if byte == quote && !escaped {
+                        quotes.push(index);
+                    }
+                    escaped = false;
+                }
+                None => {}
+            }
+        }
+
+        let position = quotes.iter().position(|index| *index == offset)?;
+        let other = if position % 2 == 0 { position + 1 } else { position - 1 };
+        quotes.get(other).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use GapBuffer;
+
+    const TEST_STRING: &str = "fn f(a: [u8; 2]) {\n    g(\")\", 'x', (a));\n}";
+
+    #[test]
+    fn match_brackets_and_quotes() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+        let test_cases = [
+            ("an opening bracket", 4, Some(15)),
+            ("a closing bracket", 15, Some(4)),
+            ("a square bracket", 8, Some(14)),
+            ("a brace across lines", 17, Some(41)),
+            ("brackets in a string are counted without syntax", 24, Some(26)),
+            ("an opening quote", 25, Some(27)),
+            ("a closing quote", 27, Some(25)),
+            ("a single quote", 30, Some(32)),
+            ("not a delimiter", 0, None),
+        ];
+
+        for (name, offset, expected) in test_cases.iter() {
+            assert_eq!(buffer.matching_delimiter(*offset, &mut |_| false), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn skip_brackets_in_strings() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+        let in_string = |offset: usize| (25..28).contains(&offset);
+
+        assert_eq!(buffer.matching_delimiter(24, &mut |offset| in_string(offset)), Some(38));
+        assert_eq!(buffer.matching_delimiter(26, &mut |offset| in_string(offset)), None);
+    }
+
+    #[test]
+    fn find_the_delimiter_near_an_offset() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+
+        assert_eq!(buffer.delimiter_near(4), Some(4));
+        assert_eq!(buffer.delimiter_near(16), Some(15));
+        assert_eq!(buffer.delimiter_near(5), None);
+        assert_eq!(buffer.delimiter_near(0), None);
+    }
+}
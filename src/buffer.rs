@@ -0,0 +1,475 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use indent::IndentSettings;
+use undo::History;
+use GapBuffer;
+
+pub use self::backup::{BackupKind, BackupSettings};
+pub use self::encoding::Encoding;
+pub use self::transform::SaveTransform;
+pub use self::watch::FileStamp;
+
+pub mod auto_save;
+pub mod backup;
+pub mod case;
+pub mod encoding;
+pub mod file;
+pub mod fill;
+pub mod lines;
+pub mod load;
+pub mod transform;
+pub mod transpose;
+pub mod watch;
+
+/// LineEnding is the sequence that ends each line of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    /// detect() returns the line ending used most in `text`, or `Lf` if it has no line breaks.
+    pub fn detect(text: &str) -> LineEnding {
+        LineEnding::detect_bytes(text.as_bytes())
+    }
+
+    /// detect_bytes() is detect() for text that may not be valid UTF-8.
+    pub fn detect_bytes(bytes: &[u8]) -> LineEnding {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+
+        let mut index = 0;
+        while index < bytes.len() {
+            match bytes[index] {
+                b'\r' if bytes.get(index + 1) == Some(&b'\n') => {
+                    crlf += 1;
+                    index += 1;
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+            index += 1;
+        }
+
+        if crlf > lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf && cr > crlf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+            LineEnding::Cr => "cr",
+        }
+    }
+
+    /// from_name() returns the line ending called `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<LineEnding> {
+        [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr]
+            .iter()
+            .copied()
+            .find(|ending| ending.name().eq_ignore_ascii_case(name))
+    }
+
+    /// normalize() returns `text` with every line ending, of any style, replaced by `\n`.
+    pub fn normalize(text: &str) -> Cow<'_, str> {
+        if !text.contains('\r') {
+            return Cow::Borrowed(text);
+        }
+
+        Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+    }
+
+    /// denormalize() returns `text`, whose lines end with `\n`, with this line ending instead.
+    pub fn denormalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            LineEnding::Lf => Cow::Borrowed(text),
+            ending => Cow::Owned(text.replace('\n', ending.as_str())),
+        }
+    }
+}
+
+/// ReadOnlyError is an edit refused because the buffer named `buffer` is read-only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyError {
+    pub buffer: String,
+}
+
+impl fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Buffer is read-only: {}", self.buffer)
+    }
+}
+
+impl Error for ReadOnlyError {}
+
+/// Buffer is text being edited along with what the editor knows about it.
+/// - `text`: The contents, with the history of changes made to them so they can be undone.
+/// - `name`: What the buffer is called, unique among open buffers.
+/// - `path`: The file the buffer is visiting, if any.
+/// - `modified`: Set by any change to the contents since the flag was last cleared.
+/// - `line_ending`: The line ending detected in the contents. Lines always end with `\n` in the
+///   buffer itself; this is what they are written out with.
+/// - `encoding`: How the contents are stored in the visited file, used when they are written out.
+/// - `backup_settings`: How the visited file is backed up before it is first overwritten.
+/// - `backed_up`: Whether that backup has been made for the current file.
+/// - `edits_since_auto_save`: Changes made since the buffer was last saved or auto-saved.
+/// - `auto_save_interval`: How many changes trigger an auto-save, or None to never auto-save.
+/// - `disk_stamp`: What the visited file looked like when it was last read or written.
+/// - `save_transforms`: Changes made to the text each time it is saved, in order.
+/// - `indent_settings`: How lines are indented, by new lines and by commands that indent.
+/// - `fill_column`: The column filling wraps lines at.
+/// - `auto_fill`: Whether lines are wrapped as they are typed past the fill column.
+///
+/// Buffer dereferences to its `GapBuffer`, so it is edited the same way.
+pub struct Buffer {
+    text: History,
+    name: String,
+    path: Option<PathBuf>,
+    modified: Arc<AtomicBool>,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    backup_settings: BackupSettings,
+    backed_up: bool,
+    edits_since_auto_save: Arc<AtomicUsize>,
+    auto_save_interval: Option<usize>,
+    disk_stamp: Option<FileStamp>,
+    save_transforms: Vec<Box<dyn SaveTransform>>,
+    indent_settings: IndentSettings,
+    fill_column: usize,
+    auto_fill: bool,
+}
+
+impl Buffer {
+    /// new() creates an empty buffer that is not visiting a file.
+    pub fn new(name: &str) -> Buffer {
+        Buffer::from_text(name, String::new())
+    }
+
+    /// from_text() creates a buffer holding `text`, turning the line ending it uses most into the
+    /// buffer's line ending and every line ending into `\n`.
+    pub fn from_text(name: &str, text: String) -> Buffer {
+        let line_ending = LineEnding::detect(&text);
+        let text = match LineEnding::normalize(&text) {
+            Cow::Borrowed(_) => text,
+            Cow::Owned(normalized) => normalized,
+        };
+        let mut text = GapBuffer::from(text);
+        text.set_point(0);
+
+        Buffer::from_gap_buffer(name, text, line_ending)
+    }
+
+    /// from_gap_buffer() wraps `text` as a buffer whose lines are saved ending with `line_ending`.
+    fn from_gap_buffer(name: &str, mut text: GapBuffer, line_ending: LineEnding) -> Buffer {
+        let modified = Arc::new(AtomicBool::new(false));
+        let edits_since_auto_save = Arc::new(AtomicUsize::new(0));
+
+        let (flag, edits) = (modified.clone(), edits_since_auto_save.clone());
+        text.on_change(move |_| {
+            flag.store(true, Ordering::Relaxed);
+            edits.fetch_add(1, Ordering::Relaxed);
+        });
+
+        Buffer {
+            text: History::new(text),
+            name: name.to_string(),
+            path: None,
+            modified,
+            line_ending,
+            encoding: Encoding::default(),
+            backup_settings: BackupSettings::default(),
+            backed_up: false,
+            edits_since_auto_save,
+            auto_save_interval: Some(auto_save::DEFAULT_AUTO_SAVE_INTERVAL),
+            disk_stamp: None,
+            save_transforms: Vec::new(),
+            indent_settings: IndentSettings::default(),
+            fill_column: fill::DEFAULT_FILL_COLUMN,
+            auto_fill: false,
+        }
+    }
+
+    /// visiting() creates a buffer holding `text` from the file at `path`, named after the file.
+    pub fn visiting<P: AsRef<Path>>(path: P, text: String) -> Buffer {
+        let path = path.as_ref();
+
+        let mut buffer = Buffer::from_text(&file_name(path), text);
+        buffer.path = Some(path.to_path_buf());
+        buffer
+    }
+
+    pub fn text(&self) -> &GapBuffer {
+        self.text.buffer()
+    }
+
+    pub fn text_mut(&mut self) -> &mut GapBuffer {
+        self.text.buffer_mut()
+    }
+
+    pub fn into_text(self) -> GapBuffer {
+        self.text.into_buffer()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.text
+    }
+
+    /// undo() undoes the last change, returning false if there was none to undo or the buffer is
+    /// read-only.
+    pub fn undo(&mut self) -> bool {
+        self.text.undo()
+    }
+
+    /// redo() redoes the last change undone, returning false if there was none to redo or the
+    /// buffer is read-only.
+    pub fn redo(&mut self) -> bool {
+        self.text.redo()
+    }
+
+    /// begin_transaction() makes every change until the matching `commit()` one undo step.
+    pub fn begin_transaction(&mut self) {
+        self.text.begin_transaction();
+    }
+
+    pub fn commit(&mut self) {
+        self.text.commit();
+    }
+
+    /// undo_boundary() ends the undo step the changes made since the last boundary belong to, as
+    /// the editor does after each command, and starts the next one where the point is. Changes
+    /// made while the buffer is read-only, which it makes to itself, cannot be undone, so they
+    /// forget the history instead.
+    pub fn undo_boundary(&mut self) {
+        if self.is_read_only() && self.text.has_unrecorded_changes() {
+            self.text.clear();
+        } else {
+            self.text.checkpoint();
+        }
+        self.text.start_step();
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// set_path() changes the file the buffer visits. The new file gets its own backup when it is
+    /// first overwritten.
+    pub fn set_path<P: AsRef<Path>>(&mut self, path: Option<P>) {
+        self.path = path.map(|path| path.as_ref().to_path_buf());
+        self.backed_up = false;
+        self.disk_stamp = None;
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified.load(Ordering::Relaxed)
+    }
+
+    /// set_modified() overrides the modified flag, such as clearing it once the buffer is saved.
+    pub fn set_modified(&mut self, modified: bool) {
+        self.modified.store(modified, Ordering::Relaxed);
+    }
+
+    /// check_writable() fails if the buffer is read-only, for code that should report an edit
+    /// being refused rather than have it silently do nothing.
+    pub fn check_writable(&self) -> Result<(), ReadOnlyError> {
+        if self.is_read_only() {
+            return Err(ReadOnlyError { buffer: self.name.clone() });
+        }
+        Ok(())
+    }
+
+    /// with_writable() runs `edit` with read-only briefly lifted, for changes the buffer makes to
+    /// itself, such as reverting to its file.
+    pub(crate) fn with_writable<T, F: FnOnce(&mut Buffer) -> T>(&mut self, edit: F) -> T {
+        let read_only = self.is_read_only();
+        self.set_read_only(false);
+        let result = edit(self);
+        self.set_read_only(read_only);
+        if read_only {
+            self.undo_boundary();
+        }
+
+        result
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+    }
+
+    /// convert_line_endings() changes the line ending the buffer is saved with, marking it
+    /// modified if that changes what would be written.
+    pub fn convert_line_endings(&mut self, line_ending: LineEnding) {
+        if self.line_ending != line_ending {
+            self.line_ending = line_ending;
+            self.set_modified(true);
+        }
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// set_encoding() changes the encoding the contents will be saved in.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn indent_settings(&self) -> IndentSettings {
+        self.indent_settings
+    }
+
+    pub fn set_indent_settings(&mut self, settings: IndentSettings) {
+        self.indent_settings = settings;
+    }
+
+    pub fn backup_settings(&self) -> &BackupSettings {
+        &self.backup_settings
+    }
+
+    pub fn set_backup_settings(&mut self, settings: BackupSettings) {
+        self.backup_settings = settings;
+    }
+}
+
+/// file_name() is what a buffer visiting `path` is called.
+fn file_name(path: &Path) -> Cow<'_, str> {
+    path.file_name().map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy())
+}
+
+impl Deref for Buffer {
+    type Target = GapBuffer;
+
+    fn deref(&self) -> &GapBuffer {
+        self.text.buffer()
+    }
+}
+
+impl DerefMut for Buffer {
+    fn deref_mut(&mut self) -> &mut GapBuffer {
+        self.text.buffer_mut()
+    }
+}
+
+impl fmt::Display for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.text.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use buffer::{Buffer, LineEnding};
+
+    #[test]
+    fn a_new_buffer_is_empty_and_unmodified() {
+        let buffer = Buffer::new("*scratch*");
+
+        assert_eq!(buffer.name(), "*scratch*");
+        assert_eq!(buffer.path(), None);
+        assert!(buffer.is_empty());
+        assert!(!buffer.is_modified());
+        assert!(!buffer.is_read_only());
+        assert_eq!(buffer.line_ending(), LineEnding::Lf);
+    }
+
+    #[test]
+    fn edits_set_the_modified_flag() {
+        let mut buffer = Buffer::from_text("notes", "hello".to_string());
+        assert_eq!(buffer.get_point(), 0);
+
+        buffer.insert_str("> ");
+        assert!(buffer.is_modified());
+        assert_eq!(buffer.to_string(), "> hello");
+
+        buffer.set_modified(false);
+        assert!(!buffer.is_modified());
+        buffer.set_point(3);
+        assert!(!buffer.is_modified());
+    }
+
+    #[test]
+    fn a_visiting_buffer_is_named_after_its_file() {
+        let buffer = Buffer::visiting("src/main.rs", "fn main() {}\r\n".to_string());
+
+        assert_eq!(buffer.name(), "main.rs");
+        assert_eq!(buffer.path(), Some(Path::new("src/main.rs")));
+        assert_eq!(buffer.line_ending(), LineEnding::CrLf);
+        assert_eq!(buffer.to_string(), "fn main() {}\n");
+    }
+
+    #[test]
+    fn read_only_buffers_refuse_edits() {
+        let mut buffer = Buffer::from_text("log", "line\n".to_string());
+        buffer.set_read_only(true);
+
+        buffer.insert_str("edit");
+        assert_eq!(buffer.delete_char_forward(), None);
+        buffer.set_point(5);
+        assert_eq!(buffer.get_point(), 5);
+
+        assert_eq!(buffer.to_string(), "line\n");
+        assert!(!buffer.is_modified());
+        assert_eq!(buffer.check_writable().unwrap_err().to_string(), "Buffer is read-only: log");
+
+        buffer.set_read_only(false);
+        assert!(buffer.check_writable().is_ok());
+    }
+
+    #[test]
+    fn convert_line_endings() {
+        let mut buffer = Buffer::from_text("notes", "a\r\nb\r\nc\r".to_string());
+        assert_eq!(buffer.to_string(), "a\nb\nc\n");
+
+        buffer.convert_line_endings(LineEnding::CrLf);
+        assert!(!buffer.is_modified());
+        buffer.convert_line_endings(LineEnding::Cr);
+        assert!(buffer.is_modified());
+        assert_eq!(buffer.line_ending().denormalize(&buffer.to_string()), "a\rb\rc\r");
+        assert_eq!(LineEnding::from_name("CRLF"), Some(LineEnding::CrLf));
+    }
+
+    #[test]
+    fn detect_line_endings() {
+        assert_eq!(LineEnding::detect("no breaks"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\rb\r"), LineEnding::Cr);
+    }
+}
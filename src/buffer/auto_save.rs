@@ -0,0 +1,159 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+
+use buffer::Buffer;
+
+/// The number of changes after which a buffer is auto-saved by default.
+pub const DEFAULT_AUTO_SAVE_INTERVAL: usize = 300;
+
+/// auto_save_path() returns the file that unsaved changes to `path` are auto-saved to, `#name#` in
+/// the same directory.
+pub fn auto_save_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    path.with_file_name(format!("#{}#", name))
+}
+
+/// has_recovery_data() reports whether `path` has an auto-save file at least as new as itself,
+/// which means changes were left unsaved, such as by a crash.
+pub fn has_recovery_data(path: &Path) -> bool {
+    let auto_save = match fs::metadata(auto_save_path(path)).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => modified,
+        Err(_) => return false,
+    };
+
+    match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => auto_save >= modified,
+        Err(_) => true,
+    }
+}
+
+/// Auto-saving keeps a copy of unsaved changes beside the visited file, so they can be recovered
+/// if the editor dies before they are saved.
+impl Buffer {
+    pub fn auto_save_interval(&self) -> Option<usize> {
+        self.auto_save_interval
+    }
+
+    /// set_auto_save_interval() sets how many changes trigger an auto-save. None turns it off.
+    pub fn set_auto_save_interval(&mut self, interval: Option<usize>) {
+        self.auto_save_interval = interval;
+    }
+
+    /// auto_save_due() reports whether the buffer visits a file and has had enough changes since
+    /// it was last saved to be auto-saved.
+    pub fn auto_save_due(&self) -> bool {
+        let edits = self.edits_since_auto_save.load(Ordering::Relaxed);
+
+        self.path.is_some() && self.is_modified() && self.auto_save_interval.is_some_and(|interval| edits >= interval)
+    }
+
+    /// auto_save() writes the buffer to its auto-save file and returns that file, or None if the
+    /// buffer has nothing to save or visits no file.
+    pub fn auto_save(&mut self) -> io::Result<Option<PathBuf>> {
+        let path = match &self.path {
+            Some(path) if self.is_modified() => auto_save_path(path),
+            _ => return Ok(None),
+        };
+
+        self.write_to(&path)?;
+        self.edits_since_auto_save.store(0, Ordering::Relaxed);
+        Ok(Some(path))
+    }
+
+    /// recover() replaces the contents with those of the auto-save file, leaving the buffer
+    /// modified so they can be saved over the file. It returns false if there is nothing to
+    /// recover, and fails if the buffer is read-only.
+    pub fn recover(&mut self) -> io::Result<bool> {
+        let path = match &self.path {
+            Some(path) => auto_save_path(path),
+            None => return Ok(false),
+        };
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(error) => return Err(error),
+        };
+
+        self.check_writable().map_err(|error| io::Error::new(io::ErrorKind::PermissionDenied, error))?;
+        self.set_contents(bytes);
+        self.set_modified(true);
+        Ok(true)
+    }
+
+    /// remove_auto_save() deletes the auto-save file once it is no longer needed.
+    pub(crate) fn remove_auto_save(&mut self) -> io::Result<()> {
+        self.edits_since_auto_save.store(0, Ordering::Relaxed);
+        let path = match &self.path {
+            Some(path) => auto_save_path(path),
+            None => return Ok(()),
+        };
+
+        match fs::remove_file(path) {
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use buffer::auto_save::{auto_save_path, has_recovery_data};
+    use buffer::Buffer;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("one-auto-save-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn auto_save_files_are_named_with_hashes() {
+        assert_eq!(auto_save_path(Path::new("src/main.rs")), Path::new("src/#main.rs#"));
+    }
+
+    #[test]
+    fn auto_save_after_enough_edits() {
+        let path = temp_path("due.txt");
+        let mut buffer = Buffer::visiting(&path, String::new());
+        buffer.set_auto_save_interval(Some(3));
+
+        buffer.insert_str("a");
+        buffer.insert_str("b");
+        assert!(!buffer.auto_save_due());
+        buffer.insert_str("c");
+        assert!(buffer.auto_save_due());
+
+        assert_eq!(buffer.auto_save().unwrap(), Some(auto_save_path(&path)));
+        assert!(!buffer.auto_save_due());
+        assert_eq!(fs::read_to_string(auto_save_path(&path)).unwrap(), "abc");
+
+        buffer.save().unwrap();
+        assert!(!auto_save_path(&path).exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn recover_unsaved_changes() {
+        let path = temp_path("recover.txt");
+        fs::write(&path, "saved").unwrap();
+        let mut buffer = Buffer::open(&path).unwrap();
+        buffer.insert_str("unsaved, ");
+        buffer.auto_save().unwrap();
+        drop(buffer);
+
+        assert!(has_recovery_data(&path));
+        let mut buffer = Buffer::open(&path).unwrap();
+        assert!(buffer.recover().unwrap());
+
+        assert_eq!(buffer.to_string(), "unsaved, saved");
+        assert!(buffer.is_modified());
+        buffer.save().unwrap();
+        assert!(!has_recovery_data(&path));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The largest file backed up by default, in bytes.
+pub const DEFAULT_BACKUP_SIZE_LIMIT: u64 = 10 * 1024 * 1024;
+
+/// BackupKind is how a file is backed up before a save overwrites it.
+/// - `None`: No backup is made.
+/// - `Single`: The one backup `file~` is replaced each time.
+/// - `Numbered`: Every backup is kept as `file.~1~`, `file.~2~` and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackupKind {
+    #[default]
+    None,
+    Single,
+    Numbered,
+}
+
+/// BackupSettings choose how and where backups are written.
+/// - `kind`: Whether to back up, and whether to keep old backups.
+/// - `directory`: Where backups go. Without one they sit next to the file; with one they are
+///   named after the file's whole path, with `!` for each separator, so files do not collide.
+/// - `size_limit`: Files larger than this many bytes are not backed up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupSettings {
+    pub kind: BackupKind,
+    pub directory: Option<PathBuf>,
+    pub size_limit: Option<u64>,
+}
+
+impl Default for BackupSettings {
+    fn default() -> BackupSettings {
+        BackupSettings {
+            kind: BackupKind::None,
+            directory: None,
+            size_limit: Some(DEFAULT_BACKUP_SIZE_LIMIT),
+        }
+    }
+}
+
+impl BackupSettings {
+    /// backup_path() returns where the next backup of `path` would be written, or None if these
+    /// settings make no backups.
+    pub fn backup_path(&self, path: &Path) -> Option<PathBuf> {
+        let base = self.backup_base(path)?;
+
+        match self.kind {
+            BackupKind::None => None,
+            BackupKind::Single => Some(append(&base, "~")),
+            BackupKind::Numbered => {
+                let next = numbered_backups(&base).into_iter().max().unwrap_or(0) + 1;
+                Some(append(&base, &format!(".~{}~", next)))
+            }
+        }
+    }
+
+    /// make_backup() copies the file at `path` to its backup and returns the backup's path. Nothing
+    /// is copied if the file does not exist yet or is over the size limit.
+    pub fn make_backup(&self, path: &Path) -> io::Result<Option<PathBuf>> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        if !metadata.is_file() || self.size_limit.is_some_and(|limit| metadata.len() > limit) {
+            return Ok(None);
+        }
+
+        let backup = match self.backup_path(path) {
+            Some(backup) => backup,
+            None => return Ok(None),
+        };
+        if let Some(directory) = &self.directory {
+            fs::create_dir_all(directory)?;
+        }
+        fs::copy(path, &backup)?;
+
+        Ok(Some(backup))
+    }
+
+    /// backup_base() is the backup's path before its suffix is added.
+    fn backup_base(&self, path: &Path) -> Option<PathBuf> {
+        match &self.directory {
+            None => Some(path.to_path_buf()),
+            Some(directory) => {
+                let absolute = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+                let flattened = absolute.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "!");
+                Some(directory.join(flattened))
+            }
+        }
+    }
+}
+
+fn append(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+
+    PathBuf::from(path)
+}
+
+/// numbered_backups() returns the numbers of the existing `base.~N~` backups.
+fn numbered_backups(base: &Path) -> Vec<usize> {
+    let (directory, name) = match (base.parent(), base.file_name()) {
+        (Some(directory), Some(name)) => (directory, name.to_string_lossy().into_owned()),
+        _ => return Vec::new(),
+    };
+    let directory = if directory.as_os_str().is_empty() { Path::new(".") } else { directory };
+    let prefix = format!("{}.~", name);
+
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            file_name.strip_prefix(&prefix)?.strip_suffix('~')?.parse().ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use buffer::backup::{BackupKind, BackupSettings};
+
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("one-backup-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn single_backups_replace_each_other() {
+        let directory = temp_directory("single");
+        let path = directory.join("notes.txt");
+        let settings = BackupSettings { kind: BackupKind::Single, ..BackupSettings::default() };
+
+        fs::write(&path, "one").unwrap();
+        assert_eq!(settings.make_backup(&path).unwrap(), Some(directory.join("notes.txt~")));
+        fs::write(&path, "two").unwrap();
+        settings.make_backup(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(directory.join("notes.txt~")).unwrap(), "two");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn numbered_backups_are_all_kept() {
+        let directory = temp_directory("numbered");
+        let path = directory.join("notes.txt");
+        let settings = BackupSettings { kind: BackupKind::Numbered, ..BackupSettings::default() };
+
+        fs::write(&path, "one").unwrap();
+        settings.make_backup(&path).unwrap();
+        fs::write(&path, "two").unwrap();
+        let second = settings.make_backup(&path).unwrap();
+
+        assert_eq!(second, Some(directory.join("notes.txt.~2~")));
+        assert_eq!(fs::read_to_string(directory.join("notes.txt.~1~")).unwrap(), "one");
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn backups_in_a_directory_are_named_after_the_whole_path() {
+        let directory = temp_directory("directory");
+        let path = directory.join("notes.txt");
+        let backups = directory.join("backups");
+        let settings = BackupSettings {
+            kind: BackupKind::Single,
+            directory: Some(backups.clone()),
+            size_limit: None,
+        };
+
+        fs::write(&path, "one").unwrap();
+        let backup = settings.make_backup(&path).unwrap().unwrap();
+
+        assert_eq!(backup.parent(), Some(backups.as_path()));
+        assert!(backup.file_name().unwrap().to_string_lossy().ends_with("!notes.txt~"));
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn no_backup_of_missing_or_large_files() {
+        let directory = temp_directory("limits");
+        let path = directory.join("large.txt");
+        let settings = BackupSettings { kind: BackupKind::Single, directory: None, size_limit: Some(2) };
+
+        assert_eq!(settings.make_backup(&path).unwrap(), None);
+        fs::write(&path, "large").unwrap();
+        assert_eq!(settings.make_backup(&path).unwrap(), None);
+        assert_eq!(BackupSettings::default().backup_path(&path), None);
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+use buffer::Buffer;
+use motion::is_word_char;
+
+/// Case is a way of writing words that text can be converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    Upper,
+    Lower,
+    /// The first letter of each word upper case and the rest lower case.
+    Capitalized,
+}
+
+impl Case {
+    /// apply() converts `text` with Unicode's case mappings, so the result may be longer, as
+    /// `ß` is `SS` in upper case.
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Case::Upper => text.to_uppercase(),
+            Case::Lower => text.to_lowercase(),
+            Case::Capitalized => {
+                let mut capitalized = String::with_capacity(text.len());
+                let mut in_word = false;
+                for character in text.chars() {
+                    match in_word {
+                        true => capitalized.extend(character.to_lowercase()),
+                        false => capitalized.extend(character.to_uppercase()),
+                    }
+                    in_word = is_word_char(character);
+                }
+                capitalized
+            }
+        }
+    }
+}
+
+/// Converting the case of text.
+impl Buffer {
+    /// change_case() converts the text in `range` to `case` in a single edit. A point or mark at
+    /// either end of `range` stays at that end of the converted text. Returns false if nothing
+    /// changed.
+    pub fn change_case(&mut self, range: Range<usize>, case: Case) -> bool {
+        if self.is_read_only() {
+            return false;
+        }
+        let old = self.slice(range.clone()).into_owned();
+        let new = case.apply(&old);
+        if old == new {
+            return false;
+        }
+
+        // Inside the range an offset keeps its count of characters, as the mapping can change how
+        // many bytes each takes.
+        let new_end = range.start + new.len();
+        let keep = |offset: usize| {
+            if offset < range.start {
+                return offset;
+            }
+            if offset >= range.end {
+                return offset + new_end - range.end;
+            }
+            let characters = self.slice(range.start..offset).chars().count();
+            range.start + new.char_indices().nth(characters).map_or(new.len(), |(index, _)| index)
+        };
+        let (point, mark) = (keep(self.get_point()), self.get_mark().map(keep));
+        self.replace_range(range, &new);
+        self.set_point(point);
+        if let Some(mark) = mark {
+            self.set_mark(mark);
+        }
+        true
+    }
+
+    /// change_case_word() converts the text from the point to the end of the next word to `case`
+    /// and moves the point there, as Emacs's `upcase-word` does. Returns false if there is no
+    /// word after the point.
+    pub fn change_case_word(&mut self, case: Case) -> bool {
+        let point = self.get_point();
+        let start = self.skip_forward(point, false);
+        let end = self.skip_forward(start, true);
+        if start == end || self.is_read_only() {
+            return false;
+        }
+
+        self.set_point(end);
+        self.change_case(point..end, case);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::case::Case;
+    use buffer::Buffer;
+
+    #[test]
+    fn convert_case() {
+        let test_cases = [
+            ("upper", Case::Upper, "straße café", "STRASSE CAFÉ"),
+            ("lower", Case::Lower, "ÉCOLE Ǆ", "école ǆ"),
+            ("capitalized", Case::Capitalized, "hello wORLD, x_y ébène", "Hello World, X_y Ébène"),
+            ("lower and shorter", Case::Lower, "ΩΩΩ", "ωωω"),
+        ];
+
+        for (name, case, text, expected) in test_cases.iter() {
+            assert_eq!(case.apply(text), *expected, "Test case: \"{}\" failed.", name);
+
+            // The point after the first character stays after it, however many bytes it takes.
+            let mut buffer = Buffer::from_text("case", text.to_string());
+            buffer.set_point(text.chars().next().unwrap().len_utf8());
+            assert!(buffer.change_case(0..text.len(), *case), "Test case: \"{}\" failed.", name);
+            let point = expected.chars().next().unwrap().len_utf8();
+            assert_eq!((buffer.to_string(), buffer.get_point()), (expected.to_string(), point), "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn convert_the_case_of_words_and_ranges() {
+        let mut buffer = Buffer::from_text("case", "groß und klein".to_string());
+
+        assert!(buffer.change_case_word(Case::Upper));
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), ("GROSS und klein", 5));
+        assert!(buffer.change_case_word(Case::Capitalized));
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), ("GROSS Und klein", 9));
+
+        buffer.set_mark(0);
+        assert!(buffer.change_case(buffer.region().unwrap(), Case::Lower));
+        assert_eq!((buffer.to_string().as_str(), buffer.region()), ("gross und klein", Some(0..9)));
+        assert!(!buffer.change_case(0..5, Case::Lower));
+
+        let end = buffer.len();
+        buffer.set_point(end);
+        assert!(!buffer.change_case_word(Case::Upper));
+    }
+}
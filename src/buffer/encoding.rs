@@ -0,0 +1,158 @@
+use std::convert::TryFrom;
+use std::io;
+
+/// Encoding is how a file's text is stored as bytes.
+/// - `Utf8Bom`: UTF-8 starting with a byte order mark.
+/// - `Utf16Le`, `Utf16Be`: UTF-16 starting with a byte order mark.
+/// - `Latin1`: ISO-8859-1, assumed for files that are not valid UTF-8.
+/// - `Bytes`: The file's bytes kept exactly as they are, even where they are not valid UTF-8.
+///   Invalid sequences are shown as `U+FFFD REPLACEMENT CHARACTER` but saved unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+    Bytes,
+}
+
+pub(crate) const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+impl Encoding {
+    /// detect() works out the encoding of `bytes` from its byte order mark, then whether it is
+    /// valid UTF-8, and decodes it. Since any bytes are valid Latin-1 this always succeeds.
+    pub fn detect(bytes: &[u8]) -> (Encoding, String) {
+        let guesses = if bytes.starts_with(UTF8_BOM) {
+            &[Encoding::Utf8Bom][..]
+        } else if bytes.starts_with(UTF16_LE_BOM) {
+            &[Encoding::Utf16Le][..]
+        } else if bytes.starts_with(UTF16_BE_BOM) {
+            &[Encoding::Utf16Be][..]
+        } else {
+            &[Encoding::Utf8][..]
+        };
+
+        for &encoding in guesses {
+            if let Some(text) = encoding.decode(bytes) {
+                return (encoding, text);
+            }
+        }
+        (Encoding::Latin1, Encoding::Latin1.decode(bytes).unwrap())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "utf-8",
+            Encoding::Utf8Bom => "utf-8-with-signature",
+            Encoding::Utf16Le => "utf-16le",
+            Encoding::Utf16Be => "utf-16be",
+            Encoding::Latin1 => "latin-1",
+            Encoding::Bytes => "raw-bytes",
+        }
+    }
+
+    /// from_name() returns the encoding called `name`, ignoring case.
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        ENCODINGS.iter().copied().find(|encoding| encoding.name().eq_ignore_ascii_case(name))
+    }
+
+    /// decode() returns the text in `bytes`, or None if they are not valid in this encoding. A
+    /// byte order mark is skipped. `Bytes` decodes lossily, since the text is not what it keeps.
+    pub fn decode(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Encoding::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            Encoding::Utf8Bom => String::from_utf8(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes).to_vec()).ok(),
+            Encoding::Utf16Le => decode_utf16(bytes.strip_prefix(UTF16_LE_BOM).unwrap_or(bytes), u16::from_le_bytes),
+            Encoding::Utf16Be => decode_utf16(bytes.strip_prefix(UTF16_BE_BOM).unwrap_or(bytes), u16::from_be_bytes),
+            Encoding::Latin1 => Some(bytes.iter().map(|&byte| byte as char).collect()),
+            Encoding::Bytes => Some(String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+
+    /// encode() returns `text` as bytes in this encoding, with a byte order mark for the encodings
+    /// that were detected by one. It fails with `InvalidData` if a character cannot be encoded.
+    pub fn encode(&self, text: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Utf8 | Encoding::Bytes => Ok(text.as_bytes().to_vec()),
+            Encoding::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+            Encoding::Utf16Le => Ok(encode_utf16(text, UTF16_LE_BOM, u16::to_le_bytes)),
+            Encoding::Utf16Be => Ok(encode_utf16(text, UTF16_BE_BOM, u16::to_be_bytes)),
+            Encoding::Latin1 => text
+                .chars()
+                .map(|character| match u8::try_from(character) {
+                    Ok(byte) => Ok(byte),
+                    Err(_) => Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{:?} cannot be encoded as {}", character, self.name()),
+                    )),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Every encoding, for listing and looking them up by name.
+pub const ENCODINGS: &[Encoding] =
+    &[Encoding::Utf8, Encoding::Utf8Bom, Encoding::Utf16Le, Encoding::Utf16Be, Encoding::Latin1, Encoding::Bytes];
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks(2).map(|pair| from_bytes([pair[0], pair[1]]));
+
+    char::decode_utf16(units).collect::<Result<String, _>>().ok()
+}
+
+fn encode_utf16(text: &str, bom: &[u8], to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    let mut bytes = bom.to_vec();
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&to_bytes(unit));
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::encoding::Encoding;
+
+    #[test]
+    fn detect_encodings() {
+        let test_cases: [(&str, &[u8], Encoding, &str); 5] = [
+            ("plain UTF-8", "café".as_bytes(), Encoding::Utf8, "café"),
+            ("UTF-8 with a BOM", b"\xEF\xBB\xBFhi", Encoding::Utf8Bom, "hi"),
+            ("UTF-16 little endian", b"\xFF\xFEh\x00i\x00", Encoding::Utf16Le, "hi"),
+            ("UTF-16 big endian", b"\xFE\xFF\x00h\x00i", Encoding::Utf16Be, "hi"),
+            ("Latin-1", b"caf\xE9", Encoding::Latin1, "café"),
+        ];
+
+        for (name, bytes, expected_encoding, expected_text) in test_cases.iter() {
+            let (encoding, text) = Encoding::detect(bytes);
+
+            assert_eq!(encoding, *expected_encoding, "Test case: \"{}\" failed.", name);
+            assert_eq!(text, *expected_text, "Test case: \"{}\" failed.", name);
+            assert_eq!(encoding.encode(&text).unwrap(), *bytes, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn broken_utf16_falls_back_to_latin1() {
+        let (encoding, text) = Encoding::detect(b"\xFF\xFEh");
+
+        assert_eq!(encoding, Encoding::Latin1);
+        assert_eq!(text, "ÿþh");
+    }
+
+    #[test]
+    fn latin1_cannot_encode_everything() {
+        let error = Encoding::Latin1.encode("snow ☃").unwrap_err();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(Encoding::from_name("UTF-16LE"), Some(Encoding::Utf16Le));
+    }
+}
@@ -0,0 +1,346 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use buffer::load::Loader;
+use buffer::{Buffer, Encoding, FileStamp, LineEnding};
+use undo::persist::{load_undo_file, save_undo_file};
+
+/// OpenMode is how a file's bytes become a buffer's text.
+/// - `Detect`: Decode them in the encoding they are detected to use.
+/// - `Lossy`: Decode them as UTF-8, replacing invalid sequences with `U+FFFD`. Saving writes the
+///   replacement characters, so the invalid bytes are lost.
+/// - `Bytes`: Keep them exactly, so the file can be edited and saved without losing any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OpenMode {
+    #[default]
+    Detect,
+    Lossy,
+    Bytes,
+}
+
+/// Reading and writing the file a buffer visits.
+impl Buffer {
+    /// open() reads the file at `path` into a new buffer visiting it, decoding it from the
+    /// encoding it is detected to use.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Buffer> {
+        Buffer::open_with(path, OpenMode::Detect)
+    }
+
+    /// open_with() reads the file at `path` into a new buffer visiting it, turning its bytes into
+    /// text as `mode` says. Except in `Bytes` mode, line endings become `\n` and the style the file
+    /// used is restored when it is saved. `Bytes` mode keeps them as they are. The undo history
+    /// saved with the file is restored if it was saved for the same text. Use a `Loader` to open a
+    /// large file without blocking until all of it is read.
+    pub fn open_with<P: AsRef<Path>>(path: P, mode: OpenMode) -> io::Result<Buffer> {
+        let path = path.as_ref();
+        let mut buffer = Loader::new(path, mode)?.finish()?;
+        // A missing or stale history only leaves nothing to undo, so it is not an error.
+        let _ = load_undo_file(&mut buffer.text, path);
+
+        Ok(buffer)
+    }
+
+    /// set_contents() replaces the contents with `bytes` decoded from the buffer's encoding, or
+    /// kept as they are for `Bytes`, and moves the point to the start. A narrowed buffer is
+    /// widened first.
+    pub(crate) fn set_contents(&mut self, bytes: Vec<u8>) {
+        self.widen();
+        let len = self.len();
+        self.remove_bytes(0..len);
+        self.set_point(0);
+
+        match self.encoding {
+            Encoding::Bytes => self.insert_bytes(bytes),
+            encoding => {
+                let text = encoding.decode(&bytes).unwrap_or_else(|| String::from_utf8_lossy(&bytes).into_owned());
+                self.insert_str(&LineEnding::normalize(&text));
+            }
+        }
+        self.set_point(0);
+    }
+
+    /// save() runs the buffer's save transforms, writes it to the file it visits and clears the
+    /// modified flag. The first save over a file backs it up according to the buffer's backup
+    /// settings. Any auto-save file is removed, since the file itself is now up to date, and the
+    /// undo history is saved next to the file.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = match self.path() {
+            Some(path) => path.to_path_buf(),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Buffer is not visiting a file")),
+        };
+
+        self.run_save_transforms();
+        self.back_up(&path)?;
+        self.write_to(&path)?;
+        self.disk_stamp = FileStamp::of(&path);
+        self.set_modified(false);
+        self.save_undo(&path);
+        self.remove_auto_save()
+    }
+
+    /// save_as() saves the buffer to `path` like save() and makes it the file the buffer visits.
+    pub fn save_as<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if self.path() != Some(path) {
+            self.backed_up = false;
+        }
+
+        self.run_save_transforms();
+        self.back_up(path)?;
+        self.write_to(path)?;
+        self.remove_auto_save()?;
+        self.path = Some(path.to_path_buf());
+        self.disk_stamp = FileStamp::of(path);
+        self.set_modified(false);
+        self.save_undo(path);
+        self.remove_auto_save()
+    }
+
+    /// save_undo() writes the undo history, with what was changed since the last step, next to
+    /// the file at `path`. The file itself is already saved, so failing to write the history is
+    /// not an error.
+    fn save_undo(&mut self, path: &Path) {
+        self.text.checkpoint();
+        let _ = save_undo_file(&self.text, path);
+    }
+
+    fn back_up(&mut self, path: &Path) -> io::Result<()> {
+        if !self.backed_up {
+            self.backup_settings.make_backup(path)?;
+            self.backed_up = true;
+        }
+        Ok(())
+    }
+
+    /// write_to() saves atomically: the text goes to a temporary file in the same directory, which
+    /// is synced and then renamed over `path`, so a crash mid-save never leaves a partial file. The
+    /// original file's permissions and, where allowed, ownership are kept. Symbolic links are
+    /// followed so the rename replaces their target rather than the link. The whole text is
+    /// written, including any that narrowing hides.
+    pub(crate) fn write_to(&self, path: &Path) -> io::Result<()> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let temp_path = temp_path_for(&path);
+        let result = self.write_temp_file(&path, &temp_path).and_then(|_| fs::rename(&temp_path, &path));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn write_temp_file(&self, path: &Path, temp_path: &Path) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(temp_path)?);
+        let verbatim = self.encoding == Encoding::Bytes || self.line_ending == LineEnding::Lf;
+        if verbatim && (self.encoding == Encoding::Utf8 || self.encoding == Encoding::Bytes) {
+            let (front, back) = self.whole_slices();
+            file.write_all(front)?;
+            file.write_all(back)?;
+        } else {
+            let text = self.whole_text();
+            file.write_all(&self.encoding.encode(&self.line_ending.denormalize(&text))?)?;
+        }
+        let file = file.into_inner().map_err(|error| error.into_error())?;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+            #[cfg(unix)]
+            {
+                // Only root can give a file away, so a failed chown is not an error.
+                let _ = std::os::unix::fs::fchown(&file, Some(metadata.uid()), Some(metadata.gid()));
+            }
+        }
+
+        file.sync_all()
+    }
+}
+
+/// temp_path_for() names a hidden file next to `path` to write a save into.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+
+    path.with_file_name(format!(".{}.one-save-{}", name, std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use buffer::file::OpenMode;
+    use buffer::{BackupKind, BackupSettings, Buffer, Encoding, LineEnding};
+    use undo::persist::undo_file_path;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("one-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn open_and_save_a_file() {
+        let path = temp_path("open.txt");
+        fs::write(&path, "hello\n").unwrap();
+
+        let mut buffer = Buffer::open(&path).unwrap();
+        assert_eq!(buffer.to_string(), "hello\n");
+        assert_eq!(buffer.path(), Some(path.as_path()));
+        assert!(!buffer.is_modified());
+
+        buffer.set_point(5);
+        buffer.insert_str(", world");
+        assert!(buffer.is_modified());
+        buffer.save().unwrap();
+
+        assert!(!buffer.is_modified());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hello, world\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_narrowed_buffer_saves_its_whole_text() {
+        let path = temp_path("narrowed.txt");
+        let mut buffer = Buffer::visiting(&path, "one\r\ntwo\r\nthree\r\n".to_string());
+        buffer.narrow(4..8);
+        buffer.set_point(3);
+        buffer.insert_str("!");
+
+        buffer.save().unwrap();
+        assert_eq!(buffer.to_string(), "two!\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\r\ntwo!\r\nthree\r\n");
+        buffer.set_line_ending(LineEnding::Lf);
+        buffer.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo!\nthree\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn undo_history_is_kept_with_the_file() {
+        let path = temp_path("undo.txt");
+        let mut buffer = Buffer::visiting(&path, "one\ntwo\nthree\n".to_string());
+        buffer.save().unwrap();
+        buffer.narrow(4..8);
+        buffer.insert_str("2 ");
+        buffer.save().unwrap();
+
+        let mut reopened = Buffer::open(&path).unwrap();
+        assert!(reopened.undo());
+        assert_eq!(reopened.to_string(), "one\ntwo\nthree\n");
+
+        fs::write(&path, "changed\n").unwrap();
+        let mut changed = Buffer::open(&path).unwrap();
+        assert!(!changed.undo());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(undo_file_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn open_a_missing_file() {
+        let error = Buffer::open(temp_path("missing.txt")).err().unwrap();
+
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn save_as_remembers_the_path() {
+        let path = temp_path("save-as.txt");
+        let mut buffer = Buffer::from_text("notes", "text".to_string());
+        assert!(buffer.save().is_err());
+
+        buffer.save_as(&path).unwrap();
+
+        assert_eq!(buffer.path(), Some(path.as_path()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "text");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn files_are_saved_in_their_encoding() {
+        let path = temp_path("utf16.txt");
+        fs::write(&path, b"\xFF\xFEh\x00i\x00").unwrap();
+
+        let mut buffer = Buffer::open(&path).unwrap();
+        assert_eq!(buffer.encoding(), Encoding::Utf16Le);
+        buffer.set_point(2);
+        buffer.insert_str("!");
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"\xFF\xFEh\x00i\x00!\x00");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn invalid_utf8_survives_a_bytes_preserving_save() {
+        let path = temp_path("invalid.bin");
+        fs::write(&path, b"ok \xFF\xFE end").unwrap();
+
+        let lossy = Buffer::open_with(&path, OpenMode::Lossy).unwrap();
+        assert_eq!(lossy.to_string(), "ok \u{FFFD}\u{FFFD} end");
+
+        let mut buffer = Buffer::open_with(&path, OpenMode::Bytes).unwrap();
+        assert_eq!(buffer.to_string(), "ok \u{FFFD}\u{FFFD} end");
+        assert!(!buffer.is_modified());
+        buffer.insert_str(">> ");
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b">> ok \xFF\xFE end");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn line_endings_are_restored_on_save() {
+        let path = temp_path("crlf.txt");
+        fs::write(&path, "one\r\ntwo\r\n").unwrap();
+
+        let mut buffer = Buffer::open(&path).unwrap();
+        assert_eq!(buffer.to_string(), "one\ntwo\n");
+        buffer.insert_str("zero\n");
+        buffer.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "zero\r\none\r\ntwo\r\n");
+
+        buffer.convert_line_endings(LineEnding::Lf);
+        buffer.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "zero\none\ntwo\n");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn only_the_first_save_makes_a_backup() {
+        let path = temp_path("backed-up.txt");
+        let backup = temp_path("backed-up.txt~");
+        fs::write(&path, "original").unwrap();
+
+        let mut buffer = Buffer::open(&path).unwrap();
+        buffer.set_backup_settings(BackupSettings { kind: BackupKind::Single, ..BackupSettings::default() });
+        buffer.insert_str("first ");
+        buffer.save().unwrap();
+        buffer.insert_str("second ");
+        buffer.save().unwrap();
+
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "original");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first second original");
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&backup).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_keeps_permissions_and_leaves_no_temporary_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_path("permissions.sh");
+        fs::write(&path, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o754)).unwrap();
+
+        let mut buffer = Buffer::open(&path).unwrap();
+        buffer.insert_str("true\n");
+        buffer.save().unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o754);
+        assert!(!super::temp_path_for(&path).exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
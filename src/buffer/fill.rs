@@ -0,0 +1,265 @@
+use buffer::Buffer;
+use view::wrap::display_width;
+
+/// The column lines are filled to unless a buffer is told otherwise, as in Emacs.
+pub const DEFAULT_FILL_COLUMN: usize = 70;
+
+/// The characters that can make up the comment leader of a fill prefix, as in `//`, `#` or `>`.
+const PREFIX_CHARS: &[char] = &['/', '#', ';', '%', '!', '>', '*', '-'];
+
+/// fill_prefix() returns the start of `line` that filling keeps in front of each line: its
+/// indentation, and the comment leaders such as `//`, `#` or `> >` after it with the spaces that
+/// follow them. A leader has to be followed by whitespace or the end of the line, so `#include`
+/// has none.
+pub fn fill_prefix(line: &str) -> &str {
+    let mut end = line.len() - line.trim_start_matches([' ', '\t']).len();
+    loop {
+        let rest = &line[end..];
+        let leader = rest.len() - rest.trim_start_matches(PREFIX_CHARS).len();
+        let after = &rest[leader..];
+        if leader == 0 || !(after.is_empty() || after.starts_with([' ', '\t'])) {
+            return &line[..end];
+        }
+        end += leader + after.len() - after.trim_start_matches([' ', '\t']).len();
+    }
+}
+
+/// words() returns the runs of non-whitespace in `text` and where each starts.
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, character) in text.char_indices() {
+        match (character.is_whitespace(), start) {
+            (true, Some(word_start)) => {
+                words.push((word_start, &text[word_start..index]));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push((word_start, &text[word_start..]));
+    }
+    words
+}
+
+/// continuation_prefix() is the prefix for the lines of a paragraph after the first, given the
+/// prefixes of its first and second lines. A paragraph of one line continues with its own prefix,
+/// except that a list item's bullet becomes spaces under it.
+fn continuation_prefix(first: &str, second: Option<&str>) -> String {
+    if let Some(second) = second {
+        return second.to_string();
+    }
+    match first.trim() {
+        "-" | "*" => first.chars().map(|character| if character == '\t' { '\t' } else { ' ' }).collect(),
+        _ => first.to_string(),
+    }
+}
+
+/// fill_lines() rewraps the words of `lines`, a paragraph, into lines no wider than `fill_column`
+/// where that can be done, keeping the fill prefix of the first line in front of the first and
+/// that of the second in front of the rest. Tabs stop every `tab_width` columns. A word wider
+/// than the fill column is put on a line of its own. Returns the new text, without a final line break, and where each word was put in it,
+/// in the order the words were in `lines`.
+fn fill_lines(lines: &[&str], fill_column: usize, tab_width: usize) -> (String, Vec<usize>) {
+    let first = fill_prefix(lines[0]);
+    let rest = continuation_prefix(first, lines.get(1).map(|line| fill_prefix(line)));
+
+    let mut text = first.to_string();
+    let mut column = display_width(first, tab_width);
+    let mut line_has_words = false;
+    let mut offsets = Vec::new();
+    for line in lines {
+        for (_, word) in words(&line[fill_prefix(line).len()..]) {
+            let word_width = display_width(word, tab_width);
+            if line_has_words && column + 1 + word_width > fill_column {
+                text.push('\n');
+                text.push_str(&rest);
+                column = display_width(&rest, tab_width);
+            } else if line_has_words {
+                text.push(' ');
+                column += 1;
+            }
+            offsets.push(text.len());
+            text.push_str(word);
+            column += word_width;
+            line_has_words = true;
+        }
+    }
+    (text, offsets)
+}
+
+/// Filling paragraphs, on demand and as text is typed.
+impl Buffer {
+    /// fill_column() is the column `fill_paragraph()` and auto-fill wrap lines at.
+    pub fn fill_column(&self) -> usize {
+        self.fill_column
+    }
+
+    pub fn set_fill_column(&mut self, fill_column: usize) {
+        self.fill_column = fill_column;
+    }
+
+    /// auto_fill() reports whether lines are wrapped as they are typed past the fill column.
+    pub fn auto_fill(&self) -> bool {
+        self.auto_fill
+    }
+
+    pub fn set_auto_fill(&mut self, auto_fill: bool) {
+        self.auto_fill = auto_fill;
+    }
+
+    /// is_blank_for_fill() reports whether `line` separates paragraphs: it has nothing but
+    /// whitespace and a comment leader on it.
+    fn is_blank_for_fill(&self, line: usize) -> bool {
+        let start = self.line_to_offset(line).unwrap();
+        let end = self.line_end_offset(line).unwrap();
+        let text = self.slice(start..end);
+
+        fill_prefix(&text).len() == text.len()
+    }
+
+    /// fill_paragraph() rewraps the paragraph the point is in to the fill column in a single
+    /// edit, with tabs stopping every `tab_width` columns, keeping the prefixes of its first two
+    /// lines, as `fill_lines()` does. Paragraphs are
+    /// separated by lines with only whitespace or a comment leader on them. The point stays on the
+    /// same character of the same word. Returns false if the point is not in a paragraph or the
+    /// paragraph is already filled.
+    pub fn fill_paragraph(&mut self, tab_width: usize) -> bool {
+        let (line, _) = self.offset_to_line_col(self.get_point());
+        if self.is_read_only() || self.is_blank_for_fill(line) {
+            return false;
+        }
+        let mut first = line;
+        while first > 0 && !self.is_blank_for_fill(first - 1) {
+            first -= 1;
+        }
+        let mut last = line;
+        while last + 1 < self.line_count() && !self.is_blank_for_fill(last + 1) {
+            last += 1;
+        }
+
+        let start = self.line_to_offset(first).unwrap();
+        let end = self.line_end_offset(last).unwrap();
+        let old = self.slice(start..end).into_owned();
+        let lines: Vec<&str> = old.split('\n').collect();
+        let (new, offsets) = fill_lines(&lines, self.fill_column, tab_width);
+        if new == old {
+            return false;
+        }
+
+        let mut old_words = Vec::new();
+        let mut line_start = 0;
+        for line in &lines {
+            let prefix = fill_prefix(line).len();
+            old_words.extend(words(&line[prefix..]).into_iter().map(|(offset, word)| (line_start + prefix + offset, word.len())));
+            line_start += line.len() + 1;
+        }
+        let point = self.get_point() - start;
+        let new_point = match old_words.iter().rposition(|(offset, _)| *offset <= point) {
+            Some(index) => offsets[index] + (point - old_words[index].0).min(old_words[index].1),
+            None => point.min(new.len()),
+        };
+
+        self.replace_range(start..end, &new);
+        self.set_point(start + new_point);
+        true
+    }
+
+    /// auto_fill_line() wraps the line of the point at the last space before the fill column, if
+    /// the text before the point reaches past it, as auto-fill does before a space or line break
+    /// is typed. Tabs stop every `tab_width` columns. The new line starts with the fill prefix of
+    /// the old one. Returns false if the line did not need wrapping or has nowhere to wrap.
+    pub fn auto_fill_line(&mut self, tab_width: usize) -> bool {
+        let point = self.get_point();
+        let (line, _) = self.offset_to_line_col(point);
+        let start = self.line_to_offset(line).unwrap();
+        let text = self.slice(start..point).into_owned();
+        if self.is_read_only() || display_width(&text, tab_width) <= self.fill_column {
+            return false;
+        }
+
+        let prefix = fill_prefix(&text).to_string();
+        let breaks: Vec<usize> = words(&text[prefix.len()..]).into_iter().skip(1).map(|(offset, _)| prefix.len() + offset).collect();
+        let fitting = breaks.iter().rev().find(|offset| display_width(text[..**offset].trim_end(), tab_width) <= self.fill_column);
+        let word_start = match fitting.or(breaks.first()) {
+            Some(offset) => *offset,
+            None => return false,
+        };
+
+        let space_start = text[..word_start].trim_end().len();
+        let continuation = continuation_prefix(&prefix, None);
+        self.replace_range(start + space_start..start + word_start, &format!("\n{}", continuation));
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::fill::{fill_lines, fill_prefix};
+    use buffer::Buffer;
+
+    #[test]
+    fn find_fill_prefixes() {
+        let test_cases = [
+            ("prose", "Some words", ""),
+            ("indentation", "    indented", "    "),
+            ("line comment", "    // a comment", "    // "),
+            ("doc comment", "/// Docs", "/// "),
+            ("hash comment", "# shell", "# "),
+            ("nested quotes", "> > quoted", "> > "),
+            ("no leader without a space", "#include <stdio.h>", ""),
+            ("only a leader", "  //", "  //"),
+        ];
+
+        for (name, line, expected) in test_cases.iter() {
+            assert_eq!(fill_prefix(line), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn fill_words_to_a_column() {
+        let test_cases = [
+            ("join short lines", vec!["one two", "three"], 20, "one two three"),
+            ("wrap a long line", vec!["one two three four"], 9, "one two\nthree\nfour"),
+            ("keep the first two prefixes", vec!["  - one two three", "    four"], 13, "  - one two\n    three\n    four"),
+            ("comment prefix", vec!["// one two three four five"], 16, "// one two three\n// four five"),
+            ("bullet of one line", vec!["- one two three"], 9, "- one two\n  three"),
+            ("a word wider than the column", vec!["a enormous b"], 4, "a\nenormous\nb"),
+            ("tab stops", vec!["\tone two"], 10, "\tone\n\ttwo"),
+        ];
+
+        for (name, lines, fill_column, expected) in test_cases.iter() {
+            assert_eq!(fill_lines(lines, *fill_column, 8).0, *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn fill_the_paragraph_of_the_point() {
+        let text = "// intro\n//\n// one two three\n// four five six seven\n//\nfn main() {}";
+        let mut buffer = Buffer::from_text("fill", text.to_string());
+        buffer.set_fill_column(16);
+        buffer.set_point(text.find("ive").unwrap());
+
+        assert!(buffer.fill_paragraph(8));
+        assert_eq!(buffer.to_string(), "// intro\n//\n// one two three\n// four five six\n// seven\n//\nfn main() {}");
+        assert_eq!(buffer.slice(buffer.get_point()..buffer.len()).split('\n').next(), Some("ive six"));
+        assert!(!buffer.fill_paragraph(8));
+
+        buffer.set_point(10);
+        assert!(!buffer.fill_paragraph(8));
+    }
+
+    #[test]
+    fn wrap_a_line_while_typing() {
+        let mut buffer = Buffer::from_text("fill", "    # one two three".to_string());
+        buffer.set_fill_column(14);
+        let end = buffer.len();
+        buffer.set_point(end);
+
+        assert!(buffer.auto_fill_line(8));
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), ("    # one two\n    # three", 25));
+        assert!(!buffer.auto_fill_line(8));
+    }
+}
@@ -0,0 +1,423 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+use buffer::Buffer;
+
+/// SortLines is how `Buffer::sort_lines()` orders lines. Lines that compare the same keep their
+/// order.
+/// - `numeric`: Compare the numbers lines start with, after any whitespace, instead of their text.
+///   Lines that do not start with a number come first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortLines {
+    pub descending: bool,
+    pub numeric: bool,
+    pub ignore_case: bool,
+}
+
+impl SortLines {
+    fn compare(&self, a: &str, b: &str) -> Ordering {
+        let ordering = match (self.numeric, self.ignore_case) {
+            (true, _) => leading_number(a).partial_cmp(&leading_number(b)).unwrap_or(Ordering::Equal),
+            (false, true) => a.to_lowercase().cmp(&b.to_lowercase()),
+            (false, false) => a.cmp(b),
+        };
+
+        match self.descending {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    }
+}
+
+/// leading_number() reads the number `line` starts with after any whitespace, such as `-1.5` in
+/// `  -1.5 kg`.
+fn leading_number(line: &str) -> Option<f64> {
+    let line = line.trim_start();
+    let end = line
+        .char_indices()
+        .find(|(index, character)| !(character.is_ascii_digit() || *character == '.' || *index == 0 && matches!(character, '-' | '+')))
+        .map_or(line.len(), |(index, _)| index);
+
+    line[..end].parse().ok()
+}
+
+/// Editing whole lines. Each edit replaces a single range of the text, and the point is kept on
+/// the text it was on, in whichever line that text moved to. A read-only buffer is left alone.
+impl Buffer {
+    /// line_span() returns the first and last of the zero-based lines `range` touches. A range
+    /// ending at the start of a line leaves that line out, as a region over whole lines does.
+    pub fn line_span(&self, range: Range<usize>) -> (usize, usize) {
+        let (first_line, _) = self.offset_to_line_col(range.start);
+        let (last_line, last_column) = self.offset_to_line_col(range.end);
+        if last_column == 0 && last_line > first_line {
+            return (first_line, last_line - 1);
+        }
+
+        (first_line, last_line)
+    }
+
+    /// lines_range() returns the range of the text of the lines `first` to `last`, including the line break
+    /// after the last if it has one.
+    pub(super) fn lines_range(&self, first: usize, last: usize) -> Range<usize> {
+        let start = self.line_to_offset(first).unwrap();
+        let end = self.line_to_offset(last + 1).unwrap_or(self.len());
+
+        start..end
+    }
+
+    /// duplicate_lines() inserts a copy of the lines `range` touches after them, and moves the
+    /// point to the same place in the copy if it was in them.
+    pub fn duplicate_lines(&mut self, range: Range<usize>) -> bool {
+        if self.is_read_only() {
+            return false;
+        }
+        let (first, last) = self.line_span(range);
+        let lines = self.lines_range(first, last);
+        let text = self.slice(lines.clone()).into_owned();
+        let copy = match text.ends_with('\n') {
+            true => text.clone(),
+            false => format!("\n{}", text),
+        };
+
+        let point = self.get_point();
+        let in_lines = lines.contains(&point) || point == lines.end && !text.ends_with('\n');
+        self.replace_range(lines.end..lines.end, &copy);
+        if in_lines {
+            let copy_start = lines.end + copy.len() - text.len();
+            self.set_point(copy_start + point - lines.start);
+        }
+        true
+    }
+
+    /// move_lines() moves the lines `range` touches down past the line after them, or up past the
+    /// line before them, taking the point and the mark along if they are in them or end `range`.
+    /// Returns false if there is no line to move past.
+    pub fn move_lines(&mut self, range: Range<usize>, down: bool) -> bool {
+        let range_end = range.end;
+        let (first, last) = self.line_span(range);
+        let neighbour = match down {
+            true if last + 1 < self.line_count() => last + 1,
+            false if first > 0 => first - 1,
+            _ => return false,
+        };
+        if self.is_read_only() || down && self.lines_range(neighbour, neighbour).is_empty() {
+            return false;
+        }
+
+        let lines = self.lines_range(first, last);
+        let other = self.lines_range(neighbour, neighbour);
+        let (lines_text, other_text) = (self.slice(lines.clone()).into_owned(), self.slice(other.clone()).into_owned());
+        let (block, moved, new_start) = match down {
+            true if other_text.ends_with('\n') => (lines.start..other.end, format!("{}{}", other_text, lines_text), lines.start + other_text.len()),
+            true => (lines.start..other.end, format!("{}\n{}", other_text, &lines_text[..lines_text.len() - 1]), lines.start + other_text.len() + 1),
+            false if lines_text.ends_with('\n') => (other.start..lines.end, format!("{}{}", lines_text, other_text), other.start),
+            false => (other.start..lines.end, format!("{}\n{}", lines_text, other_text.trim_end_matches('\n')), other.start),
+        };
+
+        // The lines lose their line break when they become the last line.
+        let moved_length = match down && !other_text.ends_with('\n') {
+            true => lines_text.len() - 1,
+            false => lines_text.len(),
+        };
+        let follow = |offset: usize| match lines.contains(&offset) || offset == lines.end && (offset == range_end || !lines_text.ends_with('\n')) {
+            true => new_start + (offset - lines.start).min(moved_length),
+            false => offset,
+        };
+        let (point, mark) = (follow(self.get_point()), self.get_mark().map(follow));
+        self.replace_range(block, &moved);
+        self.set_point(point);
+        if let Some(mark) = mark {
+            self.set_mark(mark);
+        }
+        true
+    }
+
+    /// join_lines() joins the lines `range` touches into one, or the line it is on with the next
+    /// if it touches only one. The indentation of each joined line and the whitespace ending the
+    /// line before are replaced by a single space, or by nothing if either line is blank. The
+    /// point is left at the last join. Returns false if there is no line to join.
+    pub fn join_lines(&mut self, range: Range<usize>) -> bool {
+        let (first, mut last) = self.line_span(range);
+        if last == first {
+            last += 1;
+        }
+        if self.is_read_only() || last >= self.line_count() {
+            return false;
+        }
+
+        let start = self.line_to_offset(first).unwrap();
+        let end = self.line_end_offset(last).unwrap();
+        let text = self.slice(start..end).into_owned();
+        let mut lines = text.split('\n');
+        let mut joined = lines.next().unwrap_or_default().to_string();
+        let mut join = 0;
+        for line in lines {
+            let line = line.trim_start_matches([' ', '\t']);
+            joined.truncate(joined.trim_end_matches([' ', '\t']).len());
+            join = joined.len();
+            if !joined.is_empty() && !line.is_empty() {
+                joined.push(' ');
+            }
+            joined.push_str(line);
+        }
+
+        self.replace_range(start..end, &joined);
+        self.set_point(start + join);
+        true
+    }
+
+    /// sort_lines() sorts the lines `range` touches as `sort` says. Returns false if their order
+    /// did not change.
+    pub fn sort_lines(&mut self, range: Range<usize>, sort: SortLines) -> bool {
+        self.rewrite_lines(range, |lines| lines.sort_by(|(_, a), (_, b)| sort.compare(a, b)))
+    }
+
+    /// delete_duplicate_lines() deletes the lines `range` touches that are the same as one before
+    /// them, and returns how many it deleted.
+    pub fn delete_duplicate_lines(&mut self, range: Range<usize>) -> usize {
+        let mut deleted = 0;
+        self.rewrite_lines(range, |lines| {
+            let count = lines.len();
+            let mut seen = HashSet::new();
+            lines.retain(|(_, line)| seen.insert(line.clone()));
+            deleted = count - lines.len();
+        });
+        deleted
+    }
+
+    /// rewrite_lines() replaces the lines `range` touches, without their line breaks, with what
+    /// `rewrite` makes of them, in a single edit. Each line is given with its index among them, so
+    /// the point and the mark can follow the line they were on to wherever it goes, or to a line
+    /// the same as it if it was removed. At either end of the lines they stay at that end, as for
+    /// a region over them. Returns false if nothing changed.
+    fn rewrite_lines<F: FnOnce(&mut Vec<(usize, String)>)>(&mut self, range: Range<usize>, rewrite: F) -> bool {
+        if self.is_read_only() {
+            return false;
+        }
+        let (first, last) = self.line_span(range);
+        let lines = self.lines_range(first, last);
+        let text = self.slice(lines.clone()).into_owned();
+        let body = text.strip_suffix('\n').unwrap_or(&text);
+
+        let old_lines: Vec<&str> = body.split('\n').collect();
+        let mut rewritten: Vec<(usize, String)> = old_lines.iter().map(|line| line.to_string()).enumerate().collect();
+        rewrite(&mut rewritten);
+        let mut new = rewritten.iter().map(|(_, line)| line.as_str()).collect::<Vec<&str>>().join("\n");
+        if text.ends_with('\n') && !rewritten.is_empty() {
+            new.push('\n');
+        }
+        if new == text {
+            return false;
+        }
+
+        let new_end = lines.start + new.len();
+        let new_line_start = |found: &dyn Fn(&(usize, String)) -> bool| {
+            let mut start = lines.start;
+            for line in rewritten.iter() {
+                if found(line) {
+                    return Some(start);
+                }
+                start += line.1.len() + 1;
+            }
+            None
+        };
+        let keep = |offset: usize| {
+            if offset <= lines.start {
+                return offset;
+            }
+            if offset > lines.end || offset == lines.end && text.ends_with('\n') {
+                return offset + new_end - lines.end;
+            }
+            let before = &text[..offset - lines.start];
+            let index = before.matches('\n').count();
+            let column = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1);
+            new_line_start(&|(original, _)| *original == index)
+                .or_else(|| new_line_start(&|(_, line)| line == old_lines[index]))
+                .map_or(lines.start, |start| start + column)
+        };
+        let (point, mark) = (keep(self.get_point()), self.get_mark().map(keep));
+        self.replace_range(lines, &new);
+        self.set_point(point);
+        if let Some(mark) = mark {
+            self.set_mark(mark);
+        }
+        true
+    }
+
+    /// split_line() moves the text after the point, less the whitespace it starts with, down onto
+    /// a line of its own, indented to where it was. The point stays at the end of the line.
+    pub fn split_line(&mut self) -> bool {
+        if self.is_read_only() {
+            return false;
+        }
+        let point = self.get_point();
+        let (line, _) = self.offset_to_line_col(point);
+        let line_start = self.line_to_offset(line).unwrap();
+        let line_end = self.line_end_offset(line).unwrap();
+
+        let indentation: String = self
+            .slice(line_start..point)
+            .chars()
+            .flat_map(|character| match character {
+                '\t' => vec!['\t'],
+                _ => vec![' '; character.width().unwrap_or(0)],
+            })
+            .collect();
+        let rest = self.slice(point..line_end).into_owned();
+        let whitespace = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+
+        self.replace_range(point..point + whitespace, &format!("\n{}", indentation));
+        self.set_point(point);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::lines::SortLines;
+    use buffer::Buffer;
+
+    /// An edit made in a test, returning whether it changed anything.
+    type Edit = fn(&mut Buffer) -> bool;
+
+    /// with_point() writes the text of `buffer` with a `|` where the point is.
+    fn with_point(buffer: &Buffer) -> String {
+        let mut text = buffer.to_string();
+        text.insert(buffer.get_point(), '|');
+        text
+    }
+
+    /// buffer_with_point() makes a buffer of `text`, with the point where the `|` in it is.
+    fn buffer_with_point(text: &str) -> Buffer {
+        let point = text.find('|').unwrap();
+        let mut buffer = Buffer::from_text("lines", text.replacen('|', "", 1));
+        buffer.set_point(point);
+        buffer
+    }
+
+    #[test]
+    fn edit_the_line_of_the_point() {
+        let test_cases: [(&str, Edit, &str, &str, bool); 16] = [
+            ("duplicate a line", |buffer| buffer.duplicate_lines(buffer.get_point()..buffer.get_point()), "a\nb|c\nd", "a\nbc\nb|c\nd", true),
+            ("duplicate the last line", |buffer| buffer.duplicate_lines(buffer.get_point()..buffer.get_point()), "a\nb|", "a\nb\nb|", true),
+            ("move a line down", |buffer| buffer.move_lines(buffer.get_point()..buffer.get_point(), true), "a|b\ncd\nef", "cd\na|b\nef", true),
+            ("move a line down to the end", |buffer| buffer.move_lines(buffer.get_point()..buffer.get_point(), true), "a|b\ncd", "cd\na|b", true),
+            ("move a line up from the end", |buffer| buffer.move_lines(buffer.get_point()..buffer.get_point(), false), "ab\nc|d", "c|d\nab", true),
+            ("move the first line up", |buffer| buffer.move_lines(buffer.get_point()..buffer.get_point(), false), "a|b\ncd", "a|b\ncd", false),
+            ("move the last line down", |buffer| buffer.move_lines(buffer.get_point()..buffer.get_point(), true), "ab\nc|d\n", "ab\nc|d\n", false),
+            ("join with the next line", |buffer| buffer.join_lines(buffer.get_point()..buffer.get_point()), "|fn main() {  \n    run();\n}", "fn main() {| run();\n}", true),
+            ("join with a blank line", |buffer| buffer.join_lines(buffer.get_point()..buffer.get_point()), "a|\n\nb", "a|\nb", true),
+            ("join the last line", |buffer| buffer.join_lines(buffer.get_point()..buffer.get_point()), "a\n|b", "a\n|b", false),
+            ("split a line", |buffer| buffer.split_line(), "  let x|  = 1;", "  let x|\n       = 1;", true),
+            ("split after a tab", |buffer| buffer.split_line(), "\tx|y", "\tx|\n\t y", true),
+            ("split at the end", |buffer| buffer.split_line(), "ab|", "ab|\n  ", true),
+            ("duplicate nothing read-only", |buffer| {
+                buffer.set_read_only(true);
+                buffer.duplicate_lines(0..0)
+            }, "a|", "a|", false),
+            ("move read-only", |buffer| {
+                buffer.set_read_only(true);
+                buffer.move_lines(0..0, true)
+            }, "a|\nb", "a|\nb", false),
+            ("split read-only", |buffer| {
+                buffer.set_read_only(true);
+                buffer.split_line()
+            }, "a|b", "a|b", false),
+        ];
+
+        for (name, edit, text, expected, changed) in test_cases.iter() {
+            let mut buffer = buffer_with_point(text);
+            assert_eq!((edit(&mut buffer), with_point(&buffer)), (*changed, expected.to_string()), "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn edit_the_lines_of_a_region() {
+        let mut buffer = buffer_with_point("one\n|two\nthree\nfour\n");
+        buffer.set_mark(14);
+
+        assert!(buffer.move_lines(buffer.region().unwrap(), false));
+        assert_eq!(with_point(&buffer), "|two\nthree\none\nfour\n");
+        assert_eq!(buffer.region(), Some(0..10));
+
+        assert!(buffer.move_lines(buffer.region().unwrap(), true));
+        assert!(buffer.move_lines(buffer.region().unwrap(), true));
+        assert_eq!(with_point(&buffer), "one\nfour\n|two\nthree\n");
+        assert!(!buffer.move_lines(buffer.region().unwrap(), true));
+
+        assert!(buffer.duplicate_lines(buffer.region().unwrap()));
+        assert_eq!(buffer.to_string(), "one\nfour\ntwo\nthree\ntwo\nthree\n");
+
+        assert!(buffer.join_lines(0..8));
+        assert_eq!(with_point(&buffer), "one| four\ntwo\nthree\ntwo\nthree\n");
+    }
+
+    #[test]
+    fn move_lines_down_to_the_end() {
+        let mut buffer = buffer_with_point("x\n|y");
+        buffer.set_mark(0);
+
+        assert!(buffer.move_lines(buffer.region().unwrap(), true));
+        assert_eq!(with_point(&buffer), "y\nx|");
+        assert_eq!(buffer.region(), Some(2..3));
+
+        let mut buffer = buffer_with_point("x\n\n|y");
+        buffer.set_mark(0);
+        assert!(buffer.move_lines(buffer.region().unwrap(), true));
+        assert_eq!(with_point(&buffer), "y\nx\n|");
+
+        assert!(buffer.undo());
+        assert_eq!(with_point(&buffer), "x\n\n|y");
+        assert_eq!(buffer.get_mark(), Some(0));
+    }
+
+    #[test]
+    fn sort_and_uniquify_lines() {
+        let text = "pear\nbanana\nCherry\npear\napple\n";
+        let ascending = SortLines::default();
+        let test_cases = [
+            ("ascending", ascending, "Cherry\napple\nbanana\npear\npear\n"),
+            ("descending", SortLines { descending: true, ..ascending }, "pear\npear\nbanana\napple\nCherry\n"),
+            ("ignoring case", SortLines { ignore_case: true, ..ascending }, "apple\nbanana\nCherry\npear\npear\n"),
+            ("numeric without numbers", SortLines { numeric: true, ..ascending }, text),
+        ];
+        for (name, sort, expected) in test_cases.iter() {
+            let mut buffer = Buffer::from_text("lines", text.to_string());
+            buffer.sort_lines(0..buffer.len(), *sort);
+            assert_eq!(buffer.to_string(), *expected, "Test case: \"{}\" failed.", name);
+        }
+
+        let mut buffer = Buffer::from_text("numbers", "10 kg\n-1.5\nnone\n+2\n3".to_string());
+        buffer.set_point(3);
+        assert!(buffer.sort_lines(0..buffer.len(), SortLines { numeric: true, ..ascending }));
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), ("none\n-1.5\n+2\n3\n10 kg", 18));
+        assert!(!buffer.sort_lines(0..buffer.len(), SortLines { numeric: true, ..ascending }));
+
+        let mut buffer = Buffer::from_text("duplicates", "a\nb\na\nc\nb\nd\n".to_string());
+        assert_eq!(buffer.delete_duplicate_lines(0..9), 2);
+        assert_eq!(buffer.to_string(), "a\nb\nc\nd\n");
+        assert_eq!(buffer.delete_duplicate_lines(0..buffer.len()), 0);
+    }
+
+    #[test]
+    fn sorting_keeps_the_point_on_its_line() {
+        let mut buffer = Buffer::from_text("lines", "héllo wörld\n日本語 text\n\n  (foo [bar] \"q\")\n".to_string());
+        buffer.set_point(28);
+        buffer.set_mark(17);
+
+        assert!(buffer.sort_lines(0..buffer.len(), SortLines::default()));
+        assert_eq!(buffer.to_string(), "\n  (foo [bar] \"q\")\nhéllo wörld\n日本語 text\n");
+        assert_eq!((buffer.get_point(), buffer.get_mark()), (47, Some(36)));
+
+        let mut buffer = Buffer::from_text("duplicates", "b\nä\nb\nä\n".to_string());
+        buffer.set_point(7);
+        assert_eq!(buffer.delete_duplicate_lines(0..buffer.len()), 2);
+        assert_eq!(buffer.get_point(), 2);
+
+        assert!(buffer.undo());
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), ("b\nä\nb\nä\n", 7));
+    }
+}
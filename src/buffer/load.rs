@@ -0,0 +1,137 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str;
+
+use buffer::encoding::UTF8_BOM;
+use buffer::file::OpenMode;
+use buffer::{file_name, Buffer, Encoding, FileStamp, LineEnding};
+use load::LOAD_CHUNK_SIZE;
+use GapBuffer;
+
+/// Loader reads a file into a buffer a chunk at a time, so a frontend can keep drawing and show
+/// progress while a large file opens. The bytes go straight into the buffer's storage, sized from
+/// the file's length up front. Only files that need decoding, or have line endings other than
+/// `\n`, are copied again once read. Files that cannot be written are opened read-only.
+pub struct Loader {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    text: GapBuffer,
+    mode: OpenMode,
+    done: bool,
+    stamp: FileStamp,
+    read_only: bool,
+}
+
+impl Loader {
+    /// new() opens the file at `path` to be loaded as `mode` says.
+    pub fn new<P: AsRef<Path>>(path: P, mode: OpenMode) -> io::Result<Loader> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+
+        let mut text = GapBuffer::new();
+        text.reserve(size as usize);
+        Ok(Loader {
+            path: path.to_path_buf(),
+            file,
+            size,
+            text,
+            mode,
+            done: false,
+            stamp: FileStamp::from_metadata(&metadata),
+            read_only: metadata.permissions().readonly(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// size() is the length of the file when it was opened, in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// loaded() is how many bytes have been read so far.
+    pub fn loaded(&self) -> usize {
+        self.text.len()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// load_chunk() reads the next chunk of the file and returns whether the whole file is read.
+    pub fn load_chunk(&mut self) -> io::Result<bool> {
+        if !self.done {
+            self.done = self.text.read_from(&mut self.file, LOAD_CHUNK_SIZE)? == 0;
+        }
+        Ok(self.done)
+    }
+
+    /// finish() reads whatever is left of the file and returns the buffer visiting it.
+    pub fn finish(mut self) -> io::Result<Buffer> {
+        while !self.load_chunk()? {}
+
+        let name = file_name(&self.path).into_owned();
+        let bytes = self.text.make_contiguous();
+        let plain = !bytes.starts_with(UTF8_BOM) && !bytes.contains(&b'\r') && str::from_utf8(bytes).is_ok();
+
+        let mut buffer = match self.mode {
+            OpenMode::Bytes => {
+                let line_ending = LineEnding::detect_bytes(bytes);
+                let mut buffer = Buffer::from_gap_buffer(&name, self.text, line_ending);
+                buffer.encoding = Encoding::Bytes;
+                buffer
+            }
+            _ if plain => Buffer::from_gap_buffer(&name, self.text, LineEnding::Lf),
+            OpenMode::Detect => {
+                let (encoding, text) = Encoding::detect(bytes);
+                let mut buffer = Buffer::from_text(&name, text);
+                buffer.encoding = encoding;
+                buffer
+            }
+            OpenMode::Lossy => Buffer::from_text(&name, String::from_utf8_lossy(bytes).into_owned()),
+        };
+        buffer.path = Some(self.path);
+        buffer.disk_stamp = Some(self.stamp);
+        buffer.set_read_only(self.read_only);
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use buffer::file::OpenMode;
+    use buffer::load::Loader;
+    use buffer::{Encoding, LineEnding};
+    use load::LOAD_CHUNK_SIZE;
+
+    #[test]
+    fn load_a_file_in_chunks() {
+        let path = env::temp_dir().join(format!("one-load-{}.log", std::process::id()));
+        let text = "a log line\n".repeat(LOAD_CHUNK_SIZE / 4);
+        fs::write(&path, &text).unwrap();
+
+        let mut loader = Loader::new(&path, OpenMode::Detect).unwrap();
+        assert_eq!(loader.size(), text.len() as u64);
+        assert!(!loader.load_chunk().unwrap());
+        assert_eq!(loader.loaded(), LOAD_CHUNK_SIZE);
+
+        let buffer = loader.finish().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(buffer.len(), text.len());
+        assert_eq!(buffer.line_count(), LOAD_CHUNK_SIZE / 4 + 1);
+        assert_eq!(buffer.get_point(), 0);
+        assert_eq!(buffer.encoding(), Encoding::Utf8);
+        assert_eq!(buffer.line_ending(), LineEnding::Lf);
+        assert!(!buffer.is_modified());
+    }
+}
@@ -0,0 +1,327 @@
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+use buffer::Buffer;
+use indent::IndentSettings;
+
+/// The tab width tabify and untabify use unless told otherwise.
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// SaveTransform is a change made to a buffer's text each time it is saved, such as tidying
+/// whitespace. A buffer's transforms run in the order they were added, each on the text the last
+/// one left.
+pub trait SaveTransform {
+    fn name(&self) -> &str;
+
+    /// apply() returns `text` transformed. Lines in `text` end with `\n`.
+    fn apply(&self, text: &str) -> String;
+}
+
+/// StripTrailingWhitespace removes spaces and tabs from the ends of lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StripTrailingWhitespace;
+
+impl SaveTransform for StripTrailingWhitespace {
+    fn name(&self) -> &str {
+        "strip-trailing-whitespace"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        map_lines(text, |line| line.trim_end_matches([' ', '\t']).to_string())
+    }
+}
+
+/// EnsureFinalNewline makes a non-empty text end with exactly one newline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EnsureFinalNewline;
+
+impl SaveTransform for EnsureFinalNewline {
+    fn name(&self) -> &str {
+        "ensure-final-newline"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let trimmed = text.trim_end_matches('\n');
+        if trimmed.is_empty() {
+            return String::new();
+        }
+
+        format!("{}\n", trimmed)
+    }
+}
+
+/// Untabify replaces every tab with the spaces that reach the same column. Columns are counted
+/// the way they are drawn, so wide characters take two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Untabify {
+    pub tab_width: usize,
+}
+
+impl SaveTransform for Untabify {
+    fn name(&self) -> &str {
+        "untabify"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        map_lines(text, |line| {
+            let mut untabified = String::with_capacity(line.len());
+            let mut column = 0;
+
+            for character in line.chars() {
+                if character == '\t' {
+                    let spaces = self.tab_width - column % self.tab_width;
+                    untabified.extend(std::iter::repeat_n(' ', spaces));
+                    column += spaces;
+                } else {
+                    untabified.push(character);
+                    column += character.width().unwrap_or(0);
+                }
+            }
+            untabified
+        })
+    }
+}
+
+/// Tabify replaces the spaces in each line's indentation with tabs where they reach a tab stop.
+/// Spaces after the indentation are left alone, so the insides of strings are not changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tabify {
+    pub tab_width: usize,
+}
+
+impl SaveTransform for Tabify {
+    fn name(&self) -> &str {
+        "tabify"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        map_lines(text, |line| {
+            let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+            let (indent, rest) = line.split_at(indent_len);
+            let width = Untabify { tab_width: self.tab_width }.apply(indent).len();
+
+            let tabs = "\t".repeat(width / self.tab_width);
+            let spaces = " ".repeat(width % self.tab_width);
+            format!("{}{}{}", tabs, spaces, rest)
+        })
+    }
+}
+
+/// ShiftIndentation indents each line by `levels` more levels of `settings`, or fewer for negative
+/// levels. Every line moves by the same number of columns, so their indentation relative to each
+/// other is kept: lines are taken out only as far as the least indented one goes. Blank lines are
+/// left alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShiftIndentation {
+    pub levels: isize,
+    pub settings: IndentSettings,
+    pub tab_width: usize,
+}
+
+impl ShiftIndentation {
+    /// indent_width() returns the columns `line`'s indentation takes, or None for a blank line.
+    fn indent_width(&self, line: &str) -> Option<usize> {
+        let rest = line.trim_start_matches([' ', '\t']);
+        if rest.is_empty() {
+            return None;
+        }
+
+        Some(Untabify { tab_width: self.tab_width }.apply(&line[..line.len() - rest.len()]).len())
+    }
+}
+
+impl SaveTransform for ShiftIndentation {
+    fn name(&self) -> &str {
+        "shift-indentation"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let level = self.settings.level_width(self.tab_width) as isize;
+        let least = text.lines().filter_map(|line| self.indent_width(line)).min().unwrap_or(0) as isize;
+        let shift = (self.levels * level).max(-least);
+
+        map_lines(text, |line| match self.indent_width(line) {
+            Some(width) => {
+                let rest = line.trim_start_matches([' ', '\t']);
+                let width = (width as isize + shift) as usize;
+                format!("{}{}", self.settings.indentation(width, self.tab_width), rest)
+            }
+            None => line.to_string(),
+        })
+    }
+}
+
+/// map_lines() applies `transform` to each line of `text` without its newline.
+pub(crate) fn map_lines<F: Fn(&str) -> String>(text: &str, transform: F) -> String {
+    let mut mapped = String::with_capacity(text.len());
+
+    for line in text.split_inclusive('\n') {
+        match line.strip_suffix('\n') {
+            Some(line) => {
+                mapped.push_str(&transform(line));
+                mapped.push('\n');
+            }
+            None => mapped.push_str(&transform(line)),
+        }
+    }
+    mapped
+}
+
+/// Transforming the text, on save or on demand.
+impl Buffer {
+    /// add_save_transform() adds `transform` to those run, after the ones already added, each
+    /// time the buffer is saved.
+    pub fn add_save_transform<T: SaveTransform + 'static>(&mut self, transform: T) {
+        self.save_transforms.push(Box::new(transform));
+    }
+
+    pub fn save_transforms(&self) -> impl Iterator<Item = &dyn SaveTransform> {
+        self.save_transforms.iter().map(|transform| transform.as_ref())
+    }
+
+    /// remove_save_transform() stops running the transform called `name` on save, and returns
+    /// whether there was one.
+    pub fn remove_save_transform(&mut self, name: &str) -> bool {
+        let count = self.save_transforms.len();
+        self.save_transforms.retain(|transform| transform.name() != name);
+
+        self.save_transforms.len() != count
+    }
+
+    /// apply_transform() changes the text as `transform` says and returns whether anything
+    /// changed. Only the parts of lines that differ are replaced, so the point and marks elsewhere
+    /// stay on the same text. A read-only buffer is left alone.
+    pub fn apply_transform(&mut self, transform: &dyn SaveTransform) -> bool {
+        let len = self.len();
+        self.apply_transform_to_lines(0..len, transform)
+    }
+
+    /// apply_transform_to_lines() is apply_transform() on only the whole lines `range` touches,
+    /// such as those in the region. A range ending at the start of a line leaves that line out.
+    pub fn apply_transform_to_lines(&mut self, range: Range<usize>, transform: &dyn SaveTransform) -> bool {
+        if self.is_read_only() {
+            return false;
+        }
+        let (first_line, last_line) = self.line_span(range);
+        let Range { start: offset, end } = self.lines_range(first_line, last_line);
+
+        let old = self.slice(offset..end).into_owned();
+        let new = transform.apply(&old);
+        if old == new {
+            return false;
+        }
+
+        let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+        let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+        if old_lines.len() == new_lines.len() {
+            let mut end = end;
+            for (old_line, new_line) in old_lines.iter().zip(new_lines.iter()).rev() {
+                let start = end - old_line.len();
+                if old_line != new_line {
+                    let (prefix, suffix) = common_affixes(old_line, new_line);
+                    self.replace_range(start + prefix..end - suffix, &new_line[prefix..new_line.len() - suffix]);
+                }
+                end = start;
+            }
+        } else {
+            let (prefix, suffix) = common_affixes(&old, &new);
+            self.replace_range(offset + prefix..end - suffix, &new[prefix..new.len() - suffix]);
+        }
+        true
+    }
+
+    /// run_save_transforms() applies each save transform in turn.
+    pub(crate) fn run_save_transforms(&mut self) {
+        let transforms = std::mem::take(&mut self.save_transforms);
+        for transform in transforms.iter() {
+            self.apply_transform(transform.as_ref());
+        }
+        self.save_transforms = transforms;
+    }
+}
+
+/// common_affixes() returns the lengths of the longest prefix and suffix `a` and `b` share, which
+/// do not overlap and end on character boundaries.
+fn common_affixes(a: &str, b: &str) -> (usize, usize) {
+    let mut prefix = a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count();
+    while !a.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+
+    let limit = a.len().min(b.len()) - prefix;
+    let mut suffix = a.bytes().rev().zip(b.bytes().rev()).take(limit).take_while(|(x, y)| x == y).count();
+    while !a.is_char_boundary(a.len() - suffix) {
+        suffix -= 1;
+    }
+    (prefix, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::transform::{EnsureFinalNewline, SaveTransform, ShiftIndentation, StripTrailingWhitespace, Tabify, Untabify};
+    use buffer::Buffer;
+    use indent::IndentSettings;
+
+    #[test]
+    fn transform_text() {
+        let tabs = IndentSettings { use_tabs: true, width: 4 };
+        let shift_with_tabs = ShiftIndentation { levels: 1, settings: tabs, tab_width: 4 };
+        let dedent = ShiftIndentation { levels: -1, settings: IndentSettings::default(), tab_width: 4 };
+        let test_cases: [(&str, &dyn SaveTransform, &str, &str); 8] = [
+            ("strip trailing whitespace", &StripTrailingWhitespace, "a  \nb\t\n  c ", "a\nb\n  c"),
+            ("add a final newline", &EnsureFinalNewline, "a\nb", "a\nb\n"),
+            ("remove extra final newlines", &EnsureFinalNewline, "a\n\n\n", "a\n"),
+            ("leave an empty text empty", &EnsureFinalNewline, "\n\n", ""),
+            ("untabify", &Untabify { tab_width: 4 }, "\tx\ty\n ab\tz", "    x   y\n ab z"),
+            ("tabify indentation only", &Tabify { tab_width: 4 }, "      x  y\n\t  z", "\t  x  y\n\t  z"),
+            ("indent with tabs", &shift_with_tabs, "  x\n\n\ty", "\t  x\n\n\t\ty"),
+            ("dedent as far as the least indented line", &dedent, "  a\n      b", "a\n    b"),
+        ];
+
+        for (name, transform, text, expected) in test_cases.iter() {
+            assert_eq!(transform.apply(text), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn transforms_only_replace_changed_lines() {
+        let mut buffer = Buffer::from_text("notes", "one  \ntwo\nthree \n".to_string());
+        buffer.set_point(8);
+        buffer.set_mark(14);
+
+        assert!(buffer.apply_transform(&StripTrailingWhitespace));
+        assert!(!buffer.apply_transform(&StripTrailingWhitespace));
+
+        assert_eq!(buffer.to_string(), "one\ntwo\nthree\n");
+        assert_eq!(buffer.get_point(), 6);
+        assert_eq!(buffer.get_mark(), Some(12));
+    }
+
+    #[test]
+    fn transform_only_the_lines_in_a_range() {
+        let mut buffer = Buffer::from_text("notes", "a \nb \nc \n".to_string());
+
+        assert!(buffer.apply_transform_to_lines(4..5, &StripTrailingWhitespace));
+        assert_eq!(buffer.to_string(), "a \nb\nc \n");
+        assert!(buffer.apply_transform_to_lines(0..5, &StripTrailingWhitespace));
+        assert_eq!(buffer.to_string(), "a\nb\nc \n");
+    }
+
+    #[test]
+    fn transforms_run_in_order_on_save() {
+        let path = std::env::temp_dir().join(format!("one-transform-{}.txt", std::process::id()));
+        let mut buffer = Buffer::visiting(&path, "  x  \n\n".to_string());
+        buffer.add_save_transform(StripTrailingWhitespace);
+        buffer.add_save_transform(EnsureFinalNewline);
+        buffer.add_save_transform(Tabify { tab_width: 2 });
+
+        buffer.save().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "\tx\n");
+        assert_eq!(buffer.to_string(), "\tx\n");
+        assert!(buffer.remove_save_transform("tabify"));
+        assert_eq!(buffer.save_transforms().count(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+use buffer::Buffer;
+use motion::is_word_char;
+
+/// Swapping the characters, words and lines around the point, as Emacs does. Each swap replaces
+/// a single range of the text and leaves the point after the things swapped, so repeating it
+/// drags a thing along. Each returns false, changing nothing, if there are not two things to
+/// swap or the buffer is read-only.
+impl Buffer {
+    /// transpose_chars() swaps the characters before and after the point and moves the point past
+    /// both. At the end of a line the two characters before the point are swapped instead.
+    pub fn transpose_chars(&mut self) -> bool {
+        let point = self.get_point();
+        let at_line_end = matches!(self.char_at(point), None | Some('\n'));
+        let middle = match at_line_end {
+            true => self.prev_char_boundary(point),
+            false => Some(point),
+        };
+        let (start, middle) = match middle.and_then(|middle| Some((self.prev_char_boundary(middle)?, middle))) {
+            Some(chars) => chars,
+            None => return false,
+        };
+        let end = self.next_char_boundary(middle).unwrap();
+
+        self.swap(start..middle, middle..end)
+    }
+
+    /// transpose_words() swaps the word the point is in or after with the next word, leaving
+    /// what is between them where it is. Before the first word, that word is swapped with the
+    /// one after it.
+    pub fn transpose_words(&mut self) -> bool {
+        let point = self.get_point();
+        let before_word = self.prev_char_boundary(point).and_then(|previous| self.char_at(previous)).is_some_and(is_word_char);
+        let in_word = before_word && self.char_at(point).is_some_and(is_word_char);
+        let mut first_end = match in_word {
+            true => self.skip_forward(point, true),
+            false => self.skip_backward(point, false),
+        };
+        let mut first_start = self.skip_backward(first_end, true);
+        if first_start == first_end {
+            first_start = self.skip_forward(point, false);
+            first_end = self.skip_forward(first_start, true);
+        }
+        let second_start = self.skip_forward(first_end, false);
+        let second_end = self.skip_forward(second_start, true);
+        if first_start == first_end || second_start == second_end {
+            return false;
+        }
+
+        self.swap(first_start..first_end, second_start..second_end)
+    }
+
+    /// transpose_lines() swaps the line of the point with the line before it and moves the point
+    /// to the start of the line after both.
+    pub fn transpose_lines(&mut self) -> bool {
+        let (line, _) = self.offset_to_line_col(self.get_point());
+        if line == 0 || self.is_read_only() {
+            return false;
+        }
+
+        let point = self.get_point();
+        self.move_lines(point..point, false);
+        let after = self.line_to_offset(line + 1).unwrap_or(self.len());
+        self.set_point(after);
+        true
+    }
+
+    /// swap() exchanges the text in `first` with the text in `second`, which comes after it, and
+    /// leaves the point at the end of `second`.
+    fn swap(&mut self, first: Range<usize>, second: Range<usize>) -> bool {
+        if self.is_read_only() {
+            return false;
+        }
+
+        let swapped = format!("{}{}{}", self.slice(second.clone()), self.slice(first.end..second.start), self.slice(first.clone()));
+        self.replace_range(first.start..second.end, &swapped);
+        self.set_point(second.end);
+        true
+    }
+
+    /// skip_forward() returns where the run of word characters, or of other characters, that
+    /// starts at `index` ends.
+    pub(super) fn skip_forward(&self, mut index: usize, word: bool) -> usize {
+        while let Some(character) = self.char_at(index) {
+            if is_word_char(character) != word {
+                break;
+            }
+            index = self.next_char_boundary(index).unwrap();
+        }
+        index
+    }
+
+    /// skip_backward() returns where the run of word characters, or of other characters, that
+    /// ends at `index` starts.
+    fn skip_backward(&self, mut index: usize, word: bool) -> usize {
+        while let Some(previous) = self.prev_char_boundary(index) {
+            if self.char_at(previous).is_some_and(is_word_char) != word {
+                break;
+            }
+            index = previous;
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::Buffer;
+
+    /// An edit made in a test, returning whether it changed anything.
+    type Edit = fn(&mut Buffer) -> bool;
+
+    #[test]
+    fn transpose_around_the_point() {
+        let test_cases: [(&str, Edit, &str, &str, bool); 15] = [
+            ("characters", Buffer::transpose_chars, "ab|cd", "acb|d", true),
+            ("characters at the end of a line", Buffer::transpose_chars, "abc|\nd", "acb|\nd", true),
+            ("characters at the end of the buffer", Buffer::transpose_chars, "ab|", "ba|", true),
+            ("wide characters", Buffer::transpose_chars, "né|e", "neé|", true),
+            ("characters at the start", Buffer::transpose_chars, "|ab", "|ab", false),
+            ("a single character", Buffer::transpose_chars, "a|", "a|", false),
+            ("words", Buffer::transpose_words, "one |two, three", "two one|, three", true),
+            ("words inside the first", Buffer::transpose_words, "one t|wo, three", "one three, two|", true),
+            ("words after the first", Buffer::transpose_words, "café|  = 1", "1  = café|", true),
+            ("words with no second", Buffer::transpose_words, "one two|.", "one two|.", false),
+            ("words before the first", Buffer::transpose_words, "| one two", " two one|", true),
+            ("words with only one", Buffer::transpose_words, "|  one", "|  one", false),
+            ("lines", Buffer::transpose_lines, "a\nb|\nc", "b\na\n|c", true),
+            ("the last line", Buffer::transpose_lines, "a\nb|", "b\na|", true),
+            ("the first line", Buffer::transpose_lines, "a|\nb", "a|\nb", false),
+        ];
+
+        for (name, edit, text, expected, changed) in test_cases.iter() {
+            let point = text.find('|').unwrap();
+            let mut buffer = Buffer::from_text("transpose", text.replacen('|', "", 1));
+            buffer.set_point(point);
+
+            let swapped = edit(&mut buffer);
+            let mut result = buffer.to_string();
+            result.insert(buffer.get_point(), '|');
+            assert_eq!((swapped, result.as_str()), (*changed, *expected), "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn skip_over_invalid_utf8() {
+        let mut buffer = Buffer::new("transpose");
+        buffer.insert_bytes(b"\xff\xffab \xff".to_vec());
+
+        assert_eq!(buffer.skip_forward(0, false), 2);
+        assert_eq!(buffer.skip_forward(2, true), 4);
+        assert_eq!(buffer.skip_forward(4, false), buffer.len());
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use buffer::file::OpenMode;
+use buffer::load::Loader;
+use buffer::{Buffer, Encoding};
+
+/// FileStamp is what a file looked like when a buffer last read or wrote it: enough to notice
+/// when something else changes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &Metadata) -> FileStamp {
+        FileStamp {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        }
+    }
+
+    /// of() returns the stamp of the file at `path`, or None if there is no such file.
+    pub fn of(path: &Path) -> Option<FileStamp> {
+        fs::metadata(path).ok().map(|metadata| FileStamp::from_metadata(&metadata))
+    }
+}
+
+/// Noticing when the visited file is changed by something else, and reading it again.
+impl Buffer {
+    /// changed_on_disk() reports whether the visited file has been changed or deleted since the
+    /// buffer last read or wrote it.
+    pub fn changed_on_disk(&self) -> bool {
+        match (&self.path, self.disk_stamp) {
+            (Some(path), Some(stamp)) => FileStamp::of(path) != Some(stamp),
+            (Some(path), None) => path.exists(),
+            (None, _) => false,
+        }
+    }
+
+    /// revert() replaces the contents with the visited file as it is now, discarding any changes,
+    /// even in a read-only buffer.
+    /// Marks, cursors and listeners stay attached, and the point stays where it was as far as the
+    /// new contents allow. A narrowed buffer is widened first.
+    pub fn revert(&mut self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "Buffer is not visiting a file")),
+        };
+        let mode = if self.encoding == Encoding::Bytes { OpenMode::Bytes } else { OpenMode::Detect };
+        let mut fresh = Loader::new(&path, mode)?.finish()?;
+
+        let contents = fresh.make_contiguous().to_vec();
+        self.widen();
+        self.with_writable(|buffer| {
+            let point = buffer.get_point();
+            let len = buffer.len();
+            buffer.remove_bytes(0..len);
+            buffer.set_point(0);
+            buffer.insert_bytes(contents);
+
+            let mut point = point.min(buffer.len());
+            while !buffer.is_char_boundary(point) {
+                point -= 1;
+            }
+            buffer.set_point(point);
+        });
+        self.encoding = fresh.encoding;
+        self.line_ending = fresh.line_ending;
+        self.disk_stamp = fresh.disk_stamp;
+        self.set_modified(false);
+        self.remove_auto_save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+
+    use buffer::Buffer;
+
+    #[test]
+    fn notice_changes_and_revert() {
+        let path = env::temp_dir().join(format!("one-watch-{}.txt", std::process::id()));
+        fs::write(&path, "first version\n").unwrap();
+
+        let mut buffer = Buffer::open(&path).unwrap();
+        buffer.set_point(6);
+        buffer.set_mark(0);
+        assert!(!buffer.changed_on_disk());
+
+        thread::sleep(Duration::from_millis(20));
+        fs::write(&path, "second version\n").unwrap();
+        assert!(buffer.changed_on_disk());
+
+        buffer.insert_str("unsaved ");
+        buffer.revert().unwrap();
+
+        assert_eq!(buffer.to_string(), "second version\n");
+        assert_eq!(buffer.get_point(), 14);
+        assert_eq!(buffer.get_mark(), Some(0));
+        assert!(!buffer.is_modified());
+        assert!(!buffer.changed_on_disk());
+
+        buffer.insert_str("!");
+        buffer.save().unwrap();
+        assert!(!buffer.changed_on_disk());
+        fs::remove_file(&path).unwrap();
+        assert!(buffer.changed_on_disk());
+    }
+}
@@ -0,0 +1,302 @@
+use buffer::Buffer;
+
+/// The buffer an editor starts with, for text that is not meant for a file.
+pub const SCRATCH_BUFFER: &str = "*scratch*";
+
+/// The read-only buffer every message for the user is logged to.
+pub const MESSAGES_BUFFER: &str = "*messages*";
+
+/// How many lines the messages buffer keeps, the oldest going first, as in Emacs.
+pub const MESSAGE_LOG_MAX: usize = 1000;
+
+/// BufferId identifies a buffer held by a `BufferManager`. Ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BufferId(usize);
+
+/// BufferManager holds every open buffer and which one is current.
+/// - `buffers`: The open buffers, in the order they were added.
+/// - `recent`: Buffer ids, most recently current first, to pick a new current buffer when one is
+///   killed.
+#[derive(Default)]
+pub struct BufferManager {
+    buffers: Vec<(BufferId, Buffer)>,
+    recent: Vec<BufferId>,
+    next_id: usize,
+}
+
+impl BufferManager {
+    pub fn new() -> BufferManager {
+        BufferManager::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+
+    /// create() adds an empty buffer called `name`, or a unique variant of it.
+    pub fn create(&mut self, name: &str) -> BufferId {
+        self.add(Buffer::new(name))
+    }
+
+    /// add() takes ownership of `buffer`, renaming it if its name is already taken. The first
+    /// buffer added becomes the current buffer.
+    pub fn add(&mut self, mut buffer: Buffer) -> BufferId {
+        let name = self.unique_name(buffer.name());
+        buffer.set_name(&name);
+
+        let id = BufferId(self.next_id);
+        self.next_id += 1;
+        self.buffers.push((id, buffer));
+        self.recent.push(id);
+
+        id
+    }
+
+    /// unique_name() returns `name` if no buffer has it, or else `name<2>`, `name<3>` and so on.
+    pub fn unique_name(&self, name: &str) -> String {
+        if self.find(name).is_none() {
+            return name.to_string();
+        }
+
+        (2..)
+            .map(|number| format!("{}<{}>", name, number))
+            .find(|candidate| self.find(candidate).is_none())
+            .unwrap()
+    }
+
+    /// find() returns the id of the buffer called `name`.
+    pub fn find(&self, name: &str) -> Option<BufferId> {
+        self.buffers.iter().find(|(_, buffer)| buffer.name() == name).map(|(id, _)| *id)
+    }
+
+    pub fn get(&self, id: BufferId) -> Option<&Buffer> {
+        self.buffers.iter().find(|(candidate, _)| *candidate == id).map(|(_, buffer)| buffer)
+    }
+
+    pub fn get_mut(&mut self, id: BufferId) -> Option<&mut Buffer> {
+        self.buffers.iter_mut().find(|(candidate, _)| *candidate == id).map(|(_, buffer)| buffer)
+    }
+
+    /// iter() visits the open buffers in the order they were added.
+    pub fn iter(&self) -> impl Iterator<Item = (BufferId, &Buffer)> {
+        self.buffers.iter().map(|(id, buffer)| (*id, buffer))
+    }
+
+    /// recent() visits the open buffers, the most recently current first.
+    pub fn recent(&self) -> impl Iterator<Item = (BufferId, &Buffer)> {
+        self.recent.iter().filter_map(move |id| self.get(*id).map(|buffer| (*id, buffer)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (BufferId, &mut Buffer)> {
+        self.buffers.iter_mut().map(|(id, buffer)| (*id, buffer))
+    }
+
+    pub fn current_id(&self) -> Option<BufferId> {
+        self.recent.first().copied()
+    }
+
+    pub fn current(&self) -> Option<&Buffer> {
+        self.get(self.current_id()?)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut Buffer> {
+        let id = self.current_id()?;
+        self.get_mut(id)
+    }
+
+    /// switch_to() makes buffer `id` current and returns false if there is no such buffer.
+    pub fn switch_to(&mut self, id: BufferId) -> bool {
+        match self.recent.iter().position(|candidate| *candidate == id) {
+            Some(position) => {
+                self.recent.remove(position);
+                self.recent.insert(0, id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// rename() renames buffer `id` to `name`, or a unique variant of it, and returns the name given.
+    pub fn rename(&mut self, id: BufferId, name: &str) -> Option<String> {
+        let current_name = self.get(id)?.name().to_string();
+        let name = match current_name == name {
+            true => current_name,
+            false => self.unique_name(name),
+        };
+
+        self.get_mut(id)?.set_name(&name);
+        Some(name)
+    }
+
+    /// scratch() returns the id of the scratch buffer, making a new one if it was killed.
+    pub fn scratch(&mut self) -> BufferId {
+        match self.find(SCRATCH_BUFFER) {
+            Some(id) => id,
+            None => self.create(SCRATCH_BUFFER),
+        }
+    }
+
+    /// messages() returns the id of the read-only messages buffer, making it if there is none.
+    pub fn messages(&mut self) -> BufferId {
+        match self.find(MESSAGES_BUFFER) {
+            Some(id) => id,
+            None => {
+                let mut buffer = Buffer::new(MESSAGES_BUFFER);
+                buffer.set_read_only(true);
+                self.add(buffer)
+            }
+        }
+    }
+
+    /// log_message() adds `message` as a line at the end of the messages buffer, making the
+    /// buffer the first time, and returns the buffer's id. A message that repeats the one before
+    /// is counted on its line instead, as in `Saved [3 times]`. Only the last `MESSAGE_LOG_MAX`
+    /// lines are kept. A point at the end of the buffer stays at the end.
+    pub fn log_message(&mut self, message: &str) -> BufferId {
+        let id = self.messages();
+        let buffer = self.get_mut(id).unwrap();
+        buffer.with_writable(|buffer| {
+            let at_end = buffer.get_point() == buffer.len();
+            let last_line = buffer.line_count().saturating_sub(2);
+            let start = buffer.line_to_offset(last_line).unwrap();
+            let end = buffer.len();
+            let last = buffer.slice(start..end).trim_end_matches('\n').to_string();
+
+            let (range, line) = match repeat_count(&last, message) {
+                Some(count) => (start..end, format!("{} [{} times]\n", message, count + 1)),
+                None => (end..end, format!("{}\n", message)),
+            };
+            buffer.replace_range(range, &line);
+            if buffer.line_count() - 1 > MESSAGE_LOG_MAX {
+                let oldest = buffer.line_to_offset(buffer.line_count() - 1 - MESSAGE_LOG_MAX).unwrap();
+                buffer.replace_range(0..oldest, "");
+            }
+            if at_end {
+                let end = buffer.len();
+                buffer.set_point(end);
+            }
+        });
+        buffer.set_modified(false);
+        id
+    }
+
+    /// kill() removes buffer `id` and returns it. If it was current, the buffer that was current
+    /// before it takes its place.
+    pub fn kill(&mut self, id: BufferId) -> Option<Buffer> {
+        let position = self.buffers.iter().position(|(candidate, _)| *candidate == id)?;
+        self.recent.retain(|candidate| *candidate != id);
+
+        Some(self.buffers.remove(position).1)
+    }
+}
+
+/// repeat_count() returns how many times `line` of the messages buffer says `message` was
+/// logged, or `None` if it is about another message.
+fn repeat_count(line: &str, message: &str) -> Option<usize> {
+    match line.strip_prefix(message)? {
+        "" => Some(1),
+        rest => rest.strip_prefix(" [")?.strip_suffix(" times]")?.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::Buffer;
+    use buffer_manager::{BufferManager, MESSAGES_BUFFER, MESSAGE_LOG_MAX, SCRATCH_BUFFER};
+
+    #[test]
+    fn the_first_buffer_is_current() {
+        let mut buffers = BufferManager::new();
+        assert!(buffers.current().is_none());
+
+        let scratch = buffers.create("*scratch*");
+        buffers.create("notes");
+
+        assert_eq!(buffers.len(), 2);
+        assert_eq!(buffers.current_id(), Some(scratch));
+        assert_eq!(buffers.current().unwrap().name(), "*scratch*");
+    }
+
+    #[test]
+    fn messages_are_logged_to_a_read_only_buffer() {
+        let mut buffers = BufferManager::new();
+        let scratch = buffers.scratch();
+
+        let messages = buffers.log_message("Saved");
+        buffers.log_message("Saved");
+        assert_eq!(buffers.log_message("Saved"), messages);
+        buffers.log_message("Mark set");
+        let buffer = buffers.get(messages).unwrap();
+        assert_eq!(buffer.to_string(), "Saved [3 times]\nMark set\n");
+        assert!(buffer.is_read_only() && !buffer.is_modified());
+        assert_eq!((buffer.get_point(), buffers.current_id()), (buffer.len(), Some(scratch)));
+
+        for number in 0..MESSAGE_LOG_MAX {
+            buffers.log_message(&number.to_string());
+        }
+        let buffer = buffers.get(messages).unwrap();
+        assert_eq!(buffer.line_count() - 1, MESSAGE_LOG_MAX);
+        assert!(buffer.to_string().starts_with("0\n1\n"));
+
+        buffers.kill(scratch);
+        assert_ne!(buffers.scratch(), scratch);
+        assert_eq!(buffers.find(SCRATCH_BUFFER), Some(buffers.scratch()));
+        assert_eq!(buffers.find(MESSAGES_BUFFER), Some(messages));
+    }
+
+    #[test]
+    fn names_are_made_unique() {
+        let mut buffers = BufferManager::new();
+
+        let first = buffers.create("main.rs");
+        let second = buffers.add(Buffer::visiting("other/main.rs", String::new()));
+        let third = buffers.create("main.rs");
+
+        assert_eq!(buffers.get(first).unwrap().name(), "main.rs");
+        assert_eq!(buffers.get(second).unwrap().name(), "main.rs<2>");
+        assert_eq!(buffers.get(third).unwrap().name(), "main.rs<3>");
+        assert_eq!(buffers.find("main.rs<2>"), Some(second));
+
+        assert_eq!(buffers.rename(third, "main.rs<2>"), Some("main.rs<2><2>".to_string()));
+        assert_eq!(buffers.rename(first, "main.rs"), Some("main.rs".to_string()));
+    }
+
+    #[test]
+    fn switch_between_buffers() {
+        let mut buffers = BufferManager::new();
+        let a = buffers.create("a");
+        let b = buffers.create("b");
+
+        assert!(buffers.switch_to(b));
+        buffers.current_mut().unwrap().insert_str("text");
+
+        assert_eq!(buffers.get(b).unwrap().to_string(), "text");
+        assert!(buffers.get(a).unwrap().is_empty());
+    }
+
+    #[test]
+    fn killing_the_current_buffer_returns_to_the_previous_one() {
+        let mut buffers = BufferManager::new();
+        let a = buffers.create("a");
+        let b = buffers.create("b");
+        let c = buffers.create("c");
+        buffers.switch_to(c);
+        buffers.switch_to(b);
+
+        let killed = buffers.kill(b).unwrap();
+
+        assert_eq!(killed.name(), "b");
+        assert_eq!(buffers.current_id(), Some(c));
+        assert!(buffers.kill(b).is_none());
+        assert!(!buffers.switch_to(b));
+
+        buffers.kill(c);
+        buffers.kill(a);
+        assert!(buffers.is_empty());
+        assert!(buffers.current().is_none());
+    }
+}
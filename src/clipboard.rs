@@ -0,0 +1,38 @@
+#[cfg(feature = "clipboard")]
+use arboard;
+
+/// Clipboard is somewhere text can be shared with other applications.
+/// A `KillRing` given a clipboard copies every kill to it and yanks whatever was last put there.
+pub trait Clipboard {
+    /// get_text() returns the text on the clipboard, or `None` if it is empty or unavailable.
+    fn get_text(&mut self) -> Option<String>;
+
+    /// set_text() puts `text` on the clipboard and returns false if that failed.
+    fn set_text(&mut self, text: &str) -> bool;
+}
+
+/// SystemClipboard is the clipboard of the operating system, on X11, Wayland, macOS and Windows.
+#[cfg(feature = "clipboard")]
+pub struct SystemClipboard {
+    clipboard: arboard::Clipboard,
+}
+
+#[cfg(feature = "clipboard")]
+impl SystemClipboard {
+    /// new() connects to the system clipboard and returns `None` if there is none, such as in a
+    /// session without a display.
+    pub fn new() -> Option<SystemClipboard> {
+        arboard::Clipboard::new().ok().map(|clipboard| SystemClipboard { clipboard })
+    }
+}
+
+#[cfg(feature = "clipboard")]
+impl Clipboard for SystemClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.clipboard.get_text().ok()
+    }
+
+    fn set_text(&mut self, text: &str) -> bool {
+        self.clipboard.set_text(text).is_ok()
+    }
+}
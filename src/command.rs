@@ -0,0 +1,170 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+
+use buffer::ReadOnlyError;
+use editor::Editor;
+use lsp::LspError;
+use syntax::SyntaxError;
+use theme::ThemeError;
+
+pub type CommandResult = Result<(), CommandError>;
+
+type CommandFn = dyn Fn(&mut Editor, &str) -> CommandResult;
+
+/// Completer lists the possible arguments to a command that could follow the input typed to its
+/// prompt so far.
+pub type Completer = Rc<dyn Fn(&Editor, &str) -> Vec<String>>;
+
+/// CommandError is why a command could not run.
+#[derive(Debug)]
+pub enum CommandError {
+    /// No command is registered under the name.
+    UnknownCommand(String),
+    /// The command cannot use the argument it was given.
+    InvalidArgument { command: String, argument: String },
+    /// There is no current buffer to act on.
+    NoBuffer,
+    /// The buffer is not visiting a file.
+    NoFile,
+    /// A keyboard macro tried to play a macro.
+    MacroRecursion,
+    /// The command would change a read-only buffer.
+    ReadOnly(ReadOnlyError),
+    /// A theme could not be loaded.
+    Theme(ThemeError),
+    /// A file of syntax rules could not be loaded.
+    Syntax(SyntaxError),
+    /// A language server could not be started or talked to.
+    Lsp(LspError),
+    Io(io::Error),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::UnknownCommand(name) => write!(f, "Unknown command: {}", name),
+            CommandError::InvalidArgument { command, argument } => {
+                write!(f, "Invalid argument to {}: {:?}", command, argument)
+            }
+            CommandError::NoBuffer => write!(f, "No buffer"),
+            CommandError::NoFile => write!(f, "Buffer is not visiting a file"),
+            CommandError::MacroRecursion => write!(f, "A keyboard macro cannot play a macro"),
+            CommandError::ReadOnly(error) => error.fmt(f),
+            CommandError::Theme(error) => error.fmt(f),
+            CommandError::Syntax(error) => error.fmt(f),
+            CommandError::Lsp(error) => error.fmt(f),
+            CommandError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for CommandError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CommandError::ReadOnly(error) => Some(error),
+            CommandError::Theme(error) => Some(error),
+            CommandError::Syntax(error) => Some(error),
+            CommandError::Lsp(error) => Some(error),
+            CommandError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ReadOnlyError> for CommandError {
+    fn from(error: ReadOnlyError) -> CommandError {
+        CommandError::ReadOnly(error)
+    }
+}
+
+impl From<ThemeError> for CommandError {
+    fn from(error: ThemeError) -> CommandError {
+        CommandError::Theme(error)
+    }
+}
+
+impl From<SyntaxError> for CommandError {
+    fn from(error: SyntaxError) -> CommandError {
+        CommandError::Syntax(error)
+    }
+}
+
+impl From<LspError> for CommandError {
+    fn from(error: LspError) -> CommandError {
+        CommandError::Lsp(error)
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(error: io::Error) -> CommandError {
+        CommandError::Io(error)
+    }
+}
+
+/// Command is a named operation on the editor, run with a text argument that is empty when the
+/// command needs none.
+/// - `prompt`: What to ask for in the minibuffer when the command is run from a key without an
+///   argument.
+/// - `completer`: The possible arguments, for completing the input to the prompt.
+#[derive(Clone)]
+pub struct Command {
+    name: String,
+    description: String,
+    run: Rc<CommandFn>,
+    prompt: Option<String>,
+    completer: Option<Completer>,
+}
+
+impl Command {
+    pub fn new<F>(name: &str, description: &str, run: F) -> Command
+    where
+        F: Fn(&mut Editor, &str) -> CommandResult + 'static,
+    {
+        Command {
+            name: name.to_string(),
+            description: description.to_string(),
+            run: Rc::new(run),
+            prompt: None,
+            completer: None,
+        }
+    }
+
+    /// with_prompt() makes the command ask for its argument when run from a key.
+    pub fn with_prompt(mut self, prompt: &str) -> Command {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    pub fn with_completer<F: Fn(&Editor, &str) -> Vec<String> + 'static>(mut self, completer: F) -> Command {
+        self.completer = Some(Rc::new(completer));
+        self
+    }
+
+    pub fn prompt(&self) -> Option<&str> {
+        self.prompt.as_deref()
+    }
+
+    pub fn completer(&self) -> Option<Completer> {
+        self.completer.clone()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn run(&self, editor: &mut Editor, argument: &str) -> CommandResult {
+        (self.run)(editor, argument)
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Command").field("name", &self.name).finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use buffer::transform::map_lines;
+use buffer::SaveTransform;
+
+/// A file type's extensions, the start of its line comments and the delimiters of its block
+/// comments.
+type CommentEntry = (&'static [&'static str], Option<&'static str>, Option<(&'static str, &'static str)>);
+
+/// The comment syntax of each file type.
+const COMMENT_SYNTAXES: &[CommentEntry] = &[
+    (&["rs", "c", "h", "cc", "cpp", "hpp", "js", "mjs", "cjs", "ts", "java", "go", "swift"], Some("//"), Some(("/*", "*/"))),
+    (&["py", "pyw", "sh", "bash", "zsh", "rb", "pl", "toml", "yaml", "yml", "conf"], Some("#"), None),
+    (&["lua", "sql", "hs"], Some("--"), None),
+    (&["el", "lisp", "scm", "clj", "ini"], Some(";"), None),
+    (&["css"], None, Some(("/*", "*/"))),
+    (&["html", "xml", "md"], None, Some(("<!--", "-->"))),
+];
+
+/// CommentSyntax is how a file type writes comments.
+/// - `line`: What starts a comment running to the end of the line, if the language has one.
+/// - `block`: What opens and closes a comment that can span lines, if it has those.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommentSyntax {
+    pub line: Option<String>,
+    pub block: Option<(String, String)>,
+}
+
+impl CommentSyntax {
+    /// for_path() returns the comment syntax of the file at `path`, judged by its extension.
+    pub fn for_path(path: &Path) -> Option<CommentSyntax> {
+        let extension = path.extension()?.to_str()?;
+        let (_, line, block) = COMMENT_SYNTAXES.iter().find(|(extensions, _, _)| extensions.contains(&extension))?;
+
+        Some(CommentSyntax {
+            line: line.map(|line| line.to_string()),
+            block: block.map(|(open, close)| (open.to_string(), close.to_string())),
+        })
+    }
+
+    /// is_commented() reports whether the lines in `text` are commented out: every line that is
+    /// not blank starts with a line comment, or the whole text is one block comment.
+    pub fn is_commented(&self, text: &str) -> bool {
+        if let Some(prefix) = self.line.as_ref() {
+            let mut lines = text.lines().map(str::trim_start).filter(|line| !line.is_empty()).peekable();
+            if lines.peek().is_some() && lines.all(|line| line.starts_with(prefix.as_str())) {
+                return true;
+            }
+        }
+
+        self.block.as_ref().is_some_and(|block| is_block_comment(text, block))
+    }
+
+    /// comment() comments out the lines in `text`. With line comments each line that is not blank
+    /// starts one, lined up at the indentation of the least indented line; otherwise the text is
+    /// wrapped in one block comment.
+    pub fn comment(&self, text: &str) -> String {
+        if let Some(prefix) = self.line.as_ref() {
+            let indentation = text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.len() - line.trim_start().len())
+                .min()
+                .unwrap_or(0);
+            return map_lines(text, |line| match line.trim().is_empty() {
+                true => line.to_string(),
+                false => format!("{}{} {}", &line[..indentation], prefix, &line[indentation..]),
+            });
+        }
+
+        let (open, close) = match self.block.as_ref() {
+            Some(block) => block,
+            None => return text.to_string(),
+        };
+        let body = text.trim_end_matches('\n');
+        let start = body.len() - body.trim_start().len();
+        let end = body.trim_end().len();
+        format!("{}{} {} {}{}", &body[..start], open, &body[start..end], close, &text[end..])
+    }
+
+    /// uncomment() takes the comments that comment() adds back out of `text`, along with the space
+    /// after each opening delimiter and before each closing one.
+    pub fn uncomment(&self, text: &str) -> String {
+        if let Some(prefix) = self.line.as_ref() {
+            let commented = text.lines().map(str::trim_start).filter(|line| !line.is_empty());
+            if commented.clone().next().is_some() && commented.clone().all(|line| line.starts_with(prefix.as_str())) {
+                return map_lines(text, |line| {
+                    let start = line.len() - line.trim_start().len();
+                    match line[start..].strip_prefix(prefix.as_str()) {
+                        Some(rest) => format!("{}{}", &line[..start], rest.strip_prefix(' ').unwrap_or(rest)),
+                        None => line.to_string(),
+                    }
+                });
+            }
+        }
+
+        let (open, close) = match self.block.as_ref() {
+            Some(block) if is_block_comment(text, block) => block,
+            _ => return text.to_string(),
+        };
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len();
+        let inside = &text[start + open.len()..end - close.len()];
+        let inside = inside.strip_prefix(' ').unwrap_or(inside);
+        let inside = inside.strip_suffix(' ').unwrap_or(inside);
+        format!("{}{}{}", &text[..start], inside, &text[end..])
+    }
+}
+
+/// is_block_comment() reports whether `text`, without the whitespace around it, is one comment
+/// opened and closed by `block`.
+fn is_block_comment(text: &str, (open, close): &(String, String)) -> bool {
+    let trimmed = text.trim();
+    trimmed.len() >= open.len() + close.len() && trimmed.starts_with(open.as_str()) && trimmed.ends_with(close.as_str())
+}
+
+/// CommentAction is what `CommentLines` does to lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentAction {
+    Comment,
+    Uncomment,
+    /// Uncomment lines that are commented out, and comment out the others.
+    Toggle,
+}
+
+/// CommentLines comments lines out or back in with a file type's comment syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentLines {
+    pub syntax: CommentSyntax,
+    pub action: CommentAction,
+}
+
+impl SaveTransform for CommentLines {
+    fn name(&self) -> &str {
+        "comment-lines"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let comment = match self.action {
+            CommentAction::Comment => true,
+            CommentAction::Uncomment => false,
+            CommentAction::Toggle => !self.syntax.is_commented(text),
+        };
+
+        match comment {
+            true => self.syntax.comment(text),
+            false => self.syntax.uncomment(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use buffer::SaveTransform;
+    use comment::{CommentAction, CommentLines, CommentSyntax};
+
+    #[test]
+    fn comment_and_uncomment_lines() {
+        let rust = CommentSyntax::for_path(Path::new("main.rs")).unwrap();
+        let css = CommentSyntax::for_path(Path::new("site.css")).unwrap();
+        let test_cases = [
+            ("line comments line up", &rust, CommentAction::Comment, "    a\n\n  b\n", "  //   a\n\n  // b\n"),
+            ("uncomment line comments", &rust, CommentAction::Uncomment, "  //   a\n\n  // b\n", "    a\n\n  b\n"),
+            ("toggle commented lines", &rust, CommentAction::Toggle, "// a\n//b\n", "a\nb\n"),
+            ("toggle mixed lines", &rust, CommentAction::Toggle, "// a\nb\n", "// // a\n// b\n"),
+            ("uncomment a block comment", &rust, CommentAction::Uncomment, "/* a\nb */\n", "a\nb\n"),
+            ("block comments wrap the lines", &css, CommentAction::Comment, "  a {\n  }\n", "  /* a {\n  } */\n"),
+            ("toggle a block comment", &css, CommentAction::Toggle, "  /* a {\n  } */\n", "  a {\n  }\n"),
+            ("leave uncommented text", &css, CommentAction::Uncomment, "a\n", "a\n"),
+        ];
+
+        for (name, syntax, action, text, expected) in test_cases.iter() {
+            let transform = CommentLines {
+                syntax: (*syntax).clone(),
+                action: *action,
+            };
+            assert_eq!(transform.apply(text), *expected, "Test case: \"{}\" failed.", name);
+        }
+        assert!(CommentSyntax::for_path(Path::new("notes")).is_none());
+    }
+}
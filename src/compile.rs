@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use grep::parse_location;
+
+/// parse_error_location() reads where a line of compiler output says an error is, returning the
+/// path and the zero-based line and column. gcc, clang and most other tools start the line with
+/// it, as `path:line:column: message`, and rustc points at it on the line after the message, as
+/// `  --> path:line:column`. Other indented lines, and paths with spaces in them, are taken to be
+/// part of a message rather than a location.
+pub fn parse_error_location(line: &str) -> Option<(PathBuf, usize, usize)> {
+    let trimmed = line.trim_start();
+    let location = match trimmed.strip_prefix("--> ") {
+        Some(location) => location,
+        None if trimmed.len() == line.len() => line,
+        None => return None,
+    };
+
+    let (path, line, column) = parse_location(location.trim_end())?;
+    match path.to_string_lossy().contains(char::is_whitespace) {
+        true => None,
+        false => Some((path, line, column)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use compile::parse_error_location;
+
+    #[test]
+    fn find_the_locations_in_compiler_output() {
+        let test_cases = [
+            ("gcc", "main.c:12:5: error: 'x' undeclared", Some((PathBuf::from("main.c"), 11, 4))),
+            ("without a column", "Makefile:3: *** missing separator.  Stop.", Some((PathBuf::from("Makefile"), 2, 0))),
+            ("rustc", "  --> src/main.rs:2:9", Some((PathBuf::from("src/main.rs"), 1, 8))),
+            ("rustc message", "error[E0425]: cannot find value `y` in this scope", None),
+            ("gcc context", "main.c: In function 'main':", None),
+            ("indented", "   = note: expected type src/a.rs:1:1", None),
+            ("spaces in the path", "thread 'main' panicked at src/main.rs:2:5:", None),
+            ("job summary", "Job 1 (make) exited with code 2", None),
+        ];
+        for (name, line, expected) in test_cases.iter() {
+            assert_eq!(parse_error_location(line), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+use dabbrev::expansions;
+use editor::Editor;
+use GapBuffer;
+
+/// The most candidates a completion popup shows at once.
+pub const MAX_VISIBLE_CANDIDATES: usize = 8;
+
+/// CompletionItem is a text a completion can insert.
+/// - `label`: How it is shown in the popup.
+/// - `text`: What replaces the text being completed when it is chosen.
+/// - `detail`: More about it, such as its type, shown beside the label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionItem {
+    pub label: String,
+    pub text: String,
+    pub detail: Option<String>,
+}
+
+impl CompletionItem {
+    /// new() returns an item inserting `text`, shown as it is.
+    pub fn new(text: &str) -> CompletionItem {
+        CompletionItem {
+            label: text.to_string(),
+            text: text.to_string(),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: &str) -> CompletionItem {
+        self.detail = Some(detail.to_string());
+        self
+    }
+}
+
+/// CompletionSource finds the candidates for completing the text before the point.
+pub trait CompletionSource {
+    /// name() says where the candidates come from, such as `words` or `files`.
+    fn name(&self) -> &str;
+
+    /// prefix_start() returns where the text this source completes starts, for the point at
+    /// `point`. Sources complete the word before the point unless they say otherwise.
+    fn prefix_start(&self, buffer: &GapBuffer, point: usize) -> usize {
+        word_start(buffer, point)
+    }
+
+    /// candidates() returns the items that could complete `prefix`, the text from
+    /// `prefix_start()` to the point of the current buffer of `editor`, best first.
+    fn candidates(&self, editor: &Editor, prefix: &str) -> Vec<CompletionItem>;
+}
+
+/// word_start() returns the start of the word of letters, digits and underscores that ends at
+/// `point`, or `point` if there is none.
+pub fn word_start(buffer: &GapBuffer, point: usize) -> usize {
+    let line_start = point - buffer.offset_to_line_col(point).1;
+    let before = buffer.slice(line_start..point);
+    let word: usize = before.chars().rev().take_while(|character| is_word_char(*character)).map(char::len_utf8).sum();
+
+    point - word
+}
+
+pub fn is_word_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+/// BufferWords completes with the words of the open buffers, those of the current buffer
+/// nearest the point first, as `dabbrev-expand` finds them. It needs no language server.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BufferWords;
+
+impl CompletionSource for BufferWords {
+    fn name(&self) -> &str {
+        "words"
+    }
+
+    fn candidates(&self, editor: &Editor, prefix: &str) -> Vec<CompletionItem> {
+        expansions(editor.buffers(), prefix).iter().map(|word| CompletionItem::new(word)).collect()
+    }
+}
+
+/// FilePaths completes file names once the text before the point has a `/` in it, relative to
+/// the directory of the current buffer's file, or the working directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FilePaths;
+
+impl CompletionSource for FilePaths {
+    fn name(&self) -> &str {
+        "files"
+    }
+
+    /// prefix_start() is the start of the path before the point, which runs back to whitespace,
+    /// a quote or a bracket.
+    fn prefix_start(&self, buffer: &GapBuffer, point: usize) -> usize {
+        let line_start = point - buffer.offset_to_line_col(point).1;
+        let before = buffer.slice(line_start..point);
+        let is_path_char = |character: char| !character.is_whitespace() && !"\"'`()[]{}<>,;=".contains(character);
+
+        point - before.chars().rev().take_while(|character| is_path_char(*character)).map(char::len_utf8).sum::<usize>()
+    }
+
+    fn candidates(&self, editor: &Editor, prefix: &str) -> Vec<CompletionItem> {
+        let (directory, name) = match prefix.rfind('/') {
+            Some(slash) => (&prefix[..slash + 1], &prefix[slash + 1..]),
+            None => return Vec::new(),
+        };
+
+        let base = editor.current_buffer().and_then(|buffer| buffer.path()).and_then(Path::parent).unwrap_or_else(|| Path::new("."));
+        let expanded = match directory.strip_prefix("~/") {
+            Some(rest) => std::env::var_os("HOME").map(|home| Path::new(&home).join(rest)).unwrap_or_default(),
+            None => base.join(directory),
+        };
+        let entries = match fs::read_dir(&expanded) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut items: Vec<CompletionItem> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let file_name = entry.file_name().to_string_lossy().into_owned();
+                if !file_name.starts_with(name) || (file_name.starts_with('.') && !name.starts_with('.')) {
+                    return None;
+                }
+                let slash = if entry.file_type().is_ok_and(|kind| kind.is_dir()) { "/" } else { "" };
+                Some(CompletionItem {
+                    label: format!("{}{}", file_name, slash),
+                    text: format!("{}{}{}", directory, file_name, slash),
+                    detail: None,
+                })
+            })
+            .collect();
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+
+        items
+    }
+}
+
+/// Candidate is an item found for a completion, with where the text it replaces starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub start: usize,
+    pub item: CompletionItem,
+    pub source: String,
+}
+
+/// Completion is a completion in progress.
+/// - `candidates`: Every item found, in the order they were found.
+/// - `matches`: The indexes of the candidates matching the text typed since, best first.
+/// - `selected`: The index in `matches` of the one that is chosen if the completion is accepted.
+/// - `pending`: A request for more candidates still to be answered, as the language and id of a
+///   language server request.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Completion {
+    candidates: Vec<Candidate>,
+    matches: Vec<usize>,
+    selected: usize,
+    pending: Option<(String, u64)>,
+}
+
+impl Completion {
+    pub fn new() -> Completion {
+        Completion::default()
+    }
+
+    /// add() adds candidates, leaving out those inserting a text another already inserts from the
+    /// same place. `refilter()` has to be called to match them.
+    pub fn add(&mut self, candidates: Vec<Candidate>) {
+        for candidate in candidates {
+            let duplicate = |found: &Candidate| found.start == candidate.start && found.item.text == candidate.item.text;
+            if !self.candidates.iter().any(duplicate) {
+                self.candidates.push(candidate);
+            }
+        }
+    }
+
+    pub fn pending(&self) -> Option<&(String, u64)> {
+        self.pending.as_ref()
+    }
+
+    pub fn set_pending(&mut self, pending: Option<(String, u64)>) {
+        self.pending = pending;
+    }
+
+    /// refilter() matches the candidates against the text between where each starts and `point`
+    /// in `buffer`. Those starting past the point or on another line are dropped. Candidates
+    /// starting with the text come first, then those starting with it in another case, then
+    /// those with its characters in order.
+    pub fn refilter(&mut self, buffer: &GapBuffer, point: usize) {
+        let line_start = point - buffer.offset_to_line_col(point).1;
+        let selected = self.selected().map(|candidate| candidate.item.text.clone());
+
+        let mut ranked: Vec<(usize, usize)> = Vec::new();
+        for (index, candidate) in self.candidates.iter().enumerate() {
+            if candidate.start > point || candidate.start < line_start {
+                continue;
+            }
+            let typed = buffer.slice(candidate.start..point);
+            if let Some(rank) = match_rank(&candidate.item.text, &typed) {
+                ranked.push((rank, index));
+            }
+        }
+        ranked.sort_by_key(|(rank, _)| *rank);
+
+        self.matches = ranked.into_iter().map(|(_, index)| index).collect();
+        self.selected = selected.and_then(|text| self.matches().position(|candidate| candidate.item.text == text)).unwrap_or(0);
+    }
+
+    /// matches() returns the candidates matching what is typed, best first.
+    pub fn matches(&self) -> impl Iterator<Item = &Candidate> {
+        self.matches.iter().map(move |index| &self.candidates[*index])
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn selected(&self) -> Option<&Candidate> {
+        self.matches.get(self.selected).map(|index| &self.candidates[*index])
+    }
+
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// select_next() chooses the match after the chosen one, going round to the first.
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    /// select_previous() chooses the match before the chosen one, going round to the last.
+    pub fn select_previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    /// visible() returns the indexes of the matches a popup `rows` high shows, keeping the chosen
+    /// one in view.
+    pub fn visible(&self, rows: usize) -> Range<usize> {
+        let rows = rows.min(self.matches.len());
+        let first = (self.selected + 1).saturating_sub(rows);
+
+        first..first + rows
+    }
+}
+
+/// match_rank() ranks how well `text` matches `typed`, lower being better, or returns `None` if
+/// it does not match or would insert nothing.
+fn match_rank(text: &str, typed: &str) -> Option<usize> {
+    if text == typed {
+        return None;
+    }
+    if text.starts_with(typed) {
+        return Some(0);
+    }
+
+    let text = text.to_lowercase();
+    let typed = typed.to_lowercase();
+    if text.starts_with(&typed) {
+        return Some(1);
+    }
+    let mut remaining = text.chars();
+    match typed.chars().all(|character| remaining.any(|found| found == character)) {
+        true => Some(2),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use completion::{word_start, BufferWords, Candidate, Completion, CompletionItem, CompletionSource, FilePaths};
+    use editor::Editor;
+    use GapBuffer;
+
+    fn candidate(start: usize, text: &str) -> Candidate {
+        Candidate {
+            start,
+            item: CompletionItem::new(text),
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn filter_and_choose_candidates() {
+        let buffer = GapBuffer::from("let va".to_string());
+        let mut completion = Completion::new();
+        completion.add(vec![candidate(4, "Value"), candidate(4, "value"), candidate(4, "vector_a"), candidate(4, "value"), candidate(4, "va")]);
+        completion.refilter(&buffer, 6);
+
+        let texts: Vec<&str> = completion.matches().map(|candidate| candidate.item.text.as_str()).collect();
+        assert_eq!(texts, vec!["value", "Value", "vector_a"]);
+
+        completion.select_previous();
+        assert_eq!(completion.selected().unwrap().item.text, "vector_a");
+        completion.select_next();
+        assert_eq!(completion.selected_index(), 0);
+        completion.select_next();
+        assert_eq!(completion.visible(1), 1..2);
+        assert_eq!(completion.visible(8), 0..3);
+
+        completion.refilter(&buffer, 5);
+        assert_eq!(completion.selected().unwrap().item.text, "Value");
+        completion.refilter(&buffer, 3);
+        assert_eq!(completion.match_count(), 0);
+    }
+
+    #[test]
+    fn complete_words_and_file_paths() {
+        let mut editor = Editor::new();
+        editor.execute("insert-char", "value vector valid\nva").unwrap();
+        let buffer = editor.current_buffer().unwrap();
+        assert_eq!(word_start(buffer, buffer.len()), buffer.len() - 2);
+
+        let words: Vec<String> = BufferWords.candidates(&editor, "va").into_iter().map(|item| item.text).collect();
+        assert_eq!(words, vec!["valid", "value"]);
+
+        let directory = std::env::temp_dir().join(format!("one-completion-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("src")).unwrap();
+        std::fs::write(directory.join("setup.py"), "").unwrap();
+        std::fs::write(directory.join(".secret"), "").unwrap();
+        let prefix = format!("{}/s", directory.display());
+        let files: Vec<String> = FilePaths.candidates(&editor, &prefix).into_iter().map(|item| item.label).collect();
+        assert_eq!(files, vec!["setup.py", "src/"]);
+        assert!(FilePaths.candidates(&editor, "s").is_empty());
+
+        let buffer = GapBuffer::from("open(\"./src/ma".to_string());
+        assert_eq!(FilePaths.prefix_start(&buffer, buffer.len()), 6);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
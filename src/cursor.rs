@@ -0,0 +1,238 @@
+use std::ops::Range;
+
+use anchor::{Affinity, AnchorId};
+use search::SearchOptions;
+use GapBuffer;
+
+/// Cursor is a secondary caret or selection. Its ends are anchors, so it follows the text
+/// around it as the buffer changes.
+/// - `tail`: The fixed end of the selection.
+/// - `head`: The end that moves, where text is typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    tail: AnchorId,
+    head: AnchorId,
+}
+
+/// Multiple cursors. The primary cursor is the point, selecting the region when the mark is set,
+/// and any number of secondary cursors can be added alongside it.
+impl GapBuffer {
+    /// add_cursor() adds a secondary caret at `offset` and returns false if there is already a
+    /// cursor there. It will panic if `offset` is greater than the buffer length.
+    pub fn add_cursor(&mut self, offset: usize) -> bool {
+        self.add_selection(offset..offset)
+    }
+
+    /// add_selection() adds a secondary cursor selecting `range`, with its head at the end.
+    /// Returns false if `range` overlaps an existing cursor.
+    pub fn add_selection(&mut self, range: Range<usize>) -> bool {
+        if range.start > range.end || range.end > self.len() {
+            panic!("Range out of bounds. The length is {} but the range is {:?}.", self.len(), range)
+        }
+        if self.cursor_ranges().iter().any(|cursor| overlaps(cursor, &range)) {
+            return false;
+        }
+
+        let tail = self.create_anchor(range.start, Affinity::Right);
+        let head = self.create_anchor(range.end, Affinity::Right);
+        self.cursors.push(Cursor { tail, head });
+
+        true
+    }
+
+    /// add_cursor_at_next_match() selects the next occurrence of the text in the most recently
+    /// added selection, wrapping around at the end of the buffer. Returns false if there is no
+    /// selection or no further occurrence.
+    pub fn add_cursor_at_next_match(&mut self) -> bool {
+        let last = match self.cursors.last() {
+            Some(cursor) => self.selection_range(cursor),
+            None => match self.region() {
+                Some(region) => region,
+                None => return false,
+            },
+        };
+        if last.start == last.end {
+            return false;
+        }
+
+        let pattern = self.slice(last.clone()).into_owned();
+        let options = SearchOptions {
+            wrap_around: true,
+            ..SearchOptions::default()
+        };
+        let found = self
+            .search(&pattern, last.end, options)
+            .find(|found| !self.cursor_ranges().iter().any(|cursor| overlaps(cursor, found)));
+
+        match found {
+            Some(found) => self.add_selection(found),
+            None => false,
+        }
+    }
+
+    /// cursor_ranges() returns the selection of every cursor, including the primary, in buffer order.
+    pub fn cursor_ranges(&self) -> Vec<Range<usize>> {
+        let primary = self.region().unwrap_or(self.point..self.point);
+        let mut ranges: Vec<Range<usize>> = self.cursors.iter().map(|cursor| self.selection_range(cursor)).collect();
+        ranges.push(primary);
+        ranges.sort_by_key(|range| (range.start, range.end));
+
+        ranges
+    }
+
+    pub fn cursor_count(&self) -> usize {
+        self.cursors.len() + 1
+    }
+
+    /// clear_cursors() removes every secondary cursor, leaving only the point.
+    pub fn clear_cursors(&mut self) {
+        for cursor in std::mem::take(&mut self.cursors) {
+            self.remove_anchor(cursor.tail);
+            self.remove_anchor(cursor.head);
+        }
+    }
+
+    /// insert_at_cursors() replaces the selection of every cursor with `text`, leaving each cursor
+    /// as a caret after its insertion.
+    pub fn insert_at_cursors(&mut self, text: &str) {
+        for range in self.cursor_ranges().into_iter().rev() {
+            self.replace_range(range, text);
+        }
+        self.collapse_cursors();
+    }
+
+    /// remove_at_cursors() deletes the selection of every cursor, or the character before each caret.
+    pub fn remove_at_cursors(&mut self) {
+        for range in self.backspace_ranges().into_iter().rev() {
+            self.remove_bytes(range);
+        }
+        self.collapse_cursors();
+    }
+
+    /// backspace_ranges() returns the text that `remove_at_cursors()` deletes, in buffer order.
+    pub(crate) fn backspace_ranges(&self) -> Vec<Range<usize>> {
+        self.cursor_ranges()
+            .into_iter()
+            .filter_map(|range| match range.start == range.end {
+                true => self.prev_char_boundary(range.start).map(|start| start..range.start),
+                false => Some(range),
+            })
+            .collect()
+    }
+
+    /// collapse_cursors() turns every selection into a caret at its head, drops the mark and merges
+    /// cursors that have ended up in the same place.
+    pub(crate) fn collapse_cursors(&mut self) {
+        self.clear_mark();
+
+        let mut seen = vec![self.point];
+        for cursor in std::mem::take(&mut self.cursors) {
+            let head = self.anchor_offset(cursor.head).unwrap();
+            if seen.contains(&head) {
+                self.remove_anchor(cursor.head);
+            } else {
+                seen.push(head);
+                self.cursors.push(Cursor { tail: cursor.head, head: cursor.head });
+            }
+            if cursor.tail != cursor.head {
+                self.remove_anchor(cursor.tail);
+            }
+        }
+    }
+
+    fn selection_range(&self, cursor: &Cursor) -> Range<usize> {
+        let tail = self.anchor_offset(cursor.tail).unwrap();
+        let head = self.anchor_offset(cursor.head).unwrap();
+
+        tail.min(head)..tail.max(head)
+    }
+}
+
+/// overlaps() is true if the ranges share any text, or are the same caret.
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a == b || (a.start < b.end && b.start < a.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use GapBuffer;
+
+    #[test]
+    fn add_cursors() {
+        let mut buffer = GapBuffer::from("one two three".to_string());
+        buffer.set_point(0);
+
+        assert!(buffer.add_cursor(4));
+        assert!(buffer.add_cursor(8));
+        assert!(!buffer.add_cursor(8));
+        assert!(!buffer.add_cursor(0));
+
+        assert_eq!(buffer.cursor_count(), 3);
+        assert_eq!(buffer.cursor_ranges(), vec![0..0, 4..4, 8..8]);
+
+        buffer.clear_cursors();
+        assert_eq!(buffer.cursor_ranges(), vec![0..0]);
+    }
+
+    #[test]
+    fn insert_at_every_cursor() {
+        let mut buffer = GapBuffer::from("a\nb\nc".to_string());
+        buffer.set_point(0);
+        buffer.add_cursor(2);
+        buffer.add_cursor(4);
+
+        buffer.insert_at_cursors("- ");
+
+        assert_eq!(buffer.to_string(), "- a\n- b\n- c");
+        assert_eq!(buffer.get_point(), 2);
+        assert_eq!(buffer.cursor_ranges(), vec![2..2, 6..6, 10..10]);
+    }
+
+    #[test]
+    fn remove_at_every_cursor() {
+        let mut buffer = GapBuffer::from("ab\ncd\nef".to_string());
+        buffer.set_point(2);
+        buffer.add_cursor(5);
+        buffer.add_selection(6..8);
+
+        buffer.remove_at_cursors();
+
+        assert_eq!(buffer.to_string(), "a\nc\n");
+        assert_eq!(buffer.cursor_ranges(), vec![1..1, 3..3, 4..4]);
+    }
+
+    #[test]
+    fn cursors_that_meet_are_merged() {
+        let mut buffer = GapBuffer::from("abc".to_string());
+        buffer.set_point(1);
+        buffer.add_selection(1..3);
+
+        buffer.insert_at_cursors("");
+        buffer.remove_at_cursors();
+
+        assert_eq!(buffer.to_string(), "");
+        assert_eq!(buffer.cursor_count(), 1);
+    }
+
+    #[test]
+    fn add_a_cursor_on_the_next_match() {
+        let mut buffer = GapBuffer::from("let x = x + x;".to_string());
+        buffer.set_mark(4);
+        buffer.set_point(5);
+
+        assert!(buffer.add_cursor_at_next_match());
+        assert!(buffer.add_cursor_at_next_match());
+        assert!(!buffer.add_cursor_at_next_match());
+        assert_eq!(buffer.cursor_ranges(), vec![4..5, 8..9, 12..13]);
+
+        buffer.insert_at_cursors("value");
+        assert_eq!(buffer.to_string(), "let value = value + value;");
+    }
+
+    #[test]
+    fn no_next_match_without_a_selection() {
+        let mut buffer = GapBuffer::from("x x".to_string());
+
+        assert!(!buffer.add_cursor_at_next_match());
+    }
+}
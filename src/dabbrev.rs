@@ -0,0 +1,176 @@
+use std::ops::Range;
+
+use buffer_manager::{BufferId, BufferManager};
+use completion::{is_word_char, word_start};
+use GapBuffer;
+
+/// words_near() returns the words of `buffer` longer than `prefix` that start with it, nearest to
+/// `point` first, each once.
+pub fn words_near(buffer: &GapBuffer, point: usize, prefix: &str) -> Vec<String> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let text = buffer.to_string();
+
+    let mut words: Vec<(usize, &str)> = Vec::new();
+    let mut start = None;
+    for (index, character) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        match (start, is_word_char(character)) {
+            (None, true) => start = Some(index),
+            (Some(word_start), false) => {
+                let word = &text[word_start..index];
+                if word.len() > prefix.len() && word.starts_with(prefix) {
+                    words.push((word_start.abs_diff(point), word));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words.sort_by_key(|(distance, _)| *distance);
+
+    let mut found: Vec<String> = Vec::new();
+    for (_, word) in words {
+        if !found.iter().any(|known| known == word) {
+            found.push(word.to_string());
+        }
+    }
+    found
+}
+
+/// expansions() returns the words of the open buffers that could complete `prefix`: those of the
+/// current buffer nearest its point first, then those of the other buffers, the most recently
+/// current first. Each word is returned once.
+pub fn expansions(buffers: &BufferManager, prefix: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+
+    for (_, buffer) in buffers.recent() {
+        for word in words_near(buffer, buffer.get_point(), prefix) {
+            if !found.contains(&word) {
+                found.push(word);
+            }
+        }
+    }
+    found
+}
+
+/// Expansion is a word expanded by `Dabbrev`.
+/// - `buffer`: The buffer it was expanded in.
+/// - `inserted`: The text that replaced it.
+/// - `prefix`: The word as it was.
+/// - `words`: The words it could expand into, and which one was inserted.
+#[derive(Debug)]
+struct Expansion {
+    buffer: BufferId,
+    inserted: Range<usize>,
+    prefix: String,
+    words: Vec<String>,
+    index: usize,
+}
+
+/// Dabbrev expands the word before the point into other words of the open buffers, as
+/// `dabbrev-expand` does. Expanding again right after replaces the expansion with the next one.
+#[derive(Debug, Default)]
+pub struct Dabbrev {
+    last: Option<Expansion>,
+}
+
+impl Dabbrev {
+    pub fn new() -> Dabbrev {
+        Dabbrev::default()
+    }
+
+    /// expand() replaces the word before the point of the current buffer with its nearest
+    /// expansion, or the last expansion with the next one if the point is still where that left
+    /// it. Once every expansion has been tried the word is put back as it was. Returns false when
+    /// there was no expansion to insert.
+    pub fn expand(&mut self, buffers: &mut BufferManager) -> bool {
+        let id = match buffers.current_id() {
+            Some(id) => id,
+            None => return false,
+        };
+        let point = buffers.current().unwrap().get_point();
+
+        let (start, prefix, words, index) = match self.last.take() {
+            Some(last) if last.buffer == id && last.inserted.end == point => (last.inserted.start, last.prefix, last.words, last.index + 1),
+            _ => {
+                let start = word_start(buffers.current().unwrap(), point);
+                let prefix = buffers.current().unwrap().slice(start..point).into_owned();
+                let words = expansions(buffers, &prefix);
+                (start, prefix, words, 0)
+            }
+        };
+
+        let buffer = buffers.current_mut().unwrap();
+        let text = match words.get(index) {
+            Some(word) => word.clone(),
+            None => {
+                if index > 0 {
+                    buffer.replace_range(start..point, &prefix);
+                    buffer.set_point(start + prefix.len());
+                }
+                return false;
+            }
+        };
+
+        buffer.replace_range(start..point, &text);
+        buffer.set_point(start + text.len());
+        self.last = Some(Expansion {
+            buffer: id,
+            inserted: start..start + text.len(),
+            prefix,
+            words,
+            index,
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::Buffer;
+    use buffer_manager::BufferManager;
+    use dabbrev::{expansions, words_near, Dabbrev};
+    use GapBuffer;
+
+    #[test]
+    fn find_words_nearest_first() {
+        let buffer = GapBuffer::from("value vector valid\nva vacuum".to_string());
+        let test_cases = [
+            ("around the point", 21, "va", vec!["vacuum", "valid", "value"]),
+            ("from the start", 0, "v", vec!["value", "vector", "valid", "va", "vacuum"]),
+            ("no matches", 21, "x", vec![]),
+            ("no prefix", 21, "", vec![]),
+        ];
+
+        for (name, point, prefix, expected) in test_cases.iter() {
+            assert_eq!(words_near(&buffer, *point, prefix), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn expand_from_every_open_buffer() {
+        let mut buffers = BufferManager::new();
+        let mut other = Buffer::new("other");
+        other.insert_str("foreach format");
+        let other = buffers.add(other);
+        let mut current = Buffer::new("current");
+        current.insert_str("forward fo");
+        let current = buffers.add(current);
+        buffers.switch_to(other);
+        buffers.switch_to(current);
+        assert_eq!(expansions(&buffers, "fo"), vec!["forward", "format", "foreach"]);
+
+        let mut dabbrev = Dabbrev::new();
+        let mut expanded = Vec::new();
+        while dabbrev.expand(&mut buffers) {
+            expanded.push(buffers.current().unwrap().to_string());
+        }
+        assert_eq!(expanded, vec!["forward forward", "forward format", "forward foreach"]);
+        assert_eq!(buffers.current().unwrap().to_string(), "forward fo");
+        assert!(dabbrev.expand(&mut buffers));
+
+        buffers.current_mut().unwrap().set_point(0);
+        assert!(!dabbrev.expand(&mut buffers));
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::mpsc::{self, Receiver};
+
+use events::{EditEvent, ListenerId};
+use GapBuffer;
+
+/// PositionEncoding picks the unit `Position::character` is counted in. The Language Server
+/// Protocol defaults to UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    Utf8,
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    fn length(&self, text: &str) -> usize {
+        match *self {
+            PositionEncoding::Utf8 => text.len(),
+            PositionEncoding::Utf16 => text.encode_utf16().count(),
+            PositionEncoding::Utf32 => text.chars().count(),
+        }
+    }
+}
+
+/// Position is a zero-based line and character offset, as in the Language Server Protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// TextChange is a change in the shape of an LSP `TextDocumentContentChangeEvent`: the text
+/// between `start` and `end`, in the coordinates from before the change, was replaced by `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChange {
+    pub start: Position,
+    pub end: Position,
+    pub text: String,
+}
+
+pub(crate) type DeltaListener = Box<dyn FnMut(&TextChange) + Send>;
+
+/// Conversion between byte offsets and LSP positions, and the incremental change feed.
+impl GapBuffer {
+    /// offset_to_position() converts a byte index into a position counted in `encoding`. In a
+    /// narrowed buffer the position is in the whole text.
+    /// It will panic if `offset` is greater than the buffer length.
+    pub fn offset_to_position(&self, offset: usize, encoding: PositionEncoding) -> Position {
+        let (line, column) = self.offset_to_line_col(offset);
+        let line_start = offset - column;
+        let (lines_before, hidden) = self.hidden_line_start();
+        let hidden_length = if line == 0 { encoding.length(&hidden) } else { 0 };
+
+        Position {
+            line: lines_before + line,
+            character: hidden_length + encoding.length(&self.slice(line_start..offset)),
+        }
+    }
+
+    /// position_to_offset() converts a position counted in `encoding` into a byte index.
+    /// A character past the end of the line is clamped to the end of the line, as LSP requires.
+    /// Returns `None` if the line does not exist. In a narrowed buffer the position is in the
+    /// whole text, and one before the accessible text is clamped to its start.
+    pub fn position_to_offset(&self, position: Position, encoding: PositionEncoding) -> Option<usize> {
+        let (lines_before, hidden) = self.hidden_line_start();
+        let position = match position.line.checked_sub(lines_before) {
+            Some(0) if position.character < encoding.length(&hidden) => return Some(0),
+            Some(0) => Position { line: 0, character: position.character - encoding.length(&hidden) },
+            Some(line) => Position { line, character: position.character },
+            None => return Some(0),
+        };
+        let start = self.line_to_offset(position.line)?;
+        let end = self.line_end_offset(position.line)?;
+        let mut offset = start;
+        let mut counted = 0;
+
+        for character in self.chars_at(start) {
+            if offset >= end || counted >= position.character {
+                break;
+            }
+
+            let mut encoded = [0; 4];
+            counted += encoding.length(character.encode_utf8(&mut encoded));
+            offset += character.len_utf8();
+        }
+
+        Some(offset)
+    }
+
+    /// text_change() describes `event` as a `TextChange`. It must be called while the buffer is
+    /// in the state immediately after `event`, as it is inside a change listener.
+    pub fn text_change(&self, event: &EditEvent, encoding: PositionEncoding) -> TextChange {
+        let start = self.offset_to_position(event.removed.start, encoding);
+        let end = match event.removed_text.rfind('\n') {
+            Some(last_newline) => Position {
+                line: start.line + event.removed_text.matches('\n').count(),
+                character: encoding.length(&event.removed_text[last_newline + 1..]),
+            },
+            None => Position {
+                line: start.line,
+                character: start.character + encoding.length(&event.removed_text),
+            },
+        };
+
+        TextChange {
+            start,
+            end,
+            text: event.inserted.clone(),
+        }
+    }
+
+    /// on_text_change() registers `callback` to be called with a `TextChange` after every change
+    /// to the buffer, with positions counted in `encoding`.
+    pub fn on_text_change<F: FnMut(&TextChange) + Send + 'static>(&mut self, encoding: PositionEncoding, callback: F) -> ListenerId {
+        self.listeners.add_delta(encoding, Box::new(callback))
+    }
+
+    /// subscribe_text_changes() returns a channel that receives a `TextChange` for every change
+    /// to the buffer.
+    pub fn subscribe_text_changes(&mut self, encoding: PositionEncoding) -> (ListenerId, Receiver<TextChange>) {
+        let (sender, receiver) = mpsc::channel();
+        let id = self.on_text_change(encoding, move |change| {
+            let _ = sender.send(change.clone());
+        });
+
+        (id, receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use delta::{Position, PositionEncoding, TextChange};
+    use GapBuffer;
+
+    const TEST_STRING: &str = "fn main() {\n    let 😀 = \"é\";\n}\n";
+
+    fn position(line: usize, character: usize) -> Position {
+        Position { line, character }
+    }
+
+    fn change(start: Position, end: Position, text: &str) -> TextChange {
+        TextChange {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn convert_offsets_to_positions() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+        let after_emoji = TEST_STRING.find(" = ").unwrap();
+
+        assert_eq!(buffer.offset_to_position(after_emoji, PositionEncoding::Utf8), position(1, 12));
+        assert_eq!(buffer.offset_to_position(after_emoji, PositionEncoding::Utf16), position(1, 10));
+        assert_eq!(buffer.offset_to_position(after_emoji, PositionEncoding::Utf32), position(1, 9));
+        assert_eq!(buffer.offset_to_position(TEST_STRING.len(), PositionEncoding::Utf16), position(3, 0));
+    }
+
+    #[test]
+    fn convert_positions_to_offsets() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+        let after_emoji = TEST_STRING.find(" = ").unwrap();
+
+        assert_eq!(buffer.position_to_offset(position(1, 10), PositionEncoding::Utf16), Some(after_emoji));
+        assert_eq!(buffer.position_to_offset(position(1, 9), PositionEncoding::Utf32), Some(after_emoji));
+        assert_eq!(buffer.position_to_offset(position(0, 100), PositionEncoding::Utf16), Some(11));
+        assert_eq!(buffer.position_to_offset(position(4, 0), PositionEncoding::Utf16), None);
+    }
+
+    #[test]
+    fn report_changes_in_lsp_format() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let (_, receiver) = buffer.subscribe_text_changes(PositionEncoding::Utf16);
+        let emoji = TEST_STRING.find('😀').unwrap();
+
+        buffer.replace_range(emoji..emoji + '😀'.len_utf8(), "x");
+        buffer.set_point(0);
+        buffer.insert_str("// main\n");
+        let body_start = buffer.line_to_offset(2).unwrap();
+        let body_end = buffer.line_to_offset(3).unwrap() + 1;
+        buffer.remove_bytes(body_start..body_end);
+
+        let changes: Vec<TextChange> = receiver.try_iter().collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                change(position(1, 8), position(1, 10), "x"),
+                change(position(0, 0), position(0, 0), "// main\n"),
+                change(position(2, 0), position(3, 1), ""),
+            ]
+        );
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::ops::Range;
+
+use property::{PropertyId, TextProperty};
+use theme;
+use GapBuffer;
+
+/// The group of the text properties marking diagnostics.
+pub const DIAGNOSTICS_GROUP: &str = "diagnostics";
+
+/// Severity is how serious a diagnostic is, most serious first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl Severity {
+    /// from_lsp() reads the severity numbers of the Language Server Protocol. Diagnostics without
+    /// one are taken to be errors.
+    pub fn from_lsp(severity: Option<u64>) -> Severity {
+        match severity {
+            Some(2) => Severity::Warning,
+            Some(3) => Severity::Information,
+            Some(4) => Severity::Hint,
+            _ => Severity::Error,
+        }
+    }
+
+    /// style() returns the theme style diagnostics of this severity are drawn in.
+    pub fn style(&self) -> &'static str {
+        match self {
+            Severity::Error => theme::DIAGNOSTIC_ERROR,
+            Severity::Warning => theme::DIAGNOSTIC_WARNING,
+            Severity::Information => theme::DIAGNOSTIC_INFORMATION,
+            Severity::Hint => theme::DIAGNOSTIC_HINT,
+        }
+    }
+
+    /// priority() is the priority of a diagnostic's text property, so more serious ones are
+    /// drawn over the others.
+    fn priority(&self) -> i32 {
+        match self {
+            Severity::Error => 40,
+            Severity::Warning => 30,
+            Severity::Information => 20,
+            Severity::Hint => 10,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Information => write!(f, "info"),
+            Severity::Hint => write!(f, "hint"),
+        }
+    }
+}
+
+/// Diagnostic is a problem found in a buffer's text, by a language server or a linter.
+/// - `range`: The text it is about.
+/// - `source`: What found it, such as `rustc`, if that is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub range: Range<usize>,
+    pub severity: Severity,
+    pub message: String,
+    pub source: Option<String>,
+}
+
+/// Diagnostics holds the diagnostics of a buffer. Each is a text property, so it stays on the
+/// text it is about as the buffer changes, kept with who reported it and what it says.
+#[derive(Debug, Default)]
+pub(crate) struct Diagnostics {
+    entries: Vec<(PropertyId, String, Severity, Option<String>)>,
+}
+
+/// Diagnostics are kept by who reported them, such as a language server, so each reporter can
+/// replace its own.
+impl GapBuffer {
+    /// set_diagnostics() replaces the diagnostics `reporter` made before with `diagnostics`.
+    /// Ranges past the end of the buffer are cut short at it.
+    pub fn set_diagnostics(&mut self, reporter: &str, diagnostics: Vec<Diagnostic>) {
+        self.clear_diagnostics(reporter);
+
+        for diagnostic in diagnostics {
+            let end = diagnostic.range.end.min(self.len());
+            let start = diagnostic.range.start.min(end);
+            let property = TextProperty::styled(DIAGNOSTICS_GROUP, diagnostic.severity.style())
+                .with_priority(diagnostic.severity.priority())
+                .with_annotation(&diagnostic.message);
+            let id = self.add_property(start..end, property);
+            self.diagnostics.entries.push((id, reporter.to_string(), diagnostic.severity, diagnostic.source));
+        }
+    }
+
+    /// clear_diagnostics() removes the diagnostics `reporter` made and returns how many there were.
+    pub fn clear_diagnostics(&mut self, reporter: &str) -> usize {
+        let (cleared, kept): (Vec<_>, Vec<_>) = self.diagnostics.entries.drain(..).partition(|(_, by, _, _)| by == reporter);
+        self.diagnostics.entries = kept;
+
+        for (id, _, _, _) in cleared.iter() {
+            self.remove_property(*id);
+        }
+        cleared.len()
+    }
+
+    /// diagnostics() returns every diagnostic in the order of its range.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .diagnostics
+            .entries
+            .iter()
+            .filter_map(|(id, _, severity, source)| {
+                Some(Diagnostic {
+                    range: self.property_range(*id)?,
+                    severity: *severity,
+                    message: self.property(*id)?.annotation.clone().unwrap_or_default(),
+                    source: source.clone(),
+                })
+            })
+            .collect();
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.range.start, diagnostic.range.end, diagnostic.severity));
+
+        diagnostics
+    }
+
+    /// diagnostic_count() returns how many diagnostics of `severity` the buffer has.
+    pub fn diagnostic_count(&self, severity: Severity) -> usize {
+        self.diagnostics.entries.iter().filter(|(_, _, found, _)| *found == severity).count()
+    }
+
+    /// next_diagnostic() returns the first diagnostic starting after `offset`.
+    pub fn next_diagnostic(&self, offset: usize) -> Option<Diagnostic> {
+        self.diagnostics().into_iter().find(|diagnostic| diagnostic.range.start > offset)
+    }
+
+    /// previous_diagnostic() returns the last diagnostic starting before `offset`.
+    pub fn previous_diagnostic(&self, offset: usize) -> Option<Diagnostic> {
+        self.diagnostics().into_iter().rev().find(|diagnostic| diagnostic.range.start < offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diagnostic::{Diagnostic, Severity};
+    use theme;
+    use GapBuffer;
+
+    const TEST_STRING: &str = "let x = 1;\nlet y = z;\n";
+
+    fn diagnostic(start: usize, end: usize, severity: Severity, message: &str) -> Diagnostic {
+        Diagnostic {
+            range: start..end,
+            severity,
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn diagnostics_follow_edits_and_are_replaced_by_reporter() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_diagnostics("lsp", vec![diagnostic(19, 20, Severity::Error, "unknown z"), diagnostic(4, 5, Severity::Warning, "unused x")]);
+        buffer.set_diagnostics("lint", vec![diagnostic(0, 3, Severity::Hint, "style")]);
+
+        buffer.set_point(0);
+        buffer.insert_str("\n");
+        let ranges: Vec<_> = buffer.diagnostics().into_iter().map(|diagnostic| (diagnostic.range, diagnostic.message)).collect();
+        assert_eq!(ranges, vec![(1..4, "style".to_string()), (5..6, "unused x".to_string()), (20..21, "unknown z".to_string())]);
+        assert_eq!(buffer.diagnostic_count(Severity::Error), 1);
+
+        buffer.set_diagnostics("lsp", vec![diagnostic(16, 100, Severity::Error, "past the end")]);
+        assert_eq!(buffer.diagnostics()[1].range, 16..23);
+        assert_eq!(buffer.diagnostic_count(Severity::Warning), 0);
+        assert_eq!(buffer.line_properties(2)[0].1.style.as_deref(), Some(theme::DIAGNOSTIC_ERROR));
+        assert_eq!(buffer.clear_diagnostics("lint"), 1);
+        assert_eq!(buffer.diagnostics().len(), 1);
+    }
+
+    #[test]
+    fn find_the_next_and_previous_diagnostics() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_diagnostics("lsp", vec![diagnostic(4, 5, Severity::Warning, "a"), diagnostic(19, 20, Severity::Error, "b")]);
+        let test_cases = [
+            ("next from the start", true, 0, Some(4)),
+            ("next from a diagnostic", true, 4, Some(19)),
+            ("next after the last", true, 19, None),
+            ("previous from the end", false, 21, Some(19)),
+            ("previous from a diagnostic", false, 19, Some(4)),
+            ("previous before the first", false, 4, None),
+        ];
+
+        for (name, next, offset, expected) in test_cases.iter() {
+            let found = match next {
+                true => buffer.next_diagnostic(*offset),
+                false => buffer.previous_diagnostic(*offset),
+            };
+            assert_eq!(found.map(|diagnostic| diagnostic.range.start), *expected, "Test case: \"{}\" failed.", name);
+        }
+        assert_eq!(Severity::from_lsp(Some(2)), Severity::Warning);
+        assert_eq!(Severity::from_lsp(None), Severity::Error);
+    }
+}
@@ -0,0 +1,235 @@
+use std::iter;
+use std::ops::Range;
+
+/// How many differences are searched for the fewest changes. Past that the rest of two texts is
+/// given as one hunk, which keeps very different texts from taking long to compare.
+const MAX_COST: usize = 1000;
+
+/// How many unchanged lines a unified diff usually shows on each side of a change.
+pub const CONTEXT_LINES: usize = 3;
+
+/// HunkKind is what a hunk does to the old text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HunkKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+/// Hunk is a run of items that differ between two sequences, such as the lines of two versions of
+/// a file: the `old` items of the first are replaced by the `new` items of the second. An empty
+/// range is the place the other side's items were added or removed at.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+impl Hunk {
+    pub fn kind(&self) -> HunkKind {
+        match (self.old.is_empty(), self.new.is_empty()) {
+            (true, _) => HunkKind::Added,
+            (false, true) => HunkKind::Removed,
+            (false, false) => HunkKind::Modified,
+        }
+    }
+}
+
+/// diff() returns the hunks that turn `old` into `new`, in order, changing as few items as it
+/// can.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new.iter()).take_while(|(old, new)| old == new).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut hunks = Vec::new();
+    let (mut old_start, mut new_start) = (0, 0);
+    let end = (old_middle.len(), new_middle.len());
+    for (old_index, new_index) in matches(old_middle, new_middle).into_iter().chain(iter::once(end)) {
+        if old_index > old_start || new_index > new_start {
+            hunks.push(Hunk {
+                old: prefix + old_start..prefix + old_index,
+                new: prefix + new_start..prefix + new_index,
+            });
+        }
+        old_start = old_index + 1;
+        new_start = new_index + 1;
+    }
+
+    hunks
+}
+
+/// unified_diff() writes the changes from `old` to `new` as a unified diff naming them `old_name`
+/// and `new_name`, with `context` unchanged lines on each side of each change. Changes with no
+/// more than twice that many lines between them share a hunk. It is empty if the texts are the
+/// same.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let hunks = diff(&old_lines, &new_lines);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut groups: Vec<&[Hunk]> = Vec::new();
+    let mut first = 0;
+    for index in 1..=hunks.len() {
+        if index == hunks.len() || hunks[index].old.start - hunks[index - 1].old.end > 2 * context {
+            groups.push(&hunks[first..index]);
+            first = index;
+        }
+    }
+
+    let mut text = format!("--- {}\n+++ {}\n", old_name, new_name);
+    let mut previous_end = 0;
+    for (index, group) in groups.iter().enumerate() {
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        let next_start = groups.get(index + 1).map_or(old_lines.len(), |next| next[0].old.start);
+        let before = context.min(first.old.start - previous_end);
+        let after = context.min(next_start - last.old.end);
+        let old_start = first.old.start - before;
+        let new_start = first.new.start - before;
+        let old_len = last.old.end + after - old_start;
+        let new_len = last.new.end + after - new_start;
+        // A side with no lines is said to start at the line before it.
+        let number = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+        text.push_str(&format!("@@ -{},{} +{},{} @@\n", number(old_start, old_len), old_len, number(new_start, new_len), new_len));
+
+        let mut line = old_start;
+        for hunk in group.iter() {
+            push_lines(&mut text, ' ', &old_lines[line..hunk.old.start]);
+            push_lines(&mut text, '-', &old_lines[hunk.old.clone()]);
+            push_lines(&mut text, '+', &new_lines[hunk.new.clone()]);
+            line = hunk.old.end;
+        }
+        push_lines(&mut text, ' ', &old_lines[line..line + after]);
+        previous_end = last.old.end;
+    }
+
+    text
+}
+
+/// push_lines() adds `lines` to a unified diff, each after `prefix`, noting a last line without
+/// a newline.
+fn push_lines(text: &mut String, prefix: char, lines: &[&str]) {
+    for line in lines {
+        text.push(prefix);
+        text.push_str(line);
+        if !line.ends_with('\n') {
+            text.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// matches() returns the pairs of indexes of the equal items a shortest edit from `old` to `new`
+/// keeps, in order, found with Myers' algorithm. If that takes more than `MAX_COST` changes,
+/// nothing is kept.
+fn matches<T: PartialEq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    let (old_len, new_len) = (old.len() as isize, new.len() as isize);
+    let max_cost = (old.len() + new.len()).min(MAX_COST) as isize;
+    let offset = max_cost + 1;
+    // The furthest index into `old` reached on each diagonal, by how far it is from the main one.
+    let mut furthest = vec![0isize; 2 * max_cost as usize + 3];
+    let mut trace = Vec::new();
+
+    let mut found = false;
+    'search: for cost in 0..=max_cost {
+        trace.push(furthest.clone());
+        for diagonal in (-cost..=cost).step_by(2) {
+            let index = (diagonal + offset) as usize;
+            let mut x = match diagonal == -cost || (diagonal != cost && furthest[index - 1] < furthest[index + 1]) {
+                true => furthest[index + 1],
+                false => furthest[index - 1] + 1,
+            };
+            let mut y = x - diagonal;
+            while x < old_len && y < new_len && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index] = x;
+            if x >= old_len && y >= new_len {
+                found = true;
+                break 'search;
+            }
+        }
+    }
+    if !found {
+        return Vec::new();
+    }
+
+    let mut kept = Vec::new();
+    let (mut x, mut y) = (old_len, new_len);
+    for (cost, furthest) in trace.iter().enumerate().rev() {
+        let cost = cost as isize;
+        let diagonal = x - y;
+        let previous = match diagonal == -cost || (diagonal != cost && furthest[(diagonal - 1 + offset) as usize] < furthest[(diagonal + 1 + offset) as usize]) {
+            true => diagonal + 1,
+            false => diagonal - 1,
+        };
+        let previous_x = furthest[(previous + offset) as usize];
+        let previous_y = previous_x - previous;
+        while x > previous_x && y > previous_y {
+            x -= 1;
+            y -= 1;
+            kept.push((x as usize, y as usize));
+        }
+        x = previous_x;
+        y = previous_y;
+    }
+    kept.reverse();
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use diff::{diff, unified_diff, Hunk, HunkKind};
+
+    #[test]
+    fn find_the_fewest_changed_lines() {
+        let test_cases = [
+            ("the same", "a b c", "a b c", vec![]),
+            ("a line added", "a c", "a b c", vec![(1..1, 1..2)]),
+            ("lines removed", "a b c d", "a d", vec![(1..3, 1..1)]),
+            ("a line changed", "a b c", "a x c", vec![(1..2, 1..2)]),
+            ("everything new", "", "a b", vec![(0..0, 0..2)]),
+            ("changes apart", "a b c d e", "x b c e f", vec![(0..1, 0..1), (3..4, 3..3), (5..5, 4..5)]),
+            ("a moved line", "a b c d", "b c d a", vec![(0..1, 0..0), (4..4, 3..4)]),
+        ];
+
+        for (name, old, new, expected) in test_cases.iter() {
+            let old: Vec<&str> = old.split_whitespace().collect();
+            let new: Vec<&str> = new.split_whitespace().collect();
+            let expected: Vec<Hunk> = expected.iter().map(|(old, new)| Hunk { old: old.clone(), new: new.clone() }).collect();
+            assert_eq!(diff(&old, &new), expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn say_what_a_hunk_does() {
+        let kind = |old: std::ops::Range<usize>, new: std::ops::Range<usize>| Hunk { old, new }.kind();
+
+        assert_eq!(kind(2..2, 2..4), HunkKind::Added);
+        assert_eq!(kind(2..4, 2..2), HunkKind::Removed);
+        assert_eq!(kind(2..3, 2..4), HunkKind::Modified);
+    }
+    #[test]
+    fn write_changes_as_a_unified_diff() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\n2\n3\nfour\n5\n6\n7\n8\n9\n10\n11\n12\n13";
+
+        let expected = "--- old\n+++ new\n\
+                        @@ -1,7 +1,7 @@\n 1\n 2\n 3\n-4\n+four\n 5\n 6\n 7\n\
+                        @@ -10,3 +10,4 @@\n 10\n 11\n 12\n+13\n\\ No newline at end of file\n";
+        assert_eq!(unified_diff(old, new, "old", "new", 3), expected);
+        assert_eq!(unified_diff("a\nb\n", "a\nx\nb\n", "old", "new", 3), "--- old\n+++ new\n@@ -1,2 +1,3 @@\n a\n+x\n b\n");
+        assert_eq!(unified_diff("", "a\n", "old", "new", 3), "--- old\n+++ new\n@@ -0,0 +1,1 @@\n+a\n");
+        assert_eq!(unified_diff("a\n", "a\n", "old", "new", 3), "");
+    }
+}
@@ -0,0 +1,119 @@
+use std::fs::{self, Metadata};
+use std::io;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// DirEntry is a file or directory in a directory listing.
+/// - `name`: Its name in the directory, or `..` for the directory above.
+/// - `size`: Its length in bytes.
+/// - `permissions`: Its kind and permissions as `ls -l` writes them, e.g. `drwxr-xr-x`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_directory: bool,
+    pub size: u64,
+    pub permissions: String,
+}
+
+impl DirEntry {
+    fn from_metadata(name: String, metadata: &Metadata) -> DirEntry {
+        DirEntry {
+            name,
+            is_directory: metadata.is_dir(),
+            size: metadata.len(),
+            permissions: permissions(metadata),
+        }
+    }
+
+    /// to_line() writes the entry as a line of a listing, with a slash after directories.
+    pub fn to_line(&self) -> String {
+        let slash = if self.is_directory && self.name != ".." { "/" } else { "" };
+        format!("  {} {:>10} {}{}", self.permissions, self.size, self.name, slash)
+    }
+}
+
+/// permissions() writes the kind and permissions of a file as `ls -l` does. Where there are no
+/// Unix permissions, only whether the file is read-only is shown.
+fn permissions(metadata: &Metadata) -> String {
+    let kind = if metadata.is_dir() { 'd' } else { '-' };
+
+    #[cfg(unix)]
+    {
+        let mode = metadata.permissions().mode();
+        let bits: String = ["r", "w", "x"].iter().cycle().take(9).enumerate().map(|(index, letter)| if mode & (0o400 >> index) != 0 { *letter } else { "-" }).collect();
+        format!("{}{}", kind, bits)
+    }
+    #[cfg(not(unix))]
+    {
+        let write = if metadata.permissions().readonly() { '-' } else { 'w' };
+        format!("{}r{}-------", kind, write)
+    }
+}
+
+/// read_directory() lists the entries of `directory` in order of name, after `..` for the
+/// directory above if there is one. Entries that cannot be read are left out.
+pub fn read_directory(directory: &Path) -> io::Result<Vec<DirEntry>> {
+    let mut entries: Vec<DirEntry> = fs::read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(DirEntry::from_metadata(entry.file_name().to_string_lossy().into_owned(), &metadata))
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if let Some(metadata) = directory.parent().and_then(|parent| fs::metadata(parent).ok()) {
+        entries.insert(0, DirEntry::from_metadata("..".to_string(), &metadata));
+    }
+    Ok(entries)
+}
+
+/// format_listing() writes the listing of `directory` as a dired buffer shows it: a line
+/// naming the directory, then a line for each of `entries`.
+pub fn format_listing(directory: &Path, entries: &[DirEntry]) -> String {
+    let mut text = format!("  {}:\n", directory.display());
+    for entry in entries {
+        text.push_str(&entry.to_line());
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use dired::{format_listing, read_directory, DirEntry};
+
+    #[test]
+    fn list_a_directory() {
+        let directory = env::temp_dir().join(format!("one-dired-{}", std::process::id()));
+        fs::create_dir_all(directory.join("src")).unwrap();
+        fs::write(directory.join("notes.txt"), "twelve bytes").unwrap();
+        fs::write(directory.join("Cargo.toml"), "").unwrap();
+
+        let entries = read_directory(&directory).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        let names: Vec<(&str, bool)> = entries.iter().map(|entry| (entry.name.as_str(), entry.is_directory)).collect();
+        assert_eq!(names, vec![("..", true), ("Cargo.toml", false), ("notes.txt", false), ("src", true)]);
+        assert_eq!(entries[2].size, 12);
+        assert!(read_directory(&directory).is_err());
+    }
+
+    #[test]
+    fn format_a_listing() {
+        let entries = vec![
+            DirEntry { name: "..".to_string(), is_directory: true, size: 4096, permissions: "drwxr-xr-x".to_string() },
+            DirEntry { name: "src".to_string(), is_directory: true, size: 4096, permissions: "drwxr-xr-x".to_string() },
+            DirEntry { name: "main.rs".to_string(), is_directory: false, size: 120, permissions: "-rw-r--r--".to_string() },
+        ];
+
+        assert_eq!(
+            format_listing(env::temp_dir().join("project").as_path(), &entries),
+            format!("  {}:\n  drwxr-xr-x       4096 ..\n  drwxr-xr-x       4096 src/\n  -rw-r--r--        120 main.rs\n", env::temp_dir().join("project").display())
+        );
+    }
+}
@@ -0,0 +1,1157 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use anchor::Affinity;
+use bookmark::Bookmarks;
+use buffer::{BackupSettings, Buffer};
+use buffer_manager::{BufferId, BufferManager};
+use command::{Command, CommandError, CommandResult};
+use completion::{BufferWords, Completion, CompletionSource, FilePaths};
+use dabbrev::Dabbrev;
+use indent::{CopyIndent, Indenter};
+use jump_list::JumpList;
+use keymap::{format_keys, Key, KeyCode, Keymap, Lookup};
+use kill_ring::KillRing;
+use lsp::LspClient;
+use macros::Macros;
+use minibuffer::{Minibuffer, MinibufferHistory};
+use project;
+use registers::Registers;
+use status_line::StatusLine;
+use property::TextProperty;
+use syntax::{in_string_or_comment, Highlighter, SyntaxRules};
+use theme::{self, Theme};
+use vi::{Mode, ViState};
+use view::View;
+use window::{Area, Direction, Window, WindowId, Windows};
+
+use self::compile::Compilation;
+use self::dired::Dired;
+use self::git::{Blame, GitChanges};
+use self::grep::GrepSearch;
+use self::isearch::Isearch;
+use self::jobs::BackgroundJob;
+use self::language_server::LspDocument;
+use self::mouse::Drag;
+use self::occur::Occur;
+use self::query_replace::QueryReplace;
+use self::term::Terminal;
+
+pub use buffer_manager::{MESSAGES_BUFFER, SCRATCH_BUFFER};
+pub use self::locations::Location;
+pub use self::mouse::{MouseAction, MouseEvent};
+
+pub mod bookmarks;
+pub mod commands;
+pub mod compile;
+pub mod completion_popup;
+pub mod diff;
+pub mod dired;
+pub mod git;
+pub mod grep;
+pub mod isearch;
+pub mod jobs;
+pub mod language_server;
+pub mod locations;
+pub mod mouse;
+pub mod occur;
+pub mod query_replace;
+pub mod term;
+
+/// The size of the view until a frontend says otherwise.
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_HEIGHT: usize = 24;
+
+/// The group of the text properties marking the bracket at the point and its match.
+const MATCHING_BRACKET_GROUP: &str = "matching-bracket";
+
+/// Editor holds everything being edited and the commands that edit it.
+/// - `buffers`: The open buffers.
+/// - `commands`: Every registered command, by name.
+/// - `global_keymap`: The key bindings used in every buffer.
+/// - `buffer_keymaps`: Key bindings for particular buffers, looked up before the global ones.
+/// - `pending_keys`: The keys of a sequence typed so far, while it is still a prefix.
+/// - `vi_states`: The vi layer of each buffer using modal editing.
+/// - `kill_ring`: Text killed from any buffer.
+/// - `kill_buffer`: The buffer the last command killed in, if it was a kill, so the next kill
+///   only adds to the same entry after it.
+/// - `registers`: Named places to keep text.
+/// - `message`: The last message for the user, such as why a command failed.
+/// - `macros`: Recorded keyboard macros.
+/// - `minibuffer`: The prompt keys go to while a command is asking for its argument.
+/// - `minibuffer_history`: What was entered at earlier prompts.
+/// - `last_search`: The text last searched for, which a search without any repeats.
+/// - `backup_settings`: How buffers opened from files back them up when saving.
+/// - `auto_revert`: Whether unmodified buffers are reverted when their file changes on disk.
+/// - `quit`: Set when a command has asked the editor to exit.
+/// - `windows`: The windows on screen. The selected window shows the current buffer.
+/// - `status_line`: How the line describing the current buffer is laid out.
+/// - `mouse_drag`: What the mouse is doing while its button is held.
+/// - `theme`: The styles frontends draw text in.
+/// - `highlighters`: What works out the syntax styles of each buffer that has them.
+/// - `syntax_rules`: The loaded rules for highlighting languages, tried before the built-in ones.
+/// - `indenters`: What works out the indentation of new lines in each buffer that has its own.
+/// - `language_servers`: The running language servers, by the language they serve.
+/// - `lsp_documents`: The buffers open in a language server.
+/// - `completion`: The completion in progress, whose candidates show in a popup.
+/// - `completion_sources`: Where completion looks for candidates, besides language servers.
+/// - `auto_complete`: Whether typing opens completion without asking.
+/// - `dabbrev`: The last word expansion, so expanding again can try the next one.
+/// - `locations`: The places found last, such as the references to a name, to step through.
+/// - `location`: The index in `locations` of the place visited last.
+/// - `pending_locations`: The language server request for places still to be answered, as the
+///   language and id of the request.
+/// - `jump_list`: Where the point was before each jump far away.
+/// - `bookmarks`: The places bookmarked by name.
+/// - `bookmark_file`: Where bookmarks are kept between sessions, if anywhere.
+/// - `isearch`: The incremental search in progress, which keys go to before the keymaps.
+/// - `query_replace`: The replacement asking about each match, which keys go to before the keymaps.
+/// - `query_replace_pattern`: What a query-replace is asking for the replacement of.
+/// - `occur`: What the occur buffer lists, so its lines can be gone to.
+/// - `grep`: The search the grep buffer lists, so its matches can be gone to.
+/// - `compilation`: The build the compilation buffer lists, so its errors can be gone to.
+/// - `dired`: What each dired buffer lists, by buffer.
+/// - `jobs`: The processes started in the background, running or finished, the oldest first.
+/// - `next_job_id`: The id the next job started is given.
+/// - `terminals`: The programs running in terminal buffers, by buffer.
+/// - `git_changes`: What the lines changed since the last commit are marked from, by buffer.
+/// - `blame`: The file the blame buffer lists who changed the lines of, so its lines can be gone to.
+pub struct Editor {
+    buffers: BufferManager,
+    commands: BTreeMap<String, Command>,
+    global_keymap: Keymap,
+    buffer_keymaps: HashMap<BufferId, Keymap>,
+    pending_keys: Vec<Key>,
+    vi_states: HashMap<BufferId, ViState>,
+    kill_ring: KillRing,
+    kill_buffer: Option<BufferId>,
+    registers: Registers,
+    message: Option<String>,
+    macros: Macros,
+    minibuffer: Option<Minibuffer>,
+    minibuffer_history: MinibufferHistory,
+    last_search: Option<String>,
+    backup_settings: BackupSettings,
+    auto_revert: bool,
+    quit: bool,
+    windows: Windows,
+    status_line: StatusLine,
+    mouse_drag: Option<Drag>,
+    theme: Theme,
+    highlighters: HashMap<BufferId, Box<dyn Highlighter>>,
+    syntax_rules: Vec<SyntaxRules>,
+    indenters: HashMap<BufferId, Box<dyn Indenter>>,
+    language_servers: HashMap<String, LspClient>,
+    lsp_documents: HashMap<BufferId, LspDocument>,
+    completion: Option<Completion>,
+    completion_sources: Vec<Box<dyn CompletionSource>>,
+    auto_complete: bool,
+    dabbrev: Dabbrev,
+    locations: Vec<Location>,
+    location: usize,
+    pending_locations: Option<(String, u64)>,
+    jump_list: JumpList,
+    bookmarks: Bookmarks,
+    bookmark_file: Option<PathBuf>,
+    isearch: Option<Isearch>,
+    query_replace: Option<QueryReplace>,
+    query_replace_pattern: Option<String>,
+    occur: Option<Occur>,
+    grep: Option<GrepSearch>,
+    compilation: Option<Compilation>,
+    dired: HashMap<BufferId, Dired>,
+    jobs: Vec<BackgroundJob>,
+    next_job_id: usize,
+    terminals: HashMap<BufferId, Terminal>,
+    git_changes: HashMap<BufferId, GitChanges>,
+    blame: Option<Blame>,
+}
+
+impl Default for Editor {
+    fn default() -> Editor {
+        Editor::new()
+    }
+}
+
+impl Editor {
+    /// new() creates an editor with an empty scratch buffer, the built-in commands and the Emacs
+    /// keymap.
+    pub fn new() -> Editor {
+        let mut buffers = BufferManager::new();
+        let scratch = buffers.scratch();
+
+        let mut editor = Editor {
+            buffers,
+            commands: BTreeMap::new(),
+            global_keymap: Keymap::emacs(),
+            buffer_keymaps: HashMap::new(),
+            pending_keys: Vec::new(),
+            vi_states: HashMap::new(),
+            kill_ring: KillRing::new(),
+            kill_buffer: None,
+            registers: Registers::new(),
+            message: None,
+            macros: Macros::new(),
+            minibuffer: None,
+            minibuffer_history: MinibufferHistory::new(),
+            last_search: None,
+            backup_settings: BackupSettings::default(),
+            auto_revert: false,
+            quit: false,
+            windows: Windows::new(scratch, View::new(DEFAULT_WIDTH, DEFAULT_HEIGHT)),
+            status_line: StatusLine::default(),
+            mouse_drag: None,
+            theme: Theme::default(),
+            highlighters: HashMap::new(),
+            syntax_rules: Vec::new(),
+            indenters: HashMap::new(),
+            language_servers: HashMap::new(),
+            lsp_documents: HashMap::new(),
+            completion: None,
+            completion_sources: vec![Box::new(BufferWords), Box::new(FilePaths)],
+            auto_complete: false,
+            dabbrev: Dabbrev::new(),
+            locations: Vec::new(),
+            location: 0,
+            pending_locations: None,
+            jump_list: JumpList::new(),
+            bookmarks: Bookmarks::new(),
+            bookmark_file: None,
+            isearch: None,
+            query_replace: None,
+            query_replace_pattern: None,
+            occur: None,
+            grep: None,
+            compilation: None,
+            dired: HashMap::new(),
+            jobs: Vec::new(),
+            next_job_id: 1,
+            terminals: HashMap::new(),
+            git_changes: HashMap::new(),
+            blame: None,
+        };
+        for command in commands::builtin_commands() {
+            editor.register(command);
+        }
+
+        editor
+    }
+
+    pub fn buffers(&self) -> &BufferManager {
+        &self.buffers
+    }
+
+    pub fn buffers_mut(&mut self) -> &mut BufferManager {
+        &mut self.buffers
+    }
+
+    pub fn current_buffer(&self) -> Option<&Buffer> {
+        self.buffers.current()
+    }
+
+    pub fn current_buffer_mut(&mut self) -> Option<&mut Buffer> {
+        self.buffers.current_mut()
+    }
+
+    pub fn kill_ring(&self) -> &KillRing {
+        &self.kill_ring
+    }
+
+    pub fn kill_ring_mut(&mut self) -> &mut KillRing {
+        &mut self.kill_ring
+    }
+
+    pub fn registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
+    pub fn backup_settings(&self) -> &BackupSettings {
+        &self.backup_settings
+    }
+
+    /// set_backup_settings() changes how files opened from now on are backed up.
+    pub fn set_backup_settings(&mut self, settings: BackupSettings) {
+        self.backup_settings = settings;
+    }
+
+    /// view() is the view of the selected window.
+    pub fn view(&self) -> &View {
+        self.windows.selected().view()
+    }
+
+    pub fn view_mut(&mut self) -> &mut View {
+        self.windows.selected_mut().view_mut()
+    }
+
+    pub fn windows(&self) -> &Windows {
+        &self.windows
+    }
+
+    pub fn windows_mut(&mut self) -> &mut Windows {
+        &mut self.windows
+    }
+
+    /// select_window() makes window `id` selected and its buffer current, and returns false if
+    /// there is no such window. The point of the window left is kept for when it is selected
+    /// again, and the point of the window selected is restored.
+    pub fn select_window(&mut self, id: WindowId) -> bool {
+        let buffer_id = match self.windows.get(id) {
+            Some(window) => window.buffer(),
+            None => return false,
+        };
+        let selected_id = self.windows.selected_id();
+        if selected_id == id {
+            return true;
+        }
+
+        let window = self.windows.selected_mut();
+        if let Some(buffer) = self.buffers.get_mut(window.buffer()) {
+            let point = buffer.get_point();
+            match window.point {
+                Some(anchor) => {
+                    buffer.move_anchor(anchor, point);
+                }
+                None => window.point = Some(buffer.create_anchor(point, Affinity::Left)),
+            }
+        }
+
+        self.windows.select(id);
+        self.buffers.switch_to(buffer_id);
+        let anchor = self.windows.selected().point;
+        if let (Some(buffer), Some(anchor)) = (self.buffers.get_mut(buffer_id), anchor) {
+            if let Some(offset) = buffer.anchor_offset(anchor) {
+                buffer.set_point(offset);
+            }
+        }
+        true
+    }
+
+    /// window_point() is where the point of window `id` is: the point of its buffer for the
+    /// selected window, and where it was left for the others.
+    pub fn window_point(&self, id: WindowId) -> Option<usize> {
+        let window = self.windows.get(id)?;
+        let buffer = self.buffers.get(window.buffer())?;
+
+        match window.point {
+            Some(anchor) if id != self.windows.selected_id() => buffer.anchor_offset(anchor),
+            _ => Some(buffer.get_point()),
+        }
+    }
+
+    /// show_buffer() makes buffer `id` current and shows it in the selected window.
+    pub(crate) fn show_buffer(&mut self, id: BufferId) {
+        let window = self.windows.selected();
+        if let (Some(buffer), Some(anchor)) = (self.buffers.get_mut(window.buffer()), window.point) {
+            buffer.remove_anchor(anchor);
+        }
+
+        self.buffers.switch_to(id);
+        let selected = self.windows.selected_id();
+        self.windows.set_buffer(selected, id);
+    }
+
+    /// show_text_buffer() shows `text` in the read-only buffer called `name`, making the buffer
+    /// if there is none, as for the lists commands make. Returns the buffer's id.
+    pub(crate) fn show_text_buffer(&mut self, name: &str, text: &str) -> BufferId {
+        let id = match self.buffers.find(name) {
+            Some(id) => id,
+            None => self.buffers.create(name),
+        };
+        let buffer = self.buffers.get_mut(id).unwrap();
+        let length = buffer.len();
+
+        buffer.set_read_only(false);
+        buffer.replace_range(0..length, text);
+        buffer.set_point(0);
+        buffer.set_read_only(true);
+        buffer.set_modified(false);
+        self.show_buffer(id);
+        id
+    }
+
+    /// project_root() is the directory of the project the current buffer's file is in, or the
+    /// working directory for buffers without files.
+    pub fn project_root(&self) -> PathBuf {
+        match self.current_buffer().and_then(|buffer| buffer.path()) {
+            Some(path) => project::root(path),
+            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        }
+    }
+
+    /// default_directory() is where commands the current buffer runs start: the directory of its
+    /// file, the directory a dired buffer lists, or else the working directory.
+    pub fn default_directory(&self) -> PathBuf {
+        let file_directory = self.current_buffer().and_then(|buffer| buffer.path()).and_then(|path| path.parent());
+        match file_directory.or_else(|| self.dired_directory()) {
+            Some(directory) if !directory.as_os_str().is_empty() => directory.to_path_buf(),
+            _ => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        }
+    }
+
+    /// append_to_text_buffer() adds `text` to the end of read-only buffer `id`, as output comes
+    /// in for a list still being made. A point at the end follows the text added.
+    pub(crate) fn append_to_text_buffer(&mut self, id: BufferId, text: &str) {
+        if let Some(buffer) = self.buffers.get_mut(id) {
+            let length = buffer.len();
+            buffer.set_read_only(false);
+            buffer.replace_range(length..length, text);
+            buffer.set_read_only(true);
+            buffer.set_modified(false);
+        }
+    }
+
+    /// split_window() divides the selected window in two in `direction` and returns the new
+    /// window, which starts with the same point, unless the window is too small to divide.
+    pub(crate) fn split_window(&mut self, direction: Direction) -> Option<WindowId> {
+        let selected = self.windows.selected_id();
+        let id = self.windows.split(selected, direction)?;
+
+        if let Some(buffer) = self.buffers.current_mut() {
+            let point = buffer.get_point();
+            self.windows.get_mut(id)?.point = Some(buffer.create_anchor(point, Affinity::Left));
+        }
+        Some(id)
+    }
+
+    /// delete_window() closes window `id` and returns false if it is the last window.
+    pub(crate) fn delete_window(&mut self, id: WindowId) -> bool {
+        let selected = self.windows.selected_id();
+        if id == selected && self.windows.len() > 1 {
+            let next = self.windows.next(id);
+            self.select_window(next);
+        }
+
+        match self.windows.delete(id) {
+            Some(window) => {
+                self.forget_window_point(&window);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// delete_other_windows() closes every window but the selected one.
+    pub(crate) fn delete_other_windows(&mut self) {
+        let selected = self.windows.selected_id();
+        for window in self.windows.delete_others(selected) {
+            self.forget_window_point(&window);
+        }
+    }
+
+    fn forget_window_point(&mut self, window: &Window) {
+        if let (Some(buffer), Some(anchor)) = (self.buffers.get_mut(window.buffer()), window.point) {
+            buffer.remove_anchor(anchor);
+        }
+    }
+
+    pub fn status_line(&self) -> &StatusLine {
+        &self.status_line
+    }
+
+    pub fn status_line_mut(&mut self) -> &mut StatusLine {
+        &mut self.status_line
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+
+    pub fn highlighter(&self, id: BufferId) -> Option<&dyn Highlighter> {
+        self.highlighters.get(&id).map(|highlighter| highlighter.as_ref())
+    }
+
+    /// set_highlighter() highlights buffer `id` with `highlighter` from now on. It should be
+    /// attached to the buffer so it sees its changes.
+    pub fn set_highlighter(&mut self, id: BufferId, highlighter: Box<dyn Highlighter>) {
+        self.highlighters.insert(id, highlighter);
+    }
+
+    pub fn remove_highlighter(&mut self, id: BufferId) -> Option<Box<dyn Highlighter>> {
+        self.highlighters.remove(&id)
+    }
+
+    pub fn syntax_rules(&self) -> &[SyntaxRules] {
+        &self.syntax_rules
+    }
+
+    /// add_syntax_rules() highlights files opened from now on with `rules` where they match,
+    /// ahead of the rules added before.
+    pub fn add_syntax_rules(&mut self, rules: SyntaxRules) {
+        self.syntax_rules.insert(0, rules);
+    }
+
+    /// indenter() is what indents new lines in buffer `id`. Buffers without their own copy the
+    /// indentation of the line before.
+    pub fn indenter(&self, id: BufferId) -> &dyn Indenter {
+        self.indenters.get(&id).map_or(&CopyIndent, |indenter| indenter.as_ref())
+    }
+
+    pub fn set_indenter(&mut self, id: BufferId, indenter: Box<dyn Indenter>) {
+        self.indenters.insert(id, indenter);
+    }
+
+    /// layout_windows() shares the `width` and `height` a frontend has for windows out between
+    /// them, scrolls each window to keep its point on screen, brings the highlighting of the
+    /// buffers shown up to date, and returns where each window goes. Frontends call it before
+    /// drawing, and sizes that changed since the last call, as when the terminal is resized, are
+    /// taken up then.
+    pub fn layout_windows(&mut self, width: usize, height: usize) -> Vec<(WindowId, Area)> {
+        let areas = self.windows.layout(Area { x: 0, y: 0, width, height });
+        for (id, _) in areas.iter() {
+            let buffer_id = self.windows.get(*id).unwrap().buffer();
+            if let (Some(highlighter), Some(buffer)) = (self.highlighters.get_mut(&buffer_id), self.buffers.get(buffer_id)) {
+                highlighter.update(buffer);
+            }
+            let point = match self.window_point(*id) {
+                Some(point) => point,
+                None => continue,
+            };
+            let window = self.windows.get_mut(*id).unwrap();
+            if let Some(buffer) = self.buffers.get(window.buffer()) {
+                window.view_mut().keep_offset_visible(buffer, point);
+            }
+        }
+        self.show_matching_bracket(&areas);
+        self.show_isearch_matches(&areas);
+
+        areas
+    }
+
+    /// matching_bracket() returns the offsets of the bracket or quote at or just before the point
+    /// of the current buffer and the one it matches, passing over those in strings and comments
+    /// where its highlighter knows them.
+    pub fn matching_bracket(&mut self) -> Option<(usize, usize)> {
+        let id = self.buffers.current_id()?;
+        let buffer = self.buffers.get(id)?;
+        if let Some(highlighter) = self.highlighters.get_mut(&id) {
+            highlighter.update(buffer);
+        }
+        let at = buffer.delimiter_near(buffer.get_point())?;
+        let matching = buffer.matching_delimiter(at, &mut in_string_or_comment(buffer, self.highlighter(id)))?;
+
+        Some((at, matching))
+    }
+
+    /// show_matching_bracket() marks the bracket at the selected window's point and its match,
+    /// and takes the marks off the other buffers shown in `areas`.
+    fn show_matching_bracket(&mut self, areas: &[(WindowId, Area)]) {
+        for (id, _) in areas.iter() {
+            let buffer_id = self.windows.get(*id).unwrap().buffer();
+            if let Some(buffer) = self.buffers.get_mut(buffer_id) {
+                buffer.clear_properties(MATCHING_BRACKET_GROUP);
+            }
+        }
+
+        let matching = self.matching_bracket();
+        if let (Some((at, matching)), Some(buffer)) = (matching, self.buffers.current_mut()) {
+            for offset in [at, matching].iter() {
+                buffer.add_property(*offset..*offset + 1, TextProperty::styled(MATCHING_BRACKET_GROUP, theme::MATCHING_BRACKET));
+            }
+        }
+    }
+
+    /// should_quit() reports whether a command has asked the editor to exit, for the frontend.
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    pub fn request_quit(&mut self) {
+        self.quit = true;
+    }
+
+    pub fn auto_revert(&self) -> bool {
+        self.auto_revert
+    }
+
+    pub fn set_auto_revert(&mut self, auto_revert: bool) {
+        self.auto_revert = auto_revert;
+    }
+
+    /// check_files() looks for buffers whose files have changed on disk, which a frontend should
+    /// do when it is idle or regains focus. With auto-revert on, unmodified buffers are reverted.
+    /// The others are left alone and named in the message, and their names are returned.
+    pub fn check_files(&mut self) -> Vec<String> {
+        let mut reverted = Vec::new();
+        let mut changed = Vec::new();
+
+        for id in self.buffers.iter().map(|(id, _)| id).collect::<Vec<_>>() {
+            let buffer = self.buffers.get_mut(id).unwrap();
+            if !buffer.changed_on_disk() {
+                continue;
+            }
+
+            if self.auto_revert && !buffer.is_modified() && buffer.revert().is_ok() {
+                reverted.push(buffer.name().to_string());
+            } else {
+                changed.push(buffer.name().to_string());
+            }
+        }
+
+        if !changed.is_empty() {
+            self.set_message(&format!("{} changed on disk; M-x revert-buffer to reload", changed.join(", ")));
+        } else if !reverted.is_empty() {
+            self.set_message(&format!("Reverted {}", reverted.join(", ")));
+        }
+        changed
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// set_message() shows `message` to the user until the next one, and logs it to the messages
+    /// buffer unless it is empty.
+    pub fn set_message(&mut self, message: &str) {
+        if !message.is_empty() {
+            self.buffers.log_message(message);
+        }
+        self.message = Some(message.to_string());
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    /// register() adds `command`, returning the command it replaces if the name was taken.
+    pub fn register(&mut self, command: Command) -> Option<Command> {
+        self.commands.insert(command.name().to_string(), command)
+    }
+
+    pub fn unregister(&mut self, name: &str) -> Option<Command> {
+        self.commands.remove(name)
+    }
+
+    pub fn command(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// command_names() lists the registered commands in alphabetical order.
+    pub fn command_names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(|name| name.as_str())
+    }
+
+    /// execute() runs the command called `name` with `argument`. What it changes in each buffer
+    /// is undone as one step. Unless the command before it was a kill in the current buffer, a
+    /// kill it makes starts a new kill ring entry.
+    pub fn execute(&mut self, name: &str, argument: &str) -> CommandResult {
+        let command = match self.commands.get(name) {
+            Some(command) => command.clone(),
+            None => return Err(CommandError::UnknownCommand(name.to_string())),
+        };
+
+        let kill_buffer = self.kill_buffer.take();
+        if kill_buffer.is_none() || kill_buffer != self.buffers.current_id() {
+            self.kill_ring.end_kill();
+        }
+        self.undo_boundary();
+        let result = command.run(self, argument);
+        self.undo_boundary();
+        result
+    }
+
+    /// undo_boundary() ends the undo step of every buffer, so what was changed since the last
+    /// boundary is undone together, and undoing what is changed next puts the point back where it
+    /// is now.
+    fn undo_boundary(&mut self) {
+        for (_, buffer) in self.buffers.iter_mut() {
+            buffer.undo_boundary();
+        }
+    }
+
+    /// call_interactively() runs the command called `name` as a key would: a command with a
+    /// prompt asks for its argument in the minibuffer first.
+    pub fn call_interactively(&mut self, name: &str) -> CommandResult {
+        let prompt = match self.commands.get(name) {
+            Some(command) => command.prompt().map(|prompt| prompt.to_string()),
+            None => return Err(CommandError::UnknownCommand(name.to_string())),
+        };
+
+        match prompt {
+            Some(prompt) => {
+                self.prompt(&prompt, name, name);
+                Ok(())
+            }
+            None => self.execute(name, ""),
+        }
+    }
+
+    /// prompt() opens the minibuffer, asking for the argument to `command` with `prompt`, and
+    /// keeping what is entered in the history called `history`.
+    pub fn prompt(&mut self, prompt: &str, command: &str, history: &str) {
+        self.minibuffer = Some(Minibuffer::new(prompt, command, history));
+    }
+
+    pub fn minibuffer(&self) -> Option<&Minibuffer> {
+        self.minibuffer.as_ref()
+    }
+
+    pub fn minibuffer_mut(&mut self) -> Option<&mut Minibuffer> {
+        self.minibuffer.as_mut()
+    }
+
+    pub fn minibuffer_history(&self) -> &MinibufferHistory {
+        &self.minibuffer_history
+    }
+
+    pub fn global_keymap(&self) -> &Keymap {
+        &self.global_keymap
+    }
+
+    pub fn global_keymap_mut(&mut self) -> &mut Keymap {
+        &mut self.global_keymap
+    }
+
+    pub fn buffer_keymap(&self, id: BufferId) -> Option<&Keymap> {
+        self.buffer_keymaps.get(&id)
+    }
+
+    /// buffer_keymap_mut() returns the keymap for buffer `id`, creating an empty one if needed.
+    pub fn buffer_keymap_mut(&mut self, id: BufferId) -> &mut Keymap {
+        self.buffer_keymaps.entry(id).or_default()
+    }
+
+    pub fn remove_buffer_keymap(&mut self, id: BufferId) -> Option<Keymap> {
+        self.buffer_keymaps.remove(&id)
+    }
+
+    /// pending_keys() are the keys typed so far of a sequence that is not yet complete.
+    pub fn pending_keys(&self) -> &[Key] {
+        &self.pending_keys
+    }
+
+    pub fn macros(&self) -> &Macros {
+        &self.macros
+    }
+
+    pub fn macros_mut(&mut self) -> &mut Macros {
+        &mut self.macros
+    }
+
+    /// play_macro() plays `keys` back `count` times, stopping at the first command that fails.
+    /// A macro cannot play another macro.
+    pub fn play_macro(&mut self, keys: &[Key], count: usize) -> CommandResult {
+        if self.macros.is_playing() {
+            return Err(CommandError::MacroRecursion);
+        }
+
+        self.macros.set_playing(true);
+        let result = (0..count).try_for_each(|_| keys.iter().try_for_each(|key| self.handle_key(*key)));
+        self.macros.set_playing(false);
+
+        result
+    }
+
+    /// set_vi_mode() turns modal editing on or off for buffer `id`, starting in normal mode.
+    pub fn set_vi_mode(&mut self, id: BufferId, enabled: bool) {
+        match enabled {
+            true => {
+                self.vi_states.entry(id).or_default();
+            }
+            false => {
+                self.vi_states.remove(&id);
+            }
+        }
+    }
+
+    pub fn vi_state(&self, id: BufferId) -> Option<&ViState> {
+        self.vi_states.get(&id)
+    }
+
+    /// vi_mode() is the vi mode of the current buffer, or `None` if it is not using modal editing.
+    pub fn vi_mode(&self) -> Option<Mode> {
+        self.buffer_vi_mode(self.buffers.current_id()?)
+    }
+
+    /// buffer_vi_mode() is the vi mode buffer `id` is in, or None if it does not use modal editing.
+    pub fn buffer_vi_mode(&self, id: BufferId) -> Option<Mode> {
+        self.vi_states.get(&id).map(|state| state.mode())
+    }
+
+    /// handle_key() adds `key` to the keys typed so far and runs the command they are bound to,
+    /// looking in the current buffer's keymap before the global one. A printable key that is not
+    /// bound on its own inserts itself. Buffers using modal editing read keys through their vi
+    /// layer first, and an open completion popup takes the keys that move through it before
+    /// both. What the key changes is one undo step. Afterwards the completion is matched against
+    /// what was typed, and any buffer with enough unsaved changes is auto-saved.
+    pub fn handle_key(&mut self, key: Key) -> CommandResult {
+        self.undo_boundary();
+        let result = self.dispatch_key(key);
+        self.undo_boundary();
+        self.update_completion(key);
+        self.auto_save_buffers(false);
+
+        result
+    }
+
+    /// auto_save_buffers() writes the auto-save files of modified buffers: every one if `all` is
+    /// set, as a frontend might when idle, or otherwise only those with enough changes to be due.
+    pub fn auto_save_buffers(&mut self, all: bool) {
+        let mut failed = Vec::new();
+
+        for id in self.buffers.iter().map(|(id, _)| id).collect::<Vec<_>>() {
+            let buffer = self.buffers.get_mut(id).unwrap();
+            if (all || buffer.auto_save_due()) && buffer.auto_save().is_err() {
+                failed.push(buffer.name().to_string());
+            }
+        }
+
+        if !failed.is_empty() {
+            self.set_message(&format!("Auto-saving {} failed", failed.join(", ")));
+        }
+    }
+
+    fn dispatch_key(&mut self, key: Key) -> CommandResult {
+        if self.minibuffer.is_some() {
+            self.macros.record(&[key]);
+            return self.handle_minibuffer_key(key);
+        }
+        if let Some(result) = self.handle_completion_key(key) {
+            self.macros.record(&[key]);
+            return result;
+        }
+        if let Some(result) = self.handle_isearch_key(key) {
+            self.macros.record(&[key]);
+            return result;
+        }
+        if let Some(result) = self.handle_query_replace_key(key) {
+            self.macros.record(&[key]);
+            return result;
+        }
+        if let Some(result) = self.handle_terminal_key(key) {
+            self.macros.record(&[key]);
+            return result;
+        }
+        if let Some(result) = self.handle_vi_key(key) {
+            return result;
+        }
+
+        self.pending_keys.push(key);
+        let keys = std::mem::take(&mut self.pending_keys);
+
+        let local = self.buffers.current_id().and_then(|id| self.buffer_keymaps.get(&id));
+        let lookup = match local.map_or(Lookup::Unbound, |keymap| keymap.lookup(&keys)) {
+            Lookup::Unbound => self.global_keymap.lookup(&keys),
+            found => found,
+        };
+        let command = match lookup {
+            Lookup::Command(name, argument) => Some((name.to_string(), argument.to_string())),
+            Lookup::Prefix => {
+                self.pending_keys = keys;
+                return Ok(());
+            }
+            Lookup::Unbound => None,
+        };
+
+        if !matches!(&command, Some((name, _)) if name == "start-macro" || name == "end-macro") {
+            self.macros.record(&keys);
+        }
+
+        match (command, key.printable()) {
+            (Some((name, argument)), _) if argument.is_empty() => self.call_interactively(&name),
+            (Some((name, argument)), _) => self.execute(&name, &argument),
+            (None, Some(character)) if keys.len() == 1 => self.execute("insert-char", &character.to_string()),
+            (None, _) => {
+                self.set_message(&format!("{} is undefined", format_keys(&keys)));
+                Ok(())
+            }
+        }
+    }
+
+    /// handle_minibuffer_key() edits the minibuffer input, moves through its history and completes
+    /// it, finishes the prompt with `RET` and cancels it with `C-g` or escape. When the input picks
+    /// from a list, `C-n` and `C-p` or the arrows move through the choices matching it, and `RET`
+    /// finishes with the one selected.
+    fn handle_minibuffer_key(&mut self, key: Key) -> CommandResult {
+        let minibuffer = self.minibuffer.as_mut().unwrap();
+        if minibuffer.has_choices() {
+            match (key.code, key.modifiers.control, key.modifiers.meta) {
+                (KeyCode::Down, _, _) | (KeyCode::Char('n'), true, false) => {
+                    minibuffer.select_next();
+                    return Ok(());
+                }
+                (KeyCode::Up, _, _) | (KeyCode::Char('p'), true, false) => {
+                    minibuffer.select_previous();
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+        let input = minibuffer.input_mut();
+        let point = input.get_point();
+
+        match (key.code, key.modifiers.control, key.modifiers.meta) {
+            (KeyCode::Enter, _, _) => {
+                let minibuffer = self.minibuffer.take().unwrap();
+                let text = minibuffer.selected().map_or_else(|| minibuffer.text(), |choice| choice.to_string());
+                self.minibuffer_history.add(minibuffer.history(), &text);
+                return self.execute(minibuffer.command(), &text);
+            }
+            (KeyCode::Char('g'), true, _) | (KeyCode::Escape, _, _) => {
+                self.minibuffer = None;
+                self.query_replace_pattern = None;
+                self.set_message("Quit");
+            }
+            (KeyCode::Tab, _, _) => {
+                let completer = self.commands.get(minibuffer.command()).and_then(|command| command.completer());
+                if let Some(completer) = completer {
+                    let text = minibuffer.text();
+                    let candidates = completer(self, &text);
+                    let matches = self.minibuffer.as_mut().unwrap().complete(&candidates);
+                    match matches.len() {
+                        0 => self.set_message("No match"),
+                        1 => self.clear_message(),
+                        _ => self.set_message(&matches.join(" ")),
+                    }
+                }
+            }
+            (KeyCode::Backspace, _, _) => {
+                input.delete_char_backward();
+            }
+            (KeyCode::Delete, _, _) | (KeyCode::Char('d'), true, _) => {
+                input.delete_char_forward();
+            }
+            (KeyCode::Left, _, _) | (KeyCode::Char('b'), true, _) => {
+                let target = input.prev_char_boundary(point).unwrap_or(0);
+                input.set_point(target);
+            }
+            (KeyCode::Right, _, _) | (KeyCode::Char('f'), true, _) => {
+                let target = input.next_char_boundary(point).unwrap_or(point);
+                input.set_point(target);
+            }
+            (KeyCode::Home, _, _) | (KeyCode::Char('a'), true, _) => input.set_point(0),
+            (KeyCode::End, _, _) | (KeyCode::Char('e'), true, _) => {
+                let end = input.len();
+                input.set_point(end);
+            }
+            (KeyCode::Char('k'), true, _) => {
+                let end = input.len();
+                input.remove_bytes(point..end);
+            }
+            (KeyCode::Up, _, _) | (KeyCode::Char('p'), _, true) => {
+                minibuffer.previous_history(&self.minibuffer_history);
+            }
+            (KeyCode::Down, _, _) | (KeyCode::Char('n'), _, true) => {
+                minibuffer.next_history(&self.minibuffer_history);
+            }
+            _ => match key.printable() {
+                Some(character) => input.insert_char(character),
+                None => self.set_message(&format!("{} is undefined", key)),
+            },
+        }
+
+        if let Some(minibuffer) = self.minibuffer.as_mut() {
+            minibuffer.refilter();
+        }
+        Ok(())
+    }
+
+    fn handle_vi_key(&mut self, key: Key) -> Option<CommandResult> {
+        if !self.pending_keys.is_empty() {
+            return None;
+        }
+
+        let id = self.buffers.current_id()?;
+        let mut state = self.vi_states.remove(&id)?;
+        let result = state.handle_key(self, key);
+        self.vi_states.insert(id, state);
+        if result.is_some() {
+            self.macros.record(&[key]);
+        }
+
+        result
+    }
+
+    /// buffer_or_error() is the current buffer, for commands that need one.
+    fn buffer_or_error(&mut self) -> Result<&mut Buffer, CommandError> {
+        self.buffers.current_mut().ok_or(CommandError::NoBuffer)
+    }
+
+    /// writable_buffer_or_error() is the current buffer, for commands that change it, failing if
+    /// it is read-only.
+    fn writable_buffer_or_error(&mut self) -> Result<&mut Buffer, CommandError> {
+        let buffer = self.buffer_or_error()?;
+        buffer.check_writable()?;
+
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use command::{Command, CommandError};
+    use editor::{Editor, SCRATCH_BUFFER};
+    use keymap::Key;
+
+    #[test]
+    fn a_new_editor_has_a_scratch_buffer() {
+        let editor = Editor::new();
+
+        assert_eq!(editor.current_buffer().unwrap().name(), SCRATCH_BUFFER);
+        assert!(editor.command("insert-char").is_some());
+        assert!(editor.command_names().any(|name| name == "goto-line"));
+    }
+
+    #[test]
+    fn register_and_execute_a_command() {
+        let mut editor = Editor::new();
+        let runs = Rc::new(Cell::new(0));
+        let counter = runs.clone();
+        editor.register(Command::new("count", "Count runs.", move |editor, argument| {
+            counter.set(counter.get() + 1);
+            editor.set_message(argument);
+            Ok(())
+        }));
+
+        editor.execute("count", "once").unwrap();
+        editor.execute("count", "twice").unwrap();
+
+        assert_eq!(runs.get(), 2);
+        assert_eq!(editor.message(), Some("twice"));
+    }
+
+    #[test]
+    fn execute_an_unknown_command() {
+        let mut editor = Editor::new();
+
+        match editor.execute("no-such-command", "") {
+            Err(CommandError::UnknownCommand(name)) => assert_eq!(name, "no-such-command"),
+            result => panic!("Expected an unknown command error but got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn commands_fail_without_a_buffer() {
+        let mut editor = Editor::new();
+        let scratch = editor.buffers().current_id().unwrap();
+        editor.buffers_mut().kill(scratch);
+
+        assert!(matches!(editor.execute("insert-char", "a"), Err(CommandError::NoBuffer)));
+    }
+
+    #[test]
+    fn keys_run_their_commands() {
+        let mut editor = Editor::new();
+        editor.global_keymap_mut().bind_str("C-x C-e", "insert-char").unwrap();
+        editor.global_keymap_mut().bind_str("C-b", "backward-char").unwrap();
+
+        editor.handle_key(Key::char('a')).unwrap();
+        editor.handle_key(Key::char('b')).unwrap();
+        editor.handle_key(Key::control('b')).unwrap();
+        editor.handle_key(Key::char('-')).unwrap();
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "a-b");
+
+        editor.handle_key(Key::control('x')).unwrap();
+        assert_eq!(editor.pending_keys(), &[Key::control('x')]);
+        assert!(editor.handle_key(Key::control('e')).is_err());
+        assert!(editor.pending_keys().is_empty());
+
+        editor.handle_key(Key::control('x')).unwrap();
+        editor.handle_key(Key::char('z')).unwrap();
+        assert_eq!(editor.message(), Some("C-x z is undefined"));
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "a-b");
+    }
+
+    fn press(editor: &mut Editor, keys: &str) {
+        for key in Key::parse_sequence(keys).unwrap() {
+            editor.handle_key(key).unwrap();
+        }
+    }
+
+    #[test]
+    fn commands_with_prompts_ask_in_the_minibuffer() {
+        let mut editor = Editor::new();
+        press(&mut editor, "a RET b RET c");
+
+        press(&mut editor, "M-g g");
+        assert_eq!(editor.minibuffer().unwrap().prompt(), "Goto line: ");
+        press(&mut editor, "2 RET");
+
+        assert!(editor.minibuffer().is_none());
+        assert_eq!(editor.current_buffer().unwrap().get_point(), 2);
+        assert_eq!(editor.minibuffer_history().entries("goto-line"), &["2".to_string()]);
+    }
+
+    #[test]
+    fn edit_and_cancel_the_minibuffer() {
+        let mut editor = Editor::new();
+
+        press(&mut editor, "M-x a b c DEL C-a x C-e");
+        let minibuffer = editor.minibuffer().unwrap();
+        assert_eq!(minibuffer.text(), "xab");
+        assert_eq!(minibuffer.input().get_point(), 3);
+
+        press(&mut editor, "C-g");
+        assert!(editor.minibuffer().is_none());
+        assert_eq!(editor.message(), Some("Quit"));
+        assert!(editor.current_buffer().unwrap().is_empty());
+    }
+
+    #[test]
+    fn execute_a_command_by_name() {
+        let mut editor = Editor::new();
+
+        press(&mut editor, "M-x n e w l TAB");
+        assert_eq!(editor.minibuffer().unwrap().text(), "newline");
+        press(&mut editor, "RET");
+
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "\n");
+    }
+
+    #[test]
+    fn reuse_an_earlier_answer() {
+        let mut editor = Editor::new();
+        press(&mut editor, "x y C-s RET x RET");
+        assert_eq!(editor.current_buffer().unwrap().get_point(), 2);
+
+        press(&mut editor, "C-a C-s RET M-p RET");
+
+        assert_eq!(editor.current_buffer().unwrap().get_point(), 1);
+    }
+
+    #[test]
+    fn buffer_keymaps_come_before_the_global_keymap() {
+        let mut editor = Editor::new();
+        let scratch = editor.buffers().current_id().unwrap();
+        let other = editor.buffers_mut().create("other");
+        editor.global_keymap_mut().bind_str("C-c", "set-mark").unwrap();
+        editor.buffer_keymap_mut(scratch).bind_with_argument(&[Key::control('c')], "insert-char", "!");
+
+        editor.handle_key(Key::control('c')).unwrap();
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "!");
+
+        editor.buffers_mut().switch_to(other);
+        editor.handle_key(Key::control('c')).unwrap();
+        assert_eq!(editor.current_buffer().unwrap().get_mark(), Some(0));
+    }
+
+    #[test]
+    fn check_files_reverts_unmodified_buffers() {
+        let path = std::env::temp_dir().join(format!("one-check-files-{}.txt", std::process::id()));
+        std::fs::write(&path, "old").unwrap();
+        let mut editor = Editor::new();
+        editor.execute("find-file", path.to_str().unwrap()).unwrap();
+        let name = editor.current_buffer().unwrap().name().to_string();
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&path, "new").unwrap();
+        assert_eq!(editor.check_files(), vec![name.clone()]);
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "old");
+
+        editor.set_auto_revert(true);
+        assert!(editor.check_files().is_empty());
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "new");
+        assert_eq!(editor.message(), Some(format!("Reverted {}", name).as_str()));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
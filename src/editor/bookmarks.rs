@@ -0,0 +1,113 @@
+use std::io;
+use std::path::PathBuf;
+
+use anchor::Affinity;
+use bookmark::Bookmarks;
+use buffer::file::OpenMode;
+use command::{CommandError, CommandResult};
+use delta::PositionEncoding;
+use editor::commands::visit_file;
+use editor::Editor;
+
+/// The buffer `list_bookmarks()` shows the bookmarks in.
+pub const BOOKMARKS_BUFFER: &str = "*Bookmarks*";
+
+impl Editor {
+    pub fn bookmarks(&self) -> &Bookmarks {
+        &self.bookmarks
+    }
+
+    /// set_bookmark_file() keeps the bookmarks in the file at `path` from now on, loading those
+    /// already in it, so they last from one session to the next. With `None` they are only kept
+    /// for this session.
+    pub fn set_bookmark_file(&mut self, path: Option<PathBuf>) -> io::Result<()> {
+        if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+            self.bookmarks.load(path)?;
+        }
+
+        self.bookmark_file = path;
+        Ok(())
+    }
+
+    /// set_bookmark() bookmarks the point of the current buffer as `name`, replacing any
+    /// bookmark already called that.
+    pub fn set_bookmark(&mut self, name: &str) -> CommandResult {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let buffer = self.buffers.current_mut().unwrap();
+        let point = buffer.get_point();
+        let anchor = buffer.create_anchor(point, Affinity::Left);
+        let position = buffer.offset_to_position(point, PositionEncoding::Utf8);
+        let path = buffer.path().map(|path| path.to_path_buf());
+
+        if let Some((id, anchor)) = self.bookmarks.set(name, id, anchor, path, position).and_then(|old| old.anchor(&self.buffers)) {
+            self.buffers.get_mut(id).unwrap().remove_anchor(anchor);
+        }
+        self.save_bookmarks()
+    }
+
+    /// jump_to_bookmark() moves the point to bookmark `name`, visiting its file again if its
+    /// buffer is gone. Returns false if there is no such bookmark.
+    pub fn jump_to_bookmark(&mut self, name: &str) -> Result<bool, CommandError> {
+        let bookmark = match self.bookmarks.get(name) {
+            Some(bookmark) => bookmark.clone(),
+            None => return Ok(false),
+        };
+        self.push_jump();
+
+        if let Some((id, anchor)) = bookmark.anchor(&self.buffers) {
+            self.show_buffer(id);
+            let buffer = self.buffers.get_mut(id).unwrap();
+            let offset = buffer.anchor_offset(anchor).unwrap();
+            buffer.set_point(offset);
+            return Ok(true);
+        }
+
+        let path = bookmark.path.as_ref().ok_or(CommandError::NoBuffer)?;
+        visit_file(self, "bookmark-jump", &path.to_string_lossy(), OpenMode::Detect)?;
+        let id = self.buffers.current_id().unwrap();
+        let buffer = self.buffers.current_mut().unwrap();
+        let offset = buffer.position_to_offset(bookmark.position, PositionEncoding::Utf8).unwrap_or(buffer.len());
+        buffer.set_point(offset);
+        let anchor = buffer.create_anchor(offset, Affinity::Left);
+        self.bookmarks.attach(name, id, anchor);
+        Ok(true)
+    }
+
+    /// delete_bookmark() removes bookmark `name`, returning false if there was none.
+    pub fn delete_bookmark(&mut self, name: &str) -> Result<bool, CommandError> {
+        let bookmark = match self.bookmarks.remove(name) {
+            Some(bookmark) => bookmark,
+            None => return Ok(false),
+        };
+
+        if let Some((id, anchor)) = bookmark.anchor(&self.buffers) {
+            self.buffers.get_mut(id).unwrap().remove_anchor(anchor);
+        }
+        self.save_bookmarks()?;
+        Ok(true)
+    }
+
+    /// list_bookmarks() shows every bookmark with where it is, one per line.
+    pub fn list_bookmarks(&mut self) {
+        let mut text = String::new();
+        for (name, bookmark) in self.bookmarks.iter() {
+            let place = match (bookmark.path.as_ref(), bookmark.anchor(&self.buffers)) {
+                (Some(path), _) => path.display().to_string(),
+                (None, Some((id, _))) => self.buffers.get(id).unwrap().name().to_string(),
+                (None, None) => "(killed buffer)".to_string(),
+            };
+            let position = bookmark.position(&self.buffers);
+            text.push_str(&format!("{:<20} {}:{}:{}\n", name, place, position.line + 1, position.character + 1));
+        }
+
+        self.show_text_buffer(BOOKMARKS_BUFFER, &text);
+    }
+
+    /// save_bookmarks() writes the bookmarks to the bookmark file, if there is one.
+    pub(super) fn save_bookmarks(&mut self) -> CommandResult {
+        if let Some(path) = self.bookmark_file.as_ref() {
+            self.bookmarks.save(&self.buffers, path)?;
+        }
+        Ok(())
+    }
+}
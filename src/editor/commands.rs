@@ -0,0 +1,2906 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use buffer::auto_save::has_recovery_data;
+use buffer::case::Case;
+use buffer::file::OpenMode;
+use buffer::lines::SortLines;
+use buffer::transform::{ShiftIndentation, StripTrailingWhitespace, Tabify, Untabify};
+use buffer::{Buffer, LineEnding, SaveTransform};
+use command::{Command, CommandError, CommandResult};
+use comment::{CommentAction, CommentLines, CommentSyntax};
+use diagnostic::Diagnostic;
+use editor::jobs::{JobId, ASYNC_SHELL_BUFFER};
+use editor::Editor;
+use indent::{indenter_for, IndentSettings};
+use kill_ring::KillRing;
+use lsp::{self, ServerConfig};
+use minibuffer::file_name_candidates;
+use project;
+use shell::run_shell_command;
+use syntax::{highlighter_for, SyntaxRules};
+use theme::{Theme, ThemeError};
+use view::{LineNumbers, Wrap};
+use window::Direction;
+
+/// builtin_commands() returns the commands every editor starts with.
+pub fn builtin_commands() -> Vec<Command> {
+    vec![
+        Command::new("insert-char", "Insert the argument at the point.", insert_char),
+        Command::new("delete-backward", "Delete the character before the point.", delete_backward),
+        Command::new("delete-forward", "Delete the character after the point.", delete_forward),
+        Command::new("forward-char", "Move the point forward one character.", forward_char),
+        Command::new("backward-char", "Move the point back one character.", backward_char),
+        Command::new("forward-word", "Move the point to the end of the next word.", forward_word),
+        Command::new("backward-word", "Move the point to the start of the previous word.", backward_word),
+        Command::new("next-line", "Move the point down one screen row.", next_line),
+        Command::new("previous-line", "Move the point up one screen row.", previous_line),
+        Command::new("next-logical-line", "Move the point down one line, however it is wrapped.", next_logical_line),
+        Command::new("previous-logical-line", "Move the point up one line, however it is wrapped.", previous_logical_line),
+        Command::new("beginning-of-line", "Move the point to the start of the line.", beginning_of_line),
+        Command::new("end-of-line", "Move the point to the end of the line.", end_of_line),
+        Command::new("beginning-of-buffer", "Move the point to the start of the buffer.", beginning_of_buffer),
+        Command::new("end-of-buffer", "Move the point to the end of the buffer.", end_of_buffer),
+        Command::new("scroll-up-command", "Scroll the text up by a screen, showing what follows.", scroll_up_command),
+        Command::new("scroll-down-command", "Scroll the text down by a screen, showing what precedes.", scroll_down_command),
+        Command::new("recenter", "Scroll so the point's line is in the middle of the screen.", recenter),
+        Command::new("newline", "Insert a line break and indent the new line.", newline),
+        Command::new("set-mark", "Set the mark at the point.", set_mark),
+        Command::new("kill-region", "Kill the text between the point and the mark.", kill_region),
+        Command::new("kill-line", "Kill the rest of the line.", kill_line),
+        Command::new("kill-whole-line", "Kill the line of the point with its line break.", kill_whole_line),
+        Command::new("kill-word", "Kill the text from the point to the end of the next word.", |editor, _| kill_word(editor, true)),
+        Command::new("backward-kill-word", "Kill the text from the point back to the start of the previous word.", |editor, _| kill_word(editor, false)),
+        Command::new("kill-sentence", "Kill the text from the point to the end of the sentence.", kill_sentence),
+        Command::new("copy-region", "Add the text between the point and the mark to the kill ring.", copy_region),
+        Command::new("yank", "Insert the most recently killed text.", yank),
+        Command::new("yank-pop", "Replace the text just yanked with an older kill.", yank_pop),
+        Command::new("undo", "Undo the last change to the buffer.", undo),
+        Command::new("redo", "Redo the last change undone.", redo),
+        Command::new("search-forward", "Move the point past the next occurrence of the argument.", search_forward)
+            .with_prompt("Search: "),
+        Command::new("search-backward", "Move the point to the previous occurrence of the argument.", search_backward)
+            .with_prompt("Search backward: "),
+        Command::new("isearch-forward", "Search forward for the text as it is typed.", isearch_forward),
+        Command::new("isearch-backward", "Search backward for the text as it is typed.", isearch_backward),
+        Command::new("query-replace", "Replace occurrences of some text, asking about each.", query_replace)
+            .with_prompt("Query replace: "),
+        Command::new("occur", "List the lines with the argument in them.", occur)
+            .with_prompt("List lines matching: "),
+        Command::new("occur-goto", "Go to the line listed at the point of the occur buffer.", occur_goto),
+        Command::new("grep", "Search the files of the project for the argument.", grep)
+            .with_prompt("Grep project for: "),
+        Command::new("grep-goto", "Visit the match listed at the point of the grep buffer.", grep_goto),
+        Command::new("find-file", "Visit the file named by the argument.", find_file)
+            .with_prompt("Find file: ")
+            .with_completer(|_, input| file_name_candidates(input)),
+        Command::new("dired", "List the entries of the directory named by the argument.", dired)
+            .with_prompt("Dired (directory): ")
+            .with_completer(|_, input| file_name_candidates(input)),
+        Command::new("dired-find-file", "Visit the file or directory listed at the point.", dired_find_file),
+        Command::new("dired-up-directory", "List the directory above the one listed.", dired_up_directory),
+        Command::new("dired-refresh", "List the directory again.", dired_refresh),
+        Command::new("dired-rename", "Rename the file or directory listed at the point to the argument.", dired_rename),
+        Command::new("dired-delete", "Delete the file or empty directory listed at the point, asking first.", dired_delete),
+        Command::new("dired-create-directory", "Create the directory named by the argument in the one listed.", dired_create_directory)
+            .with_prompt("Create directory: "),
+        Command::new("dired-create-file", "Create the empty file named by the argument in the directory listed.", dired_create_file)
+            .with_prompt("Create file: "),
+        Command::new("find-file-in-project", "Visit a file of the project, picked by typing parts of its name.", find_file_in_project),
+        Command::new("find-file-literally", "Visit a file keeping its bytes exactly as they are.", find_file_literally)
+            .with_prompt("Find file literally: ")
+            .with_completer(|_, input| file_name_candidates(input)),
+        Command::new("save-buffer", "Write the buffer to its file.", save_buffer),
+        Command::new("revert-buffer", "Replace the buffer with its file as it is now.", revert_buffer),
+        Command::new("recover-this-file", "Replace the buffer with its auto-saved changes.", recover_this_file),
+        Command::new("write-file", "Write the buffer to the file named by the argument and visit it.", write_file)
+            .with_prompt("Write file: ")
+            .with_completer(|_, input| file_name_candidates(input)),
+        Command::new("set-line-ending", "Save the buffer with the line ending named by the argument.", set_line_ending)
+            .with_prompt("Line ending (lf, crlf or cr): ")
+            .with_completer(|_, _| ["lf", "crlf", "cr"].iter().map(|name| name.to_string()).collect()),
+        Command::new("split-window-below", "Split the selected window into two, one above the other.", |editor, _| {
+            split_window(editor, Direction::Below)
+        }),
+        Command::new("split-window-right", "Split the selected window into two, side by side.", |editor, _| {
+            split_window(editor, Direction::Right)
+        }),
+        Command::new("other-window", "Select the next window.", other_window),
+        Command::new("delete-window", "Close the selected window.", delete_window),
+        Command::new("delete-other-windows", "Close every window but the selected one.", |editor, _| {
+            editor.delete_other_windows();
+            Ok(())
+        }),
+        Command::new("balance-windows", "Make the windows the same size.", |editor, _| {
+            editor.windows.balance();
+            Ok(())
+        }),
+        Command::new("enlarge-window", "Make the selected window taller by the argument's lines.", |editor, argument| {
+            resize_window(editor, "enlarge-window", argument, Direction::Below, 1)
+        }),
+        Command::new("shrink-window", "Make the selected window shorter by the argument's lines.", |editor, argument| {
+            resize_window(editor, "shrink-window", argument, Direction::Below, -1)
+        }),
+        Command::new("enlarge-window-horizontally", "Make the selected window wider by the argument's columns.", |editor, argument| {
+            resize_window(editor, "enlarge-window-horizontally", argument, Direction::Right, 1)
+        }),
+        Command::new("shrink-window-horizontally", "Make the selected window narrower by the argument's columns.", |editor, argument| {
+            resize_window(editor, "shrink-window-horizontally", argument, Direction::Right, -1)
+        }),
+        Command::new("display-line-numbers", "Number lines in the gutter as the argument says.", display_line_numbers)
+            .with_prompt("Line numbers (off, absolute or relative): ")
+            .with_completer(|_, _| ["off", "absolute", "relative"].iter().map(|name| name.to_string()).collect()),
+        Command::new("set-wrap", "Wrap long lines as the argument says.", set_wrap)
+            .with_prompt("Wrap lines (off, char or word): ")
+            .with_completer(|_, _| ["off", "char", "word"].iter().map(|name| name.to_string()).collect()),
+        Command::new("delete-trailing-whitespace", "Remove spaces and tabs from the ends of lines.", |editor, _| {
+            transform_buffer(editor, &StripTrailingWhitespace)
+        }),
+        Command::new("load-theme", "Draw text in the built-in theme or theme file the argument names.", load_theme)
+            .with_prompt("Load theme: ")
+            .with_completer(|_, input| {
+                let mut candidates: Vec<String> =
+                    Theme::builtin_names().filter(|name| name.starts_with(input)).map(|name| name.to_string()).collect();
+                candidates.extend(file_name_candidates(input));
+                candidates
+            }),
+        Command::new("load-syntax-rules", "Highlight files with the syntax rules in the file the argument names.", load_syntax_rules)
+            .with_prompt("Load syntax rules: ")
+            .with_completer(|_, input| file_name_candidates(input)),
+        Command::new("set-tab-width", "Put tab stops every number of columns the argument says.", set_tab_width)
+            .with_prompt("Tab width: "),
+        Command::new("tabify", "Indent the region's lines, or all lines, with tabs at tab stops.", |editor, _| {
+            let tab_width = editor.view().tab_width();
+            transform_region_lines(editor, &Tabify { tab_width })
+        }),
+        Command::new("untabify", "Replace tabs in the region's lines, or all lines, with spaces.", |editor, _| {
+            let tab_width = editor.view().tab_width();
+            transform_region_lines(editor, &Untabify { tab_width })
+        }),
+        Command::new("set-indent-style", "Indent the current buffer with tabs or spaces as the argument says.", set_indent_style)
+            .with_prompt("Indent with (tabs or spaces): ")
+            .with_completer(|_, _| ["tabs", "spaces"].iter().map(|name| name.to_string()).collect()),
+        Command::new("set-indent-width", "Indent the current buffer by the argument's columns a level with spaces.", set_indent_width)
+            .with_prompt("Indent width: "),
+        Command::new("duplicate-line", "Copy the line of the point, or the lines in the region, below it.", |editor, _| {
+            let buffer = editor.writable_buffer_or_error()?;
+            let range = buffer.region().unwrap_or_else(|| buffer.get_point()..buffer.get_point());
+            buffer.duplicate_lines(range);
+            Ok(())
+        }),
+        Command::new("move-line-up", "Move the line of the point, or the lines in the region, above the line before.", |editor, _| move_lines(editor, false)),
+        Command::new("move-line-down", "Move the line of the point, or the lines in the region, below the line after.", |editor, _| move_lines(editor, true)),
+        Command::new("join-lines", "Join the line of the point to the next, or the lines in the region into one.", join_lines),
+        Command::new("split-line", "Move the text after the point down onto a line of its own, keeping its column.", |editor, _| {
+            editor.writable_buffer_or_error()?.split_line();
+            Ok(())
+        }),
+        Command::new("transpose-chars", "Swap the characters around the point, or the two before it at the end of a line.", |editor, _| {
+            transpose(editor, Buffer::transpose_chars)
+        }),
+        Command::new("transpose-words", "Swap the word the point is in or after with the next word.", |editor, _| transpose(editor, Buffer::transpose_words)),
+        Command::new("transpose-lines", "Swap the line of the point with the line before it.", |editor, _| transpose(editor, Buffer::transpose_lines)),
+        Command::new("upcase-word", "Convert the text from the point to the end of the next word to upper case.", |editor, _| change_case_word(editor, Case::Upper)),
+        Command::new("downcase-word", "Convert the text from the point to the end of the next word to lower case.", |editor, _| change_case_word(editor, Case::Lower)),
+        Command::new("capitalize-word", "Capitalize the text from the point to the end of the next word.", |editor, _| change_case_word(editor, Case::Capitalized)),
+        Command::new("upcase-region", "Convert the text in the region to upper case.", |editor, _| change_case_region(editor, Case::Upper)),
+        Command::new("downcase-region", "Convert the text in the region to lower case.", |editor, _| change_case_region(editor, Case::Lower)),
+        Command::new("capitalize-region", "Capitalize the words in the region.", |editor, _| change_case_region(editor, Case::Capitalized)),
+        Command::new("fill-paragraph", "Rewrap the paragraph of the point to the fill column, keeping its indentation and comment leaders.", |editor, _| {
+            let tab_width = editor.view().tab_width();
+            editor.writable_buffer_or_error()?.fill_paragraph(tab_width);
+            Ok(())
+        }),
+        Command::new("set-fill-column", "Set the column the buffer's lines are filled to to the argument.", set_fill_column)
+            .with_prompt("Set fill column to: "),
+        Command::new("auto-fill-mode", "Turn wrapping lines as they are typed past the fill column on or off in the buffer.", auto_fill_mode),
+        Command::new("sort-lines", "Sort the lines in the region, or the buffer, in ascending order.", |editor, _| sort_lines(editor, SortLines::default())),
+        Command::new("sort-lines-descending", "Sort the lines in the region, or the buffer, in descending order.", |editor, _| {
+            sort_lines(editor, SortLines { descending: true, ..SortLines::default() })
+        }),
+        Command::new("sort-lines-numerically", "Sort the lines in the region, or the buffer, by the numbers they start with.", |editor, _| {
+            sort_lines(editor, SortLines { numeric: true, ..SortLines::default() })
+        }),
+        Command::new("sort-lines-ignoring-case", "Sort the lines in the region, or the buffer, ignoring case.", |editor, _| {
+            sort_lines(editor, SortLines { ignore_case: true, ..SortLines::default() })
+        }),
+        Command::new("delete-duplicate-lines", "Delete the lines in the region, or the buffer, that repeat an earlier one.", delete_duplicate_lines),
+        Command::new("narrow-to-region", "Restrict the buffer to the text in the region, hiding the rest until it is widened.", narrow_to_region),
+        Command::new("widen", "Make the whole text of a narrowed buffer accessible again.", widen),
+        Command::new("shell-command-on-region", "Replace the region with what a shell command writes when given it as input.", shell_command_on_region)
+            .with_prompt("Shell command on region: "),
+        Command::new("async-shell-command", "Run the argument as a shell command in the background, showing its output as it comes.", async_shell_command)
+            .with_prompt("Async shell command: "),
+        Command::new("list-jobs", "List the jobs started in the background and whether they are still running.", |editor, _| {
+            editor.list_jobs();
+            Ok(())
+        }),
+        Command::new("kill-job", "Stop the running job numbered by the argument, or the one writing to the current buffer.", kill_job)
+            .with_prompt("Kill job (empty for this buffer's): ")
+            .with_completer(|editor, _| editor.jobs().filter(|(_, job)| job.is_running()).map(|(id, _)| id.to_string()).collect()),
+        Command::new("compile", "Run the argument as a build in the project, or the last build again, listing its errors.", compile)
+            .with_prompt("Compile command (empty to repeat the last): "),
+        Command::new("compile-goto-error", "Visit the error listed at the point of the compilation buffer.", compile_goto_error),
+        Command::new("next-error", "Visit the next error the last build listed.", |editor, _| step_error(editor, true)),
+        Command::new("previous-error", "Visit the previous error the last build listed.", |editor, _| step_error(editor, false)),
+        Command::new("term", "Run the argument, or the shell, in a new terminal buffer that keys are typed into.", term)
+            .with_prompt("Run in terminal (empty for the shell): "),
+        Command::new("git-gutter-mode", "Turn marking the lines changed since the last commit in the gutter on or off in the buffer.", git_gutter_mode),
+        Command::new("next-hunk", "Move the point to the next run of lines changed since the last commit.", |editor, _| step_hunk(editor, true)),
+        Command::new("previous-hunk", "Move the point to the previous run of lines changed since the last commit.", |editor, _| step_hunk(editor, false)),
+        Command::new("revert-hunk", "Put the run of changed lines at the point back as they were in the last commit.", revert_hunk),
+        Command::new("git-blame", "List who last changed each line of the buffer's file, and when, beside the line.", git_blame),
+        Command::new("blame-goto-line", "Go to the line listed at the point of the blame buffer.", blame_goto_line),
+        Command::new("diff-buffer-with-file", "Show the changes made to the buffer since its file was saved.", diff_buffer_with_file),
+        Command::new("diff-buffers", "Show the changes from the buffer to the buffer named by the argument.", diff_buffers)
+            .with_prompt("Diff with buffer: ")
+            .with_completer(|editor, _| editor.buffers().iter().map(|(_, buffer)| buffer.name().to_string()).collect()),
+        Command::new("view-messages", "Show the messages buffer, where every message shown is logged.", view_messages),
+        Command::new("scratch-buffer", "Switch to the scratch buffer, making a new one if it was killed.", |editor, _| {
+            let id = editor.buffers.scratch();
+            editor.show_buffer(id);
+            Ok(())
+        }),
+        Command::new("indent-region", "Indent the region's lines, or the point's line, one level further.", |editor, _| {
+            shift_region_lines(editor, 1)
+        }),
+        Command::new("dedent-region", "Indent the region's lines, or the point's line, one level less.", |editor, _| {
+            shift_region_lines(editor, -1)
+        }),
+        Command::new("comment-region", "Comment out the region's lines, or the point's line.", |editor, _| {
+            comment_region_lines(editor, CommentAction::Comment)
+        }),
+        Command::new("uncomment-region", "Uncomment the region's lines, or the point's line.", |editor, _| {
+            comment_region_lines(editor, CommentAction::Uncomment)
+        }),
+        Command::new("toggle-comment", "Uncomment the region's lines if they are commented out, or else comment them out.", |editor, _| {
+            comment_region_lines(editor, CommentAction::Toggle)
+        }),
+        Command::new("goto-matching-bracket", "Move the point to the bracket or quote matching the one at it.", goto_matching_bracket),
+        Command::new("lsp-start", "Start a language server for the current buffer's language, run by the argument or the usual one.", lsp_start)
+            .with_prompt("Language server (empty for the usual one): ")
+            .with_completer(|editor, _| {
+                let path = editor.current_buffer().and_then(|buffer| buffer.path());
+                path.and_then(ServerConfig::for_path).map(|config| vec![config.command]).unwrap_or_default()
+            }),
+        Command::new("lsp-stop", "Shut down the language server for the current buffer's language.", lsp_stop),
+        Command::new("goto-definition", "Ask the language server where the name at the point is defined and go there.", |editor, _| {
+            find_lsp_locations(editor, "textDocument/definition", "definitionProvider", json!({}))
+        }),
+        Command::new("find-references", "Ask the language server where the name at the point is used and go to the first place.", |editor, _| {
+            find_lsp_locations(editor, "textDocument/references", "referencesProvider", json!({"context": {"includeDeclaration": true}}))
+        }),
+        Command::new("jump-back", "Go back to where the point was before the last jump, such as a search or goto-line.", |editor, _| {
+            if !editor.jump_back() {
+                editor.set_message("No earlier jump");
+            }
+            Ok(())
+        }),
+        Command::new("jump-forward", "Go forward again to where the point was before the last jump-back.", |editor, _| {
+            if !editor.jump_forward() {
+                editor.set_message("No later jump");
+            }
+            Ok(())
+        }),
+        Command::new("bookmark-set", "Bookmark the point under the name in the argument, or the buffer's name.", bookmark_set)
+            .with_prompt("Set bookmark: ")
+            .with_completer(|editor, _| editor.bookmarks().names().map(|name| name.to_string()).collect()),
+        Command::new("bookmark-jump", "Go to the bookmark named by the argument.", bookmark_jump)
+            .with_prompt("Jump to bookmark: ")
+            .with_completer(|editor, _| editor.bookmarks().names().map(|name| name.to_string()).collect()),
+        Command::new("bookmark-delete", "Delete the bookmark named by the argument.", bookmark_delete)
+            .with_prompt("Delete bookmark: ")
+            .with_completer(|editor, _| editor.bookmarks().names().map(|name| name.to_string()).collect()),
+        Command::new("bookmark-list", "Show every bookmark and where it is.", |editor, _| {
+            editor.list_bookmarks();
+            Ok(())
+        }),
+        Command::new("next-location", "Go to the next of the places found last.", |editor, _| step_location(editor, 1)),
+        Command::new("previous-location", "Go to the previous of the places found last.", |editor, _| step_location(editor, -1)),
+        Command::new("next-diagnostic", "Move the point to the next diagnostic and show its message.", next_diagnostic),
+        Command::new("previous-diagnostic", "Move the point to the previous diagnostic and show its message.", previous_diagnostic),
+        Command::new("complete", "Complete the text before the point, choosing from a popup if there is more than one way.", |editor, _| {
+            editor.start_completion(true)
+        }),
+        Command::new("dabbrev-expand", "Expand the word before the point into a nearby word of the open buffers, or the next one if repeated.", dabbrev_expand),
+        Command::new("toggle-auto-complete", "Turn opening completion as you type on or off.", toggle_auto_complete),
+        Command::new("goto-line", "Move the point to the start of the line numbered by the argument, or to a column of it given as line:column.", goto_line)
+            .with_prompt("Goto line: "),
+        Command::new("goto-column", "Move the point to the column of its line numbered by the argument.", goto_column)
+            .with_prompt("Goto column: "),
+        Command::new("goto-char", "Move the point to the character of the buffer numbered by the argument.", goto_char)
+            .with_prompt("Goto char: "),
+        Command::new("execute-extended-command", "Run the command named by the argument.", execute_extended_command)
+            .with_prompt("M-x ")
+            .with_completer(|editor, _| editor.command_names().map(|name| name.to_string()).collect()),
+        Command::new("start-macro", "Start recording keys as a keyboard macro.", start_macro),
+        Command::new("end-macro", "Stop recording the keyboard macro.", end_macro),
+        Command::new("call-macro", "Play the last keyboard macro, the number of times in the argument.", call_macro),
+        Command::new("name-macro", "Save the last keyboard macro under the name in the argument.", name_macro)
+            .with_prompt("Name for last keyboard macro: "),
+        Command::new("call-named-macro", "Play the keyboard macro named by the argument.", call_named_macro)
+            .with_prompt("Macro: ")
+            .with_completer(|editor, _| editor.macros().names().map(|name| name.to_string()).collect()),
+        Command::new("save-buffers-kill-editor", "Save every modified file and exit.", save_buffers_kill_editor),
+        Command::new("toggle-vi-mode", "Turn modal editing on or off in the buffer.", toggle_vi_mode),
+        Command::new("toggle-read-only", "Turn refusing changes to the buffer on or off.", toggle_read_only),
+    ]
+}
+
+fn invalid_argument(command: &str, argument: &str) -> CommandError {
+    CommandError::InvalidArgument {
+        command: command.to_string(),
+        argument: argument.to_string(),
+    }
+}
+
+fn insert_char(editor: &mut Editor, argument: &str) -> CommandResult {
+    if argument.is_empty() {
+        return Err(invalid_argument("insert-char", argument));
+    }
+
+    let tab_width = editor.view().tab_width();
+    let buffer = editor.writable_buffer_or_error()?;
+    if argument == " " && buffer.auto_fill() {
+        buffer.auto_fill_line(tab_width);
+    }
+    buffer.insert_str(argument);
+    Ok(())
+}
+
+fn delete_backward(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.writable_buffer_or_error()?.delete_char_backward();
+    Ok(())
+}
+
+fn delete_forward(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.writable_buffer_or_error()?.delete_char_forward();
+    Ok(())
+}
+
+fn forward_char(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    if let Some(next) = buffer.next_char_boundary(buffer.get_point()) {
+        buffer.set_point(next);
+    }
+    Ok(())
+}
+
+fn backward_char(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    if let Some(previous) = buffer.prev_char_boundary(buffer.get_point()) {
+        buffer.set_point(previous);
+    }
+    Ok(())
+}
+
+fn forward_word(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.move_point_forward_word();
+    Ok(())
+}
+
+fn backward_word(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.move_point_backward_word();
+    Ok(())
+}
+
+fn next_line(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    editor.windows.selected_mut().view_mut().move_point_by_rows(buffer, 1);
+    Ok(())
+}
+
+fn previous_line(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    editor.windows.selected_mut().view_mut().move_point_by_rows(buffer, -1);
+    Ok(())
+}
+
+fn next_logical_line(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.move_point_by_lines(1);
+    Ok(())
+}
+
+fn previous_logical_line(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.move_point_by_lines(-1);
+    Ok(())
+}
+
+fn beginning_of_line(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.move_point_line_start();
+    Ok(())
+}
+
+fn end_of_line(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.move_point_line_end();
+    Ok(())
+}
+
+fn beginning_of_buffer(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.set_point(0);
+    Ok(())
+}
+
+fn end_of_buffer(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    let end = buffer.len();
+    buffer.set_point(end);
+    Ok(())
+}
+
+fn scroll_up_command(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    if !editor.windows.selected_mut().view_mut().page_down(buffer) {
+        editor.set_message("End of buffer");
+    }
+    Ok(())
+}
+
+fn scroll_down_command(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    if !editor.windows.selected_mut().view_mut().page_up(buffer) {
+        editor.set_message("Beginning of buffer");
+    }
+    Ok(())
+}
+
+fn recenter(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current().ok_or(CommandError::NoBuffer)?;
+    editor.windows.selected_mut().view_mut().recenter(buffer);
+    Ok(())
+}
+
+/// newline() breaks the line at the point and indents the new line as the buffer's indenter says,
+/// in place of the spaces and tabs that followed the point.
+fn newline(editor: &mut Editor, _: &str) -> CommandResult {
+    let id = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    let tab_width = editor.view().tab_width();
+    let buffer = editor.writable_buffer_or_error()?;
+    if buffer.auto_fill() {
+        buffer.auto_fill_line(tab_width);
+    }
+    let point = buffer.get_point();
+    let (line, _) = buffer.offset_to_line_col(point);
+    let end = buffer.line_end_offset(line).unwrap();
+    let blanks = buffer.slice(point..end).chars().take_while(|character| *character == ' ' || *character == '\t').count();
+
+    buffer.replace_range(point..point + blanks, "\n");
+    buffer.set_point(point + 1);
+    let unit = buffer.indent_settings().unit();
+    let indentation = editor.indenter(id).indentation(editor.buffers.get(id).unwrap(), line + 1, &unit);
+    editor.buffers.get_mut(id).unwrap().insert_str(&indentation);
+    Ok(())
+}
+
+fn set_mark(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?.set_mark_at_point();
+    editor.set_message("Mark set");
+    Ok(())
+}
+
+fn kill_region(editor: &mut Editor, _: &str) -> CommandResult {
+    if !kill(editor, |kill_ring, buffer| kill_ring.kill_region(buffer))? {
+        editor.set_message("The mark is not set now, so there is no region");
+    }
+    Ok(())
+}
+
+fn kill_line(editor: &mut Editor, _: &str) -> CommandResult {
+    if !kill(editor, |kill_ring, buffer| kill_ring.kill_line(buffer))? {
+        editor.set_message("End of buffer");
+    }
+    Ok(())
+}
+
+fn kill_whole_line(editor: &mut Editor, _: &str) -> CommandResult {
+    if !kill(editor, |kill_ring, buffer| kill_ring.kill_whole_line(buffer))? {
+        editor.set_message("End of buffer");
+    }
+    Ok(())
+}
+
+fn kill_word(editor: &mut Editor, forward: bool) -> CommandResult {
+    if !kill(editor, |kill_ring, buffer| kill_ring.kill_word(buffer, forward))? {
+        editor.set_message(if forward { "End of buffer" } else { "Beginning of buffer" });
+    }
+    Ok(())
+}
+
+fn kill_sentence(editor: &mut Editor, _: &str) -> CommandResult {
+    if !kill(editor, |kill_ring, buffer| kill_ring.kill_sentence(buffer))? {
+        editor.set_message("End of buffer");
+    }
+    Ok(())
+}
+
+/// kill() runs `kill` on the current buffer if it can be changed, and notes the buffer so the
+/// next command can add to the same kill ring entry. Returns what `kill` did.
+fn kill<F>(editor: &mut Editor, kill: F) -> Result<bool, CommandError>
+where
+    F: FnOnce(&mut KillRing, &mut Buffer) -> bool,
+{
+    let id = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    buffer.check_writable()?;
+    let killed = kill(&mut editor.kill_ring, buffer);
+    editor.kill_buffer = Some(id);
+    Ok(killed)
+}
+
+fn copy_region(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current().ok_or(CommandError::NoBuffer)?;
+    if !editor.kill_ring.copy_region(buffer) {
+        editor.set_message("The mark is not set now, so there is no region");
+    }
+    Ok(())
+}
+
+fn yank(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    buffer.check_writable()?;
+    if !editor.kill_ring.yank(buffer) {
+        editor.set_message("Kill ring is empty");
+    }
+    Ok(())
+}
+
+fn yank_pop(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+    buffer.check_writable()?;
+    if !editor.kill_ring.yank_pop(buffer) {
+        editor.set_message("Previous command was not a yank");
+    }
+    Ok(())
+}
+
+fn undo(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.writable_buffer_or_error()?.undo() {
+        editor.set_message("No further undo information");
+    }
+    Ok(())
+}
+
+fn redo(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.writable_buffer_or_error()?.redo() {
+        editor.set_message("No further redo information");
+    }
+    Ok(())
+}
+
+/// search_pattern() is the argument to a search command, or the last text searched for if it is
+/// empty.
+fn search_pattern(editor: &mut Editor, command: &str, argument: &str) -> Result<String, CommandError> {
+    if !argument.is_empty() {
+        editor.last_search = Some(argument.to_string());
+    }
+
+    editor.last_search.clone().ok_or_else(|| invalid_argument(command, argument))
+}
+
+fn search_forward(editor: &mut Editor, argument: &str) -> CommandResult {
+    let pattern = search_pattern(editor, "search-forward", argument)?;
+    let buffer = editor.buffer_or_error()?;
+
+    match buffer.find(&pattern, buffer.get_point()) {
+        Some(found) => {
+            editor.push_jump();
+            editor.buffer_or_error()?.set_point(found + pattern.len());
+        }
+        None => editor.set_message(&format!("Search failed: {}", pattern)),
+    }
+    Ok(())
+}
+
+fn search_backward(editor: &mut Editor, argument: &str) -> CommandResult {
+    let pattern = search_pattern(editor, "search-backward", argument)?;
+    let buffer = editor.buffer_or_error()?;
+
+    match buffer.rfind(&pattern, buffer.get_point()) {
+        Some(found) => {
+            editor.push_jump();
+            editor.buffer_or_error()?.set_point(found);
+        }
+        None => editor.set_message(&format!("Search failed: {}", pattern)),
+    }
+    Ok(())
+}
+
+fn isearch_forward(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.start_isearch(false)
+}
+
+fn isearch_backward(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.start_isearch(true)
+}
+
+/// query_replace() asks for the text to replace its argument with, then starts replacing. When
+/// its argument answers that question, it is the replacement.
+fn query_replace(editor: &mut Editor, argument: &str) -> CommandResult {
+    match editor.take_query_replace_pattern() {
+        Some(pattern) => editor.start_query_replace(&pattern, argument),
+        None if argument.is_empty() => Err(invalid_argument("query-replace", argument)),
+        None => {
+            editor.ask_query_replace(argument);
+            Ok(())
+        }
+    }
+}
+
+fn occur(editor: &mut Editor, argument: &str) -> CommandResult {
+    if argument.is_empty() {
+        return Err(invalid_argument("occur", argument));
+    }
+
+    if editor.occur(argument)? == 0 {
+        editor.set_message(&format!("No matches for {}", argument));
+    }
+    Ok(())
+}
+
+fn occur_goto(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.occur_goto()? {
+        editor.set_message("No occurrence on this line");
+    }
+    Ok(())
+}
+
+fn dired(editor: &mut Editor, argument: &str) -> CommandResult {
+    let directory = match argument.trim() {
+        "" => editor.project_root(),
+        directory => PathBuf::from(directory),
+    };
+
+    editor.dired(&directory)?;
+    Ok(())
+}
+
+/// dired_target() is the path of the entry at the point of the current dired buffer, unless it
+/// is the directory above, saying why there is none in the message.
+fn dired_target(editor: &mut Editor) -> Option<PathBuf> {
+    match editor.dired_entry() {
+        Some((_, entry)) if entry.name == ".." => {
+            editor.set_message("Cannot change the directory above from here");
+            None
+        }
+        Some((path, _)) => Some(path),
+        None if editor.dired_directory().is_some() => {
+            editor.set_message("No file on this line");
+            None
+        }
+        None => {
+            editor.set_message("Not a dired buffer");
+            None
+        }
+    }
+}
+
+fn dired_find_file(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.dired_find_file()? {
+        editor.set_message("No file on this line");
+    }
+    Ok(())
+}
+
+fn dired_up_directory(editor: &mut Editor, _: &str) -> CommandResult {
+    match editor.dired_directory().and_then(|directory| directory.parent()).map(|parent| parent.to_path_buf()) {
+        Some(parent) => {
+            editor.dired(&parent)?;
+        }
+        None => editor.set_message("No directory above"),
+    }
+    Ok(())
+}
+
+fn dired_refresh(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.refresh_dired()? {
+        editor.set_message("Not a dired buffer");
+    }
+    Ok(())
+}
+
+/// dired_rename() renames the entry at the point to its argument, which is relative to the
+/// directory listed, asking for it if there is none.
+fn dired_rename(editor: &mut Editor, argument: &str) -> CommandResult {
+    let path = match dired_target(editor) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    if argument.trim().is_empty() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        editor.prompt(&format!("Rename {} to: ", name), "dired-rename", "dired-rename");
+        return Ok(());
+    }
+
+    let target = editor.dired_directory().unwrap().join(argument.trim());
+    std::fs::rename(&path, &target)?;
+    editor.refresh_dired()?;
+    Ok(())
+}
+
+/// dired_delete() deletes the entry at the point once its argument is `yes`, asking whether to
+/// if it has none. Directories are only deleted when they are empty.
+fn dired_delete(editor: &mut Editor, argument: &str) -> CommandResult {
+    let path = match dired_target(editor) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+    match argument.trim() {
+        "" => editor.prompt(&format!("Delete {}? (yes or no) ", name), "dired-delete", "dired-delete"),
+        "yes" => {
+            if path.is_dir() {
+                std::fs::remove_dir(&path)?;
+            } else {
+                std::fs::remove_file(&path)?;
+            }
+            editor.refresh_dired()?;
+            editor.set_message(&format!("Deleted {}", name));
+        }
+        _ => editor.set_message(&format!("Kept {}", name)),
+    }
+    Ok(())
+}
+
+fn dired_create_directory(editor: &mut Editor, argument: &str) -> CommandResult {
+    let directory = match editor.dired_directory() {
+        Some(directory) if !argument.trim().is_empty() => directory.join(argument.trim()),
+        Some(_) => return Err(invalid_argument("dired-create-directory", argument)),
+        None => {
+            editor.set_message("Not a dired buffer");
+            return Ok(());
+        }
+    };
+
+    std::fs::create_dir_all(&directory)?;
+    editor.refresh_dired()?;
+    Ok(())
+}
+
+fn dired_create_file(editor: &mut Editor, argument: &str) -> CommandResult {
+    let file = match editor.dired_directory() {
+        Some(directory) if !argument.trim().is_empty() => directory.join(argument.trim()),
+        Some(_) => return Err(invalid_argument("dired-create-file", argument)),
+        None => {
+            editor.set_message("Not a dired buffer");
+            return Ok(());
+        }
+    };
+
+    std::fs::OpenOptions::new().write(true).create_new(true).open(&file)?;
+    editor.refresh_dired()?;
+    Ok(())
+}
+
+/// find_file_in_project() visits the file of the project at its argument, or asks which file to
+/// visit if it has none, narrowing the project's files down as their name is typed.
+fn find_file_in_project(editor: &mut Editor, argument: &str) -> CommandResult {
+    let root = editor.project_root();
+    if !argument.is_empty() {
+        return visit_file(editor, "find-file-in-project", &root.join(argument).to_string_lossy(), OpenMode::Detect);
+    }
+
+    let files: Vec<String> = project::files(&root).iter().map(|path| path.to_string_lossy().into_owned()).collect();
+    if files.is_empty() {
+        editor.set_message(&format!("No files in {}", root.display()));
+        return Ok(());
+    }
+    editor.prompt(&format!("Find file in {}: ", root.display()), "find-file-in-project", "find-file-in-project");
+    editor.minibuffer_mut().unwrap().set_choices(files);
+    Ok(())
+}
+
+fn grep(editor: &mut Editor, argument: &str) -> CommandResult {
+    if argument.is_empty() {
+        return Err(invalid_argument("grep", argument));
+    }
+
+    let root = editor.project_root();
+    editor.start_grep(&root, argument);
+    Ok(())
+}
+
+fn grep_goto(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.grep_goto()? {
+        editor.set_message("No match on this line");
+    }
+    Ok(())
+}
+
+fn find_file(editor: &mut Editor, argument: &str) -> CommandResult {
+    visit_file(editor, "find-file", argument, OpenMode::Detect)
+}
+
+fn find_file_literally(editor: &mut Editor, argument: &str) -> CommandResult {
+    visit_file(editor, "find-file-literally", argument, OpenMode::Bytes)
+}
+
+/// visit_file() switches to the buffer visiting the file at `argument`, opening it as `mode` says
+/// if needed. A file that does not exist yet gets an empty buffer that will create it when saved.
+pub(super) fn visit_file(editor: &mut Editor, command: &str, argument: &str, mode: OpenMode) -> CommandResult {
+    let path = Path::new(argument.trim());
+    if argument.trim().is_empty() {
+        return Err(invalid_argument(command, argument));
+    }
+    if path.is_dir() {
+        editor.dired(path)?;
+        return Ok(());
+    }
+
+    let open = editor.buffers.iter().find(|(_, buffer)| buffer.path() == Some(path)).map(|(id, _)| id);
+    let id = match open {
+        Some(id) => id,
+        None => {
+            let mut buffer = match Buffer::open_with(path, mode) {
+                Ok(buffer) if has_recovery_data(path) => {
+                    editor.set_message("Auto-save data is newer than the file; use M-x recover-this-file to recover it");
+                    buffer
+                }
+                Ok(buffer) => buffer,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                    editor.set_message("(New file)");
+                    Buffer::visiting(path, String::new())
+                }
+                Err(error) => return Err(error.into()),
+            };
+            buffer.set_backup_settings(editor.backup_settings.clone());
+            let highlighter = highlighter_for(&mut buffer, path, &editor.syntax_rules);
+            let id = editor.buffers.add(buffer);
+            if let Some(highlighter) = highlighter {
+                editor.set_highlighter(id, highlighter);
+            }
+            editor.set_indenter(id, indenter_for(path));
+            editor.open_lsp_document(id)?;
+            editor.track_git_changes(id);
+            id
+        }
+    };
+
+    editor.show_buffer(id);
+    Ok(())
+}
+
+fn save_buffer(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    let path = buffer.path().ok_or(CommandError::NoFile)?.to_path_buf();
+
+    buffer.save()?;
+    let id = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    editor.refresh_git_changes(id);
+    editor.set_message(&format!("Wrote {}", path.display()));
+    Ok(())
+}
+
+fn revert_buffer(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    if buffer.path().is_none() {
+        return Err(CommandError::NoFile);
+    }
+
+    buffer.revert()?;
+    let name = buffer.name().to_string();
+    editor.set_message(&format!("Reverted {}", name));
+    Ok(())
+}
+
+fn recover_this_file(editor: &mut Editor, _: &str) -> CommandResult {
+    if editor.buffer_or_error()?.recover()? {
+        editor.set_message("Recovered auto-saved changes");
+    } else {
+        editor.set_message("No auto-save data to recover");
+    }
+    Ok(())
+}
+
+fn set_line_ending(editor: &mut Editor, argument: &str) -> CommandResult {
+    let line_ending = LineEnding::from_name(argument.trim()).ok_or_else(|| invalid_argument("set-line-ending", argument))?;
+
+    editor.writable_buffer_or_error()?.convert_line_endings(line_ending);
+    Ok(())
+}
+
+fn split_window(editor: &mut Editor, direction: Direction) -> CommandResult {
+    if editor.split_window(direction).is_none() {
+        editor.set_message("Window is too small for splitting");
+    }
+    Ok(())
+}
+
+fn other_window(editor: &mut Editor, _: &str) -> CommandResult {
+    let next = editor.windows.next(editor.windows.selected_id());
+    editor.select_window(next);
+    Ok(())
+}
+
+fn delete_window(editor: &mut Editor, _: &str) -> CommandResult {
+    let selected = editor.windows.selected_id();
+    if !editor.delete_window(selected) {
+        editor.set_message("Attempt to delete the only window");
+    }
+    Ok(())
+}
+
+/// resize_window() grows the selected window in `direction` by the count in `argument`, or one,
+/// times `sign`.
+fn resize_window(editor: &mut Editor, command: &str, argument: &str, direction: Direction, sign: isize) -> CommandResult {
+    let count: isize = match argument.trim() {
+        "" => 1,
+        count => count.parse().map_err(|_| invalid_argument(command, argument))?,
+    };
+
+    let selected = editor.windows.selected_id();
+    if !editor.windows.resize(selected, direction, count * sign) {
+        editor.set_message("Cannot resize the window");
+    }
+    Ok(())
+}
+
+fn display_line_numbers(editor: &mut Editor, argument: &str) -> CommandResult {
+    let line_numbers =
+        LineNumbers::from_name(argument.trim()).ok_or_else(|| invalid_argument("display-line-numbers", argument))?;
+
+    editor.windows.selected_mut().view_mut().set_line_numbers(line_numbers);
+    Ok(())
+}
+
+fn set_wrap(editor: &mut Editor, argument: &str) -> CommandResult {
+    let wrap = Wrap::from_name(argument.trim()).ok_or_else(|| invalid_argument("set-wrap", argument))?;
+
+    editor.windows.selected_mut().view_mut().set_wrap(wrap);
+    Ok(())
+}
+
+/// load_theme() switches to the built-in theme named by `argument`, or else loads the theme file
+/// it names.
+fn load_theme(editor: &mut Editor, argument: &str) -> CommandResult {
+    let name = argument.trim();
+    if name.is_empty() {
+        return Err(invalid_argument("load-theme", argument));
+    }
+
+    let theme = match Theme::builtin(name) {
+        Some(theme) => theme,
+        None if Path::new(name).exists() => Theme::load(name)?,
+        None => return Err(ThemeError::UnknownTheme(name.to_string()).into()),
+    };
+    editor.set_theme(theme);
+    Ok(())
+}
+
+/// load_syntax_rules() adds the rules in the file named by `argument`, and highlights the open
+/// buffers visiting files they match with them.
+fn load_syntax_rules(editor: &mut Editor, argument: &str) -> CommandResult {
+    let path = argument.trim();
+    if path.is_empty() {
+        return Err(invalid_argument("load-syntax-rules", argument));
+    }
+
+    let rules = SyntaxRules::load(path)?;
+    let matching: Vec<_> =
+        editor.buffers.iter().filter(|(_, buffer)| buffer.path().is_some_and(|path| rules.matches(path))).map(|(id, _)| id).collect();
+    editor.add_syntax_rules(rules);
+    for id in matching {
+        let buffer = editor.buffers.get_mut(id).unwrap();
+        let path = buffer.path().unwrap().to_path_buf();
+        if let Some(highlighter) = highlighter_for(buffer, &path, &editor.syntax_rules) {
+            editor.set_highlighter(id, highlighter);
+        }
+    }
+    Ok(())
+}
+
+fn goto_matching_bracket(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.buffer_or_error()?;
+
+    match editor.matching_bracket() {
+        Some((_, matching)) => editor.buffer_or_error()?.set_point(matching),
+        None => editor.set_message("No matching bracket"),
+    }
+    Ok(())
+}
+
+fn next_diagnostic(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    let diagnostic = buffer.next_diagnostic(buffer.get_point());
+    goto_diagnostic(editor, diagnostic)
+}
+
+fn previous_diagnostic(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    let diagnostic = buffer.previous_diagnostic(buffer.get_point());
+    goto_diagnostic(editor, diagnostic)
+}
+
+/// goto_diagnostic() moves the point to the start of `diagnostic` and shows what it says.
+fn goto_diagnostic(editor: &mut Editor, diagnostic: Option<Diagnostic>) -> CommandResult {
+    let diagnostic = match diagnostic {
+        Some(diagnostic) => diagnostic,
+        None => {
+            editor.set_message("No more diagnostics");
+            return Ok(());
+        }
+    };
+
+    editor.buffer_or_error()?.set_point(diagnostic.range.start);
+    match diagnostic.source {
+        Some(source) => editor.set_message(&format!("{}: {} [{}]", diagnostic.severity, diagnostic.message, source)),
+        None => editor.set_message(&format!("{}: {}", diagnostic.severity, diagnostic.message)),
+    }
+    Ok(())
+}
+
+fn lsp_start(editor: &mut Editor, argument: &str) -> CommandResult {
+    let path = editor.buffer_or_error()?.path().ok_or(CommandError::NoFile)?.to_path_buf();
+    let config = match lsp::language_id(&path) {
+        Some(language_id) if !argument.trim().is_empty() => ServerConfig::from_command_line(&language_id, argument),
+        Some(_) => ServerConfig::for_path(&path),
+        None => None,
+    };
+
+    match config {
+        Some(config) => {
+            editor.start_language_server(&config)?;
+            editor.set_message(&format!("Started {} for {}", config.command, config.language_id));
+            Ok(())
+        }
+        None => Err(invalid_argument("lsp-start", argument)),
+    }
+}
+
+fn lsp_stop(editor: &mut Editor, _: &str) -> CommandResult {
+    let path = editor.buffer_or_error()?.path().ok_or(CommandError::NoFile)?.to_path_buf();
+    let language_id = lsp::language_id(&path).unwrap_or_default();
+
+    if !editor.stop_language_server(&language_id)? {
+        editor.set_message("No language server is running for this buffer");
+    }
+    Ok(())
+}
+
+fn find_lsp_locations(editor: &mut Editor, method: &str, capability: &str, params: Value) -> CommandResult {
+    if !editor.find_lsp_locations(method, capability, params)? {
+        editor.set_message("No language server can find that for this buffer");
+    }
+    Ok(())
+}
+
+fn bookmark_set(editor: &mut Editor, argument: &str) -> CommandResult {
+    let name = match argument.trim() {
+        "" => editor.buffer_or_error()?.name().to_string(),
+        name => name.to_string(),
+    };
+
+    editor.set_bookmark(&name)?;
+    editor.set_message(&format!("Set bookmark {}", name));
+    Ok(())
+}
+
+fn bookmark_jump(editor: &mut Editor, argument: &str) -> CommandResult {
+    match editor.jump_to_bookmark(argument.trim())? {
+        true => Ok(()),
+        false => Err(invalid_argument("bookmark-jump", argument)),
+    }
+}
+
+fn bookmark_delete(editor: &mut Editor, argument: &str) -> CommandResult {
+    match editor.delete_bookmark(argument.trim())? {
+        true => Ok(()),
+        false => Err(invalid_argument("bookmark-delete", argument)),
+    }
+}
+
+fn step_location(editor: &mut Editor, step: isize) -> CommandResult {
+    match editor.step_location(step)? {
+        true => {
+            let (locations, index) = editor.locations();
+            let message = format!("{} of {} places", index + 1, locations.len());
+            editor.set_message(&message);
+        }
+        false => editor.set_message("No more places"),
+    }
+    Ok(())
+}
+
+fn set_tab_width(editor: &mut Editor, argument: &str) -> CommandResult {
+    let width = match argument.trim().parse::<usize>() {
+        Ok(width) if width > 0 => width,
+        _ => return Err(invalid_argument("set-tab-width", argument)),
+    };
+
+    editor.windows.selected_mut().view_mut().set_tab_width(width);
+    Ok(())
+}
+
+fn set_indent_style(editor: &mut Editor, argument: &str) -> CommandResult {
+    let use_tabs = match argument.trim() {
+        "tabs" => true,
+        "spaces" => false,
+        _ => return Err(invalid_argument("set-indent-style", argument)),
+    };
+
+    let buffer = editor.buffer_or_error()?;
+    let settings = IndentSettings { use_tabs, ..buffer.indent_settings() };
+    buffer.set_indent_settings(settings);
+    Ok(())
+}
+
+fn set_indent_width(editor: &mut Editor, argument: &str) -> CommandResult {
+    let width = match argument.trim().parse::<usize>() {
+        Ok(width) if width > 0 => width,
+        _ => return Err(invalid_argument("set-indent-width", argument)),
+    };
+
+    let buffer = editor.buffer_or_error()?;
+    let settings = IndentSettings { width, ..buffer.indent_settings() };
+    buffer.set_indent_settings(settings);
+    Ok(())
+}
+
+/// move_lines() moves the line of the point, or the lines in the region, past the line after them
+/// or before them.
+fn move_lines(editor: &mut Editor, down: bool) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    let range = buffer.region().unwrap_or_else(|| buffer.get_point()..buffer.get_point());
+    if !buffer.move_lines(range, down) {
+        editor.set_message(if down { "No line below" } else { "No line above" });
+    }
+    Ok(())
+}
+
+fn join_lines(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    let range = buffer.region().unwrap_or_else(|| buffer.get_point()..buffer.get_point());
+    if !buffer.join_lines(range) {
+        editor.set_message("No line to join");
+    }
+    Ok(())
+}
+
+/// transpose() swaps the things around the point of the current buffer with `swap`.
+fn transpose(editor: &mut Editor, swap: fn(&mut Buffer) -> bool) -> CommandResult {
+    if !swap(editor.writable_buffer_or_error()?) {
+        editor.set_message("Nothing to transpose");
+    }
+    Ok(())
+}
+
+fn change_case_word(editor: &mut Editor, case: Case) -> CommandResult {
+    if !editor.writable_buffer_or_error()?.change_case_word(case) {
+        editor.set_message("End of buffer");
+    }
+    Ok(())
+}
+
+fn change_case_region(editor: &mut Editor, case: Case) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    match buffer.region() {
+        Some(region) => {
+            buffer.change_case(region, case);
+        }
+        None => editor.set_message("The mark is not set now, so there is no region"),
+    }
+    Ok(())
+}
+
+fn set_fill_column(editor: &mut Editor, argument: &str) -> CommandResult {
+    let fill_column = match argument.trim().parse::<usize>() {
+        Ok(fill_column) if fill_column > 0 => fill_column,
+        _ => return Err(invalid_argument("set-fill-column", argument)),
+    };
+
+    editor.buffer_or_error()?.set_fill_column(fill_column);
+    editor.set_message(&format!("Fill column set to {}", fill_column));
+    Ok(())
+}
+
+fn auto_fill_mode(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    let auto_fill = !buffer.auto_fill();
+    buffer.set_auto_fill(auto_fill);
+    editor.set_message(if auto_fill { "Auto-fill enabled" } else { "Auto-fill disabled" });
+    Ok(())
+}
+
+fn narrow_to_region(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    match buffer.region() {
+        Some(region) => {
+            buffer.narrow(region);
+            buffer.clear_mark();
+            rehighlight_current_buffer(editor);
+        }
+        None => editor.set_message("The mark is not set now, so there is no region"),
+    }
+    Ok(())
+}
+
+fn widen(editor: &mut Editor, _: &str) -> CommandResult {
+    match editor.buffer_or_error()?.widen() {
+        true => rehighlight_current_buffer(editor),
+        false => editor.set_message("The buffer is not narrowed"),
+    }
+    Ok(())
+}
+
+/// shell_command_on_region() filters the region through the shell command `argument`, replacing
+/// it in a single edit with what the command writes to stdout, and leaves the point and the mark
+/// around the new text. A command that fails leaves the region as it was, and what it wrote to
+/// stderr is shown either way.
+fn shell_command_on_region(editor: &mut Editor, argument: &str) -> CommandResult {
+    let command_line = argument.trim();
+    if command_line.is_empty() {
+        return Err(invalid_argument("shell-command-on-region", argument));
+    }
+    let directory = editor.default_directory();
+    let buffer = editor.writable_buffer_or_error()?;
+    let region = match buffer.region() {
+        Some(region) => region,
+        None => {
+            editor.set_message("The mark is not set now, so there is no region");
+            return Ok(());
+        }
+    };
+
+    let output = run_shell_command(command_line, &buffer.slice(region.clone()), &directory)?;
+    if let Some(failure) = output.failure() {
+        editor.set_message(&failure);
+        return Ok(());
+    }
+    let text = LineEnding::normalize(&output.stdout);
+    buffer.replace_range(region.clone(), &text);
+    buffer.set_point(region.start);
+    buffer.set_mark(region.start + text.len());
+    if let Some(line) = output.stderr.lines().find(|line| !line.trim().is_empty()) {
+        editor.set_message(line.trim());
+    }
+    Ok(())
+}
+
+fn async_shell_command(editor: &mut Editor, argument: &str) -> CommandResult {
+    let command_line = argument.trim();
+    if command_line.is_empty() {
+        return Err(invalid_argument("async-shell-command", argument));
+    }
+
+    let directory = editor.default_directory();
+    let id = editor.start_job(command_line, &directory, ASYNC_SHELL_BUFFER)?;
+    editor.set_message(&format!("Job {} started", id));
+    Ok(())
+}
+
+fn kill_job(editor: &mut Editor, argument: &str) -> CommandResult {
+    let id = match argument.trim() {
+        "" => editor.buffers.current_id().and_then(|buffer| editor.buffer_job(buffer)),
+        number => Some(JobId(number.parse().map_err(|_| invalid_argument("kill-job", argument))?)),
+    };
+
+    match id {
+        Some(id) if editor.kill_job(id) => editor.set_message(&format!("Killed job {}", id)),
+        Some(id) => editor.set_message(&format!("Job {} is not running", id)),
+        None => editor.set_message("No job is writing to this buffer"),
+    }
+    Ok(())
+}
+
+fn compile(editor: &mut Editor, argument: &str) -> CommandResult {
+    let command_line = match (argument.trim(), editor.compile_command()) {
+        ("", Some(last)) => last.to_string(),
+        ("", None) => return Err(invalid_argument("compile", argument)),
+        (command_line, _) => command_line.to_string(),
+    };
+
+    let root = editor.project_root();
+    editor.compile(&command_line, &root)?;
+    Ok(())
+}
+
+fn compile_goto_error(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.compile_goto_error()? {
+        editor.set_message("No error on this line");
+    }
+    Ok(())
+}
+
+fn step_error(editor: &mut Editor, forward: bool) -> CommandResult {
+    if editor.compile_command().is_none() {
+        editor.set_message("No build has been run");
+    } else if !editor.next_error(forward)? {
+        editor.set_message(if forward { "No more errors" } else { "No earlier errors" });
+    }
+    Ok(())
+}
+
+fn term(editor: &mut Editor, argument: &str) -> CommandResult {
+    let program = match argument.trim() {
+        "" => std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()),
+        program => program.to_string(),
+    };
+
+    let directory = editor.default_directory();
+    editor.start_terminal(&program, &directory)?;
+    Ok(())
+}
+
+fn git_gutter_mode(editor: &mut Editor, _: &str) -> CommandResult {
+    let id = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    if editor.untrack_git_changes(id) {
+        editor.set_message("Git gutter disabled");
+    } else if editor.track_git_changes(id) {
+        editor.set_message("Git gutter enabled");
+    } else {
+        editor.set_message("The buffer's file is not in a git commit");
+    }
+    Ok(())
+}
+
+fn step_hunk(editor: &mut Editor, forward: bool) -> CommandResult {
+    if !editor.next_hunk(forward)? {
+        editor.set_message(if forward { "No more changes" } else { "No earlier changes" });
+    }
+    Ok(())
+}
+
+fn revert_hunk(editor: &mut Editor, _: &str) -> CommandResult {
+    if editor.revert_hunk()? {
+        editor.set_message("Reverted the change");
+    } else {
+        editor.set_message("No change at the point");
+    }
+    Ok(())
+}
+
+fn git_blame(editor: &mut Editor, _: &str) -> CommandResult {
+    let modified = editor.buffer_or_error()?.is_modified();
+    editor.blame()?;
+    if modified {
+        editor.set_message("The buffer has unsaved changes; the blame is of the file as last saved");
+    }
+    Ok(())
+}
+
+fn blame_goto_line(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.blame_goto_line()? {
+        editor.set_message("No line listed here");
+    }
+    Ok(())
+}
+
+fn diff_buffer_with_file(editor: &mut Editor, _: &str) -> CommandResult {
+    if !editor.diff_buffer_with_file()? {
+        editor.set_message("No unsaved changes");
+    }
+    Ok(())
+}
+
+fn diff_buffers(editor: &mut Editor, argument: &str) -> CommandResult {
+    let old = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    let new = editor.buffers.find(argument.trim()).ok_or_else(|| invalid_argument("diff-buffers", argument))?;
+    if !editor.diff_buffers(old, new)? {
+        editor.set_message("No differences");
+    }
+    Ok(())
+}
+
+fn view_messages(editor: &mut Editor, _: &str) -> CommandResult {
+    let id = editor.buffers.messages();
+    editor.show_buffer(id);
+    let buffer = editor.buffers.get_mut(id).unwrap();
+    let end = buffer.len();
+    buffer.set_point(end);
+    Ok(())
+}
+
+/// rehighlight_current_buffer() gives the current buffer a new highlighter once it has been
+/// narrowed or widened, as the old one only knows the lines that were accessible before. A buffer
+/// that is not visiting a file is left without one.
+fn rehighlight_current_buffer(editor: &mut Editor) {
+    let id = match editor.buffers.current_id() {
+        Some(id) => id,
+        None => return,
+    };
+    let buffer = editor.buffers.get_mut(id).unwrap();
+    let highlighter = match buffer.path().map(Path::to_path_buf) {
+        Some(path) => highlighter_for(buffer, &path, &editor.syntax_rules),
+        None => None,
+    };
+    match highlighter {
+        Some(highlighter) => editor.set_highlighter(id, highlighter),
+        None => {
+            editor.remove_highlighter(id);
+        }
+    }
+}
+
+/// sort_lines() sorts the lines in the region, or the whole buffer when there is no region.
+fn sort_lines(editor: &mut Editor, sort: SortLines) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    let range = buffer.region().unwrap_or(0..buffer.len());
+    buffer.sort_lines(range, sort);
+    Ok(())
+}
+
+fn delete_duplicate_lines(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    let range = buffer.region().unwrap_or(0..buffer.len());
+    let deleted = buffer.delete_duplicate_lines(range);
+
+    let plural = if deleted == 1 { "" } else { "s" };
+    editor.set_message(&format!("Deleted {} duplicate line{}", deleted, plural));
+    Ok(())
+}
+
+/// shift_region_lines() indents the lines in the region, or the point's line when there is no
+/// region, by `levels` more levels of the buffer's indentation.
+fn shift_region_lines(editor: &mut Editor, levels: isize) -> CommandResult {
+    let tab_width = editor.view().tab_width();
+    let buffer = editor.writable_buffer_or_error()?;
+    let range = buffer.region().unwrap_or_else(|| buffer.get_point()..buffer.get_point());
+    let shift = ShiftIndentation {
+        levels,
+        settings: buffer.indent_settings(),
+        tab_width,
+    };
+
+    buffer.apply_transform_to_lines(range, &shift);
+    Ok(())
+}
+
+/// comment_region_lines() comments the lines in the region, or the point's line when there is no
+/// region, out or back in with the comment syntax of the buffer's file type.
+fn comment_region_lines(editor: &mut Editor, action: CommentAction) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    let syntax = match buffer.path().and_then(CommentSyntax::for_path) {
+        Some(syntax) => syntax,
+        None => {
+            editor.set_message("No comment syntax for this buffer");
+            return Ok(());
+        }
+    };
+
+    let range = buffer.region().unwrap_or_else(|| buffer.get_point()..buffer.get_point());
+    buffer.apply_transform_to_lines(range, &CommentLines { syntax, action });
+    Ok(())
+}
+
+fn transform_buffer(editor: &mut Editor, transform: &dyn SaveTransform) -> CommandResult {
+    editor.writable_buffer_or_error()?.apply_transform(transform);
+    Ok(())
+}
+
+/// transform_region_lines() applies `transform` to the lines in the region, or to the whole buffer
+/// when there is no region.
+fn transform_region_lines(editor: &mut Editor, transform: &dyn SaveTransform) -> CommandResult {
+    let buffer = editor.writable_buffer_or_error()?;
+    match buffer.region() {
+        Some(region) => buffer.apply_transform_to_lines(region, transform),
+        None => buffer.apply_transform(transform),
+    };
+    Ok(())
+}
+
+fn write_file(editor: &mut Editor, argument: &str) -> CommandResult {
+    let path = Path::new(argument.trim());
+    let name = match path.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => return Err(invalid_argument("write-file", argument)),
+    };
+
+    let id = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    editor.buffer_or_error()?.save_as(path)?;
+    editor.buffers.rename(id, &name);
+    editor.refresh_git_changes(id);
+    editor.set_message(&format!("Wrote {}", path.display()));
+    Ok(())
+}
+
+/// parse_count() reads an argument numbering a line, column or character, which counts from one.
+fn parse_count(command: &str, argument: &str) -> Result<usize, CommandError> {
+    match argument.trim().parse() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(invalid_argument(command, argument)),
+    }
+}
+
+fn goto_line(editor: &mut Editor, argument: &str) -> CommandResult {
+    let (line, column) = match argument.trim().split_once(':') {
+        Some((line, column)) => (parse_count("goto-line", line)?, parse_count("goto-line", column)?),
+        None => (parse_count("goto-line", argument)?, 1),
+    };
+
+    editor.push_jump();
+    editor.goto_line_column(line - 1, column - 1)
+}
+
+fn goto_column(editor: &mut Editor, argument: &str) -> CommandResult {
+    let column = parse_count("goto-column", argument)?;
+    let buffer = editor.buffer_or_error()?;
+    let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+    editor.goto_line_column(line, column - 1)
+}
+
+fn goto_char(editor: &mut Editor, argument: &str) -> CommandResult {
+    let character = parse_count("goto-char", argument)?;
+
+    editor.push_jump();
+    let buffer = editor.buffer_or_error()?;
+    let offset = buffer.char_to_byte(character - 1);
+    buffer.set_point(offset);
+    Ok(())
+}
+
+fn start_macro(editor: &mut Editor, _: &str) -> CommandResult {
+    match editor.macros.start_recording() {
+        true => editor.set_message("Defining keyboard macro..."),
+        false => editor.set_message("Already defining keyboard macro"),
+    }
+    Ok(())
+}
+
+fn end_macro(editor: &mut Editor, _: &str) -> CommandResult {
+    match editor.macros.stop_recording() {
+        true => editor.set_message("Keyboard macro defined"),
+        false => editor.set_message("Not defining keyboard macro"),
+    }
+    Ok(())
+}
+
+fn call_macro(editor: &mut Editor, argument: &str) -> CommandResult {
+    let count = match argument.trim() {
+        "" => 1,
+        count => count.parse().map_err(|_| invalid_argument("call-macro", argument))?,
+    };
+
+    match editor.macros.last() {
+        Some(keys) => {
+            let keys = keys.to_vec();
+            editor.play_macro(&keys, count)
+        }
+        None => {
+            editor.set_message("No keyboard macro defined");
+            Ok(())
+        }
+    }
+}
+
+fn name_macro(editor: &mut Editor, argument: &str) -> CommandResult {
+    let name = argument.trim();
+    if name.is_empty() || !editor.macros.name_last(name) {
+        return Err(invalid_argument("name-macro", argument));
+    }
+    Ok(())
+}
+
+fn call_named_macro(editor: &mut Editor, argument: &str) -> CommandResult {
+    let keys = match editor.macros.get(argument.trim()) {
+        Some(keys) => keys.to_vec(),
+        None => return Err(invalid_argument("call-named-macro", argument)),
+    };
+
+    editor.play_macro(&keys, 1)
+}
+
+fn execute_extended_command(editor: &mut Editor, argument: &str) -> CommandResult {
+    editor.call_interactively(argument.trim())
+}
+
+fn save_buffers_kill_editor(editor: &mut Editor, _: &str) -> CommandResult {
+    for (_, buffer) in editor.buffers.iter_mut() {
+        if buffer.is_modified() && buffer.path().is_some() {
+            buffer.save()?;
+        }
+    }
+
+    editor.save_bookmarks()?;
+    editor.stop_language_servers();
+    editor.request_quit();
+    Ok(())
+}
+
+fn toggle_vi_mode(editor: &mut Editor, _: &str) -> CommandResult {
+    let id = editor.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+    let enabled = editor.vi_states.contains_key(&id);
+    editor.set_vi_mode(id, !enabled);
+    Ok(())
+}
+
+fn toggle_read_only(editor: &mut Editor, _: &str) -> CommandResult {
+    let buffer = editor.buffer_or_error()?;
+    let read_only = !buffer.is_read_only();
+
+    buffer.set_read_only(read_only);
+    editor.set_message(if read_only { "Read-only mode enabled" } else { "Read-only mode disabled" });
+    Ok(())
+}
+
+fn dabbrev_expand(editor: &mut Editor, _: &str) -> CommandResult {
+    editor.writable_buffer_or_error()?;
+    if !editor.dabbrev.expand(&mut editor.buffers) {
+        editor.set_message("No further dynamic expansion");
+    }
+    Ok(())
+}
+
+fn toggle_auto_complete(editor: &mut Editor, _: &str) -> CommandResult {
+    let auto_complete = !editor.auto_complete();
+
+    editor.set_auto_complete(auto_complete);
+    editor.set_message(if auto_complete { "Auto-complete enabled" } else { "Auto-complete disabled" });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use command::CommandError;
+    use diagnostic::{Diagnostic, Severity};
+    use editor::diff::DIFF_BUFFER;
+    use editor::git::BLAME_BUFFER;
+    use editor::jobs::ASYNC_SHELL_BUFFER;
+    use editor::{Editor, MESSAGES_BUFFER, SCRATCH_BUFFER};
+    use indent::OpenerIndent;
+    use keymap::Key;
+    use status_line::{StatusInfo, StatusLine};
+    use syntax::{RuleHighlighter, SyntaxRules};
+    use theme::{ThemeError, DIFF_ADDED, DIFF_FILE_HEADER, DIFF_HUNK_HEADER, DIFF_REMOVED, MATCHING_BRACKET};
+
+    fn editor_with_text(text: &str) -> Editor {
+        let mut editor = Editor::new();
+        editor.execute("insert-char", text).unwrap();
+        editor.current_buffer_mut().unwrap().set_point(0);
+
+        editor
+    }
+
+    fn contents(editor: &Editor) -> String {
+        editor.current_buffer().unwrap().to_string()
+    }
+
+    fn point(editor: &Editor) -> usize {
+        editor.current_buffer().unwrap().get_point()
+    }
+
+    #[test]
+    fn insert_and_delete_characters() {
+        let mut editor = editor_with_text("héllo");
+
+        editor.execute("forward-char", "").unwrap();
+        editor.execute("forward-char", "").unwrap();
+        assert_eq!(point(&editor), 3);
+
+        editor.execute("delete-backward", "").unwrap();
+        editor.execute("delete-forward", "").unwrap();
+        editor.execute("insert-char", "E").unwrap();
+        assert_eq!(contents(&editor), "hElo");
+
+        assert!(matches!(
+            editor.execute("insert-char", ""),
+            Err(CommandError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn move_between_lines() {
+        let mut editor = editor_with_text("one\ntwo\nthree");
+
+        editor.execute("next-line", "").unwrap();
+        editor.execute("end-of-line", "").unwrap();
+        assert_eq!(point(&editor), 7);
+
+        editor.execute("previous-line", "").unwrap();
+        editor.execute("beginning-of-line", "").unwrap();
+        assert_eq!(point(&editor), 0);
+    }
+
+    #[test]
+    fn goto_a_line() {
+        let mut editor = editor_with_text("one\ntwo\nthree");
+
+        editor.execute("goto-line", "3").unwrap();
+        assert_eq!(point(&editor), 8);
+
+        editor.execute("goto-line", "100").unwrap();
+        assert_eq!(point(&editor), 8);
+
+        editor.execute("goto-line", "2:3").unwrap();
+        assert_eq!(point(&editor), 6);
+
+        assert!(editor.execute("goto-line", "0").is_err());
+        assert!(editor.execute("goto-line", "three").is_err());
+        assert!(editor.execute("goto-line", "2:0").is_err());
+    }
+
+    #[test]
+    fn goto_a_column_and_a_character() {
+        let mut editor = editor_with_text("one\ncafé au lait\nthree");
+        let test_cases = [
+            ("column on the line", "goto-column", "2", "C-n", 5),
+            ("column after a wide character", "goto-column", "6", "C-n", 10),
+            ("column past the end of the line", "goto-column", "100", "C-n", 17),
+            ("first character", "goto-char", "1", "C-a", 0),
+            ("character after a wide one", "goto-char", "10", "C-a", 10),
+            ("character past the end", "goto-char", "100", "C-a", 23),
+        ];
+
+        for (name, command, argument, keys, expected) in test_cases.iter() {
+            editor.execute("beginning-of-buffer", "").unwrap();
+            press(&mut editor, keys);
+            editor.execute(command, argument).unwrap();
+            assert_eq!(point(&editor), *expected, "Test case: \"{}\" failed.", name);
+        }
+        assert!(editor.execute("goto-char", "0").is_err());
+    }
+
+    #[test]
+    fn visit_a_file_at_a_line_and_column() {
+        let directory = std::env::temp_dir().join(format!("one-file-location-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        std::fs::write(directory.join("notes:2"), "a file named like a location").unwrap();
+        let file = |name: &str| directory.join(name).to_string_lossy().into_owned();
+
+        let test_cases = [
+            ("line and column", file("main.rs:2:5"), "main.rs", 16),
+            ("line", file("main.rs:2"), "main.rs", 12),
+            ("past the end", file("main.rs:100:100"), "main.rs", 25),
+            ("a file named like a location", file("notes:2"), "notes:2", 0),
+        ];
+
+        for (name, argument, visited, expected) in test_cases.iter() {
+            let mut editor = Editor::new();
+            editor.visit_file_argument(argument).unwrap();
+            let path = editor.current_buffer().unwrap().path().map(|path| path.to_path_buf());
+            assert_eq!((path, point(&editor)), (Some(directory.join(visited)), *expected), "Test case: \"{}\" failed.", name);
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn kill_and_yank() {
+        let mut editor = editor_with_text("one\ntwo");
+
+        editor.execute("kill-line", "").unwrap();
+        editor.execute("kill-line", "").unwrap();
+        assert_eq!(contents(&editor), "two");
+
+        editor.execute("end-of-line", "").unwrap();
+        editor.execute("yank", "").unwrap();
+        assert_eq!(contents(&editor), "twoone\n");
+
+        editor.execute("set-mark", "").unwrap();
+        editor.execute("goto-line", "1").unwrap();
+        editor.execute("kill-region", "").unwrap();
+        assert_eq!(contents(&editor), "");
+    }
+
+    #[test]
+    fn only_consecutive_kills_make_one_entry() {
+        let mut editor = editor_with_text("one two three");
+
+        editor.execute("kill-word", "").unwrap();
+        editor.execute("kill-word", "").unwrap();
+        editor.execute("forward-char", "").unwrap();
+        editor.execute("backward-char", "").unwrap();
+        editor.execute("kill-word", "").unwrap();
+        assert_eq!(editor.kill_ring().entries().collect::<Vec<_>>(), [" three", "one two"]);
+
+        let other = editor.buffers.create("other");
+        editor.buffers.get_mut(other).unwrap().insert_str("four five");
+        editor.buffers.get_mut(other).unwrap().set_point(0);
+        editor.show_buffer(other);
+        editor.execute("kill-word", "").unwrap();
+        assert_eq!(editor.kill_ring().entries().collect::<Vec<_>>(), ["four", " three", "one two"]);
+    }
+
+    #[test]
+    fn undo_and_redo_each_command() {
+        let mut editor = editor_with_text("one\ntwo");
+
+        editor.execute("kill-line", "").unwrap();
+        press(&mut editor, "M-> x y");
+        assert_eq!(contents(&editor), "\ntwoxy");
+
+        press(&mut editor, "C-_");
+        assert_eq!((contents(&editor), point(&editor)), ("\ntwo".to_string(), 4));
+        editor.execute("undo", "").unwrap();
+        assert_eq!((contents(&editor), point(&editor)), ("one\ntwo".to_string(), 0));
+        press(&mut editor, "M-_");
+        assert_eq!(contents(&editor), "\ntwo");
+
+        press(&mut editor, "C-x u C-x u");
+        assert_eq!(contents(&editor), "");
+        editor.execute("undo", "").unwrap();
+        assert_eq!(editor.message(), Some("No further undo information"));
+
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.set_read_only(true);
+        assert!(editor.execute("redo", "").is_err());
+    }
+
+    #[test]
+    fn search_forward_and_backward() {
+        let mut editor = editor_with_text("a cat, a cat, a dog");
+
+        editor.execute("search-forward", "cat").unwrap();
+        assert_eq!(point(&editor), 5);
+        editor.execute("search-forward", "").unwrap();
+        assert_eq!(point(&editor), 12);
+
+        editor.execute("search-forward", "").unwrap();
+        assert_eq!(point(&editor), 12);
+        assert_eq!(editor.message(), Some("Search failed: cat"));
+
+        editor.execute("search-backward", "a ").unwrap();
+        assert_eq!(point(&editor), 7);
+    }
+
+    #[test]
+    fn search_without_a_previous_search() {
+        let mut editor = editor_with_text("text");
+
+        assert!(editor.execute("search-forward", "").is_err());
+    }
+
+    #[test]
+    fn emacs_keys_edit_the_buffer() {
+        let mut editor = editor_with_text("one two");
+
+        for keys in ["M-f", "C-SPC", "C-e", "C-w", "C-a", "C-y", "RET"] {
+            for key in Key::parse_sequence(keys).unwrap() {
+                editor.handle_key(key).unwrap();
+            }
+        }
+
+        assert_eq!(contents(&editor), " two\n one");
+    }
+
+    fn press(editor: &mut Editor, keys: &str) {
+        for key in Key::parse_sequence(keys).unwrap() {
+            editor.handle_key(key).unwrap();
+        }
+    }
+
+    #[test]
+    fn record_and_play_a_macro() {
+        let mut editor = editor_with_text("a\nb\nc\nd");
+
+        press(&mut editor, "C-x ( - SPC C-n C-a C-x )");
+        assert_eq!(contents(&editor), "- a\nb\nc\nd");
+        assert_eq!(editor.macros().last().unwrap().len(), 4);
+
+        press(&mut editor, "C-x e");
+        editor.execute("call-macro", "2").unwrap();
+        assert_eq!(contents(&editor), "- a\n- b\n- c\n- d");
+    }
+
+    #[test]
+    fn play_a_named_macro() {
+        let mut editor = Editor::new();
+        press(&mut editor, "C-x ( x C-x )");
+
+        editor.execute("name-macro", "ex").unwrap();
+        editor.execute("call-named-macro", "ex").unwrap();
+        assert_eq!(contents(&editor), "xx");
+
+        assert!(editor.execute("call-named-macro", "why").is_err());
+    }
+
+    #[test]
+    fn a_macro_cannot_call_a_macro() {
+        let mut editor = Editor::new();
+        press(&mut editor, "C-x ( C-x e C-x )");
+
+        assert!(matches!(editor.execute("call-macro", ""), Err(CommandError::MacroRecursion)));
+        assert!(!editor.macros().is_playing());
+    }
+
+    #[test]
+    fn find_write_and_save_files() {
+        let path = std::env::temp_dir().join(format!("one-find-file-{}.txt", std::process::id()));
+        let other = std::env::temp_dir().join(format!("one-write-file-{}.txt", std::process::id()));
+        let mut editor = Editor::new();
+
+        editor.execute("find-file", path.to_str().unwrap()).unwrap();
+        assert_eq!(editor.message(), Some("(New file)"));
+        editor.execute("insert-char", "new").unwrap();
+        editor.execute("save-buffer", "").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+
+        editor.execute("write-file", other.to_str().unwrap()).unwrap();
+        assert_eq!(editor.current_buffer().unwrap().path(), Some(other.as_path()));
+        assert_eq!(std::fs::read_to_string(&other).unwrap(), "new");
+
+        let count = editor.buffers().len();
+        editor.execute("find-file", other.to_str().unwrap()).unwrap();
+        assert_eq!(editor.buffers().len(), count);
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&other).unwrap();
+    }
+
+    #[test]
+    fn edits_to_a_read_only_buffer_fail() {
+        let mut editor = editor_with_text("log");
+        editor.execute("toggle-read-only", "").unwrap();
+
+        let error = editor.execute("insert-char", "x").unwrap_err();
+        assert_eq!(error.to_string(), "Buffer is read-only: *scratch*");
+        assert!(editor.execute("kill-line", "").is_err());
+        editor.execute("end-of-line", "").unwrap();
+
+        assert_eq!(contents(&editor), "log");
+        assert_eq!(point(&editor), 3);
+    }
+
+    #[test]
+    fn kill_the_editor_after_saving() {
+        let path = std::env::temp_dir().join(format!("one-kill-editor-{}.txt", std::process::id()));
+        let mut editor = Editor::new();
+        editor.execute("find-file", path.to_str().unwrap()).unwrap();
+        editor.execute("insert-char", "unsaved").unwrap();
+        assert!(!editor.should_quit());
+
+        editor.execute("save-buffers-kill-editor", "").unwrap();
+
+        assert!(editor.should_quit());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "unsaved");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn scroll_by_screens() {
+        let text: Vec<String> = (0..50).map(|line| line.to_string()).collect();
+        let mut editor = editor_with_text(&text.join("\n"));
+        editor.layout_windows(80, 11);
+
+        editor.execute("scroll-up-command", "").unwrap();
+        assert_eq!(editor.view().top_line(), 8);
+        assert_eq!(point(&editor), "0\n1\n2\n3\n4\n5\n6\n7\n".len());
+
+        editor.execute("scroll-down-command", "").unwrap();
+        assert_eq!(editor.view().top_line(), 0);
+        editor.execute("scroll-down-command", "").unwrap();
+        assert_eq!(editor.message(), Some("Beginning of buffer"));
+    }
+
+    #[test]
+    fn save_a_buffer_without_a_file() {
+        let mut editor = editor_with_text("text");
+
+        assert!(matches!(editor.execute("save-buffer", ""), Err(CommandError::NoFile)));
+    }
+
+    #[test]
+    fn windows_keep_their_own_points() {
+        let mut editor = editor_with_text("one\ntwo\nthree");
+        editor.layout_windows(80, 24);
+
+        editor.execute("split-window-below", "").unwrap();
+        assert_eq!(editor.windows().len(), 2);
+        editor.execute("end-of-buffer", "").unwrap();
+        editor.execute("other-window", "").unwrap();
+        assert_eq!(point(&editor), 0);
+
+        editor.execute("insert-char", "zero\n").unwrap();
+        editor.execute("other-window", "").unwrap();
+        assert_eq!(point(&editor), "zero\none\ntwo\nthree".len());
+
+        editor.execute("delete-window", "").unwrap();
+        assert_eq!(editor.windows().len(), 1);
+        assert_eq!(point(&editor), 5);
+        editor.execute("delete-window", "").unwrap();
+        assert_eq!(editor.message(), Some("Attempt to delete the only window"));
+    }
+
+    #[test]
+    fn every_window_follows_its_point_when_resized() {
+        let text: Vec<String> = (0..30).map(|line| line.to_string()).collect();
+        let mut editor = editor_with_text(&text.join("\n"));
+        editor.layout_windows(80, 40);
+        let first = editor.windows().selected_id();
+        editor.execute("split-window-below", "").unwrap();
+        editor.execute("end-of-buffer", "").unwrap();
+        editor.execute("other-window", "").unwrap();
+
+        editor.layout_windows(80, 10);
+        let first_view = editor.windows().get(first).unwrap().view();
+        assert!(first_view.visible_lines(editor.current_buffer().unwrap()).contains(&29));
+        assert_eq!(editor.view().top_line(), 0);
+    }
+
+    #[test]
+    fn resize_the_selected_window() {
+        let mut editor = Editor::new();
+        editor.layout_windows(80, 24);
+        editor.execute("split-window-below", "").unwrap();
+
+        editor.execute("enlarge-window", "3").unwrap();
+        assert_eq!(editor.view().height(), 14);
+        editor.execute("shrink-window", "").unwrap();
+        assert_eq!(editor.view().height(), 13);
+        editor.execute("balance-windows", "").unwrap();
+        assert_eq!(editor.view().height(), 11);
+
+        editor.execute("split-window-right", "").unwrap();
+        editor.execute("shrink-window-horizontally", "5").unwrap();
+        assert_eq!(editor.view().width(), 35);
+        assert!(editor.execute("enlarge-window", "many").is_err());
+    }
+
+    #[test]
+    fn tabify_the_region_at_the_tab_width() {
+        let mut editor = editor_with_text("\ta\n\tb\n\tc\n");
+        editor.execute("set-tab-width", "4").unwrap();
+        editor.current_buffer_mut().unwrap().set_mark(3);
+        editor.current_buffer_mut().unwrap().set_point(6);
+
+        editor.execute("untabify", "").unwrap();
+        assert_eq!(contents(&editor), "\ta\n    b\n\tc\n");
+        editor.current_buffer_mut().unwrap().clear_mark();
+        editor.execute("untabify", "").unwrap();
+        assert_eq!(contents(&editor), "    a\n    b\n    c\n");
+        editor.execute("tabify", "").unwrap();
+        assert_eq!(contents(&editor), "\ta\n\tb\n\tc\n");
+
+        assert!(editor.execute("set-tab-width", "0").is_err());
+        assert!(editor.execute("set-tab-width", "wide").is_err());
+    }
+
+    #[test]
+    fn move_between_lines_by_screen_column() {
+        let mut editor = editor_with_text("\tx\nabcdefghij");
+        editor.execute("set-tab-width", "4").unwrap();
+        editor.execute("forward-char", "").unwrap();
+
+        editor.execute("next-line", "").unwrap();
+        assert_eq!(point(&editor), 7);
+        assert_eq!(editor.view().column(editor.current_buffer().unwrap(), 7), 4);
+    }
+
+    #[test]
+    fn load_builtin_and_file_themes() {
+        let mut editor = Editor::new();
+        assert_eq!(editor.theme().name(), "default");
+
+        editor.execute("load-theme", "dark").unwrap();
+        assert_eq!(editor.theme().name(), "dark");
+
+        let path = std::env::temp_dir().join(format!("one-load-theme-{}.toml", std::process::id()));
+        std::fs::write(&path, "name = \"mine\"\ninherits = \"light\"\n").unwrap();
+        editor.execute("load-theme", path.to_str().unwrap()).unwrap();
+        assert_eq!(editor.theme().name(), "mine");
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(editor.execute("load-theme", "neon"), Err(CommandError::Theme(ThemeError::UnknownTheme(_)))));
+        assert_eq!(editor.theme().name(), "mine");
+    }
+
+    #[test]
+    fn load_syntax_rules_for_open_and_later_files() {
+        let directory = std::env::temp_dir().join(format!("one-syntax-rules-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let rules = directory.join("ini.toml");
+        let file = directory.join("setup.ini");
+        std::fs::write(&rules, "name = \"ini\"\nextensions = [\"ini\"]\nline-comments = [\";\"]\n").unwrap();
+        std::fs::write(&file, "; note\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", file.to_str().unwrap()).unwrap();
+        let id = editor.buffers().current_id().unwrap();
+        assert!(editor.highlighter(id).is_none());
+
+        editor.execute("load-syntax-rules", rules.to_str().unwrap()).unwrap();
+        editor.layout_windows(80, 24);
+        let spans = editor.highlighter(id).unwrap().line_spans(editor.current_buffer().unwrap(), 0);
+        assert_eq!(spans.iter().map(|span| span.style.as_str()).collect::<Vec<_>>(), vec!["syntax.comment"]);
+        assert_eq!(editor.syntax_rules()[0].name, "ini");
+
+        let missing = rules.with_extension("missing");
+        assert!(matches!(editor.execute("load-syntax-rules", missing.to_str().unwrap()), Err(CommandError::Syntax(_))));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn goto_and_show_the_matching_bracket() {
+        let mut editor = editor_with_text("f(\"(\", [x]);");
+        editor.current_buffer_mut().unwrap().set_point(1);
+        editor.execute("goto-matching-bracket", "").unwrap();
+        assert_eq!(point(&editor), 1);
+        assert_eq!(editor.message(), Some("No matching bracket"));
+
+        let id = editor.buffers().current_id().unwrap();
+        let highlighter = RuleHighlighter::new(editor.current_buffer_mut().unwrap(), SyntaxRules::builtin("c").unwrap());
+        editor.set_highlighter(id, Box::new(highlighter));
+        editor.current_buffer_mut().unwrap().set_point(1);
+        editor.execute("goto-matching-bracket", "").unwrap();
+        assert_eq!(point(&editor), 10);
+        editor.execute("goto-matching-bracket", "").unwrap();
+        assert_eq!(point(&editor), 1);
+
+        editor.layout_windows(80, 24);
+        let properties = editor.current_buffer().unwrap().properties_in(0..12).into_iter();
+        let marked: Vec<_> = properties.map(|(range, property)| (range, property.style.clone())).collect();
+        assert_eq!(marked, vec![(1..2, Some(MATCHING_BRACKET.to_string())), (10..11, Some(MATCHING_BRACKET.to_string()))]);
+
+        editor.current_buffer_mut().unwrap().set_point(8);
+        editor.layout_windows(80, 24);
+        assert!(editor.current_buffer().unwrap().properties_in(0..12).is_empty());
+        editor.execute("goto-matching-bracket", "").unwrap();
+        assert_eq!(point(&editor), 8);
+    }
+
+    #[test]
+    fn newline_indents_the_new_line() {
+        let mut editor = editor_with_text("\tif x {  y");
+        editor.current_buffer_mut().unwrap().set_point(7);
+        editor.execute("newline", "").unwrap();
+        assert_eq!(contents(&editor), "\tif x {\n\ty");
+        assert_eq!(point(&editor), 9);
+
+        let id = editor.buffers().current_id().unwrap();
+        editor.set_indenter(id, Box::new(OpenerIndent { openers: vec!['{'] }));
+        editor.current_buffer_mut().unwrap().set_point(7);
+        editor.execute("newline", "").unwrap();
+        assert_eq!(contents(&editor), "\tif x {\n\t    \n\ty");
+        assert_eq!(point(&editor), 13);
+    }
+
+    #[test]
+    fn indent_and_dedent_the_region_by_the_buffer_settings() {
+        let mut editor = editor_with_text("a\n  b\n\n    c\nd");
+        editor.execute("set-indent-width", "2").unwrap();
+        editor.current_buffer_mut().unwrap().set_mark(2);
+        editor.current_buffer_mut().unwrap().set_point(13);
+
+        editor.execute("indent-region", "").unwrap();
+        assert_eq!(contents(&editor), "a\n    b\n\n      c\nd");
+        editor.execute("dedent-region", "").unwrap();
+        editor.execute("dedent-region", "").unwrap();
+        assert_eq!(contents(&editor), "a\nb\n\n  c\nd");
+        editor.execute("dedent-region", "").unwrap();
+        assert_eq!(contents(&editor), "a\nb\n\n  c\nd");
+
+        editor.execute("set-indent-style", "tabs").unwrap();
+        editor.current_buffer_mut().unwrap().clear_mark();
+        editor.current_buffer_mut().unwrap().set_point(0);
+        editor.execute("indent-region", "").unwrap();
+        assert_eq!(contents(&editor), "\ta\nb\n\n  c\nd");
+        assert!(editor.current_buffer().unwrap().indent_settings().use_tabs);
+
+        assert!(editor.execute("set-indent-style", "both").is_err());
+        assert!(editor.execute("set-indent-width", "0").is_err());
+    }
+
+    #[test]
+    fn step_through_diagnostics() {
+        let mut editor = editor_with_text("let x = 1;\nlet y = z;\n");
+        let diagnostic = |range: std::ops::Range<usize>, severity, message: &str| Diagnostic {
+            range,
+            severity,
+            message: message.to_string(),
+            source: Some("rustc".to_string()),
+        };
+        let diagnostics = vec![diagnostic(4..5, Severity::Warning, "unused x"), diagnostic(19..20, Severity::Error, "unknown z")];
+        editor.current_buffer_mut().unwrap().set_diagnostics("lint", diagnostics);
+
+        press(&mut editor, "C-c ! n");
+        assert_eq!((point(&editor), editor.message()), (4, Some("warning: unused x [rustc]")));
+        editor.execute("next-diagnostic", "").unwrap();
+        assert_eq!((point(&editor), editor.message()), (19, Some("error: unknown z [rustc]")));
+        editor.execute("next-diagnostic", "").unwrap();
+        assert_eq!((point(&editor), editor.message()), (19, Some("No more diagnostics")));
+        press(&mut editor, "C-c ! p");
+        assert_eq!(point(&editor), 4);
+
+        let info = StatusInfo::of(&editor).unwrap();
+        assert_eq!(StatusLine::new("%d").render(&info), "E1 W1");
+    }
+
+    #[test]
+    fn complete_words_from_a_popup() {
+        let mut editor = editor_with_text("alpha alphabet foobar\n");
+        editor.current_buffer_mut().unwrap().set_point(22);
+        editor.execute("insert-char", "fo").unwrap();
+        editor.execute("complete", "").unwrap();
+        assert_eq!((contents(&editor), point(&editor)), ("alpha alphabet foobar\nfoobar".to_string(), 28));
+        assert!(editor.completion().is_none());
+
+        editor.execute("insert-char", " al").unwrap();
+        press(&mut editor, "M-TAB");
+        let labels: Vec<&str> = editor.completion().unwrap().matches().map(|candidate| candidate.item.label.as_str()).collect();
+        assert_eq!(labels, vec!["alphabet", "alpha"]);
+        press(&mut editor, "p C-n");
+        assert_eq!(editor.completion().unwrap().selected().unwrap().item.text, "alpha");
+        press(&mut editor, "RET");
+        assert_eq!(contents(&editor), "alpha alphabet foobar\nfoobar alpha");
+        assert!(editor.completion().is_none());
+
+        editor.execute("insert-char", " zz").unwrap();
+        editor.execute("complete", "").unwrap();
+        assert_eq!(editor.message(), Some("No completions"));
+        editor.execute("toggle-auto-complete", "").unwrap();
+        press(&mut editor, "SPC f o o");
+        assert!(editor.completion().is_some());
+        press(&mut editor, "C-g");
+        assert!(editor.completion().is_none());
+        assert!(contents(&editor).ends_with(" foo"));
+    }
+
+    #[test]
+    fn expand_words_from_other_buffers() {
+        let mut editor = editor_with_text("remote");
+        let notes = editor.buffers_mut().create("notes");
+        editor.buffers_mut().switch_to(notes);
+        editor.execute("insert-char", "reminder re").unwrap();
+
+        press(&mut editor, "M-/");
+        assert_eq!(contents(&editor), "reminder reminder");
+        press(&mut editor, "M-/");
+        assert_eq!(contents(&editor), "reminder remote");
+        press(&mut editor, "M-/");
+        assert_eq!((contents(&editor), editor.message()), ("reminder re".to_string(), Some("No further dynamic expansion")));
+    }
+
+    #[test]
+    fn jump_back_and_forward_over_edits() {
+        let mut editor = editor_with_text("one\ntwo\nthree\nfour");
+        editor.execute("search-forward", "three").unwrap();
+        editor.execute("goto-line", "4").unwrap();
+        assert_eq!(point(&editor), 14);
+        editor.current_buffer_mut().unwrap().set_point(0);
+        editor.execute("insert-char", "zero\n").unwrap();
+
+        press(&mut editor, "M-,");
+        assert_eq!(point(&editor), 18);
+        press(&mut editor, "M-,");
+        assert_eq!(point(&editor), 0);
+        press(&mut editor, "M-,");
+        assert_eq!((point(&editor), editor.message()), (0, Some("No earlier jump")));
+        press(&mut editor, "C-M-,");
+        assert_eq!(point(&editor), 18);
+        editor.execute("jump-forward", "").unwrap();
+        assert_eq!(point(&editor), 5);
+        editor.execute("jump-forward", "").unwrap();
+        assert_eq!(editor.message(), Some("No later jump"));
+        assert_eq!(editor.jump_list().len(), 3);
+    }
+
+    #[test]
+    fn search_incrementally() {
+        let mut editor = editor_with_text("cat catalog\nCat dog cat");
+        editor.current_buffer_mut().unwrap().set_point(1);
+
+        press(&mut editor, "C-s c a");
+        assert_eq!(point(&editor), 6);
+        press(&mut editor, "t l");
+        assert_eq!((point(&editor), editor.message()), (7, Some("Failing I-search: catl")));
+        press(&mut editor, "DEL C-s");
+        assert_eq!((point(&editor), editor.message()), (15, Some("I-search: cat")));
+        press(&mut editor, "C-s C-s");
+        assert_eq!((point(&editor), editor.message()), (23, Some("Failing I-search: cat")));
+        press(&mut editor, "C-s");
+        assert_eq!((point(&editor), editor.message()), (3, Some("Wrapped I-search: cat")));
+
+        editor.layout_windows(80, 24);
+        let marks: Vec<_> = editor.current_buffer().unwrap().properties_in(0..23).into_iter().filter(|(_, property)| property.group == "isearch").map(|(range, property)| (range, property.style.clone().unwrap())).collect();
+        assert_eq!(marks, vec![(0..3, "search-match.current".to_string()), (4..7, "search-match".to_string()), (12..15, "search-match".to_string()), (20..23, "search-match".to_string())]);
+
+        press(&mut editor, "RET");
+        assert!(editor.isearch().is_none());
+        assert_eq!((point(&editor), editor.jump_list().len()), (3, 1));
+        press(&mut editor, "C-r a");
+        assert_eq!((point(&editor), editor.message()), (1, Some("I-search backward: a")));
+        press(&mut editor, "C-g");
+        assert_eq!((point(&editor), editor.message()), (3, Some("Quit")));
+
+        press(&mut editor, "C-s d o C-e");
+        assert_eq!((point(&editor), editor.isearch().is_none()), (23, true));
+    }
+
+    #[test]
+    fn replace_asking_about_each_match() {
+        let mut editor = editor_with_text("cat cat cat cat");
+
+        editor.execute("query-replace", "cat").unwrap();
+        assert_eq!(editor.minibuffer().unwrap().prompt(), "Query replace cat with: ");
+        press(&mut editor, "d o g RET");
+        assert_eq!((point(&editor), editor.message()), (3, Some("Query replacing cat with dog: (y, n, !, ., q)")));
+        press(&mut editor, "y");
+        assert_eq!((contents(&editor), point(&editor)), ("dog cat cat cat".to_string(), 7));
+        press(&mut editor, "n !");
+        assert_eq!((contents(&editor), editor.message()), ("dog cat dog dog".to_string(), Some("Replaced 3 occurrences")));
+        assert!(editor.query_replace().is_none());
+
+        editor.current_buffer_mut().unwrap().set_point(0);
+        press(&mut editor, "M-% d o g RET c o w RET .");
+        assert_eq!((contents(&editor), editor.message()), ("cow cat dog dog".to_string(), Some("Replaced 1 occurrence")));
+        press(&mut editor, "M-% c a t RET C-g M-% o w RET RET");
+        assert_eq!((contents(&editor), editor.message()), ("cow cat dog dog".to_string(), Some("No matches for ow")));
+    }
+
+    #[test]
+    fn undo_a_query_replace_as_one_step() {
+        let mut editor = editor_with_text("cat cat cat cat");
+
+        press(&mut editor, "M-% c a t RET d o g RET y n !");
+        assert_eq!(contents(&editor), "dog cat dog dog");
+        editor.execute("undo", "").unwrap();
+        assert_eq!(contents(&editor), "cat cat cat cat");
+
+        press(&mut editor, "M-% c a t RET c o w RET y C-_");
+        assert!(editor.query_replace().is_none());
+        assert_eq!(contents(&editor), "cat cat cat cat");
+    }
+
+    #[test]
+    fn list_matching_lines_and_go_to_them() {
+        let mut editor = editor_with_text("alpha one\nbeta\nalpha alpha two\ngamma");
+        let source = editor.buffers().current_id().unwrap();
+
+        editor.execute("occur", "alpha").unwrap();
+        assert_eq!(editor.current_buffer().unwrap().name(), "*Occur*");
+        assert_eq!(contents(&editor), "2 matching lines for \"alpha\" in buffer *scratch*\n     1: alpha one\n     3: alpha alpha two\n");
+        let buffer = editor.buffers_mut().get_mut(source).unwrap();
+        buffer.set_point(0);
+        buffer.insert_str("new\n");
+
+        press(&mut editor, "C-n C-n RET");
+        assert_eq!((editor.buffers().current_id(), point(&editor)), (Some(source), 19));
+        press(&mut editor, "M-,");
+        assert_eq!(editor.current_buffer().unwrap().name(), "*Occur*");
+        press(&mut editor, "M-< RET");
+        assert_eq!(editor.message(), Some("No occurrence on this line"));
+
+        editor.execute("occur", "delta").unwrap();
+        assert_eq!(editor.message(), Some("No matches for delta"));
+    }
+
+    #[test]
+    fn grep_a_project_and_visit_the_matches() {
+        let root = std::env::temp_dir().join(format!("one-grep-commands-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        std::fs::write(root.join("src/run.rs"), "pub fn run() {}\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", root.join("src/main.rs").to_str().unwrap()).unwrap();
+        press(&mut editor, "M-s g r u n RET");
+        assert!(editor.grep_running());
+        while editor.sync_grep() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let header = format!("Grep for \"run\" in {}\n\n", root.display());
+        assert_eq!(contents(&editor), format!("{}src/main.rs:2:5:     run();\nsrc/run.rs:1:8: pub fn run() {{}}\n\nGrep finished with 2 matches\n", header));
+        assert_eq!(editor.message(), Some("Grep finished with 2 matches"));
+
+        press(&mut editor, "RET");
+        assert_eq!(editor.message(), Some("No match on this line"));
+        press(&mut editor, "C-n C-n C-n RET");
+        let visited = editor.current_buffer().unwrap().path().map(|path| path.to_path_buf());
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!((visited, point(&editor)), (Some(root.join("src/run.rs")), 7));
+    }
+
+    #[test]
+    fn find_a_file_in_the_project_by_parts_of_its_name() {
+        let root = std::env::temp_dir().join(format!("one-find-in-project-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src/editor")).unwrap();
+        for file in ["src/main.rs", "src/editor/mouse.rs", "README.md"].iter() {
+            std::fs::write(root.join(file), *file).unwrap();
+        }
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", root.join("README.md").to_str().unwrap()).unwrap();
+        press(&mut editor, "C-x p f m s");
+        assert_eq!(editor.minibuffer().unwrap().matches(), vec!["src/editor/mouse.rs", "src/main.rs"]);
+        press(&mut editor, "C-n RET");
+        let visited = (editor.current_buffer().unwrap().path().map(|path| path.to_path_buf()), contents(&editor));
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(visited, (Some(root.join("src/main.rs")), "src/main.rs".to_string()));
+    }
+
+    #[test]
+    fn list_and_change_a_directory() {
+        let root = std::env::temp_dir().join(format!("one-dired-commands-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a"), "").unwrap();
+        let root = std::fs::canonicalize(&root).unwrap();
+        let names = |editor: &Editor| contents(editor).lines().skip(1).map(|line| line.rsplit(' ').next().unwrap().to_string()).collect::<Vec<String>>();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", root.to_str().unwrap()).unwrap();
+        assert_eq!(editor.dired_directory(), Some(root.as_path()));
+        assert_eq!(names(&editor), vec!["..", "a", "sub/"]);
+        press(&mut editor, "D");
+        assert_eq!(editor.message(), Some("Cannot change the directory above from here"));
+
+        press(&mut editor, "n R b RET c c RET");
+        assert_eq!(names(&editor), vec!["..", "b", "c", "sub/"]);
+        assert!(root.join("b").is_file() && !root.join("a").exists());
+        press(&mut editor, "n D n o RET");
+        assert_eq!(editor.message(), Some("Kept c"));
+        press(&mut editor, "D y e s RET");
+        assert_eq!(editor.message(), Some("Deleted c"));
+        assert_eq!(names(&editor), vec!["..", "b", "sub/"]);
+
+        press(&mut editor, "RET");
+        let sub = editor.dired_directory().map(|directory| directory.to_path_buf());
+        press(&mut editor, "^");
+        let up = editor.dired_directory().map(|directory| directory.to_path_buf());
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!((sub, up), (Some(root.join("sub")), Some(root.clone())));
+    }
+
+    #[test]
+    fn duplicate_move_join_and_split_lines() {
+        let mut editor = editor_with_text("one\ntwo\nthree");
+
+        press(&mut editor, "C-n C-f C-c d");
+        assert_eq!((contents(&editor), point(&editor)), ("one\ntwo\ntwo\nthree".to_string(), 9));
+        press(&mut editor, "M-<up> M-<up> M-<up>");
+        assert_eq!((contents(&editor), point(&editor)), ("two\none\ntwo\nthree".to_string(), 1));
+        assert_eq!(editor.message(), Some("No line above"));
+        press(&mut editor, "M-<down>");
+        assert_eq!((contents(&editor), point(&editor)), ("one\ntwo\ntwo\nthree".to_string(), 5));
+
+        press(&mut editor, "C-c j C-c j");
+        assert_eq!((contents(&editor), point(&editor)), ("one\ntwo two three".to_string(), 11));
+        press(&mut editor, "C-c j");
+        assert_eq!(editor.message(), Some("No line to join"));
+        press(&mut editor, "C-M-o");
+        assert_eq!((contents(&editor), point(&editor)), ("one\ntwo two\n       three".to_string(), 11));
+    }
+
+    #[test]
+    fn sort_and_uniquify_the_lines_in_the_region() {
+        let mut editor = editor_with_text("b\nc\na\nc\n# end\n");
+
+        press(&mut editor, "C-SPC C-n C-n C-n C-n");
+        editor.execute("sort-lines", "").unwrap();
+        assert_eq!(contents(&editor), "a\nb\nc\nc\n# end\n");
+        editor.execute("sort-lines-descending", "").unwrap();
+        assert_eq!(contents(&editor), "c\nc\nb\na\n# end\n");
+        assert_eq!(editor.current_buffer().unwrap().region(), Some(0..8));
+
+        editor.execute("delete-duplicate-lines", "").unwrap();
+        assert_eq!(contents(&editor), "c\nb\na\n# end\n");
+        assert_eq!(editor.message(), Some("Deleted 1 duplicate line"));
+        editor.execute("set-mark", "").unwrap();
+        editor.execute("beginning-of-buffer", "").unwrap();
+        editor.execute("sort-lines", "").unwrap();
+        assert_eq!(contents(&editor), "a\nb\nc\n# end\n");
+        editor.execute("undo", "").unwrap();
+        assert_eq!(contents(&editor), "c\nb\na\n# end\n");
+    }
+
+    #[test]
+    fn transpose_characters_words_and_lines() {
+        let mut editor = editor_with_text("ab cd\nef");
+
+        press(&mut editor, "C-f C-t C-t");
+        assert_eq!((contents(&editor), point(&editor)), ("b acd\nef".to_string(), 3));
+        press(&mut editor, "C-a M-t");
+        assert_eq!((contents(&editor), point(&editor)), ("acd b\nef".to_string(), 5));
+        press(&mut editor, "C-n C-x C-t");
+        assert_eq!((contents(&editor), point(&editor)), ("ef\nacd b".to_string(), 8));
+        press(&mut editor, "M-< C-t");
+        assert_eq!(editor.message(), Some("Nothing to transpose"));
+    }
+
+    #[test]
+    fn change_the_case_of_words_and_the_region() {
+        let mut editor = editor_with_text("ein groß fest");
+
+        press(&mut editor, "M-c M-u M-l");
+        assert_eq!((contents(&editor), point(&editor)), ("Ein GROSS fest".to_string(), 14));
+        editor.execute("upcase-region", "").unwrap();
+        assert_eq!(editor.message(), Some("The mark is not set now, so there is no region"));
+        press(&mut editor, "M-< C-SPC M-f M-f C-x C-l");
+        assert_eq!((contents(&editor), point(&editor)), ("ein gross fest".to_string(), 9));
+        press(&mut editor, "C-x C-u");
+        assert_eq!(contents(&editor), "EIN GROSS fest");
+
+        press(&mut editor, "C-_");
+        assert_eq!(contents(&editor), "ein gross fest");
+        press(&mut editor, "C-_");
+        assert_eq!((contents(&editor), point(&editor)), ("Ein GROSS fest".to_string(), 9));
+        press(&mut editor, "C-_");
+        assert_eq!((contents(&editor), point(&editor)), ("Ein groß fest".to_string(), 3));
+    }
+
+    #[test]
+    fn fill_paragraphs_and_wrap_while_typing() {
+        let mut editor = editor_with_text("  one two\n  three four five\n\nsix");
+
+        editor.execute("set-fill-column", "12").unwrap();
+        assert_eq!(editor.message(), Some("Fill column set to 12"));
+        press(&mut editor, "M-q");
+        assert_eq!(contents(&editor), "  one two\n  three four\n  five\n\nsix");
+        assert!(editor.execute("set-fill-column", "none").is_err());
+
+        editor.execute("auto-fill-mode", "").unwrap();
+        assert_eq!(editor.message(), Some("Auto-fill enabled"));
+        press(&mut editor, "M-> SPC s e v e n SPC e i g h t SPC n i n e RET");
+        assert_eq!(contents(&editor), "  one two\n  three four\n  five\n\nsix seven\neight nine\n");
+
+        let mut editor = editor_with_text("\tone two");
+        editor.execute("set-fill-column", "9").unwrap();
+        editor.execute("set-tab-width", "2").unwrap();
+        press(&mut editor, "M-q");
+        assert_eq!(contents(&editor), "\tone two");
+        editor.execute("set-tab-width", "8").unwrap();
+        press(&mut editor, "M-q");
+        assert_eq!(contents(&editor), "\tone\n\ttwo");
+    }
+
+    #[test]
+    fn kill_words_sentences_and_lines_into_one_entry() {
+        let mut editor = editor_with_text("Keep this. Drop that one\nand this line.\nKeep too.");
+
+        press(&mut editor, "M-f M-f M-d M-d M-DEL");
+        assert_eq!(contents(&editor), "Keep  one\nand this line.\nKeep too.");
+        press(&mut editor, "C-e C-y");
+        assert_eq!(contents(&editor), "Keep  onethis. Drop that\nand this line.\nKeep too.");
+
+        press(&mut editor, "C-n C-a M-k");
+        editor.execute("kill-whole-line", "").unwrap();
+        assert_eq!(contents(&editor), "Keep  onethis. Drop that\nKeep too.");
+        press(&mut editor, "M-< C-y");
+        assert_eq!(contents(&editor), "and this line.\nKeep  onethis. Drop that\nKeep too.");
+    }
+
+    #[test]
+    fn narrow_to_the_region_and_widen() {
+        let mut editor = editor_with_text("head\nbody one\nbody two\ntail");
+
+        editor.execute("widen", "").unwrap();
+        assert_eq!(editor.message(), Some("The buffer is not narrowed"));
+        press(&mut editor, "C-n C-SPC C-n C-n C-x n n");
+        assert_eq!((contents(&editor), point(&editor)), ("body one\nbody two\n".to_string(), 18));
+        assert_eq!(StatusInfo::of(&editor).unwrap().mode, "Emacs Narrow");
+
+        press(&mut editor, "M-< C-k M-> M-<");
+        assert_eq!((contents(&editor), point(&editor)), ("\nbody two\n".to_string(), 0));
+        press(&mut editor, "C-x n w");
+        assert_eq!((contents(&editor), point(&editor)), ("head\n\nbody two\ntail".to_string(), 5));
+        assert_eq!(StatusInfo::of(&editor).unwrap().mode, "Emacs");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn filter_the_region_through_a_shell_command() {
+        let mut editor = editor_with_text("keep\npear\napple\n");
+
+        editor.execute("shell-command-on-region", "sort").unwrap();
+        assert_eq!(editor.message(), Some("The mark is not set now, so there is no region"));
+        press(&mut editor, "C-n C-SPC M->");
+        editor.execute("shell-command-on-region", "sort; echo sorted >&2").unwrap();
+        assert_eq!(contents(&editor), "keep\napple\npear\n");
+        assert_eq!(editor.current_buffer().unwrap().region(), Some(5..16));
+        assert_eq!(editor.message(), Some("sorted"));
+
+        editor.execute("shell-command-on-region", "echo broken >&2; exit 2").unwrap();
+        assert_eq!(contents(&editor), "keep\napple\npear\n");
+        assert_eq!(editor.message(), Some("Shell command exited with code 2: broken"));
+        assert!(editor.execute("shell-command-on-region", " ").is_err());
+
+        editor.execute("shell-command-on-region", "tr a-z A-Z").unwrap();
+        assert_eq!(contents(&editor), "keep\nAPPLE\nPEAR\n");
+        editor.execute("undo", "").unwrap();
+        assert_eq!((contents(&editor), point(&editor)), ("keep\napple\npear\n".to_string(), 5));
+        editor.execute("undo", "").unwrap();
+        assert_eq!(contents(&editor), "keep\npear\napple\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_shell_commands_in_the_background() {
+        let mut editor = Editor::new();
+
+        press(&mut editor, "M-& e c h o SPC h i RET");
+        assert_eq!(editor.message(), Some("Job 1 started"));
+        while editor.sync_jobs() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let buffer = editor.current_buffer().unwrap();
+        assert_eq!((buffer.name(), buffer.to_string().as_str()), (ASYNC_SHELL_BUFFER, "hi\n\nJob 1 (echo hi) finished\n"));
+        assert_eq!(editor.message(), Some("Job 1 (echo hi) finished"));
+
+        editor.execute("async-shell-command", "exec sleep 10").unwrap();
+        editor.execute("list-jobs", "").unwrap();
+        assert_eq!(
+            contents(&editor),
+            "  Id  Status             Command\n   1  finished           echo hi\n   2  running            exec sleep 10\n"
+        );
+        editor.execute("kill-job", "").unwrap();
+        assert_eq!(editor.message(), Some("No job is writing to this buffer"));
+        editor.execute("kill-job", "2").unwrap();
+        assert_eq!(editor.message(), Some("Killed job 2"));
+        while editor.sync_jobs() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(editor.message(), Some("Job 2 (exec sleep 10) killed"));
+        editor.execute("kill-job", "1").unwrap();
+        assert_eq!(editor.message(), Some("Job 1 is not running"));
+        assert!(editor.execute("kill-job", "one").is_err());
+        assert!(editor.execute("async-shell-command", "").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compile_and_step_through_the_errors() {
+        let root = std::env::temp_dir().join(format!("one-compile-commands-{}", std::process::id()));
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {\n    run();\n}\n").unwrap();
+        std::fs::write(root.join("src/run.rs"), "pub fn run() {}\n").unwrap();
+        let build = "echo 'src/main.rs:2:5: error: unknown run'; echo 'error[E0308]: mismatched types'; echo '  --> src/run.rs:1:8'; exit 1";
+
+        let mut editor = Editor::new();
+        editor.execute("next-error", "").unwrap();
+        assert_eq!(editor.message(), Some("No build has been run"));
+        assert!(editor.execute("compile", "").is_err());
+        editor.execute("find-file", root.join("src/main.rs").to_str().unwrap()).unwrap();
+        editor.execute("compile", build).unwrap();
+        while editor.sync_jobs() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let output = "src/main.rs:2:5: error: unknown run\nerror[E0308]: mismatched types\n  --> src/run.rs:1:8\n";
+        let summary = format!("Job 1 ({}) exited with code 1", build);
+        assert_eq!(contents(&editor), format!("Compile \"{}\" in {}\n\n{}\n{}\n", build, root.display(), output, summary));
+
+        press(&mut editor, "RET");
+        assert_eq!(editor.message(), Some("No error on this line"));
+        press(&mut editor, "M-g g 3 RET RET");
+        let visited = editor.current_buffer().unwrap().path().map(|path| path.to_path_buf());
+        assert_eq!((visited, point(&editor)), (Some(root.join("src/main.rs")), 16));
+        assert_eq!(editor.message(), Some("src/main.rs:2:5: error: unknown run"));
+        press(&mut editor, "C-x `");
+        let visited = editor.current_buffer().unwrap().path().map(|path| path.to_path_buf());
+        assert_eq!((visited, point(&editor)), (Some(root.join("src/run.rs")), 7));
+        assert_eq!(editor.message(), Some("error[E0308]: mismatched types"));
+        press(&mut editor, "M-g M-n");
+        assert_eq!(editor.message(), Some("No more errors"));
+        press(&mut editor, "M-g M-p");
+        assert_eq!((editor.current_buffer().unwrap().name(), point(&editor)), ("main.rs", 16));
+        press(&mut editor, "M-g M-p");
+        assert_eq!(editor.message(), Some("No earlier errors"));
+
+        editor.execute("compile", "").unwrap();
+        assert_eq!(editor.compile_command(), Some(build));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mark_step_through_and_revert_the_changes_since_the_last_commit() {
+        let root = std::env::temp_dir().join(format!("one-git-gutter-commands-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("notes.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git").arg("-C").arg(&root).args(["-c", "user.name=one", "-c", "user.email=one@example.com"]).args(args).status();
+            assert!(status.unwrap().success());
+        };
+        git(&["init", "-q"]);
+        git(&["add", "notes.txt"]);
+        git(&["commit", "-qm", "Add notes"]);
+        let signs = |editor: &Editor| -> Vec<(std::ops::Range<usize>, char)> {
+            editor.current_buffer().unwrap().signs().into_iter().map(|sign| (sign.lines, sign.text)).collect()
+        };
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", root.join("notes.txt").to_str().unwrap()).unwrap();
+        assert!(editor.is_tracking_git_changes(editor.buffers().current_id().unwrap()));
+        assert_eq!(signs(&editor), vec![]);
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.replace_range(4..7, "TWO");
+        buffer.replace_range(14..14, "new\n");
+        editor.sync_git_changes();
+        assert_eq!(signs(&editor), vec![(1..2, '~'), (3..4, '+')]);
+
+        editor.current_buffer_mut().unwrap().set_point(0);
+        press(&mut editor, "C-x v ]");
+        assert_eq!(point(&editor), 4);
+        press(&mut editor, "C-x v ] C-x v ]");
+        assert_eq!((point(&editor), editor.message()), (14, Some("No more changes")));
+        press(&mut editor, "C-x v [ C-x v n");
+        assert_eq!((contents(&editor), point(&editor)), ("one\ntwo\nthree\nnew\nfour\n".to_string(), 4));
+        editor.sync_git_changes();
+        assert_eq!(signs(&editor), vec![(3..4, '+')]);
+
+        editor.execute("save-buffer", "").unwrap();
+        git(&["commit", "-qam", "Add a line"]);
+        editor.execute("save-buffer", "").unwrap();
+        assert_eq!(signs(&editor), vec![]);
+        press(&mut editor, "C-x v n");
+        assert_eq!(editor.message(), Some("No change at the point"));
+        editor.execute("git-gutter-mode", "").unwrap();
+        assert_eq!(editor.message(), Some("Git gutter disabled"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn blame_each_line_and_go_to_it() {
+        let root = std::env::temp_dir().join(format!("one-blame-commands-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let git = |author: &str, args: &[&str]| {
+            let identity = [format!("user.name={}", author), "user.email=one@example.com".to_string()];
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args(["-c", &identity[0], "-c", &identity[1]])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            String::from_utf8(output.stdout).unwrap().trim().to_string()
+        };
+        git("Ada", &["init", "-q"]);
+        std::fs::write(root.join("notes.txt"), "one\ntwo\n").unwrap();
+        git("Ada", &["add", "notes.txt"]);
+        git("Ada", &["commit", "-qm", "Add notes"]);
+        let first = git("Ada", &["rev-parse", "--short=8", "HEAD"]);
+        std::fs::write(root.join("notes.txt"), "one\nTWO\nthree\n").unwrap();
+        git("Grace Hopper", &["commit", "-qam", "Shout"]);
+        let second = git("Grace Hopper", &["rev-parse", "--short=8", "HEAD"]);
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", root.join("notes.txt").to_str().unwrap()).unwrap();
+        press(&mut editor, "C-n C-x v g");
+        let expected = format!(
+            "{} Ada          just now | one\n{} Grace Hopper just now | TWO\n{} Grace Hopper just now | three\n",
+            first, second, second
+        );
+        assert_eq!((editor.current_buffer().unwrap().name(), contents(&editor)), (BLAME_BUFFER, expected));
+        assert_eq!(point(&editor), 37);
+
+        press(&mut editor, "C-n RET");
+        assert_eq!((editor.current_buffer().unwrap().name(), point(&editor)), ("notes.txt", 8));
+        editor.current_buffer_mut().unwrap().insert_str("!");
+        editor.execute("git-blame", "").unwrap();
+        assert_eq!(editor.message(), Some("The buffer has unsaved changes; the blame is of the file as last saved"));
+        press(&mut editor, "M-> RET");
+        assert_eq!(editor.message(), Some("No line listed here"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn diff_a_buffer_with_its_file_and_with_another_buffer() {
+        let path = std::env::temp_dir().join(format!("one-diff-commands-{}.txt", std::process::id()));
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", path.to_str().unwrap()).unwrap();
+        editor.execute("diff-buffer-with-file", "").unwrap();
+        assert_eq!(editor.message(), Some("No unsaved changes"));
+        editor.current_buffer_mut().unwrap().replace_range(4..7, "2");
+        editor.execute("diff-buffer-with-file", "").unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        let expected = format!("--- {}\n+++ {} (buffer)\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n", path.display(), name);
+        assert_eq!((editor.current_buffer().unwrap().name(), contents(&editor)), (DIFF_BUFFER, expected));
+        let styles: Vec<(usize, Option<String>)> = (0..7)
+            .map(|line| {
+                let properties = editor.current_buffer().unwrap().line_properties(line);
+                (line, properties.first().and_then(|(_, property)| property.style.clone()))
+            })
+            .collect();
+        let style = |line: usize, style: &str| (line, Some(style.to_string()));
+        assert_eq!(
+            styles,
+            vec![style(0, DIFF_FILE_HEADER), style(1, DIFF_FILE_HEADER), style(2, DIFF_HUNK_HEADER), (3, None), style(4, DIFF_REMOVED), style(5, DIFF_ADDED), (6, None)]
+        );
+
+        assert!(editor.execute("diff-buffers", "*no such buffer*").is_err());
+        editor.execute("scratch-buffer", "").unwrap();
+        editor.execute("diff-buffers", SCRATCH_BUFFER).unwrap();
+        assert_eq!(editor.message(), Some("No differences"));
+        editor.execute("diff-buffers", name).unwrap();
+        assert_eq!(contents(&editor), format!("--- {}\n+++ {}\n@@ -0,0 +1,3 @@\n+one\n+2\n+three\n", SCRATCH_BUFFER, name));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn type_into_a_program_running_in_a_terminal() {
+        let mut editor = Editor::new();
+        let sync_until = |editor: &mut Editor, done: &dyn Fn(&Editor) -> bool| {
+            let start = std::time::Instant::now();
+            while !done(editor) && start.elapsed() < std::time::Duration::from_secs(5) {
+                editor.sync_terminals();
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+        };
+
+        editor.execute("term", "cat").unwrap();
+        let id = editor.buffers.current_id().unwrap();
+        assert!(editor.is_terminal(id));
+        press(&mut editor, "h i RET");
+        sync_until(&mut editor, &|editor| contents(editor) == "hi\nhi\n");
+        assert_eq!((contents(&editor), point(&editor)), ("hi\nhi\n".to_string(), 6));
+        assert!(editor.writable_buffer_or_error().is_err());
+
+        press(&mut editor, "C-x");
+        assert_eq!(editor.pending_keys().len(), 1);
+        press(&mut editor, "C-g C-d");
+        sync_until(&mut editor, &|editor| !editor.is_terminal(id));
+        assert_eq!(contents(&editor), "hi\nhi\n\nProcess cat finished\n");
+        assert_eq!(editor.message(), Some("Process cat finished"));
+    }
+
+    #[test]
+    fn view_the_messages_and_return_to_the_scratch_buffer() {
+        let mut editor = Editor::new();
+
+        editor.execute("widen", "").unwrap();
+        editor.execute("auto-fill-mode", "").unwrap();
+        press(&mut editor, "C-h e");
+        let buffer = editor.current_buffer().unwrap();
+        assert_eq!((buffer.name(), buffer.to_string().as_str()), (MESSAGES_BUFFER, "The buffer is not narrowed\nAuto-fill enabled\n"));
+        assert_eq!(buffer.get_point(), buffer.len());
+        assert!(editor.writable_buffer_or_error().is_err());
+
+        editor.execute("scratch-buffer", "").unwrap();
+        assert_eq!(editor.current_buffer().unwrap().name(), SCRATCH_BUFFER);
+    }
+
+    #[test]
+    fn set_jump_to_and_keep_bookmarks() {
+        let directory = std::env::temp_dir().join(format!("one-bookmark-commands-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (file, bookmark_file) = (directory.join("notes.txt"), directory.join("bookmarks"));
+        std::fs::write(&file, "alpha\nbeta\ngamma\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.set_bookmark_file(Some(bookmark_file.clone())).unwrap();
+        editor.execute("find-file", file.to_str().unwrap()).unwrap();
+        editor.current_buffer_mut().unwrap().set_point(8);
+        editor.execute("bookmark-set", "").unwrap();
+        assert_eq!(editor.message(), Some("Set bookmark notes.txt"));
+        editor.current_buffer_mut().unwrap().set_point(0);
+        editor.execute("insert-char", ">> ").unwrap();
+
+        editor.execute("bookmark-jump", "notes.txt").unwrap();
+        assert_eq!(point(&editor), 11);
+        assert!(matches!(editor.execute("bookmark-jump", "nothing"), Err(CommandError::InvalidArgument { .. })));
+        press(&mut editor, "C-x r l");
+        assert_eq!(contents(&editor), format!("{:<20} {}:2:3\n", "notes.txt", file.display()));
+
+        let mut later = Editor::new();
+        later.set_bookmark_file(Some(bookmark_file.clone())).unwrap();
+        later.execute("bookmark-jump", "notes.txt").unwrap();
+        assert_eq!((later.current_buffer().unwrap().path(), point(&later)), (Some(file.as_path()), 8));
+        later.execute("bookmark-delete", "notes.txt").unwrap();
+        assert!(later.bookmarks().is_empty());
+        assert_eq!(std::fs::read_to_string(&bookmark_file).unwrap(), "");
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn comment_and_uncomment_the_region_by_file_type() {
+        let mut editor = Editor::new();
+        let file = std::env::temp_dir().join(format!("one-comment-{}", std::process::id())).join("main.rs");
+        editor.execute("find-file", file.to_str().unwrap()).unwrap();
+        editor.execute("insert-char", "fn f() {\n    g();\n}\n").unwrap();
+        editor.current_buffer_mut().unwrap().set_mark(0);
+        editor.current_buffer_mut().unwrap().set_point(20);
+
+        editor.execute("comment-region", "").unwrap();
+        assert_eq!(contents(&editor), "// fn f() {\n//     g();\n// }\n");
+        editor.execute("toggle-comment", "").unwrap();
+        assert_eq!(contents(&editor), "fn f() {\n    g();\n}\n");
+
+        editor.current_buffer_mut().unwrap().clear_mark();
+        editor.current_buffer_mut().unwrap().set_point(12);
+        editor.execute("toggle-comment", "").unwrap();
+        assert_eq!(contents(&editor), "fn f() {\n    // g();\n}\n");
+        editor.execute("uncomment-region", "").unwrap();
+        assert_eq!(contents(&editor), "fn f() {\n    g();\n}\n");
+
+        let mut editor = editor_with_text("text");
+        editor.execute("toggle-comment", "").unwrap();
+        assert_eq!(contents(&editor), "text");
+        assert_eq!(editor.message(), Some("No comment syntax for this buffer"));
+    }
+}
@@ -0,0 +1,124 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use buffer_manager::BufferId;
+use command::CommandError;
+use compile::parse_error_location;
+use delta::{Position, PositionEncoding};
+use editor::jobs::JobId;
+use editor::{Editor, Location};
+
+/// The buffer `compile()` lists the output of the build in.
+pub const COMPILATION_BUFFER: &str = "*compilation*";
+
+/// Compilation is the build listed in the compilation buffer.
+/// - `command_line`: What was run, so it can be run again.
+/// - `directory`: Where it ran, which the paths in its errors are relative to.
+/// - `buffer`: The compilation buffer.
+/// - `error_line`: The line of the compilation buffer with the error visited last, if one has been.
+#[derive(Debug)]
+pub(super) struct Compilation {
+    command_line: String,
+    directory: PathBuf,
+    buffer: BufferId,
+    error_line: Option<usize>,
+}
+
+impl Editor {
+    /// compile() runs the build `command_line` in `directory` as a job, stopping any build still
+    /// running, and lists what it writes in the compilation buffer. `RET` there visits the error
+    /// on the line of the point, and `next_error()` steps through the errors in turn.
+    pub fn compile(&mut self, command_line: &str, directory: &Path) -> io::Result<JobId> {
+        let job = self.start_job(command_line, directory, COMPILATION_BUFFER)?;
+        let id = self.buffers.find(COMPILATION_BUFFER).unwrap();
+        self.buffer_keymap_mut(id).bind_str("RET", "compile-goto-error").unwrap();
+        self.append_to_text_buffer(id, &format!("Compile \"{}\" in {}\n\n", command_line, directory.display()));
+
+        self.compilation = Some(Compilation {
+            command_line: command_line.to_string(),
+            directory: directory.to_path_buf(),
+            buffer: id,
+            error_line: None,
+        });
+        Ok(job)
+    }
+
+    /// compile_command() is the command line of the last build, if there has been one.
+    pub fn compile_command(&self) -> Option<&str> {
+        self.compilation.as_ref().map(|compilation| compilation.command_line.as_str())
+    }
+
+    /// next_error() visits the error listed after the one visited last in the compilation buffer,
+    /// or before it if `forward` is false, returning false if there is none.
+    pub fn next_error(&mut self, forward: bool) -> Result<bool, CommandError> {
+        let compilation = match self.compilation.as_ref() {
+            Some(compilation) => compilation,
+            None => return Ok(false),
+        };
+        let text = match self.buffers.get(compilation.buffer) {
+            Some(buffer) => buffer.to_string(),
+            None => return Ok(false),
+        };
+
+        let lines: Vec<&str> = text.split('\n').collect();
+        let is_error = |line: &usize| parse_error_location(lines[*line]).is_some();
+        let found = match (forward, compilation.error_line) {
+            (true, current) => (current.map_or(0, |line| line + 1)..lines.len()).find(is_error),
+            (false, Some(current)) => (0..current).rev().find(is_error),
+            (false, None) => None,
+        };
+
+        match found {
+            Some(line) => self.visit_error(line),
+            None => Ok(false),
+        }
+    }
+
+    /// compile_goto_error() visits the error listed on the line of the point in the compilation
+    /// buffer, returning false if there is none there.
+    pub fn compile_goto_error(&mut self) -> Result<bool, CommandError> {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        match self.compilation.as_ref() {
+            Some(compilation) if compilation.buffer == id => {}
+            _ => return Ok(false),
+        }
+
+        let buffer = self.buffers.get(id).unwrap();
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        self.visit_error(line)
+    }
+
+    /// visit_error() visits the error listed on `line` of the compilation buffer and shows its
+    /// message, which for rustc is the line before. The compilation buffer's point is moved to the
+    /// line, so the next error is found from there.
+    fn visit_error(&mut self, line: usize) -> Result<bool, CommandError> {
+        let compilation = self.compilation.as_mut().unwrap();
+        let buffer = self.buffers.get_mut(compilation.buffer).unwrap();
+        let line_text = |line: usize| {
+            let range = buffer.line_to_offset(line).unwrap()..buffer.line_end_offset(line).unwrap();
+            buffer.slice(range).into_owned()
+        };
+
+        let text = line_text(line);
+        let (path, error_line, column) = match parse_error_location(&text) {
+            Some(location) => location,
+            None => return Ok(false),
+        };
+        let message = match text.trim_start().starts_with("-->") && line > 0 {
+            true => line_text(line - 1),
+            false => text,
+        };
+
+        let start = buffer.line_to_offset(line).unwrap();
+        buffer.set_point(start);
+        compilation.error_line = Some(line);
+        let location = Location {
+            path: compilation.directory.join(path),
+            position: Position { line: error_line, character: column },
+            encoding: PositionEncoding::Utf8,
+        };
+        self.visit_location(&location)?;
+        self.set_message(message.trim());
+        Ok(true)
+    }
+}
@@ -0,0 +1,218 @@
+use serde_json::{json, Value};
+
+use command::CommandResult;
+use completion::{is_word_char, word_start, Candidate, Completion, CompletionItem, CompletionSource};
+use delta::Position;
+use editor::Editor;
+use keymap::{Key, KeyCode};
+use lsp::ResponseError;
+
+/// How long the word before the point has to be before typing opens completion by itself.
+pub const AUTO_COMPLETE_PREFIX: usize = 3;
+
+impl Editor {
+    pub fn completion(&self) -> Option<&Completion> {
+        self.completion.as_ref()
+    }
+
+    /// add_completion_source() adds `source` to those asked for candidates, after the others.
+    pub fn add_completion_source(&mut self, source: Box<dyn CompletionSource>) {
+        self.completion_sources.push(source);
+    }
+
+    pub fn auto_complete(&self) -> bool {
+        self.auto_complete
+    }
+
+    /// set_auto_complete() sets whether typing a word or a language server's trigger character
+    /// opens completion without asking.
+    pub fn set_auto_complete(&mut self, auto_complete: bool) {
+        self.auto_complete = auto_complete;
+    }
+
+    /// start_completion() asks every completion source, and the language server of the current
+    /// buffer if it has one, for candidates for the text before the point. The candidates are
+    /// shown as a popup to choose from. When the completion was asked for and there is only one
+    /// candidate, it is inserted at once.
+    pub fn start_completion(&mut self, asked: bool) -> CommandResult {
+        let buffer = self.buffer_or_error()?;
+        let point = buffer.get_point();
+
+        let mut completion = Completion::new();
+        for source in self.completion_sources.iter() {
+            let buffer = self.current_buffer().unwrap();
+            let start = source.prefix_start(buffer, point);
+            let prefix = buffer.slice(start..point).into_owned();
+            let items = source.candidates(self, &prefix);
+            completion.add(items.into_iter().map(|item| Candidate { start, item, source: source.name().to_string() }).collect());
+        }
+        completion.set_pending(self.request_lsp_completion(point));
+
+        let buffer = self.current_buffer().unwrap();
+        completion.refilter(buffer, point);
+        let only = match completion.match_count() {
+            1 if asked && completion.pending().is_none() => completion.selected().cloned(),
+            _ => None,
+        };
+
+        match only {
+            Some(candidate) => self.insert_candidate(&candidate),
+            None if completion.match_count() == 0 && completion.pending().is_none() => {
+                if asked {
+                    self.set_message("No completions");
+                }
+                self.completion = None;
+                Ok(())
+            }
+            None => {
+                self.completion = Some(completion);
+                Ok(())
+            }
+        }
+    }
+
+    /// accept_completion() inserts the chosen candidate in place of the text it completes.
+    pub fn accept_completion(&mut self) -> CommandResult {
+        match self.completion.take().and_then(|completion| completion.selected().cloned()) {
+            Some(candidate) => self.insert_candidate(&candidate),
+            None => Ok(()),
+        }
+    }
+
+    pub fn cancel_completion(&mut self) {
+        self.completion = None;
+    }
+
+    /// handle_completion_key() handles the keys that move through the popup, accept it or cancel
+    /// it while it shows candidates. Returns `None` for every other key.
+    pub(super) fn handle_completion_key(&mut self, key: Key) -> Option<CommandResult> {
+        let completion = match self.completion.as_mut() {
+            Some(completion) if completion.match_count() > 0 && self.pending_keys.is_empty() => completion,
+            _ => return None,
+        };
+
+        match (key.code, key.modifiers.control, key.modifiers.meta) {
+            (KeyCode::Down, false, false) | (KeyCode::Char('n'), true, false) => completion.select_next(),
+            (KeyCode::Up, false, false) | (KeyCode::Char('p'), true, false) => completion.select_previous(),
+            (KeyCode::Enter, false, false) | (KeyCode::Tab, false, false) => return Some(self.accept_completion()),
+            (KeyCode::Escape, _, _) | (KeyCode::Char('g'), true, false) => self.cancel_completion(),
+            _ => return None,
+        }
+        Some(Ok(()))
+    }
+
+    /// update_completion() matches the open completion against the text typed since it opened,
+    /// closing it once nothing matches or the point has left what it completes. With
+    /// auto-complete on, a key that typed a long enough word or a trigger character opens one.
+    pub(super) fn update_completion(&mut self, key: Key) {
+        let point = match self.current_buffer() {
+            Some(buffer) => buffer.get_point(),
+            None => return,
+        };
+
+        if let Some(completion) = self.completion.as_mut() {
+            completion.refilter(self.buffers.current().unwrap(), point);
+            if completion.match_count() == 0 && completion.pending().is_none() {
+                self.completion = None;
+            }
+            return;
+        }
+
+        let typed = match key.printable() {
+            Some(character) if self.auto_complete && self.minibuffer.is_none() => character,
+            _ => return,
+        };
+        let buffer = self.current_buffer().unwrap();
+        let words = is_word_char(typed) && point - word_start(buffer, point) >= AUTO_COMPLETE_PREFIX;
+        if words || self.is_trigger_character(typed) {
+            let _ = self.start_completion(false);
+        }
+    }
+
+    /// receive_lsp_completion() adds the candidates a language server answered a completion
+    /// request with to the completion waiting for them.
+    pub(super) fn receive_lsp_completion(&mut self, language_id: &str, id: u64, result: Result<Value, ResponseError>) {
+        let completion = match self.completion.as_mut() {
+            Some(completion) if completion.pending() == Some(&(language_id.to_string(), id)) => completion,
+            _ => return,
+        };
+        completion.set_pending(None);
+
+        let buffer = self.buffers.current().unwrap();
+        let point = buffer.get_point();
+        let encoding = self.language_servers.get(language_id).map(|client| client.encoding()).unwrap_or_default();
+        let result = result.unwrap_or(Value::Null);
+        let items = match result.get("items") {
+            Some(items) => items.as_array().cloned().unwrap_or_default(),
+            None => result.as_array().cloned().unwrap_or_default(),
+        };
+
+        let mut candidates = Vec::new();
+        for item in items.iter() {
+            let label = item["label"].as_str().unwrap_or_default();
+            let edit = &item["textEdit"];
+            let start = edit["range"]["start"]
+                .as_object()
+                .or_else(|| edit["insert"]["start"].as_object())
+                .and_then(|start| {
+                    let line = start.get("line")?.as_u64()? as usize;
+                    let character = start.get("character")?.as_u64()? as usize;
+                    buffer.position_to_offset(Position { line, character }, encoding)
+                })
+                .unwrap_or_else(|| word_start(buffer, point));
+            let text = edit["newText"].as_str().or_else(|| item["insertText"].as_str()).unwrap_or(label);
+
+            let mut candidate = CompletionItem::new(text);
+            candidate.label = label.to_string();
+            candidate.detail = item["detail"].as_str().map(|detail| detail.to_string());
+            candidates.push(Candidate { start, item: candidate, source: "lsp".to_string() });
+        }
+
+        completion.add(candidates);
+        completion.refilter(buffer, point);
+        if completion.match_count() == 0 {
+            self.completion = None;
+        }
+    }
+
+    /// request_lsp_completion() asks the language server of the current buffer for candidates
+    /// at `point`, returning the language and id of the request if one was sent. The changes
+    /// not yet sent go first, so the server completes the text as it is now.
+    fn request_lsp_completion(&mut self, point: usize) -> Option<(String, u64)> {
+        self.send_lsp_changes();
+        let id = self.buffers.current_id()?;
+        let document = self.lsp_documents.get(&id)?;
+        let client = self.language_servers.get_mut(&document.language_id)?;
+        if client.capabilities()["completionProvider"].is_null() {
+            return None;
+        }
+
+        let position = self.buffers.get(id)?.offset_to_position(point, client.encoding());
+        let params = json!({
+            "textDocument": {"uri": document.uri},
+            "position": {"line": position.line, "character": position.character},
+        });
+        let request = client.request("textDocument/completion", params).ok()?;
+        Some((document.language_id.clone(), request))
+    }
+
+    /// is_trigger_character() reports whether the language server of the current buffer asks
+    /// for completion after `character`, such as `.` in many languages.
+    fn is_trigger_character(&self, character: char) -> bool {
+        let document = self.buffers.current_id().and_then(|id| self.lsp_documents.get(&id));
+        let client = document.and_then(|document| self.language_servers.get(&document.language_id));
+        let triggers = client.and_then(|client| client.capabilities()["completionProvider"]["triggerCharacters"].as_array().cloned());
+
+        triggers.unwrap_or_default().iter().any(|trigger| trigger.as_str() == Some(character.to_string().as_str()))
+    }
+
+    fn insert_candidate(&mut self, candidate: &Candidate) -> CommandResult {
+        self.completion = None;
+        let buffer = self.writable_buffer_or_error()?;
+        let point = buffer.get_point();
+
+        buffer.replace_range(candidate.start..point, &candidate.item.text);
+        buffer.set_point(candidate.start + candidate.item.text.len());
+        Ok(())
+    }
+}
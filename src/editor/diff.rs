@@ -0,0 +1,76 @@
+use std::io;
+
+use buffer::file::OpenMode;
+use buffer::{Buffer, Encoding};
+use buffer_manager::BufferId;
+use command::CommandError;
+use diff::{unified_diff, CONTEXT_LINES};
+use editor::Editor;
+use property::TextProperty;
+use theme;
+
+/// The buffer diffs are shown in.
+pub const DIFF_BUFFER: &str = "*Diff*";
+
+/// The group of the text properties coloring the lines of the diff buffer.
+const DIFF_GROUP: &str = "diff";
+
+impl Editor {
+    /// diff_buffer_with_file() shows the changes made to the current buffer since its file was
+    /// last saved, as a unified diff in the diff buffer. A file that is not there yet is taken to
+    /// be empty. Returns false, leaving the diff buffer alone, if there are none.
+    pub fn diff_buffer_with_file(&mut self) -> Result<bool, CommandError> {
+        let buffer = self.buffers.current().ok_or(CommandError::NoBuffer)?;
+        let path = buffer.path().ok_or(CommandError::NoFile)?.to_path_buf();
+        let mode = if buffer.encoding() == Encoding::Bytes { OpenMode::Bytes } else { OpenMode::Detect };
+        let saved = match Buffer::open_with(&path, mode) {
+            Ok(saved) => saved.whole_text(),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        let text = buffer.whole_text();
+        let new_name = format!("{} (buffer)", buffer.name());
+        Ok(self.show_diff(&saved, &text, &path.display().to_string(), &new_name))
+    }
+
+    /// diff_buffers() shows the changes from buffer `old` to buffer `new` as a unified diff in the
+    /// diff buffer, returning false, and leaving it alone, if there are none.
+    pub fn diff_buffers(&mut self, old: BufferId, new: BufferId) -> Result<bool, CommandError> {
+        let old = self.buffers.get(old).ok_or(CommandError::NoBuffer)?;
+        let new = self.buffers.get(new).ok_or(CommandError::NoBuffer)?;
+
+        let (old_text, old_name) = (old.whole_text(), old.name().to_string());
+        let (new_text, new_name) = (new.whole_text(), new.name().to_string());
+        Ok(self.show_diff(&old_text, &new_text, &old_name, &new_name))
+    }
+
+    /// show_diff() shows the diff from `old` to `new` in the diff buffer, coloring its lines by
+    /// what they say, unless there are no changes.
+    fn show_diff(&mut self, old: &str, new: &str, old_name: &str, new_name: &str) -> bool {
+        let text = unified_diff(old, new, old_name, new_name, CONTEXT_LINES);
+        if text.is_empty() {
+            return false;
+        }
+
+        let id = self.show_text_buffer(DIFF_BUFFER, &text);
+        let buffer = self.buffers.get_mut(id).unwrap();
+        buffer.clear_properties(DIFF_GROUP);
+        let mut start = 0;
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            // Only the first two lines name the files; a removed line can start `---` too.
+            let style = match line.chars().next() {
+                _ if index < 2 => Some(theme::DIFF_FILE_HEADER),
+                Some('@') => Some(theme::DIFF_HUNK_HEADER),
+                Some('+') => Some(theme::DIFF_ADDED),
+                Some('-') => Some(theme::DIFF_REMOVED),
+                _ => None,
+            };
+            if let Some(style) = style {
+                buffer.add_property(start..start + line.trim_end_matches('\n').len(), TextProperty::styled(DIFF_GROUP, style));
+            }
+            start += line.len();
+        }
+        true
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use buffer::file::OpenMode;
+use buffer_manager::BufferId;
+use command::CommandError;
+use dired::{format_listing, read_directory, DirEntry};
+use editor::commands::visit_file;
+use editor::Editor;
+
+/// The keys of a dired buffer, besides the global ones.
+const DIRED_KEYS: &[(&str, &str)] = &[
+    ("RET", "dired-find-file"),
+    ("f", "dired-find-file"),
+    ("^", "dired-up-directory"),
+    ("n", "next-line"),
+    ("p", "previous-line"),
+    ("g", "dired-refresh"),
+    ("R", "dired-rename"),
+    ("D", "dired-delete"),
+    ("+", "dired-create-directory"),
+    ("c", "dired-create-file"),
+];
+
+/// The lines of a dired buffer before the first entry.
+const HEADER_LINES: usize = 1;
+
+/// Dired is what a dired buffer lists.
+/// - `directory`: The directory listed.
+/// - `entries`: Its entries, in the order they are listed.
+#[derive(Debug)]
+pub(super) struct Dired {
+    directory: PathBuf,
+    entries: Vec<DirEntry>,
+}
+
+impl Editor {
+    /// dired() lists the entries of `directory` in a buffer of their own, named after it, where
+    /// keys open, rename, delete and create them. Returns the buffer's id.
+    pub fn dired(&mut self, directory: &Path) -> Result<BufferId, CommandError> {
+        let directory = fs::canonicalize(directory)?;
+        let entries = read_directory(&directory)?;
+
+        let name = format!("{}/", directory.display().to_string().trim_end_matches('/'));
+        let id = self.show_text_buffer(&name, &format_listing(&directory, &entries));
+        let keymap = self.buffer_keymap_mut(id);
+        for (keys, command) in DIRED_KEYS.iter() {
+            keymap.bind_str(keys, command).unwrap();
+        }
+
+        let first = self.buffers.get(id).unwrap().line_to_offset(HEADER_LINES).unwrap_or(0);
+        self.buffers.get_mut(id).unwrap().set_point(first);
+        self.dired.insert(id, Dired { directory, entries });
+        Ok(id)
+    }
+
+    /// dired_directory() is the directory the current buffer lists, if it is a dired buffer.
+    pub fn dired_directory(&self) -> Option<&Path> {
+        let id = self.buffers.current_id()?;
+        self.dired.get(&id).map(|dired| dired.directory.as_path())
+    }
+
+    /// dired_entry() is the entry on the line of the point in the current dired buffer, and the
+    /// path to it.
+    pub fn dired_entry(&self) -> Option<(PathBuf, &DirEntry)> {
+        let id = self.buffers.current_id()?;
+        let dired = self.dired.get(&id)?;
+        let buffer = self.buffers.get(id)?;
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let entry = dired.entries.get(line.checked_sub(HEADER_LINES)?)?;
+
+        let path = match entry.name.as_str() {
+            ".." => dired.directory.parent()?.to_path_buf(),
+            name => dired.directory.join(name),
+        };
+        Some((path, entry))
+    }
+
+    /// refresh_dired() lists the directory of the current dired buffer again, as after its
+    /// entries have changed, keeping the point on the same line. Returns false if the current
+    /// buffer is not a dired buffer.
+    pub fn refresh_dired(&mut self) -> Result<bool, CommandError> {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let directory = match self.dired.get(&id) {
+            Some(dired) => dired.directory.clone(),
+            None => return Ok(false),
+        };
+        let buffer = self.buffers.get(id).unwrap();
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+
+        self.dired(&directory)?;
+        let buffer = self.buffers.get_mut(id).unwrap();
+        let line = line.min(buffer.line_count().saturating_sub(2));
+        let offset = buffer.line_to_offset(line).unwrap_or(0);
+        buffer.set_point(offset);
+        Ok(true)
+    }
+
+    /// dired_find_file() visits the entry at the point of the current dired buffer: a file in a
+    /// buffer of its own, or a directory in another dired buffer. Returns false if the point is
+    /// not on an entry.
+    pub fn dired_find_file(&mut self) -> Result<bool, CommandError> {
+        let (path, is_directory) = match self.dired_entry() {
+            Some((path, entry)) => (path, entry.is_directory),
+            None => return Ok(false),
+        };
+
+        if is_directory {
+            self.dired(&path)?;
+        } else {
+            visit_file(self, "dired-find-file", &path.to_string_lossy(), OpenMode::Detect)?;
+        }
+        Ok(true)
+    }
+}
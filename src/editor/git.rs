@@ -0,0 +1,332 @@
+use std::iter;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use buffer::Buffer;
+use buffer_manager::BufferId;
+use command::CommandError;
+use delta::{Position, PositionEncoding};
+use diff::{Hunk, HunkKind};
+use editor::{Editor, Location};
+use events::{EditEvent, ListenerId};
+use git::{self, describe_age, head_text, line_changes, split_lines};
+use sign::Sign;
+use theme;
+
+/// Who the signs marking changed lines are put in the gutter by.
+pub const GIT_SIGNS: &str = "git";
+
+/// The buffer `blame()` shows who last changed each line of a file in.
+pub const BLAME_BUFFER: &str = "*blame*";
+
+/// How many characters of an author's name the blame buffer shows.
+const MAX_AUTHOR_WIDTH: usize = 20;
+
+/// GitChanges is what the gutter of a buffer whose file git tracks is marked from.
+/// - `head`: The file's text in the last commit, read again each time the buffer is saved.
+/// - `listener`, `edits`: Tell when the buffer changes, so its signs are worked out again.
+#[derive(Debug)]
+pub(super) struct GitChanges {
+    head: String,
+    listener: ListenerId,
+    edits: Receiver<EditEvent>,
+}
+
+/// Blame is the file the blame buffer is about, so its lines can be gone to.
+#[derive(Debug)]
+pub(super) struct Blame {
+    path: PathBuf,
+}
+
+impl Editor {
+    /// track_git_changes() marks the lines of buffer `id` that differ from its file in the last
+    /// commit with signs in the gutter, and keeps them up to date as it is edited and saved: a
+    /// `+` beside added lines, a `~` beside changed ones and a `-` where lines were removed.
+    /// Returns false if the buffer does not visit a file git tracks.
+    pub fn track_git_changes(&mut self, id: BufferId) -> bool {
+        let head = match self.buffers.get(id).and_then(|buffer| buffer.path()).and_then(head_text) {
+            Some(head) => head,
+            None => return false,
+        };
+        if let Some(changes) = self.git_changes.get_mut(&id) {
+            changes.head = head;
+        } else {
+            let (listener, edits) = self.buffers.get_mut(id).unwrap().subscribe();
+            self.git_changes.insert(id, GitChanges { head, listener, edits });
+        }
+
+        self.mark_git_changes(id);
+        true
+    }
+
+    /// untrack_git_changes() takes the signs of changed lines out of the gutter of buffer `id`,
+    /// returning false if there were none being kept.
+    pub fn untrack_git_changes(&mut self, id: BufferId) -> bool {
+        let changes = match self.git_changes.remove(&id) {
+            Some(changes) => changes,
+            None => return false,
+        };
+        if let Some(buffer) = self.buffers.get_mut(id) {
+            buffer.remove_listener(changes.listener);
+            buffer.clear_signs(GIT_SIGNS);
+        }
+        true
+    }
+
+    /// is_tracking_git_changes() reports whether the gutter of buffer `id` marks changed lines.
+    pub fn is_tracking_git_changes(&self, id: BufferId) -> bool {
+        self.git_changes.contains_key(&id)
+    }
+
+    /// refresh_git_changes() reads the last commit of buffer `id`'s file again if its changed lines
+    /// are marked, as after saving it, when it may have been committed since.
+    pub(super) fn refresh_git_changes(&mut self, id: BufferId) {
+        if self.is_tracking_git_changes(id) && !self.track_git_changes(id) {
+            self.untrack_git_changes(id);
+        }
+    }
+
+    /// sync_git_changes() marks the changed lines again in each buffer edited since the last
+    /// call, which a frontend should do each time around its event loop. Buffers that were
+    /// killed are forgotten.
+    pub fn sync_git_changes(&mut self) {
+        let buffers = &self.buffers;
+        self.git_changes.retain(|id, _| buffers.get(*id).is_some());
+
+        let edited: Vec<BufferId> = self
+            .git_changes
+            .iter()
+            .filter(|(_, changes)| changes.edits.try_iter().count() > 0)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in edited {
+            self.mark_git_changes(id);
+        }
+    }
+
+    /// git_hunks() returns the runs of lines of buffer `id` that differ from its file in the last
+    /// commit, as they are now. The whole text is compared, but only the runs that reach the
+    /// accessible text of a narrowed buffer are returned, with their lines counted in it and cut
+    /// to fit it.
+    pub fn git_hunks(&self, id: BufferId) -> Vec<Hunk> {
+        let (changes, buffer) = match (self.git_changes.get(&id), self.buffers.get(id)) {
+            (Some(changes), Some(buffer)) => (changes, buffer),
+            _ => return Vec::new(),
+        };
+        let text = buffer.whole_text();
+        let hidden = hidden_lines(buffer);
+        let end = hidden + buffer.line_count();
+
+        line_changes(&changes.head, &text)
+            .into_iter()
+            .filter(|hunk| match hunk.new.is_empty() {
+                true => (hidden..=end).contains(&hunk.new.start),
+                false => hunk.new.start < end && hunk.new.end > hidden,
+            })
+            .map(|hunk| Hunk {
+                old: hunk.old,
+                new: hunk.new.start.max(hidden) - hidden..hunk.new.end.min(end) - hidden,
+            })
+            .collect()
+    }
+
+    /// next_hunk() moves the point to the start of the next run of changed lines in the current
+    /// buffer, or the one before if `forward` is false, returning false if there is none.
+    pub fn next_hunk(&mut self, forward: bool) -> Result<bool, CommandError> {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let hunks = self.git_hunks(id);
+        let buffer = self.buffers.get_mut(id).unwrap();
+        let last = buffer.line_count() - 1;
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+
+        let mut starts = hunks.iter().map(|hunk| hunk.new.start.min(last));
+        let found = match forward {
+            true => starts.find(|start| *start > line),
+            false => starts.rev().find(|start| *start < line),
+        };
+        match found {
+            Some(start) => {
+                let offset = buffer.line_to_offset(start).unwrap();
+                buffer.set_point(offset);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// revert_hunk() puts the lines of the run of changes the point is in back as they were in
+    /// the last commit, returning false if the point is not in one, or if narrowing hides part
+    /// of it.
+    pub fn revert_hunk(&mut self) -> Result<bool, CommandError> {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let head = match self.git_changes.get(&id) {
+            Some(changes) => changes.head.clone(),
+            None => return Ok(false),
+        };
+        let buffer = self.writable_buffer_or_error()?;
+        let hunks = line_changes(&head, &buffer.whole_text());
+        let head = split_lines(&head);
+        // The lossy text can be longer than the bytes, so offsets are counted in the bytes.
+        let (front, back) = buffer.whole_slices();
+        let whole_len = front.len() + back.len();
+        let newlines = front.iter().chain(back).enumerate().filter(|(_, byte)| **byte == b'\n');
+        let line_starts: Vec<usize> = iter::once(0).chain(newlines.map(|(offset, _)| offset + 1)).collect();
+        let line_count = line_starts.len();
+        let before = buffer.hidden_before();
+        let hidden = hidden_lines(buffer);
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let line = hidden + line;
+
+        let beside = |hunk: &&Hunk| hunk.new.contains(&line) || (hunk.new.is_empty() && hunk.new.start.min(line_count - 1) == line);
+        let hunk = match hunks.iter().find(beside) {
+            Some(hunk) => hunk,
+            None => return Ok(false),
+        };
+        let old = &head[hunk.old.clone()];
+
+        // The last line has no newline after it, so a run reaching it takes the one before.
+        let (range, old_text) = match (hunk.new.end < line_count, hunk.new.start) {
+            (true, start) => (line_starts[start]..line_starts[hunk.new.end], old.iter().map(|line| format!("{}\n", line)).collect::<String>()),
+            (false, 0) => (0..whole_len, old.join("\n")),
+            (false, start) => (line_starts[start] - 1..whole_len, old.iter().map(|line| format!("\n{}", line)).collect::<String>()),
+        };
+        if range.start < before || range.end > before + buffer.len() {
+            return Ok(false);
+        }
+        buffer.replace_range(range.start - before..range.end - before, &old_text);
+        let start = buffer.line_to_offset((hunk.new.start - hidden).min(buffer.line_count() - 1)).unwrap();
+        buffer.set_point(start);
+        Ok(true)
+    }
+
+    /// blame() lists who last changed each line of the current buffer's file, and when, in the
+    /// blame buffer: the commit, its author and how long ago it was, beside the line. The point
+    /// is put on the line the buffer's point is on, and `RET` goes to the line listed at the
+    /// point. The file is blamed as it was last saved. Returns how many lines were listed.
+    pub fn blame(&mut self) -> Result<usize, CommandError> {
+        let buffer = self.buffers.current().ok_or(CommandError::NoBuffer)?;
+        let path = buffer.path().ok_or(CommandError::NoFile)?.to_path_buf();
+        let (point_line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let lines = git::blame(&path)?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        let ages: Vec<String> = lines.iter().map(|line| describe_age(now.saturating_sub(line.time))).collect();
+        let authors: Vec<String> = lines.iter().map(|line| line.author.chars().take(MAX_AUTHOR_WIDTH).collect()).collect();
+        let author_width = authors.iter().map(|author| author.chars().count()).max().unwrap_or(0);
+        let age_width = ages.iter().map(|age| age.len()).max().unwrap_or(0);
+        let mut text = String::new();
+        for ((line, author), age) in lines.iter().zip(authors.iter()).zip(ages.iter()) {
+            text.push_str(&format!("{:.8} {:<author_width$} {:>age_width$} | {}\n", line.commit, author, age, line.text));
+        }
+
+        let id = self.show_text_buffer(BLAME_BUFFER, &text);
+        self.buffer_keymap_mut(id).bind_str("RET", "blame-goto-line").unwrap();
+        let buffer = self.buffers.get_mut(id).unwrap();
+        let start = buffer.line_to_offset(point_line.min(buffer.line_count() - 1)).unwrap();
+        buffer.set_point(start);
+        self.blame = Some(Blame { path });
+        Ok(lines.len())
+    }
+
+    /// blame_goto_line() goes to the line of the file listed at the point of the blame buffer,
+    /// returning false if the point is not on a listed line.
+    pub fn blame_goto_line(&mut self) -> Result<bool, CommandError> {
+        let buffer = self.buffers.current().ok_or(CommandError::NoBuffer)?;
+        let blame = match self.blame.as_ref() {
+            Some(blame) if buffer.name() == BLAME_BUFFER => blame,
+            _ => return Ok(false),
+        };
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        if line + 1 >= buffer.line_count() {
+            return Ok(false);
+        }
+
+        let location = Location {
+            path: blame.path.clone(),
+            position: Position { line, character: 0 },
+            encoding: PositionEncoding::Utf8,
+        };
+        self.visit_location(&location)?;
+        Ok(true)
+    }
+
+    /// mark_git_changes() replaces the signs of changed lines in buffer `id`'s gutter.
+    fn mark_git_changes(&mut self, id: BufferId) {
+        let signs: Vec<Sign> = self
+            .git_hunks(id)
+            .into_iter()
+            .map(|hunk| {
+                let (text, style) = match hunk.kind() {
+                    HunkKind::Added => ('+', theme::GIT_ADDED),
+                    HunkKind::Modified => ('~', theme::GIT_MODIFIED),
+                    HunkKind::Removed => ('-', theme::GIT_REMOVED),
+                };
+                Sign { lines: hunk.new, text, style: style.to_string() }
+            })
+            .collect();
+
+        if let Some(buffer) = self.buffers.get_mut(id) {
+            buffer.set_signs(GIT_SIGNS, signs);
+        }
+    }
+}
+
+/// hidden_lines() is how many lines narrowing hides before the accessible text of `buffer`,
+/// counted in its bytes, as invalid UTF-8 makes the lossy whole text longer than them.
+fn hidden_lines(buffer: &Buffer) -> usize {
+    let (front, back) = buffer.whole_slices();
+    front.iter().chain(back).take(buffer.hidden_before()).filter(|byte| **byte == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::Buffer;
+    use diff::Hunk;
+    use editor::git::GitChanges;
+    use editor::Editor;
+
+    #[test]
+    fn find_and_revert_changes_in_a_narrowed_buffer() {
+        let mut editor = Editor::new();
+        let id = editor.buffers_mut().add(Buffer::from_text("notes", "one\ntwo\nTHREE\nfour\nFIVE\n".to_string()));
+        editor.buffers_mut().switch_to(id);
+        let (listener, edits) = editor.buffers_mut().get_mut(id).unwrap().subscribe();
+        let head = "one\ntwo\nthree\nfour\nfive\n".to_string();
+        editor.git_changes.insert(id, GitChanges { head, listener, edits });
+
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.narrow(14..24);
+        buffer.set_point(6);
+        assert_eq!(editor.git_hunks(id), vec![Hunk { old: 4..5, new: 1..2 }]);
+        assert!(editor.revert_hunk().unwrap());
+        assert_eq!(editor.current_buffer().unwrap().whole_text(), "one\ntwo\nTHREE\nfour\nfive\n");
+
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.widen();
+        buffer.narrow(10..24);
+        buffer.set_point(0);
+        assert_eq!(editor.git_hunks(id), vec![Hunk { old: 2..3, new: 0..1 }]);
+        assert!(!editor.revert_hunk().unwrap());
+        assert_eq!(editor.current_buffer().unwrap().whole_text(), "one\ntwo\nTHREE\nfour\nfive\n");
+    }
+
+    #[test]
+    fn count_hidden_lines_over_invalid_utf8() {
+        let mut editor = Editor::new();
+        let id = editor.buffers_mut().add(Buffer::from_text("notes", "one\ntwo\nTHREE\nfour\n".to_string()));
+        editor.buffers_mut().switch_to(id);
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.insert_bytes(b"\xff\xff\n".to_vec());
+        let (listener, edits) = buffer.subscribe();
+        let head = "\u{fffd}\u{fffd}\none\ntwo\nthree\nfour\n".to_string();
+        editor.git_changes.insert(id, GitChanges { head, listener, edits });
+
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.narrow(11..22);
+        buffer.set_point(0);
+        assert_eq!(editor.git_hunks(id), vec![Hunk { old: 3..4, new: 0..1 }]);
+        assert!(editor.revert_hunk().unwrap());
+        assert_eq!(editor.current_buffer().unwrap().whole_text(), "\u{fffd}\u{fffd}\none\ntwo\nthree\nfour\n");
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use buffer_manager::BufferId;
+use command::CommandError;
+use delta::{Position, PositionEncoding};
+use editor::{Editor, Location};
+use grep::{parse_location, Grep, GrepEvent};
+
+/// The buffer `start_grep()` lists the matches in.
+pub const GREP_BUFFER: &str = "*grep*";
+
+/// GrepSearch is the search listed in the grep buffer.
+/// - `root`: The directory searched, which the paths listed are relative to.
+/// - `buffer`: The grep buffer.
+/// - `grep`: The search, while it is still running.
+/// - `matches`: How many matches have been listed.
+#[derive(Debug)]
+pub(super) struct GrepSearch {
+    root: PathBuf,
+    buffer: BufferId,
+    grep: Option<Grep>,
+    matches: usize,
+}
+
+impl Editor {
+    /// start_grep() searches the files under `root` for `pattern` in the background, stopping any
+    /// search still running. The matching lines are listed in the grep buffer as they are found,
+    /// and `RET` there goes to the match at the point.
+    pub fn start_grep(&mut self, root: &Path, pattern: &str) {
+        let header = format!("Grep for \"{}\" in {}\n\n", pattern, root.display());
+        let id = self.show_text_buffer(GREP_BUFFER, &header);
+        self.buffer_keymap_mut(id).bind_str("RET", "grep-goto").unwrap();
+
+        self.grep = Some(GrepSearch {
+            root: root.to_path_buf(),
+            buffer: id,
+            grep: Some(Grep::spawn(root, pattern)),
+            matches: 0,
+        });
+    }
+
+    /// grep_running() reports whether a grep is still searching.
+    pub fn grep_running(&self) -> bool {
+        self.grep.as_ref().is_some_and(|search| search.grep.is_some())
+    }
+
+    /// sync_grep() adds what the running grep has found since the last call to the grep buffer,
+    /// which a frontend should do each time around its event loop. Returns false once there is
+    /// no grep running.
+    pub fn sync_grep(&mut self) -> bool {
+        let search = match self.grep.as_mut() {
+            Some(search) => search,
+            None => return false,
+        };
+        let events = match search.grep.as_mut() {
+            Some(grep) => grep.poll(),
+            None => return false,
+        };
+
+        let mut text = String::new();
+        let mut finished = false;
+        for event in events {
+            match event {
+                GrepEvent::Match(found) => {
+                    search.matches += 1;
+                    text.push_str(&found.to_line());
+                    text.push('\n');
+                }
+                GrepEvent::Finished => finished = true,
+            }
+        }
+
+        let (id, matches) = (search.buffer, search.matches);
+        if finished {
+            search.grep = None;
+            let plural = if matches == 1 { "" } else { "es" };
+            let summary = format!("Grep finished with {} match{}", matches, plural);
+            text.push_str(&format!("\n{}\n", summary));
+            self.set_message(&summary);
+        }
+        self.append_to_text_buffer(id, &text);
+        !finished
+    }
+
+    /// grep_goto() visits the match listed on the line of the point in the grep buffer, returning
+    /// false if there is none there.
+    pub fn grep_goto(&mut self) -> Result<bool, CommandError> {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let search = match self.grep.as_ref() {
+            Some(search) if search.buffer == id => search,
+            _ => return Ok(false),
+        };
+
+        let buffer = self.buffers.get(id).unwrap();
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let start = buffer.line_to_offset(line).unwrap();
+        let end = buffer.line_end_offset(line).unwrap();
+        let (path, line, column) = match parse_location(&buffer.slice(start..end)) {
+            Some(location) => location,
+            None => return Ok(false),
+        };
+
+        let location = Location {
+            path: search.root.join(path),
+            position: Position { line, character: column },
+            encoding: PositionEncoding::Utf8,
+        };
+        self.visit_location(&location)?;
+        Ok(true)
+    }
+}
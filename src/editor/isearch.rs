@@ -0,0 +1,290 @@
+use std::ops::Range;
+
+use buffer_manager::BufferId;
+use command::{CommandError, CommandResult};
+use editor::Editor;
+use keymap::{Key, KeyCode};
+use property::TextProperty;
+use search::SearchOptions;
+use theme;
+use window::{Area, WindowId};
+use GapBuffer;
+
+/// The group of the text properties marking the matches of an incremental search.
+const ISEARCH_GROUP: &str = "isearch";
+
+/// IsearchStep is where an incremental search was after one of its keys.
+/// - `found`: The match the point is at, or `None` if there has been none since the query
+///   changed.
+/// - `wrapped`: Whether the search has gone past the end of the buffer to search from the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IsearchStep {
+    query: String,
+    point: usize,
+    found: Option<Range<usize>>,
+    backward: bool,
+    wrapped: bool,
+}
+
+/// Isearch is an incremental search, which moves to the next match of what has been typed so far
+/// with each key.
+/// - `buffer`: The buffer being searched.
+/// - `origin`: Where the point was when the search started, to go back to if it is cancelled.
+/// - `step`: Where the search is now.
+/// - `steps`: Where the search was before each key still in effect, for backspace to go back to.
+#[derive(Debug, Clone)]
+pub struct Isearch {
+    buffer: BufferId,
+    origin: usize,
+    step: IsearchStep,
+    steps: Vec<IsearchStep>,
+}
+
+impl Isearch {
+    pub fn query(&self) -> &str {
+        &self.step.query
+    }
+
+    pub fn is_backward(&self) -> bool {
+        self.step.backward
+    }
+
+    /// is_failing() reports whether nothing matches the query where the search has looked.
+    pub fn is_failing(&self) -> bool {
+        !self.step.query.is_empty() && self.step.found.is_none()
+    }
+
+    /// current_match() is the range of the match the point is at.
+    pub fn current_match(&self) -> Option<Range<usize>> {
+        self.step.found.clone()
+    }
+
+    /// prompt() describes the search for the echo area, as `Failing I-search backward: query`.
+    pub fn prompt(&self) -> String {
+        let failing = if self.is_failing() { "Failing " } else { "" };
+        let wrapped = if self.step.wrapped { "Wrapped " } else { "" };
+        let direction = if self.step.backward { " backward" } else { "" };
+
+        format!("{}{}I-search{}: {}", failing, wrapped, direction, self.step.query)
+    }
+}
+
+/// isearch_options() searches case-insensitively unless `query` has an uppercase letter.
+fn isearch_options(query: &str, backward: bool) -> SearchOptions {
+    SearchOptions {
+        case_insensitive: !query.chars().any(char::is_uppercase),
+        backward,
+        ..SearchOptions::default()
+    }
+}
+
+/// find_match() is the first match of `query` in `buffer` from `from` in the direction searched:
+/// starting at or after it forward, or ending at or before it backward.
+fn find_match(buffer: &GapBuffer, query: &str, from: usize, backward: bool) -> Option<Range<usize>> {
+    buffer.search(query, from, isearch_options(query, backward)).next()
+}
+
+impl Editor {
+    pub fn isearch(&self) -> Option<&Isearch> {
+        self.isearch.as_ref()
+    }
+
+    /// start_isearch() starts an incremental search of the current buffer from the point.
+    pub fn start_isearch(&mut self, backward: bool) -> CommandResult {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let point = self.buffers.current().unwrap().get_point();
+
+        let isearch = Isearch {
+            buffer: id,
+            origin: point,
+            step: IsearchStep { query: String::new(), point, found: None, backward, wrapped: false },
+            steps: Vec::new(),
+        };
+        self.set_message(&isearch.prompt());
+        self.isearch = Some(isearch);
+        Ok(())
+    }
+
+    /// handle_isearch_key() runs `key` as part of the incremental search in progress: characters
+    /// are added to the query, `C-s` and `C-r` move to the next and previous match, backspace
+    /// undoes the last key, `RET` ends the search where it is and `C-g` or escape goes back to
+    /// where it started. `RET` before anything else asks for text to search for all at once, as
+    /// `search-forward` and `search-backward` do. Any other key ends the search and returns
+    /// `None` to be run as usual.
+    pub(super) fn handle_isearch_key(&mut self, key: Key) -> Option<CommandResult> {
+        let isearch = self.isearch.as_ref()?;
+        if self.buffers.current_id() != Some(isearch.buffer) {
+            self.isearch = None;
+            return None;
+        }
+
+        match (key.code, key.modifiers.control, key.modifiers.meta) {
+            (KeyCode::Char('s'), true, false) => self.repeat_isearch(false),
+            (KeyCode::Char('r'), true, false) => self.repeat_isearch(true),
+            (KeyCode::Backspace, false, false) => self.undo_isearch_step(),
+            (KeyCode::Enter, false, false) if isearch.step.query.is_empty() && isearch.steps.is_empty() => {
+                let command = if isearch.step.backward { "search-backward" } else { "search-forward" };
+                self.isearch = None;
+                return Some(self.call_interactively(command));
+            }
+            (KeyCode::Enter, false, false) => {
+                self.finish_isearch();
+                return Some(Ok(()));
+            }
+            (KeyCode::Char('g'), true, false) | (KeyCode::Escape, _, _) => {
+                self.cancel_isearch();
+                return Some(Ok(()));
+            }
+            _ => match key.printable() {
+                Some(character) => self.extend_isearch(character),
+                None => {
+                    self.finish_isearch();
+                    return None;
+                }
+            },
+        }
+
+        let prompt = self.isearch.as_ref().unwrap().prompt();
+        self.set_message(&prompt);
+        Some(Ok(()))
+    }
+
+    /// finish_isearch() ends the incremental search with the point where it is, recording where
+    /// it started on the jump list and its query as the last search.
+    pub fn finish_isearch(&mut self) {
+        let isearch = match self.isearch.take() {
+            Some(isearch) => isearch,
+            None => return,
+        };
+        if !isearch.step.query.is_empty() {
+            self.last_search = Some(isearch.step.query.clone());
+        }
+
+        let buffer = self.buffers.get_mut(isearch.buffer).unwrap();
+        let point = buffer.get_point();
+        if point != isearch.origin {
+            buffer.set_point(isearch.origin);
+            self.push_jump();
+            self.buffers.get_mut(isearch.buffer).unwrap().set_point(point);
+        }
+    }
+
+    /// cancel_isearch() ends the incremental search and puts the point back where it started.
+    pub fn cancel_isearch(&mut self) {
+        if let Some(isearch) = self.isearch.take() {
+            if let Some(buffer) = self.buffers.get_mut(isearch.buffer) {
+                buffer.set_point(isearch.origin);
+            }
+            self.set_message("Quit");
+        }
+    }
+
+    /// extend_isearch() adds `character` to the query, keeping to the match the point is at if it
+    /// still matches, or moving on to the next one that does.
+    fn extend_isearch(&mut self, character: char) {
+        let isearch = self.isearch.as_mut().unwrap();
+        let buffer = self.buffers.get_mut(isearch.buffer).unwrap();
+        let mut step = isearch.step.clone();
+        let failing = isearch.is_failing();
+        step.query.push(character);
+
+        if !failing {
+            let from = match (step.found.as_ref(), step.backward) {
+                (Some(found), false) => found.start,
+                (Some(found), true) => found.start + step.query.len(),
+                (None, _) => step.point,
+            };
+            step.found = find_match(buffer, &step.query, from.min(buffer.len()), step.backward);
+        }
+        if let Some(found) = step.found.as_ref() {
+            step.point = if step.backward { found.start } else { found.end };
+            buffer.set_point(step.point);
+        }
+
+        isearch.steps.push(std::mem::replace(&mut isearch.step, step));
+    }
+
+    /// repeat_isearch() moves to the next match in the direction given by `backward`. A search
+    /// that is failing starts again from the other end of the buffer, and one with no query yet
+    /// searches for the last text searched for.
+    fn repeat_isearch(&mut self, backward: bool) {
+        let last_search = self.last_search.clone();
+        let isearch = self.isearch.as_mut().unwrap();
+        let buffer = self.buffers.get_mut(isearch.buffer).unwrap();
+        let mut step = isearch.step.clone();
+
+        let from = if step.query.is_empty() {
+            match last_search {
+                Some(query) => step.query = query,
+                None => return,
+            }
+            step.point
+        } else if isearch.is_failing() && step.backward == backward {
+            step.wrapped = true;
+            if backward { buffer.len() } else { 0 }
+        } else {
+            match (step.found.as_ref(), backward) {
+                (Some(found), false) => found.end,
+                (Some(found), true) => found.start,
+                (None, _) => step.point,
+            }
+        };
+        step.backward = backward;
+
+        step.found = find_match(buffer, &step.query, from, backward);
+        if let Some(found) = step.found.as_ref() {
+            step.point = if backward { found.start } else { found.end };
+            buffer.set_point(step.point);
+        }
+
+        isearch.steps.push(std::mem::replace(&mut isearch.step, step));
+    }
+
+    /// undo_isearch_step() goes back to where the search was before its last key.
+    fn undo_isearch_step(&mut self) {
+        let isearch = self.isearch.as_mut().unwrap();
+        if let Some(step) = isearch.steps.pop() {
+            isearch.step = step;
+            let point = isearch.step.point;
+            self.buffers.get_mut(isearch.buffer).unwrap().set_point(point);
+        }
+    }
+
+    /// show_isearch_matches() marks every match of the incremental search visible in the selected
+    /// window, and the one the point is at in a style of its own. The marks are taken off the
+    /// other buffers shown in `areas`.
+    pub(super) fn show_isearch_matches(&mut self, areas: &[(WindowId, Area)]) {
+        for (id, _) in areas.iter() {
+            let buffer_id = self.windows.get(*id).unwrap().buffer();
+            if let Some(buffer) = self.buffers.get_mut(buffer_id) {
+                buffer.clear_properties(ISEARCH_GROUP);
+            }
+        }
+
+        let isearch = match self.isearch.as_ref() {
+            Some(isearch) if !isearch.step.query.is_empty() => isearch,
+            _ => return,
+        };
+        let window = self.windows.selected();
+        let buffer = match self.buffers.get_mut(isearch.buffer) {
+            Some(buffer) if window.buffer() == isearch.buffer => buffer,
+            _ => return,
+        };
+
+        let rows = window.view().visible_rows(buffer);
+        let visible = match (rows.first(), rows.last()) {
+            (Some(first), Some(last)) => first.range.start..last.range.end,
+            _ => return,
+        };
+        let query = &isearch.step.query;
+        let matches: Vec<Range<usize>> = buffer
+            .search(query, visible.start, isearch_options(query, false))
+            .take_while(|found| found.start < visible.end)
+            .collect();
+
+        for found in matches {
+            let style = if isearch.step.found.as_ref() == Some(&found) { theme::SEARCH_MATCH_CURRENT } else { theme::SEARCH_MATCH };
+            buffer.add_property(found, TextProperty::styled(ISEARCH_GROUP, style));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use buffer_manager::BufferId;
+use editor::Editor;
+use job::{describe_exit, Job, JobEvent};
+
+/// The buffer `list_jobs()` lists the jobs in.
+pub const JOBS_BUFFER: &str = "*jobs*";
+
+/// The buffer the output of `async-shell-command` goes to.
+pub const ASYNC_SHELL_BUFFER: &str = "*Async Shell Command*";
+
+/// JobId identifies a job started by an editor. Ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JobId(pub usize);
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// BackgroundJob is a job the editor started.
+/// - `id`: How commands refer to it.
+/// - `job`: The process, running or not.
+/// - `buffer`: The read-only buffer its output goes to.
+#[derive(Debug)]
+pub(super) struct BackgroundJob {
+    id: JobId,
+    job: Job,
+    buffer: BufferId,
+}
+
+impl Editor {
+    /// start_job() runs `command_line` in `directory` in the background and shows the read-only
+    /// buffer called `buffer_name`, emptied, which what the command writes is added to as it
+    /// comes in. A job still writing to that buffer is killed first. Returns the new job's id.
+    pub fn start_job(&mut self, command_line: &str, directory: &Path, buffer_name: &str) -> io::Result<JobId> {
+        let job = Job::spawn(command_line, directory)?;
+        let buffer = self.show_text_buffer(buffer_name, "");
+        self.jobs.retain(|background| background.buffer != buffer || !background.job.is_running());
+
+        let id = JobId(self.next_job_id);
+        self.next_job_id += 1;
+        self.jobs.push(BackgroundJob { id, job, buffer });
+        Ok(id)
+    }
+
+    /// jobs() visits the jobs started, running or finished, the oldest first.
+    pub fn jobs(&self) -> impl Iterator<Item = (JobId, &Job)> {
+        self.jobs.iter().map(|background| (background.id, &background.job))
+    }
+
+    pub fn job(&self, id: JobId) -> Option<&Job> {
+        self.jobs.iter().find(|background| background.id == id).map(|background| &background.job)
+    }
+
+    /// buffer_job() is the job that last wrote to buffer `id`, if one did.
+    pub fn buffer_job(&self, id: BufferId) -> Option<JobId> {
+        self.jobs.iter().rev().find(|background| background.buffer == id).map(|background| background.id)
+    }
+
+    /// kill_job() stops job `id`, and returns false if there is no such job still running.
+    pub fn kill_job(&mut self, id: JobId) -> bool {
+        match self.jobs.iter_mut().find(|background| background.id == id) {
+            Some(background) => background.job.kill(),
+            None => false,
+        }
+    }
+
+    /// sync_jobs() adds what each running job has written since the last call to its buffer,
+    /// and how it exited once it has, which a frontend should do each time around its event loop.
+    /// Returns whether any job is still running.
+    pub fn sync_jobs(&mut self) -> bool {
+        let mut output = Vec::new();
+        let mut exits = Vec::new();
+        for background in self.jobs.iter_mut().filter(|background| background.job.is_running()) {
+            let mut text = String::new();
+            for event in background.job.poll() {
+                match event {
+                    JobEvent::Output(line) => text.push_str(&line),
+                    JobEvent::Exited(status) => {
+                        let summary = format!("Job {} ({}) {}", background.id, background.job.command_line(), describe_exit(status));
+                        text.push_str(&format!("\n{}\n", summary));
+                        exits.push(summary);
+                    }
+                }
+            }
+            output.push((background.buffer, text));
+        }
+
+        for (buffer, text) in output.into_iter().filter(|(_, text)| !text.is_empty()) {
+            self.append_to_text_buffer(buffer, &text);
+        }
+        for summary in exits {
+            self.set_message(&summary);
+        }
+        self.jobs.iter().any(|background| background.job.is_running())
+    }
+
+    /// list_jobs() shows the jobs started, with what they run and whether they are running, in a
+    /// buffer of their own.
+    pub fn list_jobs(&mut self) -> BufferId {
+        let mut text = "  Id  Status             Command\n".to_string();
+        for background in self.jobs.iter() {
+            let status = background.job.status().map_or("running".to_string(), describe_exit);
+            text.push_str(&format!("  {:>2}  {:<18} {}\n", background.id, status, background.job.command_line()));
+        }
+        self.show_text_buffer(JOBS_BUFFER, &text)
+    }
+}
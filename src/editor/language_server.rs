@@ -0,0 +1,542 @@
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use buffer_manager::BufferId;
+use delta::{Position, PositionEncoding, TextChange};
+use diagnostic::{Diagnostic, Severity};
+use editor::{Editor, Location};
+use events::ListenerId;
+use lsp::{self, path_to_uri, uri_to_path, LspClient, LspError, LspEvent, ResponseError, ServerConfig, TextDocumentSync};
+use project;
+use serde_json::{json, Value};
+
+/// The reporter of the diagnostics language servers publish, as the buffers keep them.
+pub const LSP_DIAGNOSTICS: &str = "lsp";
+
+/// LspDocument is a buffer open in a language server.
+/// - `language_id`: The language of the server it is open in.
+/// - `uri`: The URI of the buffer's file, which the server knows it by.
+/// - `version`: The version last sent, counting up with each change.
+/// - `listener`: The buffer's change listener feeding `changes`.
+/// - `changes`: The changes not yet sent to the server.
+#[derive(Debug)]
+pub struct LspDocument {
+    pub language_id: String,
+    pub uri: String,
+    pub version: i64,
+    listener: ListenerId,
+    changes: Receiver<TextChange>,
+}
+
+impl Editor {
+    /// start_language_server() starts the server `config` describes, for the project of the
+    /// current buffer's file, and opens every buffer of its language in it.
+    pub fn start_language_server(&mut self, config: &ServerConfig) -> Result<(), LspError> {
+        let path = self.current_buffer().and_then(|buffer| buffer.path()).map(Path::to_path_buf);
+        let root = match path {
+            Some(path) => project::root(&path),
+            None => std::env::current_dir()?,
+        };
+
+        let client = LspClient::spawn(config, &root)?;
+        self.add_language_server(&config.language_id, client)
+    }
+
+    /// add_language_server() uses the initialized `client` for files of `language_id`, stopping
+    /// any server it had before, and opens every buffer of the language in it.
+    pub fn add_language_server(&mut self, language_id: &str, client: LspClient) -> Result<(), LspError> {
+        if self.language_servers.contains_key(language_id) {
+            self.stop_language_server(language_id)?;
+        }
+
+        self.language_servers.insert(language_id.to_string(), client);
+        let ids: Vec<BufferId> = self.buffers.iter().map(|(id, _)| id).collect();
+        for id in ids {
+            self.open_lsp_document(id)?;
+        }
+        Ok(())
+    }
+
+    /// stop_language_server() closes the documents open in the server for `language_id` and shuts
+    /// it down. Returns `false` if there was no such server.
+    pub fn stop_language_server(&mut self, language_id: &str) -> Result<bool, LspError> {
+        let mut client = match self.language_servers.remove(language_id) {
+            Some(client) => client,
+            None => return Ok(false),
+        };
+
+        let ids: Vec<BufferId> = self.lsp_documents.iter().filter(|(_, document)| document.language_id == language_id).map(|(id, _)| *id).collect();
+        for id in ids {
+            let document = self.lsp_documents.remove(&id).unwrap();
+            if let Some(buffer) = self.buffers.get_mut(id) {
+                buffer.remove_listener(document.listener);
+                buffer.clear_diagnostics(LSP_DIAGNOSTICS);
+            }
+            client.did_close(&document.uri)?;
+        }
+
+        client.shutdown()?;
+        Ok(true)
+    }
+
+    /// stop_language_servers() shuts every server down, as when the editor exits.
+    pub fn stop_language_servers(&mut self) {
+        let languages: Vec<String> = self.language_servers.keys().cloned().collect();
+        for language_id in languages {
+            let _ = self.stop_language_server(&language_id);
+        }
+    }
+
+    pub fn language_server(&self, language_id: &str) -> Option<&LspClient> {
+        self.language_servers.get(language_id)
+    }
+
+    pub fn lsp_document(&self, id: BufferId) -> Option<&LspDocument> {
+        self.lsp_documents.get(&id)
+    }
+
+    /// open_lsp_document() opens the buffer with `id` in the server for its file's language, if
+    /// one is running and the buffer is not open in it already.
+    pub fn open_lsp_document(&mut self, id: BufferId) -> Result<(), LspError> {
+        if self.lsp_documents.contains_key(&id) {
+            return Ok(());
+        }
+        let buffer = match self.buffers.get_mut(id) {
+            Some(buffer) => buffer,
+            None => return Ok(()),
+        };
+        let path = match buffer.path() {
+            Some(path) => path.to_path_buf(),
+            None => return Ok(()),
+        };
+        let language_servers = &mut self.language_servers;
+        let (language_id, client) = match lsp::language_id(&path).and_then(|language| language_servers.get_mut(&language).map(|client| (language, client))) {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+        let uri = path_to_uri(&path);
+        client.did_open(&uri, &language_id, 0, &buffer.whole_text())?;
+        let (listener, changes) = buffer.subscribe_text_changes(client.encoding());
+        self.lsp_documents.insert(
+            id,
+            LspDocument {
+                language_id,
+                uri,
+                version: 0,
+                listener,
+                changes,
+            },
+        );
+        Ok(())
+    }
+
+    /// sync_language_servers() sends the changes made to open documents since the last call to
+    /// their servers, and handles what the servers have sent, which a frontend should do each
+    /// time around its event loop. Messages for the user are shown, diagnostics are kept on
+    /// their buffers, completions go to the popup and the places found are visited; the other
+    /// events are returned with the language of their server. A server that has exited is forgotten.
+    pub fn sync_language_servers(&mut self) -> Vec<(String, LspEvent)> {
+        let mut failed = self.send_lsp_changes();
+
+        let mut events = Vec::new();
+        for (language_id, client) in self.language_servers.iter_mut() {
+            match client.poll() {
+                Ok(polled) => events.extend(polled.into_iter().map(|event| (language_id.clone(), event))),
+                Err(_) => failed.push(language_id.clone()),
+            }
+        }
+
+        failed.sort();
+        failed.dedup();
+        for language_id in failed {
+            self.forget_language_server(&language_id);
+            self.set_message(&format!("The {} language server exited", language_id));
+        }
+
+        let mut unhandled = Vec::new();
+        for (language_id, event) in events {
+            match &event {
+                LspEvent::Notification { method, params } if method == "window/showMessage" => {
+                    self.set_message(params["message"].as_str().unwrap_or_default());
+                }
+                LspEvent::Notification { method, params } if method == "textDocument/publishDiagnostics" => {
+                    self.publish_diagnostics(&language_id, params);
+                }
+                LspEvent::Notification { method, .. } if method == "window/logMessage" || method == "$/progress" => {}
+                LspEvent::Response { id, method, result } if method == "textDocument/completion" => {
+                    self.receive_lsp_completion(&language_id, *id, result.clone());
+                }
+                LspEvent::Response { id, method, result } if method == "textDocument/definition" || method == "textDocument/references" => {
+                    self.receive_lsp_locations(&language_id, *id, method, result.clone());
+                }
+                _ => unhandled.push((language_id, event)),
+            }
+        }
+        unhandled
+    }
+
+    /// find_lsp_locations() asks the language server of the current buffer for the places
+    /// `method` finds for the point, such as `textDocument/definition`, with `params` added to the
+    /// request. The first place is visited once the server answers, and the others can be
+    /// stepped through. Returns false if there is no server for the buffer that has `capability`.
+    pub fn find_lsp_locations(&mut self, method: &str, capability: &str, params: Value) -> Result<bool, LspError> {
+        self.send_lsp_changes();
+        let id = match self.buffers.current_id() {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+        let document = match self.lsp_documents.get(&id) {
+            Some(document) => document,
+            None => return Ok(false),
+        };
+        let client = match self.language_servers.get_mut(&document.language_id) {
+            Some(client) => client,
+            None => return Ok(false),
+        };
+        if let Value::Null | Value::Bool(false) = client.capabilities()[capability] {
+            return Ok(false);
+        }
+
+        let buffer = self.buffers.get(id).unwrap();
+        let position = buffer.offset_to_position(buffer.get_point(), client.encoding());
+        let mut request = json!({
+            "textDocument": {"uri": document.uri},
+            "position": {"line": position.line, "character": position.character},
+        });
+        if let (Some(request), Value::Object(params)) = (request.as_object_mut(), params) {
+            request.extend(params);
+        }
+
+        let request = client.request(method, request)?;
+        self.pending_locations = Some((document.language_id.clone(), request));
+        Ok(true)
+    }
+
+    /// send_lsp_changes() sends the changes made to open documents since it was last called to
+    /// their servers, returning the languages of the servers that could not be reached.
+    pub(super) fn send_lsp_changes(&mut self) -> Vec<String> {
+        let mut failed = Vec::new();
+        for (id, document) in self.lsp_documents.iter_mut() {
+            let changes: Vec<TextChange> = document.changes.try_iter().collect();
+            let client = match self.language_servers.get_mut(&document.language_id) {
+                Some(client) if !changes.is_empty() => client,
+                _ => continue,
+            };
+
+            document.version += 1;
+            let sent = match client.sync() {
+                TextDocumentSync::None => Ok(()),
+                TextDocumentSync::Incremental => client.did_change(&document.uri, document.version, &changes),
+                TextDocumentSync::Full => match self.buffers.get(*id) {
+                    Some(buffer) => client.did_change_full(&document.uri, document.version, &buffer.whole_text()),
+                    None => Ok(()),
+                },
+            };
+            if sent.is_err() {
+                failed.push(document.language_id.clone());
+            }
+        }
+        failed
+    }
+
+    /// receive_lsp_locations() visits the places a language server answered the location request
+    /// waiting for an answer with. They are a location, a list of locations or a list of links.
+    fn receive_lsp_locations(&mut self, language_id: &str, id: u64, method: &str, result: Result<Value, ResponseError>) {
+        if self.pending_locations != Some((language_id.to_string(), id)) {
+            return;
+        }
+        self.pending_locations = None;
+
+        let encoding = self.language_servers.get(language_id).map(|client| client.encoding()).unwrap_or_default();
+        let result = match result {
+            Ok(Value::Array(results)) => results,
+            Ok(Value::Null) => Vec::new(),
+            Ok(result) => vec![result],
+            Err(error) => return self.set_message(&error.message),
+        };
+        let locations: Vec<Location> = results_to_locations(&result, encoding);
+
+        let count = locations.len();
+        if count == 0 {
+            return self.set_message(if method == "textDocument/definition" { "No definition found" } else { "No references found" });
+        }
+        match self.set_locations(locations) {
+            Ok(()) if count > 1 => self.set_message(&format!("1 of {} places", count)),
+            Ok(()) => {}
+            Err(error) => self.set_message(&error.to_string()),
+        }
+    }
+
+    /// forget_language_server() drops the server for `language_id` and its documents without
+    /// talking to it, as when it has exited.
+    fn forget_language_server(&mut self, language_id: &str) {
+        self.language_servers.remove(language_id);
+
+        let ids: Vec<BufferId> = self.lsp_documents.iter().filter(|(_, document)| document.language_id == language_id).map(|(id, _)| *id).collect();
+        for id in ids {
+            let document = self.lsp_documents.remove(&id).unwrap();
+            if let Some(buffer) = self.buffers.get_mut(id) {
+                buffer.remove_listener(document.listener);
+                buffer.clear_diagnostics(LSP_DIAGNOSTICS);
+            }
+        }
+    }
+
+    /// publish_diagnostics() replaces the diagnostics of the document a
+    /// `textDocument/publishDiagnostics` notification from the server for `language_id` is about.
+    fn publish_diagnostics(&mut self, language_id: &str, params: &Value) {
+        let uri = params["uri"].as_str().unwrap_or_default();
+        let document = self.lsp_documents.iter().find(|(_, document)| document.language_id == language_id && document.uri == uri);
+        let (id, encoding) = match (document, self.language_servers.get(language_id)) {
+            (Some((id, _)), Some(client)) => (*id, client.encoding()),
+            _ => return,
+        };
+        let buffer = match self.buffers.get_mut(id) {
+            Some(buffer) => buffer,
+            None => return,
+        };
+
+        let mut diagnostics = Vec::new();
+        for diagnostic in params["diagnostics"].as_array().into_iter().flatten() {
+            let offset = |position: &Value| {
+                let line = position["line"].as_u64()? as usize;
+                let character = position["character"].as_u64()? as usize;
+                buffer.position_to_offset(Position { line, character }, encoding).or(Some(buffer.len()))
+            };
+            let (start, end) = match (offset(&diagnostic["range"]["start"]), offset(&diagnostic["range"]["end"])) {
+                (Some(start), Some(end)) => (start, end.max(start)),
+                _ => continue,
+            };
+
+            diagnostics.push(Diagnostic {
+                range: start..end,
+                severity: Severity::from_lsp(diagnostic["severity"].as_u64()),
+                message: diagnostic["message"].as_str().unwrap_or_default().to_string(),
+                source: diagnostic["source"].as_str().map(|source| source.to_string()),
+            });
+        }
+        buffer.set_diagnostics(LSP_DIAGNOSTICS, diagnostics);
+    }
+}
+
+/// results_to_locations() reads the `Location`s and `LocationLink`s in `results`, leaving out
+/// those that are not files.
+fn results_to_locations(results: &[Value], encoding: PositionEncoding) -> Vec<Location> {
+    results
+        .iter()
+        .filter_map(|result| {
+            let (uri, start) = match result.get("targetUri") {
+                Some(uri) => (uri, &result["targetSelectionRange"]["start"]),
+                None => (&result["uri"], &result["range"]["start"]),
+            };
+            let position = Position {
+                line: start["line"].as_u64()? as usize,
+                character: start["character"].as_u64()? as usize,
+            };
+            Some(Location { path: uri_to_path(uri.as_str()?)?, position, encoding })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use command::CommandError;
+    use diagnostic::Severity;
+    use editor::Editor;
+    use keymap::Key;
+    use lsp::{fake_server, initialize_answer, path_to_uri, LspEvent, Message};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn open_and_change_documents_in_a_language_server() {
+        let file = std::env::temp_dir().join(format!("one-lsp-{}", std::process::id())).join("main.rs");
+        let uri = path_to_uri(&file);
+        let (mut client, received) = fake_server(move |message| {
+            let mut answers = initialize_answer(message, json!({"textDocumentSync": 2}));
+            if let Message::Notification { method, .. } = message {
+                if method == "textDocument/didChange" {
+                    answers.push(Message::Notification { method: "window/showMessage".to_string(), params: json!({"message": "Indexing"}) });
+                    let range = json!({"start": {"line": 0, "character": 3}, "end": {"line": 0, "character": 8}});
+                    let diagnostics = json!([{"range": range, "severity": 2, "message": "unused", "source": "rustc"}]);
+                    answers.push(Message::Notification { method: "textDocument/publishDiagnostics".to_string(), params: json!({"uri": "", "diagnostics": []}) });
+                    answers.push(Message::Notification {
+                        method: "textDocument/publishDiagnostics".to_string(),
+                        params: json!({"uri": uri, "diagnostics": diagnostics}),
+                    });
+                    answers.push(Message::Notification { method: "textDocument/hover".to_string(), params: json!({}) });
+                }
+            }
+            answers
+        });
+        client.initialize(Path::new("/work"), Duration::from_secs(5)).unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", file.to_str().unwrap()).unwrap();
+        editor.execute("insert-char", "fn main() {}").unwrap();
+        editor.add_language_server("rust", client).unwrap();
+        let id = editor.buffers().current_id().unwrap();
+        assert_eq!(editor.lsp_document(id).unwrap().version, 0);
+
+        editor.current_buffer_mut().unwrap().set_point(3);
+        editor.execute("insert-char", "x").unwrap();
+        let mut events = Vec::new();
+        while events.is_empty() {
+            events.extend(editor.sync_language_servers());
+        }
+        assert_eq!(events, vec![("rust".to_string(), LspEvent::Notification { method: "textDocument/hover".to_string(), params: json!({}) })]);
+        let diagnostics = editor.current_buffer().unwrap().diagnostics();
+        assert_eq!((diagnostics[0].range.clone(), diagnostics[0].severity), (3..8, Severity::Warning));
+        assert_eq!(editor.message(), Some("Indexing"));
+        assert_eq!(editor.lsp_document(id).unwrap().version, 1);
+
+        assert!(editor.stop_language_server("rust").unwrap());
+        assert!(editor.lsp_document(id).is_none());
+        assert!(editor.current_buffer().unwrap().diagnostics().is_empty());
+        let messages: Vec<(String, Value)> = received
+            .iter()
+            .filter_map(|message| match message {
+                Message::Notification { method, params } if method.starts_with("textDocument/") => Some((method, params)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(messages[0].1["textDocument"]["text"], "fn main() {}");
+        assert_eq!(messages[1].1["contentChanges"], json!([{"range": {"start": {"line": 0, "character": 3}, "end": {"line": 0, "character": 3}}, "text": "x"}]));
+        assert_eq!(messages[2].0, "textDocument/didClose");
+        assert!(matches!(editor.execute("lsp-start", "one-no-such-server"), Err(CommandError::Lsp(_))));
+    }
+
+    #[test]
+    fn send_the_whole_text_of_a_narrowed_document() {
+        let file = std::env::temp_dir().join(format!("one-lsp-full-{}", std::process::id())).join("main.rs");
+        let (mut client, received) = fake_server(|message| initialize_answer(message, json!({"textDocumentSync": 1})));
+        client.initialize(Path::new("/work"), Duration::from_secs(5)).unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", file.to_str().unwrap()).unwrap();
+        editor.execute("insert-char", "one\ntwo\nthree\n").unwrap();
+        editor.add_language_server("rust", client).unwrap();
+        let buffer = editor.current_buffer_mut().unwrap();
+        buffer.narrow(4..8);
+        buffer.set_point(3);
+        editor.execute("insert-char", "s").unwrap();
+        editor.sync_language_servers();
+
+        assert!(editor.stop_language_server("rust").unwrap());
+        let changes: Vec<Value> = received
+            .iter()
+            .filter_map(|message| match message {
+                Message::Notification { method, params } if method == "textDocument/didChange" => Some(params),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changes[0]["contentChanges"], json!([{"text": "one\ntwos\nthree\n"}]));
+    }
+
+    #[test]
+    fn complete_with_a_language_server() {
+        let file = std::env::temp_dir().join(format!("one-lsp-completion-{}", std::process::id())).join("main.rs");
+        let (mut client, received) = fake_server(|message| {
+            let mut answers = initialize_answer(message, json!({"completionProvider": {"triggerCharacters": ["."]}}));
+            if let Message::Request { id, method, .. } = message {
+                if method == "textDocument/completion" {
+                    let edit = json!({"range": {"start": {"line": 0, "character": 2}, "end": {"line": 0, "character": 3}}, "newText": "len()"});
+                    let items = json!([{"label": "len", "detail": "fn(&self) -> usize", "textEdit": edit}, {"label": "push"}]);
+                    answers.push(Message::Response { id: id.clone(), result: Ok(json!({"isIncomplete": false, "items": items})) });
+                }
+            }
+            answers
+        });
+        client.initialize(Path::new("/work"), Duration::from_secs(5)).unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", file.to_str().unwrap()).unwrap();
+        editor.add_language_server("rust", client).unwrap();
+        editor.set_auto_complete(true);
+        editor.handle_key(Key::parse("v").unwrap()).unwrap();
+        editor.handle_key(Key::parse(".").unwrap()).unwrap();
+        editor.handle_key(Key::parse("l").unwrap()).unwrap();
+        while editor.completion().is_some_and(|completion| completion.pending().is_some()) {
+            editor.sync_language_servers();
+        }
+
+        let completion = editor.completion().unwrap();
+        let labels: Vec<&str> = completion.matches().map(|candidate| candidate.item.label.as_str()).collect();
+        assert_eq!(labels, vec!["len"]);
+        assert_eq!(completion.selected().unwrap().item.detail.as_deref(), Some("fn(&self) -> usize"));
+        editor.accept_completion().unwrap();
+        assert_eq!(editor.current_buffer().unwrap().to_string(), "v.len()");
+
+        editor.stop_language_servers();
+        let requests: Vec<Value> = received
+            .iter()
+            .filter_map(|message| match message {
+                Message::Request { method, params, .. } if method == "textDocument/completion" => Some(params),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(requests[0]["position"], json!({"line": 0, "character": 2}));
+    }
+
+    #[test]
+    fn go_to_definitions_and_references() {
+        let directory = std::env::temp_dir().join(format!("one-lsp-locations-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let (main, library) = (directory.join("main.rs"), directory.join("lib.rs"));
+        std::fs::write(&library, "mod a;\nfn helper() {}\n").unwrap();
+        let (main_uri, library_uri) = (path_to_uri(&main), path_to_uri(&library));
+        let (mut client, _) = fake_server(move |message| {
+            let mut answers = initialize_answer(message, json!({"definitionProvider": true, "referencesProvider": {}}));
+            let range = |line, character| json!({"start": {"line": line, "character": character}, "end": {"line": line, "character": character + 6}});
+            let result = match message {
+                Message::Request { method, .. } if method == "textDocument/definition" => {
+                    json!([{"targetUri": library_uri, "targetRange": range(1, 0), "targetSelectionRange": range(1, 3)}])
+                }
+                Message::Request { method, .. } if method == "textDocument/references" => {
+                    json!([{"uri": main_uri, "range": range(0, 0)}, {"uri": library_uri, "range": range(1, 3)}, {"uri": "http://x", "range": range(0, 0)}])
+                }
+                _ => return answers,
+            };
+            if let Message::Request { id, .. } = message {
+                answers.push(Message::Response { id: id.clone(), result: Ok(result) });
+            }
+            answers
+        });
+        client.initialize(Path::new("/work"), Duration::from_secs(5)).unwrap();
+
+        let mut editor = Editor::new();
+        editor.execute("find-file", main.to_str().unwrap()).unwrap();
+        editor.execute("insert-char", "helper();").unwrap();
+        editor.add_language_server("rust", client).unwrap();
+        let wait = |editor: &mut Editor| {
+            while editor.pending_locations.is_some() {
+                editor.sync_language_servers();
+            }
+        };
+
+        editor.execute("goto-definition", "").unwrap();
+        wait(&mut editor);
+        let buffer = editor.current_buffer().unwrap();
+        assert_eq!((buffer.path(), buffer.get_point()), (Some(library.as_path()), 10));
+        assert_eq!(editor.jump_list.len(), 1);
+
+        editor.execute("find-references", "").unwrap();
+        wait(&mut editor);
+        assert_eq!(editor.current_buffer().unwrap().path(), Some(main.as_path()));
+        assert_eq!(editor.message(), Some("1 of 2 places"));
+        editor.execute("next-location", "").unwrap();
+        assert_eq!(editor.current_buffer().unwrap().get_point(), 10);
+        assert_eq!(editor.message(), Some("2 of 2 places"));
+        editor.execute("next-location", "").unwrap();
+        assert_eq!(editor.message(), Some("No more places"));
+        assert_eq!(editor.jump_list.len(), 3);
+
+        editor.stop_language_servers();
+        editor.execute("goto-definition", "").unwrap();
+        assert_eq!(editor.message(), Some("No language server can find that for this buffer"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use std::path::{Path, PathBuf};
+
+use anchor::{Affinity, AnchorId};
+use buffer::file::OpenMode;
+use buffer_manager::BufferId;
+use command::{CommandError, CommandResult};
+use delta::{Position, PositionEncoding};
+use editor::commands::visit_file;
+use editor::Editor;
+use jump_list::JumpList;
+
+/// Location is a place in a file, such as a definition a language server found.
+/// - `position`: The line and character in it, with characters counted in `encoding`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub position: Position,
+    pub encoding: PositionEncoding,
+}
+
+/// parse_file_location() splits an argument naming a place in a file the way compilers and grep
+/// write them, as `file:line` or `file:line:column`, into the file and the one-based line and
+/// column.
+fn parse_file_location(argument: &str) -> Option<(&str, usize, Option<usize>)> {
+    let (rest, last) = argument.rsplit_once(':')?;
+    let last: usize = last.parse().ok()?;
+    let (path, line, column) = match rest.rsplit_once(':').and_then(|(path, line)| Some((path, line.parse().ok()?))) {
+        Some((path, line)) => (path, line, Some(last)),
+        None => (rest, last, None),
+    };
+
+    match path.is_empty() || line == 0 || column == Some(0) {
+        true => None,
+        false => Some((path, line, column)),
+    }
+}
+
+impl Editor {
+    /// locations() returns the places found last, such as the references to a name, and which
+    /// of them was visited last.
+    pub fn locations(&self) -> (&[Location], usize) {
+        (&self.locations, self.location)
+    }
+
+    /// set_locations() keeps `locations` to step through and visits the first.
+    pub fn set_locations(&mut self, locations: Vec<Location>) -> CommandResult {
+        self.locations = locations;
+        self.location = 0;
+
+        match self.locations.first().cloned() {
+            Some(location) => self.visit_location(&location),
+            None => Ok(()),
+        }
+    }
+
+    /// step_location() visits the place `step` after the one visited last among the places
+    /// found, returning false if there is none.
+    pub fn step_location(&mut self, step: isize) -> Result<bool, CommandError> {
+        let index = match self.location.checked_add_signed(step) {
+            Some(index) if index < self.locations.len() => index,
+            _ => return Ok(false),
+        };
+
+        self.location = index;
+        let location = self.locations[index].clone();
+        self.visit_location(&location)?;
+        Ok(true)
+    }
+
+    /// visit_location() moves the point to `location`, visiting its file if it is not the current
+    /// buffer's. Where the point was is pushed onto the jump list first.
+    pub fn visit_location(&mut self, location: &Location) -> CommandResult {
+        self.push_jump();
+        let path = location.path.to_string_lossy().into_owned();
+        visit_file(self, "visit-location", &path, OpenMode::Detect)?;
+
+        let buffer = self.buffer_or_error()?;
+        let offset = buffer.position_to_offset(location.position, location.encoding).unwrap_or(buffer.len());
+        buffer.set_point(offset);
+        Ok(())
+    }
+
+    /// goto_line_column() moves the point of the current buffer to the zero-based `column`, in
+    /// characters, of the zero-based `line`, stopping at the end of the buffer or the line.
+    pub fn goto_line_column(&mut self, line: usize, column: usize) -> CommandResult {
+        let buffer = self.buffer_or_error()?;
+        let line = line.min(buffer.line_count() - 1);
+        let start = buffer.line_to_offset(line).unwrap();
+        let end = buffer.line_end_offset(line).unwrap();
+
+        let offset = buffer.char_to_byte(buffer.byte_to_char(start) + column).min(end);
+        buffer.set_point(offset);
+        Ok(())
+    }
+
+    /// visit_file_argument() visits the file named by a command-line argument, which may say
+    /// where in it to go as `file:line` or `file:line:column`. A file whose name only looks like
+    /// that is visited as it is.
+    pub fn visit_file_argument(&mut self, argument: &str) -> CommandResult {
+        let (path, line, column) = match parse_file_location(argument) {
+            Some(location) if !Path::new(argument).exists() => location,
+            _ => return self.execute("find-file", argument),
+        };
+
+        self.execute("find-file", path)?;
+        self.goto_line_column(line - 1, column.unwrap_or(1) - 1)
+    }
+
+    /// push_jump() records the point of the current buffer on the jump list, as a command does
+    /// before it moves the point far away.
+    pub fn push_jump(&mut self) {
+        let id = match self.buffers.current_id() {
+            Some(id) => id,
+            None => return,
+        };
+        let buffer = self.buffers.current_mut().unwrap();
+        let point = buffer.get_point();
+        let anchor = buffer.create_anchor(point, Affinity::Left);
+
+        for (id, anchor) in self.jump_list.push(id, anchor) {
+            if let Some(buffer) = self.buffers.get_mut(id) {
+                buffer.remove_anchor(anchor);
+            }
+        }
+    }
+
+    /// jump_back() goes back to where the point was before the last jump it has not gone back
+    /// over, remembering where it is now to come forward to again. Returns false if there is
+    /// no such place. Places in buffers that were killed are passed over.
+    pub fn jump_back(&mut self) -> bool {
+        if self.jump_list.at_newest() && !self.jump_list.is_empty() {
+            self.push_jump();
+            self.jump_list.back();
+        }
+
+        while let Some((id, anchor)) = self.jump_list.back() {
+            if self.goto_jump(id, anchor) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// jump_forward() goes forward again to the place the last jump back left. Returns false if
+    /// there is no such place.
+    pub fn jump_forward(&mut self) -> bool {
+        while let Some((id, anchor)) = self.jump_list.forward() {
+            if self.goto_jump(id, anchor) {
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn jump_list(&self) -> &JumpList {
+        &self.jump_list
+    }
+
+    /// goto_jump() shows buffer `id` with the point at `anchor`, returning false if either is gone.
+    fn goto_jump(&mut self, id: BufferId, anchor: AnchorId) -> bool {
+        let offset = match self.buffers.get(id).and_then(|buffer| buffer.anchor_offset(anchor)) {
+            Some(offset) => offset,
+            None => return false,
+        };
+
+        self.show_buffer(id);
+        self.buffers.get_mut(id).unwrap().set_point(offset);
+        true
+    }
+}
@@ -0,0 +1,190 @@
+use command::{CommandError, CommandResult};
+use editor::Editor;
+use window::{Direction, Target, WindowId};
+
+/// How many rows the wheel scrolls at a time.
+pub const WHEEL_ROWS: isize = 3;
+
+/// MouseAction is what the mouse did. Only the first button is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseAction {
+    Press,
+    Drag,
+    Release,
+    WheelUp,
+    WheelDown,
+}
+
+/// MouseEvent is something the mouse did at a column and line of the screen, counting from the
+/// top left as the window layout does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub action: MouseAction,
+    pub column: usize,
+    pub row: usize,
+}
+
+/// Drag is what the mouse is doing while its button is held.
+/// - `Select`: Selecting text in a window from `start`, where the button was pressed.
+/// - `Resize`: Moving the divider or status line of a window, last at `position`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Drag {
+    Select { window: WindowId, start: usize },
+    Resize { window: WindowId, direction: Direction, position: usize },
+}
+
+impl Editor {
+    /// handle_mouse() acts on a mouse event from a frontend. Pressing in a window selects it and
+    /// moves the point there, and dragging from there selects the text between. Dragging a
+    /// divider or a status line resizes the windows beside it. The wheel scrolls the window
+    /// under the mouse, selecting it.
+    pub fn handle_mouse(&mut self, event: MouseEvent) -> CommandResult {
+        match event.action {
+            MouseAction::Press => self.press_mouse(event.column, event.row),
+            MouseAction::Drag => self.drag_mouse(event.column, event.row),
+            MouseAction::Release => {
+                self.mouse_drag = None;
+                Ok(())
+            }
+            MouseAction::WheelUp => self.scroll_mouse(event.column, event.row, -WHEEL_ROWS),
+            MouseAction::WheelDown => self.scroll_mouse(event.column, event.row, WHEEL_ROWS),
+        }
+    }
+
+    fn press_mouse(&mut self, column: usize, row: usize) -> CommandResult {
+        self.mouse_drag = match self.windows.target_at(column, row) {
+            Some(Target::Text { window, column, row }) => {
+                self.select_window(window);
+                let offset = self.move_point_to(column, row)?;
+                self.buffer_or_error()?.clear_mark();
+                Some(Drag::Select { window, start: offset })
+            }
+            Some(Target::StatusLine(window)) if !self.windows.is_bottom(window) => Some(Drag::Resize {
+                window,
+                direction: Direction::Below,
+                position: row,
+            }),
+            Some(Target::StatusLine(window)) => {
+                self.select_window(window);
+                None
+            }
+            Some(Target::Divider(window)) => Some(Drag::Resize {
+                window,
+                direction: Direction::Right,
+                position: column,
+            }),
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn drag_mouse(&mut self, column: usize, row: usize) -> CommandResult {
+        match self.mouse_drag {
+            Some(Drag::Select { window, start }) => {
+                let area = match self.windows.layout(self.windows.area()).into_iter().find(|(id, _)| *id == window) {
+                    Some((_, area)) => area,
+                    None => return Ok(()),
+                };
+                let column = column.saturating_sub(area.x).min(area.width.saturating_sub(1));
+                let row = row.saturating_sub(area.y).min(area.height.saturating_sub(2));
+
+                self.move_point_to(column, row)?;
+                self.buffer_or_error()?.set_mark(start);
+            }
+            Some(Drag::Resize { window, direction, position }) => {
+                let moved = match direction {
+                    Direction::Below => row,
+                    Direction::Right => column,
+                };
+                if self.windows.resize(window, direction, moved as isize - position as isize) {
+                    self.mouse_drag = Some(Drag::Resize { window, direction, position: moved });
+                }
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn scroll_mouse(&mut self, column: usize, row: usize, rows: isize) -> CommandResult {
+        let window = match self.windows.target_at(column, row) {
+            Some(Target::Text { window, .. }) | Some(Target::StatusLine(window)) => window,
+            _ => return Ok(()),
+        };
+
+        self.select_window(window);
+        let buffer = self.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+        self.windows.selected_mut().view_mut().scroll(buffer, rows);
+        Ok(())
+    }
+
+    /// move_point_to() moves the point to what is drawn at `column` and `row` of the selected
+    /// window, and returns where that is.
+    fn move_point_to(&mut self, column: usize, row: usize) -> Result<usize, CommandError> {
+        let buffer = self.buffers.current_mut().ok_or(CommandError::NoBuffer)?;
+        let offset = self.windows.selected().view().offset_at(buffer, column, row);
+
+        buffer.set_point(offset);
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use editor::mouse::{MouseAction, MouseEvent};
+    use editor::Editor;
+
+    fn mouse(editor: &mut Editor, action: MouseAction, column: usize, row: usize) {
+        editor.handle_mouse(MouseEvent { action, column, row }).unwrap();
+    }
+
+    fn editor_with_lines(count: usize) -> Editor {
+        let mut editor = Editor::new();
+        let text: Vec<String> = (0..count).map(|line| format!("line {}", line)).collect();
+        editor.execute("insert-char", &text.join("\n")).unwrap();
+        editor.current_buffer_mut().unwrap().set_point(0);
+        editor.layout_windows(80, 24);
+
+        editor
+    }
+
+    #[test]
+    fn click_and_drag_to_select() {
+        let mut editor = editor_with_lines(10);
+
+        mouse(&mut editor, MouseAction::Press, 2, 1);
+        assert_eq!(editor.current_buffer().unwrap().get_point(), 9);
+        mouse(&mut editor, MouseAction::Drag, 4, 2);
+        mouse(&mut editor, MouseAction::Release, 4, 2);
+
+        let buffer = editor.current_buffer().unwrap();
+        assert_eq!(buffer.copy_region().as_deref(), Some("ne 1\nline"));
+    }
+
+    #[test]
+    fn the_wheel_scrolls() {
+        let mut editor = editor_with_lines(100);
+
+        mouse(&mut editor, MouseAction::WheelDown, 10, 10);
+        assert_eq!(editor.view().top_line(), 3);
+        mouse(&mut editor, MouseAction::WheelUp, 10, 10);
+        assert_eq!(editor.view().top_line(), 0);
+    }
+
+    #[test]
+    fn drag_dividers_to_resize() {
+        let mut editor = editor_with_lines(10);
+        editor.execute("split-window-right", "").unwrap();
+
+        mouse(&mut editor, MouseAction::Press, 40, 5);
+        mouse(&mut editor, MouseAction::Drag, 45, 5);
+        assert_eq!(editor.view().width(), 45);
+
+        editor.execute("split-window-below", "").unwrap();
+        mouse(&mut editor, MouseAction::Press, 10, 11);
+        mouse(&mut editor, MouseAction::Drag, 10, 7);
+        assert_eq!(editor.view().height(), 7);
+
+        mouse(&mut editor, MouseAction::Press, 60, 3);
+        assert_ne!(editor.windows().selected_id(), editor.windows().ids()[0]);
+    }
+}
@@ -0,0 +1,89 @@
+use anchor::{Affinity, AnchorId};
+use buffer_manager::BufferId;
+use command::CommandError;
+use editor::Editor;
+
+/// The buffer `occur()` lists the matching lines in.
+pub const OCCUR_BUFFER: &str = "*Occur*";
+
+/// Occur is what the occur buffer lists.
+/// - `source`: The buffer the lines are in.
+/// - `matches`: An anchor at the first match on each line listed, in the order they are listed.
+#[derive(Debug)]
+pub(super) struct Occur {
+    source: BufferId,
+    matches: Vec<AnchorId>,
+}
+
+impl Editor {
+    /// occur() lists every line of the current buffer that has `pattern` in it, with its line
+    /// number, in the occur buffer. `RET` there goes to the line listed at the point. Returns how
+    /// many lines were listed; the occur buffer is left alone when none were.
+    pub fn occur(&mut self, pattern: &str) -> Result<usize, CommandError> {
+        let source = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let buffer = self.buffers.get_mut(source).unwrap();
+
+        let mut found: Vec<(usize, usize)> = Vec::new();
+        for offset in buffer.find_all(pattern) {
+            let (line, _) = buffer.offset_to_line_col(offset);
+            if found.last().is_none_or(|(last, _)| *last != line) {
+                found.push((line, offset));
+            }
+        }
+        if found.is_empty() {
+            return Ok(0);
+        }
+
+        let plural = if found.len() == 1 { "" } else { "s" };
+        let mut text = format!("{} matching line{} for \"{}\" in buffer {}\n", found.len(), plural, pattern, buffer.name());
+        let mut matches = Vec::new();
+        for (line, offset) in found.iter() {
+            let start = buffer.line_to_offset(*line).unwrap();
+            let end = buffer.line_end_offset(*line).unwrap();
+            text.push_str(&format!("{:>6}: {}\n", line + 1, buffer.slice(start..end)));
+            matches.push(buffer.create_anchor(*offset, Affinity::Left));
+        }
+
+        self.clear_occur();
+        self.occur = Some(Occur { source, matches });
+        let id = self.show_text_buffer(OCCUR_BUFFER, &text);
+        self.buffer_keymap_mut(id).bind_str("RET", "occur-goto").unwrap();
+        Ok(found.len())
+    }
+
+    /// occur_goto() goes to the match on the line listed at the point of the occur buffer,
+    /// returning false if the point is not on a listed line or the line is gone.
+    pub fn occur_goto(&mut self) -> Result<bool, CommandError> {
+        let buffer = self.buffers.current().ok_or(CommandError::NoBuffer)?;
+        let occur = match self.occur.as_ref() {
+            Some(occur) if buffer.name() == OCCUR_BUFFER => occur,
+            _ => return Ok(false),
+        };
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let anchor = match line.checked_sub(1).and_then(|index| occur.matches.get(index)) {
+            Some(anchor) => *anchor,
+            None => return Ok(false),
+        };
+        let source = occur.source;
+        let offset = match self.buffers.get(source).and_then(|buffer| buffer.anchor_offset(anchor)) {
+            Some(offset) => offset,
+            None => return Ok(false),
+        };
+
+        self.push_jump();
+        self.show_buffer(source);
+        self.buffers.get_mut(source).unwrap().set_point(offset);
+        Ok(true)
+    }
+
+    /// clear_occur() forgets what the occur buffer lists, removing its anchors.
+    fn clear_occur(&mut self) {
+        if let Some(occur) = self.occur.take() {
+            if let Some(buffer) = self.buffers.get_mut(occur.source) {
+                for anchor in occur.matches {
+                    buffer.remove_anchor(anchor);
+                }
+            }
+        }
+    }
+}
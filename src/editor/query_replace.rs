@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+use buffer_manager::BufferId;
+use command::{CommandError, CommandResult};
+use editor::Editor;
+use keymap::{Key, KeyCode};
+use property::TextProperty;
+use theme;
+
+/// The group of the text property marking the match a query-replace is asking about.
+const QUERY_REPLACE_GROUP: &str = "query-replace";
+
+/// QueryReplace is a replacement going through the matches of `pattern` one at a time, asking
+/// whether to replace each with `replacement`.
+/// - `buffer`: The buffer the matches are in.
+/// - `found`: The match being asked about.
+/// - `replaced`: How many matches have been replaced so far.
+#[derive(Debug, Clone)]
+pub struct QueryReplace {
+    buffer: BufferId,
+    pattern: String,
+    replacement: String,
+    found: Range<usize>,
+    replaced: usize,
+}
+
+impl QueryReplace {
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+
+    /// current_match() is the range of the match being asked about.
+    pub fn current_match(&self) -> Range<usize> {
+        self.found.clone()
+    }
+
+    pub fn replaced(&self) -> usize {
+        self.replaced
+    }
+
+    /// prompt() asks about the match for the echo area.
+    pub fn prompt(&self) -> String {
+        format!("Query replacing {} with {}: (y, n, !, ., q)", self.pattern, self.replacement)
+    }
+}
+
+impl Editor {
+    pub fn query_replace(&self) -> Option<&QueryReplace> {
+        self.query_replace.as_ref()
+    }
+
+    /// ask_query_replace() asks for what to replace `pattern` with, after which the replacement
+    /// starts.
+    pub(super) fn ask_query_replace(&mut self, pattern: &str) {
+        self.query_replace_pattern = Some(pattern.to_string());
+        self.prompt(&format!("Query replace {} with: ", pattern), "query-replace", "query-replace");
+    }
+
+    /// take_query_replace_pattern() is the pattern `ask_query_replace()` last asked about, if
+    /// its replacement has not been given yet.
+    pub(super) fn take_query_replace_pattern(&mut self) -> Option<String> {
+        self.query_replace_pattern.take()
+    }
+
+    /// start_query_replace() goes through the matches of `pattern` from the point of the current
+    /// buffer, asking about each in turn as keys come in. Where the point was is pushed onto the
+    /// jump list first. Every replacement made until it finishes is undone as one step.
+    pub fn start_query_replace(&mut self, pattern: &str, replacement: &str) -> CommandResult {
+        let id = self.buffers.current_id().ok_or(CommandError::NoBuffer)?;
+        let buffer = self.writable_buffer_or_error()?;
+
+        let start = match buffer.find(pattern, buffer.get_point()) {
+            Some(start) => start,
+            None => {
+                self.set_message(&format!("No matches for {}", pattern));
+                return Ok(());
+            }
+        };
+        buffer.begin_transaction();
+        self.push_jump();
+
+        self.query_replace = Some(QueryReplace {
+            buffer: id,
+            pattern: pattern.to_string(),
+            replacement: replacement.to_string(),
+            found: start..start + pattern.len(),
+            replaced: 0,
+        });
+        self.show_query_replace_match();
+        Ok(())
+    }
+
+    /// handle_query_replace_key() answers the question about the current match with `key`: `y`
+    /// or space replaces it, `n` or backspace skips it, `!` replaces it and every match after it,
+    /// `.` replaces it and stops, and `q`, `RET`, `C-g` or escape stop. Any other key stops the
+    /// replacement and returns `None` to be run as usual.
+    pub(super) fn handle_query_replace_key(&mut self, key: Key) -> Option<CommandResult> {
+        let query_replace = self.query_replace.as_ref()?;
+        if self.buffers.current_id() != Some(query_replace.buffer) {
+            self.finish_query_replace();
+            return None;
+        }
+
+        match (key.code, key.modifiers.control, key.modifiers.meta) {
+            (KeyCode::Char('y'), false, false) | (KeyCode::Char(' '), false, false) => {
+                self.replace_current_match();
+                self.next_query_replace_match();
+            }
+            (KeyCode::Char('n'), false, false) | (KeyCode::Backspace, false, false) => self.next_query_replace_match(),
+            (KeyCode::Char('!'), false, false) => {
+                while self.query_replace.is_some() {
+                    self.replace_current_match();
+                    self.next_query_replace_match();
+                }
+            }
+            (KeyCode::Char('.'), false, false) => {
+                self.replace_current_match();
+                self.finish_query_replace();
+            }
+            (KeyCode::Char('q'), false, false) | (KeyCode::Enter, false, false) | (KeyCode::Char('g'), true, false) | (KeyCode::Escape, _, _) => {
+                self.finish_query_replace()
+            }
+            _ => {
+                self.finish_query_replace();
+                return None;
+            }
+        }
+        Some(Ok(()))
+    }
+
+    /// finish_query_replace() stops the replacement, leaving the point where it is and saying how
+    /// many matches were replaced.
+    pub fn finish_query_replace(&mut self) {
+        let query_replace = match self.query_replace.take() {
+            Some(query_replace) => query_replace,
+            None => return,
+        };
+        if let Some(buffer) = self.buffers.get_mut(query_replace.buffer) {
+            buffer.clear_properties(QUERY_REPLACE_GROUP);
+            buffer.commit();
+        }
+
+        let plural = if query_replace.replaced == 1 { "" } else { "s" };
+        self.set_message(&format!("Replaced {} occurrence{}", query_replace.replaced, plural));
+    }
+
+    /// replace_current_match() replaces the match being asked about, moving the point past the
+    /// replacement.
+    fn replace_current_match(&mut self) {
+        let query_replace = self.query_replace.as_mut().unwrap();
+        let buffer = self.buffers.get_mut(query_replace.buffer).unwrap();
+
+        if let Some(inserted) = buffer.replace(&query_replace.pattern, &query_replace.replacement, query_replace.found.start) {
+            buffer.set_point(inserted.end);
+            query_replace.found = inserted.end..inserted.end;
+            query_replace.replaced += 1;
+        }
+    }
+
+    /// next_query_replace_match() moves on to the next match after the current one, finishing
+    /// when there are no more.
+    fn next_query_replace_match(&mut self) {
+        let query_replace = self.query_replace.as_mut().unwrap();
+        let buffer = self.buffers.get_mut(query_replace.buffer).unwrap();
+
+        match buffer.find(&query_replace.pattern, query_replace.found.end) {
+            Some(start) => {
+                query_replace.found = start..start + query_replace.pattern.len();
+                self.show_query_replace_match();
+            }
+            None => self.finish_query_replace(),
+        }
+    }
+
+    /// show_query_replace_match() moves the point past the match being asked about, marks it and
+    /// asks about it.
+    fn show_query_replace_match(&mut self) {
+        let query_replace = self.query_replace.as_ref().unwrap();
+        let buffer = self.buffers.get_mut(query_replace.buffer).unwrap();
+        buffer.clear_properties(QUERY_REPLACE_GROUP);
+        buffer.add_property(query_replace.found.clone(), TextProperty::styled(QUERY_REPLACE_GROUP, theme::SEARCH_MATCH_CURRENT));
+        buffer.set_point(query_replace.found.end);
+
+        let prompt = query_replace.prompt();
+        self.set_message(&prompt);
+    }
+}
@@ -0,0 +1,123 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use ansi::{encode_key, AnsiParser};
+use buffer_manager::BufferId;
+use command::{CommandError, CommandResult};
+use editor::Editor;
+use job::describe_exit;
+use keymap::{Key, KeyCode};
+use pty::{Pty, DEFAULT_SIZE};
+use term::Emulator;
+use window::{Area, WindowId};
+
+/// The name terminal buffers are given, or a unique variant of it.
+pub const TERMINAL_BUFFER: &str = "*terminal*";
+
+/// Terminal is a program running in a terminal buffer.
+/// - `program`: What was run, to say when it exits.
+/// - `pty`: The pseudo-terminal it runs on.
+/// - `parser`: Reads what it writes, which can end part way through a sequence.
+/// - `emulator`: Draws that into the buffer.
+#[derive(Debug)]
+pub(super) struct Terminal {
+    program: String,
+    pty: Pty,
+    parser: AnsiParser,
+    emulator: Emulator,
+}
+
+impl Editor {
+    /// start_terminal() runs `program` in `directory` on a pseudo-terminal and shows a new
+    /// terminal buffer, where it draws and where keys are sent to it as they are typed. Only
+    /// `C-x` and `M-x` sequences go to the editor, so windows and buffers can still be switched.
+    pub fn start_terminal(&mut self, program: &str, directory: &Path) -> io::Result<BufferId> {
+        let mut command = Command::new(program);
+        command.current_dir(directory);
+        let pty = Pty::spawn(command, DEFAULT_SIZE)?;
+
+        let id = self.buffers.create(TERMINAL_BUFFER);
+        self.buffers.get_mut(id).unwrap().set_read_only(true);
+        self.show_buffer(id);
+        self.terminals.insert(id, Terminal {
+            program: program.to_string(),
+            pty,
+            parser: AnsiParser::new(),
+            emulator: Emulator::new(DEFAULT_SIZE.0 as usize),
+        });
+        Ok(id)
+    }
+
+    /// is_terminal() reports whether buffer `id` shows a program that is still running.
+    pub fn is_terminal(&self, id: BufferId) -> bool {
+        self.terminals.contains_key(&id)
+    }
+
+    /// sync_terminals() draws what each terminal's program has written since the last call into
+    /// its buffer, moving the point to its cursor, which a frontend should do each time around its
+    /// event loop. A program that has exited is reported and its buffer becomes a plain read-only
+    /// one. A terminal whose buffer was killed has its program killed. Returns whether any is
+    /// still running.
+    pub fn sync_terminals(&mut self) -> bool {
+        let buffers = &self.buffers;
+        self.terminals.retain(|id, _| buffers.get(*id).is_some());
+
+        let mut exits = Vec::new();
+        for (id, terminal) in self.terminals.iter_mut() {
+            let output = terminal.pty.read();
+            let buffer = self.buffers.get_mut(*id).unwrap();
+            if !output.is_empty() {
+                let events = terminal.parser.feed(&output);
+                buffer.set_read_only(false);
+                let cursor = terminal.emulator.apply(buffer, &events);
+                buffer.set_point(cursor);
+                buffer.set_read_only(true);
+                buffer.set_modified(false);
+            }
+            if let Some(status) = terminal.pty.status() {
+                exits.push((*id, format!("Process {} {}", terminal.program, describe_exit(status))));
+            }
+        }
+
+        for (id, summary) in exits {
+            self.terminals.remove(&id);
+            self.append_to_text_buffer(id, &format!("\n{}\n", summary));
+            self.set_message(&summary);
+        }
+        !self.terminals.is_empty()
+    }
+
+    /// fit_terminals() tells the program in each terminal shown in one of `areas` how many rows
+    /// and columns the window has for it, less the status line below.
+    pub fn fit_terminals(&mut self, areas: &[(WindowId, Area)]) {
+        for (window, area) in areas {
+            let id = match self.windows.get(*window) {
+                Some(window) => window.buffer(),
+                None => continue,
+            };
+            if let Some(terminal) = self.terminals.get_mut(&id) {
+                let rows = area.height.saturating_sub(1).max(1);
+                if terminal.pty.resize((rows as u16, area.width.max(1) as u16)).is_ok() {
+                    terminal.emulator.set_rows(rows);
+                }
+            }
+        }
+    }
+
+    /// handle_terminal_key() sends `key` to the program in the current buffer if it is a
+    /// terminal, returning None to run it as usual if not, or if it is part of a `C-x` or `M-x`
+    /// sequence.
+    pub(super) fn handle_terminal_key(&mut self, key: Key) -> Option<CommandResult> {
+        let id = self.buffers.current_id()?;
+        let for_editor = (key.code == KeyCode::Char('x') && (key.modifiers.control || key.modifiers.meta))
+            || !self.pending_keys.is_empty();
+        if for_editor {
+            return None;
+        }
+
+        let terminal = self.terminals.get_mut(&id)?;
+        let bytes = encode_key(key)?;
+        Some(terminal.pty.write(&bytes).map_err(CommandError::from))
+    }
+}
@@ -0,0 +1,192 @@
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver};
+
+use delta::{DeltaListener, PositionEncoding, TextChange};
+use GapBuffer;
+
+/// EditEvent describes one change to a buffer, in the coordinates from before the change.
+/// - `removed`: The range of bytes that was removed. It is empty for a pure insertion.
+/// - `removed_text`: The text that was in `removed`.
+/// - `inserted`: The text inserted at `removed.start`.
+/// - `new_len`: The length of the buffer after the change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditEvent {
+    pub removed: Range<usize>,
+    pub removed_text: String,
+    pub inserted: String,
+    pub new_len: usize,
+}
+
+impl EditEvent {
+    /// inserted_range() is the range the inserted text occupies after the change.
+    pub fn inserted_range(&self) -> Range<usize> {
+        self.removed.start..self.removed.start + self.inserted.len()
+    }
+}
+
+/// ListenerId identifies a callback registered with `GapBuffer::on_change()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(usize);
+
+pub(crate) type Listener = Box<dyn FnMut(&EditEvent) + Send>;
+
+/// Listeners holds the callbacks notified of every change to a buffer.
+#[derive(Default)]
+pub(crate) struct Listeners {
+    callbacks: Vec<(ListenerId, Listener)>,
+    delta_callbacks: Vec<(ListenerId, PositionEncoding, DeltaListener)>,
+    next_id: usize,
+}
+
+impl Listeners {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.callbacks.is_empty() && self.delta_callbacks.is_empty()
+    }
+
+    pub(crate) fn add_delta(&mut self, encoding: PositionEncoding, callback: DeltaListener) -> ListenerId {
+        let id = self.next_id();
+        self.delta_callbacks.push((id, encoding, callback));
+
+        id
+    }
+
+    /// delta_encodings() lists each encoding a `TextChange` is needed in for the next change.
+    pub(crate) fn delta_encodings(&self) -> Vec<PositionEncoding> {
+        let mut encodings = Vec::new();
+        for (_, encoding, _) in self.delta_callbacks.iter() {
+            if !encodings.contains(encoding) {
+                encodings.push(*encoding);
+            }
+        }
+
+        encodings
+    }
+
+    /// notify() calls every listener, using `changes` to describe the change to delta listeners.
+    pub(crate) fn notify(&mut self, event: &EditEvent, changes: Vec<(PositionEncoding, TextChange)>) {
+        for (_, callback) in self.callbacks.iter_mut() {
+            callback(event);
+        }
+
+        for (_, encoding, callback) in self.delta_callbacks.iter_mut() {
+            if let Some((_, change)) = changes.iter().find(|(candidate, _)| candidate == encoding) {
+                callback(change);
+            }
+        }
+    }
+
+    fn next_id(&mut self) -> ListenerId {
+        let id = ListenerId(self.next_id);
+        self.next_id += 1;
+
+        id
+    }
+}
+
+/// The change feed. Every mutation of the buffer, however it is made, is reported exactly once.
+impl GapBuffer {
+    /// on_change() registers `callback` to be called after every change to the buffer.
+    pub fn on_change<F: FnMut(&EditEvent) + Send + 'static>(&mut self, callback: F) -> ListenerId {
+        let id = self.listeners.next_id();
+        self.listeners.callbacks.push((id, Box::new(callback)));
+
+        id
+    }
+
+    /// remove_listener() unregisters a callback and returns false if it was not registered.
+    pub fn remove_listener(&mut self, id: ListenerId) -> bool {
+        let count = self.listeners.callbacks.len() + self.listeners.delta_callbacks.len();
+        self.listeners.callbacks.retain(|(listener, _)| *listener != id);
+        self.listeners.delta_callbacks.retain(|(listener, _, _)| *listener != id);
+
+        self.listeners.callbacks.len() + self.listeners.delta_callbacks.len() != count
+    }
+
+    /// subscribe() returns a channel that receives every change to the buffer. The listener
+    /// stays registered after the receiver is dropped until it is removed.
+    pub fn subscribe(&mut self) -> (ListenerId, Receiver<EditEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let id = self.on_change(move |event| {
+            let _ = sender.send(event.clone());
+        });
+
+        (id, receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use events::EditEvent;
+    use GapBuffer;
+
+    const TEST_STRING: &str = "The quick brown fox";
+
+    fn event(removed: std::ops::Range<usize>, removed_text: &str, inserted: &str, new_len: usize) -> EditEvent {
+        EditEvent {
+            removed,
+            removed_text: removed_text.to_string(),
+            inserted: inserted.to_string(),
+            new_len,
+        }
+    }
+
+    #[test]
+    fn every_mutation_is_reported() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let (_, receiver) = buffer.subscribe();
+
+        buffer.set_point(3);
+        buffer.insert(b'!');
+        buffer.insert_str(" so");
+        buffer.remove();
+        buffer.delete_char_forward();
+        buffer.remove_bytes(0..2);
+        buffer.replace_range(4..9, "slow");
+        buffer.push_str(".");
+
+        let events: Vec<EditEvent> = receiver.try_iter().collect();
+
+        assert_eq!(
+            events,
+            vec![
+                event(3..3, "", "!", 20),
+                event(4..4, "", " so", 23),
+                event(6..7, "o", "", 22),
+                event(6..7, " ", "", 21),
+                event(0..2, "Th", "", 19),
+                event(4..9, "quick", "slow", 18),
+                event(18..18, "", ".", 19),
+            ]
+        );
+    }
+
+    #[test]
+    fn undo_is_reported_as_changes() {
+        let mut history = ::undo::History::new(GapBuffer::from(TEST_STRING.to_string()));
+        history.insert_str("!");
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        history.on_change(move |event| recorded.lock().unwrap().push(event.clone()));
+
+        history.undo();
+
+        assert_eq!(*events.lock().unwrap(), vec![event(19..20, "!", "", 19)]);
+    }
+
+    #[test]
+    fn removed_listeners_are_not_called() {
+        let mut buffer = GapBuffer::new();
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let id = buffer.on_change(move |_| *counter.lock().unwrap() += 1);
+
+        buffer.insert_str("a");
+        assert!(buffer.remove_listener(id));
+        buffer.insert_str("b");
+
+        assert_eq!(*calls.lock().unwrap(), 1);
+        assert!(!buffer.remove_listener(id));
+    }
+}
@@ -0,0 +1,119 @@
+/// What a fuzzy match scores for each character matched, before bonuses and penalties.
+const MATCH_SCORE: i64 = 16;
+/// The bonus for a character matched right after the one before it in the pattern.
+const CONSECUTIVE_BONUS: i64 = 12;
+/// The bonus for a character matched at the start of the candidate or after a separator.
+const BOUNDARY_BONUS: i64 = 10;
+/// The bonus for an uppercase character matched after a lowercase one, as in `camelCase`.
+const CAMEL_BONUS: i64 = 6;
+/// The bonus for a character matched in the last component of a path.
+const FILE_NAME_BONUS: i64 = 2;
+/// The penalty for each character skipped between two matched ones, up to `MAX_GAP_PENALTY`.
+const GAP_PENALTY: i64 = 1;
+const MAX_GAP_PENALTY: i64 = 10;
+
+/// is_separator() reports whether `character` separates the words of a file name.
+fn is_separator(character: char) -> bool {
+    matches!(character, '/' | '\\' | '_' | '-' | '.' | ' ')
+}
+
+/// score() rates how well `candidate` matches `pattern`, whose characters have to appear in it in
+/// order but not necessarily next to each other. Higher is better, and `None` means no match.
+/// Matches of consecutive characters, at the starts of words and in the file name of a path score
+/// more, and gaps between matched characters score less. The pattern is matched ignoring case
+/// unless it has an uppercase letter.
+pub fn score(pattern: &str, candidate: &str) -> Option<i64> {
+    let ignore_case = !pattern.chars().any(char::is_uppercase);
+    let fold = |character: char| if ignore_case { character.to_lowercase().next().unwrap_or(character) } else { character };
+    let pattern: Vec<char> = pattern.chars().map(fold).collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let folded: Vec<char> = original.iter().map(|character| fold(*character)).collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+
+    let file_name_start = original.iter().rposition(|character| *character == '/').map_or(0, |slash| slash + 1);
+    let bonus = |index: usize| {
+        let mut bonus = if index >= file_name_start { FILE_NAME_BONUS } else { 0 };
+        match index.checked_sub(1).map(|previous| original[previous]) {
+            None => bonus += BOUNDARY_BONUS,
+            Some(previous) if is_separator(previous) => bonus += BOUNDARY_BONUS,
+            Some(previous) if previous.is_lowercase() && original[index].is_uppercase() => bonus += CAMEL_BONUS,
+            _ => {}
+        }
+        bonus
+    };
+
+    // best[j] is the best score of the pattern so far with its last character matched at j.
+    let mut best: Vec<Option<i64>> = folded.iter().enumerate().map(|(index, character)| (*character == pattern[0]).then(|| MATCH_SCORE + bonus(index))).collect();
+    for character in pattern.iter().skip(1) {
+        let mut next = vec![None; folded.len()];
+        for (index, candidate) in folded.iter().enumerate() {
+            if candidate != character {
+                continue;
+            }
+            let from_earlier = (0..index)
+                .filter_map(|earlier| {
+                    let score = best[earlier]?;
+                    let gap = (index - earlier - 1) as i64;
+                    Some(if gap == 0 { score + CONSECUTIVE_BONUS } else { score - (gap * GAP_PENALTY).min(MAX_GAP_PENALTY) })
+                })
+                .max();
+            next[index] = from_earlier.map(|score| score + MATCH_SCORE + bonus(index));
+        }
+        best = next;
+    }
+
+    best.into_iter().flatten().max()
+}
+
+/// rank() returns the indexes of the `candidates` matching `pattern`, best first. Candidates
+/// that score the same are put shorter first, and then in their order in `candidates`. With no
+/// pattern they are all kept in their order.
+pub fn rank<S: AsRef<str>>(pattern: &str, candidates: &[S]) -> Vec<usize> {
+    if pattern.is_empty() {
+        return (0..candidates.len()).collect();
+    }
+    let mut scored: Vec<(usize, i64)> = candidates.iter().enumerate().filter_map(|(index, candidate)| Some((index, score(pattern, candidate.as_ref())?))).collect();
+    scored.sort_by_key(|(index, score)| (-score, candidates[*index].as_ref().len(), *index));
+
+    scored.into_iter().map(|(index, _)| index).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use fuzzy::{rank, score};
+
+    #[test]
+    fn score_matches_in_order() {
+        let test_cases = [
+            ("in order", "mbf", "minibuffer.rs", true),
+            ("out of order", "fbm", "minibuffer.rs", false),
+            ("ignoring case", "readme", "README.md", true),
+            ("keeping case", "Readme", "readme.md", false),
+            ("empty pattern", "", "anything", true),
+            ("longer than the candidate", "main.rs.bak", "main.rs", false),
+        ];
+
+        for (name, pattern, candidate, expected) in test_cases.iter() {
+            assert_eq!(score(pattern, candidate).is_some(), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn rank_better_matches_first() {
+        let candidates = ["src/buffer_manager.rs", "src/editor/commands.rs", "src/minibuffer.rs", "src/buffer.rs", "src/bookmark.rs", "Cargo.toml"];
+        let test_cases = [
+            ("consecutive characters", "buffer", vec!["src/buffer.rs", "src/buffer_manager.rs", "src/minibuffer.rs"]),
+            ("word starts", "bm", vec!["src/buffer_manager.rs", "src/bookmark.rs"]),
+            ("file name", "com", vec!["src/editor/commands.rs", "Cargo.toml", "src/bookmark.rs"]),
+            ("nothing", "xyz", vec![]),
+            ("no pattern", "", candidates.to_vec()),
+        ];
+
+        for (name, pattern, expected) in test_cases.iter() {
+            let ranked: Vec<&str> = rank(pattern, &candidates).into_iter().map(|index| candidates[index]).collect();
+            assert_eq!(ranked, *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use diff::{diff, Hunk};
+
+/// run() runs git with `args` in `directory` and returns what it wrote, or an error with what it
+/// wrote to its standard error if it failed.
+pub fn run(directory: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(io::Error::other(message));
+    }
+    String::from_utf8(output.stdout).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// head_text() returns the text of the file at `path` as of the last commit of the repository it
+/// is in, or None if it is not in one, was not in that commit, or git can not be run.
+pub fn head_text(path: &Path) -> Option<String> {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let name = path.file_name()?.to_str()?;
+
+    run(directory, &["show", &format!("HEAD:./{}", name)]).ok()
+}
+
+/// line_changes() returns the hunks of lines that changed from `head` to `text`. Lines are
+/// counted as a buffer counts them, so the line after a final newline is one of them, and
+/// carriage returns before newlines are ignored.
+pub fn line_changes(head: &str, text: &str) -> Vec<Hunk> {
+    diff(&split_lines(head), &split_lines(text))
+}
+
+/// split_lines() returns the lines of `text` as `line_changes()` compares them.
+pub fn split_lines(text: &str) -> Vec<String> {
+    text.split('\n').map(|line| line.trim_end_matches('\r').to_string()).collect()
+}
+
+/// How long each unit `describe_age()` counts in is, in seconds, the longest first.
+const AGE_UNITS: [(&str, u64); 5] = [("year", 365 * 86400), ("month", 30 * 86400), ("day", 86400), ("hour", 3600), ("minute", 60)];
+
+/// BlameLine is who last changed a line of a file.
+/// - `commit`: The id of the commit that did, which is all zeros for a change not committed yet.
+/// - `author`: Who wrote the change.
+/// - `time`: When the change was written, in seconds since the Unix epoch.
+/// - `text`: The line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    pub commit: String,
+    pub author: String,
+    pub time: u64,
+    pub text: String,
+}
+
+/// blame() returns who last changed each line of the file at `path`, as it is on disk, in order.
+pub fn blame(path: &Path) -> io::Result<Vec<BlameLine>> {
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().and_then(|name| name.to_str()).ok_or_else(|| io::Error::other("The file has no name"))?;
+
+    Ok(parse_blame(&run(directory, &["blame", "--porcelain", "--", name])?))
+}
+
+/// parse_blame() reads what `git blame --porcelain` writes. Each line is given after a header
+/// naming its commit, and the author of each commit is only given the first time it is named.
+pub fn parse_blame(porcelain: &str) -> Vec<BlameLine> {
+    let mut authors: HashMap<String, (String, u64)> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut commit = String::new();
+
+    for line in porcelain.lines() {
+        if let Some(text) = line.strip_prefix('\t') {
+            let (author, time) = authors.get(&commit).cloned().unwrap_or_default();
+            lines.push(BlameLine { commit: commit.clone(), author, time, text: text.to_string() });
+        } else if let Some(author) = line.strip_prefix("author ") {
+            authors.entry(commit.clone()).or_default().0 = author.to_string();
+        } else if let Some(time) = line.strip_prefix("author-time ") {
+            authors.entry(commit.clone()).or_default().1 = time.trim().parse().unwrap_or(0);
+        } else {
+            let id = line.split(' ').next().unwrap_or("");
+            if id.len() == 40 && id.chars().all(|character| character.is_ascii_hexdigit()) {
+                commit = id.to_string();
+            }
+        }
+    }
+
+    lines
+}
+
+/// describe_age() writes how long `seconds` is as a person would say how long ago something
+/// was, in the largest unit it has a whole one of, such as `3 days ago`.
+pub fn describe_age(seconds: u64) -> String {
+    match AGE_UNITS.iter().find(|(_, length)| seconds >= *length) {
+        Some((unit, length)) => {
+            let count = seconds / length;
+            format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+        }
+        None => "just now".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diff::Hunk;
+    use git::{describe_age, line_changes, parse_blame, BlameLine};
+
+    #[test]
+    fn compare_lines_as_the_buffer_numbers_them() {
+        let hunks = line_changes("one\r\ntwo\r\nthree\r\n", "one\ntwo\nthree\nfour\n");
+
+        assert_eq!(hunks, vec![Hunk { old: 3..3, new: 3..4 }]);
+        assert_eq!(line_changes("one\n", "one\n"), vec![]);
+    }
+    #[test]
+    fn read_who_changed_each_line() {
+        let first = "a".repeat(40);
+        let second = "b".repeat(40);
+        let porcelain = format!(
+            "{first} 1 1 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 1000\nsummary Start\nfilename notes.txt\n\tone\n\
+             {first} 2 2\n\ttwo\n\
+             {second} 3 3 1\nauthor Grace\nauthor-time 2000\nfilename notes.txt\n\t\tthree\n",
+            first = first,
+            second = second
+        );
+
+        let line = |commit: &str, author: &str, time: u64, text: &str| BlameLine {
+            commit: commit.to_string(),
+            author: author.to_string(),
+            time,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            parse_blame(&porcelain),
+            vec![line(&first, "Ada", 1000, "one"), line(&first, "Ada", 1000, "two"), line(&second, "Grace", 2000, "\tthree")]
+        );
+    }
+
+    #[test]
+    fn say_how_long_ago() {
+        let test_cases = [
+            ("under a minute", 59, "just now"),
+            ("a minute", 60, "1 minute ago"),
+            ("hours", 3 * 3600 + 59, "3 hours ago"),
+            ("days", 2 * 86400, "2 days ago"),
+            ("months", 40 * 86400, "1 month ago"),
+            ("years", 800 * 86400, "2 years ago"),
+        ];
+
+        for (name, seconds, expected) in test_cases.iter() {
+            assert_eq!(describe_age(*seconds), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+}
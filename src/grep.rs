@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+
+use project;
+
+/// GrepMatch is a line of a file with a match in it.
+/// - `path`: The file, relative to the directory searched.
+/// - `line`: The zero-based line.
+/// - `column`: The byte column of the first match on the line.
+/// - `text`: The line, without its line break.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+impl GrepMatch {
+    /// to_line() writes the match as `grep -n` and compilers do, as `path:line:column: text`
+    /// with the line and column counted from one.
+    pub fn to_line(&self) -> String {
+        format!("{}:{}:{}: {}", self.path.display(), self.line + 1, self.column + 1, self.text)
+    }
+}
+
+/// parse_location() reads the `path:line:column` a line written by `GrepMatch::to_line()` starts
+/// with, returning the path and the zero-based line and column. The column may be left out.
+pub fn parse_location(line: &str) -> Option<(PathBuf, usize, usize)> {
+    let mut fields = line.splitn(4, ':');
+    let path = fields.next().filter(|path| !path.is_empty())?;
+    let line_number: usize = fields.next()?.trim().parse().ok()?;
+    let column: usize = fields.next().and_then(|column| column.trim().parse().ok()).unwrap_or(1);
+
+    Some((PathBuf::from(path), line_number.checked_sub(1)?, column.saturating_sub(1)))
+}
+
+/// search_text() returns the lines of `text` with `pattern` in them, as being at `path`. Text
+/// with a NUL byte in it is taken to be binary and has no matches.
+pub fn search_text(path: &Path, text: &str, pattern: &str) -> Vec<GrepMatch> {
+    if pattern.is_empty() || text.contains('\0') {
+        return Vec::new();
+    }
+
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, content)| {
+            let column = content.find(pattern)?;
+            Some(GrepMatch { path: path.to_path_buf(), line, column, text: content.to_string() })
+        })
+        .collect()
+}
+
+/// GrepEvent is what a running grep has to report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GrepEvent {
+    Match(GrepMatch),
+    /// Every file has been searched.
+    Finished,
+}
+
+/// Grep searches the files of a directory for a pattern on a thread of its own, so the editor
+/// can go on while it runs. Its matches are picked up with `poll()`. The search stops when the
+/// grep is dropped.
+#[derive(Debug)]
+pub struct Grep {
+    receiver: Receiver<GrepEvent>,
+    cancelled: Arc<AtomicBool>,
+    finished: bool,
+}
+
+impl Grep {
+    /// spawn() starts searching the files under `root` that its `.gitignore` files leave in for
+    /// `pattern`.
+    pub fn spawn(root: &Path, pattern: &str) -> Grep {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (root, pattern, stop) = (root.to_path_buf(), pattern.to_string(), cancelled.clone());
+
+        thread::spawn(move || {
+            for path in project::files(&root) {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let text = match fs::read_to_string(root.join(&path)) {
+                    Ok(text) => text,
+                    Err(_) => continue,
+                };
+                for found in search_text(&path, &text, &pattern) {
+                    if sender.send(GrepEvent::Match(found)).is_err() {
+                        return;
+                    }
+                }
+            }
+            let _ = sender.send(GrepEvent::Finished);
+        });
+
+        Grep { receiver, cancelled, finished: false }
+    }
+
+    /// poll() returns what the search has found since the last call, without waiting.
+    pub fn poll(&mut self) -> Vec<GrepEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(event) => {
+                    self.finished |= event == GrepEvent::Finished;
+                    events.push(event);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if !self.finished {
+                        self.finished = true;
+                        events.push(GrepEvent::Finished);
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl Drop for Grep {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    use grep::{parse_location, search_text, Grep, GrepEvent, GrepMatch};
+
+    #[test]
+    fn find_and_read_back_matching_lines() {
+        let found = search_text(Path::new("src/lib.rs"), "fn one() {}\nlet x = 1;\n    one();\n", "one");
+        let lines: Vec<String> = found.iter().map(GrepMatch::to_line).collect();
+        assert_eq!(lines, vec!["src/lib.rs:1:4: fn one() {}", "src/lib.rs:3:5:     one();"]);
+        assert!(search_text(Path::new("image.png"), "one\0", "one").is_empty());
+
+        let test_cases = [
+            ("with a column", "src/lib.rs:3:5: one();", Some((PathBuf::from("src/lib.rs"), 2, 4))),
+            ("without a column", "README:10: one", Some((PathBuf::from("README"), 9, 0))),
+            ("no line", "Grep finished", None),
+            ("line zero", "README:0:1: one", None),
+        ];
+        for (name, line, expected) in test_cases.iter() {
+            assert_eq!(parse_location(line), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn grep_a_directory_in_the_background() {
+        let root = env::temp_dir().join(format!("one-grep-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "needle\nhay\n").unwrap();
+        fs::write(root.join("src/b.rs"), "hay\nhay needle\n").unwrap();
+
+        let mut grep = Grep::spawn(&root, "needle");
+        let mut events = Vec::new();
+        while !grep.is_finished() {
+            events.extend(grep.poll());
+            thread::sleep(Duration::from_millis(1));
+        }
+        fs::remove_dir_all(&root).unwrap();
+
+        let matches: Vec<String> = events
+            .iter()
+            .filter_map(|event| match event {
+                GrepEvent::Match(found) => Some(found.to_line()),
+                GrepEvent::Finished => None,
+            })
+            .collect();
+        assert_eq!(matches, vec!["src/a.rs:1:1: needle", "src/b.rs:2:5: hay needle"]);
+        assert_eq!(events.last(), Some(&GrepEvent::Finished));
+    }
+}
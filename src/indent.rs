@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use GapBuffer;
+
+/// The columns of a level of indentation until a buffer says otherwise.
+pub const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// IndentSettings is how a buffer is indented.
+/// - `use_tabs`: Whether indentation is made of tabs, with spaces only for what is left over
+///   between tab stops. A level is then one tab.
+/// - `width`: The columns a level of indentation made of spaces takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndentSettings {
+    pub use_tabs: bool,
+    pub width: usize,
+}
+
+impl Default for IndentSettings {
+    fn default() -> IndentSettings {
+        IndentSettings {
+            use_tabs: false,
+            width: DEFAULT_INDENT_WIDTH,
+        }
+    }
+}
+
+impl IndentSettings {
+    /// unit() returns the text of one level of indentation.
+    pub fn unit(&self) -> String {
+        match self.use_tabs {
+            true => "\t".to_string(),
+            false => " ".repeat(self.width),
+        }
+    }
+
+    /// level_width() returns the columns a level takes with tab stops every `tab_width` columns.
+    pub fn level_width(&self, tab_width: usize) -> usize {
+        match self.use_tabs {
+            true => tab_width,
+            false => self.width,
+        }
+    }
+
+    /// indentation() returns the text that indents to `columns`, with tab stops every `tab_width`
+    /// columns.
+    pub fn indentation(&self, columns: usize, tab_width: usize) -> String {
+        match self.use_tabs {
+            true => format!("{}{}", "\t".repeat(columns / tab_width), " ".repeat(columns % tab_width)),
+            false => " ".repeat(columns),
+        }
+    }
+}
+
+/// Indentation analysis. A line's indentation is the spaces and tabs it starts with.
+impl GapBuffer {
+    /// line_indentation() returns the spaces and tabs at the start of `line`, or an empty string
+    /// if there is no such line.
+    pub fn line_indentation(&self, line: usize) -> String {
+        let (start, end) = match (self.line_to_offset(line), self.line_end_offset(line)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return String::new(),
+        };
+
+        self.slice(start..end).chars().take_while(|character| *character == ' ' || *character == '\t').collect()
+    }
+
+    /// is_blank() reports whether `line` holds nothing but spaces and tabs.
+    pub fn is_blank(&self, line: usize) -> bool {
+        match (self.line_to_offset(line), self.line_end_offset(line)) {
+            (Some(start), Some(end)) => self.slice(start..end).trim().is_empty(),
+            _ => true,
+        }
+    }
+
+    /// previous_non_blank_line() returns the nearest line before `line` with more than spaces and
+    /// tabs on it.
+    pub fn previous_non_blank_line(&self, line: usize) -> Option<usize> {
+        (0..line.min(self.line_count())).rev().find(|line| !self.is_blank(*line))
+    }
+
+    /// last_non_blank_char() returns the last character of `line` that is not whitespace.
+    pub fn last_non_blank_char(&self, line: usize) -> Option<char> {
+        let start = self.line_to_offset(line)?;
+        let end = self.line_end_offset(line)?;
+
+        self.slice(start..end).chars().rev().find(|character| !character.is_whitespace())
+    }
+}
+
+/// Indenter works out how a new line is indented from the lines before it. Each file type can
+/// have its own.
+pub trait Indenter {
+    /// indentation() returns the indentation for `line` of `buffer`, indenting by `unit` for each
+    /// level it adds.
+    fn indentation(&self, buffer: &GapBuffer, line: usize, unit: &str) -> String;
+}
+
+/// CopyIndent indents a line as far as the last non-blank line before it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CopyIndent;
+
+impl Indenter for CopyIndent {
+    fn indentation(&self, buffer: &GapBuffer, line: usize, _: &str) -> String {
+        buffer.previous_non_blank_line(line).map_or_else(String::new, |previous| buffer.line_indentation(previous))
+    }
+}
+
+/// OpenerIndent indents a line one level further than the last non-blank line before it when
+/// that line ends in one of `openers`, such as `{` or `:`, and as far as it otherwise.
+#[derive(Debug, Clone)]
+pub struct OpenerIndent {
+    pub openers: Vec<char>,
+}
+
+impl Indenter for OpenerIndent {
+    fn indentation(&self, buffer: &GapBuffer, line: usize, unit: &str) -> String {
+        let previous = match buffer.previous_non_blank_line(line) {
+            Some(previous) => previous,
+            None => return String::new(),
+        };
+
+        let mut indentation = buffer.line_indentation(previous);
+        if buffer.last_non_blank_char(previous).is_some_and(|last| self.openers.contains(&last)) {
+            indentation.push_str(unit);
+        }
+        indentation
+    }
+}
+
+/// indenter_for() returns the indenter for the file at `path`, judged by its extension: one that
+/// indents after opening brackets for C-like languages, and after colons too for Python. Other
+/// files copy the indentation of the line before.
+pub fn indenter_for(path: &Path) -> Box<dyn Indenter> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("");
+
+    match extension {
+        "rs" | "c" | "h" | "cc" | "cpp" | "hpp" | "js" | "mjs" | "cjs" | "ts" | "java" | "go" | "json" => {
+            Box::new(OpenerIndent { openers: vec!['{', '[', '('] })
+        }
+        "py" | "pyw" => Box::new(OpenerIndent { openers: vec![':', '{', '[', '('] }),
+        _ => Box::new(CopyIndent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use indent::{indenter_for, CopyIndent, IndentSettings, Indenter};
+    use GapBuffer;
+
+    const TEST_STRING: &str = "fn main() {\n\tif x {\n\n  \n\t\tone();\n";
+
+    #[test]
+    fn analyse_indentation() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+
+        assert_eq!(buffer.line_indentation(1), "\t");
+        assert_eq!(buffer.line_indentation(3), "  ");
+        assert_eq!(buffer.line_indentation(9), "");
+        assert!(buffer.is_blank(2) && buffer.is_blank(3) && !buffer.is_blank(4));
+        assert_eq!(buffer.previous_non_blank_line(4), Some(1));
+        assert_eq!(buffer.previous_non_blank_line(0), None);
+        assert_eq!(buffer.last_non_blank_char(0), Some('{'));
+        assert_eq!(buffer.last_non_blank_char(2), None);
+    }
+
+    #[test]
+    fn indent_new_lines_by_file_type() {
+        let buffer = GapBuffer::from(TEST_STRING.to_string());
+        let test_cases = [
+            ("after an opening brace", "main.rs", 1, "  "),
+            ("after blank lines", "main.rs", 4, "\t  "),
+            ("after a plain line", "main.rs", 5, "\t\t"),
+            ("a file type without openers", "notes.txt", 4, "\t"),
+            ("the first line", "main.rs", 0, ""),
+        ];
+
+        for (name, path, line, expected) in test_cases.iter() {
+            let indentation = indenter_for(Path::new(path)).indentation(&buffer, *line, "  ");
+            assert_eq!(indentation, *expected, "Test case: \"{}\" failed.", name);
+        }
+        assert_eq!(CopyIndent.indentation(&GapBuffer::from("a:\n".to_string()), 1, "  "), "");
+    }
+
+    #[test]
+    fn indent_with_spaces_or_tabs() {
+        let spaces = IndentSettings { use_tabs: false, width: 2 };
+        let tabs = IndentSettings { use_tabs: true, width: 2 };
+
+        assert_eq!(spaces.unit(), "  ");
+        assert_eq!(tabs.unit(), "\t");
+        assert_eq!(spaces.level_width(8), 2);
+        assert_eq!(tabs.level_width(8), 8);
+        assert_eq!(spaces.indentation(10, 8), " ".repeat(10));
+        assert_eq!(tabs.indentation(10, 8), "\t  ");
+    }
+}
@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+use std::iter::{Chain, Copied, Peekable};
+use std::slice;
+
+use {is_continuation_byte, GapBuffer};
+
+/// Bytes iterates over the bytes of a `GapBuffer`, skipping the gap.
+pub struct Bytes<'a> {
+    inner: Chain<Copied<slice::Iter<'a, u8>>, Copied<slice::Iter<'a, u8>>>,
+}
+
+impl<'a> Bytes<'a> {
+    pub(crate) fn new(front: &'a [u8], back: &'a [u8]) -> Bytes<'a> {
+        Bytes {
+            inner: front.iter().copied().chain(back.iter().copied()),
+        }
+    }
+}
+
+impl<'a> Iterator for Bytes<'a> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Bytes<'a> {
+    fn next_back(&mut self) -> Option<u8> {
+        self.inner.next_back()
+    }
+}
+
+/// Chars decodes the UTF-8 characters of a `GapBuffer`, skipping the gap.
+/// Invalid UTF-8 sequences are yielded as `U+FFFD REPLACEMENT CHARACTER`.
+pub struct Chars<'a> {
+    bytes: Peekable<Bytes<'a>>,
+}
+
+impl<'a> Chars<'a> {
+    pub(crate) fn new(bytes: Bytes<'a>) -> Chars<'a> {
+        Chars {
+            bytes: bytes.peekable(),
+        }
+    }
+}
+
+impl<'a> Iterator for Chars<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let lead = self.bytes.next()?;
+        let width = utf8_width(lead);
+        if width == 0 {
+            return Some(char::REPLACEMENT_CHARACTER);
+        }
+
+        let mut encoded = [lead, 0, 0, 0];
+        for byte in encoded.iter_mut().take(width).skip(1) {
+            match self.bytes.peek() {
+                Some(next) if is_continuation_byte(*next) => *byte = self.bytes.next().unwrap(),
+                _ => return Some(char::REPLACEMENT_CHARACTER),
+            }
+        }
+
+        let character = std::str::from_utf8(&encoded[..width])
+            .ok()
+            .and_then(|text| text.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+
+        Some(character)
+    }
+}
+
+/// Chunks yields the contiguous runs of text either side of the gap. Empty runs are skipped.
+/// Invalid UTF-8 sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+pub struct Chunks<'a> {
+    chunks: [&'a [u8]; 2],
+    index: usize,
+}
+
+impl<'a> Chunks<'a> {
+    pub(crate) fn new(front: &'a [u8], back: &'a [u8]) -> Chunks<'a> {
+        Chunks {
+            chunks: [front, back],
+            index: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Cow<'a, str>> {
+        while self.index < self.chunks.len() {
+            let chunk = self.chunks[self.index];
+            self.index += 1;
+
+            if !chunk.is_empty() {
+                return Some(String::from_utf8_lossy(chunk));
+            }
+        }
+
+        None
+    }
+}
+
+/// Lines iterates over the lines of a `GapBuffer`, split on `\n`. The newline is not included.
+/// The text after the last newline is always yielded as the final line, even when it is empty.
+pub struct Lines<'a> {
+    buffer: &'a GapBuffer,
+    start: Option<usize>,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(buffer: &'a GapBuffer) -> Lines<'a> {
+        Lines {
+            buffer,
+            start: Some(0),
+        }
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = Cow<'a, str>;
+
+    fn next(&mut self) -> Option<Cow<'a, str>> {
+        let start = self.start?;
+
+        match self.buffer.find_byte(b'\n', start) {
+            Some(newline) => {
+                self.start = Some(newline + 1);
+                Some(self.buffer.slice(start..newline))
+            }
+            None => {
+                self.start = None;
+                Some(self.buffer.slice(start..self.buffer.len()))
+            }
+        }
+    }
+}
+
+fn utf8_width(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {buffer_with_gap_at, GapBuffer};
+
+    const TEST_STRING: &str = "Grüße, 世界! 🦀";
+
+    #[test]
+    fn iterate_bytes() {
+        for index in [0, 2, 12, TEST_STRING.len()].iter() {
+            let buffer = buffer_with_gap_at(TEST_STRING, *index);
+
+            assert_eq!(buffer.bytes().collect::<Vec<u8>>(), TEST_STRING.as_bytes().to_vec());
+            assert_eq!(buffer.bytes().rev().collect::<Vec<u8>>(), TEST_STRING.bytes().rev().collect::<Vec<u8>>());
+        }
+    }
+
+    #[test]
+    fn iterate_chars() {
+        for index in [0, 2, 12, TEST_STRING.len()].iter() {
+            let buffer = buffer_with_gap_at(TEST_STRING, *index);
+
+            assert_eq!(buffer.chars().collect::<String>(), TEST_STRING);
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_when_iterating_chars() {
+        let mut buffer = GapBuffer::new();
+        buffer.insert_bytes(vec![b'a', 0xff, 0xe4, 0xb8, b'b']);
+
+        assert_eq!(buffer.chars().collect::<String>(), "a\u{fffd}\u{fffd}b");
+    }
+
+    #[test]
+    fn iterate_chunks() {
+        let buffer = buffer_with_gap_at(TEST_STRING, 8);
+        let chunks: Vec<String> = buffer.chunks().map(|chunk| chunk.into_owned()).collect();
+
+        assert_eq!(chunks, vec![TEST_STRING[..8].to_string(), TEST_STRING[8..].to_string()]);
+    }
+
+    #[test]
+    fn iterate_lines() {
+        let test_cases = [
+            ("empty buffer", "", vec![""]),
+            ("single line", "one", vec!["one"]),
+            ("trailing line without newline", "one\ntwo", vec!["one", "two"]),
+            ("trailing newline", "one\ntwo\n", vec!["one", "two", ""]),
+            ("blank lines", "\n\none\n\n", vec!["", "", "one", "", ""]),
+        ];
+
+        for (name, text, expected_lines) in test_cases.iter() {
+            for gap_position in 0..=text.len() {
+                let mut buffer = GapBuffer::from(text.to_string());
+                buffer.set_point(gap_position);
+                buffer.insert_bytes(Vec::new());
+
+                let lines: Vec<String> = buffer.lines().map(|line| line.into_owned()).collect();
+
+                assert_eq!(&lines, expected_lines, "Test case: \"{}\" failed.", name);
+                assert_eq!(buffer.line_count(), expected_lines.len(), "Test case: \"{}\" failed.", name);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_chunks_are_skipped() {
+        let buffer = buffer_with_gap_at(TEST_STRING, TEST_STRING.len());
+
+        assert_eq!(buffer.chunks().count(), 1);
+        assert_eq!(GapBuffer::new().chunks().count(), 0);
+    }
+}
@@ -0,0 +1,184 @@
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+use shell::shell;
+
+/// JobEvent is what a running job has to report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobEvent {
+    /// Text the process wrote, to stdout or stderr, a line at a time.
+    Output(String),
+    /// The process has exited and all of its output has been reported.
+    Exited(ExitStatus),
+}
+
+/// Job is an external process, such as a formatter or a compiler, run with the system shell in
+/// the background so the editor can go on while it runs. Its output is read on threads of its
+/// own and picked up with `poll()`. The process is killed if it is still running when the job is
+/// dropped.
+#[derive(Debug)]
+pub struct Job {
+    command_line: String,
+    child: Child,
+    receiver: Receiver<String>,
+    status: Option<ExitStatus>,
+}
+
+impl Job {
+    /// spawn() starts `command_line` in `directory`, with nothing on its stdin.
+    pub fn spawn(command_line: &str, directory: &Path) -> io::Result<Job> {
+        let mut child = shell(command_line)
+            .current_dir(directory)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let (sender, receiver) = mpsc::channel();
+        forward_lines(child.stdout.take().unwrap(), sender.clone());
+        forward_lines(child.stderr.take().unwrap(), sender);
+
+        Ok(Job {
+            command_line: command_line.to_string(),
+            child,
+            receiver,
+            status: None,
+        })
+    }
+
+    pub fn command_line(&self) -> &str {
+        &self.command_line
+    }
+
+    /// id() is the operating system's id for the process.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// status() is how the process exited, or None while it is running or has output left to
+    /// report.
+    pub fn status(&self) -> Option<ExitStatus> {
+        self.status
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.is_none()
+    }
+
+    /// poll() returns what the process has written since the last call, without waiting, and
+    /// then `JobEvent::Exited` once, after the last of its output.
+    pub fn poll(&mut self) -> Vec<JobEvent> {
+        let mut events = Vec::new();
+        if self.status.is_some() {
+            return events;
+        }
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(line) => events.push(JobEvent::Output(line)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if let Ok(Some(status)) = self.child.try_wait() {
+                        self.status = Some(status);
+                        events.push(JobEvent::Exited(status));
+                    }
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    /// kill() stops the process, and returns false if it had already exited. Its exit is still
+    /// reported by `poll()`.
+    pub fn kill(&mut self) -> bool {
+        match self.child.try_wait() {
+            Ok(None) => self.child.kill().is_ok(),
+            _ => false,
+        }
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        if self.kill() {
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// forward_lines() sends each line read from `output`, with its line break, until it is closed.
+/// Text that is not valid UTF-8 is replaced with `U+FFFD REPLACEMENT CHARACTER`.
+fn forward_lines<R: Read + Send + 'static>(output: R, sender: Sender<String>) {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(output);
+        let mut line = Vec::new();
+        while let Ok(count) = reader.read_until(b'\n', &mut line) {
+            if count == 0 || sender.send(String::from_utf8_lossy(&line).into_owned()).is_err() {
+                return;
+            }
+            line.clear();
+        }
+    });
+}
+
+/// describe_exit() says how a process exited, as a job's status is listed.
+pub fn describe_exit(status: ExitStatus) -> String {
+    match status.code() {
+        Some(0) => "finished".to_string(),
+        Some(code) => format!("exited with code {}", code),
+        None => "killed".to_string(),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::env;
+    use std::thread;
+    use std::time::Duration;
+
+    use job::{describe_exit, Job, JobEvent};
+
+    /// wait() polls `job` until it has exited, returning everything it reported.
+    fn wait(job: &mut Job) -> Vec<JobEvent> {
+        let mut events = Vec::new();
+        while job.is_running() {
+            events.extend(job.poll());
+            thread::sleep(Duration::from_millis(1));
+        }
+        events
+    }
+
+    #[test]
+    fn stream_the_output_of_a_job() {
+        let mut job = Job::spawn("echo one; echo two >&2; printf three; exit 4", &env::temp_dir()).unwrap();
+        let events = wait(&mut job);
+
+        let mut output: Vec<&str> = events
+            .iter()
+            .filter_map(|event| match event {
+                JobEvent::Output(text) => Some(text.as_str()),
+                JobEvent::Exited(_) => None,
+            })
+            .collect();
+        output.sort();
+        assert_eq!(output, vec!["one\n", "three", "two\n"]);
+        assert_eq!(events.last(), Some(&JobEvent::Exited(job.status().unwrap())));
+        assert_eq!(describe_exit(job.status().unwrap()), "exited with code 4");
+        assert!(job.poll().is_empty());
+    }
+
+    #[test]
+    fn kill_a_running_job() {
+        let mut job = Job::spawn("exec sleep 10", &env::temp_dir()).unwrap();
+        assert_eq!(job.command_line(), "exec sleep 10");
+
+        assert!(job.kill());
+        wait(&mut job);
+        assert_eq!(describe_exit(job.status().unwrap()), "killed");
+        assert!(!job.kill());
+    }
+}
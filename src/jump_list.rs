@@ -0,0 +1,103 @@
+use anchor::AnchorId;
+use buffer_manager::BufferId;
+
+/// The most places a jump list keeps. The oldest are dropped to make room for new ones.
+pub const MAX_JUMPS: usize = 100;
+
+/// JumpList keeps the places the point left by jumping far away, oldest first, to go back and
+/// forward through as a browser goes through its history. Each is an anchor in its buffer, so it
+/// stays on the same text as the buffer changes.
+/// - `position`: The index of the place gone back to, or the number of places when none is.
+#[derive(Debug, Default)]
+pub struct JumpList {
+    entries: Vec<(BufferId, AnchorId)>,
+    position: usize,
+}
+
+impl JumpList {
+    pub fn new() -> JumpList {
+        JumpList::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// at_newest() reports whether the list has not been gone back through since the last push.
+    pub fn at_newest(&self) -> bool {
+        self.position == self.entries.len()
+    }
+
+    /// push() records the place held by `anchor` in buffer `id` as the newest, in place of the
+    /// place gone back to and those after it. Returns the places dropped, whose anchors are no
+    /// longer needed.
+    pub fn push(&mut self, id: BufferId, anchor: AnchorId) -> Vec<(BufferId, AnchorId)> {
+        let mut dropped: Vec<(BufferId, AnchorId)> = self.entries.drain(self.position..).collect();
+        self.entries.push((id, anchor));
+        if self.entries.len() > MAX_JUMPS {
+            dropped.extend(self.entries.drain(..self.entries.len() - MAX_JUMPS));
+        }
+
+        self.position = self.entries.len();
+        dropped
+    }
+
+    /// back() goes back to the place before the one gone back to, or the newest, and returns it.
+    pub fn back(&mut self) -> Option<(BufferId, AnchorId)> {
+        self.position = self.position.checked_sub(1)?;
+        Some(self.entries[self.position])
+    }
+
+    /// forward() goes forward to the place after the one gone back to and returns it.
+    pub fn forward(&mut self) -> Option<(BufferId, AnchorId)> {
+        let entry = *self.entries.get(self.position + 1)?;
+        self.position += 1;
+        Some(entry)
+    }
+
+    /// entries() visits the places recorded, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = (BufferId, AnchorId)> + '_ {
+        self.entries.iter().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor::Affinity;
+    use buffer_manager::BufferManager;
+    use jump_list::{JumpList, MAX_JUMPS};
+
+    #[test]
+    fn go_back_and_forward_through_jumps() {
+        let mut buffers = BufferManager::new();
+        let id = buffers.create("jumps");
+        let buffer = buffers.get_mut(id).unwrap();
+        buffer.insert_str("0123456789");
+        let anchors: Vec<_> = (0..4).map(|offset| buffer.create_anchor(offset, Affinity::Left)).collect();
+
+        let mut jumps = JumpList::new();
+        assert_eq!(jumps.back(), None);
+        for anchor in anchors.iter().take(3) {
+            assert!(jumps.push(id, *anchor).is_empty());
+        }
+        assert!(jumps.at_newest());
+        assert_eq!(jumps.back(), Some((id, anchors[2])));
+        assert_eq!(jumps.back(), Some((id, anchors[1])));
+        assert_eq!(jumps.forward(), Some((id, anchors[2])));
+        assert_eq!(jumps.forward(), None);
+        assert!(!jumps.at_newest());
+
+        jumps.back();
+        assert_eq!(jumps.push(id, anchors[3]), vec![(id, anchors[1]), (id, anchors[2])]);
+        assert_eq!(jumps.entries().collect::<Vec<_>>(), vec![(id, anchors[0]), (id, anchors[3])]);
+
+        for _ in 0..MAX_JUMPS {
+            jumps.push(id, anchors[0]);
+        }
+        assert_eq!(jumps.len(), MAX_JUMPS);
+    }
+}
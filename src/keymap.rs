@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// KeyCode is a key on the keyboard, without modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Escape,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    F(u8),
+}
+
+/// Modifiers are the keys held down alongside another key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Modifiers {
+    pub control: bool,
+    pub meta: bool,
+    pub shift: bool,
+}
+
+/// Key is one key press, written in Emacs notation such as `a`, `C-x`, `M-<`, `C-M-f` or `RET`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+/// KeyParseError is returned for key notation that does not describe a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyParseError(pub String);
+
+impl fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid key: {:?}", self.0)
+    }
+}
+
+impl Error for KeyParseError {}
+
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("RET", KeyCode::Enter),
+    ("TAB", KeyCode::Tab),
+    ("SPC", KeyCode::Char(' ')),
+    ("DEL", KeyCode::Backspace),
+    ("ESC", KeyCode::Escape),
+    ("<delete>", KeyCode::Delete),
+    ("<left>", KeyCode::Left),
+    ("<right>", KeyCode::Right),
+    ("<up>", KeyCode::Up),
+    ("<down>", KeyCode::Down),
+    ("<home>", KeyCode::Home),
+    ("<end>", KeyCode::End),
+    ("<prior>", KeyCode::PageUp),
+    ("<next>", KeyCode::PageDown),
+    ("<insert>", KeyCode::Insert),
+];
+
+impl Key {
+    /// plain() is a key pressed without modifiers.
+    pub fn plain(code: KeyCode) -> Key {
+        Key {
+            code,
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    pub fn char(character: char) -> Key {
+        Key::plain(KeyCode::Char(character))
+    }
+
+    pub fn control(character: char) -> Key {
+        Key {
+            code: KeyCode::Char(character),
+            modifiers: Modifiers {
+                control: true,
+                ..Modifiers::default()
+            },
+        }
+    }
+
+    pub fn meta(character: char) -> Key {
+        Key {
+            code: KeyCode::Char(character),
+            modifiers: Modifiers {
+                meta: true,
+                ..Modifiers::default()
+            },
+        }
+    }
+
+    /// printable() returns the character this key types, if it types one.
+    pub fn printable(&self) -> Option<char> {
+        match self.code {
+            KeyCode::Char(character) if !self.modifiers.control && !self.modifiers.meta => Some(character),
+            _ => None,
+        }
+    }
+
+    /// parse() reads one key, e.g. `C-x`.
+    pub fn parse(text: &str) -> Result<Key, KeyParseError> {
+        let error = || KeyParseError(text.to_string());
+        let mut modifiers = Modifiers::default();
+        let mut rest = text;
+
+        while rest.len() > 2 && rest.as_bytes()[1] == b'-' {
+            match rest.as_bytes()[0] {
+                b'C' => modifiers.control = true,
+                b'M' => modifiers.meta = true,
+                b'S' => modifiers.shift = true,
+                _ => return Err(error()),
+            }
+            rest = &rest[2..];
+        }
+
+        let code = if let Some((_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == rest) {
+            *code
+        } else if let Some(number) = rest.strip_prefix("<f").and_then(|rest| rest.strip_suffix('>')) {
+            KeyCode::F(number.parse().map_err(|_| error())?)
+        } else {
+            let mut characters = rest.chars();
+            match (characters.next(), characters.next()) {
+                (Some(character), None) => KeyCode::Char(character),
+                _ => return Err(error()),
+            }
+        };
+
+        Ok(Key { code, modifiers })
+    }
+
+    /// parse_sequence() reads keys separated by spaces, e.g. `C-x C-s`.
+    pub fn parse_sequence(text: &str) -> Result<Vec<Key>, KeyParseError> {
+        let keys: Vec<Key> = text.split_whitespace().map(Key::parse).collect::<Result<_, _>>()?;
+        if keys.is_empty() {
+            return Err(KeyParseError(text.to_string()));
+        }
+
+        Ok(keys)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.control {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.meta {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "S-")?;
+        }
+
+        match self.code {
+            KeyCode::F(number) => write!(f, "<f{}>", number),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => match code {
+                    KeyCode::Char(character) => write!(f, "{}", character),
+                    _ => unreachable!(),
+                },
+            },
+        }
+    }
+}
+
+/// format_keys() writes a key sequence in Emacs notation, e.g. `C-x C-s`.
+pub fn format_keys(keys: &[Key]) -> String {
+    keys.iter().map(|key| key.to_string()).collect::<Vec<String>>().join(" ")
+}
+
+/// Binding is what a key is bound to: a command, run with a fixed argument, or a prefix key
+/// leading to further bindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    Command { name: String, argument: String },
+    Prefix(Keymap),
+}
+
+/// Lookup is the result of looking up a key sequence in a keymap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lookup<'a> {
+    /// The keys run the command with the argument.
+    Command(&'a str, &'a str),
+    /// The keys are the start of longer bindings.
+    Prefix,
+    Unbound,
+}
+
+/// The bindings of `Keymap::emacs()`, in Emacs notation.
+const EMACS_BINDINGS: &[(&str, &str)] = &[
+    ("C-f", "forward-char"),
+    ("C-b", "backward-char"),
+    ("C-n", "next-line"),
+    ("C-p", "previous-line"),
+    ("C-a", "beginning-of-line"),
+    ("C-e", "end-of-line"),
+    ("M-f", "forward-word"),
+    ("M-b", "backward-word"),
+    ("M-<", "beginning-of-buffer"),
+    ("M->", "end-of-buffer"),
+    ("<right>", "forward-char"),
+    ("<left>", "backward-char"),
+    ("<down>", "next-line"),
+    ("<up>", "previous-line"),
+    ("<home>", "beginning-of-line"),
+    ("<end>", "end-of-line"),
+    ("C-v", "scroll-up-command"),
+    ("M-v", "scroll-down-command"),
+    ("<next>", "scroll-up-command"),
+    ("<prior>", "scroll-down-command"),
+    ("C-l", "recenter"),
+    ("RET", "newline"),
+    ("DEL", "delete-backward"),
+    ("C-d", "delete-forward"),
+    ("<delete>", "delete-forward"),
+    ("C-SPC", "set-mark"),
+    ("C-k", "kill-line"),
+    ("M-d", "kill-word"),
+    ("M-DEL", "backward-kill-word"),
+    ("M-k", "kill-sentence"),
+    ("C-w", "kill-region"),
+    ("M-w", "copy-region"),
+    ("C-y", "yank"),
+    ("M-y", "yank-pop"),
+    ("C-s", "isearch-forward"),
+    ("C-r", "isearch-backward"),
+    ("M-%", "query-replace"),
+    ("M-s o", "occur"),
+    ("M-s g", "grep"),
+    ("C-x C-f", "find-file"),
+    ("C-x p f", "find-file-in-project"),
+    ("C-x d", "dired"),
+    ("C-x C-s", "save-buffer"),
+    ("C-x C-w", "write-file"),
+    ("C-x C-c", "save-buffers-kill-editor"),
+    ("C-x 0", "delete-window"),
+    ("C-x 1", "delete-other-windows"),
+    ("C-x 2", "split-window-below"),
+    ("C-x 3", "split-window-right"),
+    ("C-x o", "other-window"),
+    ("C-x +", "balance-windows"),
+    ("C-x ^", "enlarge-window"),
+    ("C-x }", "enlarge-window-horizontally"),
+    ("C-x {", "shrink-window-horizontally"),
+    ("M-x", "execute-extended-command"),
+    ("M-g g", "goto-line"),
+    ("M-g TAB", "goto-column"),
+    ("M-g c", "goto-char"),
+    ("M-g n", "next-location"),
+    ("M-g p", "previous-location"),
+    ("M-g M-n", "next-error"),
+    ("M-g M-p", "previous-error"),
+    ("C-x `", "next-error"),
+    ("C-x v ]", "next-hunk"),
+    ("C-x v [", "previous-hunk"),
+    ("C-x v n", "revert-hunk"),
+    ("C-x v g", "git-blame"),
+    ("M-.", "goto-definition"),
+    ("M-?", "find-references"),
+    ("M-,", "jump-back"),
+    ("C-M-,", "jump-forward"),
+    ("M-;", "toggle-comment"),
+    ("C-_", "undo"),
+    ("C-/", "undo"),
+    ("C-x u", "undo"),
+    ("M-_", "redo"),
+    ("C-t", "transpose-chars"),
+    ("M-t", "transpose-words"),
+    ("C-x C-t", "transpose-lines"),
+    ("M-u", "upcase-word"),
+    ("M-l", "downcase-word"),
+    ("M-c", "capitalize-word"),
+    ("C-x C-u", "upcase-region"),
+    ("C-x C-l", "downcase-region"),
+    ("M-q", "fill-paragraph"),
+    ("C-x f", "set-fill-column"),
+    ("C-x n n", "narrow-to-region"),
+    ("C-x n w", "widen"),
+    ("C-h e", "view-messages"),
+    ("M-|", "shell-command-on-region"),
+    ("M-&", "async-shell-command"),
+    ("C-c d", "duplicate-line"),
+    ("M-<up>", "move-line-up"),
+    ("M-<down>", "move-line-down"),
+    ("C-c j", "join-lines"),
+    ("C-M-o", "split-line"),
+    ("C-c ! n", "next-diagnostic"),
+    ("C-c ! p", "previous-diagnostic"),
+    ("M-TAB", "complete"),
+    ("M-/", "dabbrev-expand"),
+    ("C-x r m", "bookmark-set"),
+    ("C-x r b", "bookmark-jump"),
+    ("C-x r l", "bookmark-list"),
+    ("C-x (", "start-macro"),
+    ("C-x )", "end-macro"),
+    ("C-x e", "call-macro"),
+];
+
+/// Keymap binds key sequences to commands. Sequences longer than one key are stored as nested
+/// keymaps under each prefix key.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Keymap {
+    bindings: HashMap<Key, Binding>,
+}
+
+impl Keymap {
+    pub fn new() -> Keymap {
+        Keymap::default()
+    }
+
+    /// emacs() is the default keymap, with the Emacs bindings for moving, killing and yanking,
+    /// searching and saving.
+    pub fn emacs() -> Keymap {
+        let mut keymap = Keymap::new();
+        for (keys, name) in EMACS_BINDINGS {
+            keymap.bind_str(keys, name).unwrap();
+        }
+        keymap.bind_with_argument(&[Key::plain(KeyCode::Tab)], "insert-char", "\t");
+
+        keymap
+    }
+
+    /// bind() binds `keys` to the command `name`. Any binding that `keys` passes through or
+    /// extends is replaced. It will panic if `keys` is empty.
+    pub fn bind(&mut self, keys: &[Key], name: &str) {
+        self.bind_with_argument(keys, name, "");
+    }
+
+    /// bind_with_argument() binds `keys` to run the command `name` with `argument`.
+    pub fn bind_with_argument(&mut self, keys: &[Key], name: &str, argument: &str) {
+        let (last, prefix) = keys.split_last().expect("A binding needs at least one key.");
+
+        let mut keymap = self;
+        for key in prefix {
+            let binding = keymap.bindings.entry(*key).or_insert_with(|| Binding::Prefix(Keymap::new()));
+            if let Binding::Command { .. } = binding {
+                *binding = Binding::Prefix(Keymap::new());
+            }
+            keymap = match binding {
+                Binding::Prefix(keymap) => keymap,
+                Binding::Command { .. } => unreachable!(),
+            };
+        }
+
+        keymap.bindings.insert(
+            *last,
+            Binding::Command {
+                name: name.to_string(),
+                argument: argument.to_string(),
+            },
+        );
+    }
+
+    /// bind_str() binds keys written in Emacs notation, e.g. `keymap.bind_str("C-x C-s", "save-buffer")`.
+    pub fn bind_str(&mut self, keys: &str, name: &str) -> Result<(), KeyParseError> {
+        self.bind(&Key::parse_sequence(keys)?, name);
+        Ok(())
+    }
+
+    /// unbind() removes the binding for `keys`, and any bindings under it if it is a prefix.
+    pub fn unbind(&mut self, keys: &[Key]) -> Option<Binding> {
+        let (last, prefix) = keys.split_last()?;
+
+        let mut keymap = self;
+        for key in prefix {
+            keymap = match keymap.bindings.get_mut(key) {
+                Some(Binding::Prefix(keymap)) => keymap,
+                _ => return None,
+            };
+        }
+
+        keymap.bindings.remove(last)
+    }
+
+    /// lookup() finds what `keys` are bound to.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup<'_> {
+        let mut keymap = self;
+        let mut keys = keys.iter();
+
+        while let Some(key) = keys.next() {
+            match keymap.bindings.get(key) {
+                Some(Binding::Command { name, argument }) if keys.len() == 0 => return Lookup::Command(name, argument),
+                Some(Binding::Prefix(prefix)) => keymap = prefix,
+                _ => return Lookup::Unbound,
+            }
+        }
+
+        Lookup::Prefix
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+
+    /// bindings() lists every key sequence bound to a command, with the command's name.
+    pub fn bindings(&self) -> Vec<(Vec<Key>, &str)> {
+        let mut bindings = Vec::new();
+        for (key, binding) in self.bindings.iter() {
+            match binding {
+                Binding::Command { name, .. } => bindings.push((vec![*key], name.as_str())),
+                Binding::Prefix(keymap) => {
+                    for (mut keys, name) in keymap.bindings() {
+                        keys.insert(0, *key);
+                        bindings.push((keys, name));
+                    }
+                }
+            }
+        }
+
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use keymap::{format_keys, Key, KeyCode, Keymap, Lookup};
+
+    #[test]
+    fn parse_keys() {
+        assert_eq!(Key::parse("a"), Ok(Key::char('a')));
+        assert_eq!(Key::parse("C-x"), Ok(Key::control('x')));
+        assert_eq!(Key::parse("M-<"), Ok(Key::meta('<')));
+        assert_eq!(Key::parse("RET"), Ok(Key::plain(KeyCode::Enter)));
+        assert_eq!(Key::parse("<f5>"), Ok(Key::plain(KeyCode::F(5))));
+        assert_eq!(Key::parse("-"), Ok(Key::char('-')));
+
+        let key = Key::parse("C-M-f").unwrap();
+        assert!(key.modifiers.control && key.modifiers.meta);
+
+        assert!(Key::parse("X-a").is_err());
+        assert!(Key::parse("ab").is_err());
+        assert!(Key::parse_sequence("").is_err());
+    }
+
+    #[test]
+    fn format_keys_in_emacs_notation() {
+        let keys = Key::parse_sequence("C-x C-M-s SPC <f12> <left>").unwrap();
+
+        assert_eq!(format_keys(&keys), "C-x C-M-s SPC <f12> <left>");
+    }
+
+    #[test]
+    fn look_up_a_prefix_sequence() {
+        let mut keymap = Keymap::new();
+        keymap.bind_str("C-x C-s", "save-buffer").unwrap();
+        keymap.bind_str("C-f", "forward-char").unwrap();
+
+        let x = Key::control('x');
+        let s = Key::control('s');
+        assert_eq!(keymap.lookup(&[x]), Lookup::Prefix);
+        assert_eq!(keymap.lookup(&[x, s]), Lookup::Command("save-buffer", ""));
+        assert_eq!(keymap.lookup(&[Key::control('f')]), Lookup::Command("forward-char", ""));
+        assert_eq!(keymap.lookup(&[x, Key::char('q')]), Lookup::Unbound);
+        assert_eq!(keymap.lookup(&[Key::control('f'), s]), Lookup::Unbound);
+    }
+
+    #[test]
+    fn rebind_keys() {
+        let mut keymap = Keymap::new();
+        keymap.bind_str("C-c", "first").unwrap();
+
+        keymap.bind_str("C-c a", "second").unwrap();
+        assert_eq!(keymap.lookup(&[Key::control('c')]), Lookup::Prefix);
+
+        keymap.bind_str("C-c", "third").unwrap();
+        assert_eq!(keymap.lookup(&[Key::control('c')]), Lookup::Command("third", ""));
+        assert_eq!(keymap.bindings(), vec![(vec![Key::control('c')], "third")]);
+
+        assert!(keymap.unbind(&[Key::control('c')]).is_some());
+        assert!(keymap.is_empty());
+    }
+
+    #[test]
+    fn the_emacs_keymap() {
+        let keymap = Keymap::emacs();
+
+        assert_eq!(keymap.lookup(&[Key::control('f')]), Lookup::Command("forward-char", ""));
+        assert_eq!(keymap.lookup(&[Key::control('x')]), Lookup::Prefix);
+        assert_eq!(keymap.lookup(&[Key::plain(KeyCode::Tab)]), Lookup::Command("insert-char", "\t"));
+        assert_eq!(keymap.lookup(&Key::parse_sequence("C-SPC").unwrap()), Lookup::Command("set-mark", ""));
+    }
+
+    #[test]
+    fn bind_with_an_argument() {
+        let mut keymap = Keymap::new();
+        keymap.bind_with_argument(&[Key::meta('g')], "goto-line", "1");
+
+        assert_eq!(keymap.lookup(&[Key::meta('g')]), Lookup::Command("goto-line", "1"));
+    }
+}
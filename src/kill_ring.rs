@@ -0,0 +1,416 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+
+use clipboard::Clipboard;
+use GapBuffer;
+
+/// The number of kills a `KillRing` keeps by default.
+pub const DEFAULT_KILL_RING_CAPACITY: usize = 60;
+
+/// KillRing keeps recently killed text so it can be yanked back into a buffer.
+/// - `entries`: The killed text, most recent first.
+/// - `capacity`: How many entries are kept before the oldest is dropped.
+/// - `last_kill`: Where the point was left by the last kill, so a kill that follows on from it
+///   is appended to the same entry.
+/// - `last_yank`: The text inserted by the last yank and which entry it was, for `yank_pop()`.
+/// - `clipboard`: Where kills are shared with other applications, if anywhere.
+pub struct KillRing {
+    entries: VecDeque<String>,
+    capacity: usize,
+    last_kill: Option<usize>,
+    last_yank: Option<(Range<usize>, usize)>,
+    clipboard: Option<Box<dyn Clipboard>>,
+}
+
+impl fmt::Debug for KillRing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("KillRing")
+            .field("entries", &self.entries)
+            .field("capacity", &self.capacity)
+            .field("clipboard", &self.clipboard.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for KillRing {
+    fn default() -> KillRing {
+        KillRing::new()
+    }
+}
+
+impl KillRing {
+    pub fn new() -> KillRing {
+        KillRing::with_capacity(DEFAULT_KILL_RING_CAPACITY)
+    }
+
+    /// with_capacity() will panic if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> KillRing {
+        if capacity == 0 {
+            panic!("A kill ring must be able to hold at least one entry.")
+        }
+
+        KillRing {
+            entries: VecDeque::new(),
+            capacity,
+            last_kill: None,
+            last_yank: None,
+            clipboard: None,
+        }
+    }
+
+    /// set_clipboard() shares kills with `clipboard`: every kill is copied to it, and a yank takes
+    /// whatever was copied there by another application first.
+    pub fn set_clipboard(&mut self, clipboard: Box<dyn Clipboard>) {
+        self.clipboard = Some(clipboard);
+    }
+
+    pub fn take_clipboard(&mut self) -> Option<Box<dyn Clipboard>> {
+        self.clipboard.take()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// entries() iterates over the killed text, most recent first.
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.as_str())
+    }
+
+    /// push() adds `text` as the most recent entry, dropping the oldest if the ring is full.
+    pub fn push(&mut self, text: String) {
+        if let Some(clipboard) = self.clipboard.as_mut() {
+            clipboard.set_text(&text);
+        }
+        self.push_entry(text);
+    }
+
+    /// copy_region() adds the region of `buffer` to the ring without removing it.
+    /// Returns false if the mark is not set.
+    pub fn copy_region(&mut self, buffer: &GapBuffer) -> bool {
+        match buffer.copy_region() {
+            Some(copied) => {
+                self.push(copied);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// kill_region() removes the region from `buffer` and adds it to the ring.
+    /// Returns false if the mark is not set.
+    pub fn kill_region(&mut self, buffer: &mut GapBuffer) -> bool {
+        match buffer.delete_region() {
+            Some(killed) => {
+                self.push(killed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// kill() removes `range` from `buffer` and adds it to the ring. A kill that follows on from
+    /// the last one, starting or ending where it left the point, is added to the end or the start
+    /// of the same entry, so a run of kills in either direction is yanked back as one. Returns
+    /// false, killing nothing, if `range` is empty.
+    pub fn kill(&mut self, buffer: &mut GapBuffer, range: Range<usize>) -> bool {
+        if range.is_empty() {
+            return false;
+        }
+        let killed = buffer.remove_bytes(range.clone());
+
+        match (self.last_kill, self.entries.front_mut()) {
+            (Some(last_kill), Some(entry)) if last_kill == range.start || last_kill == range.end => {
+                match last_kill == range.start {
+                    true => entry.push_str(&killed),
+                    false => entry.insert_str(0, &killed),
+                }
+                if let Some(clipboard) = self.clipboard.as_mut() {
+                    clipboard.set_text(entry);
+                }
+            }
+            _ => self.push(killed),
+        }
+        self.last_kill = Some(range.start);
+
+        true
+    }
+
+    /// kill_line() removes the text from the point to the end of the line, or the newline itself
+    /// if the point is already there. Returns false if the point is at the end of the buffer.
+    /// Repeated kills from the same place are collected into one entry.
+    pub fn kill_line(&mut self, buffer: &mut GapBuffer) -> bool {
+        let point = buffer.get_point();
+        let (line, _) = buffer.offset_to_line_col(point);
+        let line_end = buffer.line_end_offset(line).unwrap();
+
+        let end = match point == line_end {
+            true if point == buffer.len() => return false,
+            true => point + 1,
+            false => line_end,
+        };
+        self.kill(buffer, point..end)
+    }
+
+    /// kill_whole_line() removes the line of the point with its line break, or with the one
+    /// before it on the last line. Returns false if the buffer is empty.
+    pub fn kill_whole_line(&mut self, buffer: &mut GapBuffer) -> bool {
+        let (line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let start = buffer.line_to_offset(line).unwrap();
+
+        let range = match buffer.line_to_offset(line + 1) {
+            Some(next) => start..next,
+            None if line > 0 => start - 1..buffer.len(),
+            None => start..buffer.len(),
+        };
+        self.kill(buffer, range)
+    }
+
+    /// kill_word() removes the text from the point to the end of the next word, or back to the
+    /// start of the previous word when `forward` is false. Returns false if there is no text to
+    /// remove.
+    pub fn kill_word(&mut self, buffer: &mut GapBuffer, forward: bool) -> bool {
+        let point = buffer.get_point();
+        match forward {
+            true => buffer.move_point_forward_word(),
+            false => buffer.move_point_backward_word(),
+        }
+        let other = buffer.get_point();
+        buffer.set_point(point);
+
+        self.kill(buffer, point.min(other)..point.max(other))
+    }
+
+    /// kill_sentence() removes the text from the point to the end of the sentence. Returns false
+    /// if the point is at the end of the buffer.
+    pub fn kill_sentence(&mut self, buffer: &mut GapBuffer) -> bool {
+        let point = buffer.get_point();
+        buffer.move_point_forward_sentence();
+        let end = buffer.get_point();
+        buffer.set_point(point);
+
+        self.kill(buffer, point..end)
+    }
+
+    /// yank() inserts the most recent entry at the point, leaving the point after it.
+    /// Returns false if the ring is empty.
+    pub fn yank(&mut self, buffer: &mut GapBuffer) -> bool {
+        let copied = self.clipboard.as_mut().and_then(|clipboard| clipboard.get_text());
+        if let Some(copied) = copied {
+            if self.entries.front() != Some(&copied) {
+                self.push_entry(copied);
+            }
+        }
+
+        self.yank_entry(buffer, 0)
+    }
+
+    /// yank_pop() replaces the text inserted by the last yank with the next older entry, cycling
+    /// back to the most recent after the oldest. Returns false if the point is not where the last
+    /// yank left it.
+    pub fn yank_pop(&mut self, buffer: &mut GapBuffer) -> bool {
+        match self.last_yank.clone() {
+            Some((yanked, index)) if buffer.get_point() == yanked.end && yanked.end <= buffer.len() => {
+                buffer.remove_bytes(yanked);
+                self.yank_entry(buffer, (index + 1) % self.entries.len())
+            }
+            _ => false,
+        }
+    }
+
+    /// end_kill() stops the next kill from being added to the last one's entry, as when some
+    /// other command has run in between.
+    pub fn end_kill(&mut self) {
+        self.last_kill = None;
+    }
+
+    fn push_entry(&mut self, text: String) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_back();
+        }
+        self.entries.push_front(text);
+        self.last_kill = None;
+        self.last_yank = None;
+    }
+
+    fn yank_entry(&mut self, buffer: &mut GapBuffer, index: usize) -> bool {
+        let entry = match self.entries.get(index) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        let start = buffer.get_point();
+        buffer.insert_str(entry);
+        self.last_kill = None;
+        self.last_yank = Some((start..buffer.get_point(), index));
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use clipboard::Clipboard;
+    use kill_ring::KillRing;
+    use GapBuffer;
+
+    /// SharedClipboard is a clipboard whose contents the test can see and change.
+    struct SharedClipboard(Arc<Mutex<Option<String>>>);
+
+    impl Clipboard for SharedClipboard {
+        fn get_text(&mut self) -> Option<String> {
+            self.0.lock().unwrap().clone()
+        }
+
+        fn set_text(&mut self, text: &str) -> bool {
+            *self.0.lock().unwrap() = Some(text.to_string());
+            true
+        }
+    }
+
+    #[test]
+    fn kill_and_yank_the_region() {
+        let mut buffer = GapBuffer::from("The quick brown fox".to_string());
+        let mut ring = KillRing::new();
+        buffer.set_point(4);
+        buffer.set_mark(10);
+
+        assert!(ring.kill_region(&mut buffer));
+        assert_eq!(buffer.to_string(), "The brown fox");
+
+        buffer.set_point(buffer.len());
+        assert!(ring.yank(&mut buffer));
+        assert_eq!(buffer.to_string(), "The brown foxquick ");
+        assert_eq!(buffer.get_point(), buffer.len());
+    }
+
+    #[test]
+    fn kill_region_without_a_mark() {
+        let mut buffer = GapBuffer::from("text".to_string());
+        let mut ring = KillRing::new();
+
+        assert!(!ring.kill_region(&mut buffer));
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn repeated_kill_lines_make_one_entry() {
+        let mut buffer = GapBuffer::from("one\ntwo\nthree".to_string());
+        let mut ring = KillRing::new();
+        buffer.set_point(0);
+
+        assert!(ring.kill_line(&mut buffer));
+        assert!(ring.kill_line(&mut buffer));
+        assert!(ring.kill_line(&mut buffer));
+        assert_eq!(buffer.to_string(), "\nthree");
+        assert_eq!(ring.entries().collect::<Vec<_>>(), vec!["one\ntwo"]);
+
+        buffer.set_point(buffer.len());
+        assert!(!ring.kill_line(&mut buffer));
+    }
+
+    #[test]
+    fn kill_words_over_invalid_utf8() {
+        let mut buffer = GapBuffer::new();
+        let mut ring = KillRing::new();
+        buffer.insert_bytes(b"\xffab \xffc".to_vec());
+        buffer.set_point(0);
+
+        assert!(ring.kill_word(&mut buffer, true));
+        assert_eq!(buffer.to_string(), " \u{fffd}c");
+        assert!(ring.kill_word(&mut buffer, true));
+        assert!(buffer.is_empty());
+        assert_eq!(ring.entries().collect::<Vec<_>>(), vec!["\u{fffd}ab \u{fffd}c"]);
+    }
+
+    #[test]
+    fn kills_in_either_direction_make_one_entry() {
+        let mut buffer = GapBuffer::from("let one = two + three. four\nfive\n".to_string());
+        let mut ring = KillRing::new();
+        buffer.set_point(4);
+
+        assert!(ring.kill_word(&mut buffer, true));
+        assert!(ring.kill_word(&mut buffer, true));
+        assert!(ring.kill_word(&mut buffer, false));
+        assert_eq!(buffer.to_string(), " + three. four\nfive\n");
+        assert_eq!(ring.entries().collect::<Vec<&str>>(), vec!["let one = two"]);
+
+        assert!(ring.kill_sentence(&mut buffer));
+        assert_eq!(buffer.to_string(), " four\nfive\n");
+        assert!(ring.kill_whole_line(&mut buffer));
+        assert!(ring.kill_whole_line(&mut buffer));
+        assert_eq!(buffer.to_string(), "");
+        assert_eq!(ring.entries().collect::<Vec<&str>>(), vec!["let one = two + three. four\nfive\n"]);
+        assert!(!ring.kill_whole_line(&mut buffer));
+        assert!(!ring.kill_word(&mut buffer, false));
+    }
+
+    #[test]
+    fn yank_pop_cycles_through_the_ring() {
+        let mut buffer = GapBuffer::from("".to_string());
+        let mut ring = KillRing::new();
+        ring.push("first".to_string());
+        ring.push("second".to_string());
+
+        assert!(!ring.yank_pop(&mut buffer));
+        assert!(ring.yank(&mut buffer));
+        assert_eq!(buffer.to_string(), "second");
+
+        assert!(ring.yank_pop(&mut buffer));
+        assert_eq!(buffer.to_string(), "first");
+        assert!(ring.yank_pop(&mut buffer));
+        assert_eq!(buffer.to_string(), "second");
+
+        buffer.set_point(0);
+        assert!(!ring.yank_pop(&mut buffer));
+    }
+
+    #[test]
+    fn the_oldest_entry_is_dropped_when_full() {
+        let mut ring = KillRing::with_capacity(2);
+        ring.push("a".to_string());
+        ring.push("b".to_string());
+        ring.push("c".to_string());
+
+        assert_eq!(ring.entries().collect::<Vec<_>>(), vec!["c", "b"]);
+    }
+
+    #[test]
+    fn kills_are_copied_to_the_clipboard() {
+        let contents = Arc::new(Mutex::new(None));
+        let mut ring = KillRing::new();
+        ring.set_clipboard(Box::new(SharedClipboard(contents.clone())));
+        let mut buffer = GapBuffer::from("one\ntwo".to_string());
+        buffer.set_point(0);
+        buffer.set_mark(3);
+
+        assert!(ring.copy_region(&buffer));
+        assert_eq!(contents.lock().unwrap().as_deref(), Some("one"));
+
+        ring.kill_line(&mut buffer);
+        ring.kill_line(&mut buffer);
+        assert_eq!(contents.lock().unwrap().as_deref(), Some("one\n"));
+    }
+
+    #[test]
+    fn yank_takes_text_copied_elsewhere() {
+        let contents = Arc::new(Mutex::new(None));
+        let mut ring = KillRing::new();
+        ring.set_clipboard(Box::new(SharedClipboard(contents.clone())));
+        ring.push("ours".to_string());
+        *contents.lock().unwrap() = Some("theirs".to_string());
+        let mut buffer = GapBuffer::new();
+
+        assert!(ring.yank(&mut buffer));
+        assert_eq!(buffer.to_string(), "theirs");
+        assert!(ring.yank_pop(&mut buffer));
+        assert_eq!(buffer.to_string(), "ours");
+    }
+}
@@ -1,166 +1,828 @@
+#[cfg(feature = "clipboard")]
+extern crate arboard;
+#[cfg(feature = "tui")]
+extern crate crossterm;
+#[cfg(unix)]
+extern crate libc;
+#[cfg(feature = "regex")]
+extern crate regex;
+extern crate serde_json;
+extern crate unicode_segmentation;
+extern crate toml;
+#[cfg(feature = "tree-sitter")]
+extern crate tree_sitter;
+#[cfg(feature = "tree-sitter")]
+extern crate tree_sitter_rust;
+extern crate unicode_width;
+
+use std::borrow::Cow;
 use std::fmt;
 use std::ops::Range;
 
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+pub mod anchor;
+pub mod ansi;
+pub mod bookmark;
+pub mod brackets;
+pub mod buffer;
+pub mod buffer_manager;
+pub mod clipboard;
+pub mod command;
+pub mod comment;
+pub mod compile;
+pub mod completion;
+pub mod cursor;
+pub mod dabbrev;
+pub mod delta;
+pub mod diagnostic;
+pub mod diff;
+pub mod dired;
+pub mod editor;
+pub mod events;
+pub mod fuzzy;
+pub mod git;
+pub mod grep;
+pub mod indent;
+pub mod iter;
+pub mod job;
+pub mod jump_list;
+pub mod keymap;
+pub mod kill_ring;
+pub mod line_index;
+pub mod load;
+pub mod lsp;
+pub mod macros;
+pub mod minibuffer;
+pub mod motion;
+pub mod narrow;
+pub mod project;
+pub mod property;
+pub mod pty;
+pub mod rect;
+#[cfg(feature = "regex")]
+pub mod regex_search;
+pub mod region;
+pub mod registers;
+pub mod search;
+pub mod shell;
+pub mod sign;
+pub mod snapshot;
+pub mod status_line;
+pub mod syntax;
+pub mod term;
+#[cfg(feature = "tui")]
+pub mod terminal;
+pub mod theme;
+pub mod undo;
+pub mod vi;
+pub mod view;
+pub mod window;
+
+use anchor::{AnchorId, Anchors};
+use cursor::Cursor;
+use diagnostic::Diagnostics;
+use events::{EditEvent, Listeners};
+use iter::{Bytes, Chars, Chunks, Lines};
+use line_index::LineIndex;
+use narrow::Narrowing;
+use property::Properties;
+use sign::Signs;
+use undo::Edit;
+
 const DEFAULT_BUFFER_CAPACITY: usize = 10;
 const INITIAL_GAP_SIZE: usize = 10;
 
+/// GrowthPolicy decides how many bytes to add to the gap once it is exhausted.
+/// - `Fixed`: Grow the gap by a set number of bytes.
+/// - `Proportional`: Grow the gap by a percentage of the current buffer length.
+///
+/// The gap always grows by at least `INITIAL_GAP_SIZE` bytes, or by enough to fit the insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrowthPolicy {
+    Fixed(usize),
+    Proportional(usize),
+}
+
+impl GrowthPolicy {
+    fn gap_size(&self, buffer_length: usize, required: usize) -> usize {
+        let size = match *self {
+            GrowthPolicy::Fixed(size) => size,
+            GrowthPolicy::Proportional(percent) => buffer_length * percent / 100,
+        };
+
+        size.max(required).max(INITIAL_GAP_SIZE)
+    }
+}
+
+impl Default for GrowthPolicy {
+    fn default() -> GrowthPolicy {
+        GrowthPolicy::Proportional(50)
+    }
+}
+
 /// GapBuffer is a datastructure designed for efficient local insertion and deletion operations.
 /// - `point`: The current index where operations are taking place.
-struct GapBuffer {
+/// - `mark`: An optional second position, which with the point bounds the region.
+/// - `cursors`: The secondary cursors, edited alongside the point.
+/// - `read_only`: Whether changes to the contents are refused. Every method that would change
+///   them does nothing instead.
+/// - `narrowing`: The text hidden while the buffer is narrowed, if it is. Every index is into the
+///   accessible text.
+/// - `version`: How many times the contents have changed, so snapshots can tell whether they still
+///   match.
+/// - `journal`: The edits made since an undo history last took them, while one is recording them.
+pub struct GapBuffer {
     buffer: Vec<u8>,
     point: usize,
+    mark: Option<AnchorId>,
+    cursors: Vec<Cursor>,
     gap_start: usize,
     gap_end: usize,
+    line_index: LineIndex,
+    growth_policy: GrowthPolicy,
+    listeners: Listeners,
+    anchors: Anchors,
+    properties: Properties,
+    diagnostics: Diagnostics,
+    signs: Signs,
+    read_only: bool,
+    narrowing: Option<Narrowing>,
+    version: u64,
+    journal: Option<Vec<Edit>>,
 }
 
 impl GapBuffer {
-    fn new() -> GapBuffer {
+    pub fn new() -> GapBuffer {
+        GapBuffer::with_capacity(DEFAULT_BUFFER_CAPACITY)
+    }
+
+    /// with_capacity() creates an empty buffer whose gap can hold `capacity` bytes before growing.
+    pub fn with_capacity(capacity: usize) -> GapBuffer {
         GapBuffer {
-            buffer: vec![0; INITIAL_GAP_SIZE],
+            buffer: vec![0; capacity],
             point: 0,
+            mark: None,
+            cursors: Vec::new(),
             gap_start: 0,
-            gap_end: INITIAL_GAP_SIZE,
+            gap_end: capacity,
+            line_index: LineIndex::new(),
+            growth_policy: GrowthPolicy::default(),
+            listeners: Listeners::default(),
+            anchors: Anchors::default(),
+            properties: Properties::default(),
+            diagnostics: Diagnostics::default(),
+            signs: Signs::default(),
+            read_only: false,
+            narrowing: None,
+            version: 0,
+            journal: None,
         }
     }
 
-    fn from(content: String) -> GapBuffer {
-        let gap_bytes: [u8; INITIAL_GAP_SIZE] = [0; INITIAL_GAP_SIZE];
-        let buffer_length = content.len() + gap_bytes.len();
-        let mut buffer: Vec<u8> = Vec::with_capacity(buffer_length);
+    pub fn growth_policy(&self) -> GrowthPolicy {
+        self.growth_policy
+    }
 
-        let gap_start = content.len();
+    pub fn set_growth_policy(&mut self, policy: GrowthPolicy) {
+        self.growth_policy = policy;
+    }
 
-        for byte in content.as_bytes() {
-            buffer.push(*byte);
+    /// reserve() makes sure at least `additional` bytes can be inserted without growing the gap.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.gap_len() < additional {
+            self.grow_gap(additional);
         }
+    }
 
-        buffer.extend(gap_bytes.iter());
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
 
-        GapBuffer {
-            point: gap_start,
-            gap_start,
-            gap_end: buffer_length,
-            buffer,
-        }
+    /// set_read_only() turns refusing changes to the contents on or off. The point, mark and
+    /// cursors can still move in a read-only buffer.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
     }
 
-    fn capacity(&self) -> usize {
+    pub fn capacity(&self) -> usize {
         self.buffer.capacity()
     }
 
-    fn len(&self) -> usize {
-        let gap_length = self.gap_end - self.gap_start;
-        self.buffer.len() - gap_length
+    pub fn len(&self) -> usize {
+        self.buffer.len() - self.gap_len() - self.hidden_before() - self.hidden_after()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// len_chars() counts the UTF-8 encoded characters in the buffer.
+    pub fn len_chars(&self) -> usize {
+        let (front, back) = self.as_slices();
+
+        front.iter().chain(back.iter()).filter(|byte| !is_continuation_byte(**byte)).count()
     }
 
-    /// set_point() will panic if `index` is greater than the buffer length - 1.
-    fn set_point(&mut self, index: usize) {
-        if index > self.len() - 1 {
+    /// set_point() will panic if `index` is greater than the buffer length, or if `index` is
+    /// not on a UTF-8 character boundary.
+    pub fn set_point(&mut self, index: usize) {
+        if index > self.len() {
             panic!("Index out of bounds. The length is {} but the index is {}.", self.len(), index)
         }
 
+        if !self.is_char_boundary(index) {
+            panic!("Index {} is not on a UTF-8 character boundary.", index)
+        }
+
         self.point = index;
     }
 
-    fn get_point(&self) -> usize {
+    pub fn get_point(&self) -> usize {
         self.point
     }
 
-    fn convert_user_index_to_gap_index(&self, index: usize) -> usize {
-        if index < self.gap_start {
-            index
+    /// set_point_char() moves the point to the start of the character at `char_index`.
+    /// It will panic if `char_index` is greater than the number of characters in the buffer.
+    pub fn set_point_char(&mut self, char_index: usize) {
+        let length = self.len_chars();
+        if char_index > length {
+            panic!("Character index out of bounds. The length is {} but the index is {}.", length, char_index)
+        }
+
+        let index = self.char_to_byte(char_index);
+        self.set_point(index);
+    }
+
+    /// is_char_boundary() returns true if `index` is the first byte of a UTF-8 encoded
+    /// character, or the start or end of the buffer.
+    pub fn is_char_boundary(&self, index: usize) -> bool {
+        if index == 0 || index == self.len() {
+            return true;
+        }
+
+        match self.byte(index) {
+            Some(byte) => !is_continuation_byte(byte),
+            None => false,
+        }
+    }
+
+    /// char_to_byte() converts a character index into a byte index.
+    /// Indexes past the last character are clamped to the buffer length.
+    pub fn char_to_byte(&self, char_index: usize) -> usize {
+        let mut chars = 0;
+
+        for index in 0..self.len() {
+            if self.is_char_boundary(index) {
+                if chars == char_index {
+                    return index;
+                }
+                chars += 1;
+            }
+        }
+
+        self.len()
+    }
+
+    /// byte_to_char() converts a byte index into the index of the character containing it.
+    pub fn byte_to_char(&self, index: usize) -> usize {
+        (0..index.min(self.len()))
+            .filter(|index| self.is_char_boundary(*index))
+            .count()
+    }
+
+    /// next_grapheme_boundary() returns the index of the first grapheme cluster boundary after
+    /// `index`, or `None` if `index` is at the end of the buffer. Text that is not valid UTF-8 is
+    /// stepped over a character at a time instead.
+    pub fn next_grapheme_boundary(&self, index: usize) -> Option<usize> {
+        let (front, back) = match self.text_halves() {
+            Some(halves) => halves,
+            None => return self.next_char_boundary(index),
+        };
+        let mut cursor = GraphemeCursor::new(index, self.len(), true);
+        let mut in_front = index < front.len();
+
+        loop {
+            let (chunk, chunk_start) = if in_front { (front, 0) } else { (back, front.len()) };
+
+            match cursor.next_boundary(chunk, chunk_start) {
+                Ok(boundary) => return boundary,
+                Err(GraphemeIncomplete::NextChunk) => in_front = false,
+                Err(GraphemeIncomplete::PreContext(end)) => cursor.provide_context(&front[..end], 0),
+                Err(error) => panic!("Unexpected grapheme segmentation state: {:?}", error),
+            }
+        }
+    }
+
+    /// prev_grapheme_boundary() returns the index of the first grapheme cluster boundary before
+    /// `index`, or `None` if `index` is at the start of the buffer. Like next_grapheme_boundary(),
+    /// it steps a character at a time over text that is not valid UTF-8.
+    pub fn prev_grapheme_boundary(&self, index: usize) -> Option<usize> {
+        let (front, back) = match self.text_halves() {
+            Some(halves) => halves,
+            None => return self.prev_char_boundary(index),
+        };
+        let mut cursor = GraphemeCursor::new(index, self.len(), true);
+        let mut in_front = index <= front.len();
+
+        loop {
+            let (chunk, chunk_start) = if in_front { (front, 0) } else { (back, front.len()) };
+
+            match cursor.prev_boundary(chunk, chunk_start) {
+                Ok(boundary) => return boundary,
+                Err(GraphemeIncomplete::PrevChunk) => in_front = true,
+                Err(GraphemeIncomplete::PreContext(end)) => cursor.provide_context(&front[..end], 0),
+                Err(error) => panic!("Unexpected grapheme segmentation state: {:?}", error),
+            }
+        }
+    }
+
+    /// as_slices() returns the contents before and after the gap without copying.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        let end = self.buffer.len() - self.hidden_after();
+        (&self.buffer[self.hidden_before()..self.gap_start], &self.buffer[self.gap_end..end])
+    }
+
+    /// make_contiguous() moves the gap to the end of the buffer so the whole contents can be
+    /// borrowed as a single slice, and returns that slice.
+    pub fn make_contiguous(&mut self) -> &[u8] {
+        let end = self.len();
+        self.move_gap(end);
+
+        &self.buffer[self.hidden_before()..self.gap_start]
+    }
+
+    /// contiguous_bytes() returns the bytes in `range`. They are only copied when `range`
+    /// straddles the gap.
+    fn contiguous_bytes(&self, range: Range<usize>) -> Cow<'_, [u8]> {
+        let (front, back) = self.as_slices();
+
+        if range.end <= front.len() {
+            Cow::Borrowed(&front[range])
+        } else if range.start >= front.len() {
+            Cow::Borrowed(&back[range.start - front.len()..range.end - front.len()])
         } else {
-            (self.gap_end - self.gap_start) + index
+            let mut bytes = front[range.start..].to_vec();
+            bytes.extend_from_slice(&back[..range.end - front.len()]);
+            Cow::Owned(bytes)
+        }
+    }
+
+    /// slice() returns the text in `range`. The text is only copied when `range` straddles the gap.
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    /// It will panic if `range` extends past the end of the buffer.
+    pub fn slice(&self, range: Range<usize>) -> Cow<'_, str> {
+        if range.start > range.end || range.end > self.len() {
+            panic!("Range out of bounds. The length is {} but the range is {:?}.", self.len(), range)
+        }
+
+        match self.contiguous_bytes(range) {
+            Cow::Borrowed(bytes) => String::from_utf8_lossy(bytes),
+            Cow::Owned(bytes) => Cow::Owned(String::from_utf8_lossy(&bytes).into_owned()),
+        }
+    }
+
+    /// bytes() iterates over the contents of the buffer one byte at a time.
+    pub fn bytes(&self) -> Bytes<'_> {
+        let (front, back) = self.as_slices();
+        Bytes::new(front, back)
+    }
+
+    /// bytes_at() iterates over the contents of the buffer starting at `index`.
+    pub fn bytes_at(&self, index: usize) -> Bytes<'_> {
+        let (front, back) = self.as_slices();
+        let index = index.min(self.len());
+
+        if index < front.len() {
+            Bytes::new(&front[index..], back)
+        } else {
+            Bytes::new(&[], &back[index - front.len()..])
+        }
+    }
+
+    /// chars() iterates over the UTF-8 characters in the buffer.
+    pub fn chars(&self) -> Chars<'_> {
+        Chars::new(self.bytes())
+    }
+
+    /// chars_at() iterates over the UTF-8 characters in the buffer starting at `index`.
+    pub fn chars_at(&self, index: usize) -> Chars<'_> {
+        Chars::new(self.bytes_at(index))
+    }
+
+    /// char_at() returns the character starting at `index`, or `None` at the end of the buffer.
+    pub fn char_at(&self, index: usize) -> Option<char> {
+        self.chars_at(index).next()
+    }
+
+    /// next_char_boundary() returns the start of the character after the one at `index`,
+    /// or `None` at the end of the buffer.
+    pub fn next_char_boundary(&self, index: usize) -> Option<usize> {
+        (index + 1..=self.len()).find(|index| self.is_char_boundary(*index))
+    }
+
+    /// prev_char_boundary() returns the start of the character before `index`,
+    /// or `None` at the start of the buffer.
+    pub fn prev_char_boundary(&self, index: usize) -> Option<usize> {
+        (0..index.min(self.len() + 1)).rev().find(|index| self.is_char_boundary(*index))
+    }
+
+    /// chunks() iterates over the text before and after the gap.
+    pub fn chunks(&self) -> Chunks<'_> {
+        let (front, back) = self.as_slices();
+        Chunks::new(front, back)
+    }
+
+    /// lines() iterates over the lines in the buffer. See `iter::Lines` for how lines are split.
+    pub fn lines(&self) -> Lines<'_> {
+        Lines::new(self)
+    }
+
+    /// line_count() returns the number of lines in the buffer. An empty buffer has one line.
+    pub fn line_count(&self) -> usize {
+        self.line_index.line_count()
+    }
+
+    /// line_to_offset() returns the index of the first byte of the zero-based `line`,
+    /// or `None` if the buffer has fewer lines.
+    pub fn line_to_offset(&self, line: usize) -> Option<usize> {
+        self.line_index.line_to_offset(line)
+    }
+
+    /// line_end_offset() returns the index of the newline ending the zero-based `line`, or the
+    /// buffer length for the last line. Returns `None` if the buffer has fewer lines.
+    pub fn line_end_offset(&self, line: usize) -> Option<usize> {
+        self.line_to_offset(line)?;
+
+        match self.line_to_offset(line + 1) {
+            Some(next_line_start) => Some(next_line_start - 1),
+            None => Some(self.len()),
+        }
+    }
+
+    /// offset_to_line_col() converts a byte index into a zero-based line and byte column.
+    /// It will panic if `offset` is greater than the buffer length.
+    pub fn offset_to_line_col(&self, offset: usize) -> (usize, usize) {
+        if offset > self.len() {
+            panic!("Index out of bounds. The length is {} but the index is {}.", self.len(), offset)
+        }
+
+        let line = self.line_index.offset_to_line(offset);
+        let line_start = self.line_index.line_to_offset(line).unwrap();
+
+        (line, offset - line_start)
+    }
+
+    /// line_col_to_offset() converts a zero-based line and byte column into a byte index.
+    /// Returns `None` if the line does not exist or the column is past the end of the line.
+    pub fn line_col_to_offset(&self, line: usize, col: usize) -> Option<usize> {
+        let start = self.line_to_offset(line)?;
+        let end = self.line_end_offset(line)?;
+
+        if start + col > end {
+            None
+        } else {
+            Some(start + col)
+        }
+    }
+
+    /// find_byte() returns the index of the first occurrence of `byte` at or after `from`.
+    fn find_byte(&self, byte: u8, from: usize) -> Option<usize> {
+        let (front, back) = self.as_slices();
+
+        if from < front.len() {
+            if let Some(index) = front[from..].iter().position(|candidate| *candidate == byte) {
+                return Some(from + index);
+            }
+        }
+
+        let back_start = from.max(front.len()) - front.len();
+        back.get(back_start..)?
+            .iter()
+            .position(|candidate| *candidate == byte)
+            .map(|index| front.len() + back_start + index)
+    }
+
+    /// text_halves() returns the text before and after the gap, or `None` if either half is not
+    /// valid UTF-8.
+    fn text_halves(&self) -> Option<(&str, &str)> {
+        let (front, back) = self.as_slices();
+        Some((std::str::from_utf8(front).ok()?, std::str::from_utf8(back).ok()?))
+    }
+
+    fn byte(&self, index: usize) -> Option<u8> {
+        if index < self.len() {
+            Some(self.buffer[self.convert_user_index_to_gap_index(index)])
+        } else {
+            None
         }
     }
 
-    fn convert_gap_index_to_user_index(&self, index: usize) -> usize {
+    fn convert_user_index_to_gap_index(&self, index: usize) -> usize {
+        let index = index + self.hidden_before();
         if index < self.gap_start {
             index
         } else {
-            index - (self.gap_end - self.gap_start)
+            (self.gap_end - self.gap_start) + index
         }
     }
 
-    fn prepare_gap(&mut self) {
-        if self.is_gap_start_before_point(){
-            let quantity_characters_to_move = self.convert_user_index_to_gap_index(self.point) - self.gap_end;
-            let bytes: Vec<u8> = self.buffer.drain(self.gap_end..self.gap_end + quantity_characters_to_move).collect();
+    /// move_gap() moves the gap so it starts at the user index `index`.
+    fn move_gap(&mut self, index: usize) {
+        let index = index + self.hidden_before();
+        if self.gap_start < index {
+            let quantity_characters_to_move = index - self.gap_start;
+            let source = self.gap_end..self.gap_end + quantity_characters_to_move;
 
-            for byte in bytes {
-                self.buffer.insert(self.gap_start, byte);
-                self.gap_start += 1;
-                self.gap_end += 1;
-            }
-        } else if self.is_gap_start_after_point() {
-            let quantity_characters_to_move = self.gap_start - self.convert_user_index_to_gap_index(self.point);
-            let bytes: Vec<u8> = self.buffer.drain(self.convert_user_index_to_gap_index(self.point)..self.gap_start).collect();
+            self.buffer.copy_within(source, self.gap_start);
+            self.gap_start += quantity_characters_to_move;
+            self.gap_end += quantity_characters_to_move;
+        } else if self.gap_start > index {
+            let quantity_characters_to_move = self.gap_start - index;
+            let source = index..self.gap_start;
 
+            self.buffer.copy_within(source, self.gap_end - quantity_characters_to_move);
             self.gap_start -= quantity_characters_to_move;
             self.gap_end -= quantity_characters_to_move;
+        }
+    }
+
+    fn gap_len(&self) -> usize {
+        self.gap_end - self.gap_start
+    }
 
-            let mut index = self.gap_end;
-            for byte in bytes {
-                self.buffer.insert(index, byte);
-                index += 1;
+    /// grow_gap() reopens the gap so it can hold at least `required` more bytes,
+    /// sized according to the growth policy.
+    fn grow_gap(&mut self, required: usize) {
+        let additional = self.growth_policy.gap_size(self.len(), required);
+        let old_length = self.buffer.len();
+
+        self.buffer.resize(old_length + additional, 0);
+        self.buffer.copy_within(self.gap_end..old_length, self.gap_end + additional);
+        self.gap_end += additional;
+    }
+
+    pub fn insert(&mut self, byte: u8) {
+        let point = self.point;
+        if self.insert_at(point, &[byte]) {
+            self.point += 1;
+        }
+    }
+
+    pub fn insert_bytes(&mut self, bytes: Vec<u8>) {
+        let point = self.point;
+        if self.insert_at(point, &bytes) {
+            self.point += bytes.len();
+        }
+    }
+
+    /// insert_at() inserts `bytes` at `index` without moving the point, returning false if the
+    /// buffer is read-only.
+    fn insert_at(&mut self, index: usize, bytes: &[u8]) -> bool {
+        self.splice(index..index, bytes).is_some()
+    }
+
+    /// splice() replaces the bytes in `range` with `bytes` without moving the point, and returns
+    /// the bytes that were removed. Every change to the contents of the buffer goes through here,
+    /// so this is the one place that refuses them while the buffer is read-only, returning `None`.
+    fn splice(&mut self, range: Range<usize>, bytes: &[u8]) -> Option<Vec<u8>> {
+        if self.read_only {
+            return None;
+        }
+        let count = range.end - range.start;
+        self.move_gap(range.start);
+
+        let removed = self.buffer[self.gap_end..self.gap_end + count].to_vec();
+        self.gap_end += count;
+        self.reserve(bytes.len());
+        self.buffer[self.gap_start..self.gap_start + bytes.len()].copy_from_slice(bytes);
+        self.gap_start += bytes.len();
+
+        self.line_index.remove(range.clone());
+        self.line_index.insert(range.start, bytes);
+        let before = self.hidden_before();
+        self.anchors.update(before + range.start..before + range.end, bytes.len());
+        if count > 0 || !bytes.is_empty() {
+            self.version += 1;
+            if let Some(journal) = self.journal.as_mut() {
+                journal.push(Edit {
+                    offset: before + range.start,
+                    removed: removed.clone(),
+                    inserted: bytes.to_vec(),
+                    point_before: before + self.point,
+                    point_after: before + self.point,
+                });
             }
         }
+
+        if !self.listeners.is_empty() {
+            let event = EditEvent {
+                removed: range,
+                removed_text: String::from_utf8_lossy(&removed).into_owned(),
+                inserted: String::from_utf8_lossy(bytes).into_owned(),
+                new_len: self.len(),
+            };
+            let changes = self
+                .listeners
+                .delta_encodings()
+                .into_iter()
+                .map(|encoding| (encoding, self.text_change(&event, encoding)))
+                .collect();
+            self.listeners.notify(&event, changes);
+        }
+
+        Some(removed)
+    }
+
+    /// insert_char() inserts the UTF-8 encoding of `character` at the point.
+    /// It will panic if the point is not on a UTF-8 character boundary.
+    pub fn insert_char(&mut self, character: char) {
+        let mut encoded = [0; 4];
+        self.insert_str(character.encode_utf8(&mut encoded));
+    }
+
+    /// insert_str() inserts `text` at the point and moves the point to the end of it.
+    /// It will panic if the point is not on a UTF-8 character boundary.
+    pub fn insert_str(&mut self, text: &str) {
+        if !self.is_char_boundary(self.point) {
+            panic!("Index {} is not on a UTF-8 character boundary.", self.point)
+        }
+
+        let point = self.point;
+        if self.insert_at(point, text.as_bytes()) {
+            self.point += text.len();
+        }
+    }
+
+    /// push_str() appends `text` to the end of the buffer without moving the point.
+    pub fn push_str(&mut self, text: &str) {
+        let end = self.len();
+        self.insert_at(end, text.as_bytes());
+    }
+
+    /// remove() removes the byte before the point. It does nothing at the start of the buffer.
+    pub fn remove(&mut self) {
+        if self.point == 0 {
+            return;
+        }
+        let point = self.point;
+        self.remove_bytes(point - 1..point);
     }
 
-    fn insert(&mut self, byte: u8) {
-        self.prepare_gap();
-        self.gap_start += 1;
-        self.buffer[self.point] = byte;
-        self.set_point(self.point + 1);
+    /// delete_forward() removes the byte at the point and returns it, or `None` at the end of the buffer.
+    pub fn delete_forward(&mut self) -> Option<u8> {
+        let byte = self.byte(self.point)?;
+        let point = self.point;
+        self.remove_at(point..point + 1)?;
+
+        Some(byte)
     }
 
-    fn insert_bytes(&mut self, bytes: Vec<u8>) {
-        self.prepare_gap();
+    /// delete_char_forward() removes the character at the point and returns it,
+    /// or `None` at the end of the buffer.
+    pub fn delete_char_forward(&mut self) -> Option<char> {
+        let start = self.point;
+        let end = self.next_char_boundary(start)?;
 
-        let mut index = self.point;
-        for byte in bytes {
-            self.buffer[index] = byte;
-            index += 1;
-            self.gap_start += 1;
-            self.set_point(self.point + 1);
+        self.remove_bytes(start..end).chars().next()
+    }
+
+    /// delete_char_backward() removes the character before the point and returns it,
+    /// or `None` at the start of the buffer.
+    pub fn delete_char_backward(&mut self) -> Option<char> {
+        let end = self.point;
+        let start = self.prev_char_boundary(end)?;
+
+        self.remove_bytes(start..end).chars().next()
+    }
+
+    /// replace_range() replaces the text in `range` with `text` and returns the text that was removed.
+    /// A point after `range` moves to stay on the same text, and a point inside `range` moves to
+    /// the end of the replacement.
+    /// It will panic if `range` extends past the end of the buffer.
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) -> String {
+        if range.start > range.end || range.end > self.len() {
+            panic!("Range out of bounds. The length is {} but the range is {:?}.", self.len(), range)
         }
+
+        let point = self.point;
+        let removed = match self.splice(range.clone(), text.as_bytes()) {
+            Some(removed) => String::from_utf8_lossy(&removed).into_owned(),
+            None => return String::new(),
+        };
+
+        self.point = if point >= range.end {
+            point - (range.end - range.start) + text.len()
+        } else if point > range.start {
+            range.start + text.len()
+        } else {
+            point
+        };
+
+        removed
     }
 
-    fn is_gap_start_before_point(&self) -> bool {
-        self.gap_start < self.convert_user_index_to_gap_index(self.point)
+    /// remove_bytes() removes the text in `range` and returns it. The point is moved to stay
+    /// on the same text, or to the start of `range` if it was inside it.
+    /// Invalid UTF-8 sequences in the returned text are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    /// It will panic if `range` extends past the end of the buffer.
+    pub fn remove_bytes(&mut self, range: Range<usize>) -> String {
+        if range.start > range.end || range.end > self.len() {
+            panic!("Range out of bounds. The length is {} but the range is {:?}.", self.len(), range)
+        }
+
+        let removed = match self.remove_at(range.clone()) {
+            Some(removed) => removed,
+            None => return String::new(),
+        };
+
+        if self.point >= range.end {
+            self.point -= removed.len();
+        } else if self.point > range.start {
+            self.point = range.start;
+        }
+
+        String::from_utf8_lossy(&removed).into_owned()
     }
 
-    fn is_gap_start_after_point(&self) -> bool {
-        self.gap_start > self.convert_user_index_to_gap_index(self.point)
+    /// remove_at() removes the bytes in `range` and returns them without moving the point, or
+    /// `None` if the buffer is read-only.
+    fn remove_at(&mut self, range: Range<usize>) -> Option<Vec<u8>> {
+        self.splice(range, &[])
     }
+}
 
-    fn remove(&mut self) {
-        self.prepare_gap();
-        self.gap_start -= 1;
-        self.set_point(self.point - 1)
+impl Default for GapBuffer {
+    fn default() -> GapBuffer {
+        GapBuffer::new()
     }
+}
+
+impl From<String> for GapBuffer {
+    fn from(content: String) -> GapBuffer {
+        let gap_bytes: [u8; INITIAL_GAP_SIZE] = [0; INITIAL_GAP_SIZE];
+        let buffer_length = content.len() + gap_bytes.len();
+        let mut buffer: Vec<u8> = Vec::with_capacity(buffer_length);
+
+        let gap_start = content.len();
+
+        for byte in content.as_bytes() {
+            buffer.push(*byte);
+        }
 
-    // TODO: move the gap for insert, insert_bytes, remove, and remove_bytes.
-    fn remove_bytes(&mut self, range: Range<usize>) -> Vec<u8> {
-        self.buffer.drain(range).collect()
+        buffer.extend(gap_bytes.iter());
+
+        GapBuffer {
+            point: gap_start,
+            mark: None,
+            cursors: Vec::new(),
+            gap_start,
+            gap_end: buffer_length,
+            line_index: LineIndex::from_bytes(content.bytes()),
+            growth_policy: GrowthPolicy::default(),
+            listeners: Listeners::default(),
+            anchors: Anchors::default(),
+            properties: Properties::default(),
+            diagnostics: Diagnostics::default(),
+            signs: Signs::default(),
+            read_only: false,
+            narrowing: None,
+            version: 0,
+            journal: None,
+            buffer,
+        }
     }
 }
 
 impl fmt::Display for GapBuffer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut buffer_contents = std::str::from_utf8(&self.buffer).unwrap().to_owned();
-        let gap_range = self.gap_start..self.gap_end;
+        let (front, back) = self.as_slices();
 
-        buffer_contents.replace_range(gap_range, "");
-
-        write!(f, "{}", buffer_contents)
+        write!(f, "{}{}", String::from_utf8_lossy(front), String::from_utf8_lossy(back))
     }
 }
 
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// buffer_with_gap_at() returns a buffer holding `text` with its gap at `index`, for tests that
+/// check code works wherever the gap is.
+#[cfg(test)]
+pub(crate) fn buffer_with_gap_at(text: &str, index: usize) -> GapBuffer {
+    let mut buffer = GapBuffer::from(text.to_string());
+    buffer.set_point(index);
+    buffer.insert_bytes(Vec::new());
+    buffer
+}
+
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
     use GapBuffer;
+    use GrowthPolicy;
     use DEFAULT_BUFFER_CAPACITY;
+    use INITIAL_GAP_SIZE;
 
     const TEST_STRING: &str = r"The quick brown
 fox jumped over
@@ -354,15 +1016,79 @@ the lazy dog.";
 
     #[test]
     fn remove_bytes_from_buffer() {
-        let expected_bytes = "quick ".as_bytes().to_vec();
         let mut buffer = GapBuffer::from(TEST_STRING.to_string());
         let mut expected_string = TEST_STRING.to_owned();
         expected_string.drain(4..10);
 
-        assert_bytes_eq(buffer.remove_bytes(4..10), expected_bytes);
+        assert_eq!(buffer.remove_bytes(4..10), "quick ");
         assert_eq!(buffer.to_string(), expected_string);
     }
 
+    struct RangeRemovalTestCase {
+        name: String,
+        range: std::ops::Range<usize>,
+        point: usize,
+        expected_point: usize,
+    }
+
+    #[test]
+    fn remove_bytes_around_the_gap() {
+        let test_cases = [
+            RangeRemovalTestCase {
+                name: "Remove before the gap".to_string(),
+                range: 4..10,
+                point: 20,
+                expected_point: 14,
+            },
+            RangeRemovalTestCase {
+                name: "Remove after the gap".to_string(),
+                range: 32..36,
+                point: 10,
+                expected_point: 10,
+            },
+            RangeRemovalTestCase {
+                name: "Remove across the gap".to_string(),
+                range: 10..26,
+                point: 16,
+                expected_point: 10,
+            },
+            RangeRemovalTestCase {
+                name: "Remove everything".to_string(),
+                range: 0..TEST_STRING.len(),
+                point: TEST_STRING.len(),
+                expected_point: 0,
+            },
+            RangeRemovalTestCase {
+                name: "Remove nothing".to_string(),
+                range: 5..5,
+                point: 30,
+                expected_point: 30,
+            },
+        ];
+
+        for test_case in test_cases.iter() {
+            let mut buffer = buffer_with_contents();
+            let mut expected_string = TEST_STRING.to_owned();
+            let expected_removed: String = expected_string.drain(test_case.range.clone()).collect();
+
+            buffer.set_point(test_case.point);
+            buffer.insert_bytes(Vec::new());
+
+            assert_eq!(buffer.remove_bytes(test_case.range.clone()), expected_removed, "Test case: \"{}\" failed.", test_case.name);
+            assert_eq!(buffer.to_string(), expected_string, "Test case: \"{}\" failed.", test_case.name);
+            assert_eq!(buffer.get_point(), test_case.expected_point, "Test case: \"{}\" failed.", test_case.name);
+            assert_eq!(buffer.line_count(), expected_string.split('\n').count(), "Test case: \"{}\" failed.", test_case.name);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Range out of bounds. The length is 45 but the range is 40..46.")]
+    fn remove_bytes_out_of_bounds_panics() {
+        let mut buffer = buffer_with_contents();
+
+        buffer.remove_bytes(40..46);
+    }
+
     #[test]
     fn set_the_point() {
         let mut buffer = GapBuffer::from(TEST_STRING.to_string());
@@ -385,6 +1111,483 @@ the lazy dog.";
         buffer.set_point(50);
     }
 
+    const MULTI_BYTE_STRING: &str = "naïve café 日本";
+
+    #[test]
+    fn count_characters() {
+        let buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+
+        assert_eq!(buffer.len(), MULTI_BYTE_STRING.len());
+        assert_eq!(buffer.len_chars(), MULTI_BYTE_STRING.chars().count());
+    }
+
+    #[test]
+    fn set_the_point_by_character() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+
+        buffer.set_point_char(3);
+        assert_eq!(buffer.get_point(), 4);
+
+        buffer.set_point_char(12);
+        assert_eq!(buffer.get_point(), 16);
+
+        buffer.set_point_char(MULTI_BYTE_STRING.chars().count());
+        assert_eq!(buffer.get_point(), MULTI_BYTE_STRING.len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 3 is not on a UTF-8 character boundary.")]
+    fn set_the_point_inside_a_character_panics() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+
+        buffer.set_point(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Character index out of bounds. The length is 13 but the index is 14.")]
+    fn set_the_point_by_character_out_of_bounds_panics() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+
+        buffer.set_point_char(14);
+    }
+
+    #[test]
+    fn convert_between_character_and_byte_indexes() {
+        let buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+
+        for (char_index, (byte_index, _)) in MULTI_BYTE_STRING.char_indices().enumerate() {
+            assert_eq!(buffer.char_to_byte(char_index), byte_index);
+            assert_eq!(buffer.byte_to_char(byte_index), char_index);
+        }
+    }
+
+    #[test]
+    fn insert_multi_byte_characters() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+        let mut expected_string = MULTI_BYTE_STRING.to_owned();
+        expected_string.insert(4, '€');
+        expected_string.insert(0, '😀');
+
+        buffer.set_point_char(3);
+        buffer.insert_char('€');
+        assert_eq!(buffer.get_point(), 4 + '€'.len_utf8());
+
+        buffer.set_point_char(0);
+        buffer.insert_char('😀');
+
+        assert_eq!(buffer.to_string(), expected_string);
+        assert_eq!(buffer.len_chars(), expected_string.chars().count());
+        assert_eq!(buffer.get_point(), '😀'.len_utf8());
+    }
+
+    #[test]
+    fn insert_characters_into_empty_buffer() {
+        let mut buffer = GapBuffer::new();
+
+        for character in MULTI_BYTE_STRING.chars() {
+            buffer.insert_char(character);
+        }
+
+        assert_eq!(buffer.to_string(), MULTI_BYTE_STRING);
+        assert_eq!(buffer.get_point(), MULTI_BYTE_STRING.len());
+    }
+
+    const GRAPHEME_STRING: &str = "e\u{301}👨‍👩‍👧x🇳🇿";
+
+    fn grapheme_boundaries() -> Vec<usize> {
+        let mut boundaries = vec![0, 3];
+        boundaries.push(boundaries[1] + "👨‍👩‍👧".len());
+        boundaries.push(boundaries[2] + 1);
+        boundaries.push(GRAPHEME_STRING.len());
+        boundaries
+    }
+
+    #[test]
+    fn step_forward_over_grapheme_clusters() {
+        let boundaries = grapheme_boundaries();
+
+        // Move the gap around so clusters straddling the gap are covered.
+        for gap_position in boundaries.iter().chain([1, 7].iter()) {
+            let mut buffer = GapBuffer::from(GRAPHEME_STRING.to_string());
+            buffer.set_point(*gap_position);
+            buffer.insert_bytes(Vec::new());
+
+            for pair in boundaries.windows(2) {
+                assert_eq!(buffer.next_grapheme_boundary(pair[0]), Some(pair[1]), "Gap at {}.", gap_position);
+            }
+            assert_eq!(buffer.next_grapheme_boundary(GRAPHEME_STRING.len()), None);
+        }
+    }
+
+    #[test]
+    fn step_backward_over_grapheme_clusters() {
+        let boundaries = grapheme_boundaries();
+
+        for gap_position in boundaries.iter().chain([1, 7].iter()) {
+            let mut buffer = GapBuffer::from(GRAPHEME_STRING.to_string());
+            buffer.set_point(*gap_position);
+            buffer.insert_bytes(Vec::new());
+
+            for pair in boundaries.windows(2) {
+                assert_eq!(buffer.prev_grapheme_boundary(pair[1]), Some(pair[0]), "Gap at {}.", gap_position);
+            }
+            assert_eq!(buffer.prev_grapheme_boundary(0), None);
+        }
+    }
+
+    #[test]
+    fn step_over_characters_in_invalid_utf8() {
+        let mut buffer = GapBuffer::from("ab\u{301}c".to_string());
+        buffer.set_point(1);
+        buffer.insert(0xff);
+
+        assert_eq!(buffer.next_grapheme_boundary(0), Some(1));
+        assert_eq!(buffer.next_grapheme_boundary(1), Some(2));
+        assert_eq!(buffer.next_grapheme_boundary(2), Some(3));
+        assert_eq!(buffer.prev_grapheme_boundary(6), Some(5));
+        assert_eq!(buffer.prev_grapheme_boundary(5), Some(3));
+        assert_eq!(buffer.next_grapheme_boundary(6), None);
+    }
+
+    #[test]
+    fn access_slices_either_side_of_the_gap() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(16);
+        buffer.insert_bytes(Vec::new());
+
+        let (front, back) = buffer.as_slices();
+
+        assert_bytes_eq(front.to_vec(), TEST_STRING.as_bytes()[..16].to_vec());
+        assert_bytes_eq(back.to_vec(), TEST_STRING.as_bytes()[16..].to_vec());
+    }
+
+    #[test]
+    fn slice_the_buffer() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(16);
+        buffer.insert_bytes(Vec::new());
+
+        let front = buffer.slice(4..9);
+        let back = buffer.slice(20..26);
+        let straddling = buffer.slice(10..19);
+
+        assert!(matches!(front, Cow::Borrowed(_)));
+        assert_eq!(front, "quick");
+        assert!(matches!(back, Cow::Borrowed(_)));
+        assert_eq!(back, "jumped");
+        assert!(matches!(straddling, Cow::Owned(_)));
+        assert_eq!(straddling, "brown\nfox");
+        assert_eq!(buffer.slice(0..TEST_STRING.len()), TEST_STRING);
+    }
+
+    #[test]
+    #[should_panic(expected = "Range out of bounds. The length is 45 but the range is 40..50.")]
+    fn slice_out_of_bounds_panics() {
+        let buffer = buffer_with_contents();
+
+        buffer.slice(40..50);
+    }
+
+    #[test]
+    fn convert_offsets_to_lines_and_columns() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(20);
+        buffer.insert_bytes(Vec::new());
+
+        assert_eq!(buffer.offset_to_line_col(0), (0, 0));
+        assert_eq!(buffer.offset_to_line_col(15), (0, 15));
+        assert_eq!(buffer.offset_to_line_col(16), (1, 0));
+        assert_eq!(buffer.offset_to_line_col(24), (1, 8));
+        assert_eq!(buffer.offset_to_line_col(TEST_STRING.len()), (2, 13));
+    }
+
+    #[test]
+    fn convert_lines_and_columns_to_offsets() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(20);
+        buffer.insert_bytes(Vec::new());
+
+        assert_eq!(buffer.line_col_to_offset(0, 0), Some(0));
+        assert_eq!(buffer.line_col_to_offset(0, 15), Some(15));
+        assert_eq!(buffer.line_col_to_offset(0, 16), None);
+        assert_eq!(buffer.line_col_to_offset(1, 8), Some(24));
+        assert_eq!(buffer.line_col_to_offset(2, 13), Some(TEST_STRING.len()));
+        assert_eq!(buffer.line_col_to_offset(3, 0), None);
+    }
+
+    #[test]
+    fn find_line_boundaries() {
+        let buffer = buffer_with_contents();
+
+        assert_eq!(buffer.line_to_offset(1), Some(16));
+        assert_eq!(buffer.line_end_offset(1), Some(31));
+        assert_eq!(buffer.line_to_offset(2), Some(32));
+        assert_eq!(buffer.line_end_offset(2), Some(TEST_STRING.len()));
+        assert_eq!(buffer.line_to_offset(3), None);
+        assert_eq!(GapBuffer::new().line_end_offset(0), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds. The length is 45 but the index is 46.")]
+    fn convert_offset_out_of_bounds_panics() {
+        let buffer = buffer_with_contents();
+
+        buffer.offset_to_line_col(46);
+    }
+
+    #[test]
+    fn line_index_follows_edits() {
+        let mut buffer = buffer_with_contents();
+
+        buffer.set_point(20);
+        buffer.insert_bytes("\nnew\nlines\n".to_string().into_bytes());
+        buffer.set_point(5);
+        buffer.insert(b'\n');
+        buffer.set_point(17);
+        buffer.remove();
+        buffer.set_point(0);
+        buffer.insert_char('\n');
+
+        let expected_lines: Vec<String> = buffer.to_string().split('\n').map(String::from).collect();
+
+        assert_eq!(buffer.line_count(), expected_lines.len());
+        for (line, text) in expected_lines.iter().enumerate() {
+            let start = buffer.line_to_offset(line).unwrap();
+            let end = buffer.line_end_offset(line).unwrap();
+
+            assert_eq!(buffer.slice(start..end), text.as_str());
+        }
+    }
+
+    #[test]
+    fn initialized_with_capacity() {
+        let buffer = GapBuffer::with_capacity(100);
+
+        assert_eq!(buffer.capacity(), 100);
+        assert_eq!(buffer.gap_len(), 100);
+        assert_eq!(buffer.len(), 0);
+    }
+
+    #[test]
+    fn reserve_room_in_the_gap() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(20);
+        buffer.insert_bytes(Vec::new());
+
+        buffer.reserve(500);
+
+        assert!(buffer.gap_len() >= 500);
+        assert_eq!(buffer.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn grow_the_gap_by_a_fixed_size() {
+        let mut buffer = GapBuffer::with_capacity(0);
+        buffer.set_growth_policy(GrowthPolicy::Fixed(64));
+
+        buffer.insert(b'a');
+
+        assert_eq!(buffer.gap_len(), 63);
+    }
+
+    #[test]
+    fn grow_the_gap_in_proportion_to_the_buffer_length() {
+        let content = "a".repeat(1000);
+        let mut buffer = GapBuffer::from(content.clone());
+        buffer.set_growth_policy(GrowthPolicy::Proportional(25));
+        buffer.set_point(500);
+
+        buffer.insert_bytes(vec![b'b'; INITIAL_GAP_SIZE + 1]);
+
+        assert_eq!(buffer.gap_len(), 250 - 1);
+        assert_eq!(buffer.len(), 1000 + INITIAL_GAP_SIZE + 1);
+    }
+
+    #[test]
+    fn grow_the_gap_by_at_least_the_insertion_size() {
+        let mut buffer = GapBuffer::with_capacity(0);
+        buffer.set_growth_policy(GrowthPolicy::Fixed(1));
+
+        buffer.insert_bytes(vec![b'c'; 40]);
+
+        assert_eq!(buffer.gap_len(), 0);
+        assert_eq!(buffer.to_string(), "c".repeat(40));
+    }
+
+    #[test]
+    fn delete_byte_forward() {
+        let mut buffer = buffer_with_contents();
+        let mut expected_string = TEST_STRING.to_owned();
+        expected_string.remove(4);
+
+        buffer.set_point(4);
+
+        assert_eq!(buffer.delete_forward(), Some(b'q'));
+        assert_eq!(buffer.to_string(), expected_string);
+        assert_eq!(buffer.get_point(), 4);
+    }
+
+    #[test]
+    fn delete_forward_at_end_of_buffer() {
+        let mut buffer = buffer_with_contents();
+
+        assert_eq!(buffer.delete_forward(), None);
+        assert_eq!(buffer.delete_char_forward(), None);
+        assert_eq!(buffer.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn delete_backward_at_start_of_buffer() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(0);
+
+        assert_eq!(buffer.delete_char_backward(), None);
+        buffer.remove();
+        assert_eq!(buffer.to_string(), TEST_STRING);
+        assert_eq!(buffer.get_point(), 0);
+    }
+
+    #[test]
+    fn delete_characters_forward_and_backward() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+        buffer.set_point_char(2);
+
+        assert_eq!(buffer.delete_char_forward(), Some('ï'));
+        assert_eq!(buffer.get_point(), 2);
+        assert_eq!(buffer.to_string(), "nave café 日本");
+
+        buffer.set_point(buffer.len());
+
+        assert_eq!(buffer.delete_char_backward(), Some('本'));
+        assert_eq!(buffer.delete_char_backward(), Some('日'));
+        assert_eq!(buffer.get_point(), buffer.len());
+        assert_eq!(buffer.to_string(), "nave café ");
+    }
+
+    struct ReplacementTestCase {
+        name: String,
+        range: std::ops::Range<usize>,
+        text: String,
+        point: usize,
+        expected_point: usize,
+    }
+
+    #[test]
+    fn replace_a_range() {
+        let test_cases = [
+            ReplacementTestCase {
+                name: "Replace with longer text before the point".to_string(),
+                range: 4..9,
+                text: "very slow".to_string(),
+                point: 20,
+                expected_point: 24,
+            },
+            ReplacementTestCase {
+                name: "Replace with shorter text after the point".to_string(),
+                range: 36..40,
+                text: "big".to_string(),
+                point: 10,
+                expected_point: 10,
+            },
+            ReplacementTestCase {
+                name: "Replace around the point".to_string(),
+                range: 10..19,
+                text: "red\nhen".to_string(),
+                point: 16,
+                expected_point: 17,
+            },
+            ReplacementTestCase {
+                name: "Replace an empty range at the point".to_string(),
+                range: 0..0,
+                text: "So, ".to_string(),
+                point: 0,
+                expected_point: 4,
+            },
+            ReplacementTestCase {
+                name: "Replace with nothing".to_string(),
+                range: 15..32,
+                text: "".to_string(),
+                point: TEST_STRING.len(),
+                expected_point: TEST_STRING.len() - 17,
+            },
+        ];
+
+        for test_case in test_cases.iter() {
+            let mut buffer = buffer_with_contents();
+            let mut expected_string = TEST_STRING.to_owned();
+            let expected_removed = expected_string[test_case.range.clone()].to_string();
+            expected_string.replace_range(test_case.range.clone(), &test_case.text);
+
+            buffer.set_point(test_case.point);
+
+            assert_eq!(buffer.replace_range(test_case.range.clone(), &test_case.text), expected_removed, "Test case: \"{}\" failed.", test_case.name);
+            assert_eq!(buffer.to_string(), expected_string, "Test case: \"{}\" failed.", test_case.name);
+            assert_eq!(buffer.get_point(), test_case.expected_point, "Test case: \"{}\" failed.", test_case.name);
+            assert_eq!(buffer.line_count(), expected_string.split('\n').count(), "Test case: \"{}\" failed.", test_case.name);
+        }
+    }
+
+    #[test]
+    fn insert_a_string_at_the_point() {
+        let mut buffer = buffer_with_contents();
+        let mut expected_string = TEST_STRING.to_owned();
+        expected_string.insert_str(10, "very ");
+
+        buffer.set_point(10);
+        buffer.insert_str("very ");
+
+        assert_eq!(buffer.to_string(), expected_string);
+        assert_eq!(buffer.get_point(), 15);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index 3 is not on a UTF-8 character boundary.")]
+    fn insert_a_string_inside_a_character_panics() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+        buffer.point = 3;
+
+        buffer.insert_str("x");
+    }
+
+    #[test]
+    fn push_a_string_onto_the_end() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(4);
+
+        buffer.push_str("\nThe end.");
+        buffer.push_str("");
+
+        assert_eq!(buffer.to_string(), TEST_STRING.to_owned() + "\nThe end.");
+        assert_eq!(buffer.get_point(), 4);
+        assert_eq!(buffer.line_count(), 4);
+    }
+
+    #[test]
+    fn read_characters_from_an_index() {
+        let mut buffer = GapBuffer::from(MULTI_BYTE_STRING.to_string());
+        buffer.set_point(12);
+        buffer.insert_bytes(Vec::new());
+
+        assert_eq!(buffer.char_at(10), Some('é'));
+        assert_eq!(buffer.chars_at(7).collect::<String>(), "café 日本");
+        assert_eq!(buffer.char_at(MULTI_BYTE_STRING.len()), None);
+        assert_eq!(buffer.next_char_boundary(10), Some(12));
+        assert_eq!(buffer.prev_char_boundary(12), Some(10));
+        assert_eq!(buffer.prev_char_boundary(0), None);
+        assert_eq!(buffer.next_char_boundary(MULTI_BYTE_STRING.len()), None);
+    }
+
+    #[test]
+    fn make_the_contents_contiguous() {
+        let mut buffer = buffer_with_contents();
+        buffer.set_point(10);
+        buffer.insert_bytes(Vec::new());
+
+        assert_bytes_eq(buffer.make_contiguous().to_vec(), TEST_STRING.as_bytes().to_vec());
+        assert_eq!(buffer.as_slices().1.len(), 0);
+        assert_eq!(buffer.get_point(), 10);
+    }
+
     fn buffer_with_contents() -> GapBuffer {
         GapBuffer::from(TEST_STRING.to_string())
     }
@@ -0,0 +1,153 @@
+use std::ops::Range;
+
+/// LineIndex caches the offset at which each line of a buffer starts so line lookups don't
+/// need to scan the buffer. It is kept up to date as text is inserted and removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new() -> LineIndex {
+        LineIndex {
+            line_starts: vec![0],
+        }
+    }
+
+    /// from_bytes() builds an index by scanning `bytes` for newlines.
+    pub fn from_bytes<I: Iterator<Item = u8>>(bytes: I) -> LineIndex {
+        let mut line_starts = vec![0];
+
+        for (index, byte) in bytes.enumerate() {
+            if byte == b'\n' {
+                line_starts.push(index + 1);
+            }
+        }
+
+        LineIndex { line_starts }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// line_to_offset() returns the offset of the start of `line`, or `None` if there is no such line.
+    pub fn line_to_offset(&self, line: usize) -> Option<usize> {
+        self.line_starts.get(line).cloned()
+    }
+
+    /// offset_to_line() returns the line containing `offset`.
+    pub fn offset_to_line(&self, offset: usize) -> usize {
+        self.line_starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    /// insert() updates the index after `bytes` are inserted at `offset`.
+    pub fn insert(&mut self, offset: usize, bytes: &[u8]) {
+        let index = self.line_starts.partition_point(|start| *start <= offset);
+
+        for start in self.line_starts[index..].iter_mut() {
+            *start += bytes.len();
+        }
+
+        let new_starts = bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+            .map(|(position, _)| offset + position + 1);
+
+        self.line_starts.splice(index..index, new_starts);
+    }
+
+    /// remove() updates the index after the bytes in `range` are removed.
+    pub fn remove(&mut self, range: Range<usize>) {
+        let first = self.line_starts.partition_point(|start| *start <= range.start);
+        let last = self.line_starts.partition_point(|start| *start <= range.end);
+        let length = range.end - range.start;
+
+        self.line_starts.drain(first..last);
+
+        for start in self.line_starts[first..].iter_mut() {
+            *start -= length;
+        }
+    }
+}
+
+impl Default for LineIndex {
+    fn default() -> LineIndex {
+        LineIndex::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use line_index::LineIndex;
+
+    fn index_of(text: &str) -> LineIndex {
+        LineIndex::from_bytes(text.bytes())
+    }
+
+    #[test]
+    fn index_line_starts() {
+        let index = index_of("one\ntwo\n\nfour");
+
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_to_offset(0), Some(0));
+        assert_eq!(index.line_to_offset(1), Some(4));
+        assert_eq!(index.line_to_offset(2), Some(8));
+        assert_eq!(index.line_to_offset(3), Some(9));
+        assert_eq!(index.line_to_offset(4), None);
+    }
+
+    #[test]
+    fn look_up_the_line_of_an_offset() {
+        let index = index_of("one\ntwo\n\nfour");
+
+        assert_eq!(index.offset_to_line(0), 0);
+        assert_eq!(index.offset_to_line(3), 0);
+        assert_eq!(index.offset_to_line(4), 1);
+        assert_eq!(index.offset_to_line(8), 2);
+        assert_eq!(index.offset_to_line(13), 3);
+    }
+
+    #[test]
+    fn update_after_insertion() {
+        let test_cases = [
+            ("insert without newlines", "one\ntwo", 5, "xx"),
+            ("insert a newline", "one\ntwo", 5, "\n"),
+            ("insert at a line start", "one\ntwo", 4, "a\nb\n"),
+            ("insert at the start", "one\ntwo", 0, "\n"),
+            ("insert at the end", "one\ntwo", 7, "\nthree\n"),
+        ];
+
+        for (name, text, offset, inserted) in test_cases.iter() {
+            let mut expected_text = text.to_string();
+            expected_text.insert_str(*offset, inserted);
+            let mut index = index_of(text);
+
+            index.insert(*offset, inserted.as_bytes());
+
+            assert_eq!(index, index_of(&expected_text), "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn update_after_removal() {
+        let test_cases = [
+            ("remove without newlines", "one\ntwo\nthree", 4..6),
+            ("remove a newline", "one\ntwo\nthree", 3..4),
+            ("remove whole lines", "one\ntwo\nthree", 4..8),
+            ("remove across lines", "one\ntwo\nthree", 1..10),
+            ("remove everything", "one\ntwo\nthree", 0..13),
+        ];
+
+        for (name, text, range) in test_cases.iter() {
+            let mut expected_text = text.to_string();
+            expected_text.replace_range(range.clone(), "");
+            let mut index = index_of(text);
+
+            index.remove(range.clone());
+
+            assert_eq!(index, index_of(&expected_text), "Test case: \"{}\" failed.", name);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::io::{self, Read};
+
+use GapBuffer;
+
+/// The most bytes read at once while loading a buffer.
+pub const LOAD_CHUNK_SIZE: usize = 1 << 20;
+
+/// Loading text straight from a reader into the buffer's storage, without first collecting it in
+/// a `String`.
+impl GapBuffer {
+    /// from_reader() creates a buffer holding everything `reader` produces, read a chunk at a time.
+    /// The point is left at the start. The bytes are not checked to be UTF-8.
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<GapBuffer> {
+        let mut buffer = GapBuffer::new();
+        while buffer.read_from(&mut reader, LOAD_CHUNK_SIZE)? > 0 {}
+
+        Ok(buffer)
+    }
+
+    /// read_from() appends up to `max` bytes from `reader` to the end of the buffer and returns how
+    /// many were read, zero once `reader` is exhausted. The point does not move. Bytes are read
+    /// directly into the gap unless someone is listening for changes, who is then told about them
+    /// like any other insertion.
+    pub fn read_from<R: Read>(&mut self, reader: &mut R, max: usize) -> io::Result<usize> {
+        let end = self.len();
+
+        if !self.listeners.is_empty() {
+            let mut chunk = Vec::with_capacity(max.min(LOAD_CHUNK_SIZE));
+            let count = reader.take(max as u64).read_to_end(&mut chunk)?;
+            self.insert_at(end, &chunk);
+            return Ok(count);
+        }
+
+        self.move_gap(end);
+        self.reserve(max);
+        let count = read_fully(reader, &mut self.buffer[self.gap_start..self.gap_start + max])?;
+
+        self.line_index.insert(end, &self.buffer[self.gap_start..self.gap_start + count]);
+        let before = self.hidden_before();
+        self.anchors.update(before + end..before + end, count);
+        self.gap_start += count;
+        if count > 0 {
+            self.version += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// read_fully() fills as much of `chunk` as `reader` can, stopping early only at the end of input.
+fn read_fully<R: Read>(reader: &mut R, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut count = 0;
+
+    while count < chunk.len() {
+        match reader.read(&mut chunk[count..]) {
+            Ok(0) => break,
+            Ok(read) => count += read,
+            Err(ref error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    use GapBuffer;
+
+    #[test]
+    fn load_from_a_reader() {
+        let text = "line one\nline two\n".repeat(1000);
+
+        let buffer = GapBuffer::from_reader(Cursor::new(text.clone())).unwrap();
+
+        assert_eq!(buffer.to_string(), text);
+        assert_eq!(buffer.get_point(), 0);
+        assert_eq!(buffer.line_count(), 2001);
+        assert_eq!(buffer.line_to_offset(3), Some(27));
+    }
+
+    #[test]
+    fn read_in_chunks() {
+        let mut reader = Cursor::new("abcdefg");
+        let mut buffer = GapBuffer::from("> ".to_string());
+        buffer.set_point(0);
+
+        assert_eq!(buffer.read_from(&mut reader, 3).unwrap(), 3);
+        assert_eq!(buffer.read_from(&mut reader, 3).unwrap(), 3);
+        assert_eq!(buffer.read_from(&mut reader, 3).unwrap(), 1);
+        assert_eq!(buffer.read_from(&mut reader, 3).unwrap(), 0);
+
+        assert_eq!(buffer.to_string(), "> abcdefg");
+        assert_eq!(buffer.get_point(), 0);
+    }
+
+    #[test]
+    fn listeners_hear_about_loaded_text() {
+        let mut buffer = GapBuffer::new();
+        let inserted = Arc::new(Mutex::new(String::new()));
+        let seen = inserted.clone();
+        buffer.on_change(move |event| seen.lock().unwrap().push_str(&event.inserted));
+
+        buffer.read_from(&mut Cursor::new("hello"), 10).unwrap();
+
+        assert_eq!(*inserted.lock().unwrap(), "hello");
+    }
+}
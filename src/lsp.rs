@@ -0,0 +1,615 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use delta::{PositionEncoding, TextChange};
+
+use self::jsonrpc::{read_message, write_message, METHOD_NOT_FOUND};
+
+pub use self::jsonrpc::{Message, ResponseError};
+
+pub mod jsonrpc;
+
+/// How long a server has to answer `initialize` and `shutdown`.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a server has to exit after it is told to.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The language servers started for each language until told otherwise: its language identifier,
+/// the extensions of its files, and the command and arguments that start its server.
+const LANGUAGE_SERVERS: &[(&str, &[&str], &str, &[&str])] = &[
+    ("rust", &["rs"], "rust-analyzer", &[]),
+    ("c", &["c", "h"], "clangd", &[]),
+    ("cpp", &["cc", "cpp", "hpp"], "clangd", &[]),
+    ("python", &["py", "pyw"], "pylsp", &[]),
+    ("javascript", &["js", "mjs", "cjs"], "typescript-language-server", &["--stdio"]),
+    ("typescript", &["ts"], "typescript-language-server", &["--stdio"]),
+    ("go", &["go"], "gopls", &[]),
+];
+
+/// LspError is why a language server could not be talked to.
+#[derive(Debug)]
+pub enum LspError {
+    /// The server answered a request with an error.
+    Server(ResponseError),
+    /// The server did not answer the request for the method in time.
+    Timeout(String),
+    /// The server has exited, or closed its output.
+    Disconnected,
+    Io(io::Error),
+}
+
+impl fmt::Display for LspError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LspError::Server(error) => write!(f, "Language server error {}: {}", error.code, error.message),
+            LspError::Timeout(method) => write!(f, "Language server did not answer {}", method),
+            LspError::Disconnected => write!(f, "Language server exited"),
+            LspError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for LspError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LspError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LspError {
+    fn from(error: io::Error) -> LspError {
+        LspError::Io(error)
+    }
+}
+
+/// ServerConfig is how to start a language server.
+/// - `language_id`: The language identifier documents are opened with, such as `rust`.
+/// - `command`: The program to run, found on the `PATH` if it is not a path.
+/// - `args`: The arguments to run it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub language_id: String,
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl ServerConfig {
+    /// for_path() returns the usual server for the file at `path`, judged by its extension.
+    pub fn for_path(path: &Path) -> Option<ServerConfig> {
+        let extension = path.extension()?.to_str()?;
+        let (language_id, _, command, args) = LANGUAGE_SERVERS.iter().find(|(_, extensions, _, _)| extensions.contains(&extension))?;
+
+        Some(ServerConfig {
+            language_id: language_id.to_string(),
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        })
+    }
+
+    /// from_command_line() returns a config running the words of `command_line` as a server for
+    /// `language_id`, or `None` if there are no words.
+    pub fn from_command_line(language_id: &str, command_line: &str) -> Option<ServerConfig> {
+        let mut words = command_line.split_whitespace().map(|word| word.to_string());
+
+        Some(ServerConfig {
+            language_id: language_id.to_string(),
+            command: words.next()?,
+            args: words.collect(),
+        })
+    }
+}
+
+/// language_id() returns the language identifier of the file at `path`: that of its usual server,
+/// or else its extension.
+pub fn language_id(path: &Path) -> Option<String> {
+    match ServerConfig::for_path(path) {
+        Some(config) => Some(config.language_id),
+        None => path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_string()),
+    }
+}
+
+/// TextDocumentSync is how a server wants to hear about changes to open documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextDocumentSync {
+    /// Not at all. Documents are still opened and closed.
+    None,
+    /// With the whole text after each change.
+    Full,
+    /// With the ranges that changed and their new text.
+    Incremental,
+}
+
+/// LspEvent is something a server sent that the client did not handle itself.
+/// - `Notification`: A notification, such as `textDocument/publishDiagnostics`.
+/// - `Response`: The answer to the request with `id`, which called `method`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LspEvent {
+    Notification { method: String, params: Value },
+    Response { id: u64, method: String, result: Result<Value, ResponseError> },
+}
+
+/// LspClient talks to a language server over JSON-RPC. Messages from the server are read on a
+/// thread of their own, and handled as `poll()` is called, so the editor never waits on the
+/// server except while starting and stopping it.
+/// - `writer`: Where messages to the server are written.
+/// - `receiver`: The messages read from the server.
+/// - `child`: The server's process, if the client started it.
+/// - `next_id`: The id of the next request.
+/// - `pending`: The method of each request still to be answered, by id.
+/// - `queued`: Events that arrived while waiting for a response, to be returned by `poll()`.
+/// - `capabilities`: What the server said it can do when it was initialized.
+/// - `encoding`: How the server counts characters in positions.
+/// - `sync`: How the server wants changes to documents sent.
+pub struct LspClient {
+    writer: Box<dyn Write + Send>,
+    receiver: Receiver<Message>,
+    child: Option<Child>,
+    next_id: u64,
+    pending: HashMap<u64, String>,
+    queued: VecDeque<LspEvent>,
+    capabilities: Value,
+    encoding: PositionEncoding,
+    sync: TextDocumentSync,
+}
+
+impl fmt::Debug for LspClient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LspClient").field("pending", &self.pending).field("encoding", &self.encoding).field("sync", &self.sync).finish()
+    }
+}
+
+impl LspClient {
+    /// spawn() starts the server `config` describes, talking to it over its standard input and
+    /// output, and initializes it for the workspace at `root`.
+    pub fn spawn(config: &ServerConfig, root: &Path) -> Result<LspClient, LspError> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or(LspError::Disconnected)?;
+        let stdout = child.stdout.take().ok_or(LspError::Disconnected)?;
+
+        let mut client = LspClient::connect(stdout, stdin);
+        client.child = Some(child);
+        client.initialize(root, REQUEST_TIMEOUT)?;
+        Ok(client)
+    }
+
+    /// connect() returns a client for a server that reads from `writer` and writes to `reader`.
+    /// It still has to be initialized.
+    pub fn connect<R: Read + Send + 'static, W: Write + Send + 'static>(reader: R, writer: W) -> LspClient {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            while let Ok(Some(message)) = read_message(&mut reader) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        LspClient {
+            writer: Box::new(writer),
+            receiver,
+            child: None,
+            next_id: 1,
+            pending: HashMap::new(),
+            queued: VecDeque::new(),
+            capabilities: Value::Null,
+            encoding: PositionEncoding::Utf16,
+            sync: TextDocumentSync::Full,
+        }
+    }
+
+    /// initialize() performs the `initialize` handshake for the workspace at `root`, waiting up
+    /// to `timeout` for the server's answer, and records what the server can do.
+    pub fn initialize(&mut self, root: &Path, timeout: Duration) -> Result<(), LspError> {
+        let root_uri = path_to_uri(root);
+        let params = json!({
+            "processId": std::process::id(),
+            "clientInfo": {"name": "one"},
+            "rootUri": root_uri,
+            "workspaceFolders": [{"uri": root_uri, "name": root.file_name().map(|name| name.to_string_lossy()).unwrap_or_default()}],
+            "capabilities": {
+                "general": {"positionEncodings": ["utf-8", "utf-16"]},
+                "textDocument": {"synchronization": {"dynamicRegistration": false}},
+                "window": {"showMessage": {}},
+            },
+        });
+        let id = self.request("initialize", params)?;
+        let result = self.wait_for(id, timeout)?;
+
+        self.capabilities = result["capabilities"].clone();
+        self.encoding = match self.capabilities["positionEncoding"].as_str() {
+            Some("utf-8") => PositionEncoding::Utf8,
+            Some("utf-32") => PositionEncoding::Utf32,
+            _ => PositionEncoding::Utf16,
+        };
+        let sync = &self.capabilities["textDocumentSync"];
+        self.sync = match sync.as_u64().or_else(|| sync["change"].as_u64()) {
+            Some(0) => TextDocumentSync::None,
+            Some(2) => TextDocumentSync::Incremental,
+            _ => TextDocumentSync::Full,
+        };
+
+        self.notify("initialized", json!({}))
+    }
+
+    /// shutdown() asks the server to shut down and exit, and waits for it to. A server that does
+    /// not exit in time is killed.
+    pub fn shutdown(mut self) -> Result<(), LspError> {
+        let id = self.request("shutdown", Value::Null)?;
+        let answered = self.wait_for(id, REQUEST_TIMEOUT).map(|_| ());
+        self.notify("exit", Value::Null)?;
+
+        if let Some(child) = self.child.as_mut() {
+            let start = Instant::now();
+            while child.try_wait()?.is_none() && start.elapsed() < EXIT_TIMEOUT {
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        answered
+    }
+
+    pub fn capabilities(&self) -> &Value {
+        &self.capabilities
+    }
+
+    pub fn encoding(&self) -> PositionEncoding {
+        self.encoding
+    }
+
+    pub fn sync(&self) -> TextDocumentSync {
+        self.sync
+    }
+
+    /// request() sends a request for `method` and returns its id. The answer is returned by
+    /// `poll()` as an `LspEvent::Response`.
+    pub fn request(&mut self, method: &str, params: Value) -> Result<u64, LspError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&Message::Request {
+            id: json!(id),
+            method: method.to_string(),
+            params,
+        })?;
+
+        self.pending.insert(id, method.to_string());
+        Ok(id)
+    }
+
+    pub fn notify(&mut self, method: &str, params: Value) -> Result<(), LspError> {
+        self.send(&Message::Notification {
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    pub fn did_open(&mut self, uri: &str, language_id: &str, version: i64, text: &str) -> Result<(), LspError> {
+        let document = json!({"uri": uri, "languageId": language_id, "version": version, "text": text});
+        self.notify("textDocument/didOpen", json!({ "textDocument": document }))
+    }
+
+    /// did_change() tells the server about `changes` to a document, each in the coordinates left
+    /// by the ones before it, as the buffer's change feed reports them.
+    pub fn did_change(&mut self, uri: &str, version: i64, changes: &[TextChange]) -> Result<(), LspError> {
+        let changes: Vec<Value> = changes
+            .iter()
+            .map(|change| {
+                let start = json!({"line": change.start.line, "character": change.start.character});
+                let end = json!({"line": change.end.line, "character": change.end.character});
+                json!({"range": {"start": start, "end": end}, "text": change.text})
+            })
+            .collect();
+
+        self.notify("textDocument/didChange", json!({"textDocument": {"uri": uri, "version": version}, "contentChanges": changes}))
+    }
+
+    /// did_change_full() tells the server a document's text is now `text`.
+    pub fn did_change_full(&mut self, uri: &str, version: i64, text: &str) -> Result<(), LspError> {
+        let changes = json!([{ "text": text }]);
+        self.notify("textDocument/didChange", json!({"textDocument": {"uri": uri, "version": version}, "contentChanges": changes}))
+    }
+
+    pub fn did_close(&mut self, uri: &str) -> Result<(), LspError> {
+        self.notify("textDocument/didClose", json!({"textDocument": {"uri": uri}}))
+    }
+
+    /// poll() handles the messages the server has sent, without waiting for more. Requests from
+    /// the server are answered; everything else is returned. It fails with `Disconnected` once
+    /// the server has gone and everything it sent has been returned.
+    pub fn poll(&mut self) -> Result<Vec<LspEvent>, LspError> {
+        let mut events: Vec<LspEvent> = self.queued.drain(..).collect();
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => events.extend(self.receive(message)?),
+                Err(TryRecvError::Empty) => return Ok(events),
+                Err(TryRecvError::Disconnected) if events.is_empty() => return Err(LspError::Disconnected),
+                Err(TryRecvError::Disconnected) => return Ok(events),
+            }
+        }
+    }
+
+    /// wait_for() waits up to `timeout` for the answer to the request with `id`, queueing other
+    /// events for `poll()`.
+    fn wait_for(&mut self, id: u64, timeout: Duration) -> Result<Value, LspError> {
+        let start = Instant::now();
+
+        loop {
+            let remaining = timeout.checked_sub(start.elapsed()).unwrap_or_default();
+            let message = match self.receiver.recv_timeout(remaining) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => return Err(LspError::Timeout(self.pending.remove(&id).unwrap_or_default())),
+                Err(RecvTimeoutError::Disconnected) => return Err(LspError::Disconnected),
+            };
+
+            match self.receive(message)? {
+                Some(LspEvent::Response { id: answered, result, .. }) if answered == id => return result.map_err(LspError::Server),
+                Some(event) => self.queued.push_back(event),
+                None => {}
+            }
+        }
+    }
+
+    /// receive() handles one message from the server, returning the event it is, if any.
+    fn receive(&mut self, message: Message) -> Result<Option<LspEvent>, LspError> {
+        match message {
+            Message::Request { id, method, params } => {
+                let result = match method.as_str() {
+                    "workspace/configuration" => Ok(json!(vec![Value::Null; params["items"].as_array().map_or(0, Vec::len)])),
+                    "window/workDoneProgress/create" | "client/registerCapability" | "client/unregisterCapability" => Ok(Value::Null),
+                    _ => Err(ResponseError {
+                        code: METHOD_NOT_FOUND,
+                        message: format!("Unhandled method {}", method),
+                    }),
+                };
+                self.send(&Message::Response { id, result })?;
+                Ok(None)
+            }
+            Message::Response { id, result } => {
+                let id = match id.as_u64() {
+                    Some(id) => id,
+                    None => return Ok(None),
+                };
+                Ok(self.pending.remove(&id).map(|method| LspEvent::Response { id, method, result }))
+            }
+            Message::Notification { method, params } => Ok(Some(LspEvent::Notification { method, params })),
+        }
+    }
+
+    fn send(&mut self, message: &Message) -> Result<(), LspError> {
+        write_message(&mut self.writer, message).map_err(|error| match error.kind() {
+            io::ErrorKind::BrokenPipe => LspError::Disconnected,
+            _ => LspError::Io(error),
+        })
+    }
+}
+
+impl Drop for LspClient {
+    fn drop(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            if let Ok(None) = child.try_wait() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// path_to_uri() returns the `file` URI of `path`, made absolute against the working directory.
+pub fn path_to_uri(path: &Path) -> String {
+    let path = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => env::current_dir().map(|directory| directory.join(path)).unwrap_or_else(|_| path.to_path_buf()),
+    };
+
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// uri_to_path() returns the path of a `file` URI, or `None` if it is some other kind.
+pub fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+
+    let mut index = 0;
+    while index < encoded.len() {
+        let escaped = encoded.get(index + 1..index + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (encoded[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok().map(PathBuf::from)
+}
+
+/// fake_server() returns a client connected to a server on a thread of its own, which sends what
+/// `answer` returns for each message it gets. Every message it gets is also sent to the returned
+/// receiver. It stops after the `exit` notification.
+#[cfg(test)]
+pub(crate) fn fake_server<F>(mut answer: F) -> (LspClient, Receiver<Message>)
+where
+    F: FnMut(&Message) -> Vec<Message> + Send + 'static,
+{
+    use std::sync::mpsc::Sender;
+
+    struct Pipe(Sender<Vec<u8>>);
+
+    impl Write for Pipe {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.send(bytes.to_vec()).map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct PipeEnd(Receiver<Vec<u8>>, Vec<u8>);
+
+    impl Read for PipeEnd {
+        fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+            if self.1.is_empty() {
+                match self.0.recv() {
+                    Ok(bytes) => self.1 = bytes,
+                    Err(_) => return Ok(0),
+                }
+            }
+
+            let length = buffer.len().min(self.1.len());
+            buffer[..length].copy_from_slice(&self.1[..length]);
+            self.1.drain(..length);
+            Ok(length)
+        }
+    }
+
+    let (to_server, server_input) = mpsc::channel();
+    let (server_output, from_server) = mpsc::channel();
+    let (log, received) = mpsc::channel();
+    thread::spawn(move || {
+        let mut input = BufReader::new(PipeEnd(server_input, Vec::new()));
+        let mut output = Pipe(server_output);
+        while let Ok(Some(message)) = read_message(&mut input) {
+            for reply in answer(&message) {
+                let _ = write_message(&mut output, &reply);
+            }
+            let exit = message == Message::Notification { method: "exit".to_string(), params: Value::Null };
+            let _ = log.send(message);
+            if exit {
+                break;
+            }
+        }
+    });
+
+    (LspClient::connect(PipeEnd(from_server, Vec::new()), Pipe(to_server)), received)
+}
+
+/// initialize_answer() answers `initialize` with `capabilities` for `fake_server()`.
+#[cfg(test)]
+pub(crate) fn initialize_answer(message: &Message, capabilities: Value) -> Vec<Message> {
+    match message {
+        Message::Request { id, method, .. } if method == "initialize" || method == "shutdown" => {
+            let result = if method == "initialize" { json!({ "capabilities": capabilities }) } else { Value::Null };
+            vec![Message::Response { id: id.clone(), result: Ok(result) }]
+        }
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::time::Duration;
+
+    use delta::{Position, PositionEncoding, TextChange};
+    use lsp::{fake_server, initialize_answer, path_to_uri, uri_to_path, LspEvent, Message, ServerConfig, TextDocumentSync};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn initialize_sync_documents_and_shut_down() {
+        let (mut client, received) = fake_server(|message| {
+            let mut answers = initialize_answer(message, json!({"positionEncoding": "utf-8", "textDocumentSync": {"change": 2}}));
+            if let Message::Notification { method, .. } = message {
+                if method == "initialized" {
+                    answers.push(Message::Request { id: json!("config"), method: "workspace/configuration".to_string(), params: json!({"items": [{}]}) });
+                    answers.push(Message::Notification { method: "window/logMessage".to_string(), params: json!({"message": "ready"}) });
+                }
+            }
+            answers
+        });
+
+        client.initialize(Path::new("/work"), Duration::from_secs(5)).unwrap();
+        assert_eq!(client.encoding(), PositionEncoding::Utf8);
+        assert_eq!(client.sync(), TextDocumentSync::Incremental);
+
+        client.did_open("file:///work/a.rs", "rust", 1, "fn main() {}\n").unwrap();
+        let change = TextChange { start: Position { line: 0, character: 3 }, end: Position { line: 0, character: 7 }, text: "f".to_string() };
+        client.did_change("file:///work/a.rs", 2, &[change]).unwrap();
+        let id = client.request("textDocument/hover", json!({})).unwrap();
+        client.shutdown().unwrap();
+
+        let methods: Vec<(String, Value)> = received
+            .iter()
+            .map(|message| match message {
+                Message::Request { method, params, .. } | Message::Notification { method, params } => (method, params),
+                Message::Response { result, .. } => ("response".to_string(), result.unwrap()),
+            })
+            .collect();
+        let names: Vec<&str> = methods.iter().map(|(method, _)| method.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["initialize", "initialized", "textDocument/didOpen", "textDocument/didChange", "textDocument/hover", "shutdown", "response", "exit"]
+        );
+        assert_eq!(methods[0].1["rootUri"], "file:///work");
+        assert_eq!(methods[6].1, json!([null]));
+        assert_eq!(methods[3].1["contentChanges"][0]["range"]["end"], json!({"line": 0, "character": 7}));
+        assert!(id > 0);
+    }
+
+    #[test]
+    fn poll_returns_notifications_and_responses() {
+        let (mut client, _) = fake_server(|message| match message {
+            Message::Request { id, method, .. } if method == "textDocument/hover" => {
+                vec![
+                    Message::Notification { method: "window/showMessage".to_string(), params: json!({"message": "hi"}) },
+                    Message::Response { id: id.clone(), result: Ok(json!("docs")) },
+                ]
+            }
+            _ => Vec::new(),
+        });
+
+        let id = client.request("textDocument/hover", json!({})).unwrap();
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            events.extend(client.poll().unwrap());
+        }
+        assert_eq!(
+            events,
+            vec![
+                LspEvent::Notification { method: "window/showMessage".to_string(), params: json!({"message": "hi"}) },
+                LspEvent::Response { id, method: "textDocument/hover".to_string(), result: Ok(json!("docs")) },
+            ]
+        );
+    }
+
+    #[test]
+    fn convert_paths_and_uris() {
+        assert_eq!(path_to_uri(Path::new("/home/me/my file.rs")), "file:///home/me/my%20file.rs");
+        assert_eq!(uri_to_path("file:///home/me/my%20file.rs").unwrap(), Path::new("/home/me/my file.rs"));
+        assert_eq!(uri_to_path("https://example.com"), None);
+
+        assert_eq!(ServerConfig::for_path(Path::new("main.rs")).unwrap().command, "rust-analyzer");
+        let config = ServerConfig::from_command_line("rust", "ra-multiplex --stdio").unwrap();
+        assert_eq!((config.command.as_str(), config.args), ("ra-multiplex", vec!["--stdio".to_string()]));
+        assert!(ServerConfig::from_command_line("rust", " ").is_none());
+    }
+}
@@ -0,0 +1,151 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Map, Value};
+
+/// The header every message starts with, giving the length of its JSON body in bytes.
+const CONTENT_LENGTH: &str = "Content-Length";
+
+/// ResponseError is the error a server answers a request with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// The error code for a request whose method the receiver does not have.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Message is one JSON-RPC 2.0 message, as sent between an editor and a language server.
+/// - `Request`: Asks the other side for a response with the same `id`.
+/// - `Response`: Answers the request with `id`, with its result or why it failed.
+/// - `Notification`: Tells the other side something, expecting no answer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Request { id: Value, method: String, params: Value },
+    Response { id: Value, result: Result<Value, ResponseError> },
+    Notification { method: String, params: Value },
+}
+
+impl Message {
+    /// to_value() returns the message as the JSON object sent for it.
+    pub fn to_value(&self) -> Value {
+        match self {
+            Message::Request { id, method, params } => json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}),
+            Message::Response { id, result: Ok(result) } => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Message::Response { id, result: Err(error) } => {
+                json!({"jsonrpc": "2.0", "id": id, "error": {"code": error.code, "message": error.message}})
+            }
+            Message::Notification { method, params } => json!({"jsonrpc": "2.0", "method": method, "params": params}),
+        }
+    }
+
+    /// from_value() reads a message from its JSON object, or returns `None` if it is not one.
+    pub fn from_value(value: Value) -> Option<Message> {
+        let mut object: Map<String, Value> = match value {
+            Value::Object(object) => object,
+            _ => return None,
+        };
+        let params = object.remove("params").unwrap_or(Value::Null);
+
+        match (object.remove("id"), object.remove("method")) {
+            (Some(id), Some(Value::String(method))) => Some(Message::Request { id, method, params }),
+            (None, Some(Value::String(method))) => Some(Message::Notification { method, params }),
+            (Some(id), None) => {
+                let result = match object.remove("error") {
+                    Some(error) => Err(ResponseError {
+                        code: error["code"].as_i64().unwrap_or(0),
+                        message: error["message"].as_str().unwrap_or("").to_string(),
+                    }),
+                    None => Ok(object.remove("result").unwrap_or(Value::Null)),
+                };
+                Some(Message::Response { id, result })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// write_message() sends `message` with the header giving its length.
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> io::Result<()> {
+    let body = message.to_value().to_string();
+
+    write!(writer, "{}: {}\r\n\r\n{}", CONTENT_LENGTH, body.len(), body)?;
+    writer.flush()
+}
+
+/// read_message() reads the next message, or returns `None` if the stream ends before another
+/// one starts. Headers other than the content length are ignored.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Message>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case(CONTENT_LENGTH) {
+                length = Some(value.trim().parse::<usize>().map_err(|_| invalid("Invalid content length"))?);
+            }
+        }
+    }
+
+    let mut body = vec![0; length.ok_or_else(|| invalid("Missing content length"))?];
+    reader.read_exact(&mut body)?;
+    let value = serde_json::from_slice(&body).map_err(|error| invalid(&error.to_string()))?;
+
+    Message::from_value(value).map(Some).ok_or_else(|| invalid("Not a JSON-RPC message"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use lsp::jsonrpc::{read_message, write_message, Message, ResponseError};
+    use serde_json::json;
+
+    #[test]
+    fn write_and_read_messages() {
+        let messages = [
+            Message::Request { id: json!(1), method: "initialize".to_string(), params: json!({"rootUri": null}) },
+            Message::Response { id: json!(1), result: Ok(json!({"capabilities": {}})) },
+            Message::Response { id: json!(2), result: Err(ResponseError { code: -32601, message: "Unknown".to_string() }) },
+            Message::Notification { method: "exit".to_string(), params: json!(null) },
+        ];
+
+        let mut written = Vec::new();
+        for message in messages.iter() {
+            write_message(&mut written, message).unwrap();
+        }
+        assert!(written.starts_with(b"Content-Length: "));
+
+        let mut reader = Cursor::new(written);
+        for message in messages.iter() {
+            assert_eq!(read_message(&mut reader).unwrap().as_ref(), Some(message));
+        }
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn reject_malformed_messages() {
+        let test_cases = [
+            ("no content length", "Content-Type: x\r\n\r\n{}"),
+            ("not JSON", "Content-Length: 3\r\n\r\nabc"),
+            ("not a message", "Content-Length: 2\r\n\r\n[]"),
+            ("a short body", "Content-Length: 10\r\n\r\n{}"),
+        ];
+
+        for (name, text) in test_cases.iter() {
+            assert!(read_message(&mut Cursor::new(text.as_bytes())).is_err(), "Test case: \"{}\" failed.", name);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use keymap::{format_keys, Key};
+
+/// Macros records key sequences so they can be played back, and keeps them by name.
+/// - `recording`: The keys typed since recording started, if it has.
+/// - `last`: The most recently recorded macro.
+/// - `named`: Macros saved under a name.
+/// - `playing`: Whether a macro is being played back, so its keys are not recorded again.
+#[derive(Debug, Default)]
+pub struct Macros {
+    recording: Option<Vec<Key>>,
+    last: Option<Vec<Key>>,
+    named: BTreeMap<String, Vec<Key>>,
+    playing: bool,
+}
+
+impl Macros {
+    pub fn new() -> Macros {
+        Macros::default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub(crate) fn set_playing(&mut self, playing: bool) {
+        self.playing = playing;
+    }
+
+    /// start_recording() begins a new macro and returns false if one is already being recorded.
+    pub fn start_recording(&mut self) -> bool {
+        if self.recording.is_some() {
+            return false;
+        }
+
+        self.recording = Some(Vec::new());
+        true
+    }
+
+    /// stop_recording() finishes the macro being recorded and makes it the last macro.
+    /// Returns false if nothing was being recorded.
+    pub fn stop_recording(&mut self) -> bool {
+        match self.recording.take() {
+            Some(keys) => {
+                self.last = Some(keys);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// record() adds keys to the macro being recorded, unless a macro is playing.
+    pub fn record(&mut self, keys: &[Key]) {
+        if self.playing {
+            return;
+        }
+        if let Some(recording) = self.recording.as_mut() {
+            recording.extend_from_slice(keys);
+        }
+    }
+
+    pub fn last(&self) -> Option<&[Key]> {
+        self.last.as_deref()
+    }
+
+    pub fn set_last(&mut self, keys: Vec<Key>) {
+        self.last = Some(keys);
+    }
+
+    /// name_last() saves the last macro as `name`, replacing any macro already called that.
+    /// Returns false if no macro has been recorded.
+    pub fn name_last(&mut self, name: &str) -> bool {
+        match self.last.clone() {
+            Some(keys) => {
+                self.named.insert(name.to_string(), keys);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[Key]> {
+        self.named.get(name).map(|keys| keys.as_slice())
+    }
+
+    pub fn insert(&mut self, name: &str, keys: Vec<Key>) {
+        self.named.insert(name.to_string(), keys);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Vec<Key>> {
+        self.named.remove(name)
+    }
+
+    /// names() lists the named macros in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.named.keys().map(|name| name.as_str())
+    }
+
+    /// save() writes the named macros to `path`, one per line as the name, a tab and the keys in
+    /// Emacs notation.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = String::new();
+        for (name, keys) in self.named.iter() {
+            contents.push_str(&format!("{}\t{}\n", name, format_keys(keys)));
+        }
+
+        fs::write(path, contents)
+    }
+
+    /// load() reads named macros written by `save()`, adding them to any already named.
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let contents = fs::read_to_string(path)?;
+
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let (name, keys) = line
+                .split_once('\t')
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid macro: {:?}", line)))?;
+            let keys = Key::parse_sequence(keys).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            self.named.insert(name.to_string(), keys);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use keymap::Key;
+    use macros::Macros;
+
+    #[test]
+    fn record_a_macro() {
+        let mut macros = Macros::new();
+        macros.record(&[Key::char('x')]);
+        assert!(!macros.stop_recording());
+
+        assert!(macros.start_recording());
+        assert!(!macros.start_recording());
+        macros.record(&[Key::char('a'), Key::control('f')]);
+        macros.set_playing(true);
+        macros.record(&[Key::char('b')]);
+        macros.set_playing(false);
+        assert!(macros.stop_recording());
+
+        assert_eq!(macros.last(), Some(&[Key::char('a'), Key::control('f')][..]));
+    }
+
+    #[test]
+    fn save_and_load_named_macros() {
+        let path = env::temp_dir().join(format!("one-macros-{}.txt", std::process::id()));
+        let mut macros = Macros::new();
+        assert!(!macros.name_last("nothing"));
+        macros.set_last(Key::parse_sequence("C-a SPC - RET").unwrap());
+        assert!(macros.name_last("bullet"));
+        macros.insert("save", Key::parse_sequence("C-x C-s").unwrap());
+
+        macros.save(&path).unwrap();
+        let mut loaded = Macros::new();
+        loaded.load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.names().collect::<Vec<_>>(), vec!["bullet", "save"]);
+        assert_eq!(loaded.get("bullet"), macros.get("bullet"));
+    }
+
+    #[test]
+    fn load_rejects_invalid_keys() {
+        let path = env::temp_dir().join(format!("one-bad-macros-{}.txt", std::process::id()));
+        fs::write(&path, "broken\tQ-q\n").unwrap();
+
+        let result = Macros::new().load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
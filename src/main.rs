@@ -1,3 +1,32 @@
+extern crate gap_buffer;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+use gap_buffer::editor::Editor;
+use gap_buffer::terminal;
+
+/// The file in the home directory bookmarks are kept in between sessions.
+const BOOKMARK_FILE: &str = ".one-bookmarks";
+
 fn main() {
-    println!("Hello world");
+    let mut editor = Editor::new();
+    if let Some(home) = env::var_os("HOME") {
+        if let Err(error) = editor.set_bookmark_file(Some(Path::new(&home).join(BOOKMARK_FILE))) {
+            editor.set_message(&format!("Could not load bookmarks: {}", error));
+        }
+    }
+
+    for path in env::args().skip(1) {
+        if let Err(error) = editor.visit_file_argument(&path) {
+            eprintln!("one: {}: {}", path, error);
+            process::exit(1);
+        }
+    }
+
+    if let Err(error) = terminal::run(&mut editor) {
+        eprintln!("one: {}", error);
+        process::exit(1);
+    }
 }
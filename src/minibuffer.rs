@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use fuzzy;
+use GapBuffer;
+
+/// The number of entries each minibuffer history keeps.
+pub const HISTORY_CAPACITY: usize = 100;
+
+/// Minibuffer is a one-line prompt for the argument to a command. The text typed is held in its
+/// own gap buffer, and finishing the prompt runs the command with it.
+/// - `prompt`: What is being asked for, e.g. `Goto line: `.
+/// - `input`: The text typed so far.
+/// - `command`: The command run with the input when the prompt is finished.
+/// - `history`: The name of the history the input is added to, shared by prompts for similar things.
+/// - `history_index`: Which history entry is being shown, while moving through the history.
+/// - `draft`: The input typed before moving into the history, restored on moving back out of it.
+/// - `choices`: What the input picks from, if it picks from a list, such as the files of a project.
+/// - `matches`: The indexes of the choices matching the input, best first.
+/// - `filtered`: The input `matches` were ranked against.
+/// - `selected`: Which of `matches` finishing the prompt picks.
+pub struct Minibuffer {
+    prompt: String,
+    input: GapBuffer,
+    command: String,
+    history: String,
+    history_index: Option<usize>,
+    draft: String,
+    choices: Vec<String>,
+    matches: Vec<usize>,
+    filtered: Option<String>,
+    selected: usize,
+}
+
+impl Minibuffer {
+    pub fn new(prompt: &str, command: &str, history: &str) -> Minibuffer {
+        Minibuffer {
+            prompt: prompt.to_string(),
+            input: GapBuffer::new(),
+            command: command.to_string(),
+            history: history.to_string(),
+            history_index: None,
+            draft: String::new(),
+            choices: Vec::new(),
+            matches: Vec::new(),
+            filtered: None,
+            selected: 0,
+        }
+    }
+
+    pub fn prompt(&self) -> &str {
+        &self.prompt
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    pub fn history(&self) -> &str {
+        &self.history
+    }
+
+    pub fn input(&self) -> &GapBuffer {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut GapBuffer {
+        &mut self.input
+    }
+
+    pub fn text(&self) -> String {
+        self.input.to_string()
+    }
+
+    /// set_text() replaces the input with `text` and puts the point at its end.
+    pub fn set_text(&mut self, text: &str) {
+        let length = self.input.len();
+        self.input.replace_range(0..length, text);
+        self.input.set_point(text.len());
+    }
+
+    /// previous_history() shows the history entry before the one shown, starting from the most
+    /// recent. Returns false if there are no older entries.
+    pub fn previous_history(&mut self, history: &MinibufferHistory) -> bool {
+        let entries = history.entries(&self.history);
+        let index = match self.history_index {
+            Some(0) => return false,
+            Some(index) => index - 1,
+            None if entries.is_empty() => return false,
+            None => {
+                self.draft = self.text();
+                entries.len() - 1
+            }
+        };
+
+        self.history_index = Some(index);
+        let entry = entries[index].clone();
+        self.set_text(&entry);
+        true
+    }
+
+    /// next_history() shows the history entry after the one shown, or the input typed before
+    /// moving into the history after the most recent entry. Returns false if not in the history.
+    pub fn next_history(&mut self, history: &MinibufferHistory) -> bool {
+        let entries = history.entries(&self.history);
+        let index = match self.history_index {
+            Some(index) => index + 1,
+            None => return false,
+        };
+
+        if index < entries.len() {
+            self.history_index = Some(index);
+            let entry = entries[index].clone();
+            self.set_text(&entry);
+        } else {
+            self.history_index = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_text(&draft);
+        }
+        true
+    }
+
+    /// complete() extends the input with what every one of `candidates` starting with it has in
+    /// common, and returns the candidates that matched.
+    pub fn complete(&mut self, candidates: &[String]) -> Vec<String> {
+        let input = self.text();
+        let matches: Vec<String> = candidates.iter().filter(|candidate| candidate.starts_with(&input)).cloned().collect();
+
+        if let Some(first) = matches.first() {
+            let common = matches.iter().fold(first.as_str(), |common, candidate| common_prefix(common, candidate));
+            if common.len() > input.len() {
+                let common = common.to_string();
+                self.set_text(&common);
+            }
+        }
+
+        matches
+    }
+
+    /// set_choices() makes the input pick one of `choices`, which are narrowed down to those it
+    /// fuzzily matches as it is typed.
+    pub fn set_choices(&mut self, choices: Vec<String>) {
+        self.choices = choices;
+        self.filtered = None;
+        self.refilter();
+    }
+
+    pub fn has_choices(&self) -> bool {
+        !self.choices.is_empty()
+    }
+
+    /// refilter() ranks the choices against the input again if it has changed since they were
+    /// last ranked, selecting the best.
+    pub fn refilter(&mut self) {
+        let text = self.text();
+        if self.choices.is_empty() || self.filtered.as_ref() == Some(&text) {
+            return;
+        }
+
+        self.matches = fuzzy::rank(&text, &self.choices);
+        self.selected = 0;
+        self.filtered = Some(text);
+    }
+
+    /// matches() lists the choices matching the input, best first.
+    pub fn matches(&self) -> Vec<&str> {
+        self.matches.iter().map(|index| self.choices[*index].as_str()).collect()
+    }
+
+    /// selected() is the choice finishing the prompt picks, if any matches the input.
+    pub fn selected(&self) -> Option<&str> {
+        self.matches.get(self.selected).map(|index| self.choices[*index].as_str())
+    }
+
+    /// selected_index() is where the selected choice is in `matches()`.
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+}
+
+/// common_prefix() returns the longest start `a` and `b` share, ending on a character boundary.
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let length = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map_or(a.len().min(b.len()), |((index, _), _)| index);
+
+    &a[..length]
+}
+
+/// file_name_candidates() lists the files in the directory named by `input` up to its last
+/// slash, written with that directory in front, and with a slash after directories.
+pub fn file_name_candidates(input: &str) -> Vec<String> {
+    let prefix = match input.rfind('/') {
+        Some(slash) => &input[..slash + 1],
+        None => "",
+    };
+    let directory = match prefix {
+        "" => Path::new("."),
+        prefix => Path::new(prefix),
+    };
+
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let mut candidate = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                candidate.push('/');
+            }
+            candidate
+        })
+        .collect();
+    candidates.sort();
+
+    candidates
+}
+
+/// MinibufferHistory keeps what was entered at earlier prompts, in separate lists for each kind
+/// of prompt, oldest first.
+#[derive(Debug, Default)]
+pub struct MinibufferHistory {
+    lists: HashMap<String, Vec<String>>,
+}
+
+impl MinibufferHistory {
+    pub fn new() -> MinibufferHistory {
+        MinibufferHistory::default()
+    }
+
+    pub fn entries(&self, history: &str) -> &[String] {
+        self.lists.get(history).map_or(&[], |entries| entries.as_slice())
+    }
+
+    /// add() records `entry` in `history`, unless it is empty or the same as the last entry.
+    pub fn add(&mut self, history: &str, entry: &str) {
+        let entries = self.lists.entry(history.to_string()).or_default();
+        if entry.is_empty() || entries.last().map(|last| last.as_str()) == Some(entry) {
+            return;
+        }
+
+        if entries.len() == HISTORY_CAPACITY {
+            entries.remove(0);
+        }
+        entries.push(entry.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use minibuffer::{file_name_candidates, Minibuffer, MinibufferHistory};
+
+    #[test]
+    fn move_through_the_history() {
+        let mut history = MinibufferHistory::new();
+        history.add("search", "first");
+        history.add("search", "second");
+        history.add("search", "second");
+        history.add("search", "");
+        history.add("files", "main.rs");
+        let mut minibuffer = Minibuffer::new("Search: ", "search-forward", "search");
+        minibuffer.set_text("dra");
+
+        assert!(!minibuffer.next_history(&history));
+        assert!(minibuffer.previous_history(&history));
+        assert_eq!(minibuffer.text(), "second");
+        assert!(minibuffer.previous_history(&history));
+        assert_eq!(minibuffer.text(), "first");
+        assert!(!minibuffer.previous_history(&history));
+
+        assert!(minibuffer.next_history(&history));
+        assert!(minibuffer.next_history(&history));
+        assert_eq!(minibuffer.text(), "dra");
+        assert_eq!(minibuffer.input().get_point(), 3);
+    }
+
+    #[test]
+    fn complete_the_input() {
+        let candidates: Vec<String> = ["forward-char", "forward-word", "find-file"].iter().map(|c| c.to_string()).collect();
+        let mut minibuffer = Minibuffer::new("M-x ", "execute-extended-command", "commands");
+
+        minibuffer.set_text("fo");
+        assert_eq!(minibuffer.complete(&candidates).len(), 2);
+        assert_eq!(minibuffer.text(), "forward-");
+
+        minibuffer.set_text("fi");
+        assert_eq!(minibuffer.complete(&candidates), vec!["find-file"]);
+        assert_eq!(minibuffer.text(), "find-file");
+
+        minibuffer.set_text("x");
+        assert!(minibuffer.complete(&candidates).is_empty());
+        assert_eq!(minibuffer.text(), "x");
+    }
+
+    #[test]
+    fn pick_from_choices_as_the_input_is_typed() {
+        let mut minibuffer = Minibuffer::new("Find file: ", "find-file-in-project", "files");
+        minibuffer.set_choices(vec!["src/main.rs".to_string(), "README.md".to_string(), "src/editor/mouse.rs".to_string()]);
+        assert_eq!((minibuffer.matches().len(), minibuffer.selected()), (3, Some("src/main.rs")));
+
+        minibuffer.set_text("ms");
+        minibuffer.refilter();
+        assert_eq!(minibuffer.matches(), vec!["src/editor/mouse.rs", "src/main.rs"]);
+        minibuffer.select_next();
+        assert_eq!((minibuffer.selected_index(), minibuffer.selected()), (1, Some("src/main.rs")));
+        minibuffer.select_next();
+        assert_eq!(minibuffer.selected(), Some("src/editor/mouse.rs"));
+        minibuffer.select_previous();
+        assert_eq!(minibuffer.selected(), Some("src/main.rs"));
+
+        minibuffer.set_text("msx");
+        minibuffer.refilter();
+        assert_eq!(minibuffer.selected(), None);
+    }
+
+    #[test]
+    fn complete_file_names() {
+        let directory = env::temp_dir().join(format!("one-completion-{}", std::process::id()));
+        fs::create_dir_all(directory.join("src")).unwrap();
+        fs::write(directory.join("Cargo.toml"), "").unwrap();
+        let prefix = format!("{}/", directory.display());
+
+        let candidates = file_name_candidates(&format!("{}C", prefix));
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(candidates, vec![format!("{}Cargo.toml", prefix), format!("{}src/", prefix)]);
+    }
+}
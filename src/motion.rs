@@ -0,0 +1,294 @@
+use GapBuffer;
+
+/// Point motions used by editor commands. Words are runs of alphanumeric characters and
+/// underscores. Paragraphs are separated by blank lines. Sentences end with `.`, `!` or `?`,
+/// and any closing quotes or brackets after it, before whitespace, or at the end of a paragraph.
+impl GapBuffer {
+    /// move_point_forward_word() moves the point to the end of the next word.
+    pub fn move_point_forward_word(&mut self) {
+        let mut index = self.point;
+
+        while let Some(character) = self.char_at(index) {
+            if is_word_char(character) {
+                break;
+            }
+            index = self.next_char_boundary(index).unwrap();
+        }
+
+        while let Some(character) = self.char_at(index) {
+            if !is_word_char(character) {
+                break;
+            }
+            index = self.next_char_boundary(index).unwrap();
+        }
+
+        self.point = index;
+    }
+
+    /// move_point_backward_word() moves the point to the start of the previous word.
+    pub fn move_point_backward_word(&mut self) {
+        let mut index = self.point;
+
+        while let Some(previous) = self.prev_char_boundary(index) {
+            if self.char_at(previous).is_some_and(is_word_char) {
+                break;
+            }
+            index = previous;
+        }
+
+        while let Some(previous) = self.prev_char_boundary(index) {
+            if !self.char_at(previous).is_some_and(is_word_char) {
+                break;
+            }
+            index = previous;
+        }
+
+        self.point = index;
+    }
+
+    /// move_point_line_start() moves the point to the start of the current line.
+    pub fn move_point_line_start(&mut self) {
+        let (line, _) = self.offset_to_line_col(self.point);
+        self.point = self.line_to_offset(line).unwrap();
+    }
+
+    /// move_point_line_end() moves the point to the end of the current line, before the newline.
+    pub fn move_point_line_end(&mut self) {
+        let (line, _) = self.offset_to_line_col(self.point);
+        self.point = self.line_end_offset(line).unwrap();
+    }
+
+    /// move_point_by_lines() moves the point `count` lines down, or up for a negative `count`,
+    /// keeping the same column where the target line is long enough. Movement stops at the
+    /// first and last lines.
+    pub fn move_point_by_lines(&mut self, count: isize) {
+        let (line, column) = self.offset_to_line_col(self.point);
+        let last_line = self.line_count() - 1;
+        let target = if count < 0 {
+            line.saturating_sub(count.unsigned_abs())
+        } else {
+            line.saturating_add(count as usize).min(last_line)
+        };
+
+        let start = self.line_to_offset(target).unwrap();
+        let end = self.line_end_offset(target).unwrap();
+        let mut index = (start + column).min(end);
+
+        while !self.is_char_boundary(index) {
+            index -= 1;
+        }
+
+        self.point = index;
+    }
+
+    /// move_point_forward_sentence() moves the point to the end of the sentence it is in, or of
+    /// the next one if it is already at the end of one.
+    pub fn move_point_forward_sentence(&mut self) {
+        let mut index = self.point;
+
+        while let Some(character) = self.char_at(index) {
+            let next = self.next_char_boundary(index).unwrap();
+            if character == '\n' && self.char_at(next) == Some('\n') && index > self.point {
+                break;
+            }
+            if matches!(character, '.' | '!' | '?') {
+                let mut end = next;
+                while let Some(closer) = self.char_at(end).filter(|closer| matches!(closer, '"' | '\'' | ')' | ']')) {
+                    end += closer.len_utf8();
+                }
+                if self.char_at(end).is_none_or(char::is_whitespace) {
+                    index = end;
+                    break;
+                }
+            }
+            index = next;
+        }
+
+        self.point = index;
+    }
+
+    /// move_point_forward_paragraph() moves the point to the blank line after the current
+    /// paragraph, or the end of the buffer.
+    pub fn move_point_forward_paragraph(&mut self) {
+        let (mut line, _) = self.offset_to_line_col(self.point);
+        let last_line = self.line_count() - 1;
+
+        while line < last_line && self.is_blank_line(line) {
+            line += 1;
+        }
+
+        while line < last_line && !self.is_blank_line(line) {
+            line += 1;
+        }
+
+        self.point = if self.is_blank_line(line) {
+            self.line_to_offset(line).unwrap()
+        } else {
+            self.len()
+        };
+    }
+
+    /// move_point_backward_paragraph() moves the point to the blank line before the current
+    /// paragraph, or the start of the buffer.
+    pub fn move_point_backward_paragraph(&mut self) {
+        let (mut line, _) = self.offset_to_line_col(self.point);
+
+        while line > 0 && self.is_blank_line(line) {
+            line -= 1;
+        }
+
+        while line > 0 && !self.is_blank_line(line) {
+            line -= 1;
+        }
+
+        self.point = self.line_to_offset(line).unwrap();
+    }
+
+    fn is_blank_line(&self, line: usize) -> bool {
+        let start = self.line_to_offset(line).unwrap();
+        let end = self.line_end_offset(line).unwrap();
+
+        self.bytes_at(start).take(end - start).all(|byte| byte == b' ' || byte == b'\t' || byte == b'\r')
+    }
+}
+
+pub fn is_word_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use GapBuffer;
+
+    const TEST_STRING: &str = "fn main() {\n    let café = 1;\n\n    println!(\"{}\", café);\n}";
+
+    fn buffer_at(point: usize) -> GapBuffer {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(point);
+        buffer
+    }
+
+    #[test]
+    fn move_forward_by_words() {
+        let mut buffer = buffer_at(0);
+        let mut stops = Vec::new();
+
+        for _ in 0..4 {
+            buffer.move_point_forward_word();
+            stops.push(buffer.get_point());
+        }
+
+        assert_eq!(stops, vec![2, 7, 19, 25]);
+    }
+
+    #[test]
+    fn move_backward_by_words() {
+        let mut buffer = buffer_at(25);
+        let mut stops = Vec::new();
+
+        for _ in 0..4 {
+            buffer.move_point_backward_word();
+            stops.push(buffer.get_point());
+        }
+
+        assert_eq!(stops, vec![20, 16, 3, 0]);
+    }
+
+    #[test]
+    fn word_motion_stops_at_buffer_ends() {
+        let mut buffer = buffer_at(TEST_STRING.len() - 3);
+
+        buffer.move_point_forward_word();
+        assert_eq!(buffer.get_point(), TEST_STRING.len());
+
+        buffer.set_point(1);
+        buffer.move_point_backward_word();
+        buffer.move_point_backward_word();
+        assert_eq!(buffer.get_point(), 0);
+    }
+
+    #[test]
+    fn move_forward_by_sentences() {
+        let text = "One. \"Two?\" Three 1.5 four!\nFive\n\nSix.";
+        let mut buffer = GapBuffer::from(text.to_string());
+        buffer.set_point(0);
+        let mut stops = Vec::new();
+
+        for _ in 0..5 {
+            buffer.move_point_forward_sentence();
+            stops.push(buffer.get_point());
+        }
+
+        assert_eq!(stops, vec![4, 11, 27, 32, 38]);
+    }
+
+    #[test]
+    fn step_over_invalid_utf8() {
+        let mut buffer = GapBuffer::new();
+        buffer.insert_bytes(vec![0xff]);
+        buffer.set_point(0);
+        buffer.move_point_forward_word();
+        assert_eq!(buffer.get_point(), 1);
+
+        buffer.insert_bytes(b"ab\xff. c".to_vec());
+        buffer.set_point(0);
+        buffer.move_point_forward_word();
+        assert_eq!(buffer.get_point(), 3);
+        buffer.set_point(0);
+        buffer.move_point_forward_sentence();
+        assert_eq!(buffer.get_point(), 5);
+        buffer.move_point_forward_sentence();
+        assert_eq!(buffer.get_point(), buffer.len());
+    }
+
+    #[test]
+    fn move_to_line_start_and_end() {
+        let mut buffer = buffer_at(20);
+
+        buffer.move_point_line_start();
+        assert_eq!(buffer.get_point(), 12);
+
+        buffer.move_point_line_end();
+        assert_eq!(buffer.get_point(), 30);
+
+        buffer.move_point_line_end();
+        assert_eq!(buffer.get_point(), 30);
+    }
+
+    #[test]
+    fn move_by_lines_keeps_the_column() {
+        let test_cases = [
+            ("down one line", 6, 1, 18),
+            ("up one line", 18, -1, 6),
+            ("down onto a short line", 20, 1, 31),
+            ("up into a multi-byte character", 44, -2, 23),
+            ("past the last line", 4, 10, TEST_STRING.len()),
+            ("before the first line", 36, -10, 4),
+        ];
+
+        for (name, point, count, expected_point) in test_cases.iter() {
+            let mut buffer = buffer_at(*point);
+
+            buffer.move_point_by_lines(*count);
+
+            assert_eq!(buffer.get_point(), *expected_point, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn move_by_paragraphs() {
+        let mut buffer = buffer_at(5);
+
+        buffer.move_point_forward_paragraph();
+        assert_eq!(buffer.get_point(), 31);
+
+        buffer.move_point_forward_paragraph();
+        assert_eq!(buffer.get_point(), TEST_STRING.len());
+
+        buffer.move_point_backward_paragraph();
+        assert_eq!(buffer.get_point(), 31);
+
+        buffer.move_point_backward_paragraph();
+        assert_eq!(buffer.get_point(), 0);
+    }
+}
@@ -0,0 +1,180 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use line_index::LineIndex;
+use GapBuffer;
+
+/// Narrowing is the text a narrowed buffer hides. It stays in the storage, before and after the
+/// accessible text with the gap between them, so widening only has to stop hiding it.
+/// - `before`: How many bytes are hidden before the accessible text.
+/// - `after`: How many bytes are hidden after it.
+/// - `lines_before`: How many line breaks are hidden before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Narrowing {
+    before: usize,
+    after: usize,
+    lines_before: usize,
+}
+
+/// Narrowing restricts a buffer to part of its text. While it is narrowed every offset, line,
+/// anchor and edit event is relative to the accessible text, as if that were all there is, and
+/// nothing outside it can be changed. Only LSP positions still count from the start of the
+/// whole text, so a language server keeps seeing the whole document.
+impl GapBuffer {
+    /// narrow() restricts the buffer to the text in `range` until widen() is called, moving the
+    /// point into it. Narrowing a narrowed buffer restricts it further. The accessible text
+    /// changes, so the version goes up as for an edit.
+    /// It will panic if `range` extends past the end of the buffer, or if either end of it is not
+    /// on a UTF-8 character boundary.
+    pub fn narrow(&mut self, range: Range<usize>) {
+        if range.start > range.end || range.end > self.len() {
+            panic!("Range out of bounds. The length is {} but the range is {:?}.", self.len(), range)
+        }
+
+        if !self.is_char_boundary(range.start) || !self.is_char_boundary(range.end) {
+            panic!("Range {:?} is not on UTF-8 character boundaries.", range)
+        }
+
+        let narrowing = self.narrowing.unwrap_or_default();
+        let lines_before = self.line_index.offset_to_line(range.start);
+        let after = self.len() - range.end;
+
+        self.move_gap(range.start);
+        self.point = self.point.clamp(range.start, range.end) - range.start;
+        self.narrowing = Some(Narrowing {
+            before: self.gap_start,
+            after: narrowing.after + after,
+            lines_before: narrowing.lines_before + lines_before,
+        });
+        self.line_index = LineIndex::from_bytes(self.bytes());
+        self.version += 1;
+    }
+
+    /// widen() makes the whole text accessible again, leaving the point on the same character,
+    /// and puts the version up. Returns false if the buffer was not narrowed.
+    pub fn widen(&mut self) -> bool {
+        let narrowing = match self.narrowing.take() {
+            Some(narrowing) => narrowing,
+            None => return false,
+        };
+
+        self.point += narrowing.before;
+        self.line_index = LineIndex::from_bytes(self.bytes());
+        self.version += 1;
+        true
+    }
+
+    pub fn is_narrowed(&self) -> bool {
+        self.narrowing.is_some()
+    }
+
+    /// narrowed_range() returns where the accessible text lies in the whole text, or `None` if
+    /// the buffer is not narrowed.
+    pub fn narrowed_range(&self) -> Option<Range<usize>> {
+        let before = self.narrowing?.before;
+        Some(before..before + self.len())
+    }
+
+    /// whole_slices() is as_slices() for the whole text, including what narrowing hides, as it is
+    /// written to a file.
+    pub fn whole_slices(&self) -> (&[u8], &[u8]) {
+        (&self.buffer[..self.gap_start], &self.buffer[self.gap_end..])
+    }
+
+    /// whole_text() returns the whole text, including what narrowing hides.
+    /// Invalid UTF-8 sequences are replaced with `U+FFFD REPLACEMENT CHARACTER`.
+    pub fn whole_text(&self) -> String {
+        let (front, back) = self.whole_slices();
+        format!("{}{}", String::from_utf8_lossy(front), String::from_utf8_lossy(back))
+    }
+
+    /// hidden_before() is how many bytes narrowing hides before the accessible text. They are
+    /// the first bytes of the storage.
+    pub(crate) fn hidden_before(&self) -> usize {
+        self.narrowing.map_or(0, |narrowing| narrowing.before)
+    }
+
+    /// hidden_after() is how many bytes narrowing hides after the accessible text. They are the
+    /// last bytes of the storage.
+    pub(crate) fn hidden_after(&self) -> usize {
+        self.narrowing.map_or(0, |narrowing| narrowing.after)
+    }
+
+    /// hidden_line_start() returns how many whole lines narrowing hides before the accessible
+    /// text, and the hidden text on the line it starts in.
+    pub(crate) fn hidden_line_start(&self) -> (usize, Cow<'_, str>) {
+        let before = self.hidden_before();
+        let line_start = self.buffer[..before].iter().rposition(|byte| *byte == b'\n').map_or(0, |newline| newline + 1);
+        let lines_before = self.narrowing.map_or(0, |narrowing| narrowing.lines_before);
+
+        (lines_before, String::from_utf8_lossy(&self.buffer[line_start..before]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor::Affinity;
+    use delta::{Position, PositionEncoding};
+    use GapBuffer;
+
+    const TEST_STRING: &str = "one\ntwo three\nfour\nfive";
+
+    #[test]
+    fn narrowing_hides_the_rest_of_the_text() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(10);
+        buffer.narrow(8..18);
+
+        assert!(buffer.is_narrowed());
+        assert_eq!(buffer.to_string(), "three\nfour");
+        assert_eq!((buffer.len(), buffer.get_point(), buffer.line_count()), (10, 2, 2));
+        assert_eq!(buffer.line_to_offset(1), Some(6));
+        assert_eq!(buffer.narrowed_range(), Some(8..18));
+
+        buffer.narrow(6..10);
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), ("four", 0));
+        assert_eq!(buffer.narrowed_range(), Some(14..18));
+
+        assert!(buffer.widen());
+        assert_eq!((buffer.to_string().as_str(), buffer.get_point()), (TEST_STRING, 14));
+        assert_eq!(buffer.line_count(), 4);
+        assert!(!buffer.widen());
+    }
+
+    #[test]
+    fn edits_stay_in_the_accessible_text() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let after = buffer.create_anchor(19, Affinity::Left);
+        buffer.narrow(4..13);
+        assert_eq!(buffer.anchor_offset(after), Some(9));
+
+        buffer.set_point(0);
+        buffer.insert_str("> ");
+        let end = buffer.len();
+        buffer.replace_range(end - 5..end, "3");
+        assert_eq!(buffer.whole_text(), "one\n> two 3\nfour\nfive");
+        assert_eq!(buffer.line_count(), 1);
+
+        buffer.widen();
+        assert_eq!(buffer.to_string(), "one\n> two 3\nfour\nfive");
+        assert_eq!(buffer.anchor_offset(after), Some(17));
+        assert_eq!(buffer.line_to_offset(2), Some(12));
+    }
+
+    #[test]
+    fn positions_count_from_the_start_of_the_whole_text() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.narrow(8..18);
+        let (_, changes) = buffer.subscribe_text_changes(PositionEncoding::Utf8);
+
+        assert_eq!(buffer.offset_to_position(0, PositionEncoding::Utf8), Position { line: 1, character: 4 });
+        assert_eq!(buffer.offset_to_position(7, PositionEncoding::Utf8), Position { line: 2, character: 1 });
+        assert_eq!(buffer.position_to_offset(Position { line: 1, character: 6 }, PositionEncoding::Utf8), Some(2));
+        assert_eq!(buffer.position_to_offset(Position { line: 0, character: 2 }, PositionEncoding::Utf8), Some(0));
+
+        buffer.set_point(6);
+        buffer.insert_str("4");
+        let change = changes.try_recv().unwrap();
+        assert_eq!((change.start, change.end), (Position { line: 2, character: 0 }, Position { line: 2, character: 0 }));
+    }
+}
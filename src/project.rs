@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The directories a project's files are never looked for in, besides those it ignores.
+const SKIPPED_DIRECTORIES: &[&str] = &[".git", ".hg"];
+
+/// root() returns the nearest directory holding `path` with a version control directory in it,
+/// or else the directory of `path`.
+pub fn root(path: &Path) -> PathBuf {
+    let path = match path.is_absolute() {
+        true => path.to_path_buf(),
+        false => std::env::current_dir().map(|directory| directory.join(path)).unwrap_or_else(|_| path.to_path_buf()),
+    };
+    let directory = path.parent().unwrap_or(&path).to_path_buf();
+
+    let mut ancestors = directory.ancestors();
+    ancestors.find(|ancestor| ancestor.join(".git").exists() || ancestor.join(".hg").exists()).unwrap_or(&directory).to_path_buf()
+}
+
+/// IgnoreRule is one pattern of a `.gitignore` file.
+/// - `base`: The directory of the file, relative to the project root.
+/// - `anchored`: Whether the pattern has a slash before its end, so it matches paths from `base`
+///   rather than names at any depth below it.
+/// - `directory_only`: Whether the pattern ended with a slash, so it only matches directories.
+/// - `negated`: Whether the pattern started with `!`, so it takes back an earlier one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreRule {
+    base: PathBuf,
+    pattern: String,
+    anchored: bool,
+    directory_only: bool,
+    negated: bool,
+}
+
+impl IgnoreRule {
+    /// parse() reads a line of the `.gitignore` file in `base`, returning `None` for blank lines
+    /// and comments.
+    pub fn parse(base: &Path, line: &str) -> Option<IgnoreRule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (directory_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+
+        Some(IgnoreRule {
+            base: base.to_path_buf(),
+            pattern: line.trim_start_matches('/').to_string(),
+            anchored,
+            directory_only,
+            negated,
+        })
+    }
+
+    /// matches() reports whether the rule matches `path`, relative to the project root.
+    pub fn matches(&self, path: &Path, is_directory: bool) -> bool {
+        if self.directory_only && !is_directory {
+            return false;
+        }
+        let relative = match path.strip_prefix(&self.base) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => return false,
+        };
+
+        if self.anchored {
+            glob_matches(&self.pattern, &relative)
+        } else {
+            relative.rsplit('/').next().is_some_and(|name| glob_matches(&self.pattern, name))
+        }
+    }
+}
+
+/// is_ignored() reports whether the last of `rules` matching `path` ignores it.
+pub fn is_ignored(rules: &[IgnoreRule], path: &Path, is_directory: bool) -> bool {
+    rules.iter().rev().find(|rule| rule.matches(path, is_directory)).is_some_and(|rule| !rule.negated)
+}
+
+/// glob_matches() reports whether `text` matches the glob `pattern`, in which `*` matches
+/// anything but a slash, `**` matches anything and `?` matches one character.
+pub fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern.first() {
+            None => text.is_empty(),
+            Some('*') if pattern.get(1) == Some(&'*') => {
+                let rest = pattern[2..].strip_prefix(&['/']).unwrap_or(&pattern[2..]);
+                (0..=text.len()).any(|skip| matches(rest, &text[skip..]) || matches(&pattern[2..], &text[skip..]))
+            }
+            Some('*') => {
+                let run = text.iter().take_while(|character| **character != '/').count();
+                (0..=run).any(|skip| matches(&pattern[1..], &text[skip..]))
+            }
+            Some('?') => text.first().is_some_and(|character| *character != '/') && matches(&pattern[1..], &text[1..]),
+            Some(character) => text.first() == Some(character) && matches(&pattern[1..], &text[1..]),
+        }
+    }
+
+    matches(&pattern, &text)
+}
+
+/// files() lists the files under `root`, relative to it and in order, leaving out version
+/// control directories and whatever the `.gitignore` files along the way ignore. Directories
+/// that cannot be read are passed over.
+pub fn files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    walk(root, Path::new(""), &mut Vec::new(), &mut files);
+
+    files
+}
+
+fn walk(root: &Path, directory: &Path, rules: &mut Vec<IgnoreRule>, files: &mut Vec<PathBuf>) {
+    let added = rules.len();
+    if let Ok(contents) = fs::read_to_string(root.join(directory).join(".gitignore")) {
+        rules.extend(contents.lines().filter_map(|line| IgnoreRule::parse(directory, line)));
+    }
+
+    let mut entries: Vec<(PathBuf, bool)> = match fs::read_dir(root.join(directory)) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| (directory.join(entry.file_name()), entry.path().is_dir())).collect(),
+        Err(_) => Vec::new(),
+    };
+    entries.sort();
+
+    for (path, is_directory) in entries {
+        let skipped = is_directory && path.file_name().is_some_and(|name| SKIPPED_DIRECTORIES.iter().any(|skipped| name == *skipped));
+        if skipped || is_ignored(rules, &path, is_directory) {
+            continue;
+        }
+
+        if is_directory {
+            walk(root, &path, rules, files);
+        } else {
+            files.push(path);
+        }
+    }
+    rules.truncate(added);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use project::{files, glob_matches, is_ignored, IgnoreRule};
+
+    #[test]
+    fn match_globs() {
+        let test_cases = [
+            ("literal", "main.rs", "main.rs", true),
+            ("star", "*.rs", "main.rs", true),
+            ("star stops at slashes", "*.rs", "src/main.rs", false),
+            ("question mark", "main.?s", "main.rs", true),
+            ("double star", "**/main.rs", "src/bin/main.rs", true),
+            ("double star at the top", "**/main.rs", "main.rs", true),
+            ("double star inside", "src/**/*.rs", "src/a/b/c.rs", true),
+            ("different", "*.toml", "main.rs", false),
+        ];
+
+        for (name, pattern, text, expected) in test_cases.iter() {
+            assert_eq!(glob_matches(pattern, text), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn follow_gitignore_rules() {
+        let base = Path::new("");
+        let rules: Vec<IgnoreRule> = ["# build output", "target/", "*.log", "!keep.log", "/docs/*.html", ""].iter().filter_map(|line| IgnoreRule::parse(base, line)).collect();
+        let test_cases = [
+            ("directory", "target", true, true),
+            ("file named like a directory rule", "target", false, false),
+            ("glob at any depth", "src/debug.log", false, true),
+            ("negated", "keep.log", false, false),
+            ("anchored", "docs/index.html", false, true),
+            ("anchored elsewhere", "src/docs/index.html", false, false),
+            ("not ignored", "src/main.rs", false, false),
+        ];
+
+        for (name, path, is_directory, expected) in test_cases.iter() {
+            assert_eq!(is_ignored(&rules, Path::new(path), *is_directory), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn list_project_files() {
+        let root = env::temp_dir().join(format!("one-project-{}", std::process::id()));
+        for directory in ["src/nested", "target/debug", ".git"].iter() {
+            fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        for file in ["src/main.rs", "src/nested/mod.rs", "src/nested/out.tmp", "target/debug/one", ".git/HEAD", "README"].iter() {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::write(root.join("src/nested/.gitignore"), "*.tmp\n").unwrap();
+
+        let found = files(&root);
+        fs::remove_dir_all(&root).unwrap();
+
+        let expected: Vec<PathBuf> = [".gitignore", "README", "src/main.rs", "src/nested/.gitignore", "src/nested/mod.rs"].iter().map(PathBuf::from).collect();
+        assert_eq!(found, expected);
+    }
+}
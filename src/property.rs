@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anchor::{Affinity, AnchorId};
+use GapBuffer;
+
+/// PropertyId identifies a span registered with `GapBuffer::add_property()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PropertyId(usize);
+
+/// TextProperty is what a span of a buffer is marked with.
+/// - `group`: What added it, such as `search` or `diagnostics`, so it can take all of its own
+///   away at once.
+/// - `style`: The theme style the span is drawn in, if it is drawn at all.
+/// - `priority`: Where spans overlap, ones with a higher priority are drawn over lower ones.
+/// - `annotation`: A note on the text, such as a diagnostic's message.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextProperty {
+    pub group: String,
+    pub style: Option<String>,
+    pub priority: i32,
+    pub annotation: Option<String>,
+}
+
+impl TextProperty {
+    /// styled() returns a property of `group` drawing its span in `style`.
+    pub fn styled(group: &str, style: &str) -> TextProperty {
+        TextProperty {
+            group: group.to_string(),
+            style: Some(style.to_string()),
+            ..TextProperty::default()
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> TextProperty {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_annotation(mut self, annotation: &str) -> TextProperty {
+        self.annotation = Some(annotation.to_string());
+        self
+    }
+}
+
+/// Properties holds the spans of a buffer marked with text properties. Each span is kept as a
+/// pair of anchors, so it moves with the text it covers.
+#[derive(Debug, Default)]
+pub(crate) struct Properties {
+    properties: HashMap<PropertyId, (AnchorId, AnchorId, TextProperty)>,
+    next_id: usize,
+}
+
+/// Text properties mark spans of the buffer that stay on the same text as it changes.
+impl GapBuffer {
+    /// add_property() marks the text in `range` with `property`. Text inserted at either end of the
+    /// span is left out of it, and the span shrinks as the text in it is removed.
+    /// It will panic if `range` ends past the buffer length or starts after it ends.
+    pub fn add_property(&mut self, range: Range<usize>, property: TextProperty) -> PropertyId {
+        if range.start > range.end {
+            panic!("Invalid range. The start {} is after the end {}.", range.start, range.end)
+        }
+
+        let start = self.create_anchor(range.start, Affinity::Right);
+        let end = self.create_anchor(range.end, Affinity::Left);
+        let id = PropertyId(self.properties.next_id);
+        self.properties.next_id += 1;
+        self.properties.properties.insert(id, (start, end, property));
+
+        id
+    }
+
+    pub fn property(&self, id: PropertyId) -> Option<&TextProperty> {
+        self.properties.properties.get(&id).map(|(_, _, property)| property)
+    }
+
+    /// property_range() returns the span a property marks now, or `None` if it was removed.
+    pub fn property_range(&self, id: PropertyId) -> Option<Range<usize>> {
+        let (start, end, _) = self.properties.properties.get(&id)?;
+        Some(self.anchored_range(*start, *end))
+    }
+
+    /// remove_property() takes the property off its span and returns it.
+    pub fn remove_property(&mut self, id: PropertyId) -> Option<TextProperty> {
+        let (start, end, property) = self.properties.properties.remove(&id)?;
+        self.remove_anchor(start);
+        self.remove_anchor(end);
+
+        Some(property)
+    }
+
+    /// clear_properties() removes every property of `group` and returns how many there were.
+    pub fn clear_properties(&mut self, group: &str) -> usize {
+        let properties = self.properties.properties.iter();
+        let ids: Vec<PropertyId> = properties.filter(|(_, (_, _, property))| property.group == group).map(|(id, _)| *id).collect();
+        for id in ids.iter() {
+            self.remove_property(*id);
+        }
+
+        ids.len()
+    }
+
+    /// properties_in() returns the spans overlapping `range` and their properties, in the order
+    /// they are drawn: by priority, then by where they start. Empty spans inside `range` are
+    /// included.
+    pub fn properties_in(&self, range: Range<usize>) -> Vec<(Range<usize>, &TextProperty)> {
+        let mut found: Vec<(Range<usize>, &TextProperty)> = self
+            .properties
+            .properties
+            .values()
+            .map(|(start, end, property)| (self.anchored_range(*start, *end), property))
+            .filter(|(span, _)| if span.is_empty() { range.contains(&span.start) } else { span.start < range.end && range.start < span.end })
+            .collect();
+        found.sort_by_key(|(span, property)| (property.priority, span.start, span.end));
+
+        found
+    }
+
+    /// line_properties() returns the spans on `line` and their properties, cut to the line and in
+    /// the order they are drawn.
+    pub fn line_properties(&self, line: usize) -> Vec<(Range<usize>, &TextProperty)> {
+        let (start, end) = match (self.line_to_offset(line), self.line_end_offset(line)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Vec::new(),
+        };
+
+        self.properties_in(start..end.max(start + 1))
+            .into_iter()
+            .map(|(span, property)| (span.start.max(start)..span.end.min(end), property))
+            .collect()
+    }
+
+    /// anchored_range() returns the range between two anchors. A span whose text was all removed
+    /// can have its start pushed past its end by an insertion there, and is empty at its end.
+    fn anchored_range(&self, start: AnchorId, end: AnchorId) -> Range<usize> {
+        let end = self.anchor_offset(end).unwrap_or(0);
+        let start = self.anchor_offset(start).unwrap_or(0).min(end);
+
+        start..end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use property::TextProperty;
+    use GapBuffer;
+
+    const TEST_STRING: &str = "The quick brown fox\njumps over\n";
+
+    #[test]
+    fn properties_follow_edits() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let quick = buffer.add_property(4..9, TextProperty::styled("search", "search-match"));
+        let fox = buffer.add_property(16..19, TextProperty::styled("search", "search-match"));
+
+        buffer.set_point(4);
+        buffer.insert_str("very ");
+        buffer.set_point(14);
+        buffer.insert_str("!");
+
+        assert_eq!(buffer.property_range(quick), Some(9..14));
+        assert_eq!(buffer.property_range(fox), Some(22..25));
+
+        buffer.remove_bytes(20..26);
+        assert_eq!(buffer.property_range(fox), Some(20..20));
+        buffer.set_point(20);
+        buffer.insert_str("cat");
+        assert_eq!(buffer.property_range(fox), Some(20..20));
+    }
+
+    #[test]
+    fn query_properties_by_line_in_drawing_order() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        let error = TextProperty::styled("diagnostics", "error").with_priority(10).with_annotation("unknown word");
+        buffer.add_property(10..25, error.clone());
+        buffer.add_property(4..9, TextProperty::styled("search", "search-match"));
+        let point = buffer.add_property(26..26, TextProperty::styled("search", "search-match"));
+
+        let line = |buffer: &GapBuffer, line: usize| -> Vec<(std::ops::Range<usize>, Option<String>)> {
+            buffer.line_properties(line).into_iter().map(|(range, property)| (range, property.style.clone())).collect()
+        };
+        assert_eq!(line(&buffer, 0), vec![(4..9, Some("search-match".to_string())), (10..19, Some("error".to_string()))]);
+        assert_eq!(line(&buffer, 1), vec![(26..26, Some("search-match".to_string())), (20..25, Some("error".to_string()))]);
+        assert_eq!(buffer.properties_in(20..21)[0].1.annotation.as_deref(), Some("unknown word"));
+
+        assert_eq!(buffer.clear_properties("search"), 2);
+        assert_eq!(buffer.property(point), None);
+        assert_eq!(buffer.remove_property(point), None);
+        assert_eq!(line(&buffer, 0), vec![(10..19, Some("error".to_string()))]);
+    }
+}
@@ -0,0 +1,246 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// The size a terminal starts at, in rows and columns, until it is told the size of its window.
+pub const DEFAULT_SIZE: (u16, u16) = (24, 80);
+
+/// Pty is a program run on a pseudo-terminal, so it behaves as it would in a terminal of its own:
+/// it can edit a line, draw in color and be interrupted with `C-c`. What it writes is read on a
+/// thread of its own and picked up with `read()`. The program is killed if it is still running
+/// when the pty is dropped. Pseudo-terminals are only available on Unix.
+#[derive(Debug)]
+pub struct Pty {
+    master: File,
+    child: Child,
+    receiver: Receiver<Vec<u8>>,
+    size: (u16, u16),
+    status: Option<ExitStatus>,
+}
+
+impl Pty {
+    /// spawn() starts `command` on a new pseudo-terminal of `size` rows and columns, as the
+    /// leader of a session of its own, with `TERM` set to say which sequences it may use.
+    #[cfg(unix)]
+    pub fn spawn(mut command: Command, size: (u16, u16)) -> io::Result<Pty> {
+        use std::os::unix::process::CommandExt;
+
+        let (master, slave) = open_pty()?;
+        set_size(&master, size)?;
+        command
+            .env("TERM", "xterm-256color")
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave);
+        // Safety: only functions that are safe to call between fork and exec are called.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        // The program holds the other end now, so reads end once it and its children close it.
+        drop(command);
+
+        let (sender, receiver) = mpsc::channel();
+        let mut output = master.try_clone()?;
+        thread::spawn(move || {
+            let mut chunk = [0; 4096];
+            // Reading fails rather than ending once the program closes the terminal.
+            while let Ok(count) = output.read(&mut chunk) {
+                if count == 0 || sender.send(chunk[..count].to_vec()).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Pty {
+            master,
+            child,
+            receiver,
+            size,
+            status: None,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn spawn(_: Command, _: (u16, u16)) -> io::Result<Pty> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Terminals need a Unix pseudo-terminal"))
+    }
+
+    /// id() is the operating system's id for the program.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        self.size
+    }
+
+    /// status() is how the program exited, or None while it is running or has output left to
+    /// read.
+    pub fn status(&self) -> Option<ExitStatus> {
+        self.status
+    }
+
+    /// read() returns what the program has written since the last call, without waiting. Once it
+    /// has exited and everything it wrote has been returned, its status is set.
+    pub fn read(&mut self) -> Vec<u8> {
+        let mut output = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(chunk) => output.extend(chunk),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if let Ok(Some(status)) = self.child.try_wait() {
+                        self.status = Some(status);
+                    }
+                    break;
+                }
+            }
+        }
+        output
+    }
+
+    /// write() sends `bytes` to the program as if they were typed.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.master.write_all(bytes)?;
+        self.master.flush()
+    }
+
+    /// resize() tells the program it has `size` rows and columns to draw in, if that has changed.
+    pub fn resize(&mut self, size: (u16, u16)) -> io::Result<()> {
+        if size != self.size {
+            set_size(&self.master, size)?;
+            self.size = size;
+        }
+        Ok(())
+    }
+
+    /// kill() stops the program, and returns false if it had already exited.
+    pub fn kill(&mut self) -> bool {
+        match self.child.try_wait() {
+            Ok(None) => self.child.kill().is_ok(),
+            _ => false,
+        }
+    }
+}
+
+impl Drop for Pty {
+    fn drop(&mut self) {
+        if self.kill() {
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// open_pty() opens a new pseudo-terminal, returning its master and slave ends.
+#[cfg(unix)]
+fn open_pty() -> io::Result<(File, File)> {
+    use std::ffi::CStr;
+    use std::fs::OpenOptions;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::FromRawFd;
+
+    // Safety: the descriptor is owned by the file made from it, which closes it, and the name is
+    // read from a buffer ptsname_r() was told the size of.
+    let (master, name) = unsafe {
+        let fd = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let master = File::from_raw_fd(fd);
+        if libc::grantpt(fd) == -1 || libc::unlockpt(fd) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut name = [0 as libc::c_char; 128];
+        if pts_name(fd, &mut name) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        (master, CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned())
+    };
+
+    let slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY | libc::O_CLOEXEC)
+        .open(name)?;
+    Ok((master, slave))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn pts_name(fd: libc::c_int, name: &mut [libc::c_char]) -> libc::c_int {
+    libc::ptsname_r(fd, name.as_mut_ptr(), name.len())
+}
+
+/// pts_name() copies the name of the slave end of `fd` into `name`. Elsewhere than Linux there is
+/// no ptsname_r(), and ptsname() is safe enough as the editor opens terminals on one thread.
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+unsafe fn pts_name(fd: libc::c_int, name: &mut [libc::c_char]) -> libc::c_int {
+    let found = libc::ptsname(fd);
+    if found.is_null() {
+        return -1;
+    }
+    libc::strncpy(name.as_mut_ptr(), found, name.len() - 1);
+    0
+}
+
+#[cfg(unix)]
+fn set_size(master: &File, (rows, columns): (u16, u16)) -> io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    let size = libc::winsize { ws_row: rows, ws_col: columns, ws_xpixel: 0, ws_ypixel: 0 };
+    // Safety: the descriptor is open for as long as `master` is, and TIOCSWINSZ only reads `size`.
+    match unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &size) } {
+        -1 => Err(io::Error::last_os_error()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+fn set_size(_: &File, _: (u16, u16)) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::process::Command;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use pty::Pty;
+
+    /// read_until() reads from `pty` until what it has written contains `expected`, giving up
+    /// after a few seconds.
+    fn read_until(pty: &mut Pty, expected: &str) -> String {
+        let start = Instant::now();
+        let mut output = Vec::new();
+        while !String::from_utf8_lossy(&output).contains(expected) && start.elapsed() < Duration::from_secs(5) {
+            output.extend(pty.read());
+            thread::sleep(Duration::from_millis(1));
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    }
+
+    #[test]
+    fn run_a_program_on_a_pseudo_terminal() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("stty size; test -t 0 && echo tty; read line; echo \"got $line\"");
+        let mut pty = Pty::spawn(command, (30, 100)).unwrap();
+
+        assert!(read_until(&mut pty, "tty").contains("30 100\r\ntty\r\n"));
+        pty.write(b"hello\r").unwrap();
+        assert!(read_until(&mut pty, "got hello").contains("got hello"));
+        while pty.status().is_none() {
+            pty.read();
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(pty.status().unwrap().success());
+        assert!(!pty.kill());
+    }
+}
@@ -0,0 +1,175 @@
+use std::ops::Range;
+
+use GapBuffer;
+
+/// Rectangles. A rectangle is the block of text between two corners, spanning their lines and
+/// the character columns between them. The rectangle commands use the point and the mark as
+/// the corners.
+impl GapBuffer {
+    /// rectangle() returns the part of each line inside the rectangle with corners at the byte
+    /// indices `a` and `b`. Lines too short to reach the rectangle give an empty range at their end.
+    pub fn rectangle(&self, a: usize, b: usize) -> Vec<Range<usize>> {
+        let (first_line, last_line, left, right) = self.rectangle_bounds(a, b);
+
+        (first_line..=last_line)
+            .map(|line| self.column_offset(line, left).0..self.column_offset(line, right).0)
+            .collect()
+    }
+
+    /// copy_rect() returns the text of each line of the rectangle between the point and the mark.
+    pub fn copy_rect(&self) -> Vec<String> {
+        let (a, b) = self.rectangle_corners();
+
+        self.rectangle(a, b).into_iter().map(|range| self.slice(range).into_owned()).collect()
+    }
+
+    /// insert_rect() inserts `text` on every line of the rectangle between the point and the mark,
+    /// at its left column. Lines too short to reach the column are padded with spaces.
+    /// The point is left after the insertion on the last line and the mark is cleared.
+    pub fn insert_rect(&mut self, text: &str) {
+        let insertions = self.rect_insertions(text);
+        let point = rect_point_after(&insertions);
+
+        for (offset, inserted) in insertions.into_iter().rev() {
+            self.insert_at(offset, inserted.as_bytes());
+        }
+        self.clear_mark();
+        self.point = point;
+    }
+
+    /// delete_rect() removes the rectangle between the point and the mark and returns the text
+    /// removed from each line. The point is left at the top left corner and the mark is cleared.
+    pub fn delete_rect(&mut self) -> Vec<String> {
+        let (a, b) = self.rectangle_corners();
+        let ranges = self.rectangle(a, b);
+        let point = ranges[0].start;
+
+        let mut removed: Vec<String> = ranges
+            .into_iter()
+            .rev()
+            .map(|range| String::from_utf8_lossy(&self.remove_at(range).unwrap_or_default()).into_owned())
+            .collect();
+        removed.reverse();
+        self.clear_mark();
+        self.point = point;
+
+        removed
+    }
+
+    /// rect_insertions() returns where `insert_rect()` inserts on each line and what, in buffer order.
+    pub(crate) fn rect_insertions(&self, text: &str) -> Vec<(usize, String)> {
+        let (a, b) = self.rectangle_corners();
+        let (first_line, last_line, left, _) = self.rectangle_bounds(a, b);
+
+        (first_line..=last_line)
+            .map(|line| {
+                let (offset, missing) = self.column_offset(line, left);
+                (offset, " ".repeat(missing) + text)
+            })
+            .collect()
+    }
+
+    /// char_column() returns the number of characters between the start of the line and `offset`.
+    pub fn char_column(&self, offset: usize) -> usize {
+        let (line, _) = self.offset_to_line_col(offset);
+        let start = self.line_to_offset(line).unwrap();
+
+        (start..offset).filter(|index| self.is_char_boundary(*index)).count()
+    }
+
+    fn rectangle_corners(&self) -> (usize, usize) {
+        (self.point, self.get_mark().unwrap_or(self.point))
+    }
+
+    /// rectangle_bounds() returns the first and last lines and the left and right character
+    /// columns of the rectangle with corners at `a` and `b`.
+    fn rectangle_bounds(&self, a: usize, b: usize) -> (usize, usize, usize, usize) {
+        let (line_a, _) = self.offset_to_line_col(a);
+        let (line_b, _) = self.offset_to_line_col(b);
+        let (column_a, column_b) = (self.char_column(a), self.char_column(b));
+
+        (line_a.min(line_b), line_a.max(line_b), column_a.min(column_b), column_a.max(column_b))
+    }
+
+    /// column_offset() returns the byte index of the character `column` on `line`, clamped to the
+    /// end of the line, and how many columns short of `column` the line ends.
+    fn column_offset(&self, line: usize, column: usize) -> (usize, usize) {
+        let start = self.line_to_offset(line).unwrap();
+        let end = self.line_end_offset(line).unwrap();
+
+        let mut offset = start;
+        for reached in 0..column {
+            if offset == end {
+                return (end, column - reached);
+            }
+            offset = self.next_char_boundary(offset).unwrap_or(end);
+        }
+
+        (offset, 0)
+    }
+}
+
+/// rect_point_after() returns where the point ends up after `insertions` are made: after the
+/// last one, moved along by everything inserted before it.
+pub(crate) fn rect_point_after(insertions: &[(usize, String)]) -> usize {
+    let inserted: usize = insertions.iter().map(|(_, text)| text.len()).sum();
+
+    insertions.last().map_or(0, |(offset, _)| offset + inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use GapBuffer;
+
+    const TABLE: &str = "ab cd\nef gh\nij kl";
+
+    #[test]
+    fn rectangle_ranges() {
+        let buffer = GapBuffer::from(TABLE.to_string());
+
+        assert_eq!(buffer.rectangle(1, 16), vec![1..4, 7..10, 13..16]);
+        assert_eq!(buffer.rectangle(16, 1), vec![1..4, 7..10, 13..16]);
+        assert_eq!(buffer.rectangle(7, 7), vec![7..7]);
+    }
+
+    #[test]
+    fn rectangle_columns_count_characters() {
+        let buffer = GapBuffer::from("né x\nab\nxyz w".to_string());
+
+        assert_eq!(buffer.char_column(3), 2);
+        assert_eq!(buffer.rectangle(3, 13), vec![3..5, 8..8, 11..13]);
+    }
+
+    #[test]
+    fn copy_a_rectangle() {
+        let mut buffer = GapBuffer::from(TABLE.to_string());
+        buffer.set_mark(2);
+        buffer.set_point(16);
+
+        assert_eq!(buffer.copy_rect(), vec![" c", " g", " k"]);
+    }
+
+    #[test]
+    fn delete_a_rectangle() {
+        let mut buffer = GapBuffer::from(TABLE.to_string());
+        buffer.set_mark(1);
+        buffer.set_point(16);
+
+        assert_eq!(buffer.delete_rect(), vec!["b c", "f g", "j k"]);
+        assert_eq!(buffer.to_string(), "ad\neh\nil");
+        assert_eq!(buffer.get_point(), 1);
+        assert_eq!(buffer.get_mark(), None);
+    }
+
+    #[test]
+    fn insert_a_rectangle() {
+        let mut buffer = GapBuffer::from("abc\nd\nefg".to_string());
+        buffer.set_mark(2);
+        buffer.set_point(8);
+
+        buffer.insert_rect("| ");
+
+        assert_eq!(buffer.to_string(), "ab| c\nd | \nef| g");
+        assert_eq!(buffer.get_point(), 15);
+    }
+}
@@ -0,0 +1,87 @@
+use std::ops::Range;
+
+use regex::bytes::Regex;
+use GapBuffer;
+
+/// Regular expression search over the buffer contents, enabled by the `regex` feature.
+/// Searches borrow the buffer in place when the gap is outside the searched text. Call
+/// `make_contiguous()` first to avoid copying when the gap is in the way.
+impl GapBuffer {
+    /// find_regex() returns the range of the first match of `regex` starting at or after `from`.
+    pub fn find_regex(&self, regex: &Regex, from: usize) -> Option<Range<usize>> {
+        if from > self.len() {
+            return None;
+        }
+
+        let haystack = self.contiguous_bytes(0..self.len());
+        regex.find_at(&haystack, from).map(|found| found.range())
+    }
+
+    /// matches_regex() returns the ranges of every match of `regex` that lies within `range`.
+    /// Text outside `range` is still visible to anchors and word boundaries.
+    pub fn matches_regex(&self, regex: &Regex, range: Range<usize>) -> Vec<Range<usize>> {
+        let end = range.end.min(self.len());
+        let haystack = self.contiguous_bytes(0..end);
+        let mut matches = Vec::new();
+        let mut from = range.start;
+
+        while from <= end {
+            let found = match regex.find_at(&haystack, from) {
+                Some(found) => found,
+                None => break,
+            };
+
+            matches.push(found.range());
+            from = if found.is_empty() { found.end() + 1 } else { found.end() };
+        }
+
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::bytes::Regex;
+    use {buffer_with_gap_at, GapBuffer};
+
+    const TEST_STRING: &str = "let x = 10;\nlet y = 200;\nlet z = x + y;";
+
+    #[test]
+    fn find_a_regex_match() {
+        let regex = Regex::new(r"\d+").unwrap();
+
+        for gap_position in [0, 9, 21, TEST_STRING.len()].iter() {
+            let buffer = buffer_with_gap_at(TEST_STRING, *gap_position);
+
+            assert_eq!(buffer.find_regex(&regex, 0), Some(8..10));
+            assert_eq!(buffer.find_regex(&regex, 9), Some(9..10));
+            assert_eq!(buffer.find_regex(&regex, 11), Some(20..23));
+            assert_eq!(buffer.find_regex(&regex, 23), None);
+        }
+    }
+
+    #[test]
+    fn anchors_see_text_before_from() {
+        let buffer = buffer_with_gap_at(TEST_STRING, 5);
+        let regex = Regex::new(r"(?m)^let").unwrap();
+
+        assert_eq!(buffer.find_regex(&regex, 1), Some(12..15));
+    }
+
+    #[test]
+    fn list_matches_in_a_range() {
+        let buffer = buffer_with_gap_at(TEST_STRING, 16);
+        let regex = Regex::new(r"\b[xyz]\b").unwrap();
+
+        assert_eq!(buffer.matches_regex(&regex, 0..TEST_STRING.len()), vec![4..5, 16..17, 29..30, 33..34, 37..38]);
+        assert_eq!(buffer.matches_regex(&regex, 10..30), vec![16..17, 29..30]);
+    }
+
+    #[test]
+    fn empty_matches_advance() {
+        let buffer = buffer_with_gap_at(TEST_STRING, 3);
+        let regex = Regex::new(r"\d*").unwrap();
+
+        assert_eq!(buffer.matches_regex(&regex, 0..3).len(), 4);
+    }
+}
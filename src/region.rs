@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+use anchor::Affinity;
+use GapBuffer;
+
+/// The mark and region. The mark is an anchor, so it follows the text it was set on, and the
+/// region is the text between the point and the mark.
+impl GapBuffer {
+    /// set_mark() sets the mark at `offset`, replacing any previous mark.
+    /// It will panic if `offset` is greater than the buffer length.
+    pub fn set_mark(&mut self, offset: usize) {
+        match self.mark {
+            Some(mark) => {
+                self.move_anchor(mark, offset);
+            }
+            None => self.mark = Some(self.create_anchor(offset, Affinity::Left)),
+        }
+    }
+
+    /// set_mark_at_point() sets the mark where the point is.
+    pub fn set_mark_at_point(&mut self) {
+        let point = self.point;
+        self.set_mark(point);
+    }
+
+    pub fn get_mark(&self) -> Option<usize> {
+        self.mark.and_then(|mark| self.anchor_offset(mark))
+    }
+
+    pub fn clear_mark(&mut self) {
+        if let Some(mark) = self.mark.take() {
+            self.remove_anchor(mark);
+        }
+    }
+
+    /// region() returns the range between the point and the mark, or `None` if there is no mark.
+    pub fn region(&self) -> Option<Range<usize>> {
+        let mark = self.get_mark()?;
+
+        Some(self.point.min(mark)..self.point.max(mark))
+    }
+
+    /// copy_region() returns the text in the region.
+    pub fn copy_region(&self) -> Option<String> {
+        self.region().map(|region| self.slice(region).into_owned())
+    }
+
+    /// delete_region() removes the text in the region and returns it.
+    /// The point and the mark are both left at the start of the removed text.
+    pub fn delete_region(&mut self) -> Option<String> {
+        let region = self.region()?;
+
+        Some(self.remove_bytes(region))
+    }
+
+    /// swap_point_and_mark() exchanges the point and the mark and returns false if there is no mark.
+    pub fn swap_point_and_mark(&mut self) -> bool {
+        match self.get_mark() {
+            Some(mark) => {
+                let point = self.point;
+                self.set_mark(point);
+                self.point = mark;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use GapBuffer;
+
+    const TEST_STRING: &str = "The quick brown fox";
+
+    #[test]
+    fn no_region_without_a_mark() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+
+        assert_eq!(buffer.get_mark(), None);
+        assert_eq!(buffer.region(), None);
+        assert_eq!(buffer.copy_region(), None);
+        assert_eq!(buffer.delete_region(), None);
+        assert!(!buffer.swap_point_and_mark());
+    }
+
+    #[test]
+    fn the_region_spans_point_and_mark_in_either_order() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(4);
+        buffer.set_mark(9);
+
+        assert_eq!(buffer.region(), Some(4..9));
+        assert_eq!(buffer.copy_region(), Some("quick".to_string()));
+
+        buffer.set_point(15);
+        assert_eq!(buffer.region(), Some(9..15));
+    }
+
+    #[test]
+    fn delete_the_region() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(3);
+        buffer.set_mark(9);
+
+        assert_eq!(buffer.delete_region(), Some(" quick".to_string()));
+        assert_eq!(buffer.to_string(), "The brown fox");
+        assert_eq!(buffer.get_point(), 3);
+        assert_eq!(buffer.get_mark(), Some(3));
+    }
+
+    #[test]
+    fn swap_the_point_and_mark() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(2);
+        buffer.set_mark_at_point();
+        buffer.set_point(12);
+
+        assert!(buffer.swap_point_and_mark());
+        assert_eq!(buffer.get_point(), 2);
+        assert_eq!(buffer.get_mark(), Some(12));
+    }
+
+    #[test]
+    fn the_mark_follows_edits() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_mark(10);
+        buffer.set_point(0);
+
+        buffer.insert_str("See ");
+
+        assert_eq!(buffer.get_mark(), Some(14));
+        assert_eq!(buffer.copy_region(), Some("The quick ".to_string()));
+
+        buffer.clear_mark();
+        assert_eq!(buffer.region(), None);
+    }
+}
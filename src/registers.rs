@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use GapBuffer;
+
+/// The register that every yank and delete also goes to.
+pub const UNNAMED_REGISTER: char = '"';
+
+/// Registers holds text yanked and deleted into named places, as in Vim.
+/// - `"`: The unnamed register, holding the text of the last yank or delete.
+/// - `a` to `z`: Named registers, set only when asked for. Naming one in upper case, `A` to `Z`,
+///   appends to it instead of replacing it.
+/// - `0`: The text of the last yank that did not name a register.
+/// - `1` to `9`: The last nine deletes that did not name a register, most recent in `1`.
+#[derive(Debug, Default)]
+pub struct Registers {
+    registers: HashMap<char, String>,
+}
+
+impl Registers {
+    pub fn new() -> Registers {
+        Registers::default()
+    }
+
+    /// is_register() is true if `name` names a register, in either case.
+    pub fn is_register(name: char) -> bool {
+        name == UNNAMED_REGISTER || name.is_ascii_alphanumeric()
+    }
+
+    /// get() returns the text in register `name`, where an upper case name reads the same register
+    /// as its lower case one.
+    pub fn get(&self, name: char) -> Option<&str> {
+        self.registers.get(&name.to_ascii_lowercase()).map(|text| text.as_str())
+    }
+
+    /// set() puts `text` into register `name`, or appends it if the name is upper case, and makes it
+    /// the unnamed register's text. Returns false if `name` is not a register.
+    pub fn set(&mut self, name: char, text: String) -> bool {
+        if !Registers::is_register(name) {
+            return false;
+        }
+
+        let register = self.registers.entry(name.to_ascii_lowercase()).or_default();
+        if name.is_ascii_uppercase() {
+            register.push_str(&text);
+        } else {
+            *register = text;
+        }
+        let contents = register.clone();
+        self.registers.insert(UNNAMED_REGISTER, contents);
+
+        true
+    }
+
+    /// yank() stores copied text in register `name`, or in the yank register `0` without one.
+    pub fn yank(&mut self, name: Option<char>, text: String) -> bool {
+        self.set(name.unwrap_or('0'), text)
+    }
+
+    /// delete() stores deleted text in register `name`. Without one, the numbered registers shift
+    /// along, dropping `9`, and the text goes in `1`.
+    pub fn delete(&mut self, name: Option<char>, text: String) -> bool {
+        if let Some(name) = name {
+            return self.set(name, text);
+        }
+
+        for number in (1..9).rev() {
+            let from = char::from(b'0' + number);
+            if let Some(shifted) = self.registers.remove(&from) {
+                self.registers.insert(char::from(b'1' + number), shifted);
+            }
+        }
+        self.set('1', text)
+    }
+
+    /// paste() inserts the text in register `name`, or the unnamed register without one, at the
+    /// point of `buffer`. Returns false if the register is empty.
+    pub fn paste(&self, name: Option<char>, buffer: &mut GapBuffer) -> bool {
+        match self.get(name.unwrap_or(UNNAMED_REGISTER)) {
+            Some(text) => {
+                buffer.insert_str(text);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use registers::{Registers, UNNAMED_REGISTER};
+    use GapBuffer;
+
+    #[test]
+    fn named_registers() {
+        let mut registers = Registers::new();
+
+        assert!(registers.set('a', "apple".to_string()));
+        assert_eq!(registers.get('a'), Some("apple"));
+        assert_eq!(registers.get(UNNAMED_REGISTER), Some("apple"));
+        assert_eq!(registers.get('b'), None);
+
+        assert!(!registers.set('%', "nothing".to_string()));
+        assert_eq!(registers.get('%'), None);
+    }
+
+    #[test]
+    fn upper_case_names_append() {
+        let mut registers = Registers::new();
+        registers.set('a', "one".to_string());
+
+        registers.set('A', " two".to_string());
+
+        assert_eq!(registers.get('a'), Some("one two"));
+        assert_eq!(registers.get('A'), Some("one two"));
+        assert_eq!(registers.get(UNNAMED_REGISTER), Some("one two"));
+    }
+
+    #[test]
+    fn yanks_go_to_register_zero() {
+        let mut registers = Registers::new();
+
+        registers.yank(None, "copied".to_string());
+        registers.delete(None, "deleted".to_string());
+
+        assert_eq!(registers.get('0'), Some("copied"));
+        assert_eq!(registers.get(UNNAMED_REGISTER), Some("deleted"));
+    }
+
+    #[test]
+    fn deletes_shift_the_numbered_registers() {
+        let mut registers = Registers::new();
+
+        for number in 0..10 {
+            registers.delete(None, number.to_string());
+        }
+
+        assert_eq!(registers.get('1'), Some("9"));
+        assert_eq!(registers.get('2'), Some("8"));
+        assert_eq!(registers.get('9'), Some("1"));
+    }
+
+    #[test]
+    fn paste_a_register() {
+        let mut registers = Registers::new();
+        let mut buffer = GapBuffer::from("ab".to_string());
+        buffer.set_point(1);
+        registers.yank(Some('q'), "--".to_string());
+        registers.yank(None, "++".to_string());
+
+        assert!(registers.paste(Some('q'), &mut buffer));
+        assert!(registers.paste(None, &mut buffer));
+        assert!(!registers.paste(Some('z'), &mut buffer));
+        assert_eq!(buffer.to_string(), "a--++b");
+    }
+}
@@ -0,0 +1,464 @@
+use std::ops::Range;
+
+use motion::is_word_char;
+use GapBuffer;
+
+/// SearchOptions controls how `GapBuffer::search()` matches a pattern.
+/// - `case_insensitive`: Compare characters using Unicode lowercase folding.
+/// - `whole_word`: Only match when the text either side of the match is not a word character.
+/// - `wrap_around`: Continue searching from the other end of the buffer after reaching one end.
+/// - `backward`: Yield matches before the starting index, closest first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_insensitive: bool,
+    pub whole_word: bool,
+    pub wrap_around: bool,
+    pub backward: bool,
+}
+
+/// Matches iterates over the non-overlapping ranges matching a search pattern, in the order
+/// given by its `SearchOptions`.
+pub struct Matches<'a> {
+    buffer: &'a GapBuffer,
+    pattern: &'a str,
+    options: SearchOptions,
+    origin: usize,
+    cursor: usize,
+    wrapped: bool,
+}
+
+impl<'a> Iterator for Matches<'a> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        if self.pattern.is_empty() {
+            return None;
+        }
+
+        loop {
+            let found = if self.options.backward {
+                let lowest_end = if self.wrapped { self.origin + 1 } else { 0 };
+                self.buffer.match_backward(self.pattern, self.cursor, lowest_end, self.options)
+            } else {
+                let highest_start = if self.wrapped { self.origin } else { self.buffer.len() + 1 };
+                self.buffer.match_forward(self.pattern, self.cursor, highest_start, self.options)
+            };
+
+            match found {
+                Some(range) => {
+                    self.cursor = if self.options.backward { range.start } else { range.end };
+                    return Some(range);
+                }
+                None if self.options.wrap_around && !self.wrapped => {
+                    self.wrapped = true;
+                    self.cursor = if self.options.backward { self.buffer.len() } else { 0 };
+                }
+                None => return None,
+            }
+        }
+    }
+}
+
+/// Substring search over the buffer contents. Both halves of the buffer are searched in place,
+/// and only matches that straddle the gap are compared byte by byte.
+impl GapBuffer {
+    /// find() returns the index of the first match of `pattern` starting at or after `from`.
+    pub fn find(&self, pattern: &str, from: usize) -> Option<usize> {
+        let needle = pattern.as_bytes();
+        if from + needle.len() > self.len() {
+            return None;
+        }
+        if needle.is_empty() {
+            return Some(from);
+        }
+
+        let (front, back) = self.as_slices();
+
+        if from < front.len() {
+            if let Some(index) = position(&front[from..], needle) {
+                return Some(from + index);
+            }
+        }
+
+        let straddle_start = from.max(front.len().saturating_sub(needle.len() - 1));
+        if let Some(index) = (straddle_start..front.len()).find(|index| self.matches_at(*index, needle)) {
+            return Some(index);
+        }
+
+        let back_start = from.max(front.len()) - front.len();
+        position(&back[back_start..], needle).map(|index| front.len() + back_start + index)
+    }
+
+    /// rfind() returns the index of the last match of `pattern` that ends at or before `before`.
+    pub fn rfind(&self, pattern: &str, before: usize) -> Option<usize> {
+        let needle = pattern.as_bytes();
+        let before = before.min(self.len());
+        if needle.len() > before {
+            return None;
+        }
+        if needle.is_empty() {
+            return Some(before);
+        }
+
+        let (front, back) = self.as_slices();
+
+        if before > front.len() {
+            if let Some(index) = rposition(&back[..before - front.len()], needle) {
+                return Some(front.len() + index);
+            }
+        }
+
+        let last_start = before - needle.len();
+        let straddle_start = front.len().saturating_sub(needle.len() - 1);
+        let straddle_end = front.len().min(last_start + 1);
+        if let Some(index) = (straddle_start..straddle_end).rev().find(|index| self.matches_at(*index, needle)) {
+            return Some(index);
+        }
+
+        rposition(&front[..before.min(front.len())], needle)
+    }
+
+    /// search() iterates over the matches of `pattern` starting from `from`.
+    /// See `SearchOptions` for the available matching behaviour.
+    pub fn search<'a>(&'a self, pattern: &'a str, from: usize, options: SearchOptions) -> Matches<'a> {
+        let from = from.min(self.len());
+
+        Matches {
+            buffer: self,
+            pattern,
+            options,
+            origin: from,
+            cursor: from,
+            wrapped: false,
+        }
+    }
+
+    /// match_forward() finds the first match starting at or after `from` and before `highest_start`.
+    fn match_forward(&self, pattern: &str, from: usize, highest_start: usize, options: SearchOptions) -> Option<Range<usize>> {
+        let mut start = from;
+
+        while start < highest_start && start <= self.len() {
+            if !options.case_insensitive {
+                start = self.find(pattern, start)?;
+                if start >= highest_start {
+                    return None;
+                }
+            }
+
+            if let Some(end) = self.match_end(pattern, start, options) {
+                return Some(start..end);
+            }
+
+            start = self.next_char_boundary(start)?;
+        }
+
+        None
+    }
+
+    /// match_backward() finds the last match ending at or before `before` and at or after `lowest_end`.
+    fn match_backward(&self, pattern: &str, before: usize, lowest_end: usize, options: SearchOptions) -> Option<Range<usize>> {
+        let mut start = self.prev_char_boundary(before + 1)?;
+
+        loop {
+            if !options.case_insensitive {
+                start = self.rfind(pattern, (start + pattern.len()).min(before))?;
+            }
+
+            if let Some(end) = self.match_end(pattern, start, options) {
+                if end < lowest_end {
+                    return None;
+                }
+                if end <= before {
+                    return Some(start..end);
+                }
+            }
+
+            start = self.prev_char_boundary(start)?;
+        }
+    }
+
+    /// match_end() returns the end of the match of `pattern` starting at `start`, if there is one.
+    fn match_end(&self, pattern: &str, start: usize, options: SearchOptions) -> Option<usize> {
+        let end = if options.case_insensitive {
+            let mut end = start;
+            let mut haystack = self.chars_at(start);
+
+            for expected in pattern.chars() {
+                let character = haystack.next()?;
+                if !character.to_lowercase().eq(expected.to_lowercase()) {
+                    return None;
+                }
+                end = self.next_char_boundary(end)?;
+            }
+
+            end
+        } else if self.matches_at(start, pattern.as_bytes()) {
+            start + pattern.len()
+        } else {
+            return None;
+        };
+
+        if options.whole_word {
+            let before = self.prev_char_boundary(start).and_then(|index| self.char_at(index));
+            let after = self.char_at(end);
+
+            if before.is_some_and(is_word_char) || after.is_some_and(is_word_char) {
+                return None;
+            }
+        }
+
+        Some(end)
+    }
+
+    /// find_all() returns the index of every non-overlapping match of `pattern`.
+    pub fn find_all(&self, pattern: &str) -> Vec<usize> {
+        let mut matches = Vec::new();
+        let mut from = 0;
+
+        while let Some(index) = self.find(pattern, from) {
+            matches.push(index);
+            from = index + pattern.len().max(1);
+        }
+
+        matches
+    }
+
+    /// replace() replaces the first match of `pattern` at or after `from` with `replacement`
+    /// and returns the range of the inserted text, or `None` if there was no match or the buffer
+    /// is read-only.
+    pub fn replace(&mut self, pattern: &str, replacement: &str, from: usize) -> Option<Range<usize>> {
+        if self.is_read_only() {
+            return None;
+        }
+        let start = self.find(pattern, from)?;
+        self.replace_range(start..start + pattern.len(), replacement);
+
+        Some(start..start + replacement.len())
+    }
+
+    /// replace_all() replaces every non-overlapping match of `pattern` with `replacement` and
+    /// returns how many replacements were made, which is none in a read-only buffer. The point
+    /// follows the text around it as described by `replace_range()`.
+    pub fn replace_all(&mut self, pattern: &str, replacement: &str) -> usize {
+        if pattern.is_empty() || self.is_read_only() {
+            return 0;
+        }
+
+        let matches = self.find_all(pattern);
+
+        for start in matches.iter().rev() {
+            self.replace_range(*start..*start + pattern.len(), replacement);
+        }
+
+        matches.len()
+    }
+
+    fn matches_at(&self, index: usize, needle: &[u8]) -> bool {
+        index + needle.len() <= self.len() && self.bytes_at(index).zip(needle.iter()).all(|(byte, expected)| byte == *expected)
+    }
+}
+
+fn position(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn rposition(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).rposition(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use search::SearchOptions;
+    use {buffer_with_gap_at, GapBuffer};
+
+    const TEST_STRING: &str = "the cat sat on the mat with the hat";
+
+    #[test]
+    fn find_matches_wherever_the_gap_is() {
+        let patterns = ["the", "at", "hat", "t w", "x", "the cat sat on the mat with the hat"];
+
+        for gap_position in 0..=TEST_STRING.len() {
+            let buffer = buffer_with_gap_at(TEST_STRING, gap_position);
+
+            for pattern in patterns.iter() {
+                for from in 0..=TEST_STRING.len() {
+                    let expected = TEST_STRING[from..].find(pattern).map(|index| from + index);
+
+                    assert_eq!(buffer.find(pattern, from), expected, "Pattern {:?} from {} with gap at {}.", pattern, from, gap_position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rfind_matches_wherever_the_gap_is() {
+        let patterns = ["the", "at", "hat", "t w", "x", "the cat sat on the mat with the hat"];
+
+        for gap_position in 0..=TEST_STRING.len() {
+            let buffer = buffer_with_gap_at(TEST_STRING, gap_position);
+
+            for pattern in patterns.iter() {
+                for before in 0..=TEST_STRING.len() {
+                    let expected = TEST_STRING[..before].rfind(pattern);
+
+                    assert_eq!(buffer.rfind(pattern, before), expected, "Pattern {:?} before {} with gap at {}.", pattern, before, gap_position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_an_empty_pattern() {
+        let buffer = buffer_with_gap_at(TEST_STRING, 10);
+
+        assert_eq!(buffer.find("", 4), Some(4));
+        assert_eq!(buffer.rfind("", 4), Some(4));
+        assert_eq!(buffer.find("", TEST_STRING.len() + 1), None);
+    }
+
+    #[test]
+    fn find_all_matches() {
+        let buffer = buffer_with_gap_at(TEST_STRING, 17);
+
+        assert_eq!(buffer.find_all("the"), vec![0, 15, 28]);
+        assert_eq!(buffer.find_all("dog"), Vec::<usize>::new());
+    }
+
+    fn search(text: &str, pattern: &str, from: usize, options: SearchOptions) -> Vec<std::ops::Range<usize>> {
+        let mut buffer = GapBuffer::from(text.to_string());
+        buffer.set_point(text.len() / 2);
+        buffer.insert_bytes(Vec::new());
+
+        buffer.search(pattern, from, options).collect()
+    }
+
+    #[test]
+    fn search_forward() {
+        let options = SearchOptions::default();
+
+        assert_eq!(search(TEST_STRING, "the", 0, options), vec![0..3, 15..18, 28..31]);
+        assert_eq!(search(TEST_STRING, "the", 1, options), vec![15..18, 28..31]);
+        assert_eq!(search(TEST_STRING, "", 0, options), Vec::<std::ops::Range<usize>>::new());
+    }
+
+    #[test]
+    fn search_backward() {
+        let options = SearchOptions {
+            backward: true,
+            ..SearchOptions::default()
+        };
+
+        assert_eq!(search(TEST_STRING, "the", TEST_STRING.len(), options), vec![28..31, 15..18, 0..3]);
+        assert_eq!(search(TEST_STRING, "the", 30, options), vec![15..18, 0..3]);
+    }
+
+    #[test]
+    fn search_ignoring_case() {
+        let options = SearchOptions {
+            case_insensitive: true,
+            ..SearchOptions::default()
+        };
+
+        assert_eq!(search("Straße STRASSE straße", "STRAßE", 0, options), vec![0..7, 16..23]);
+        assert_eq!(search("The THE the", "the", 0, options), vec![0..3, 4..7, 8..11]);
+
+        let backward = SearchOptions {
+            backward: true,
+            ..options
+        };
+        assert_eq!(search("The THE the", "the", 11, backward), vec![8..11, 4..7, 0..3]);
+    }
+
+    #[test]
+    fn search_ignoring_case_over_invalid_utf8() {
+        let options = SearchOptions {
+            case_insensitive: true,
+            ..SearchOptions::default()
+        };
+        let mut buffer = GapBuffer::new();
+        buffer.insert_bytes(b"\xffBc".to_vec());
+
+        let matches: Vec<std::ops::Range<usize>> = buffer.search("\u{fffd}b", 0, options).collect();
+        assert_eq!(matches, vec![0..2]);
+    }
+
+    #[test]
+    fn search_whole_words() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+
+        assert_eq!(search("cat concat cat_s cats cat", "cat", 0, options), vec![0..3, 22..25]);
+
+        let backward = SearchOptions {
+            backward: true,
+            ..options
+        };
+        assert_eq!(search("cat concat cat_s cats cat", "cat", 25, backward), vec![22..25, 0..3]);
+    }
+
+    #[test]
+    fn search_wrapping_around() {
+        let forward = SearchOptions {
+            wrap_around: true,
+            ..SearchOptions::default()
+        };
+        let backward = SearchOptions {
+            backward: true,
+            ..forward
+        };
+
+        assert_eq!(search(TEST_STRING, "the", 10, forward), vec![15..18, 28..31, 0..3]);
+        assert_eq!(search(TEST_STRING, "the", 20, backward), vec![15..18, 0..3, 28..31]);
+        assert_eq!(search(TEST_STRING, "at", 0, forward).len(), 4);
+    }
+
+    #[test]
+    fn replace_the_next_match() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+
+        assert_eq!(buffer.replace("the", "a", 5), Some(15..16));
+        assert_eq!(buffer.to_string(), "the cat sat on a mat with the hat");
+        assert_eq!(buffer.replace("dog", "a", 0), None);
+    }
+
+    #[test]
+    fn replace_every_match() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(20);
+
+        assert_eq!(buffer.replace_all("at", "og"), 4);
+        assert_eq!(buffer.to_string(), "the cog sog on the mog with the hog");
+        assert_eq!(buffer.get_point(), 20);
+    }
+
+    #[test]
+    fn replace_every_match_keeps_the_point_on_its_text() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_point(19);
+
+        assert_eq!(buffer.replace_all("the", "a"), 3);
+        assert_eq!(buffer.to_string(), "a cat sat on a mat with a hat");
+        assert_eq!(buffer.get_point(), 15);
+        assert_eq!(buffer.to_string()[15..], *"mat with a hat");
+    }
+
+    #[test]
+    fn replace_with_an_empty_pattern_does_nothing() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+
+        assert_eq!(buffer.replace_all("", "x"), 0);
+        assert_eq!(buffer.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn replace_nothing_in_a_read_only_buffer() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_read_only(true);
+
+        assert_eq!(buffer.replace_all("at", "og"), 0);
+        assert_eq!(buffer.replace("the", "a", 0), None);
+        assert_eq!(buffer.to_string(), TEST_STRING);
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+
+/// ShellOutput is what a shell command wrote and how it ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub status: ExitStatus,
+}
+
+impl ShellOutput {
+    /// failure() describes how the command failed, with the first line it wrote to stderr, or
+    /// returns None if it succeeded.
+    pub fn failure(&self) -> Option<String> {
+        if self.status.success() {
+            return None;
+        }
+
+        let status = match self.status.code() {
+            Some(code) => format!("Shell command exited with code {}", code),
+            None => "Shell command was killed by a signal".to_string(),
+        };
+        match self.stderr.lines().find(|line| !line.trim().is_empty()) {
+            Some(line) => Some(format!("{}: {}", status, line.trim())),
+            None => Some(status),
+        }
+    }
+}
+
+/// shell() is the command that runs a command line: `sh -c` or, on Windows, `cmd /C`.
+pub fn shell(command_line: &str) -> Command {
+    #[cfg(windows)]
+    let (program, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (program, flag) = ("sh", "-c");
+
+    let mut command = Command::new(program);
+    command.arg(flag).arg(command_line);
+    command
+}
+
+/// run_shell_command() runs `command_line` in `directory` and waits for it to finish, giving it
+/// `input` on stdin. The input is written from another thread, so a command that writes a lot
+/// before it has read everything cannot deadlock. Output that is not valid UTF-8 is replaced
+/// with `U+FFFD REPLACEMENT CHARACTER`.
+pub fn run_shell_command(command_line: &str, input: &str, directory: &Path) -> io::Result<ShellOutput> {
+    let mut child = shell(command_line)
+        .current_dir(directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_string();
+    // A command that exits without reading all of its input closes the pipe, which is not an error.
+    let writer = thread::spawn(move || {
+        let _ = stdin.write_all(input.as_bytes());
+    });
+    let output = child.wait_with_output()?;
+    let _ = writer.join();
+
+    Ok(ShellOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        status: output.status,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::env;
+
+    use shell::run_shell_command;
+
+    #[test]
+    fn filter_text_through_a_command() {
+        let output = run_shell_command("sort | tr a-z A-Z", "pear\napple\n", &env::temp_dir()).unwrap();
+
+        assert_eq!(output.stdout, "APPLE\nPEAR\n");
+        assert_eq!(output.failure(), None);
+    }
+
+    #[test]
+    fn describe_a_failed_command() {
+        let output = run_shell_command("echo >&2; echo 'no such file' >&2; exit 3", "ignored", &env::temp_dir()).unwrap();
+        assert_eq!(output.failure(), Some("Shell command exited with code 3: no such file".to_string()));
+
+        let quiet = run_shell_command("false", "", &env::temp_dir()).unwrap();
+        assert_eq!(quiet.failure(), Some("Shell command exited with code 1".to_string()));
+    }
+}
@@ -0,0 +1,134 @@
+use std::ops::Range;
+
+use property::{PropertyId, TextProperty};
+use GapBuffer;
+
+/// The group of the text properties keeping signs on their lines.
+pub const SIGNS_GROUP: &str = "signs";
+
+/// Sign is a mark drawn in the gutter beside a run of lines, such as lines changed since the
+/// last commit.
+/// - `lines`: The lines it is beside. An empty range is the place between two lines, and is drawn
+///   beside the line after it.
+/// - `text`: What is drawn, one column wide.
+/// - `style`: The theme style it is drawn in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sign {
+    pub lines: Range<usize>,
+    pub text: char,
+    pub style: String,
+}
+
+/// Signs holds the signs of a buffer. Each covers its lines with a text property so it stays on
+/// them as the buffer changes, kept with who put it there and how it is drawn.
+#[derive(Debug, Default)]
+pub(crate) struct Signs {
+    entries: Vec<(PropertyId, String, char, String)>,
+}
+
+/// Signs are kept by who put them there, such as the git gutter, so each can replace its own.
+impl GapBuffer {
+    /// set_signs() replaces the signs `reporter` put in the gutter before with `signs`. Lines past
+    /// the end of the buffer are cut short at it.
+    pub fn set_signs(&mut self, reporter: &str, signs: Vec<Sign>) {
+        self.clear_signs(reporter);
+
+        let last = self.line_count() - 1;
+        for sign in signs {
+            let start = self.line_to_offset(sign.lines.start.min(last)).unwrap();
+            let end = match sign.lines.is_empty() {
+                true => start,
+                false => self.line_end_offset((sign.lines.end - 1).min(last)).unwrap(),
+            };
+            let id = self.add_property(start..end, TextProperty { group: SIGNS_GROUP.to_string(), ..TextProperty::default() });
+            self.signs.entries.push((id, reporter.to_string(), sign.text, sign.style));
+        }
+    }
+
+    /// clear_signs() removes the signs `reporter` put in the gutter and returns how many there were.
+    pub fn clear_signs(&mut self, reporter: &str) -> usize {
+        let (cleared, kept): (Vec<_>, Vec<_>) = self.signs.entries.drain(..).partition(|(_, by, _, _)| by == reporter);
+        self.signs.entries = kept;
+
+        for (id, _, _, _) in cleared.iter() {
+            self.remove_property(*id);
+        }
+        cleared.len()
+    }
+
+    /// has_signs() reports whether there are any signs to make room for in the gutter.
+    pub fn has_signs(&self) -> bool {
+        !self.signs.entries.is_empty()
+    }
+
+    /// signs() returns every sign in the order of its lines.
+    pub fn signs(&self) -> Vec<Sign> {
+        let mut signs: Vec<Sign> = self
+            .signs
+            .entries
+            .iter()
+            .filter_map(|(id, _, text, style)| {
+                Some(Sign {
+                    lines: self.sign_lines(self.property_range(*id)?),
+                    text: *text,
+                    style: style.clone(),
+                })
+            })
+            .collect();
+        signs.sort_by_key(|sign| (sign.lines.start, sign.lines.end));
+
+        signs
+    }
+
+    /// line_sign() returns the text and style of the sign drawn beside `line`, if it has one.
+    /// Where signs meet on a line, the one put there first is drawn.
+    pub fn line_sign(&self, line: usize) -> Option<(char, &str)> {
+        self.signs.entries.iter().find_map(|(id, _, text, style)| {
+            let lines = self.sign_lines(self.property_range(*id)?);
+            let beside = lines.contains(&line) || (lines.is_empty() && lines.start == line);
+            if beside {
+                Some((*text, style.as_str()))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// sign_lines() is the lines the span of a sign's property is on.
+    fn sign_lines(&self, span: Range<usize>) -> Range<usize> {
+        let (start, _) = self.offset_to_line_col(span.start);
+        match span.is_empty() {
+            true => start..start,
+            false => start..self.offset_to_line_col(span.end).0 + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sign::Sign;
+    use GapBuffer;
+
+    fn sign(lines: std::ops::Range<usize>, text: char) -> Sign {
+        Sign { lines, text, style: "git.added".to_string() }
+    }
+
+    #[test]
+    fn signs_stay_beside_their_lines() {
+        let mut buffer = GapBuffer::from("one\ntwo\nthree\nfour\n".to_string());
+        buffer.set_signs("git", vec![sign(1..3, '+'), sign(4..4, '-')]);
+
+        assert!(buffer.has_signs());
+        let beside: Vec<Option<char>> = (0..5).map(|line| buffer.line_sign(line).map(|(text, _)| text)).collect();
+        assert_eq!(beside, vec![None, Some('+'), Some('+'), None, Some('-')]);
+
+        buffer.set_point(0);
+        buffer.insert_str("zero\n");
+        assert_eq!(buffer.signs(), vec![sign(2..4, '+'), sign(5..5, '-')]);
+
+        assert_eq!(buffer.clear_signs("other"), 0);
+        assert_eq!(buffer.clear_signs("git"), 2);
+        assert!(!buffer.has_signs());
+        assert_eq!(buffer.line_sign(2), None);
+    }
+}
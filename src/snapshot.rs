@@ -0,0 +1,179 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use diff::diff;
+use GapBuffer;
+
+/// Snapshot is the text of a buffer as it was at one moment, which stays as it was however the
+/// buffer changes after. Clones share the text, so a snapshot is cheap to keep, compare and hand
+/// to another thread.
+/// - `version`: How many times the buffer had changed when it was taken. The buffer has not
+///   changed since if its version is still the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    text: Arc<str>,
+    version: u64,
+}
+
+impl Snapshot {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+/// Change is one edit of the text of a snapshot: the bytes in `range` are replaced with `text`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// Snapshots let what the buffer was be kept and compared with what it is, as for deciding
+/// whether to save it or sending the edits made to someone else's copy.
+impl GapBuffer {
+    /// version() counts the changes made to the accessible text, by edits and by narrowing and
+    /// widening. It only goes up, so a buffer whose version is what it was has the same text.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// snapshot() returns a copy of the accessible text as it is now.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            text: Arc::from(self.to_string()),
+            version: self.version,
+        }
+    }
+
+    /// has_changed_since() reports whether the contents have changed since `snapshot` of the
+    /// buffer was taken, without comparing the text.
+    pub fn has_changed_since(&self, snapshot: &Snapshot) -> bool {
+        self.version != snapshot.version
+    }
+
+    /// diff() returns the edits that turn the text of `snapshot` into the accessible text as it
+    /// is now, in order. Their ranges are into the snapshot's text, so they are made from the last
+    /// to the first. The lines that changed are found first, and then which characters of those
+    /// did, so the edits are as small as can be found quickly.
+    pub fn diff(&self, snapshot: &Snapshot) -> Vec<Change> {
+        if !self.has_changed_since(snapshot) {
+            return Vec::new();
+        }
+        let old: &str = &snapshot.text;
+        let new = self.to_string();
+        let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+        let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+        let line_offsets = |lines: &[&str]| -> Vec<usize> {
+            let mut offsets = vec![0];
+            offsets.extend(lines.iter().scan(0, |offset, line| {
+                *offset += line.len();
+                Some(*offset)
+            }));
+            offsets
+        };
+        let (old_offsets, new_offsets) = (line_offsets(&old_lines), line_offsets(&new_lines));
+
+        let mut changes = Vec::new();
+        for hunk in diff(&old_lines, &new_lines) {
+            let old_start = old_offsets[hunk.old.start];
+            let old_text = &old[old_start..old_offsets[hunk.old.end]];
+            let new_text = &new[new_offsets[hunk.new.start]..new_offsets[hunk.new.end]];
+            changes.extend(character_changes(old_text, new_text).into_iter().map(|change| Change {
+                range: old_start + change.range.start..old_start + change.range.end,
+                text: change.text,
+            }));
+        }
+
+        changes
+    }
+}
+
+/// character_changes() returns the edits that turn `old` into `new`, changing as few characters
+/// as it can.
+fn character_changes(old: &str, new: &str) -> Vec<Change> {
+    let old_chars: Vec<char> = old.chars().collect();
+    let new_chars: Vec<char> = new.chars().collect();
+    let offsets = |text: &str| -> Vec<usize> { text.char_indices().map(|(offset, _)| offset).chain(Some(text.len())).collect() };
+    let (old_offsets, new_offsets) = (offsets(old), offsets(new));
+
+    diff(&old_chars, &new_chars)
+        .into_iter()
+        .map(|hunk| Change {
+            range: old_offsets[hunk.old.start]..old_offsets[hunk.old.end],
+            text: new[new_offsets[hunk.new.start]..new_offsets[hunk.new.end]].to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use snapshot::{Change, Snapshot};
+    use GapBuffer;
+
+    /// apply() makes the edits `changes` to the text of `snapshot`.
+    fn apply(snapshot: &Snapshot, changes: &[Change]) -> String {
+        let mut text = snapshot.text().to_string();
+        for change in changes.iter().rev() {
+            text.replace_range(change.range.clone(), &change.text);
+        }
+        text
+    }
+
+    #[test]
+    fn snapshots_keep_the_text_they_were_taken_with() {
+        let mut buffer = GapBuffer::from("The quick brown fox\njumps over\n".to_string());
+        let snapshot = buffer.snapshot();
+        assert!(!buffer.has_changed_since(&snapshot));
+        assert_eq!(buffer.snapshot(), snapshot);
+
+        buffer.set_point(4);
+        buffer.insert_str("very ");
+        assert_eq!(snapshot.text(), "The quick brown fox\njumps over\n");
+        assert!(buffer.has_changed_since(&snapshot));
+        assert_eq!(buffer.version(), snapshot.version() + 1);
+
+        buffer.set_read_only(true);
+        buffer.insert_str("ignored");
+        assert_eq!(buffer.version(), snapshot.version() + 1);
+    }
+
+    #[test]
+    fn diff_finds_the_few_edits_between_versions() {
+        let mut buffer = GapBuffer::from("The quick brown fox\njumps over\nthe lazy dög\n".to_string());
+        let snapshot = buffer.snapshot();
+        assert_eq!(buffer.diff(&snapshot), vec![]);
+
+        buffer.replace_range(10..15, "red");
+        buffer.set_point(0);
+        buffer.insert_str("# ");
+        let end = buffer.len();
+        buffer.replace_range(end - 4..end - 2, "o");
+
+        let changes = buffer.diff(&snapshot);
+        let change = |range: std::ops::Range<usize>, text: &str| Change { range, text: text.to_string() };
+        assert_eq!(changes, vec![change(0..0, "# "), change(10..11, ""), change(12..15, "ed"), change(41..43, "o")]);
+        assert_eq!(apply(&snapshot, &changes), buffer.to_string());
+    }
+
+    #[test]
+    fn narrowing_and_widening_change_the_version() {
+        let mut buffer = GapBuffer::from("one\ntwo\nthree\n".to_string());
+        let snapshot = buffer.snapshot();
+
+        buffer.narrow(4..8);
+        assert!(buffer.has_changed_since(&snapshot));
+        let change = |range: std::ops::Range<usize>, text: &str| Change { range, text: text.to_string() };
+        assert_eq!(buffer.diff(&snapshot), vec![change(0..4, ""), change(8..14, "")]);
+
+        let narrowed = buffer.snapshot();
+        buffer.widen();
+        assert!(buffer.has_changed_since(&narrowed));
+        assert_eq!(apply(&narrowed, &buffer.diff(&narrowed)), "one\ntwo\nthree\n");
+        assert!(!buffer.widen());
+        assert!(!buffer.has_changed_since(&buffer.snapshot()));
+    }
+}
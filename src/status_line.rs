@@ -0,0 +1,219 @@
+use diagnostic::Severity;
+use editor::Editor;
+use vi::Mode;
+use window::WindowId;
+
+/// The status line format used unless another is configured.
+pub const DEFAULT_STATUS_FORMAT: &str = "-%*- %15b  L%l C%c  %p  (%m)  %d";
+
+/// StatusInfo is what a status line can show about the current buffer.
+/// - `name`, `file`: The buffer name, and the file it visits if any.
+/// - `modified`, `read_only`: The buffer's flags.
+/// - `line`, `column`: Where the point is, counting from one and zero like Emacs does. The column
+///   is the screen column, with tabs expanded.
+/// - `position`: How far through the buffer the view is, as `Top`, `Bot`, `All` or a percentage.
+/// - `mode`: How keys are being read: `Emacs`, or the vi mode. `Narrow` follows it while the
+///   buffer is narrowed, as in Emacs.
+/// - `errors`, `warnings`: How many diagnostics of each severity the buffer has.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusInfo {
+    pub name: String,
+    pub file: Option<String>,
+    pub modified: bool,
+    pub read_only: bool,
+    pub line: usize,
+    pub column: usize,
+    pub position: String,
+    pub mode: String,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl StatusInfo {
+    /// of() describes the current buffer of `editor`, or returns None if there is none.
+    pub fn of(editor: &Editor) -> Option<StatusInfo> {
+        StatusInfo::of_window(editor, editor.windows().selected_id())
+    }
+
+    /// of_window() describes the buffer shown in window `id`, as the window shows it.
+    pub fn of_window(editor: &Editor, id: WindowId) -> Option<StatusInfo> {
+        let window = editor.windows().get(id)?;
+        let buffer = editor.buffers().get(window.buffer())?;
+        let point = editor.window_point(id)?;
+        let (line, _) = buffer.offset_to_line_col(point);
+        let visible = window.view().visible_lines(buffer);
+        let line_count = buffer.line_count();
+
+        let position = match (visible.start == 0, visible.end >= line_count) {
+            (true, true) => "All".to_string(),
+            (true, false) => "Top".to_string(),
+            (false, true) => "Bot".to_string(),
+            (false, false) => format!("{}%", buffer.line_to_offset(visible.start).unwrap() * 100 / buffer.len()),
+        };
+        let mode = match editor.buffer_vi_mode(window.buffer()) {
+            None => "Emacs",
+            Some(Mode::Normal) => "Normal",
+            Some(Mode::Insert) => "Insert",
+            Some(Mode::Visual) => "Visual",
+        };
+
+        Some(StatusInfo {
+            name: buffer.name().to_string(),
+            file: buffer.path().map(|path| path.display().to_string()),
+            modified: buffer.is_modified(),
+            read_only: buffer.is_read_only(),
+            line: line + 1,
+            column: window.view().column(buffer, point),
+            position,
+            mode: if buffer.is_narrowed() { format!("{} Narrow", mode) } else { mode.to_string() },
+            errors: buffer.diagnostic_count(Severity::Error),
+            warnings: buffer.diagnostic_count(Severity::Warning),
+        })
+    }
+}
+
+/// StatusLine lays out a status line from a format string, in the style of Emacs mode lines. Each
+/// `%` is followed by an optional minimum width and one of:
+/// - `b`: The buffer name.
+/// - `f`: The visited file, or the buffer name.
+/// - `*`: `%%` if read-only, `**` if modified, otherwise `--`.
+/// - `+`: `**` if modified, `%%` if read-only, otherwise `--`.
+/// - `l`, `c`: The point's line and column.
+/// - `p`: How far through the buffer the view is.
+/// - `m`: The mode.
+/// - `d`: The errors and warnings in the buffer, like `E2 W1`, or nothing if there are none.
+/// - `%`: A `%`.
+///
+/// Anything else is shown as it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine {
+    format: String,
+}
+
+impl Default for StatusLine {
+    fn default() -> StatusLine {
+        StatusLine::new(DEFAULT_STATUS_FORMAT)
+    }
+}
+
+impl StatusLine {
+    pub fn new(format: &str) -> StatusLine {
+        StatusLine {
+            format: format.to_string(),
+        }
+    }
+
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+
+    pub fn set_format(&mut self, format: &str) {
+        self.format = format.to_string();
+    }
+
+    /// render() fills in the format from `info`.
+    pub fn render(&self, info: &StatusInfo) -> String {
+        let mut line = String::new();
+        let mut characters = self.format.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            if character != '%' {
+                line.push(character);
+                continue;
+            }
+
+            let mut width = 0;
+            while let Some(digit) = characters.peek().and_then(|digit| digit.to_digit(10)) {
+                width = width * 10 + digit as usize;
+                characters.next();
+            }
+
+            let field = match characters.next() {
+                Some('b') => info.name.clone(),
+                Some('f') => info.file.clone().unwrap_or_else(|| info.name.clone()),
+                Some('*') if info.read_only => "%%".to_string(),
+                Some('+') if info.modified => "**".to_string(),
+                Some('*') | Some('+') if info.read_only => "%%".to_string(),
+                Some('*') | Some('+') if info.modified => "**".to_string(),
+                Some('*') | Some('+') => "--".to_string(),
+                Some('l') => info.line.to_string(),
+                Some('c') => info.column.to_string(),
+                Some('p') => info.position.clone(),
+                Some('m') => info.mode.clone(),
+                Some('d') => match (info.errors, info.warnings) {
+                    (0, 0) => String::new(),
+                    (errors, 0) => format!("E{}", errors),
+                    (0, warnings) => format!("W{}", warnings),
+                    (errors, warnings) => format!("E{} W{}", errors, warnings),
+                },
+                Some('%') => "%".to_string(),
+                Some(other) => format!("%{}", other),
+                None => "%".to_string(),
+            };
+            line.push_str(&format!("{:width$}", field, width = width));
+        }
+
+        line
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use editor::Editor;
+    use status_line::{StatusInfo, StatusLine};
+
+    fn info() -> StatusInfo {
+        StatusInfo {
+            name: "main.rs".to_string(),
+            file: Some("src/main.rs".to_string()),
+            modified: true,
+            read_only: false,
+            line: 12,
+            column: 4,
+            position: "Top".to_string(),
+            mode: "Emacs".to_string(),
+            errors: 0,
+            warnings: 2,
+        }
+    }
+
+    #[test]
+    fn render_formats() {
+        let test_cases = [
+            ("the default format", "-%*- %15b  L%l C%c  %p  (%m)", "-**- main.rs          L12 C4  Top  (Emacs)"),
+            ("the file name", "%f:%l:%c", "src/main.rs:12:4"),
+            ("a literal percent", "100%% %p", "100% Top"),
+            ("an unknown construct", "%q", "%q"),
+            ("the diagnostics", "[%d]", "[W2]"),
+        ];
+
+        for (name, format, expected) in test_cases.iter() {
+            let line = StatusLine::new(format).render(&info());
+
+            assert_eq!(line, *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn flags_differ_in_priority() {
+        let mut info = info();
+        info.read_only = true;
+
+        assert_eq!(StatusLine::new("%* %+").render(&info), "%% **");
+        info.modified = false;
+        assert_eq!(StatusLine::new("%* %+").render(&info), "%% %%");
+    }
+
+    #[test]
+    fn describe_the_current_buffer() {
+        let mut editor = Editor::new();
+        editor.execute("insert-char", "one\ntwo").unwrap();
+
+        let info = StatusInfo::of(&editor).unwrap();
+
+        assert_eq!((info.line, info.column), (2, 3));
+        assert_eq!(info.position, "All");
+        assert_eq!(info.mode, "Emacs");
+        assert!(info.modified);
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Range;
+use std::path::Path;
+
+use GapBuffer;
+
+pub use self::rules::{RuleHighlighter, SyntaxError, SyntaxRules};
+#[cfg(feature = "tree-sitter")]
+pub use self::tree::{Grammar, TreeSitterHighlighter, GRAMMARS};
+
+pub mod rules;
+#[cfg(feature = "tree-sitter")]
+pub mod tree;
+
+/// The prefix of the theme styles syntax is drawn in, as in `syntax.keyword`.
+pub const SYNTAX_STYLE_PREFIX: &str = "syntax.";
+
+/// StyleSpan is a part of a buffer drawn in a named style of the theme, such as
+/// `syntax.keyword`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleSpan {
+    pub range: Range<usize>,
+    pub style: String,
+}
+
+/// Highlighter works out which styles the text of a buffer is drawn in from its syntax.
+pub trait Highlighter {
+    /// update() catches up with the changes made to `buffer` since it was last called. Frontends
+    /// call it before drawing.
+    fn update(&mut self, buffer: &GapBuffer);
+
+    /// line_spans() returns the styled parts of `line` of `buffer`, cut to the line, in the order
+    /// they are drawn: where spans overlap, the later one is drawn over the earlier.
+    fn line_spans(&self, buffer: &GapBuffer, line: usize) -> Vec<StyleSpan>;
+}
+
+/// in_string_or_comment() returns a test of whether an offset in `buffer` is in a string or a
+/// comment, as far as `highlighter` knows, which is not at all without one. It keeps the spans of
+/// the last line it was asked about, as delimiter matching asks about offsets in order.
+pub fn in_string_or_comment<'a>(buffer: &'a GapBuffer, highlighter: Option<&'a dyn Highlighter>) -> impl FnMut(usize) -> bool + 'a {
+    let mut literals: Option<(usize, Vec<Range<usize>>)> = None;
+
+    move |offset| {
+        let highlighter = match highlighter {
+            Some(highlighter) => highlighter,
+            None => return false,
+        };
+        let (line, _) = buffer.offset_to_line_col(offset);
+        if literals.as_ref().is_none_or(|(cached, _)| *cached != line) {
+            let spans = highlighter.line_spans(buffer, line).into_iter();
+            literals = Some((line, spans.filter(|span| is_literal_style(&span.style)).map(|span| span.range).collect()));
+        }
+
+        literals.iter().flat_map(|(_, ranges)| ranges.iter()).any(|range| range.contains(&offset))
+    }
+}
+
+/// is_literal_style() reports whether `style` is that of a string or comment, or a scope in one.
+fn is_literal_style(style: &str) -> bool {
+    ["string", "comment"].iter().any(|scope| {
+        let scope = format!("{}{}", SYNTAX_STYLE_PREFIX, scope);
+        style == scope || style.starts_with(&format!("{}.", scope))
+    })
+}
+
+/// highlighter_for() returns a highlighter for the language of the file at `path`, attached to
+/// `buffer` so it follows its changes, or None if there is none for the language. A tree-sitter
+/// grammar is used where there is one, and otherwise the first of `rules`, then of the built-in
+/// rules, for the file.
+pub fn highlighter_for(buffer: &mut GapBuffer, path: &Path, rules: &[SyntaxRules]) -> Option<Box<dyn Highlighter>> {
+    #[cfg(feature = "tree-sitter")]
+    {
+        if let Some(grammar) = Grammar::for_path(path) {
+            return Some(Box::new(TreeSitterHighlighter::new(buffer, grammar)));
+        }
+    }
+
+    let rules = rules.iter().find(|rules| rules.matches(path)).cloned().or_else(|| SyntaxRules::for_path(path))?;
+    Some(Box::new(RuleHighlighter::new(buffer, rules)))
+}
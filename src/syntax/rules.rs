@@ -0,0 +1,490 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use toml::{Table, Value};
+
+use events::EditEvent;
+use syntax::{Highlighter, StyleSpan, SYNTAX_STYLE_PREFIX};
+use GapBuffer;
+
+const C_RULES: &str = r##"
+name = "c"
+extensions = ["c", "h", "cc", "cpp", "hpp"]
+line-comments = ["//"]
+block-comment = ["/*", "*/"]
+strings = ['"', "'"]
+escape = "\\"
+keywords = [
+    "break", "case", "class", "const", "continue", "default", "do", "else", "enum", "extern", "for",
+    "goto", "if", "inline", "namespace", "private", "protected", "public", "return", "sizeof",
+    "static", "struct", "switch", "template", "typedef", "union", "using", "virtual", "volatile",
+    "while", "#include", "#define", "#if", "#ifdef", "#ifndef", "#endif", "#else",
+]
+types = ["bool", "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "size_t"]
+constants = ["NULL", "true", "false", "nullptr"]
+"##;
+
+const PYTHON_RULES: &str = r##"
+name = "python"
+extensions = ["py", "pyw"]
+line-comments = ["#"]
+strings = ['"""', "'''", '"', "'"]
+multiline-strings = ['"""', "'''"]
+escape = "\\"
+keywords = [
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda",
+    "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+]
+types = ["bool", "bytes", "dict", "float", "int", "list", "object", "set", "str", "tuple"]
+constants = ["True", "False", "None"]
+"##;
+
+const SHELL_RULES: &str = r##"
+name = "shell"
+extensions = ["sh", "bash", "zsh"]
+line-comments = ["#"]
+strings = ['"', "'"]
+escape = "\\"
+keywords = [
+    "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if", "in",
+    "local", "return", "then", "until", "while",
+]
+"##;
+
+const JAVASCRIPT_RULES: &str = r##"
+name = "javascript"
+extensions = ["js", "mjs", "cjs", "ts"]
+line-comments = ["//"]
+block-comment = ["/*", "*/"]
+strings = ['"', "'", "`"]
+multiline-strings = ["`"]
+escape = "\\"
+keywords = [
+    "async", "await", "break", "case", "catch", "class", "const", "continue", "default", "delete",
+    "do", "else", "export", "extends", "finally", "for", "function", "if", "import", "in",
+    "instanceof", "interface", "let", "new", "of", "return", "switch", "throw", "try", "type",
+    "typeof", "var", "void", "while", "yield",
+]
+constants = ["true", "false", "null", "undefined", "this"]
+"##;
+
+/// The built-in rules, by name.
+const BUILTIN_RULES: [(&str, &str); 4] =
+    [("c", C_RULES), ("python", PYTHON_RULES), ("shell", SHELL_RULES), ("javascript", JAVASCRIPT_RULES)];
+
+/// SyntaxError is why a file of syntax rules could not be loaded.
+#[derive(Debug)]
+pub enum SyntaxError {
+    /// The file is not valid TOML.
+    Parse(String),
+    /// A setting has a value that is not understood.
+    InvalidSetting { setting: String, message: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SyntaxError::Parse(message) => write!(f, "Invalid syntax rules: {}", message),
+            SyntaxError::InvalidSetting { setting, message } => write!(f, "Invalid setting {}: {}", setting, message),
+            SyntaxError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for SyntaxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SyntaxError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SyntaxError {
+    fn from(error: io::Error) -> SyntaxError {
+        SyntaxError::Io(error)
+    }
+}
+
+/// SyntaxRules describes the words and delimiters of a language, for highlighting it without a
+/// parser. Rules are read from TOML like:
+///
+/// ```toml
+/// name = "python"
+/// extensions = ["py"]
+/// line-comments = ["#"]
+/// block-comment = ["/*", "*/"]
+/// strings = ['"""', '"', "'"]
+/// multiline-strings = ['"""']
+/// escape = "\\"
+/// keywords = ["def", "class"]
+/// types = ["int", "str"]
+/// constants = ["True", "False"]
+/// ```
+///
+/// Comments, strings, numbers, keywords, types and constants are drawn in the `syntax.comment`,
+/// `syntax.string`, `syntax.number`, `syntax.keyword`, `syntax.type` and `syntax.constant`
+/// styles. String delimiters are tried in order, so longer ones go first. Only block comments and
+/// multiline strings continue past the end of a line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SyntaxRules {
+    pub name: String,
+    pub extensions: Vec<String>,
+    line_comments: Vec<String>,
+    block_comment: Option<(String, String)>,
+    strings: Vec<String>,
+    multiline_strings: Vec<String>,
+    escape: Option<char>,
+    keywords: Vec<String>,
+    types: Vec<String>,
+    constants: Vec<String>,
+}
+
+impl SyntaxRules {
+    /// builtin() returns the built-in rules for the language called `name`.
+    pub fn builtin(name: &str) -> Option<SyntaxRules> {
+        let (_, text) = BUILTIN_RULES.iter().find(|(builtin, _)| *builtin == name)?;
+        Some(SyntaxRules::from_toml(text).expect("built-in syntax rules are valid"))
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_RULES.iter().map(|(name, _)| *name)
+    }
+
+    /// for_path() returns the built-in rules for the file at `path`, judged by its extension.
+    pub fn for_path(path: &Path) -> Option<SyntaxRules> {
+        SyntaxRules::builtin_names().filter_map(SyntaxRules::builtin).find(|rules| rules.matches(path))
+    }
+
+    /// load() reads rules from the TOML file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SyntaxRules, SyntaxError> {
+        SyntaxRules::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// from_toml() reads rules from the TOML in `text`.
+    pub fn from_toml(text: &str) -> Result<SyntaxRules, SyntaxError> {
+        let table = text.parse::<Table>().map_err(|error| SyntaxError::Parse(error.message().to_string()))?;
+        let mut rules = SyntaxRules::default();
+
+        for (key, value) in table.iter() {
+            let invalid = |message: &str| SyntaxError::InvalidSetting {
+                setting: key.to_string(),
+                message: message.to_string(),
+            };
+            match key.as_str() {
+                "name" => rules.name = value.as_str().ok_or_else(|| invalid("must be a string"))?.to_string(),
+                "extensions" => rules.extensions = strings(value).ok_or_else(|| invalid("must be a list of strings"))?,
+                "line-comments" => rules.line_comments = strings(value).ok_or_else(|| invalid("must be a list of strings"))?,
+                "block-comment" => match strings(value).as_deref() {
+                    Some([open, close]) if !open.is_empty() && !close.is_empty() => {
+                        rules.block_comment = Some((open.to_string(), close.to_string()))
+                    }
+                    _ => return Err(invalid("must be an opening and a closing delimiter")),
+                },
+                "strings" => rules.strings = strings(value).ok_or_else(|| invalid("must be a list of strings"))?,
+                "multiline-strings" => {
+                    rules.multiline_strings = strings(value).ok_or_else(|| invalid("must be a list of strings"))?
+                }
+                "escape" => {
+                    let mut characters = value.as_str().map(|escape| escape.chars());
+                    rules.escape = match characters.as_mut().map(|characters| (characters.next(), characters.next())) {
+                        Some((Some(escape), None)) => Some(escape),
+                        _ => return Err(invalid("must be one character")),
+                    };
+                }
+                "keywords" => rules.keywords = strings(value).ok_or_else(|| invalid("must be a list of strings"))?,
+                "types" => rules.types = strings(value).ok_or_else(|| invalid("must be a list of strings"))?,
+                "constants" => rules.constants = strings(value).ok_or_else(|| invalid("must be a list of strings"))?,
+                _ => return Err(invalid("is not a syntax setting")),
+            }
+        }
+
+        if rules.line_comments.iter().chain(rules.strings.iter()).any(|delimiter| delimiter.is_empty()) {
+            return Err(SyntaxError::InvalidSetting {
+                setting: "strings".to_string(),
+                message: "delimiters cannot be empty".to_string(),
+            });
+        }
+        Ok(rules)
+    }
+
+    /// matches() reports whether the rules are for the file at `path`, judged by its extension.
+    pub fn matches(&self, path: &Path) -> bool {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => self.extensions.iter().any(|candidate| candidate == extension),
+            None => false,
+        }
+    }
+
+    /// scan_line() returns the styled spans of `line`, which starts in `state`, with offsets from
+    /// the start of the line, and the state the next line starts in.
+    fn scan_line(&self, line: &str, state: LineState) -> (Vec<StyleSpan>, LineState) {
+        let mut spans = Vec::new();
+        let mut position = 0;
+        let span = |start: usize, end: usize, style: &str| StyleSpan {
+            range: start..end,
+            style: format!("{}{}", SYNTAX_STYLE_PREFIX, style),
+        };
+
+        match state {
+            LineState::Code => {}
+            LineState::BlockComment => match self.block_comment.as_ref().and_then(|(_, close)| find_end(line, 0, close, None)) {
+                Some(end) => {
+                    spans.push(span(0, end, "comment"));
+                    position = end;
+                }
+                None => return (vec![span(0, line.len(), "comment")], LineState::BlockComment),
+            },
+            LineState::String(index) => match find_end(line, 0, &self.strings[index], self.escape) {
+                Some(end) => {
+                    spans.push(span(0, end, "string"));
+                    position = end;
+                }
+                None => return (vec![span(0, line.len(), "string")], LineState::String(index)),
+            },
+        }
+
+        while position < line.len() {
+            let rest = &line[position..];
+            let character = rest.chars().next().unwrap();
+
+            if let Some((open, close)) = self.block_comment.as_ref().filter(|(open, _)| rest.starts_with(open.as_str())) {
+                match find_end(line, position + open.len(), close, None) {
+                    Some(end) => {
+                        spans.push(span(position, end, "comment"));
+                        position = end;
+                        continue;
+                    }
+                    None => {
+                        spans.push(span(position, line.len(), "comment"));
+                        return (spans, LineState::BlockComment);
+                    }
+                }
+            }
+            if self.line_comments.iter().any(|prefix| rest.starts_with(prefix.as_str())) {
+                spans.push(span(position, line.len(), "comment"));
+                break;
+            }
+            if let Some(index) = self.strings.iter().position(|delimiter| rest.starts_with(delimiter.as_str())) {
+                let delimiter = &self.strings[index];
+                match find_end(line, position + delimiter.len(), delimiter, self.escape) {
+                    Some(end) => {
+                        spans.push(span(position, end, "string"));
+                        position = end;
+                        continue;
+                    }
+                    None => {
+                        spans.push(span(position, line.len(), "string"));
+                        let multiline = self.multiline_strings.contains(delimiter);
+                        return (spans, if multiline { LineState::String(index) } else { LineState::Code });
+                    }
+                }
+            }
+            if is_word_character(character) || character == '#' {
+                let length = rest.char_indices().skip(1).find(|(_, next)| !is_word_character(*next)).map_or(rest.len(), |(index, _)| index);
+                let word = &rest[..length];
+                let style = if character.is_ascii_digit() {
+                    Some("number")
+                } else if self.keywords.iter().any(|keyword| keyword == word) {
+                    Some("keyword")
+                } else if self.types.iter().any(|name| name == word) {
+                    Some("type")
+                } else if self.constants.iter().any(|name| name == word) {
+                    Some("constant")
+                } else {
+                    None
+                };
+                if let Some(style) = style {
+                    spans.push(span(position, position + length, style));
+                }
+                position += length;
+                continue;
+            }
+
+            position += character.len_utf8();
+        }
+
+        (spans, LineState::Code)
+    }
+}
+
+/// LineState is what a line starts inside of, carried over from the lines before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineState {
+    Code,
+    BlockComment,
+    /// A multiline string opened with the delimiter at this index of the rules' strings.
+    String(usize),
+}
+
+/// RuleHighlighter highlights a buffer with a language's `SyntaxRules`. It keeps the state each
+/// line starts in, and after a change works it out again from the first changed line on.
+/// - `edits`: The buffer's changes since the last update.
+pub struct RuleHighlighter {
+    rules: SyntaxRules,
+    line_states: Vec<LineState>,
+    edits: Receiver<EditEvent>,
+}
+
+impl RuleHighlighter {
+    /// new() returns a highlighter for `buffer` using `rules`. The buffer is scanned on the first
+    /// update.
+    pub fn new(buffer: &mut GapBuffer, rules: SyntaxRules) -> RuleHighlighter {
+        let (_, edits) = buffer.subscribe();
+
+        RuleHighlighter {
+            rules,
+            line_states: Vec::new(),
+            edits,
+        }
+    }
+
+    pub fn rules(&self) -> &SyntaxRules {
+        &self.rules
+    }
+}
+
+impl Highlighter for RuleHighlighter {
+    fn update(&mut self, buffer: &GapBuffer) {
+        let changed = self.edits.try_iter().map(|event| event.removed.start).min();
+        let first_line = match changed {
+            _ if self.line_states.is_empty() => 0,
+            Some(offset) => buffer.offset_to_line_col(offset.min(buffer.len())).0.min(self.line_states.len() - 1),
+            None => return,
+        };
+
+        self.line_states.truncate(first_line + 1);
+        let mut state = self.line_states.pop().unwrap_or(LineState::Code);
+        for line in first_line..buffer.line_count() {
+            self.line_states.push(state);
+            let text = buffer.slice(buffer.line_to_offset(line).unwrap()..buffer.line_end_offset(line).unwrap());
+            state = self.rules.scan_line(&text, state).1;
+        }
+    }
+
+    fn line_spans(&self, buffer: &GapBuffer, line: usize) -> Vec<StyleSpan> {
+        let (start, end) = match (buffer.line_to_offset(line), buffer.line_end_offset(line)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Vec::new(),
+        };
+        let state = self.line_states.get(line).cloned().unwrap_or(LineState::Code);
+        let (spans, _) = self.rules.scan_line(&buffer.slice(start..end), state);
+
+        spans
+            .into_iter()
+            .map(|span| StyleSpan {
+                range: start + span.range.start..start + span.range.end,
+                style: span.style,
+            })
+            .collect()
+    }
+}
+
+/// strings() reads a TOML list of strings.
+fn strings(value: &Value) -> Option<Vec<String>> {
+    value.as_array()?.iter().map(|item| item.as_str().map(|item| item.to_string())).collect()
+}
+
+/// find_end() returns the offset just past the first `close` in `line` at or after `from` that is
+/// not escaped by `escape`.
+fn find_end(line: &str, from: usize, close: &str, escape: Option<char>) -> Option<usize> {
+    let mut position = from;
+
+    while position < line.len() {
+        let rest = &line[position..];
+        if rest.starts_with(close) {
+            return Some(position + close.len());
+        }
+
+        let character = rest.chars().next().unwrap();
+        position += character.len_utf8();
+        if Some(character) == escape {
+            position += line[position..].chars().next().map_or(0, |escaped| escaped.len_utf8());
+        }
+    }
+    None
+}
+
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use syntax::rules::{RuleHighlighter, SyntaxRules};
+    use syntax::Highlighter;
+    use GapBuffer;
+
+    fn styles(highlighter: &RuleHighlighter, buffer: &GapBuffer, line: usize) -> Vec<(String, String)> {
+        highlighter
+            .line_spans(buffer, line)
+            .into_iter()
+            .map(|span| (buffer.slice(span.range).into_owned(), span.style))
+            .collect()
+    }
+
+    fn pair(text: &str, style: &str) -> (String, String) {
+        (text.to_string(), format!("syntax.{}", style))
+    }
+
+    #[test]
+    fn highlight_a_line() {
+        let mut buffer = GapBuffer::from("def f(x=10):\n    return \"a # \\\" b\" # done\n".to_string());
+        let mut highlighter = RuleHighlighter::new(&mut buffer, SyntaxRules::for_path(Path::new("f.py")).unwrap());
+        highlighter.update(&buffer);
+
+        assert_eq!(styles(&highlighter, &buffer, 0), vec![pair("def", "keyword"), pair("10", "number")]);
+        assert_eq!(
+            styles(&highlighter, &buffer, 1),
+            vec![pair("return", "keyword"), pair("\"a # \\\" b\"", "string"), pair("# done", "comment")]
+        );
+    }
+
+    #[test]
+    fn block_comments_and_multiline_strings_continue_on_later_lines() {
+        let mut buffer = GapBuffer::from("int x; /* one\ntwo */ int y;\nchar *s = \"open\nint z;".to_string());
+        let mut highlighter = RuleHighlighter::new(&mut buffer, SyntaxRules::for_path(Path::new("a.c")).unwrap());
+        highlighter.update(&buffer);
+
+        assert_eq!(styles(&highlighter, &buffer, 0), vec![pair("int", "type"), pair("/* one", "comment")]);
+        assert_eq!(styles(&highlighter, &buffer, 1), vec![pair("two */", "comment"), pair("int", "type")]);
+        assert_eq!(styles(&highlighter, &buffer, 3), vec![pair("int", "type")]);
+
+        buffer.set_point(0);
+        buffer.insert_str("/*\n");
+        highlighter.update(&buffer);
+        assert_eq!(styles(&highlighter, &buffer, 1), vec![pair("int x; /* one", "comment")]);
+        assert_eq!(styles(&highlighter, &buffer, 2), vec![pair("two */", "comment"), pair("int", "type")]);
+    }
+
+    #[test]
+    fn read_rules_from_toml() {
+        let rules = SyntaxRules::from_toml("name = \"ini\"\nextensions = [\"ini\"]\nline-comments = [\";\"]\n").unwrap();
+        assert_eq!(rules.name, "ini");
+        assert!(rules.matches(Path::new("setup.ini")));
+        assert!(!rules.matches(Path::new("setup")));
+
+        let test_cases = [
+            ("not TOML", "name = "),
+            ("an unknown setting", "colors = []"),
+            ("a bad list", "keywords = \"if\""),
+            ("one block delimiter", "block-comment = [\"/*\"]"),
+            ("a long escape", "escape = \"ab\""),
+            ("an empty delimiter", "strings = [\"\"]"),
+        ];
+        for (name, text) in test_cases.iter() {
+            assert!(SyntaxRules::from_toml(text).is_err(), "Test case: \"{}\" failed.", name);
+        }
+        for name in SyntaxRules::builtin_names() {
+            assert_eq!(SyntaxRules::builtin(name).unwrap().name, name);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use std::cmp::Reverse;
+use std::iter;
+use std::path::Path;
+use std::sync::mpsc::Receiver;
+
+use tree_sitter::{InputEdit, Language, Node, Parser, Point, Query, QueryCursor, StreamingIterator, Tree};
+
+use delta::{PositionEncoding, TextChange};
+use events::EditEvent;
+use syntax::{Highlighter, StyleSpan, SYNTAX_STYLE_PREFIX};
+use GapBuffer;
+
+/// The languages there are tree-sitter grammars for.
+pub const GRAMMARS: &[Grammar] = &[Grammar {
+    name: "rust",
+    extensions: &["rs"],
+    language: rust,
+    highlights: tree_sitter_rust::HIGHLIGHTS_QUERY,
+}];
+
+/// Grammar is a language tree-sitter can parse, with the query that picks out the captures to
+/// highlight. A capture such as `@function.method` is drawn in the theme style
+/// `syntax.function.method`.
+/// - `name`: The language's name.
+/// - `extensions`: The extensions of the files written in it.
+pub struct Grammar {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    language: fn() -> Language,
+    highlights: &'static str,
+}
+
+impl Grammar {
+    /// for_path() is the grammar for the file at `path`, judged by its extension.
+    pub fn for_path(path: &Path) -> Option<&'static Grammar> {
+        let extension = path.extension()?.to_str()?;
+
+        GRAMMARS.iter().find(|grammar| grammar.extensions.contains(&extension))
+    }
+}
+
+fn rust() -> Language {
+    tree_sitter_rust::LANGUAGE.into()
+}
+
+/// TreeSitterHighlighter highlights a buffer from the syntax tree tree-sitter parses it into.
+/// The buffer's changes are read from its change feed and applied to the old tree, so each parse
+/// after an edit reuses the parts of the tree the edit did not touch.
+/// - `edits`, `changes`: The buffer's changes since the last update, as byte ranges and as line
+///   and byte column positions. Each change arrives on both.
+pub struct TreeSitterHighlighter {
+    parser: Parser,
+    query: Query,
+    tree: Option<Tree>,
+    edits: Receiver<EditEvent>,
+    changes: Receiver<TextChange>,
+}
+
+impl TreeSitterHighlighter {
+    /// new() returns a highlighter for `buffer` written in `grammar`. The buffer is parsed on the
+    /// first update.
+    pub fn new(buffer: &mut GapBuffer, grammar: &Grammar) -> TreeSitterHighlighter {
+        let language = (grammar.language)();
+        let mut parser = Parser::new();
+        parser.set_language(&language).expect("built-in grammars are compatible");
+        let query = Query::new(&language, grammar.highlights).expect("built-in queries are valid");
+        let (_, edits) = buffer.subscribe();
+        let (_, changes) = buffer.subscribe_text_changes(PositionEncoding::Utf8);
+
+        TreeSitterHighlighter {
+            parser,
+            query,
+            tree: None,
+            edits,
+            changes,
+        }
+    }
+
+    /// tree() is the syntax tree from the last update, if there has been one.
+    pub fn tree(&self) -> Option<&Tree> {
+        self.tree.as_ref()
+    }
+}
+
+impl Highlighter for TreeSitterHighlighter {
+    fn update(&mut self, buffer: &GapBuffer) {
+        let mut edited = false;
+        for (event, change) in self.edits.try_iter().zip(self.changes.try_iter()) {
+            if let Some(tree) = self.tree.as_mut() {
+                tree.edit(&input_edit(&event, &change));
+            }
+            edited = true;
+        }
+        if self.tree.is_some() && !edited {
+            return;
+        }
+
+        let (front, back) = buffer.as_slices();
+        let mut read = |offset: usize, _: Point| match offset.checked_sub(front.len()) {
+            None => &front[offset..],
+            Some(offset) => &back[offset.min(back.len())..],
+        };
+        self.tree = self.parser.parse_with_options(&mut read, self.tree.as_ref(), None);
+    }
+
+    fn line_spans(&self, buffer: &GapBuffer, line: usize) -> Vec<StyleSpan> {
+        let (tree, start, end) = match (self.tree.as_ref(), buffer.line_to_offset(line), buffer.line_end_offset(line)) {
+            (Some(tree), Some(start), Some(end)) => (tree, start, end),
+            _ => return Vec::new(),
+        };
+
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(start..end);
+        let text = |node: Node| iter::once(buffer.slice(node.byte_range()).into_owned().into_bytes());
+        let mut captures = cursor.captures(&self.query, tree.root_node(), text);
+        let mut spans = Vec::new();
+
+        while let Some((found, index)) = captures.next() {
+            let capture = found.captures()[*index];
+            let range = capture.node.byte_range();
+            let range = range.start.max(start)..range.end.min(end);
+            if range.start < range.end {
+                let style = format!("{}{}", SYNTAX_STYLE_PREFIX, self.query.capture_names()[capture.index as usize]);
+                spans.push((found.pattern_index, StyleSpan { range, style }));
+            }
+        }
+
+        // Earlier patterns in a highlights query take precedence, so they are drawn last.
+        spans.sort_by_key(|(pattern, _)| Reverse(*pattern));
+        spans.into_iter().map(|(_, span)| span).collect()
+    }
+}
+
+/// input_edit() describes a change to tree-sitter, from the same change as an `EditEvent` and as
+/// a `TextChange` counted in bytes.
+fn input_edit(event: &EditEvent, change: &TextChange) -> InputEdit {
+    let new_end_position = match change.text.rfind('\n') {
+        Some(last_newline) => Point {
+            row: change.start.line + change.text.matches('\n').count(),
+            column: change.text.len() - last_newline - 1,
+        },
+        None => Point {
+            row: change.start.line,
+            column: change.start.character + change.text.len(),
+        },
+    };
+
+    InputEdit {
+        start_byte: event.removed.start,
+        old_end_byte: event.removed.end,
+        new_end_byte: event.inserted_range().end,
+        start_position: Point {
+            row: change.start.line,
+            column: change.start.character,
+        },
+        old_end_position: Point {
+            row: change.end.line,
+            column: change.end.character,
+        },
+        new_end_position,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use syntax::tree::{Grammar, TreeSitterHighlighter};
+    use syntax::Highlighter;
+    use GapBuffer;
+
+    fn styles(highlighter: &TreeSitterHighlighter, buffer: &GapBuffer, line: usize) -> Vec<(String, String)> {
+        highlighter
+            .line_spans(buffer, line)
+            .into_iter()
+            .map(|span| (buffer.slice(span.range).into_owned(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn highlight_captures_as_theme_styles() {
+        let mut buffer = GapBuffer::from("fn main() {\n    let x = \"hi\"; // note\n}\n".to_string());
+        let mut highlighter = TreeSitterHighlighter::new(&mut buffer, Grammar::for_path(Path::new("main.rs")).unwrap());
+        highlighter.update(&buffer);
+
+        let line = styles(&highlighter, &buffer, 1);
+        assert!(line.contains(&("let".to_string(), "syntax.keyword".to_string())));
+        assert!(line.contains(&("\"hi\"".to_string(), "syntax.string".to_string())));
+        assert!(line.contains(&("// note".to_string(), "syntax.comment".to_string())));
+        assert!(styles(&highlighter, &buffer, 0).contains(&("main".to_string(), "syntax.function".to_string())));
+        assert!(Grammar::for_path(Path::new("notes.txt")).is_none());
+    }
+
+    #[test]
+    fn edits_update_the_tree_incrementally() {
+        let mut buffer = GapBuffer::from("fn main() {}\n".to_string());
+        let mut highlighter = TreeSitterHighlighter::new(&mut buffer, Grammar::for_path(Path::new("main.rs")).unwrap());
+        highlighter.update(&buffer);
+
+        buffer.set_point(0);
+        buffer.insert_str("// top\n");
+        let end = buffer.len();
+        buffer.replace_range(end - 3..end - 1, "{ 1 }");
+        highlighter.update(&buffer);
+
+        let tree = highlighter.tree().unwrap();
+        assert!(!tree.root_node().has_error());
+        assert_eq!(tree.root_node().byte_range(), 0..buffer.len());
+        assert_eq!(styles(&highlighter, &buffer, 0), vec![("// top".to_string(), "syntax.comment".to_string())]);
+        assert!(styles(&highlighter, &buffer, 1).contains(&("fn".to_string(), "syntax.keyword".to_string())));
+    }
+}
@@ -0,0 +1,268 @@
+use ansi::{apply_sgr, face_styles, AnsiEvent};
+use property::TextProperty;
+use theme::Face;
+use GapBuffer;
+
+/// The group of the text properties a terminal colors what it draws with.
+pub const TERMINAL_GROUP: &str = "terminal";
+
+/// How far apart the tab stops are.
+const TAB_STOP: usize = 8;
+
+/// Emulator draws what a program writes to a terminal into a buffer. Text overwrites what is at
+/// the cursor, as it does on a screen, and is marked with the styles of its colors. A window of
+/// `rows` lines at the end of the buffer is the screen the cursor moves about in. The lines above
+/// it are the scrollback, and clearing the screen scrolls what was on it up into the
+/// scrollback. Columns are counted in characters and long lines are not wrapped. Sequences it
+/// does not follow, such as those switching to the alternate screen, are ignored, so programs
+/// that draw the whole screen are not shown as they would be in a real terminal.
+/// - `cursor`: The line and column the next text is drawn at. Lines and columns are added to the
+///   buffer as text is drawn past its end.
+/// - `top`: The line of the buffer the screen starts at.
+/// - `rows`: How many lines the screen has.
+/// - `face`: How the text drawn next is colored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Emulator {
+    cursor: (usize, usize),
+    top: usize,
+    rows: usize,
+    face: Face,
+}
+
+impl Emulator {
+    pub fn new(rows: usize) -> Emulator {
+        Emulator {
+            cursor: (0, 0),
+            top: 0,
+            rows: rows.max(1),
+            face: Face::default(),
+        }
+    }
+
+    /// cursor() is the line and column of the buffer the cursor is at.
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    /// set_rows() changes how many lines the screen has, keeping the cursor on it.
+    pub fn set_rows(&mut self, rows: usize) {
+        self.rows = rows.max(1);
+        self.top = self.top.max((self.cursor.0 + 1).saturating_sub(self.rows));
+    }
+
+    /// apply() draws `events` into `buffer` and returns the offset the cursor is at after them.
+    pub fn apply(&mut self, buffer: &mut GapBuffer, events: &[AnsiEvent]) -> usize {
+        for event in events {
+            match event {
+                AnsiEvent::Text(text) => self.draw_text(buffer, text),
+                AnsiEvent::Control('\r') => self.cursor.1 = 0,
+                AnsiEvent::Control('\n') => self.line_feed(),
+                AnsiEvent::Control('\x08') => self.cursor.1 = self.cursor.1.saturating_sub(1),
+                AnsiEvent::Control('\t') => self.cursor.1 = (self.cursor.1 / TAB_STOP + 1) * TAB_STOP,
+                AnsiEvent::Control(_) => {}
+                AnsiEvent::Csi { private: false, params, action } => self.control_sequence(buffer, params, *action),
+                AnsiEvent::Csi { .. } => {}
+            }
+        }
+
+        self.place(buffer)
+    }
+
+    fn line_feed(&mut self) {
+        self.cursor.0 += 1;
+        if self.cursor.0 >= self.top + self.rows {
+            self.top += 1;
+        }
+    }
+
+    fn control_sequence(&mut self, buffer: &mut GapBuffer, params: &[u16], action: char) {
+        let param = |index: usize| params.get(index).map_or(1, |param| (*param as usize).max(1));
+        let bottom = self.top + self.rows - 1;
+        let (line, column) = self.cursor;
+
+        match action {
+            'm' => apply_sgr(&mut self.face, params),
+            'A' => self.cursor.0 = line.saturating_sub(param(0)).max(self.top),
+            'B' => self.cursor.0 = (line + param(0)).min(bottom),
+            'C' => self.cursor.1 = column + param(0),
+            'D' => self.cursor.1 = column.saturating_sub(param(0)),
+            'E' => self.cursor = ((line + param(0)).min(bottom), 0),
+            'F' => self.cursor = (line.saturating_sub(param(0)).max(self.top), 0),
+            'G' | '`' => self.cursor.1 = param(0) - 1,
+            'd' => self.cursor.0 = (self.top + param(0) - 1).min(bottom),
+            'H' | 'f' => self.cursor = ((self.top + param(0) - 1).min(bottom), param(1) - 1),
+            'K' => self.erase_line(buffer, params.first().copied().unwrap_or(0)),
+            'J' => self.erase_screen(buffer, params.first().copied().unwrap_or(0)),
+            'P' => {
+                let (start, end) = self.cells(buffer, param(0));
+                buffer.replace_range(start..end, "");
+            }
+            'X' => {
+                let (start, end) = self.cells(buffer, param(0));
+                let blank = " ".repeat(buffer.slice(start..end).chars().count());
+                buffer.replace_range(start..end, &blank);
+            }
+            '@' => {
+                let offset = self.place(buffer);
+                buffer.replace_range(offset..offset, &" ".repeat(param(0)));
+            }
+            _ => {}
+        }
+    }
+
+    /// draw_text() draws `text` at the cursor, over what is there, and moves the cursor past it.
+    fn draw_text(&mut self, buffer: &mut GapBuffer, text: &str) {
+        let count = text.chars().count();
+        let (start, end) = self.cells(buffer, count);
+
+        buffer.replace_range(start..end, text);
+        for style in face_styles(&self.face) {
+            buffer.add_property(start..start + text.len(), TextProperty::styled(TERMINAL_GROUP, &style));
+        }
+        self.cursor.1 += count;
+    }
+
+    /// erase_line() blanks the line of the cursor from it to the end for `mode` 0, from the start
+    /// up to and including it for 1, and all of it for 2.
+    fn erase_line(&mut self, buffer: &mut GapBuffer, mode: u16) {
+        let offset = self.place(buffer);
+        let start = buffer.line_to_offset(self.cursor.0).unwrap();
+        let end = buffer.line_end_offset(self.cursor.0).unwrap();
+
+        match mode {
+            0 => {
+                buffer.replace_range(offset..end, "");
+            }
+            1 => {
+                let (_, end) = self.cells(buffer, 1);
+                let blank = " ".repeat(buffer.slice(start..end).chars().count());
+                buffer.replace_range(start..end, &blank);
+            }
+            2 => {
+                buffer.replace_range(start..end, "");
+                self.place(buffer);
+            }
+            _ => {}
+        }
+    }
+
+    /// erase_screen() blanks the screen from the cursor to the end for `mode` 0, or all of it
+    /// for 2 and 3, which scrolls what is on it into the scrollback.
+    fn erase_screen(&mut self, buffer: &mut GapBuffer, mode: u16) {
+        match mode {
+            0 => {
+                self.erase_line(buffer, 0);
+                let end = buffer.line_end_offset(self.cursor.0).unwrap();
+                let length = buffer.len();
+                buffer.replace_range(end..length, "");
+            }
+            2 | 3 => {
+                let top = match buffer.line_to_offset(self.top) {
+                    Some(top) => top,
+                    None => return,
+                };
+                if buffer.slice(top..buffer.len()).trim().is_empty() {
+                    let length = buffer.len();
+                    buffer.replace_range(top..length, "");
+                } else {
+                    let length = buffer.len();
+                    buffer.replace_range(length..length, "\n");
+                    let row = self.cursor.0.saturating_sub(self.top);
+                    self.top = buffer.line_count() - 1;
+                    self.cursor.0 = self.top + row;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// cells() is the span of the buffer from the cursor across `count` characters, or to the
+    /// end of the line if that comes first.
+    fn cells(&mut self, buffer: &mut GapBuffer, count: usize) -> (usize, usize) {
+        let start = self.place(buffer);
+        let line_end = buffer.line_end_offset(self.cursor.0).unwrap();
+        let rest = buffer.slice(start..line_end);
+        let length = rest.char_indices().nth(count).map_or(rest.len(), |(index, _)| index);
+
+        (start, start + length)
+    }
+
+    /// place() adds the lines and spaces needed for the cursor to be on text, and returns the
+    /// offset it is at.
+    fn place(&mut self, buffer: &mut GapBuffer) -> usize {
+        let (line, column) = self.cursor;
+        while buffer.line_count() <= line {
+            let length = buffer.len();
+            buffer.replace_range(length..length, "\n");
+        }
+
+        let start = buffer.line_to_offset(line).unwrap();
+        let end = buffer.line_end_offset(line).unwrap();
+        let text = buffer.slice(start..end);
+        let characters = text.chars().count();
+        match text.char_indices().nth(column) {
+            Some((index, _)) => start + index,
+            None => {
+                buffer.replace_range(end..end, &" ".repeat(column - characters));
+                end + column - characters
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ansi::AnsiParser;
+    use term::{Emulator, TERMINAL_GROUP};
+    use GapBuffer;
+
+    /// draw() draws what a program wrote into `buffer`, returning the offset of the cursor.
+    fn draw(emulator: &mut Emulator, buffer: &mut GapBuffer, output: &str) -> usize {
+        let events = AnsiParser::new().feed(output.as_bytes());
+        emulator.apply(buffer, &events)
+    }
+
+    #[test]
+    fn draw_lines_over_each_other() {
+        let mut buffer = GapBuffer::new();
+        let mut emulator = Emulator::new(24);
+
+        let cursor = draw(&mut emulator, &mut buffer, "$ ls\r\none  two\r\n50%\r100%\ta\x08b\r\n$ ");
+        assert_eq!(buffer.to_string(), "$ ls\none  two\n100%    b\n$ ");
+        assert_eq!((cursor, emulator.cursor()), (buffer.len(), (3, 2)));
+
+        draw(&mut emulator, &mut buffer, "typo\x08\x08\x1b[K\x1b[2Dxyz\x1b[D\x1b[2@!");
+        assert_eq!(buffer.to_string(), "$ ls\none  two\n100%    b\n$ xy! z");
+        draw(&mut emulator, &mut buffer, "\x1b[2;3H\x1b[2P\x1b[2X\x1b[1K");
+        assert_eq!(buffer.to_string(), "$ ls\n    wo\n100%    b\n$ xy! z");
+    }
+
+    #[test]
+    fn colors_become_text_properties() {
+        let mut buffer = GapBuffer::new();
+        let mut emulator = Emulator::new(24);
+
+        draw(&mut emulator, &mut buffer, "a \x1b[1;31mred\x1b[0m b");
+        let mut spans: Vec<_> = buffer
+            .line_properties(0)
+            .into_iter()
+            .map(|(range, property)| (range.start, range.end, property.group.as_str(), property.style.as_deref().unwrap()))
+            .collect();
+        spans.sort();
+        assert_eq!(spans, vec![(2, 5, TERMINAL_GROUP, "ansi.bold"), (2, 5, TERMINAL_GROUP, "ansi.foreground.red")]);
+    }
+
+    #[test]
+    fn the_screen_scrolls_and_clearing_it_keeps_the_scrollback() {
+        let mut buffer = GapBuffer::new();
+        let mut emulator = Emulator::new(2);
+
+        draw(&mut emulator, &mut buffer, "one\r\ntwo\r\nthree\x1b[H>");
+        assert_eq!(buffer.to_string(), "one\n>wo\nthree");
+        draw(&mut emulator, &mut buffer, "\x1b[2J\x1b[Hcleared");
+        assert_eq!(buffer.to_string(), "one\n>wo\nthree\ncleared");
+        draw(&mut emulator, &mut buffer, "\r\nmore\x1b[A\x1b[J");
+        assert_eq!(buffer.to_string(), "one\n>wo\nthree\nclea");
+        assert_eq!(emulator.cursor(), (3, 4));
+    }
+}
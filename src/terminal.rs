@@ -0,0 +1,481 @@
+use std::io;
+use std::iter;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyEventKind, KeyModifiers};
+use crossterm::event::{MouseButton, MouseEvent as TermMouseEvent, MouseEventKind};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, event::KeyCode as TermKeyCode};
+
+use buffer::transform::DEFAULT_TAB_WIDTH;
+use completion::MAX_VISIBLE_CANDIDATES;
+use editor::{Editor, MouseAction, MouseEvent};
+use keymap::{Key, KeyCode, Modifiers};
+use minibuffer::Minibuffer;
+use status_line::StatusInfo;
+use syntax::{Highlighter, StyleSpan};
+use theme::{self, ColorSupport, Face};
+use view::wrap::{advance, display_width};
+use view::TRUNCATION_INDICATOR;
+use window::{Area, WindowId};
+use GapBuffer;
+
+use self::screen::Screen;
+
+mod screen;
+
+/// How long the terminal waits for a key before doing idle work.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long without a key press before every modified buffer is auto-saved and files are checked
+/// for changes on disk.
+const IDLE_TIME: Duration = Duration::from_secs(30);
+
+/// What is drawn between windows side by side.
+const DIVIDER: &str = "|";
+
+/// run() edits in the terminal until a command asks the editor to exit. The terminal is put in
+/// raw mode on the alternate screen, and restored when this returns, even by panicking.
+pub fn run(editor: &mut Editor) -> io::Result<()> {
+    let _guard = RawMode::enter()?;
+    let mut out = io::stdout();
+    let mut last_key = Instant::now();
+    let mut idle_done = false;
+    let mut shown: Option<Screen> = None;
+    let colors = ColorSupport::detect();
+
+    while !editor.should_quit() {
+        editor.sync_language_servers();
+        editor.sync_grep();
+        editor.sync_jobs();
+        editor.sync_terminals();
+        editor.sync_git_changes();
+        let (width, height) = terminal::size()?;
+        let areas = editor.layout_windows(width as usize, (height as usize).saturating_sub(1));
+        editor.fit_terminals(&areas);
+        let screen = draw(editor, &areas, width as usize, height as usize, colors);
+        screen.flush(&mut out, shown.as_ref())?;
+        shown = Some(screen);
+
+        if !event::poll(POLL_INTERVAL)? {
+            if !idle_done && last_key.elapsed() >= IDLE_TIME {
+                editor.auto_save_buffers(true);
+                editor.check_files();
+                idle_done = true;
+            }
+            continue;
+        }
+
+        let result = match event::read()? {
+            Event::Key(event) if event.kind != KeyEventKind::Release => match key_from_event(event.code, event.modifiers) {
+                Some(key) => {
+                    editor.clear_message();
+                    editor.handle_key(key)
+                }
+                None => continue,
+            },
+            Event::Mouse(event) => match mouse_from_event(event) {
+                Some(event) => editor.handle_mouse(event),
+                None => continue,
+            },
+            // The next frame is laid out for the terminal's new size and redrawn in full.
+            Event::Resize(_, _) => continue,
+            _ => continue,
+        };
+        last_key = Instant::now();
+        idle_done = false;
+        if let Err(error) = result {
+            editor.set_message(&error.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// RawMode holds the terminal in raw mode on the alternate screen while it is alive.
+struct RawMode;
+
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(io::stdout(), DisableMouseCapture, LeaveAlternateScreen, cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// key_from_event() turns a key reported by the terminal into the editor's `Key`, or None for
+/// keys the editor has no name for.
+pub fn key_from_event(code: TermKeyCode, modifiers: KeyModifiers) -> Option<Key> {
+    let code = match code {
+        TermKeyCode::Char(character) => KeyCode::Char(character),
+        TermKeyCode::Enter => KeyCode::Enter,
+        TermKeyCode::Tab | TermKeyCode::BackTab => KeyCode::Tab,
+        TermKeyCode::Backspace => KeyCode::Backspace,
+        TermKeyCode::Delete => KeyCode::Delete,
+        TermKeyCode::Esc => KeyCode::Escape,
+        TermKeyCode::Left => KeyCode::Left,
+        TermKeyCode::Right => KeyCode::Right,
+        TermKeyCode::Up => KeyCode::Up,
+        TermKeyCode::Down => KeyCode::Down,
+        TermKeyCode::Home => KeyCode::Home,
+        TermKeyCode::End => KeyCode::End,
+        TermKeyCode::PageUp => KeyCode::PageUp,
+        TermKeyCode::PageDown => KeyCode::PageDown,
+        TermKeyCode::Insert => KeyCode::Insert,
+        TermKeyCode::F(number) => KeyCode::F(number),
+        _ => return None,
+    };
+
+    // A shifted character arrives already shifted, so shift only counts for other keys.
+    let shift = modifiers.contains(KeyModifiers::SHIFT) && !matches!(code, KeyCode::Char(_));
+    Some(Key {
+        code,
+        modifiers: Modifiers {
+            control: modifiers.contains(KeyModifiers::CONTROL),
+            meta: modifiers.contains(KeyModifiers::ALT),
+            shift: shift || code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT),
+        },
+    })
+}
+
+/// mouse_from_event() turns a mouse event reported by the terminal into the editor's
+/// `MouseEvent`, or None for buttons and movements the editor does not use.
+pub fn mouse_from_event(event: TermMouseEvent) -> Option<MouseEvent> {
+    let action = match event.kind {
+        MouseEventKind::Down(MouseButton::Left) => MouseAction::Press,
+        MouseEventKind::Drag(MouseButton::Left) => MouseAction::Drag,
+        MouseEventKind::Up(MouseButton::Left) => MouseAction::Release,
+        MouseEventKind::ScrollUp => MouseAction::WheelUp,
+        MouseEventKind::ScrollDown => MouseAction::WheelDown,
+        _ => return None,
+    };
+
+    Some(MouseEvent {
+        action,
+        column: event.column as usize,
+        row: event.row as usize,
+    })
+}
+
+/// draw() lays out a frame: each window with its status line, dividers between windows side by
+/// side, the completion popup if one is open, and the echo area at the bottom, in the editor's theme with the colors the terminal
+/// supports.
+fn draw(editor: &Editor, areas: &[(WindowId, Area)], width: usize, height: usize, colors: ColorSupport) -> Screen {
+    let mut screen = Screen::new(width, height);
+    let default = face(editor, colors, theme::DEFAULT);
+    for y in 0..height {
+        screen.fill(0, y, width, default);
+    }
+
+    for (id, area) in areas.iter() {
+        draw_window(&mut screen, editor, *id, *area, colors);
+        if area.x + area.width < width {
+            for y in area.y..area.y + area.height {
+                screen.put(area.x + area.width, y, DIVIDER, default);
+            }
+        }
+    }
+
+    let echo_row = height.saturating_sub(1);
+    match editor.minibuffer() {
+        Some(minibuffer) => {
+            let input = minibuffer.input();
+            let before = input.slice(0..input.get_point());
+            let mut line = format!("{}{}", minibuffer.prompt(), minibuffer.text());
+            if minibuffer.has_choices() {
+                line.push_str(&format_choices(minibuffer));
+            }
+            screen.put(0, echo_row, &line, default);
+            let column = display_width(minibuffer.prompt(), DEFAULT_TAB_WIDTH) + display_width(&before, DEFAULT_TAB_WIDTH);
+            screen.set_cursor(column.min(width.saturating_sub(1)), echo_row);
+        }
+        None => {
+            draw_completion(&mut screen, editor, echo_row, colors);
+            if let Some(message) = editor.message() {
+                screen.put(0, echo_row, message, default);
+            }
+        }
+    }
+
+    screen
+}
+
+/// format_choices() lists the choices matching the minibuffer input after it, from the one
+/// selected on, as `{a | b | c}`.
+fn format_choices(minibuffer: &Minibuffer) -> String {
+    let matches = minibuffer.matches();
+    if matches.is_empty() {
+        return " [No match]".to_string();
+    }
+
+    let selected = minibuffer.selected_index();
+    let shown: Vec<&str> = matches[selected..].iter().chain(matches[..selected].iter()).copied().collect();
+    format!(" {{{}}}", shown.join(" | "))
+}
+
+/// draw_completion() draws the candidates of the open completion in a popup below the cursor,
+/// or above it if there is no room below, lined up with the start of the text they complete.
+/// The popup stays above row `bottom`.
+fn draw_completion(screen: &mut Screen, editor: &Editor, bottom: usize, colors: ColorSupport) {
+    let (completion, buffer) = match (editor.completion(), editor.current_buffer()) {
+        (Some(completion), Some(buffer)) if completion.match_count() > 0 => (completion, buffer),
+        _ => return,
+    };
+    let (cursor_x, cursor_y) = screen.cursor();
+    let typed = completion.selected().map_or(0, |candidate| {
+        display_width(&buffer.slice(candidate.start.min(buffer.get_point())..buffer.get_point()), DEFAULT_TAB_WIDTH)
+    });
+
+    let below = bottom.saturating_sub(cursor_y + 1);
+    let rows = completion.match_count().min(MAX_VISIBLE_CANDIDATES);
+    let (rows, top) = match below >= rows || below >= cursor_y {
+        true => (rows.min(below), cursor_y + 1),
+        false => (rows.min(cursor_y), cursor_y - rows.min(cursor_y)),
+    };
+    let visible = completion.visible(rows);
+    let lines: Vec<String> = completion
+        .matches()
+        .skip(visible.start)
+        .take(visible.len())
+        .map(|candidate| match candidate.item.detail.as_ref() {
+            Some(detail) => format!(" {}  {} ", candidate.item.label, detail),
+            None => format!(" {} ", candidate.item.label),
+        })
+        .collect();
+    let width = lines.iter().map(|line| display_width(line, DEFAULT_TAB_WIDTH)).max().unwrap_or(0);
+    let x = cursor_x.saturating_sub(typed + 1);
+
+    let popup = face(editor, colors, theme::COMPLETION);
+    let selected = face(editor, colors, theme::COMPLETION_SELECTED);
+    for (row, line) in lines.iter().enumerate() {
+        let face = if visible.start + row == completion.selected_index() { selected } else { popup };
+        screen.fill(x, top + row, width, face);
+        screen.put(x, top + row, line, face);
+    }
+}
+
+/// draw_window() draws the lines of window `id` in its view, with its status line below them,
+/// and puts the cursor in it if it is selected.
+fn draw_window(screen: &mut Screen, editor: &Editor, id: WindowId, area: Area, colors: ColorSupport) {
+    let window = match editor.windows().get(id) {
+        Some(window) => window,
+        None => return,
+    };
+    let buffer = match editor.buffers().get(window.buffer()) {
+        Some(buffer) => buffer,
+        None => return,
+    };
+    let selected = id == editor.windows().selected_id();
+    let view = window.view();
+    let width = area.width;
+    let gutter_width = view.gutter_width(buffer).min(width);
+    let text_width = width - gutter_width;
+    let offset = view.horizontal_offset();
+    let tab_width = view.tab_width();
+    let point = editor.window_point(id).unwrap_or(0);
+    let region = if selected { buffer.region() } else { None };
+    let default = face(editor, colors, theme::DEFAULT);
+    let line_number = face(editor, colors, theme::LINE_NUMBER);
+    let selection = editor.theme().overlay(theme::SELECTION).downgrade(colors);
+
+    let highlighter = editor.highlighter(window.buffer());
+    let mut spans: Option<(usize, Vec<StyleSpan>)> = None;
+
+    for (screen_row, row) in view.visible_rows(buffer).iter().enumerate() {
+        let y = area.y + screen_row;
+        if row.row == 0 {
+            screen.put(area.x, y, truncate(&view.gutter(buffer, row.line), width), line_number);
+            if let Some((_, style)) = buffer.line_sign(row.line).filter(|_| gutter_width > 0) {
+                screen.apply_face(area.x, y, 1, editor.theme().overlay(style).downgrade(colors));
+            }
+        }
+        let x = area.x + gutter_width;
+        screen.put(x, y, &expand_tabs(&buffer.slice(row.range.clone()), offset, text_width, tab_width), default);
+
+        // Draws a face over the columns of the row showing `range`.
+        let apply_face = |screen: &mut Screen, range: &Range<usize>, face: Face| {
+            if range.start >= row.range.end || range.end <= row.range.start {
+                return;
+            }
+            let start = display_width(&buffer.slice(row.range.start..range.start.max(row.range.start)), tab_width);
+            let end = display_width(&buffer.slice(row.range.start..range.end.min(row.range.end)), tab_width);
+            let start = start.saturating_sub(offset).min(text_width);
+            let end = end.saturating_sub(offset).min(text_width);
+            screen.apply_face(x + start, y, end - start, face);
+        };
+        if spans.as_ref().is_none_or(|(line, _)| *line != row.line) {
+            spans = Some((row.line, line_spans(buffer, highlighter, row.line)));
+        }
+        for span in spans.iter().flat_map(|(_, spans)| spans.iter()) {
+            apply_face(screen, &span.range, editor.theme().overlay(&span.style).downgrade(colors));
+        }
+        if let Some(region) = region.as_ref() {
+            apply_face(screen, region, selection);
+        }
+
+        let (left, right) = view.clipped(buffer, row);
+        if left && text_width > 0 {
+            screen.put(x, y, TRUNCATION_INDICATOR, default);
+        }
+        if right && text_width > 0 {
+            screen.put(x + text_width - 1, y, TRUNCATION_INDICATOR, default);
+        }
+
+        if selected && row.contains(point) {
+            let before = buffer.slice(row.range.start..point);
+            let column = display_width(&before, tab_width).saturating_sub(offset).min(text_width.saturating_sub(1));
+            screen.set_cursor(area.x + gutter_width + column, y);
+        }
+    }
+
+    let status_line = StatusInfo::of_window(editor, id).map_or_else(String::new, |info| editor.status_line().render(&info));
+    let status_face = face(editor, colors, if selected { theme::STATUS_LINE } else { theme::STATUS_LINE_INACTIVE });
+    let y = area.y + area.height.saturating_sub(1);
+    screen.fill(area.x, y, width, status_face);
+    screen.put(area.x, y, truncate(&status_line, width), status_face);
+}
+
+/// line_spans() is what `line` of `buffer` is styled with, in the order it is drawn: its syntax,
+/// then its text properties.
+fn line_spans(buffer: &GapBuffer, highlighter: Option<&dyn Highlighter>, line: usize) -> Vec<StyleSpan> {
+    let mut spans = highlighter.map_or_else(Vec::new, |highlighter| highlighter.line_spans(buffer, line));
+    for (range, property) in buffer.line_properties(line) {
+        if let Some(style) = property.style.as_ref() {
+            spans.push(StyleSpan { range, style: style.clone() });
+        }
+    }
+
+    spans
+}
+
+/// face() is how the style called `name` is drawn in the editor's theme, in colors the terminal
+/// supports.
+fn face(editor: &Editor, colors: ColorSupport, name: &str) -> Face {
+    editor.theme().face(name).downgrade(colors)
+}
+
+/// expand_tabs() is the part of `text` drawn from column `first_column` for `width` columns,
+/// with tabs turned into the spaces that reach the next multiple of `tab_width`. A wide character
+/// cut by either edge is drawn as spaces.
+fn expand_tabs(text: &str, first_column: usize, width: usize, tab_width: usize) -> String {
+    let last_column = first_column + width;
+    let mut expanded = String::new();
+    let mut column = 0;
+
+    for character in text.chars() {
+        let next = advance(column, character, tab_width);
+        if next > last_column {
+            expanded.extend(iter::repeat_n(' ', last_column.saturating_sub(column.max(first_column))));
+            break;
+        }
+
+        if character == '\t' || column < first_column {
+            expanded.extend(iter::repeat_n(' ', next.saturating_sub(column.max(first_column))));
+        } else {
+            expanded.push(character);
+        }
+        column = next;
+    }
+
+    expanded
+}
+
+/// truncate() is as much of `text` as fits in `width` columns, with tabs at their default width.
+fn truncate(text: &str, width: usize) -> &str {
+    let mut column = 0;
+
+    for (index, character) in text.char_indices() {
+        column = advance(column, character, DEFAULT_TAB_WIDTH);
+        if column > width {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyCode as TermKeyCode, KeyModifiers};
+
+    use keymap::{Key, KeyCode};
+    use crossterm::event::{MouseButton, MouseEvent as TermMouseEvent, MouseEventKind};
+
+    use editor::{MouseAction, MouseEvent};
+    use property::TextProperty;
+    use terminal::{expand_tabs, key_from_event, line_spans, mouse_from_event, truncate};
+    use GapBuffer;
+
+    #[test]
+    fn terminal_keys_become_editor_keys() {
+        let test_cases = [
+            ("a control key", TermKeyCode::Char('x'), KeyModifiers::CONTROL, "C-x"),
+            ("a meta key", TermKeyCode::Char('<'), KeyModifiers::ALT, "M-<"),
+            ("a shifted character", TermKeyCode::Char('A'), KeyModifiers::SHIFT, "A"),
+            ("a named key", TermKeyCode::Enter, KeyModifiers::NONE, "RET"),
+            ("a shifted arrow", TermKeyCode::Left, KeyModifiers::SHIFT, "S-<left>"),
+        ];
+
+        for (name, code, modifiers, expected) in test_cases.iter() {
+            let key = key_from_event(*code, *modifiers);
+
+            assert_eq!(key, Some(Key::parse(expected).unwrap()), "Test case: \"{}\" failed.", name);
+        }
+        assert_eq!(key_from_event(TermKeyCode::Null, KeyModifiers::NONE), None);
+        assert_eq!(key_from_event(TermKeyCode::Tab, KeyModifiers::NONE).unwrap().code, KeyCode::Tab);
+    }
+
+    #[test]
+    fn tabs_are_expanded_for_display() {
+        assert_eq!(expand_tabs("a\tb", 0, 20, 8), "a       b");
+        assert_eq!(expand_tabs("a\tb\tc", 0, 20, 4), "a   b   c");
+        assert_eq!(expand_tabs("abcdef", 0, 4, 8), "abcd");
+        assert_eq!(expand_tabs("a\tbcdef", 6, 4, 8), "  bc");
+        assert_eq!(expand_tabs("日本語", 1, 4, 8), " 本 ");
+        assert_eq!(truncate("日本語", 5), "日本");
+    }
+
+    #[test]
+    fn terminal_mouse_events_become_editor_events() {
+        let event = |kind| TermMouseEvent {
+            kind,
+            column: 3,
+            row: 4,
+            modifiers: KeyModifiers::NONE,
+        };
+
+        let test_cases = [
+            ("a press", MouseEventKind::Down(MouseButton::Left), Some(MouseAction::Press)),
+            ("a drag", MouseEventKind::Drag(MouseButton::Left), Some(MouseAction::Drag)),
+            ("the wheel", MouseEventKind::ScrollDown, Some(MouseAction::WheelDown)),
+            ("another button", MouseEventKind::Down(MouseButton::Right), None),
+            ("a movement", MouseEventKind::Moved, None),
+        ];
+
+        for (name, kind, expected) in test_cases.iter() {
+            let actual = mouse_from_event(event(*kind));
+
+            let expected = expected.map(|action| MouseEvent { action, column: 3, row: 4 });
+            assert_eq!(actual, expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn text_properties_are_drawn_with_their_style() {
+        let mut buffer = GapBuffer::from("one two\nthree".to_string());
+        buffer.add_property(4..11, TextProperty::styled("search", "search-match"));
+        buffer.add_property(0..3, TextProperty::default());
+
+        let spans = line_spans(&buffer, None, 0);
+        assert_eq!(spans.iter().map(|span| (span.range.clone(), span.style.as_str())).collect::<Vec<_>>(), vec![(4..7, "search-match")]);
+        assert_eq!(line_spans(&buffer, None, 1)[0].range, 8..11);
+    }
+}
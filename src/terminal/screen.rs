@@ -0,0 +1,387 @@
+use std::io::{self, Write};
+use std::ops::Range;
+
+use crossterm::style::{Attribute, Color as TermColor, Print, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, queue};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use theme::{Color, Face};
+
+/// How many more rows a scroll must leave in place than repainting before it is worth doing.
+const SCROLL_THRESHOLD: usize = 3;
+
+/// Cell is one column of the screen. It holds a character with any combining marks after it, or
+/// nothing if it is the second column of a wide character to its left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    pub face: Face,
+}
+
+impl Cell {
+    fn blank(face: Face) -> Cell {
+        Cell {
+            text: " ".to_string(),
+            face,
+        }
+    }
+
+    fn is_continuation(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+/// Screen is what the terminal shows: a grid of cells and where the cursor is. Frames are drawn
+/// into a screen and then compared with the last one flushed, so only what changed is sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    width: usize,
+    height: usize,
+    rows: Vec<Vec<Cell>>,
+    cursor: (usize, usize),
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Screen {
+        Screen {
+            width,
+            height,
+            rows: vec![vec![Cell::blank(Face::default()); width]; height],
+            cursor: (0, 0),
+        }
+    }
+
+    /// put() writes `text` from column `x` of row `y` in `face`, cut off at the edge of the
+    /// screen. Wide characters take two cells, and one that would not fit at the edge is left
+    /// out. Combining marks join the character before them.
+    pub fn put(&mut self, x: usize, y: usize, text: &str, face: Face) {
+        let width = self.width;
+        let row = match self.rows.get_mut(y) {
+            Some(row) => row,
+            None => return,
+        };
+
+        let mut column = x;
+        for grapheme in text.graphemes(true) {
+            let grapheme_width = grapheme.width();
+            if grapheme_width == 0 {
+                if column > x {
+                    let lead = (x..column).rev().find(|&lead| !row[lead].is_continuation()).unwrap_or(x);
+                    row[lead].text.push_str(grapheme);
+                }
+                continue;
+            }
+            if column + grapheme_width > width {
+                break;
+            }
+
+            clear_wide_characters(row, column, grapheme_width);
+            row[column] = Cell {
+                text: grapheme.to_string(),
+                face,
+            };
+            for continuation in row.iter_mut().skip(column + 1).take(grapheme_width - 1) {
+                *continuation = Cell { text: String::new(), face };
+            }
+            column += grapheme_width;
+        }
+    }
+
+    /// fill() sets `width` cells from column `x` of row `y` to blanks in `face`.
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, face: Face) {
+        if let Some(row) = self.rows.get_mut(y) {
+            let width = width.min(row.len().saturating_sub(x));
+            clear_wide_characters(row, x, width);
+            for cell in row.iter_mut().skip(x).take(width) {
+                *cell = Cell::blank(face);
+            }
+        }
+    }
+
+    /// apply_face() draws `face` over the faces of `width` cells from column `x` of row `y`,
+    /// keeping what is in them.
+    pub fn apply_face(&mut self, x: usize, y: usize, width: usize, face: Face) {
+        if let Some(row) = self.rows.get_mut(y) {
+            for cell in row.iter_mut().skip(x).take(width) {
+                cell.face = face.over(&cell.face);
+            }
+        }
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        self.cursor = (x, y);
+    }
+
+    /// flush() sends `out` what it takes to turn `previous`, what the terminal last showed, into
+    /// this screen: every row if there is nothing to compare with or the size changed, otherwise
+    /// a scroll if that leaves more rows right, and then the changed part of each row that
+    /// still differs.
+    pub fn flush<W: Write>(&self, out: &mut W, previous: Option<&Screen>) -> io::Result<()> {
+        queue!(out, cursor::Hide)?;
+
+        let mut shown = match previous {
+            Some(previous) if previous.width == self.width && previous.height == self.height => previous.clone(),
+            _ => {
+                queue!(out, terminal::Clear(ClearType::All))?;
+                Screen::new(self.width, self.height)
+            }
+        };
+
+        let scroll = best_scroll(&shown.rows, &self.rows);
+        if scroll > 0 {
+            queue!(out, terminal::ScrollUp(scroll as u16))?;
+            shown.rows.drain(..scroll as usize);
+            shown.rows.resize(self.height, vec![Cell::blank(Face::default()); self.width]);
+        } else if scroll < 0 {
+            queue!(out, terminal::ScrollDown(scroll.unsigned_abs() as u16))?;
+            shown.rows.truncate(self.height - scroll.unsigned_abs());
+            for _ in 0..scroll.unsigned_abs() {
+                shown.rows.insert(0, vec![Cell::blank(Face::default()); self.width]);
+            }
+        }
+
+        for (y, (before, after)) in shown.rows.iter().zip(self.rows.iter()).enumerate() {
+            if let Some(span) = changed_span(before, after) {
+                queue!(out, cursor::MoveTo(span.start as u16, y as u16))?;
+                write_cells(out, &after[span])?;
+            }
+        }
+
+        queue!(out, cursor::MoveTo(self.cursor.0 as u16, self.cursor.1 as u16), cursor::Show)?;
+        out.flush()
+    }
+}
+
+/// clear_wide_characters() blanks the parts outside `x..x + width` of any wide characters that
+/// are partly inside it, before it is overwritten.
+fn clear_wide_characters(row: &mut [Cell], x: usize, width: usize) {
+    if width == 0 {
+        return;
+    }
+
+    if row[x].is_continuation() {
+        let lead = (0..x).rev().find(|&lead| !row[lead].is_continuation()).unwrap_or(0);
+        for cell in row[lead..x].iter_mut() {
+            *cell = Cell::blank(cell.face);
+        }
+    }
+    let end = (x + width).min(row.len());
+    for cell in row[end..].iter_mut().take_while(|cell| cell.is_continuation()) {
+        *cell = Cell::blank(cell.face);
+    }
+}
+
+/// write_cells() prints `cells`, changing attributes only where the face changes.
+fn write_cells<W: Write>(out: &mut W, cells: &[Cell]) -> io::Result<()> {
+    let mut face = Face::default();
+    let mut text = String::new();
+
+    for cell in cells {
+        if cell.face != face {
+            write_run(out, &text, face)?;
+            text.clear();
+            face = cell.face;
+        }
+        text.push_str(&cell.text);
+    }
+
+    write_run(out, &text, face)
+}
+
+fn write_run<W: Write>(out: &mut W, text: &str, face: Face) -> io::Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let attributes = [
+        (face.bold, Attribute::Bold),
+        (face.dim, Attribute::Dim),
+        (face.italic, Attribute::Italic),
+        (face.underline, Attribute::Underlined),
+        (face.reverse, Attribute::Reverse),
+    ];
+    for (_, attribute) in attributes.iter().filter(|(on, _)| *on) {
+        queue!(out, SetAttribute(*attribute))?;
+    }
+    if let Some(color) = term_color(face.foreground) {
+        queue!(out, SetForegroundColor(color))?;
+    }
+    if let Some(color) = term_color(face.background) {
+        queue!(out, SetBackgroundColor(color))?;
+    }
+    queue!(out, Print(text))?;
+    if face != Face::default() {
+        queue!(out, SetAttribute(Attribute::Reset))?;
+    }
+    Ok(())
+}
+
+/// term_color() is how `color` is sent to the terminal, or None to leave the terminal's own. The
+/// ANSI colors are sent as such, since a terminal with only those does not understand palette
+/// indexes.
+fn term_color(color: Color) -> Option<TermColor> {
+    const ANSI: [TermColor; 16] = [
+        TermColor::Black,
+        TermColor::DarkRed,
+        TermColor::DarkGreen,
+        TermColor::DarkYellow,
+        TermColor::DarkBlue,
+        TermColor::DarkMagenta,
+        TermColor::DarkCyan,
+        TermColor::Grey,
+        TermColor::DarkGrey,
+        TermColor::Red,
+        TermColor::Green,
+        TermColor::Yellow,
+        TermColor::Blue,
+        TermColor::Magenta,
+        TermColor::Cyan,
+        TermColor::White,
+    ];
+
+    match color {
+        Color::Default => None,
+        Color::Indexed(index) if index < 16 => Some(ANSI[index as usize]),
+        Color::Indexed(index) => Some(TermColor::AnsiValue(index)),
+        Color::Rgb(r, g, b) => Some(TermColor::Rgb { r, g, b }),
+    }
+}
+
+/// changed_span() is the range of columns from the first cell that differs between `before` and
+/// `after` to the last one, or None if they are the same. It starts at a whole character, so a
+/// wide character is always printed from its first column.
+fn changed_span(before: &[Cell], after: &[Cell]) -> Option<Range<usize>> {
+    let mut start = before.iter().zip(after.iter()).position(|(a, b)| a != b)?;
+    let end = after.len() - before.iter().rev().zip(after.iter().rev()).position(|(a, b)| a != b).unwrap();
+
+    while start > 0 && after[start].is_continuation() {
+        start -= 1;
+    }
+    Some(start..end)
+}
+
+/// best_scroll() is how many rows to scroll the terminal up, or down for a negative count, so
+/// that the most rows of `before` end up where they are in `after`. It is 0 unless scrolling
+/// saves repainting at least `SCROLL_THRESHOLD` rows.
+fn best_scroll(before: &[Vec<Cell>], after: &[Vec<Cell>]) -> isize {
+    let height = before.len().min(after.len());
+    let unchanged = (0..height).filter(|&y| before[y] == after[y]).count();
+    let mut best = (0, unchanged + SCROLL_THRESHOLD - 1);
+
+    for shift in 1..height {
+        let up = (0..height - shift).filter(|&y| before[y + shift] == after[y]).count();
+        let down = (0..height - shift).filter(|&y| before[y] == after[y + shift]).count();
+
+        if up > best.1 {
+            best = (shift as isize, up);
+        }
+        if down > best.1 {
+            best = (-(shift as isize), down);
+        }
+    }
+
+    best.0
+}
+
+#[cfg(test)]
+mod tests {
+    use terminal::screen::{best_scroll, changed_span, Screen};
+    use theme::Face;
+
+    fn screen(lines: &[&str]) -> Screen {
+        let mut screen = Screen::new(10, lines.len());
+        for (y, line) in lines.iter().enumerate() {
+            screen.put(0, y, line, Face::default());
+        }
+
+        screen
+    }
+
+    #[test]
+    fn only_changed_columns_are_repainted() {
+        let before = screen(&["the quick", "brown fox"]);
+        let mut after = before.clone();
+        after.put(4, 0, "slow", Face::default());
+
+        assert_eq!(changed_span(&before.rows[0], &after.rows[0]), Some(4..8));
+        assert_eq!(changed_span(&before.rows[1], &after.rows[1]), None);
+
+        after.fill(0, 1, 10, Face { reverse: true, ..Face::default() });
+        assert_eq!(changed_span(&before.rows[1], &after.rows[1]), Some(0..10));
+    }
+
+    #[test]
+    fn faces_are_drawn_over_each_other() {
+        let mut screen = screen(&["region"]);
+        let bold = Face { bold: true, ..Face::default() };
+        screen.put(0, 0, "re", bold);
+
+        screen.apply_face(1, 0, 3, Face { reverse: true, ..Face::default() });
+        let faces: Vec<(bool, bool)> = screen.rows[0].iter().take(5).map(|cell| (cell.face.bold, cell.face.reverse)).collect();
+        assert_eq!(faces, vec![(true, false), (true, true), (false, true), (false, true), (false, false)]);
+    }
+
+    #[test]
+    fn scroll_when_it_saves_repainting() {
+        let lines = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+        let before = screen(&lines);
+
+        let test_cases = [
+            ("unchanged", lines.to_vec(), 0),
+            ("scrolled up", vec!["2", "3", "4", "5", "6", "7", "8", "9", "10", "11"], 2),
+            ("scrolled down", vec!["a", "0", "1", "2", "3", "4", "5", "6", "7", "8"], -1),
+            ("too little in common", vec!["9", "a", "b", "c", "d", "e", "f", "g", "h", "i"], 0),
+        ];
+
+        for (name, after, expected) in test_cases.iter() {
+            let after = screen(after);
+
+            assert_eq!(best_scroll(&before.rows, &after.rows), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn flush_sends_nothing_for_an_unchanged_screen() {
+        let before = screen(&["the quick", "brown fox"]);
+        let mut full = Vec::new();
+        let mut incremental = Vec::new();
+
+        before.flush(&mut full, None).unwrap();
+        before.flush(&mut incremental, Some(&before)).unwrap();
+
+        assert!(String::from_utf8_lossy(&full).contains("the quick"));
+        assert!(!String::from_utf8_lossy(&incremental).contains("quick"));
+    }
+
+    #[test]
+    fn wide_characters_take_two_cells() {
+        let test_cases = [
+            ("a wide character", vec![(0, "日本")], "日本      "),
+            ("a combining mark", vec![(0, "e\u{301}x")], "e\u{301}x        "),
+            ("too wide for the edge", vec![(9, "日")], "          "),
+            ("over the first half", vec![(0, "日本"), (0, "a")], "a 本      "),
+            ("over the second half", vec![(0, "日本"), (1, "b")], " b本      "),
+        ];
+
+        for (name, puts, expected) in test_cases.iter() {
+            let mut screen = Screen::new(10, 1);
+            for (x, text) in puts.iter() {
+                screen.put(*x, 0, text, Face::default());
+            }
+
+            let row: String = screen.rows[0].iter().map(|cell| cell.text.as_str()).collect();
+            assert_eq!(row, *expected, "Test case: \"{}\" failed.", name);
+        }
+
+        let before = screen(&["ab日本"]);
+        let mut after = before.clone();
+        after.put(4, 0, "語", Face::default());
+        assert_eq!(changed_span(&before.rows[0], &after.rows[0]), Some(4..5));
+    }
+}
@@ -0,0 +1,773 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use toml::{Table, Value};
+
+/// The style of text with no other style. Every other style is drawn over it.
+pub const DEFAULT: &str = "default";
+/// The style of the region.
+pub const SELECTION: &str = "selection";
+/// The style of the selected window's status line.
+pub const STATUS_LINE: &str = "status-line";
+/// The style of the other windows' status lines.
+pub const STATUS_LINE_INACTIVE: &str = "status-line-inactive";
+/// The style of the line numbers in the gutter.
+pub const LINE_NUMBER: &str = "line-number";
+/// The style of text matching a search.
+pub const SEARCH_MATCH: &str = "search-match";
+/// The style of the match an incremental search is at.
+pub const SEARCH_MATCH_CURRENT: &str = "search-match.current";
+/// The style of the bracket or quote at the point and the one it matches.
+pub const MATCHING_BRACKET: &str = "matching-bracket";
+/// The styles of the text diagnostics are about, by severity. A theme can style them all with
+/// `diagnostic`.
+pub const DIAGNOSTIC_ERROR: &str = "diagnostic.error";
+pub const DIAGNOSTIC_WARNING: &str = "diagnostic.warning";
+pub const DIAGNOSTIC_INFORMATION: &str = "diagnostic.info";
+pub const DIAGNOSTIC_HINT: &str = "diagnostic.hint";
+/// The style of the completion popup.
+pub const COMPLETION: &str = "completion";
+/// The style of the candidate chosen in the completion popup.
+pub const COMPLETION_SELECTED: &str = "completion.selected";
+/// The styles of the signs in the gutter beside lines added, changed or removed since the last
+/// commit. A theme can style them all with `git`.
+pub const GIT_ADDED: &str = "git.added";
+pub const GIT_MODIFIED: &str = "git.modified";
+pub const GIT_REMOVED: &str = "git.removed";
+/// The styles of the lines of a diff, by what they say. A theme can style them all with `diff`.
+pub const DIFF_FILE_HEADER: &str = "diff.file-header";
+pub const DIFF_HUNK_HEADER: &str = "diff.hunk-header";
+pub const DIFF_ADDED: &str = "diff.added";
+pub const DIFF_REMOVED: &str = "diff.removed";
+/// The styles of syntax scopes. A scope such as `syntax.keyword.control` that a theme does not
+/// set is drawn in the style of the scope it is part of, here `syntax.keyword`.
+pub const SYNTAX_SCOPES: &[&str] = &[
+    "syntax.comment",
+    "syntax.keyword",
+    "syntax.string",
+    "syntax.number",
+    "syntax.constant",
+    "syntax.type",
+    "syntax.function",
+    "syntax.variable",
+    "syntax.operator",
+    "syntax.punctuation",
+];
+
+/// The names of the 16 ANSI colors, which are also indexes 0 to 15 of the 256-color palette.
+const ANSI_NAMES: [&str; 16] = [
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "bright-black",
+    "bright-red",
+    "bright-green",
+    "bright-yellow",
+    "bright-blue",
+    "bright-magenta",
+    "bright-cyan",
+    "bright-white",
+];
+
+/// Roughly how terminals show the 16 ANSI colors, for finding the nearest one to another color.
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The levels of each channel in the 6×6×6 color cube at indexes 16 to 231 of the 256-color
+/// palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+const DEFAULT_THEME: &str = r##"
+name = "default"
+
+[styles]
+selection = { reverse = true }
+status-line = { reverse = true, bold = true }
+status-line-inactive = { reverse = true, dim = true }
+line-number = { dim = true }
+matching-bracket = { background = "cyan", foreground = "black" }
+
+[styles.search-match]
+background = "yellow"
+foreground = "black"
+current = { background = "magenta", foreground = "white", bold = true }
+
+[styles.completion]
+reverse = true
+selected = { background = "blue", foreground = "white", bold = true }
+
+[styles.diagnostic]
+underline = true
+error = { foreground = "red", underline = true }
+warning = { foreground = "yellow", underline = true }
+
+[styles.git]
+added = { foreground = "green" }
+modified = { foreground = "yellow" }
+removed = { foreground = "red" }
+
+[styles.diff]
+file-header = { bold = true }
+hunk-header = { foreground = "cyan" }
+added = { foreground = "green" }
+removed = { foreground = "red" }
+
+[styles.syntax]
+comment = { foreground = "bright-black" }
+keyword = { foreground = "magenta" }
+string = { foreground = "green" }
+number = { foreground = "cyan" }
+constant = { foreground = "cyan" }
+type = { foreground = "yellow" }
+function = { foreground = "blue" }
+"##;
+
+const DARK_THEME: &str = r##"
+name = "dark"
+
+[styles]
+default = { foreground = "#d0d0d0", background = "#1c1c1c" }
+selection = { background = "#3a3a5a" }
+status-line = { foreground = "#1c1c1c", background = "#87afd7", bold = true }
+status-line-inactive = { foreground = "#bcbcbc", background = "#3a3a3a" }
+line-number = { foreground = "#6c6c6c" }
+matching-bracket = { background = "#4e4e4e", bold = true }
+
+[styles.search-match]
+foreground = "#1c1c1c"
+background = "#d7af5f"
+current = { foreground = "#1c1c1c", background = "#d787d7", bold = true }
+
+[styles.completion]
+foreground = "#d0d0d0"
+background = "#3a3a3a"
+selected = { foreground = "#1c1c1c", background = "#87afd7" }
+
+[styles.diagnostic]
+underline = true
+error = { foreground = "#ff5f5f", underline = true }
+warning = { foreground = "#d7af5f", underline = true }
+info = { foreground = "#87afd7", underline = true }
+
+[styles.git]
+added = { foreground = "#87af87" }
+modified = { foreground = "#d7af5f" }
+removed = { foreground = "#ff5f5f" }
+
+[styles.diff]
+file-header = { foreground = "#d0d0d0", bold = true }
+hunk-header = { foreground = "#87afd7" }
+added = { foreground = "#87af87" }
+removed = { foreground = "#ff5f5f" }
+
+[styles.syntax]
+comment = { foreground = "#808080", italic = true }
+keyword = { foreground = "#d787d7" }
+string = { foreground = "#87af87" }
+number = { foreground = "#d7875f" }
+constant = { foreground = "#d7875f" }
+type = { foreground = "#d7d787" }
+function = { foreground = "#87afd7" }
+operator = { foreground = "#afafaf" }
+"##;
+
+const LIGHT_THEME: &str = r##"
+name = "light"
+
+[styles]
+default = { foreground = "#303030", background = "#fafafa" }
+selection = { background = "#c6dbf0" }
+status-line = { foreground = "#fafafa", background = "#4a6d8c", bold = true }
+status-line-inactive = { foreground = "#303030", background = "#d0d0d0" }
+line-number = { foreground = "#a0a0a0" }
+matching-bracket = { background = "#c6e2c6", bold = true }
+
+[styles.search-match]
+background = "#ffd75f"
+current = { background = "#ffaf5f", bold = true }
+
+[styles.completion]
+foreground = "#303030"
+background = "#e4e4e4"
+selected = { foreground = "#fafafa", background = "#4a6d8c" }
+
+[styles.diagnostic]
+underline = true
+error = { foreground = "#d70000", underline = true }
+warning = { foreground = "#af8700", underline = true }
+info = { foreground = "#005faf", underline = true }
+
+[styles.git]
+added = { foreground = "#3a7a3a" }
+modified = { foreground = "#af8700" }
+removed = { foreground = "#d70000" }
+
+[styles.diff]
+file-header = { foreground = "#303030", bold = true }
+hunk-header = { foreground = "#005faf" }
+added = { foreground = "#3a7a3a" }
+removed = { foreground = "#d70000" }
+
+[styles.syntax]
+comment = { foreground = "#8a8a8a", italic = true }
+keyword = { foreground = "#8700af" }
+string = { foreground = "#3a7a3a" }
+number = { foreground = "#af5f00" }
+constant = { foreground = "#af5f00" }
+type = { foreground = "#875f00" }
+function = { foreground = "#005faf" }
+"##;
+
+/// The built-in themes, by name.
+const BUILTIN_THEMES: [(&str, &str); 3] = [("default", DEFAULT_THEME), ("dark", DARK_THEME), ("light", LIGHT_THEME)];
+
+/// Color is the color of text or of the background behind it.
+/// - `Default`: Whatever the terminal uses, or when drawing one style over another, the color
+///   underneath.
+/// - `Indexed`: A color of the 256-color palette. The first 16 are the ANSI colors.
+/// - `Rgb`: A true color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Color {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// parse() reads a color written as `default`, an ANSI color name such as `bright-red`, or
+    /// `#rrggbb`.
+    pub fn parse(text: &str) -> Option<Color> {
+        if text == "default" {
+            return Some(Color::Default);
+        }
+        if let Some(index) = ANSI_NAMES.iter().position(|name| *name == text) {
+            return Some(Color::Indexed(index as u8));
+        }
+
+        let hex = text.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.is_ascii())?;
+        let channel = |range| u8::from_str_radix(&hex[range], 16).ok();
+        Some(Color::Rgb(channel(0..2)?, channel(2..4)?, channel(4..6)?))
+    }
+
+    /// downgrade() is the nearest color a terminal with `support` can show.
+    pub fn downgrade(self, support: ColorSupport) -> Color {
+        match (self, support) {
+            (Color::Default, _) | (_, ColorSupport::TrueColor) => self,
+            (Color::Indexed(index), ColorSupport::Indexed256) => Color::Indexed(index),
+            (Color::Indexed(index), ColorSupport::Ansi16) if index < 16 => Color::Indexed(index),
+            (Color::Indexed(index), ColorSupport::Ansi16) => Color::Indexed(nearest_ansi(indexed_rgb(index))),
+            (Color::Rgb(r, g, b), ColorSupport::Indexed256) => Color::Indexed(nearest_indexed((r, g, b))),
+            (Color::Rgb(r, g, b), ColorSupport::Ansi16) => Color::Indexed(nearest_ansi((r, g, b))),
+        }
+    }
+}
+
+/// ColorSupport is which colors a terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    Ansi16,
+    Indexed256,
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// detect() guesses what the terminal the editor runs in supports from `COLORTERM` and
+    /// `TERM`.
+    pub fn detect() -> ColorSupport {
+        let colorterm = env::var("COLORTERM").ok();
+        let term = env::var("TERM").ok();
+
+        ColorSupport::from_env(colorterm.as_deref(), term.as_deref())
+    }
+
+    /// from_env() is what a terminal supports given the values of `COLORTERM` and `TERM`.
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> ColorSupport {
+        match (colorterm, term) {
+            (Some("truecolor"), _) | (Some("24bit"), _) => ColorSupport::TrueColor,
+            (_, Some(term)) if term.contains("256color") => ColorSupport::Indexed256,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+}
+
+/// Face is how text in a style is drawn: its colors and attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Face {
+    pub foreground: Color,
+    pub background: Color,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Face {
+    /// over() is `self` drawn over `under`: its colors where it has them and the colors of
+    /// `under` elsewhere, with the attributes of both.
+    pub fn over(&self, under: &Face) -> Face {
+        let color = |color, under| if color == Color::Default { under } else { color };
+
+        Face {
+            foreground: color(self.foreground, under.foreground),
+            background: color(self.background, under.background),
+            bold: self.bold || under.bold,
+            dim: self.dim || under.dim,
+            italic: self.italic || under.italic,
+            underline: self.underline || under.underline,
+            reverse: self.reverse || under.reverse,
+        }
+    }
+
+    /// downgrade() is the face with its colors changed to the nearest ones a terminal with
+    /// `support` can show.
+    pub fn downgrade(&self, support: ColorSupport) -> Face {
+        Face {
+            foreground: self.foreground.downgrade(support),
+            background: self.background.downgrade(support),
+            ..*self
+        }
+    }
+}
+
+/// ThemeError is why a theme could not be loaded.
+#[derive(Debug)]
+pub enum ThemeError {
+    /// There is no built-in theme with the name.
+    UnknownTheme(String),
+    /// The file is not valid TOML.
+    Parse(String),
+    /// A style in the file has a value that is not understood.
+    InvalidStyle { style: String, message: String },
+    Io(io::Error),
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThemeError::UnknownTheme(name) => write!(f, "Unknown theme: {}", name),
+            ThemeError::Parse(message) => write!(f, "Invalid theme: {}", message),
+            ThemeError::InvalidStyle { style, message } => write!(f, "Invalid style {}: {}", style, message),
+            ThemeError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl Error for ThemeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ThemeError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ThemeError {
+    fn from(error: io::Error) -> ThemeError {
+        ThemeError::Io(error)
+    }
+}
+
+/// Theme is a set of named styles, such as `selection` or `syntax.keyword`, and the faces they
+/// are drawn with.
+///
+/// A theme file is TOML with an optional `name`, an optional `inherits` naming a built-in theme
+/// to start from, and a `styles` table of faces:
+///
+/// ```toml
+/// name = "mine"
+/// inherits = "dark"
+///
+/// [styles]
+/// selection = { background = "#444444" }
+/// "syntax.keyword" = { foreground = "magenta", bold = true }
+/// ```
+///
+/// A face has `foreground` and `background` colors, each `default`, an ANSI color name such as
+/// `bright-red`, `#rrggbb` or a 256-color palette index, and `bold`, `dim`, `italic`,
+/// `underline` and `reverse` flags. Nested tables such as `[styles.syntax]` name styles by
+/// their path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    name: String,
+    faces: HashMap<String, Face>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::builtin("default").unwrap()
+    }
+}
+
+impl Theme {
+    /// new() returns a theme called `name` with no styles, which draws everything in the
+    /// terminal's own colors.
+    pub fn new(name: &str) -> Theme {
+        Theme {
+            name: name.to_string(),
+            faces: HashMap::new(),
+        }
+    }
+
+    /// builtin() returns the built-in theme called `name`.
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let (_, text) = BUILTIN_THEMES.iter().find(|(builtin, _)| *builtin == name)?;
+        Some(Theme::from_toml(text).expect("built-in themes are valid"))
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_THEMES.iter().map(|(name, _)| *name)
+    }
+
+    /// load() reads a theme from the TOML file at `path`. A theme without a name is named after
+    /// the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, ThemeError> {
+        let path = path.as_ref();
+        let mut theme = Theme::from_toml(&fs::read_to_string(path)?)?;
+        if theme.name.is_empty() {
+            theme.name = path.file_stem().map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        }
+        Ok(theme)
+    }
+
+    /// from_toml() reads a theme from the TOML in `text`.
+    pub fn from_toml(text: &str) -> Result<Theme, ThemeError> {
+        let table = text.parse::<Table>().map_err(|error| ThemeError::Parse(error.message().to_string()))?;
+
+        let mut theme = match table.get("inherits") {
+            Some(Value::String(name)) => {
+                Theme::builtin(name).ok_or_else(|| ThemeError::UnknownTheme(name.to_string()))?
+            }
+            Some(_) => return Err(ThemeError::Parse("inherits must be the name of a theme".to_string())),
+            None => Theme::new(""),
+        };
+        theme.name = match table.get("name") {
+            Some(Value::String(name)) => name.to_string(),
+            Some(_) => return Err(ThemeError::Parse("name must be a string".to_string())),
+            None => String::new(),
+        };
+
+        match table.get("styles") {
+            Some(Value::Table(styles)) => read_styles(&mut theme, "", styles)?,
+            Some(_) => return Err(ThemeError::Parse("styles must be a table".to_string())),
+            None => {}
+        }
+        Ok(theme)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// set_face() draws the style called `name` with `face` from now on.
+    pub fn set_face(&mut self, name: &str, face: Face) {
+        self.faces.insert(name.to_string(), face);
+    }
+
+    /// face() is how to draw text in the style called `name`: its own face, or that of the
+    /// nearest scope it is part of, over the default face.
+    pub fn face(&self, name: &str) -> Face {
+        let default = self.faces.get(DEFAULT).cloned().unwrap_or_default();
+
+        self.overlay(name).over(&default)
+    }
+
+    /// overlay() is the face of the style called `name`, or of the nearest scope it is part of,
+    /// alone. It is for drawing over text that already has a face, as the selection is. The
+    /// styles of terminal output, such as `ansi.foreground.red`, are drawn as they say unless the
+    /// theme sets them.
+    pub fn overlay(&self, name: &str) -> Face {
+        if let (None, Some(face)) = (self.faces.get(name), ansi_face(name)) {
+            return face;
+        }
+
+        let mut scope = name;
+
+        loop {
+            if let Some(face) = self.faces.get(scope) {
+                return *face;
+            }
+            match scope.rfind('.') {
+                Some(dot) => scope = &scope[..dot],
+                None => return Face::default(),
+            }
+        }
+    }
+}
+
+/// ansi_color_name() writes color `index` of the 256-color palette as a theme reads it: the name
+/// of an ANSI color, or else the index.
+pub(crate) fn ansi_color_name(index: u8) -> String {
+    match ANSI_NAMES.get(index as usize) {
+        Some(name) => name.to_string(),
+        None => index.to_string(),
+    }
+}
+
+/// ansi_face() is the face of a style terminal output is marked with: `ansi.foreground.COLOR` or
+/// `ansi.background.COLOR`, with COLOR an ANSI color name, a palette index or `#rrggbb`, or one
+/// of `ansi.bold`, `ansi.dim`, `ansi.italic`, `ansi.underline` and `ansi.reverse`.
+fn ansi_face(name: &str) -> Option<Face> {
+    let style = name.strip_prefix("ansi.")?;
+    let color = |text: &str| text.parse().ok().map(Color::Indexed).or_else(|| Color::parse(text));
+    let mut face = Face::default();
+
+    match style.split_once('.') {
+        Some(("foreground", text)) => face.foreground = color(text)?,
+        Some(("background", text)) => face.background = color(text)?,
+        Some(_) => return None,
+        None => match style {
+            "bold" => face.bold = true,
+            "dim" => face.dim = true,
+            "italic" => face.italic = true,
+            "underline" => face.underline = true,
+            "reverse" => face.reverse = true,
+            _ => return None,
+        },
+    }
+    Some(face)
+}
+
+/// read_styles() sets the faces in `table` on `theme`, naming each by its path after `prefix`.
+/// The values in a table that are not tables themselves make up the face of its own path.
+fn read_styles(theme: &mut Theme, prefix: &str, table: &Table) -> Result<(), ThemeError> {
+    let mut face = Face::default();
+    let mut has_face = false;
+
+    for (key, value) in table {
+        let path = if prefix.is_empty() { key.to_string() } else { format!("{}.{}", prefix, key) };
+        if let Value::Table(table) = value {
+            read_styles(theme, &path, table)?;
+            continue;
+        }
+
+        let invalid = |message: &str| ThemeError::InvalidStyle {
+            style: prefix.to_string(),
+            message: format!("{} {}", key, message),
+        };
+        has_face = true;
+        match (key.as_str(), value) {
+            ("foreground", value) => face.foreground = read_color(value).ok_or_else(|| invalid("is not a color"))?,
+            ("background", value) => face.background = read_color(value).ok_or_else(|| invalid("is not a color"))?,
+            ("bold", Value::Boolean(on)) => face.bold = *on,
+            ("dim", Value::Boolean(on)) => face.dim = *on,
+            ("italic", Value::Boolean(on)) => face.italic = *on,
+            ("underline", Value::Boolean(on)) => face.underline = *on,
+            ("reverse", Value::Boolean(on)) => face.reverse = *on,
+            ("bold", _) | ("dim", _) | ("italic", _) | ("underline", _) | ("reverse", _) => {
+                return Err(invalid("must be true or false"))
+            }
+            _ => return Err(invalid("is not a face attribute")),
+        }
+    }
+
+    if has_face {
+        theme.set_face(prefix, face);
+    }
+    Ok(())
+}
+
+/// read_color() reads a color written as a string Color::parse() understands or as a palette
+/// index.
+fn read_color(value: &Value) -> Option<Color> {
+    match value {
+        Value::String(text) => Color::parse(text),
+        Value::Integer(index) => u8::try_from(*index).ok().map(Color::Indexed),
+        _ => None,
+    }
+}
+
+/// indexed_rgb() is roughly how a terminal shows color `index` of the 256-color palette.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI_RGB[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            let level = |value: u8| CUBE_LEVELS[value as usize];
+            (level(cube / 36), level(cube / 6 % 6), level(cube % 6))
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// nearest_indexed() is the color of the cube or gray ramp of the 256-color palette nearest to
+/// `rgb`. The ANSI colors are left out, since terminals show them differently.
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255).min_by_key(|&index| distance(rgb, indexed_rgb(index))).unwrap()
+}
+
+/// nearest_ansi() is the ANSI color nearest to `rgb`.
+fn nearest_ansi(rgb: (u8, u8, u8)) -> u8 {
+    (0..16).min_by_key(|&index| distance(rgb, ANSI_RGB[index as usize])).unwrap()
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    channel(r1, r2) + channel(g1, g2) + channel(b1, b2)
+}
+
+#[cfg(test)]
+mod tests {
+    use theme::{Color, ColorSupport, Face, Theme, ThemeError, DEFAULT, SELECTION, STATUS_LINE, SYNTAX_SCOPES};
+
+    #[test]
+    fn parse_colors() {
+        let test_cases = [
+            ("the terminal's color", "default", Some(Color::Default)),
+            ("an ANSI color", "red", Some(Color::Indexed(1))),
+            ("a bright ANSI color", "bright-white", Some(Color::Indexed(15))),
+            ("a true color", "#ff8000", Some(Color::Rgb(255, 128, 0))),
+            ("a short hex color", "#fff", None),
+            ("not hex", "#gggggg", None),
+            ("an unknown name", "chartreuse", None),
+        ];
+
+        for (name, text, expected) in test_cases.iter() {
+            assert_eq!(Color::parse(text), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn downgrade_colors() {
+        let test_cases = [
+            ("true color is kept", Color::Rgb(1, 2, 3), ColorSupport::TrueColor, Color::Rgb(1, 2, 3)),
+            ("to the color cube", Color::Rgb(255, 135, 0), ColorSupport::Indexed256, Color::Indexed(208)),
+            ("to the gray ramp", Color::Rgb(128, 128, 130), ColorSupport::Indexed256, Color::Indexed(244)),
+            ("true color to ANSI", Color::Rgb(250, 10, 10), ColorSupport::Ansi16, Color::Indexed(9)),
+            ("palette to ANSI", Color::Indexed(22), ColorSupport::Ansi16, Color::Indexed(0)),
+            ("ANSI is kept", Color::Indexed(4), ColorSupport::Ansi16, Color::Indexed(4)),
+            ("default is kept", Color::Default, ColorSupport::Ansi16, Color::Default),
+        ];
+
+        for (name, color, support, expected) in test_cases.iter() {
+            assert_eq!(color.downgrade(*support), *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn detect_color_support() {
+        assert_eq!(ColorSupport::from_env(Some("truecolor"), Some("xterm")), ColorSupport::TrueColor);
+        assert_eq!(ColorSupport::from_env(None, Some("xterm-256color")), ColorSupport::Indexed256);
+        assert_eq!(ColorSupport::from_env(None, Some("linux")), ColorSupport::Ansi16);
+        assert_eq!(ColorSupport::from_env(None, None), ColorSupport::Ansi16);
+    }
+
+    #[test]
+    fn builtin_themes_load() {
+        for name in Theme::builtin_names() {
+            let theme = Theme::builtin(name).unwrap();
+
+            assert_eq!(theme.name(), name);
+            for scope in SYNTAX_SCOPES.iter().take(2) {
+                assert_ne!(theme.face(scope), theme.face(DEFAULT), "Test case: \"{}\" failed.", name);
+            }
+        }
+        assert!(Theme::builtin("neon").is_none());
+    }
+
+    #[test]
+    fn styles_fall_back_to_their_scope_and_the_default() {
+        let theme = Theme::from_toml(
+            r##"
+            [styles]
+            default = { foreground = "white", background = "#000000" }
+            selection = { background = 238, bold = true }
+
+            [styles.syntax]
+            foreground = "green"
+            keyword = { foreground = "magenta" }
+            "##,
+        )
+        .unwrap();
+
+        let selection = theme.face(SELECTION);
+        assert_eq!((selection.foreground, selection.background), (Color::Indexed(7), Color::Indexed(238)));
+        assert!(selection.bold);
+        assert_eq!(theme.face("syntax.keyword.control").foreground, Color::Indexed(5));
+        assert_eq!(theme.face("syntax.string").foreground, Color::Indexed(2));
+        assert_eq!(theme.face("syntax.string").background, Color::Rgb(0, 0, 0));
+        assert_eq!(theme.face(STATUS_LINE), theme.face(DEFAULT));
+        assert_eq!(theme.overlay(SELECTION), Face { background: Color::Indexed(238), bold: true, ..Face::default() });
+    }
+
+    #[test]
+    fn terminal_styles_are_drawn_as_they_say_unless_the_theme_sets_them() {
+        let theme = Theme::from_toml("[styles.ansi.foreground]\nred = { foreground = \"#d75f5f\" }").unwrap();
+
+        assert_eq!(theme.overlay("ansi.foreground.red").foreground, Color::Rgb(215, 95, 95));
+        assert_eq!(theme.overlay("ansi.foreground.bright-green").foreground, Color::Indexed(10));
+        assert_eq!(theme.overlay("ansi.background.208").background, Color::Indexed(208));
+        assert_eq!(theme.overlay("ansi.background.#102030").background, Color::Rgb(16, 32, 48));
+        assert_eq!(theme.overlay("ansi.underline"), Face { underline: true, ..Face::default() });
+        assert_eq!(theme.overlay("ansi.blink"), Face::default());
+    }
+
+    #[test]
+    fn themes_inherit_from_builtin_themes() {
+        let theme = Theme::from_toml("name = \"mine\"\ninherits = \"dark\"\n[styles]\n\"syntax.keyword\" = { bold = true }")
+            .unwrap();
+        let dark = Theme::builtin("dark").unwrap();
+
+        assert_eq!(theme.name(), "mine");
+        assert_eq!(theme.face(SELECTION), dark.face(SELECTION));
+        assert_eq!(theme.face("syntax.keyword"), Face { bold: true, ..dark.face(DEFAULT) });
+    }
+
+    #[test]
+    fn invalid_themes_are_rejected() {
+        let test_cases = [
+            ("not TOML", "[styles"),
+            ("an unknown base", "inherits = \"neon\""),
+            ("a bad color", "[styles]\ndefault = { foreground = \"chartreuse\" }"),
+            ("a bad flag", "[styles]\ndefault = { bold = \"yes\" }"),
+            ("an unknown attribute", "[styles]\ndefault = { blink = true }"),
+        ];
+
+        for (name, text) in test_cases.iter() {
+            assert!(Theme::from_toml(text).is_err(), "Test case: \"{}\" failed.", name);
+        }
+        assert!(matches!(Theme::from_toml("inherits = \"neon\""), Err(ThemeError::UnknownTheme(_))));
+    }
+
+    #[test]
+    fn load_a_theme_file() {
+        let path = std::env::temp_dir().join(format!("one-theme-{}.toml", std::process::id()));
+        std::fs::write(&path, "[styles]\nselection = { background = \"blue\" }\n").unwrap();
+
+        let theme = Theme::load(&path).unwrap();
+        assert_eq!(theme.name(), format!("one-theme-{}", std::process::id()));
+        assert_eq!(theme.face(SELECTION).background, Color::Indexed(4));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
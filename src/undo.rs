@@ -0,0 +1,1031 @@
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::time::{Duration, Instant, SystemTime};
+
+use anchor::{Affinity, AnchorId};
+use events::{EditEvent, ListenerId};
+use rect::rect_point_after;
+use GapBuffer;
+
+pub mod persist;
+
+/// Edit records a single change to a buffer so it can be reverted and reapplied.
+/// - `offset`: Where the change took place, counted from the start of the whole text, so it
+///   stays right however the buffer is narrowed later.
+/// - `removed`: The bytes that were removed from `offset`.
+/// - `inserted`: The bytes that were inserted at `offset`.
+/// - `point_before` and `point_after`: The point either side of the change, counted the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub offset: usize,
+    pub removed: Vec<u8>,
+    pub inserted: Vec<u8>,
+    pub point_before: usize,
+    pub point_after: usize,
+}
+
+impl Edit {
+    /// apply() makes the change to `buffer` again.
+    /// It will panic if the change is outside the accessible text.
+    pub fn apply(&self, buffer: &mut GapBuffer) {
+        let offset = self.offset - buffer.hidden_before();
+        buffer.splice(offset..offset + self.removed.len(), &self.inserted);
+        buffer.point = accessible_point(buffer, self.point_after);
+    }
+
+    /// revert() undoes the change to `buffer`.
+    /// It will panic if the change is outside the accessible text.
+    pub fn revert(&self, buffer: &mut GapBuffer) {
+        let offset = self.offset - buffer.hidden_before();
+        buffer.splice(offset..offset + self.inserted.len(), &self.removed);
+        buffer.point = accessible_point(buffer, self.point_before);
+    }
+}
+
+/// accessible_point() is where the point recorded at `point` in the whole text goes in `buffer`,
+/// keeping it in the accessible text.
+fn accessible_point(buffer: &GapBuffer, point: usize) -> usize {
+    point.saturating_sub(buffer.hidden_before()).min(buffer.len())
+}
+
+/// UndoGroup is a list of edits that are undone and redone together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoGroup {
+    pub edits: Vec<Edit>,
+    coalescable: bool,
+    last_change: Instant,
+}
+
+impl UndoGroup {
+    fn new(coalescable: bool) -> UndoGroup {
+        UndoGroup {
+            edits: Vec::new(),
+            coalescable,
+            last_change: Instant::now(),
+        }
+    }
+
+    /// apply() makes the group's edits again, without journaling them as new ones.
+    fn apply(&self, buffer: &mut GapBuffer) {
+        let journal = buffer.journal.take();
+        for edit in self.edits.iter() {
+            edit.apply(buffer);
+        }
+        buffer.journal = journal;
+    }
+
+    /// revert() undoes the group's edits, without journaling them as new ones.
+    fn revert(&self, buffer: &mut GapBuffer) {
+        let journal = buffer.journal.take();
+        for edit in self.edits.iter().rev() {
+            edit.revert(buffer);
+        }
+        buffer.journal = journal;
+    }
+
+    /// fits() reports whether each edit is inside the accessible text of `buffer` when it comes to
+    /// be made, applying the group if `forward` is set and reverting it if not. Only a narrowed
+    /// buffer can have edits outside it.
+    fn fits(&self, buffer: &GapBuffer, forward: bool) -> bool {
+        let start = buffer.hidden_before();
+        let mut end = start + buffer.len();
+        let edits: Box<dyn Iterator<Item = &Edit>> = match forward {
+            true => Box::new(self.edits.iter()),
+            false => Box::new(self.edits.iter().rev()),
+        };
+
+        for edit in edits {
+            let (old, new) = match forward {
+                true => (edit.removed.len(), edit.inserted.len()),
+                false => (edit.inserted.len(), edit.removed.len()),
+            };
+            if edit.offset < start || edit.offset + old > end {
+                return false;
+            }
+            end = end - old + new;
+        }
+
+        true
+    }
+}
+
+/// CoalescePolicy decides when consecutive single character insertions are merged into one
+/// undo step.
+/// - `max_interval`: The longest pause between keystrokes that still continues the step.
+/// - `max_chars`: The most characters a single step may hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoalescePolicy {
+    pub max_interval: Duration,
+    pub max_chars: usize,
+}
+
+impl Default for CoalescePolicy {
+    fn default() -> CoalescePolicy {
+        CoalescePolicy {
+            max_interval: Duration::from_secs(1),
+            max_chars: 20,
+        }
+    }
+}
+
+/// UndoNode is one state in the undo tree, reached from its parent by applying `group`.
+/// The root node holds the state before any recorded change and has an empty group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoNode {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    redo_child: Option<usize>,
+    group: UndoGroup,
+    timestamp: SystemTime,
+}
+
+impl UndoNode {
+    fn new(parent: Option<usize>, group: UndoGroup) -> UndoNode {
+        UndoNode {
+            parent,
+            children: Vec::new(),
+            redo_child: None,
+            group,
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    /// children() lists the branches leading from this node, oldest first.
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    pub fn group(&self) -> &UndoGroup {
+        &self.group
+    }
+
+    /// timestamp() is when the change leading to this node was recorded.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// History wraps a `GapBuffer` and records every change made to it in an undo tree, so changes
+/// can be undone and redone without losing branches. Undoing then making a new change starts a
+/// new branch; the old one stays reachable with `jump_to()`. Nodes are identified by their index
+/// in `nodes()`, with the root at `0`.
+///
+/// The buffer can be read through `Deref`. Each change made through `History` is an undo step of
+/// its own, while changes made through `buffer_mut()` are gathered into one step until the next
+/// `checkpoint()`, so an editor can make each of its commands one step.
+/// - `step_start`: Where the point was in the whole text when the step being gathered started,
+///   if that was marked, which is where undoing it puts the point back.
+pub struct History {
+    buffer: GapBuffer,
+    step_start: Option<usize>,
+    nodes: Vec<UndoNode>,
+    current: usize,
+    transaction: Option<UndoGroup>,
+    transaction_depth: usize,
+    coalesce_policy: CoalescePolicy,
+}
+
+impl History {
+    pub fn new(mut buffer: GapBuffer) -> History {
+        buffer.journal = Some(Vec::new());
+        History {
+            step_start: None,
+            buffer,
+            nodes: vec![UndoNode::new(None, UndoGroup::new(false))],
+            current: 0,
+            transaction: None,
+            transaction_depth: 0,
+            coalesce_policy: CoalescePolicy::default(),
+        }
+    }
+
+    pub fn coalesce_policy(&self) -> CoalescePolicy {
+        self.coalesce_policy
+    }
+
+    pub fn set_coalesce_policy(&mut self, policy: CoalescePolicy) {
+        self.coalesce_policy = policy;
+    }
+
+    /// begin_transaction() groups every change until the matching `commit()` into a single undo
+    /// step. Transactions may be nested; only the outermost `commit()` closes the step.
+    pub fn begin_transaction(&mut self) {
+        if self.transaction_depth == 0 {
+            self.checkpoint();
+            self.transaction = Some(UndoGroup::new(false));
+        }
+        self.transaction_depth += 1;
+    }
+
+    /// commit() closes the innermost open transaction. It does nothing when no transaction is open.
+    pub fn commit(&mut self) {
+        if self.transaction_depth == 0 {
+            return;
+        }
+
+        self.checkpoint();
+        self.transaction_depth -= 1;
+        if self.transaction_depth == 0 {
+            if let Some(group) = self.transaction.take() {
+                if !group.edits.is_empty() {
+                    self.push_node(group);
+                }
+            }
+        }
+    }
+
+    pub fn in_transaction(&self) -> bool {
+        self.transaction_depth > 0
+    }
+
+    pub fn buffer(&self) -> &GapBuffer {
+        &self.buffer
+    }
+
+    /// buffer_mut() lets the buffer be changed with its own methods. What is changed is recorded
+    /// at the next `checkpoint()`.
+    pub fn buffer_mut(&mut self) -> &mut GapBuffer {
+        &mut self.buffer
+    }
+
+    pub fn into_buffer(self) -> GapBuffer {
+        let mut buffer = self.buffer;
+        buffer.journal = None;
+        buffer
+    }
+
+    /// checkpoint() records the changes made through `buffer_mut()` since the last checkpoint as
+    /// one undo step, or adds them to the open transaction. A single character typed instead
+    /// continues the step before it, as the coalescing policy allows.
+    pub fn checkpoint(&mut self) {
+        let step_start = self.step_start.take();
+        let mut edits = match self.buffer.journal.as_mut() {
+            Some(journal) if !journal.is_empty() => std::mem::take(journal),
+            _ => return,
+        };
+        // The point after each edit is taken to be where it was when the next one was made.
+        let mut point_after = self.buffer.hidden_before() + self.buffer.point;
+        for edit in edits.iter_mut().rev() {
+            edit.point_after = point_after;
+            point_after = edit.point_before;
+        }
+        if let Some(step_start) = step_start {
+            edits[0].point_before = step_start;
+        }
+
+        if let Some(group) = self.transaction.as_mut() {
+            group.edits.extend(edits);
+            return;
+        }
+
+        let policy = self.coalesce_policy;
+        let current = &mut self.nodes[self.current];
+        if edits.len() == 1 && current.children.is_empty() && coalesce(&mut current.group, &edits[0], policy) {
+            return;
+        }
+
+        let mut group = UndoGroup::new(edits.len() == 1 && is_typing(&edits[0]));
+        group.edits = edits;
+        self.push_node(group);
+    }
+
+    /// start_step() marks the point as it is now as where undoing the next step puts it back, as
+    /// an editor does before each command. Otherwise undoing a step puts the point back where it
+    /// was when the step's first change was made.
+    pub fn start_step(&mut self) {
+        self.step_start = Some(self.buffer.hidden_before() + self.buffer.point);
+    }
+
+    /// has_unrecorded_changes() reports whether changes have been made through `buffer_mut()`
+    /// since the last checkpoint.
+    pub fn has_unrecorded_changes(&self) -> bool {
+        self.buffer.journal.as_ref().is_some_and(|journal| !journal.is_empty())
+    }
+
+    /// clear() forgets every change recorded, and any not recorded yet, leaving nothing to undo or
+    /// redo. An open transaction stays open.
+    pub fn clear(&mut self) {
+        if let Some(journal) = self.buffer.journal.as_mut() {
+            journal.clear();
+        }
+        if let Some(group) = self.transaction.as_mut() {
+            group.edits.clear();
+        }
+        self.nodes = vec![UndoNode::new(None, UndoGroup::new(false))];
+        self.current = 0;
+        self.step_start = None;
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.nodes[self.current].parent.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].redo_child.is_some()
+    }
+
+    pub fn nodes(&self) -> &[UndoNode] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: usize) -> Option<&UndoNode> {
+        self.nodes.get(id)
+    }
+
+    /// current_node() is the node matching the current state of the buffer.
+    pub fn current_node(&self) -> usize {
+        self.current
+    }
+
+    /// branches() lists the nodes `redo()` could move to from the current state.
+    pub fn branches(&self) -> &[usize] {
+        &self.nodes[self.current].children
+    }
+
+    /// on_change() registers a callback for every change to the buffer, including undo and redo.
+    /// See `GapBuffer::on_change()`.
+    pub fn on_change<F: FnMut(&EditEvent) + Send + 'static>(&mut self, callback: F) -> ListenerId {
+        self.buffer.on_change(callback)
+    }
+
+    pub fn create_anchor(&mut self, offset: usize, affinity: Affinity) -> AnchorId {
+        self.buffer.create_anchor(offset, affinity)
+    }
+
+    pub fn move_anchor(&mut self, id: AnchorId, offset: usize) -> bool {
+        self.buffer.move_anchor(id, offset)
+    }
+
+    pub fn remove_anchor(&mut self, id: AnchorId) -> Option<usize> {
+        self.buffer.remove_anchor(id)
+    }
+
+    pub fn set_mark(&mut self, offset: usize) {
+        self.buffer.set_mark(offset);
+    }
+
+    pub fn clear_mark(&mut self) {
+        self.buffer.clear_mark();
+    }
+
+    pub fn swap_point_and_mark(&mut self) -> bool {
+        self.buffer.swap_point_and_mark()
+    }
+
+    pub fn delete_region(&mut self) -> Option<String> {
+        let region = self.buffer.region()?;
+
+        Some(self.remove_bytes(region))
+    }
+
+    pub fn add_cursor(&mut self, offset: usize) -> bool {
+        self.buffer.add_cursor(offset)
+    }
+
+    pub fn add_selection(&mut self, range: Range<usize>) -> bool {
+        self.buffer.add_selection(range)
+    }
+
+    pub fn add_cursor_at_next_match(&mut self) -> bool {
+        self.buffer.add_cursor_at_next_match()
+    }
+
+    pub fn clear_cursors(&mut self) {
+        self.buffer.clear_cursors();
+    }
+
+    /// insert_at_cursors() inserts `text` at every cursor as one undo step.
+    pub fn insert_at_cursors(&mut self, text: &str) {
+        self.begin_transaction();
+        for range in self.buffer.cursor_ranges().into_iter().rev() {
+            self.replace_range(range, text);
+        }
+        self.commit();
+        self.buffer.collapse_cursors();
+    }
+
+    /// remove_at_cursors() deletes backwards at every cursor as one undo step.
+    pub fn remove_at_cursors(&mut self) {
+        self.begin_transaction();
+        for range in self.buffer.backspace_ranges().into_iter().rev() {
+            self.remove_bytes(range);
+        }
+        self.commit();
+        self.buffer.collapse_cursors();
+    }
+
+    /// insert_rect() inserts `text` on every line of the rectangle as one undo step.
+    pub fn insert_rect(&mut self, text: &str) {
+        let insertions = self.buffer.rect_insertions(text);
+        let point = rect_point_after(&insertions);
+
+        self.begin_transaction();
+        for (offset, inserted) in insertions.iter().rev() {
+            self.buffer.point = *offset;
+            self.insert_str(inserted);
+        }
+        self.commit();
+        self.buffer.clear_mark();
+        self.buffer.point = point;
+    }
+
+    /// delete_rect() removes the rectangle as one undo step.
+    pub fn delete_rect(&mut self) -> Vec<String> {
+        let (a, b) = (self.buffer.point, self.buffer.get_mark().unwrap_or(self.buffer.point));
+        let ranges = self.buffer.rectangle(a, b);
+
+        self.begin_transaction();
+        let mut removed: Vec<String> = ranges.iter().rev().map(|range| self.remove_bytes(range.clone())).collect();
+        self.commit();
+        removed.reverse();
+        self.buffer.clear_mark();
+        self.buffer.point = ranges[0].start;
+
+        removed
+    }
+
+    /// set_point() moves the point. Point movement is not recorded.
+    pub fn set_point(&mut self, index: usize) {
+        self.buffer.set_point(index);
+    }
+
+    pub fn insert(&mut self, byte: u8) {
+        self.record(|buffer| buffer.insert(byte));
+    }
+
+    pub fn insert_bytes(&mut self, bytes: Vec<u8>) {
+        self.record(|buffer| buffer.insert_bytes(bytes));
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.record(|buffer| buffer.insert_str(text));
+    }
+
+    pub fn remove(&mut self) {
+        self.record(|buffer| buffer.remove());
+    }
+
+    pub fn remove_bytes(&mut self, range: Range<usize>) -> String {
+        self.record(|buffer| buffer.remove_bytes(range))
+    }
+
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) -> String {
+        self.record(|buffer| buffer.replace_range(range, text))
+    }
+
+    /// undo() moves to the parent of the current node and returns false if there was nothing to
+    /// undo, the buffer is read-only, or the change is outside the accessible text of a narrowed
+    /// buffer. Any open transaction is committed first.
+    pub fn undo(&mut self) -> bool {
+        if self.buffer.is_read_only() {
+            return false;
+        }
+        self.checkpoint();
+        self.commit_all();
+
+        let current = self.current;
+        match self.nodes[current].parent {
+            Some(parent) if self.nodes[current].group.fits(&self.buffer, false) => {
+                self.nodes[current].group.revert(&mut self.buffer);
+                self.nodes[parent].redo_child = Some(current);
+                self.current = parent;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// redo() moves to the most recently visited branch of the current node and returns false if
+    /// there was nothing to redo, the buffer is read-only, or the change is outside the accessible
+    /// text of a narrowed buffer.
+    pub fn redo(&mut self) -> bool {
+        if self.buffer.is_read_only() {
+            return false;
+        }
+        self.checkpoint();
+        self.commit_all();
+
+        match self.nodes[self.current].redo_child {
+            Some(child) if self.nodes[child].group.fits(&self.buffer, true) => {
+                self.enter(child);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// jump_to() undoes and redoes changes until the buffer is in the state of node `id`.
+    /// Returns false if there is no such node or the buffer is read-only, or if a change on the
+    /// way is outside the accessible text of a narrowed buffer, stopping before that change.
+    pub fn jump_to(&mut self, id: usize) -> bool {
+        if id >= self.nodes.len() || self.buffer.is_read_only() {
+            return false;
+        }
+        self.checkpoint();
+        self.commit_all();
+
+        let target_path = self.path_from_root(id);
+        while !target_path.contains(&self.current) {
+            if !self.undo() {
+                return false;
+            }
+        }
+
+        let position = target_path.iter().position(|node| *node == self.current).unwrap();
+        for node in target_path[position + 1..].iter() {
+            if !self.nodes[*node].group.fits(&self.buffer, true) {
+                return false;
+            }
+            self.enter(*node);
+        }
+
+        true
+    }
+
+    /// enter() applies the change leading to `child`, which must be a child of the current node.
+    fn enter(&mut self, child: usize) {
+        self.nodes[child].group.apply(&mut self.buffer);
+        self.nodes[child].group.coalescable = false;
+        self.nodes[self.current].redo_child = Some(child);
+        self.current = child;
+    }
+
+    fn path_from_root(&self, id: usize) -> Vec<usize> {
+        let mut path = vec![id];
+        while let Some(parent) = self.nodes[*path.last().unwrap()].parent {
+            path.push(parent);
+        }
+
+        path.reverse();
+        path
+    }
+
+    fn push_node(&mut self, group: UndoGroup) {
+        let id = self.nodes.len();
+        self.nodes.push(UndoNode::new(Some(self.current), group));
+        self.nodes[self.current].children.push(id);
+        self.nodes[self.current].redo_child = Some(id);
+        self.current = id;
+    }
+
+    fn commit_all(&mut self) {
+        while self.in_transaction() {
+            self.commit();
+        }
+    }
+
+    /// record() makes `change` and records it as an undo step of its own, or as part of the open
+    /// transaction. Nothing is recorded if it changed nothing, as in a read-only buffer.
+    fn record<T, F: FnOnce(&mut GapBuffer) -> T>(&mut self, change: F) -> T {
+        self.checkpoint();
+        let result = change(&mut self.buffer);
+        self.checkpoint();
+
+        result
+    }
+}
+
+/// is_typing() is true for edits that insert a single character other than a newline.
+fn is_typing(edit: &Edit) -> bool {
+    match std::str::from_utf8(&edit.inserted) {
+        Ok(text) => edit.removed.is_empty() && text.chars().count() == 1 && text != "\n",
+        Err(_) => false,
+    }
+}
+
+/// coalesce() merges `edit` into `group` if it continues the typing recorded there,
+/// returning true if it was merged.
+fn coalesce(group: &mut UndoGroup, edit: &Edit, policy: CoalescePolicy) -> bool {
+    if !group.coalescable || !is_typing(edit) || group.last_change.elapsed() > policy.max_interval {
+        return false;
+    }
+
+    let previous = match group.edits.last_mut() {
+        Some(previous) => previous,
+        None => return false,
+    };
+    let typed = String::from_utf8_lossy(&previous.inserted).chars().count();
+
+    if previous.offset + previous.inserted.len() != edit.offset || typed >= policy.max_chars {
+        return false;
+    }
+
+    previous.inserted.extend_from_slice(&edit.inserted);
+    previous.point_after = edit.point_after;
+    group.last_change = Instant::now();
+
+    true
+}
+
+impl Deref for History {
+    type Target = GapBuffer;
+
+    fn deref(&self) -> &GapBuffer {
+        &self.buffer
+    }
+}
+
+impl fmt::Display for History {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.buffer.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use undo::{CoalescePolicy, History};
+    use GapBuffer;
+
+    const TEST_STRING: &str = "The quick brown fox";
+
+    fn history() -> History {
+        let mut history = History::new(GapBuffer::from(TEST_STRING.to_string()));
+        history.set_coalesce_policy(CoalescePolicy {
+            max_interval: Duration::from_secs(0),
+            max_chars: 1,
+        });
+        history
+    }
+
+    fn typing_history(max_chars: usize) -> History {
+        let mut history = History::new(GapBuffer::from(TEST_STRING.to_string()));
+        history.set_coalesce_policy(CoalescePolicy {
+            max_interval: Duration::from_secs(3600),
+            max_chars,
+        });
+        history
+    }
+
+    #[test]
+    fn undo_and_redo_an_insertion() {
+        let mut history = history();
+        history.set_point(4);
+        history.insert_str("very ");
+
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert_eq!(history.get_point(), 4);
+
+        assert!(history.redo());
+        assert_eq!(history.to_string(), "The very quick brown fox");
+        assert_eq!(history.get_point(), 9);
+    }
+
+    #[test]
+    fn undo_single_byte_edits() {
+        let mut history = history();
+        history.set_point(3);
+
+        history.insert(b'!');
+        history.remove();
+        history.remove();
+
+        assert_eq!(history.to_string(), "Th quick brown fox");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "The quick brown fox");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "The! quick brown fox");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert!(!history.undo());
+
+        history.set_point(0);
+        history.remove();
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_and_redo_a_removal() {
+        let mut history = history();
+        history.set_point(TEST_STRING.len());
+
+        assert_eq!(history.remove_bytes(4..10), "quick ");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert_eq!(history.get_point(), TEST_STRING.len());
+
+        assert!(history.redo());
+        assert_eq!(history.to_string(), "The brown fox");
+        assert_eq!(history.get_point(), TEST_STRING.len() - 6);
+    }
+
+    #[test]
+    fn undo_a_replacement() {
+        let mut history = history();
+        history.insert_bytes(vec![b'!']);
+        history.replace_range(10..15, "red");
+
+        assert_eq!(history.to_string(), "The quick red fox!");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "The quick brown fox!");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn a_new_edit_starts_a_new_branch() {
+        let mut history = history();
+        history.insert_str(" jumped");
+        history.undo();
+
+        history.insert_str(" ran");
+
+        assert!(!history.can_redo());
+        assert!(!history.redo());
+        assert_eq!(history.to_string(), "The quick brown fox ran");
+    }
+
+    #[test]
+    fn undo_and_redo_with_nothing_recorded() {
+        let mut history = history();
+
+        assert!(!history.can_undo());
+        assert!(!history.undo());
+        assert!(!history.redo());
+        assert_eq!(history.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn undo_a_transaction_as_one_step() {
+        let mut history = history();
+
+        history.begin_transaction();
+        history.replace_range(4..9, "slow");
+        history.begin_transaction();
+        history.insert_str("!");
+        history.commit();
+        history.remove_bytes(0..4);
+        history.commit();
+
+        assert_eq!(history.to_string(), "slow brown fox!");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert_eq!(history.get_point(), TEST_STRING.len());
+        assert!(!history.can_undo());
+
+        assert!(history.redo());
+        assert_eq!(history.to_string(), "slow brown fox!");
+    }
+
+    #[test]
+    fn undo_an_edit_at_every_cursor() {
+        let mut history = history();
+        history.set_point(0);
+        history.add_cursor(4);
+
+        history.insert_at_cursors("_");
+        assert_eq!(history.to_string(), "_The _quick brown fox");
+
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_a_rectangle_edit() {
+        let mut history = History::new(GapBuffer::from("ab\ncd".to_string()));
+        history.set_mark(1);
+        history.set_point(4);
+
+        history.insert_rect("|");
+        assert_eq!(history.to_string(), "a|b\nc|d");
+        assert_eq!(history.get_point(), 6);
+
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "ab\ncd");
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_commits_an_open_transaction() {
+        let mut history = history();
+
+        history.begin_transaction();
+        history.insert_str(" jumped");
+        history.insert_str(" over");
+
+        assert!(history.undo());
+        assert!(!history.in_transaction());
+        assert_eq!(history.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn refused_edits_record_nothing() {
+        let mut buffer = GapBuffer::from(TEST_STRING.to_string());
+        buffer.set_read_only(true);
+        let mut history = History::new(buffer);
+
+        history.insert_str("ignored");
+        history.replace_range(0..3, "A");
+        history.remove();
+
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert_eq!(history.nodes().len(), 1);
+    }
+
+    #[test]
+    fn changes_made_to_the_buffer_are_recorded_at_a_checkpoint() {
+        let mut history = history();
+
+        history.buffer_mut().set_point(3);
+        history.buffer_mut().insert_str(",");
+        history.buffer_mut().remove_bytes(10..16);
+        assert!(history.has_unrecorded_changes());
+        assert!(!history.can_undo());
+
+        history.checkpoint();
+        assert!(!history.has_unrecorded_changes());
+        assert_eq!(history.to_string(), "The, quick fox");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert_eq!(history.get_point(), 3);
+        assert!(!history.can_undo());
+
+        assert!(history.redo());
+        assert_eq!(history.to_string(), "The, quick fox");
+        assert_eq!(history.get_point(), 4);
+    }
+
+    #[test]
+    fn undo_in_a_narrowed_buffer() {
+        let mut history = history();
+        history.set_point(0);
+        history.insert_str("> ");
+        history.buffer_mut().narrow(6..11);
+        history.set_point(5);
+        history.insert_str("er");
+        assert_eq!(history.to_string(), "quicker");
+
+        assert!(history.undo());
+        assert_eq!(history.buffer().whole_text(), "> The quick brown fox");
+        assert_eq!(history.get_point(), 5);
+        assert!(!history.undo());
+        assert_eq!(history.buffer().whole_text(), "> The quick brown fox");
+
+        history.buffer_mut().widen();
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn clear_forgets_every_change() {
+        let mut history = history();
+        history.insert_str(" jumped");
+        history.buffer_mut().insert_str(" over");
+
+        history.clear();
+        assert!(!history.can_undo() && !history.has_unrecorded_changes());
+        assert_eq!(history.to_string(), "The quick brown fox jumped over");
+    }
+
+    #[test]
+    fn an_empty_transaction_records_nothing() {
+        let mut history = history();
+
+        history.begin_transaction();
+        history.commit();
+        history.commit();
+
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn coalesce_consecutive_typing() {
+        let mut history = typing_history(20);
+
+        for character in " jumps".chars() {
+            history.insert_str(&character.to_string());
+        }
+        history.insert(b'!');
+
+        assert_eq!(history.to_string(), "The quick brown fox jumps!");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn coalescing_stops_at_the_size_limit() {
+        let mut history = typing_history(3);
+
+        for character in "abcdef".chars() {
+            history.insert_str(&character.to_string());
+        }
+
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "The quick brown foxabc");
+    }
+
+    #[test]
+    fn coalescing_stops_when_the_point_moves_or_at_a_newline() {
+        let mut history = typing_history(20);
+
+        history.insert_str("a");
+        history.insert_str("b");
+        history.set_point(0);
+        history.insert_str("c");
+        history.insert_str("\n");
+        history.insert_str("d");
+
+        assert_eq!(history.to_string(), "c\ndThe quick brown foxab");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "c\nThe quick brown foxab");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "cThe quick brown foxab");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "The quick brown foxab");
+        assert!(history.undo());
+        assert_eq!(history.to_string(), TEST_STRING);
+    }
+
+    #[test]
+    fn coalescing_stops_after_the_time_limit() {
+        let mut history = history();
+        history.set_coalesce_policy(CoalescePolicy {
+            max_interval: Duration::from_millis(0),
+            max_chars: 20,
+        });
+
+        history.insert_str("a");
+        std::thread::sleep(Duration::from_millis(2));
+        history.insert_str("b");
+
+        assert!(history.undo());
+        assert_eq!(history.to_string(), "The quick brown foxa");
+    }
+
+    #[test]
+    fn earlier_branches_are_kept() {
+        let mut history = history();
+        history.insert_str(" jumped");
+        let jumped = history.current_node();
+        history.undo();
+        history.insert_str(" ran");
+        let ran = history.current_node();
+
+        history.undo();
+
+        assert_eq!(history.current_node(), 0);
+        assert_eq!(history.branches(), &[jumped, ran]);
+        assert!(history.redo());
+        assert_eq!(history.to_string(), "The quick brown fox ran");
+    }
+
+    #[test]
+    fn redo_follows_the_branch_last_undone() {
+        let mut history = history();
+        history.insert_str(" jumped");
+        history.undo();
+        history.insert_str(" ran");
+        history.jump_to(1);
+        history.undo();
+
+        assert!(history.redo());
+        assert_eq!(history.to_string(), "The quick brown fox jumped");
+    }
+
+    #[test]
+    fn jump_between_branches() {
+        let mut history = history();
+        history.insert_str(" jumped");
+        history.insert_str(" high");
+        let high = history.current_node();
+        history.undo();
+        history.undo();
+        history.set_point(3);
+        history.insert_str(" sly");
+        history.remove_bytes(0..4);
+        let sly = history.current_node();
+
+        assert!(history.jump_to(high));
+        assert_eq!(history.to_string(), "The quick brown fox jumped high");
+        assert_eq!(history.current_node(), high);
+
+        assert!(history.jump_to(sly));
+        assert_eq!(history.to_string(), "sly quick brown fox");
+
+        assert!(history.jump_to(0));
+        assert_eq!(history.to_string(), TEST_STRING);
+        assert!(!history.jump_to(99));
+    }
+
+    #[test]
+    fn nodes_are_timestamped_in_order() {
+        let mut history = history();
+        history.insert_str("a");
+        history.insert_str("b");
+
+        let nodes = history.nodes();
+
+        assert_eq!(nodes.len(), 3);
+        assert!(nodes[1].timestamp() <= nodes[2].timestamp());
+        assert_eq!(nodes[2].parent(), Some(1));
+        assert_eq!(nodes[1].group().edits[0].inserted, b"a".to_vec());
+    }
+}
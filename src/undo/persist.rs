@@ -0,0 +1,378 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use undo::{Edit, History, UndoGroup, UndoNode};
+use GapBuffer;
+
+/// Undo histories are written next to the file they belong to, in this directory.
+pub const UNDO_DIRECTORY: &str = ".one-undo";
+
+const MAGIC: &[u8; 8] = b"ONEUNDO\0";
+const FORMAT_VERSION: u32 = 1;
+const NONE: u64 = u64::MAX;
+
+/// undo_file_path() returns where the undo history for the file at `path` is stored,
+/// e.g. `src/.one-undo/main.rs.undo` for `src/main.rs`.
+pub fn undo_file_path(path: &Path) -> PathBuf {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".undo");
+
+    directory.join(UNDO_DIRECTORY).join(name)
+}
+
+/// save_undo_file() writes the undo history for the file at `path`, creating the undo
+/// directory if needed.
+pub fn save_undo_file(history: &History, path: &Path) -> io::Result<()> {
+    let undo_path = undo_file_path(path);
+    if let Some(directory) = undo_path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(undo_path)?);
+    history.write_undo(&mut writer)?;
+    writer.flush()
+}
+
+/// load_undo_file() restores the undo history saved for the file at `path` into `history`, whose
+/// buffer should hold that file's contents. Returns false, leaving `history` as it was, if no
+/// history was saved, and an `InvalidData` error if it is corrupt or was saved for different
+/// contents.
+pub fn load_undo_file(history: &mut History, path: &Path) -> io::Result<bool> {
+    let file = match File::open(undo_file_path(path)) {
+        Ok(file) => file,
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error),
+    };
+
+    history.restore_undo(&mut BufReader::new(file))?;
+    Ok(true)
+}
+
+/// content_hash() is a 64-bit FNV-1a hash of the buffer's whole text, including any that
+/// narrowing hides, since that is what the edits are made to. It is part of the on-disk format so
+/// it must not change between versions.
+pub fn content_hash(buffer: &GapBuffer) -> u64 {
+    let (front, back) = buffer.whole_slices();
+    front.iter().chain(back).fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// The on-disk format is a header followed by every node in order. All integers are
+/// little-endian `u64`s unless noted, and missing node ids are written as `u64::MAX`.
+/// - Header: `MAGIC`, version (`u32`), content hash, current node, node count.
+/// - Node: parent, redo child, timestamp seconds, timestamp nanoseconds (`u32`),
+///   child count then child ids, edit count then edits.
+/// - Edit: offset, point before, point after, removed length then bytes, inserted length then bytes.
+impl History {
+    pub fn write_undo<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        write_u64(writer, content_hash(&self.buffer))?;
+        write_u64(writer, self.current as u64)?;
+        write_u64(writer, self.nodes.len() as u64)?;
+
+        for node in self.nodes.iter() {
+            let timestamp = node.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+            write_id(writer, node.parent)?;
+            write_id(writer, node.redo_child)?;
+            write_u64(writer, timestamp.as_secs())?;
+            writer.write_all(&timestamp.subsec_nanos().to_le_bytes())?;
+
+            write_u64(writer, node.children.len() as u64)?;
+            for child in node.children.iter() {
+                write_u64(writer, *child as u64)?;
+            }
+
+            write_u64(writer, node.group.edits.len() as u64)?;
+            for edit in node.group.edits.iter() {
+                write_u64(writer, edit.offset as u64)?;
+                write_u64(writer, edit.point_before as u64)?;
+                write_u64(writer, edit.point_after as u64)?;
+                write_bytes(writer, &edit.removed)?;
+                write_bytes(writer, &edit.inserted)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn read_undo<R: Read>(buffer: GapBuffer, reader: &mut R) -> io::Result<History> {
+        let mut history = History::new(buffer);
+        history.restore_undo(reader)?;
+
+        Ok(history)
+    }
+
+    /// restore_undo() replaces the undo tree with the one read from `reader`, which must have been
+    /// written for the buffer's current contents. The history is left as it was if it cannot be.
+    pub fn restore_undo<R: Read>(&mut self, reader: &mut R) -> io::Result<()> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not an undo file"));
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        if u32::from_le_bytes(version) != FORMAT_VERSION {
+            return Err(invalid_data("unsupported undo file version"));
+        }
+
+        if read_u64(reader)? != content_hash(&self.buffer) {
+            return Err(invalid_data("undo file does not match the buffer contents"));
+        }
+
+        let current = read_u64(reader)? as usize;
+        let node_count = read_u64(reader)? as usize;
+        let mut nodes = Vec::new();
+
+        for _ in 0..node_count {
+            let parent = read_id(reader)?;
+            let redo_child = read_id(reader)?;
+            let seconds = read_u64(reader)?;
+            let mut nanoseconds = [0; 4];
+            reader.read_exact(&mut nanoseconds)?;
+
+            let child_count = read_u64(reader)?;
+            let mut children = Vec::new();
+            for _ in 0..child_count {
+                children.push(read_u64(reader)? as usize);
+            }
+
+            let edit_count = read_u64(reader)?;
+            let mut edits = Vec::new();
+            for _ in 0..edit_count {
+                edits.push(Edit {
+                    offset: read_u64(reader)? as usize,
+                    point_before: read_u64(reader)? as usize,
+                    point_after: read_u64(reader)? as usize,
+                    removed: read_bytes(reader)?,
+                    inserted: read_bytes(reader)?,
+                });
+            }
+
+            nodes.push(UndoNode {
+                parent,
+                children,
+                redo_child,
+                group: UndoGroup {
+                    edits,
+                    coalescable: false,
+                    last_change: Instant::now(),
+                },
+                timestamp: UNIX_EPOCH + Duration::new(seconds, u32::from_le_bytes(nanoseconds)),
+            });
+        }
+
+        // Nodes are added after their parents, which is what keeps the tree from looping.
+        let ids_are_valid = nodes.iter().enumerate().all(|(id, node)| {
+            let related = node.parent.iter().chain(node.redo_child.iter()).chain(node.children.iter());
+            let children_are_own = node.children.iter().all(|child| *child < node_count && nodes[*child].parent == Some(id));
+            related.into_iter().all(|id| *id < node_count)
+                && node.parent.map_or(id == 0, |parent| parent < id)
+                && children_are_own
+                && node.redo_child.iter().all(|child| node.children.contains(child))
+        });
+        let (front, back) = self.buffer.whole_slices();
+        if node_count == 0 || current >= node_count || !ids_are_valid || !edits_fit(&nodes, current, front.len() + back.len()) {
+            return Err(invalid_data("undo file is corrupt"));
+        }
+
+        self.nodes = nodes;
+        self.current = current;
+
+        Ok(())
+    }
+}
+
+/// edits_fit() reports whether every edit in `nodes` stays inside the text it is made to, when
+/// the text of the `current` node is `length` bytes long, so undoing and redoing them cannot reach
+/// past the end of the buffer. Each node's parent must come before it.
+fn edits_fit(nodes: &[UndoNode], current: usize, length: usize) -> bool {
+    let growth = |group: &UndoGroup| -> i64 { group.edits.iter().map(|edit| edit.inserted.len() as i64 - edit.removed.len() as i64).sum() };
+    let mut root_length = length as i64;
+    let mut node = current;
+    while let Some(parent) = nodes[node].parent {
+        root_length -= growth(&nodes[node].group);
+        node = parent;
+    }
+    if root_length < 0 {
+        return false;
+    }
+
+    let mut lengths = vec![root_length as usize; nodes.len()];
+    for id in 1..nodes.len() {
+        let mut length = lengths[nodes[id].parent.unwrap()];
+        for edit in nodes[id].group.edits.iter() {
+            if edit.point_before > length || edit.offset > length || edit.removed.len() > length - edit.offset {
+                return false;
+            }
+            length = length - edit.removed.len() + edit.inserted.len();
+            if edit.point_after > length {
+                return false;
+            }
+        }
+        lengths[id] = length;
+    }
+
+    true
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn write_id<W: Write>(writer: &mut W, id: Option<usize>) -> io::Result<()> {
+    write_u64(writer, id.map_or(NONE, |id| id as u64))
+}
+
+fn read_id<R: Read>(reader: &mut R) -> io::Result<Option<usize>> {
+    let id = read_u64(reader)?;
+
+    Ok(if id == NONE { None } else { Some(id as usize) })
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u64(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let length = read_u64(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(length).read_to_end(&mut bytes)?;
+
+    if bytes.len() as u64 != length {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "undo file is truncated"));
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    use undo::persist::{load_undo_file, save_undo_file, undo_file_path};
+    use undo::History;
+    use GapBuffer;
+
+    const TEST_STRING: &str = "The quick brown fox";
+
+    fn history_with_branches() -> History {
+        let mut history = History::new(GapBuffer::from(TEST_STRING.to_string()));
+        history.insert_str(" jumped");
+        history.undo();
+        history.set_point(3);
+        history.insert_str(" sly");
+        history
+    }
+
+    fn temporary_directory(name: &str) -> PathBuf {
+        let directory = env::temp_dir().join(format!("one-editor-undo-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn undo_files_live_in_the_undo_directory() {
+        assert_eq!(undo_file_path(Path::new("src/main.rs")), PathBuf::from("src/.one-undo/main.rs.undo"));
+        assert_eq!(undo_file_path(Path::new("notes.txt")), PathBuf::from(".one-undo/notes.txt.undo"));
+    }
+
+    #[test]
+    fn round_trip_the_undo_tree() {
+        let history = history_with_branches();
+        let mut bytes = Vec::new();
+        history.write_undo(&mut bytes).unwrap();
+
+        let buffer = GapBuffer::from(history.to_string());
+        let mut restored = History::read_undo(buffer, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(restored.nodes().len(), history.nodes().len());
+        assert_eq!(restored.current_node(), history.current_node());
+        assert_eq!(restored.nodes()[1].timestamp(), history.nodes()[1].timestamp());
+        assert!(restored.undo());
+        assert_eq!(restored.to_string(), TEST_STRING);
+        assert!(restored.jump_to(1));
+        assert_eq!(restored.to_string(), "The quick brown fox jumped");
+    }
+
+    #[test]
+    fn reject_history_for_different_contents() {
+        let history = history_with_branches();
+        let mut bytes = Vec::new();
+        history.write_undo(&mut bytes).unwrap();
+
+        let result = History::read_undo(GapBuffer::from("changed".to_string()), &mut bytes.as_slice());
+
+        assert_eq!(result.err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData));
+    }
+
+    /// assert_rejected() checks the history from `history_with_branches()` is refused once
+    /// `corrupt` has changed it.
+    fn assert_rejected(name: &str, corrupt: fn(&mut History)) {
+        let mut history = history_with_branches();
+        corrupt(&mut history);
+        let mut bytes = Vec::new();
+        history.write_undo(&mut bytes).unwrap();
+
+        let result = History::read_undo(GapBuffer::from(history.to_string()), &mut bytes.as_slice());
+        assert_eq!(result.err().map(|error| error.kind()), Some(io::ErrorKind::InvalidData), "Test case: \"{}\" failed.", name);
+    }
+
+    #[test]
+    fn reject_edits_that_do_not_fit_the_buffer() {
+        assert_rejected("an offset past the end", |history| history.nodes[2].group.edits[0].offset = 100);
+        assert_rejected("too much removed", |history| history.nodes[1].group.edits[0].removed = vec![b'x'; 30]);
+        assert_rejected("a point past the end", |history| history.nodes[2].group.edits[0].point_after = 100);
+        assert_rejected("a child of another node", |history| history.nodes[1].children = vec![2]);
+    }
+
+    #[test]
+    fn reject_truncated_history() {
+        let history = history_with_branches();
+        let mut bytes = Vec::new();
+        history.write_undo(&mut bytes).unwrap();
+        bytes.truncate(bytes.len() - 3);
+
+        let result = History::read_undo(GapBuffer::from(history.to_string()), &mut bytes.as_slice());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn save_and_load_next_to_the_file() {
+        let directory = temporary_directory("save");
+        let path = directory.join("fox.txt");
+        let history = history_with_branches();
+
+        save_undo_file(&history, &path).unwrap();
+        let mut restored = History::new(GapBuffer::from(history.to_string()));
+        assert!(load_undo_file(&mut restored, &path).unwrap());
+
+        assert!(directory.join(".one-undo").join("fox.txt.undo").exists());
+        assert_eq!(restored.nodes().len(), 3);
+        assert!(!load_undo_file(&mut restored, &directory.join("missing.txt")).unwrap());
+        assert_eq!(restored.nodes().len(), 3);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
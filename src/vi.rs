@@ -0,0 +1,994 @@
+use std::ops::Range;
+
+use command::{CommandError, CommandResult};
+use editor::Editor;
+use keymap::{Key, KeyCode};
+use motion::is_word_char;
+use registers::UNNAMED_REGISTER;
+use GapBuffer;
+
+/// Mode is how keys are read by the vi layer.
+/// - `Normal`: Keys move the point and operate on text.
+/// - `Insert`: Keys go to the keymap, so printable keys insert themselves.
+/// - `Visual`: Motions extend a selection from the mark to the point, and operators act on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+}
+
+/// Operator is an action applied to the text covered by a motion or text object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+impl Operator {
+    fn from_char(character: char) -> Option<Operator> {
+        match character {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Operator::Delete => 'd',
+            Operator::Change => 'c',
+            Operator::Yank => 'y',
+        }
+    }
+}
+
+/// Pending is a key that needs another key to finish it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    /// `g`, waiting for the second `g` of `gg`.
+    G,
+    /// `i` or `a` after an operator or in visual mode, waiting for the kind of text object.
+    TextObject { inner: bool },
+}
+
+/// Motion is where a motion key moves the point to, and how operators treat the text it crosses.
+/// - `inclusive`: The character at `target` is covered as well.
+/// - `linewise`: Whole lines are covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Motion {
+    target: usize,
+    inclusive: bool,
+    linewise: bool,
+}
+
+impl Motion {
+    fn exclusive(target: usize) -> Motion {
+        Motion {
+            target,
+            inclusive: false,
+            linewise: false,
+        }
+    }
+}
+
+/// ViState is the vi layer for one buffer: its mode and the keys typed towards the next command,
+/// a count, an operator and a pending key, as in `2d3w` or `ci"`.
+#[derive(Debug, Clone, Default)]
+pub struct ViState {
+    mode: Mode,
+    count: Option<usize>,
+    operator: Option<(Operator, Option<usize>)>,
+    pending: Option<Pending>,
+}
+
+impl ViState {
+    pub fn new() -> ViState {
+        ViState::default()
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// is_pending() is true while a command has been started but not finished.
+    pub fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator.is_some() || self.pending.is_some()
+    }
+
+    /// handle_key() reads `key` and returns `None` if the vi layer leaves it to the keymap, which
+    /// it does for every key in insert mode except escape and for keys with modifiers.
+    pub fn handle_key(&mut self, editor: &mut Editor, key: Key) -> Option<CommandResult> {
+        if key.code == KeyCode::Escape {
+            return Some(self.escape(editor));
+        }
+        if self.mode == Mode::Insert {
+            return None;
+        }
+
+        match key.printable() {
+            Some(character) => Some(self.handle_char(editor, character)),
+            None => {
+                self.reset();
+                None
+            }
+        }
+    }
+
+    fn escape(&mut self, editor: &mut Editor) -> CommandResult {
+        let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+
+        match self.mode {
+            Mode::Insert => {
+                let point = buffer.get_point();
+                if point != line_start(buffer, point) {
+                    let target = previous(buffer, point);
+                    buffer.set_point(target);
+                }
+            }
+            Mode::Visual => buffer.clear_mark(),
+            Mode::Normal => {}
+        }
+        self.mode = Mode::Normal;
+        self.reset();
+
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.count = None;
+        self.operator = None;
+        self.pending = None;
+    }
+
+    /// total_count() is the count typed before the operator times the count typed after it.
+    fn total_count(&self) -> usize {
+        let before = self.operator.and_then(|(_, count)| count).unwrap_or(1);
+
+        before * self.count.unwrap_or(1)
+    }
+
+    fn handle_char(&mut self, editor: &mut Editor, character: char) -> CommandResult {
+        match self.pending.take() {
+            Some(Pending::G) => {
+                let result = match character {
+                    'g' => {
+                        let count = self.count.map_or(0, |count| count - 1);
+                        self.apply_motion(editor, |buffer, _| Some(goto_line(buffer, count)))
+                    }
+                    _ => Ok(()),
+                };
+                self.reset();
+                return result;
+            }
+            Some(Pending::TextObject { inner }) => {
+                let result = self.apply_text_object(editor, character, inner);
+                self.reset();
+                return result;
+            }
+            None => {}
+        }
+
+        if let Some(digit) = character.to_digit(10) {
+            if digit != 0 || self.count.is_some() {
+                self.count = Some(self.count.unwrap_or(0) * 10 + digit as usize);
+                return Ok(());
+            }
+        }
+
+        if let Some((operator, _)) = self.operator {
+            if character == operator.to_char() {
+                let count = self.total_count();
+                let result = apply_to_lines(editor, operator, count).map(|changing| {
+                    if changing {
+                        self.mode = Mode::Insert;
+                    }
+                });
+                self.reset();
+                return result;
+            }
+        }
+
+        if (self.operator.is_some() || self.mode == Mode::Visual) && (character == 'i' || character == 'a') {
+            self.pending = Some(Pending::TextObject { inner: character == 'i' });
+            return Ok(());
+        }
+
+        if character == 'g' {
+            self.pending = Some(Pending::G);
+            return Ok(());
+        }
+
+        if is_motion(character) {
+            let change_word = character == 'w' && matches!(self.operator, Some((Operator::Change, _)));
+            let result = self.apply_motion(editor, |buffer, count| match change_word {
+                true => motion(buffer, 'e', count),
+                false => motion(buffer, character, count),
+            });
+            self.reset();
+            return result;
+        }
+
+        if let Some(operator) = Operator::from_char(character) {
+            if self.mode == Mode::Visual {
+                let result = self.apply_to_selection(editor, operator);
+                self.reset();
+                return result;
+            }
+
+            self.operator = Some((operator, self.count.take()));
+            return Ok(());
+        }
+
+        let count = self.total_count();
+        let result = self.run_command(editor, character, count);
+        self.reset();
+        result
+    }
+
+    /// apply_motion() moves the point with a motion, or applies the pending operator to the text
+    /// between the point and where the motion goes.
+    fn apply_motion<F>(&mut self, editor: &mut Editor, find: F) -> CommandResult
+    where
+        F: FnOnce(&GapBuffer, usize) -> Option<Motion>,
+    {
+        let count = self.total_count();
+        let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+        let point = buffer.get_point();
+        let motion = match find(buffer, count) {
+            Some(motion) => motion,
+            None => return Ok(()),
+        };
+
+        let operator = match self.operator {
+            Some((operator, _)) => operator,
+            None => {
+                buffer.set_point(motion.target);
+                return Ok(());
+            }
+        };
+
+        if motion.linewise {
+            let lines = line_count_between(buffer, point, motion.target);
+            buffer.set_point(point.min(motion.target));
+            if apply_to_lines(editor, operator, lines)? {
+                self.mode = Mode::Insert;
+            }
+            return Ok(());
+        }
+
+        let mut range = point.min(motion.target)..point.max(motion.target);
+        if motion.inclusive {
+            range.end = next(buffer, range.end);
+        }
+        self.apply_operator(editor, operator, range)
+    }
+
+    fn apply_text_object(&mut self, editor: &mut Editor, kind: char, inner: bool) -> CommandResult {
+        let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+        let range = match text_object(buffer, buffer.get_point(), kind, inner) {
+            Some(range) => range,
+            None => return Ok(()),
+        };
+
+        match self.operator {
+            Some((operator, _)) => self.apply_operator(editor, operator, range),
+            None => {
+                if range.start < range.end {
+                    buffer.set_mark(range.start);
+                    let target = previous(buffer, range.end);
+                    buffer.set_point(target);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn apply_to_selection(&mut self, editor: &mut Editor, operator: Operator) -> CommandResult {
+        let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+        let point = buffer.get_point();
+        let mark = buffer.get_mark().unwrap_or(point);
+        let range = point.min(mark)..next(buffer, point.max(mark));
+        buffer.clear_mark();
+        self.mode = Mode::Normal;
+
+        self.apply_operator(editor, operator, range)
+    }
+
+    fn apply_operator(&mut self, editor: &mut Editor, operator: Operator, range: Range<usize>) -> CommandResult {
+        let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+        if operator != Operator::Yank {
+            buffer.check_writable()?;
+        }
+
+        match operator {
+            Operator::Yank => {
+                let text = buffer.slice(range.clone()).into_owned();
+                buffer.set_point(range.start);
+                editor.registers_mut().yank(None, text);
+            }
+            Operator::Delete | Operator::Change => {
+                let text = buffer.remove_bytes(range.clone());
+                buffer.set_point(range.start);
+                editor.registers_mut().delete(None, text);
+                if operator == Operator::Change {
+                    self.mode = Mode::Insert;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// run_command() runs the normal mode commands that are neither motions nor operators.
+    fn run_command(&mut self, editor: &mut Editor, character: char, count: usize) -> CommandResult {
+        let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+        if "iaIAoOxXDCpPu".contains(character) {
+            buffer.check_writable()?;
+        }
+        let point = buffer.get_point();
+        let (line, _) = buffer.offset_to_line_col(point);
+        let line_end = buffer.line_end_offset(line).unwrap();
+
+        match character {
+            'i' => self.mode = Mode::Insert,
+            'a' => {
+                let target = next(buffer, point).min(line_end);
+                buffer.set_point(target);
+                self.mode = Mode::Insert;
+            }
+            'I' => {
+                let target = first_non_blank(buffer, point);
+                buffer.set_point(target);
+                self.mode = Mode::Insert;
+            }
+            'A' => {
+                buffer.set_point(line_end);
+                self.mode = Mode::Insert;
+            }
+            'o' => {
+                buffer.set_point(line_end);
+                buffer.insert_str("\n");
+                self.mode = Mode::Insert;
+            }
+            'O' => {
+                let start = line_start(buffer, point);
+                buffer.set_point(start);
+                buffer.insert_str("\n");
+                buffer.set_point(start);
+                self.mode = Mode::Insert;
+            }
+            'v' => match self.mode {
+                Mode::Visual => {
+                    buffer.clear_mark();
+                    self.mode = Mode::Normal;
+                }
+                _ => {
+                    buffer.set_mark_at_point();
+                    self.mode = Mode::Visual;
+                }
+            },
+            'x' => {
+                let end = (0..count).fold(point, |end, _| next(buffer, end).min(line_end));
+                return self.apply_operator(editor, Operator::Delete, point..end);
+            }
+            'X' => {
+                let start = line_start(buffer, point);
+                let begin = (0..count).fold(point, |begin, _| previous(buffer, begin).max(start));
+                return self.apply_operator(editor, Operator::Delete, begin..point);
+            }
+            'D' => return self.apply_operator(editor, Operator::Delete, point..line_end),
+            'C' => return self.apply_operator(editor, Operator::Change, point..line_end),
+            'p' | 'P' => {
+                let text = match editor.registers().get(UNNAMED_REGISTER) {
+                    Some(text) => text.repeat(count),
+                    None => return Ok(()),
+                };
+                let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+                paste(buffer, &text, character == 'p');
+            }
+            'u' => {
+                let undone = (0..count).all(|_| buffer.undo());
+                if !undone {
+                    editor.set_message("Already at oldest change");
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// apply_to_lines() applies `operator` to `count` whole lines starting at the point's line and
+/// returns true if the editor should enter insert mode.
+fn apply_to_lines(editor: &mut Editor, operator: Operator, count: usize) -> Result<bool, CommandError> {
+    let buffer = editor.current_buffer_mut().ok_or(CommandError::NoBuffer)?;
+    if operator != Operator::Yank {
+        buffer.check_writable()?;
+    }
+    let (first, _) = buffer.offset_to_line_col(buffer.get_point());
+    let last = (first + count - 1).min(buffer.line_count() - 1);
+    let start = buffer.line_to_offset(first).unwrap();
+    let end = buffer.line_end_offset(last).unwrap();
+    let text = buffer.slice(start..end).into_owned() + "\n";
+
+    match operator {
+        Operator::Yank => {
+            editor.registers_mut().yank(None, text);
+            Ok(false)
+        }
+        Operator::Change => {
+            buffer.remove_bytes(start..end);
+            buffer.set_point(start);
+            editor.registers_mut().delete(None, text);
+            Ok(true)
+        }
+        Operator::Delete => {
+            let range = line_range(buffer, start, end);
+            buffer.remove_bytes(range.clone());
+            let point = line_start(buffer, range.start.min(buffer.len()));
+            let target = first_non_blank(buffer, point);
+            buffer.set_point(target);
+            editor.registers_mut().delete(None, text);
+            Ok(false)
+        }
+    }
+}
+
+/// paste() inserts `text` after the point, or before it if `after` is false. Text ending in a
+/// newline was taken from whole lines, so it goes on its own lines below or above the point's.
+fn paste(buffer: &mut GapBuffer, text: &str, after: bool) {
+    let point = buffer.get_point();
+    let (line, _) = buffer.offset_to_line_col(point);
+
+    if let Some(lines) = text.strip_suffix('\n') {
+        let start = match (after, buffer.line_to_offset(line + 1)) {
+            (false, _) => buffer.line_to_offset(line).unwrap(),
+            (true, Some(next_line)) => next_line,
+            (true, None) => {
+                let end = buffer.len();
+                buffer.set_point(end);
+                buffer.insert_str("\n");
+                buffer.insert_str(lines);
+                buffer.set_point(end + 1);
+                return;
+            }
+        };
+        buffer.set_point(start);
+        buffer.insert_str(text);
+        buffer.set_point(start);
+    } else {
+        let line_end = buffer.line_end_offset(line).unwrap();
+        let start = match after {
+            true => next(buffer, point).min(line_end),
+            false => point,
+        };
+        buffer.set_point(start);
+        buffer.insert_str(text);
+        let end = buffer.get_point();
+        let target = previous(buffer, end);
+        buffer.set_point(target);
+    }
+}
+
+fn is_motion(character: char) -> bool {
+    "hjklwbe0^$G".contains(character)
+}
+
+/// motion() finds where the motion key `character` moves the point to, repeated `count` times.
+fn motion(buffer: &GapBuffer, character: char, count: usize) -> Option<Motion> {
+    let point = buffer.get_point();
+    let (line, _) = buffer.offset_to_line_col(point);
+    let repeat = |step: &dyn Fn(usize) -> usize| (0..count).fold(point, |offset, _| step(offset));
+
+    let motion = match character {
+        'h' => Motion::exclusive(repeat(&|offset| previous(buffer, offset).max(line_start(buffer, point)))),
+        'l' => Motion::exclusive(repeat(&|offset| next(buffer, offset).min(buffer.line_end_offset(line).unwrap()))),
+        'w' => Motion::exclusive(repeat(&|offset| forward_word_start(buffer, offset))),
+        'b' => Motion::exclusive(repeat(&|offset| backward_word_start(buffer, offset))),
+        'e' => Motion {
+            target: repeat(&|offset| forward_word_end(buffer, offset)),
+            inclusive: true,
+            linewise: false,
+        },
+        '0' => Motion::exclusive(line_start(buffer, point)),
+        '^' => Motion::exclusive(first_non_blank(buffer, point)),
+        '$' => Motion::exclusive(buffer.line_end_offset(line).unwrap()),
+        'j' | 'k' => {
+            let last_line = buffer.line_count() - 1;
+            let target_line = match character {
+                'j' => (line + count).min(last_line),
+                _ => line.saturating_sub(count),
+            };
+            let column = buffer.char_column(point);
+            Motion {
+                target: column_on_line(buffer, target_line, column),
+                inclusive: false,
+                linewise: true,
+            }
+        }
+        'G' => goto_line(buffer, buffer.line_count() - 1),
+        _ => return None,
+    };
+
+    Some(motion)
+}
+
+/// goto_line() is the linewise motion to the first non-blank character of `line`, clamped to the
+/// last line. `G` takes a count as a line number, which reaches here through `gg`'s motion.
+fn goto_line(buffer: &GapBuffer, line: usize) -> Motion {
+    let line = line.min(buffer.line_count() - 1);
+
+    Motion {
+        target: first_non_blank(buffer, buffer.line_to_offset(line).unwrap()),
+        inclusive: false,
+        linewise: true,
+    }
+}
+
+/// text_object() returns the range of the text object `kind` around `offset`, such as the word
+/// for `w` or the quoted text for `"`. With `inner`, surrounding delimiters or spaces are left out.
+fn text_object(buffer: &GapBuffer, offset: usize, kind: char, inner: bool) -> Option<Range<usize>> {
+    match kind {
+        'w' => Some(word_object(buffer, offset, inner)),
+        '"' | '\'' | '`' => quote_object(buffer, offset, kind as u8, inner),
+        '(' | ')' | 'b' => bracket_object(buffer, offset, b'(', b')', inner),
+        '[' | ']' => bracket_object(buffer, offset, b'[', b']', inner),
+        '{' | '}' | 'B' => bracket_object(buffer, offset, b'{', b'}', inner),
+        '<' | '>' => bracket_object(buffer, offset, b'<', b'>', inner),
+        _ => None,
+    }
+}
+
+fn word_object(buffer: &GapBuffer, offset: usize, inner: bool) -> Range<usize> {
+    let class = match buffer.char_at(offset) {
+        Some(character) if character != '\n' => CharClass::of(character),
+        _ => return offset..offset,
+    };
+    let same = |index: usize| buffer.char_at(index).is_some_and(|c| c != '\n' && CharClass::of(c) == class);
+    let blank = |index: usize| buffer.char_at(index).is_some_and(|c| c == ' ' || c == '\t');
+
+    let mut start = offset;
+    while start > 0 && same(previous(buffer, start)) {
+        start = previous(buffer, start);
+    }
+    let mut end = offset;
+    while same(end) {
+        end = next(buffer, end);
+    }
+
+    if !inner {
+        if blank(end) {
+            while blank(end) {
+                end = next(buffer, end);
+            }
+        } else {
+            while start > 0 && blank(previous(buffer, start)) {
+                start = previous(buffer, start);
+            }
+        }
+    }
+
+    start..end
+}
+
+/// quote_object() finds the pair of `quote` characters on the line of `offset` that surround it,
+/// or else the first pair after it.
+fn quote_object(buffer: &GapBuffer, offset: usize, quote: u8, inner: bool) -> Option<Range<usize>> {
+    let start = line_start(buffer, offset);
+    let (line, _) = buffer.offset_to_line_col(offset);
+    let end = buffer.line_end_offset(line).unwrap();
+
+    let quotes: Vec<usize> = (start..end).filter(|index| buffer.byte(*index) == Some(quote)).collect();
+    let (open, close) = quotes
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .find(|(_, close)| offset <= *close)?;
+
+    match inner {
+        true => Some(open + 1..close),
+        false => Some(open..close + 1),
+    }
+}
+
+/// bracket_object() finds the innermost `open` and `close` pair surrounding `offset`.
+fn bracket_object(buffer: &GapBuffer, offset: usize, open: u8, close: u8, inner: bool) -> Option<Range<usize>> {
+    let start = match buffer.byte(offset) {
+        Some(byte) if byte == open => offset,
+        _ => {
+            let mut depth = 0;
+            let mut index = offset;
+            loop {
+                if index == 0 {
+                    return None;
+                }
+                index -= 1;
+                match buffer.byte(index) {
+                    Some(byte) if byte == open && depth == 0 => break index,
+                    Some(byte) if byte == open => depth -= 1,
+                    Some(byte) if byte == close => depth += 1,
+                    _ => {}
+                }
+            }
+        }
+    };
+
+    let mut depth = 0;
+    let end = (start + 1..buffer.len()).find(|index| match buffer.byte(*index) {
+        Some(byte) if byte == open => {
+            depth += 1;
+            false
+        }
+        Some(byte) if byte == close => {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+            false
+        }
+        _ => false,
+    })?;
+
+    match inner {
+        true => Some(start + 1..end),
+        false => Some(start..end + 1),
+    }
+}
+
+/// CharClass groups characters the way vi words do: a word is a run of one class other than space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+impl CharClass {
+    fn of(character: char) -> CharClass {
+        if character.is_whitespace() {
+            CharClass::Space
+        } else if is_word_char(character) {
+            CharClass::Word
+        } else {
+            CharClass::Punctuation
+        }
+    }
+}
+
+fn class_at(buffer: &GapBuffer, offset: usize) -> Option<CharClass> {
+    buffer.char_at(offset).map(CharClass::of)
+}
+
+fn forward_word_start(buffer: &GapBuffer, offset: usize) -> usize {
+    let mut index = offset;
+
+    if let Some(class) = class_at(buffer, index).filter(|class| *class != CharClass::Space) {
+        while class_at(buffer, index) == Some(class) {
+            index = next(buffer, index);
+        }
+    }
+    while class_at(buffer, index) == Some(CharClass::Space) {
+        index = next(buffer, index);
+    }
+
+    index
+}
+
+fn forward_word_end(buffer: &GapBuffer, offset: usize) -> usize {
+    let mut index = next(buffer, offset);
+    while class_at(buffer, index) == Some(CharClass::Space) {
+        index = next(buffer, index);
+    }
+
+    let class = match class_at(buffer, index) {
+        Some(class) => class,
+        None => return previous(buffer, buffer.len()),
+    };
+    while class_at(buffer, next(buffer, index)) == Some(class) {
+        index = next(buffer, index);
+    }
+
+    index
+}
+
+fn backward_word_start(buffer: &GapBuffer, offset: usize) -> usize {
+    if offset == 0 {
+        return 0;
+    }
+
+    let mut index = previous(buffer, offset);
+    while index > 0 && class_at(buffer, index) == Some(CharClass::Space) {
+        index = previous(buffer, index);
+    }
+
+    let class = class_at(buffer, index);
+    while index > 0 && class_at(buffer, previous(buffer, index)) == class {
+        index = previous(buffer, index);
+    }
+
+    index
+}
+
+fn next(buffer: &GapBuffer, offset: usize) -> usize {
+    buffer.next_char_boundary(offset).unwrap_or(buffer.len())
+}
+
+fn previous(buffer: &GapBuffer, offset: usize) -> usize {
+    buffer.prev_char_boundary(offset).unwrap_or(0)
+}
+
+fn line_start(buffer: &GapBuffer, offset: usize) -> usize {
+    let (line, _) = buffer.offset_to_line_col(offset);
+
+    buffer.line_to_offset(line).unwrap()
+}
+
+fn first_non_blank(buffer: &GapBuffer, offset: usize) -> usize {
+    let mut index = line_start(buffer, offset);
+    while buffer.char_at(index).is_some_and(|c| c == ' ' || c == '\t') {
+        index = next(buffer, index);
+    }
+
+    index
+}
+
+/// column_on_line() returns the offset of character `column` on `line`, or the line's end if it
+/// is shorter.
+fn column_on_line(buffer: &GapBuffer, line: usize, column: usize) -> usize {
+    let end = buffer.line_end_offset(line).unwrap();
+
+    (0..column).fold(buffer.line_to_offset(line).unwrap(), |offset, _| next(buffer, offset).min(end))
+}
+
+fn line_count_between(buffer: &GapBuffer, a: usize, b: usize) -> usize {
+    let (first, _) = buffer.offset_to_line_col(a.min(b));
+    let (last, _) = buffer.offset_to_line_col(a.max(b));
+
+    last - first + 1
+}
+
+/// line_range() covers every line from the one holding `a` to the one holding `b` with the
+/// newline that ends them, or the newline before them for the last line of the buffer.
+fn line_range(buffer: &GapBuffer, a: usize, b: usize) -> Range<usize> {
+    let (first, _) = buffer.offset_to_line_col(a.min(b));
+    let (last, _) = buffer.offset_to_line_col(a.max(b));
+    let start = buffer.line_to_offset(first).unwrap();
+
+    match buffer.line_to_offset(last + 1) {
+        Some(end) => start..end,
+        None if start > 0 => start - 1..buffer.len(),
+        None => start..buffer.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer::Buffer;
+    use editor::Editor;
+    use keymap::{Key, KeyCode};
+    use registers::UNNAMED_REGISTER;
+    use vi::Mode;
+
+    /// vi_editor() returns an editor in vi normal mode holding `text` with the point at `point`.
+    fn vi_editor(text: &str, point: usize) -> Editor {
+        let mut editor = Editor::new();
+        let id = editor.buffers_mut().add(Buffer::from_text("test", text.to_string()));
+        editor.buffers_mut().switch_to(id);
+        editor.current_buffer_mut().unwrap().set_point(point);
+        editor.set_vi_mode(id, true);
+
+        editor
+    }
+
+    fn type_keys(editor: &mut Editor, keys: &str) {
+        for character in keys.chars() {
+            let key = match character {
+                '\u{1b}' => Key::plain(KeyCode::Escape),
+                character => Key::char(character),
+            };
+            editor.handle_key(key).unwrap();
+        }
+    }
+
+    fn contents(editor: &Editor) -> String {
+        editor.current_buffer().unwrap().to_string()
+    }
+
+    fn point(editor: &Editor) -> usize {
+        editor.current_buffer().unwrap().get_point()
+    }
+
+    fn mode(editor: &Editor) -> Mode {
+        let id = editor.buffers().current_id().unwrap();
+        editor.vi_state(id).unwrap().mode()
+    }
+
+    #[test]
+    fn motions() {
+        let mut editor = vi_editor("one two.three\n  four", 0);
+
+        type_keys(&mut editor, "w");
+        assert_eq!(point(&editor), 4);
+        type_keys(&mut editor, "w");
+        assert_eq!(point(&editor), 7);
+        type_keys(&mut editor, "e");
+        assert_eq!(point(&editor), 12);
+        type_keys(&mut editor, "b");
+        assert_eq!(point(&editor), 8);
+        type_keys(&mut editor, "$");
+        assert_eq!(point(&editor), 13);
+        type_keys(&mut editor, "0");
+        assert_eq!(point(&editor), 0);
+        type_keys(&mut editor, "j^");
+        assert_eq!(point(&editor), 16);
+        type_keys(&mut editor, "gg3l");
+        assert_eq!(point(&editor), 3);
+        type_keys(&mut editor, "G");
+        assert_eq!(point(&editor), 16);
+    }
+
+    #[test]
+    fn counts_repeat_motions() {
+        let mut editor = vi_editor("a b c d e f", 0);
+
+        type_keys(&mut editor, "3w");
+        assert_eq!(point(&editor), 6);
+        type_keys(&mut editor, "2h");
+        assert_eq!(point(&editor), 4);
+        type_keys(&mut editor, "10l");
+        assert_eq!(point(&editor), 11);
+    }
+
+    #[test]
+    fn operators_compose_with_motions_and_counts() {
+        let mut editor = vi_editor("one two three four five", 0);
+
+        type_keys(&mut editor, "d2w");
+        assert_eq!(contents(&editor), "three four five");
+        assert_eq!(editor.registers().get(UNNAMED_REGISTER), Some("one two "));
+
+        type_keys(&mut editor, "2d2w");
+        assert_eq!(contents(&editor), "");
+    }
+
+    #[test]
+    fn change_a_word_keeps_the_space() {
+        let mut editor = vi_editor("let old = 1;", 4);
+
+        type_keys(&mut editor, "cwnew\u{1b}");
+
+        assert_eq!(contents(&editor), "let new = 1;");
+        assert_eq!(mode(&editor), Mode::Normal);
+        assert_eq!(point(&editor), 6);
+    }
+
+    #[test]
+    fn change_inside_quotes() {
+        let mut editor = vi_editor("print(\"hello\", x)", 2);
+
+        type_keys(&mut editor, "ci\"bye\u{1b}");
+        assert_eq!(contents(&editor), "print(\"bye\", x)");
+
+        type_keys(&mut editor, "di(");
+        assert_eq!(contents(&editor), "print()");
+    }
+
+    #[test]
+    fn text_objects() {
+        let mut editor = vi_editor("f(a, (b), c) word  next", 6);
+
+        type_keys(&mut editor, "da(");
+        assert_eq!(contents(&editor), "f(a, , c) word  next");
+
+        type_keys(&mut editor, "ya(");
+        assert_eq!(editor.registers().get('0'), Some("(a, , c)"));
+
+        type_keys(&mut editor, "$bdaw");
+        assert_eq!(contents(&editor), "f(a, , c) word");
+        type_keys(&mut editor, "bdiw");
+        assert_eq!(contents(&editor), "f(a, , c) ");
+    }
+
+    #[test]
+    fn line_operators() {
+        let mut editor = vi_editor("one\ntwo\nthree\nfour", 5);
+
+        type_keys(&mut editor, "yy");
+        assert_eq!(editor.registers().get('0'), Some("two\n"));
+
+        type_keys(&mut editor, "2dd");
+        assert_eq!(contents(&editor), "one\nfour");
+        assert_eq!(point(&editor), 4);
+
+        type_keys(&mut editor, "P");
+        assert_eq!(contents(&editor), "one\ntwo\nthree\nfour");
+
+        type_keys(&mut editor, "G");
+        assert_eq!(point(&editor), 14);
+        type_keys(&mut editor, "dk");
+        assert_eq!(contents(&editor), "one\ntwo");
+
+        type_keys(&mut editor, "ccnew\u{1b}");
+        assert_eq!(contents(&editor), "one\nnew");
+    }
+
+    #[test]
+    fn paste_after_the_point() {
+        let mut editor = vi_editor("abc\ndef", 0);
+
+        type_keys(&mut editor, "xp");
+        assert_eq!(contents(&editor), "bac\ndef");
+        assert_eq!(point(&editor), 1);
+
+        type_keys(&mut editor, "yyjp");
+        assert_eq!(contents(&editor), "bac\ndef\nbac");
+        assert_eq!(point(&editor), 8);
+    }
+
+    #[test]
+    fn undo_changes() {
+        let mut editor = vi_editor("abc\ndef", 0);
+
+        type_keys(&mut editor, "xxdd");
+        assert_eq!(contents(&editor), "def");
+        type_keys(&mut editor, "u");
+        assert_eq!(contents(&editor), "c\ndef");
+        type_keys(&mut editor, "2u");
+        assert_eq!(contents(&editor), "abc\ndef");
+        type_keys(&mut editor, "u");
+        assert_eq!(editor.message(), Some("Already at oldest change"));
+    }
+
+    #[test]
+    fn insert_mode_uses_the_keymap() {
+        let mut editor = vi_editor("text", 2);
+
+        type_keys(&mut editor, "i");
+        assert_eq!(mode(&editor), Mode::Insert);
+        type_keys(&mut editor, "dw\u{1b}");
+        assert_eq!(contents(&editor), "tedwxt");
+        assert_eq!(point(&editor), 3);
+
+        type_keys(&mut editor, "A!\u{1b}o-\u{1b}");
+        assert_eq!(contents(&editor), "tedwxt!\n-");
+    }
+
+    #[test]
+    fn visual_mode_selects_for_operators() {
+        let mut editor = vi_editor("one two three", 4);
+
+        type_keys(&mut editor, "vey");
+        assert_eq!(editor.registers().get('0'), Some("two"));
+        assert_eq!(mode(&editor), Mode::Normal);
+        assert_eq!(editor.current_buffer().unwrap().get_mark(), None);
+
+        type_keys(&mut editor, "viwd");
+        assert_eq!(contents(&editor), "one  three");
+    }
+
+    #[test]
+    fn control_keys_fall_through_to_the_keymap() {
+        let mut editor = vi_editor("one\ntwo", 0);
+        editor.global_keymap_mut().bind_str("C-n", "next-line").unwrap();
+
+        editor.handle_key(Key::control('n')).unwrap();
+
+        assert_eq!(point(&editor), 4);
+    }
+
+    #[test]
+    fn vi_mode_is_per_buffer() {
+        let mut editor = vi_editor("abc", 0);
+        let id = editor.buffers().current_id().unwrap();
+
+        editor.execute("toggle-vi-mode", "").unwrap();
+        assert!(editor.vi_state(id).is_none());
+
+        type_keys(&mut editor, "x");
+        assert_eq!(contents(&editor), "xabc");
+    }
+}
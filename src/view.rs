@@ -0,0 +1,756 @@
+use std::cmp;
+use std::iter;
+use std::ops::Range;
+
+use buffer::transform::DEFAULT_TAB_WIDTH;
+use GapBuffer;
+
+pub use self::wrap::{VisualRow, Wrap};
+
+pub mod wrap;
+
+/// How many lines of the old screen stay visible after paging, for context.
+pub const PAGE_OVERLAP: usize = 2;
+
+/// What is drawn at the edge of the view where a line is cut off.
+pub const TRUNCATION_INDICATOR: &str = "$";
+
+/// LineNumbers is how lines are numbered in the gutter beside the text.
+/// - `Absolute`: Every line shows its own number.
+/// - `Relative`: The point's line shows its own number, and the others how far they are from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    Relative,
+}
+
+impl LineNumbers {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineNumbers::Off => "off",
+            LineNumbers::Absolute => "absolute",
+            LineNumbers::Relative => "relative",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LineNumbers> {
+        [LineNumbers::Off, LineNumbers::Absolute, LineNumbers::Relative]
+            .iter()
+            .find(|line_numbers| line_numbers.name() == name)
+            .cloned()
+    }
+}
+
+/// View is the part of a buffer on screen, independent of how it is drawn.
+/// - `top_line`, `top_row`: The first line shown, and which of its screen rows is at the top when
+///   it is wrapped.
+/// - `width`, `height`: The size of the window onto the buffer, in columns and lines.
+/// - `horizontal_offset`: How many columns are scrolled off the left edge.
+/// - `horizontal_step`: How many columns to scroll sideways at a time when the point goes off the
+///   left or right edge, or 0 to put the point in the middle.
+/// - `scroll_margin`: How many lines to keep between the point and the top or bottom edge, where
+///   the buffer allows.
+/// - `line_numbers`: Whether and how lines are numbered in a gutter.
+/// - `wrap`: How lines wider than the view are shown.
+/// - `tab_width`: How many columns apart tab stops are.
+///
+/// Screen rows are laid out from the buffer when they are needed rather than kept, so the mapping
+/// from lines to rows follows every edit and resize.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    top_line: usize,
+    top_row: usize,
+    width: usize,
+    height: usize,
+    horizontal_offset: usize,
+    horizontal_step: usize,
+    scroll_margin: usize,
+    line_numbers: LineNumbers,
+    wrap: Wrap,
+    tab_width: usize,
+}
+
+impl View {
+    pub fn new(width: usize, height: usize) -> View {
+        View {
+            top_line: 0,
+            top_row: 0,
+            width,
+            height,
+            horizontal_offset: 0,
+            horizontal_step: 0,
+            scroll_margin: 0,
+            line_numbers: LineNumbers::Off,
+            wrap: Wrap::Off,
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+
+    pub fn top_line(&self) -> usize {
+        self.top_line
+    }
+
+    pub fn set_top_line(&mut self, line: usize) {
+        self.top_line = line;
+        self.top_row = 0;
+    }
+
+    pub fn top_row(&self) -> usize {
+        self.top_row
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+    }
+
+    pub fn horizontal_offset(&self) -> usize {
+        self.horizontal_offset
+    }
+
+    pub fn set_horizontal_offset(&mut self, offset: usize) {
+        self.horizontal_offset = offset;
+    }
+
+    pub fn horizontal_step(&self) -> usize {
+        self.horizontal_step
+    }
+
+    pub fn set_horizontal_step(&mut self, step: usize) {
+        self.horizontal_step = step;
+    }
+
+    pub fn scroll_margin(&self) -> usize {
+        self.scroll_margin
+    }
+
+    pub fn set_scroll_margin(&mut self, margin: usize) {
+        self.scroll_margin = margin;
+    }
+
+    pub fn line_numbers(&self) -> LineNumbers {
+        self.line_numbers
+    }
+
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
+    }
+
+    pub fn wrap(&self) -> Wrap {
+        self.wrap
+    }
+
+    /// set_wrap() changes how long lines are shown. Wrapped lines are never scrolled horizontally.
+    pub fn set_wrap(&mut self, wrap: Wrap) {
+        self.wrap = wrap;
+        self.top_row = 0;
+        if wrap != Wrap::Off {
+            self.horizontal_offset = 0;
+        }
+    }
+
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// set_tab_width() changes how far apart tab stops are. A width of zero is taken as one.
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width.max(1);
+        self.top_row = 0;
+    }
+
+    /// column() is the screen column `offset` of `buffer` is drawn at, counting from the start of
+    /// its line, with tabs expanded and before any horizontal scrolling.
+    pub fn column(&self, buffer: &GapBuffer, offset: usize) -> usize {
+        let start = buffer.line_to_offset(buffer.offset_to_line_col(offset).0).unwrap();
+        wrap::display_width(&buffer.slice(start..offset), self.tab_width)
+    }
+
+    /// gutter_width() is how many columns the signs and line numbers of `buffer` take up,
+    /// including the space between the numbers and the text. It is wide enough for the last
+    /// line's number, so it grows with the buffer rather than with the lines on screen. A column
+    /// is kept for signs while the buffer has any.
+    pub fn gutter_width(&self, buffer: &GapBuffer) -> usize {
+        let signs = if buffer.has_signs() { 1 } else { 0 };
+        match self.line_numbers {
+            LineNumbers::Off => signs,
+            LineNumbers::Absolute | LineNumbers::Relative => signs + buffer.line_count().to_string().len() + 1,
+        }
+    }
+
+    /// text_width() is how many columns are left for the text beside the gutter.
+    pub fn text_width(&self, buffer: &GapBuffer) -> usize {
+        self.width.saturating_sub(self.gutter_width(buffer))
+    }
+
+    /// gutter() is the gutter beside `line`, padded to the gutter width: the line's sign, if the
+    /// buffer has signs, then its number. It is not scrolled horizontally with the text.
+    pub fn gutter(&self, buffer: &GapBuffer, line: usize) -> String {
+        let sign = match (buffer.has_signs(), buffer.line_sign(line)) {
+            (false, _) => String::new(),
+            (true, Some((text, _))) => text.to_string(),
+            (true, None) => " ".to_string(),
+        };
+        let (point_line, _) = buffer.offset_to_line_col(buffer.get_point());
+        let number = match self.line_numbers {
+            LineNumbers::Off => return sign,
+            LineNumbers::Relative if line != point_line => (line as isize - point_line as isize).unsigned_abs(),
+            LineNumbers::Absolute | LineNumbers::Relative => line + 1,
+        };
+
+        format!("{}{:>width$} ", sign, number, width = self.gutter_width(buffer) - sign.chars().count() - 1)
+    }
+
+    /// line_rows() returns the screen rows `line` of `buffer` is shown on.
+    pub fn line_rows(&self, buffer: &GapBuffer, line: usize) -> Vec<VisualRow> {
+        let start = buffer.line_to_offset(line).unwrap();
+        let end = buffer.line_end_offset(line).unwrap();
+        let ranges = match self.wrap {
+            Wrap::Off => iter::once(0..end - start).collect(),
+            Wrap::Char | Wrap::Word => {
+                wrap::wrap_line(&buffer.slice(start..end), self.text_width(buffer), self.wrap, self.tab_width)
+            }
+        };
+        let count = ranges.len();
+
+        ranges
+            .into_iter()
+            .enumerate()
+            .map(|(row, range)| VisualRow {
+                line,
+                row,
+                range: start + range.start..start + range.end,
+                last: row + 1 == count,
+            })
+            .collect()
+    }
+
+    /// visible_rows() returns the screen rows on screen, from the top.
+    pub fn visible_rows(&self, buffer: &GapBuffer) -> Vec<VisualRow> {
+        let mut rows = Vec::new();
+        let mut skip = self.top_row;
+
+        for line in self.top_line..buffer.line_count() {
+            if rows.len() >= self.height {
+                break;
+            }
+            let remaining = self.height - rows.len();
+            rows.extend(self.line_rows(buffer, line).into_iter().skip(skip).take(remaining));
+            skip = 0;
+        }
+
+        rows
+    }
+
+    /// visible_lines() returns the lines of `buffer` that are at least partly on screen.
+    pub fn visible_lines(&self, buffer: &GapBuffer) -> Range<usize> {
+        let rows = self.visible_rows(buffer);
+
+        match (rows.first(), rows.last()) {
+            (Some(first), Some(last)) => first.line..last.line + 1,
+            _ => {
+                let end = self.top_line.min(buffer.line_count());
+                end..end
+            }
+        }
+    }
+
+    /// clipped() reports whether text of `row` is hidden past the left edge and past the right
+    /// edge of the view, where a frontend draws `TRUNCATION_INDICATOR`.
+    pub fn clipped(&self, buffer: &GapBuffer, row: &VisualRow) -> (bool, bool) {
+        if self.wrap != Wrap::Off {
+            return (false, false);
+        }
+        let width = wrap::display_width(&buffer.slice(row.range.clone()), self.tab_width);
+
+        (self.horizontal_offset > 0 && width > 0, width > self.horizontal_offset + self.text_width(buffer))
+    }
+
+    /// offset_at() is the offset of the character drawn at `column` of screen row `row`,
+    /// counting from the left edge of the view including the gutter. A position past the end of a
+    /// row is the end of the row, and one below the last row is on the last row.
+    pub fn offset_at(&self, buffer: &GapBuffer, column: usize, row: usize) -> usize {
+        let rows = self.visible_rows(buffer);
+        let row = match rows.get(row).or_else(|| rows.last()) {
+            Some(row) => row,
+            None => return buffer.len(),
+        };
+        let column = column.saturating_sub(self.gutter_width(buffer)) + self.horizontal_offset;
+
+        self.offset_in_row(buffer, row, column)
+    }
+
+    /// point_row() returns the screen row the point is on.
+    pub fn point_row(&self, buffer: &GapBuffer) -> VisualRow {
+        self.offset_row(buffer, buffer.get_point())
+    }
+
+    /// offset_row() returns the screen row the point would be on at `offset`.
+    pub fn offset_row(&self, buffer: &GapBuffer, offset: usize) -> VisualRow {
+        let (line, _) = buffer.offset_to_line_col(offset);
+
+        self.line_rows(buffer, line).into_iter().find(|row| row.contains(offset)).unwrap()
+    }
+
+    /// keep_point_visible() scrolls as little as needed to bring the point's row on screen, and
+    /// outside the scroll margins unless it is near the start or end of the buffer.
+    pub fn keep_point_visible(&mut self, buffer: &GapBuffer) {
+        self.keep_offset_visible(buffer, buffer.get_point());
+    }
+
+    /// keep_offset_visible() is keep_point_visible() for a window whose point is at `offset`
+    /// while it is not the buffer's point, as for a window that is not selected. A top row left
+    /// past the end of its line by a wider view wrapping the line into fewer rows is moved back
+    /// onto it first.
+    pub fn keep_offset_visible(&mut self, buffer: &GapBuffer, offset: usize) {
+        self.top_line = self.top_line.min(buffer.line_count() - 1);
+        self.top_row = self.top_row.min(self.line_rows(buffer, self.top_line).len() - 1);
+
+        let point = self.offset_row(buffer, offset);
+        let point = (point.line, point.row);
+        let margin = self.margin() as isize;
+        let top = (self.top_line, self.top_row);
+
+        let earliest = self.step(buffer, point, -margin);
+        let top = if earliest < top {
+            earliest
+        } else if self.height > 0 {
+            let bottom = self.step(buffer, point, margin);
+            cmp::max(top, self.step(buffer, bottom, 1 - self.height as isize))
+        } else {
+            top
+        };
+        self.top_line = top.0;
+        self.top_row = top.1;
+        self.keep_column_visible(buffer, offset);
+    }
+
+    /// keep_column_visible() scrolls sideways, by the horizontal step, when `point` is off the
+    /// left or right edge or under a truncation indicator there. Wrapped lines never scroll.
+    fn keep_column_visible(&mut self, buffer: &GapBuffer, point: usize) {
+        let width = self.text_width(buffer);
+        if self.wrap != Wrap::Off || width < 3 {
+            self.horizontal_offset = 0;
+            return;
+        }
+
+        let column = self.column(buffer, point);
+        let offset = self.horizontal_offset;
+        let first = if offset > 0 { offset + 1 } else { 0 };
+
+        if column < first {
+            self.horizontal_offset = match self.horizontal_step {
+                0 => column.saturating_sub(width / 2),
+                step => offset.saturating_sub(step).min(column.saturating_sub(1)),
+            };
+        } else if column + 1 >= offset + width {
+            self.horizontal_offset = match self.horizontal_step {
+                0 => column + 1 - width / 2,
+                step => (offset + step).max(column + 2 - width),
+            };
+        }
+    }
+
+    /// scroll() moves the view `lines` screen rows down the buffer, or up for a negative count,
+    /// and moves the point onto the screen if it was scrolled off. It returns false if the view
+    /// could not move.
+    pub fn scroll(&mut self, buffer: &mut GapBuffer, lines: isize) -> bool {
+        let top = (self.top_line, self.top_row);
+        let (top_line, top_row) = self.step(buffer, top, lines);
+        if (top_line, top_row) == top {
+            return false;
+        }
+
+        self.top_line = top_line;
+        self.top_row = top_row;
+        self.move_point_into_view(buffer);
+        true
+    }
+
+    /// page_down() scrolls forward by a screen, less `PAGE_OVERLAP` lines of context.
+    pub fn page_down(&mut self, buffer: &mut GapBuffer) -> bool {
+        let lines = self.height.saturating_sub(PAGE_OVERLAP).max(1);
+        self.scroll(buffer, lines as isize)
+    }
+
+    /// page_up() scrolls back by a screen, less `PAGE_OVERLAP` lines of context.
+    pub fn page_up(&mut self, buffer: &mut GapBuffer) -> bool {
+        let lines = self.height.saturating_sub(PAGE_OVERLAP).max(1);
+        self.scroll(buffer, -(lines as isize))
+    }
+
+    /// recenter() scrolls so the point's row is in the middle of the screen.
+    pub fn recenter(&mut self, buffer: &GapBuffer) {
+        let point = self.point_row(buffer);
+        let (top_line, top_row) = self.step(buffer, (point.line, point.row), -((self.height / 2) as isize));
+
+        self.top_line = top_line;
+        self.top_row = top_row;
+    }
+
+    /// move_point_by_rows() moves the point `count` screen rows down, or up for a negative
+    /// `count`, keeping its screen column in the row where the row is long enough. Without
+    /// wrapping this is the same as moving by lines.
+    pub fn move_point_by_rows(&self, buffer: &mut GapBuffer, count: isize) {
+        let point = self.point_row(buffer);
+        let target = self.step(buffer, (point.line, point.row), count);
+
+        let column = self.row_column(buffer, &point, buffer.get_point());
+        self.move_point_to_row(buffer, target, column);
+    }
+
+    /// move_point_into_view() moves the point, keeping its column, to the nearest row that is on
+    /// screen and outside the scroll margins.
+    fn move_point_into_view(&mut self, buffer: &mut GapBuffer) {
+        let point = self.point_row(buffer);
+        let position = (point.line, point.row);
+        let margin = self.margin();
+        let top = (self.top_line, self.top_row);
+        let first = if top == (0, 0) { top } else { self.step(buffer, top, margin as isize) };
+        let last = cmp::max(self.step(buffer, top, self.height.saturating_sub(margin + 1) as isize), first);
+
+        let target = if position < first {
+            first
+        } else if position > last {
+            last
+        } else {
+            return;
+        };
+        let column = self.row_column(buffer, &point, buffer.get_point());
+        self.move_point_to_row(buffer, target, column);
+    }
+
+    /// move_point_to_row() moves the point to screen column `column` of screen row `row` of
+    /// `line`, or to the end of the row if it is shorter.
+    fn move_point_to_row(&self, buffer: &mut GapBuffer, (line, row): (usize, usize), column: usize) {
+        let row = &self.line_rows(buffer, line)[row];
+        let offset = self.offset_in_row(buffer, row, column);
+
+        buffer.set_point(offset);
+    }
+
+    /// row_column() is the screen column `offset` is drawn at, counting from the start of `row`.
+    fn row_column(&self, buffer: &GapBuffer, row: &VisualRow, offset: usize) -> usize {
+        wrap::display_width(&buffer.slice(row.range.start..offset), self.tab_width)
+    }
+
+    /// offset_in_row() is the offset of the character drawn over screen column `column` of `row`,
+    /// counting from the start of the row, or the end of the row if it is shorter. A column inside
+    /// a tab or wide character is on that character.
+    fn offset_in_row(&self, buffer: &GapBuffer, row: &VisualRow, column: usize) -> usize {
+        let end = if row.last {
+            row.range.end
+        } else {
+            buffer.prev_char_boundary(row.range.end).unwrap()
+        };
+
+        let mut offset = row.range.start;
+        let mut width = 0;
+        for character in buffer.slice(row.range.clone()).chars() {
+            width = wrap::advance(width, character, self.tab_width);
+            if width > column || offset >= end {
+                break;
+            }
+            offset += character.len_utf8();
+        }
+        offset.min(end)
+    }
+
+    /// step() returns the screen row `count` rows after `(line, row)`, or before it for a negative
+    /// `count`, stopping at the first and last rows of the buffer.
+    fn step(&self, buffer: &GapBuffer, (mut line, mut row): (usize, usize), count: isize) -> (usize, usize) {
+        let last_line = buffer.line_count() - 1;
+        if self.wrap == Wrap::Off {
+            let line = if count < 0 {
+                line.saturating_sub(count.unsigned_abs())
+            } else {
+                line.saturating_add(count as usize).min(last_line)
+            };
+            return (line, 0);
+        }
+
+        if count < 0 {
+            for _ in 0..count.unsigned_abs() {
+                if row > 0 {
+                    row -= 1;
+                } else if line > 0 {
+                    line -= 1;
+                    row = self.line_rows(buffer, line).len() - 1;
+                } else {
+                    break;
+                }
+            }
+        } else {
+            let mut rows = self.line_rows(buffer, line.min(last_line)).len();
+            for _ in 0..count {
+                if row + 1 < rows {
+                    row += 1;
+                } else if line < last_line {
+                    line += 1;
+                    row = 0;
+                    rows = self.line_rows(buffer, line).len();
+                } else {
+                    break;
+                }
+            }
+        }
+
+        (line, row)
+    }
+
+    /// margin() is the scroll margin, limited so the point always has somewhere to be.
+    fn margin(&self) -> usize {
+        self.scroll_margin.min(self.height.saturating_sub(1) / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sign::Sign;
+    use view::{LineNumbers, View, Wrap};
+    use GapBuffer;
+
+    fn numbered_lines(count: usize) -> GapBuffer {
+        let text: Vec<String> = (0..count).map(|line| format!("line {}", line)).collect();
+        let mut buffer = GapBuffer::from(text.join("\n"));
+        buffer.set_point(0);
+        buffer
+    }
+
+    fn point_line(buffer: &GapBuffer) -> usize {
+        buffer.offset_to_line_col(buffer.get_point()).0
+    }
+
+    #[test]
+    fn scroll_to_follow_the_point() {
+        let mut buffer = numbered_lines(100);
+        let mut view = View::new(80, 10);
+
+        buffer.move_point_by_lines(15);
+        view.keep_point_visible(&buffer);
+        assert_eq!(view.top_line(), 6);
+        assert_eq!(view.visible_lines(&buffer), 6..16);
+
+        buffer.move_point_by_lines(-12);
+        view.keep_point_visible(&buffer);
+        assert_eq!(view.top_line(), 3);
+    }
+
+    #[test]
+    fn scroll_margins_keep_context_around_the_point() {
+        let mut buffer = numbered_lines(100);
+        let mut view = View::new(80, 10);
+        view.set_scroll_margin(3);
+
+        buffer.move_point_by_lines(7);
+        view.keep_point_visible(&buffer);
+        assert_eq!(view.top_line(), 1);
+
+        buffer.move_point_by_lines(-7);
+        view.keep_point_visible(&buffer);
+        assert_eq!(view.top_line(), 0);
+
+        buffer.move_point_by_lines(99);
+        view.keep_point_visible(&buffer);
+        assert_eq!(view.top_line(), 90);
+    }
+
+    #[test]
+    fn page_through_the_buffer() {
+        let mut buffer = numbered_lines(25);
+        let mut view = View::new(80, 10);
+
+        assert!(view.page_down(&mut buffer));
+        assert_eq!(view.top_line(), 8);
+        assert_eq!(point_line(&buffer), 8);
+
+        assert!(view.page_down(&mut buffer));
+        assert!(view.page_down(&mut buffer));
+        assert_eq!(view.top_line(), 24);
+        assert!(!view.page_down(&mut buffer));
+
+        assert!(view.page_up(&mut buffer));
+        assert_eq!(view.top_line(), 16);
+        assert_eq!(point_line(&buffer), 24);
+    }
+
+    #[test]
+    fn recenter_on_the_point() {
+        let mut buffer = numbered_lines(100);
+        let mut view = View::new(80, 10);
+        buffer.move_point_by_lines(50);
+
+        view.recenter(&buffer);
+
+        assert_eq!(view.top_line(), 45);
+    }
+
+    #[test]
+    fn number_lines_in_the_gutter() {
+        let mut buffer = numbered_lines(12);
+        let mut view = View::new(80, 10);
+        buffer.move_point_by_lines(9);
+
+        let test_cases = [
+            ("no line numbers", LineNumbers::Off, 0, ["", "", ""]),
+            ("absolute line numbers", LineNumbers::Absolute, 3, [" 1 ", "10 ", "12 "]),
+            ("relative line numbers", LineNumbers::Relative, 3, [" 9 ", "10 ", " 2 "]),
+        ];
+
+        for (name, line_numbers, width, gutters) in test_cases.iter() {
+            view.set_line_numbers(*line_numbers);
+
+            assert_eq!(view.gutter_width(&buffer), *width, "Test case: \"{}\" failed.", name);
+            let actual: Vec<String> = [0, 9, 11].iter().map(|line| view.gutter(&buffer, *line)).collect();
+            assert_eq!(actual, gutters, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn the_gutter_grows_with_the_buffer() {
+        let mut buffer = numbered_lines(99);
+        let mut view = View::new(80, 10);
+        view.set_line_numbers(LineNumbers::Absolute);
+        assert_eq!(view.text_width(&buffer), 77);
+
+        buffer.insert_str("\n");
+
+        assert_eq!(view.gutter_width(&buffer), 4);
+        assert_eq!(view.gutter(&buffer, 0), "  1 ");
+        assert_eq!(view.text_width(&buffer), 76);
+    }
+
+    #[test]
+    fn make_room_for_signs_in_the_gutter() {
+        let mut buffer = numbered_lines(12);
+        let mut view = View::new(80, 10);
+        buffer.set_signs("git", vec![Sign { lines: 9..10, text: '+', style: "git.added".to_string() }]);
+
+        assert_eq!(view.gutter_width(&buffer), 1);
+        assert_eq!((view.gutter(&buffer, 0), view.gutter(&buffer, 9)), (" ".to_string(), "+".to_string()));
+        view.set_line_numbers(LineNumbers::Absolute);
+        assert_eq!(view.gutter_width(&buffer), 4);
+        assert_eq!((view.gutter(&buffer, 0), view.gutter(&buffer, 9)), ("  1 ".to_string(), "+10 ".to_string()));
+    }
+
+    #[test]
+    fn wrapped_lines_take_several_rows() {
+        let mut buffer = GapBuffer::from("the quick brown fox\njumps".to_string());
+        let mut view = View::new(10, 3);
+        view.set_wrap(Wrap::Word);
+
+        let rows: Vec<String> = view.visible_rows(&buffer).iter().map(|row| buffer.slice(row.range.clone()).into_owned()).collect();
+        assert_eq!(rows, ["the quick ", "brown fox", "jumps"]);
+
+        view.set_size(5, 3);
+        assert_eq!(view.visible_lines(&buffer), 0..1);
+        buffer.set_point(0);
+        buffer.insert_str("a ");
+        assert_eq!(view.line_rows(&buffer, 0).len(), 4);
+    }
+
+    #[test]
+    fn rewrap_when_the_view_is_resized() {
+        let mut buffer = GapBuffer::from(format!("{}\nend", "x".repeat(40)));
+        let mut view = View::new(10, 2);
+        view.set_wrap(Wrap::Char);
+        buffer.set_point(35);
+        view.keep_point_visible(&buffer);
+        assert_eq!((view.top_line(), view.top_row()), (0, 2));
+
+        view.set_size(30, 2);
+        view.keep_point_visible(&buffer);
+        assert_eq!((view.top_line(), view.top_row()), (0, 1));
+        assert_eq!(view.point_row(&buffer).range, 30..40);
+
+        view.set_size(30, 1);
+        view.keep_offset_visible(&buffer, 43);
+        assert_eq!((view.top_line(), view.top_row()), (1, 0));
+    }
+
+    #[test]
+    fn move_by_screen_rows() {
+        let mut buffer = GapBuffer::from("the quick brown fox\njumps".to_string());
+        let mut view = View::new(10, 2);
+        view.set_wrap(Wrap::Char);
+        buffer.set_point(2);
+
+        view.move_point_by_rows(&mut buffer, 1);
+        assert_eq!(buffer.get_point(), 12);
+        view.move_point_by_rows(&mut buffer, 1);
+        assert_eq!(buffer.get_point(), 22);
+        view.keep_point_visible(&buffer);
+        assert_eq!((view.top_line(), view.top_row()), (0, 1));
+
+        view.move_point_by_rows(&mut buffer, -1);
+        view.move_point_by_rows(&mut buffer, -1);
+        assert_eq!(buffer.get_point(), 2);
+        view.keep_point_visible(&buffer);
+        assert_eq!((view.top_line(), view.top_row()), (0, 0));
+    }
+
+    #[test]
+    fn scroll_sideways_to_follow_the_point() {
+        let mut buffer = GapBuffer::from(format!("{}\nshort", "x".repeat(100)));
+        let mut view = View::new(20, 5);
+        view.set_horizontal_step(10);
+
+        let test_cases = [
+            ("the start of the line", 0, 0),
+            ("under the right indicator", 19, 10),
+            ("a step is not far enough", 60, 42),
+            ("near the left edge", 40, 32),
+            ("under the left indicator", 32, 22),
+            ("back at the start", 0, 0),
+        ];
+
+        for (name, point, offset) in test_cases.iter() {
+            buffer.set_point(*point);
+            view.keep_point_visible(&buffer);
+
+            assert_eq!(view.horizontal_offset(), *offset, "Test case: \"{}\" failed.", name);
+        }
+
+        buffer.set_point(60);
+        view.set_horizontal_step(0);
+        view.keep_point_visible(&buffer);
+        assert_eq!(view.horizontal_offset(), 51);
+        let rows = view.visible_rows(&buffer);
+        assert_eq!(view.clipped(&buffer, &rows[0]), (true, true));
+        assert_eq!(view.clipped(&buffer, &rows[1]), (true, false));
+    }
+
+    #[test]
+    fn find_the_text_at_a_screen_position() {
+        let buffer = GapBuffer::from("a\tb\nthe quick brown fox\n".to_string());
+        let mut view = View::new(10, 5);
+        view.set_wrap(Wrap::Char);
+
+        let test_cases = [
+            ("a character", 0, 0, 0),
+            ("inside a tab", 5, 0, 1),
+            ("after a tab", 8, 0, 2),
+            ("past the end of a line", 9, 0, 3),
+            ("a wrapped row", 2, 2, 16),
+            ("past the end of a wrapped row", 10, 1, 13),
+            ("below the last line", 3, 4, 24),
+        ];
+
+        for (name, column, row, expected) in test_cases.iter() {
+            assert_eq!(view.offset_at(&buffer, *column, *row), *expected, "Test case: \"{}\" failed.", name);
+        }
+
+        view.set_line_numbers(LineNumbers::Absolute);
+        assert_eq!(view.offset_at(&buffer, 4, 2), 6);
+    }
+}
@@ -0,0 +1,151 @@
+use std::iter;
+use std::ops::Range;
+
+use unicode_width::UnicodeWidthChar;
+
+/// Wrap is how lines wider than the view are shown.
+/// - `Off`: Long lines run off the right edge.
+/// - `Char`: Long lines continue on the next screen row at the edge.
+/// - `Word`: Long lines continue on the next screen row after the last space that fits, or at the
+///   edge for a word wider than the view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Wrap {
+    #[default]
+    Off,
+    Char,
+    Word,
+}
+
+impl Wrap {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Wrap::Off => "off",
+            Wrap::Char => "char",
+            Wrap::Word => "word",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Wrap> {
+        [Wrap::Off, Wrap::Char, Wrap::Word].iter().find(|wrap| wrap.name() == name).cloned()
+    }
+}
+
+/// VisualRow is one screen row of a view: all or part of a line of the buffer.
+/// - `line`: The line the row shows.
+/// - `row`: Which of the line's rows it is, counting from zero.
+/// - `range`: The bytes of the buffer it shows, without the newline.
+/// - `last`: Whether it is the line's last row, so the end of `range` is the end of the line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisualRow {
+    pub line: usize,
+    pub row: usize,
+    pub range: Range<usize>,
+    pub last: bool,
+}
+
+impl VisualRow {
+    /// contains() reports whether the point belongs on this row when it is at `offset`. An offset
+    /// where a line is wrapped belongs to the row it starts.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.range.start <= offset && (offset < self.range.end || self.last && offset == self.range.end)
+    }
+}
+
+/// wrap_line() splits `text`, one line without its newline, into the byte ranges shown on each
+/// screen row of a view `width` columns wide. Tabs stop every `tab_width` columns, counted from
+/// the start of each row the way rows are drawn. There is always at least one row. When wrapping at words, the spaces that
+/// end a row are kept on it even past the edge, so no row starts with a space.
+pub fn wrap_line(text: &str, width: usize, wrap: Wrap, tab_width: usize) -> Vec<Range<usize>> {
+    if wrap == Wrap::Off || width == 0 {
+        return iter::once(0..text.len()).collect();
+    }
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut column = 0;
+    let mut last_space = None;
+
+    for (index, character) in text.char_indices() {
+        let space = character == ' ' || character == '\t';
+        if advance(column, character, tab_width) > width && index > start && !(space && wrap == Wrap::Word) {
+            let end = match last_space {
+                Some(end) if wrap == Wrap::Word => end,
+                _ => index,
+            };
+            rows.push(start..end);
+            column = display_width(&text[end..index], tab_width);
+            start = end;
+            last_space = None;
+        }
+
+        column = advance(column, character, tab_width);
+        if space {
+            last_space = Some(index + character.len_utf8());
+        }
+    }
+
+    rows.push(start..text.len());
+    rows
+}
+
+/// display_width() is how many columns `text` takes up, with tabs stopping every `tab_width`
+/// columns, wide characters taking two columns, and combining and other zero-width characters
+/// none.
+pub fn display_width(text: &str, tab_width: usize) -> usize {
+    text.chars().fold(0, |column, character| advance(column, character, tab_width))
+}
+
+/// advance() is the column after `character` is drawn at `column`.
+pub(crate) fn advance(column: usize, character: char, tab_width: usize) -> usize {
+    match character {
+        '\t' => column + tab_width - column % tab_width,
+        _ => column + character.width().unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use view::wrap::{display_width, wrap_line, Wrap};
+
+    #[test]
+    fn wrap_long_lines() {
+        let test_cases = [
+            ("no wrapping", "the quick brown fox", 8, Wrap::Off, vec!["the quick brown fox"]),
+            ("a short line", "the quick", 20, Wrap::Char, vec!["the quick"]),
+            ("an empty line", "", 8, Wrap::Char, vec![""]),
+            ("at the edge", "the quick brown fox", 8, Wrap::Char, vec!["the quic", "k brown ", "fox"]),
+            ("at words", "the quick brown fox", 10, Wrap::Word, vec!["the quick ", "brown fox"]),
+            ("a long word", "a abcdefghijk", 5, Wrap::Word, vec!["a ", "abcde", "fghij", "k"]),
+            ("spaces at the edge", "quick  brown", 5, Wrap::Word, vec!["quick  ", "brown"]),
+            ("a tab", "ab\tcdefghij", 10, Wrap::Char, vec!["ab\tcd", "efghij"]),
+            ("multibyte characters", "ééééé", 2, Wrap::Char, vec!["éé", "éé", "é"]),
+            ("wide characters", "日本語です", 5, Wrap::Char, vec!["日本", "語で", "す"]),
+            ("combining marks", "e\u{301}e\u{301}e\u{301}", 2, Wrap::Char, vec!["e\u{301}e\u{301}", "e\u{301}"]),
+        ];
+
+        for (name, text, width, wrap, expected) in test_cases.iter() {
+            let rows: Vec<&str> = wrap_line(text, *width, *wrap, 8).into_iter().map(|row| &text[row]).collect();
+
+            assert_eq!(rows, *expected, "Test case: \"{}\" failed.", name);
+        }
+    }
+
+    #[test]
+    fn tabs_are_expanded_when_measuring() {
+        assert_eq!(display_width("ab\t", 8), 8);
+        assert_eq!(display_width("ab\tc", 8), 9);
+        assert_eq!(display_width("ab\tc", 4), 5);
+        assert_eq!(display_width("abcd\t", 4), 8);
+        assert_eq!(display_width("éé", 8), 2);
+        assert_eq!(display_width("日本", 8), 4);
+        assert_eq!(display_width("e\u{301}\u{200b}", 8), 1);
+    }
+
+    #[test]
+    fn tabs_wrap_at_their_width() {
+        let text = "a\tb\tc";
+        let rows: Vec<&str> = wrap_line(text, 6, Wrap::Char, 4).into_iter().map(|row| &text[row]).collect();
+
+        assert_eq!(rows, vec!["a\tb", "\tc"]);
+    }
+}
@@ -0,0 +1,577 @@
+use anchor::AnchorId;
+use buffer_manager::BufferId;
+use view::View;
+
+/// The smallest a window can be made, in lines including its status line, and in columns.
+pub const WINDOW_MIN_HEIGHT: usize = 4;
+pub const WINDOW_MIN_WIDTH: usize = 10;
+
+/// WindowId identifies a window held by `Windows`. Ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct WindowId(usize);
+
+/// Direction is which way a window is split.
+/// - `Below`: The new window goes below, and the two share the height.
+/// - `Right`: The new window goes to the right, and the two share the width with a divider column
+///   between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Below,
+    Right,
+}
+
+/// Area is the part of the screen a window takes up, in columns and lines from the top left. It
+/// includes the window's status line but not the divider to its right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Area {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Area {
+    /// length() is the size of the area in `direction`.
+    fn length(&self, direction: Direction) -> usize {
+        match direction {
+            Direction::Below => self.height,
+            Direction::Right => self.width,
+        }
+    }
+}
+
+/// Target is what is on screen at a position.
+/// - `Text`: The text of a window, at `column` and `row` from its top left.
+/// - `StatusLine`: The status line of a window.
+/// - `Divider`: The divider to the right of a window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Text { window: WindowId, column: usize, row: usize },
+    StatusLine(WindowId),
+    Divider(WindowId),
+}
+
+/// Window shows a buffer on part of the screen.
+/// - `buffer`: The buffer shown.
+/// - `view`: The part of the buffer on screen.
+/// - `point`: Where the point was left while another window was selected. Windows onto the same
+///   buffer each have their own point, kept as an anchor in the buffer.
+#[derive(Debug, Clone)]
+pub struct Window {
+    id: WindowId,
+    buffer: BufferId,
+    view: View,
+    pub(crate) point: Option<AnchorId>,
+}
+
+impl Window {
+    pub fn id(&self) -> WindowId {
+        self.id
+    }
+
+    pub fn buffer(&self) -> BufferId {
+        self.buffer
+    }
+
+    pub fn view(&self) -> &View {
+        &self.view
+    }
+
+    pub fn view_mut(&mut self) -> &mut View {
+        &mut self.view
+    }
+}
+
+/// Node is part of the window layout: a window, or windows sharing an area between them. Each
+/// child of a split has a weight, its share of the split's length in `direction`. Laying out
+/// sets the weights to the lengths given, so resizing can work in lines and columns.
+#[derive(Debug, Clone)]
+enum Node {
+    Window(WindowId),
+    Split { direction: Direction, children: Vec<(Node, usize)> },
+}
+
+impl Node {
+    fn contains(&self, id: WindowId) -> bool {
+        match self {
+            Node::Window(window) => *window == id,
+            Node::Split { children, .. } => children.iter().any(|(child, _)| child.contains(id)),
+        }
+    }
+
+    /// windows() lists the windows in the node from top left to bottom right.
+    fn windows(&self, windows: &mut Vec<WindowId>) {
+        match self {
+            Node::Window(window) => windows.push(*window),
+            Node::Split { children, .. } => children.iter().for_each(|(child, _)| child.windows(windows)),
+        }
+    }
+
+    /// min_length() is the smallest the node can be in `direction`.
+    fn min_length(&self, direction: Direction) -> usize {
+        match self {
+            Node::Window(_) => match direction {
+                Direction::Below => WINDOW_MIN_HEIGHT,
+                Direction::Right => WINDOW_MIN_WIDTH,
+            },
+            Node::Split { direction: split, children } => {
+                let lengths = children.iter().map(|(child, _)| child.min_length(direction));
+                if *split != direction {
+                    lengths.max().unwrap_or(0)
+                } else if direction == Direction::Right {
+                    lengths.sum::<usize>() + children.len() - 1
+                } else {
+                    lengths.sum()
+                }
+            }
+        }
+    }
+
+    /// layout() gives each window in the node its part of `area`.
+    fn layout(&mut self, area: Area, areas: &mut Vec<(WindowId, Area)>) {
+        let (direction, children) = match self {
+            Node::Window(window) => return areas.push((*window, area)),
+            Node::Split { direction, children } => (*direction, children),
+        };
+
+        let dividers = match direction {
+            Direction::Below => 0,
+            Direction::Right => children.len() - 1,
+        };
+        let available = area.length(direction).saturating_sub(dividers);
+        let total: usize = children.iter().map(|(_, weight)| *weight).sum();
+        let count = children.len();
+        let mut position = 0;
+        let mut given = 0;
+
+        for (index, (child, weight)) in children.iter_mut().enumerate() {
+            let length = match index + 1 == count {
+                true => available - given,
+                false => available * *weight / total.max(1),
+            };
+            let child_area = match direction {
+                Direction::Below => Area { y: area.y + position, height: length, ..area },
+                Direction::Right => Area { x: area.x + position, width: length, ..area },
+            };
+            child.layout(child_area, areas);
+
+            *weight = length;
+            given += length;
+            position += length + if direction == Direction::Right { 1 } else { 0 };
+        }
+    }
+
+    /// split() puts `new` beside window `id` and gives it half of the window's length.
+    fn split(&mut self, id: WindowId, new: WindowId, direction: Direction, length: usize) -> bool {
+        match self {
+            Node::Window(window) if *window == id => {
+                let first = length - length / 2;
+                *self = Node::Split {
+                    direction,
+                    children: vec![(Node::Window(id), first), (Node::Window(new), length - first)],
+                };
+                true
+            }
+            Node::Window(_) => false,
+            Node::Split { direction: split, children } => {
+                let position = children.iter().position(|(child, _)| child.contains(id)).unwrap();
+                match &children[position].0 {
+                    Node::Window(_) if *split == direction => {
+                        let weight = children[position].1;
+                        children[position].1 = weight - weight / 2;
+                        children.insert(position + 1, (Node::Window(new), weight / 2));
+                        true
+                    }
+                    _ => children[position].0.split(id, new, direction, length),
+                }
+            }
+        }
+    }
+
+    /// remove() takes window `id` out of the node, giving its length to a neighbour, and returns
+    /// false if the node is that window.
+    fn remove(&mut self, id: WindowId) -> bool {
+        let children = match self {
+            Node::Window(_) => return false,
+            Node::Split { children, .. } => children,
+        };
+
+        let position = children.iter().position(|(child, _)| child.contains(id)).unwrap();
+        if children[position].0.remove(id) {
+            return true;
+        }
+        let (_, weight) = children.remove(position);
+        let neighbour = position.min(children.len() - 1);
+        children[neighbour].1 += weight;
+
+        if children.len() == 1 {
+            *self = children.remove(0).0;
+        }
+        true
+    }
+
+    /// resize() grows window `id` by `delta` in `direction`, or shrinks it for a negative `delta`,
+    /// taking the difference from its next neighbour, or its previous one for the last window.
+    /// It returns how much it changed by, which is less than asked where a window would become
+    /// too small.
+    fn resize(&mut self, id: WindowId, direction: Direction, delta: isize) -> isize {
+        let (split, children) = match self {
+            Node::Window(_) => return 0,
+            Node::Split { direction, children } => (*direction, children),
+        };
+
+        let position = children.iter().position(|(child, _)| child.contains(id)).unwrap();
+        let resized = children[position].0.resize(id, direction, delta);
+        if resized != 0 || split != direction || children.len() < 2 {
+            return resized;
+        }
+
+        let neighbour = if position + 1 < children.len() { position + 1 } else { position - 1 };
+        let grows = children[position].1 as isize;
+        let gives = children[neighbour].1 as isize;
+        let delta = delta
+            .min(gives - children[neighbour].0.min_length(direction) as isize)
+            .max(children[position].0.min_length(direction) as isize - grows)
+            .min(gives.max(0))
+            .max(-grows);
+
+        children[position].1 = (grows + delta) as usize;
+        children[neighbour].1 = (gives - delta) as usize;
+        delta
+    }
+
+    fn balance(&mut self) {
+        if let Node::Split { children, .. } = self {
+            for (child, weight) in children.iter_mut() {
+                child.balance();
+                *weight = 1;
+            }
+        }
+    }
+}
+
+/// Windows holds the windows on screen, how they are laid out and which one is selected.
+/// - `area`: The screen area the windows share, from the last layout.
+#[derive(Debug, Clone)]
+pub struct Windows {
+    windows: Vec<Window>,
+    layout: Node,
+    selected: WindowId,
+    area: Area,
+    next_id: usize,
+}
+
+impl Windows {
+    /// new() creates a single window showing `buffer` in `view`.
+    pub fn new(buffer: BufferId, view: View) -> Windows {
+        let id = WindowId(0);
+        let area = Area {
+            x: 0,
+            y: 0,
+            width: view.width(),
+            height: view.height() + 1,
+        };
+
+        Windows {
+            windows: vec![Window { id, buffer, view, point: None }],
+            layout: Node::Window(id),
+            selected: id,
+            area,
+            next_id: 1,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    pub fn get(&self, id: WindowId) -> Option<&Window> {
+        self.windows.iter().find(|window| window.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: WindowId) -> Option<&mut Window> {
+        self.windows.iter_mut().find(|window| window.id == id)
+    }
+
+    /// ids() lists the windows from top left to bottom right, the order `next()` visits them.
+    pub fn ids(&self) -> Vec<WindowId> {
+        let mut ids = Vec::new();
+        self.layout.windows(&mut ids);
+
+        ids
+    }
+
+    pub fn selected_id(&self) -> WindowId {
+        self.selected
+    }
+
+    pub fn selected(&self) -> &Window {
+        self.get(self.selected).unwrap()
+    }
+
+    pub fn selected_mut(&mut self) -> &mut Window {
+        let id = self.selected;
+        self.get_mut(id).unwrap()
+    }
+
+    /// select() makes window `id` the selected one and returns false if there is no such window.
+    pub fn select(&mut self, id: WindowId) -> bool {
+        if self.get(id).is_none() {
+            return false;
+        }
+
+        self.selected = id;
+        true
+    }
+
+    /// next() is the window after `id`, going back to the first after the last.
+    pub fn next(&self, id: WindowId) -> WindowId {
+        let ids = self.ids();
+        let position = ids.iter().position(|candidate| *candidate == id).unwrap_or(0);
+
+        ids[(position + 1) % ids.len()]
+    }
+
+    /// set_buffer() shows `buffer` in window `id`, from its start.
+    pub fn set_buffer(&mut self, id: WindowId, buffer: BufferId) {
+        if let Some(window) = self.get_mut(id) {
+            if window.buffer != buffer {
+                window.buffer = buffer;
+                window.point = None;
+                window.view.set_top_line(0);
+                window.view.set_horizontal_offset(0);
+            }
+        }
+    }
+
+    pub fn area(&self) -> Area {
+        self.area
+    }
+
+    /// layout() shares `area` out between the windows, sizes their views to fit, and returns
+    /// where each one goes. A window's view is one line shorter than its area, leaving room for
+    /// its status line.
+    pub fn layout(&mut self, area: Area) -> Vec<(WindowId, Area)> {
+        self.area = area;
+        let mut areas = Vec::new();
+        self.layout.layout(area, &mut areas);
+
+        for (id, window_area) in areas.iter() {
+            if let Some(window) = self.get_mut(*id) {
+                window.view.set_size(window_area.width, window_area.height.saturating_sub(1));
+            }
+        }
+        areas
+    }
+
+    /// target_at() is what is at column `x` of line `y` in the last layout.
+    pub fn target_at(&mut self, x: usize, y: usize) -> Option<Target> {
+        let screen = self.area;
+        self.layout(screen).into_iter().find_map(|(window, area)| {
+            if y < area.y || y >= area.y + area.height {
+                None
+            } else if x >= area.x && x < area.x + area.width {
+                match y + 1 == area.y + area.height {
+                    true => Some(Target::StatusLine(window)),
+                    false => Some(Target::Text { window, column: x - area.x, row: y - area.y }),
+                }
+            } else if x == area.x + area.width && x < screen.x + screen.width {
+                Some(Target::Divider(window))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// is_bottom() reports whether window `id` is at the bottom of the screen, so its status line
+    /// has no window below to resize.
+    pub fn is_bottom(&mut self, id: WindowId) -> bool {
+        let screen = self.area;
+        self.layout(screen)
+            .into_iter()
+            .any(|(window, area)| window == id && area.y + area.height >= screen.y + screen.height)
+    }
+
+    /// split() divides window `id` in two in `direction`. The new window shows the same buffer
+    /// from the same place, and is returned unless the window is too small to divide.
+    pub fn split(&mut self, id: WindowId, direction: Direction) -> Option<WindowId> {
+        let area = self.layout(self.area).into_iter().find(|(window, _)| *window == id)?.1;
+        let min_length = match direction {
+            Direction::Below => WINDOW_MIN_HEIGHT,
+            Direction::Right => WINDOW_MIN_WIDTH,
+        };
+        let length = area.length(direction);
+        let dividers = if direction == Direction::Right { 1 } else { 0 };
+        if length < 2 * min_length + dividers {
+            return None;
+        }
+
+        let new = WindowId(self.next_id);
+        self.next_id += 1;
+        let mut window = self.get(id)?.clone();
+        window.id = new;
+        window.point = None;
+        self.windows.push(window);
+        self.layout.split(id, new, direction, length - dividers);
+
+        self.layout(self.area);
+        Some(new)
+    }
+
+    /// delete() closes window `id`, giving its space to a neighbour, and returns it. The last
+    /// window cannot be closed. If the window was selected, the next one is selected instead.
+    pub fn delete(&mut self, id: WindowId) -> Option<Window> {
+        if self.windows.len() < 2 || self.get(id).is_none() {
+            return None;
+        }
+
+        if self.selected == id {
+            self.selected = self.next(id);
+        }
+        self.layout.remove(id);
+        let position = self.windows.iter().position(|window| window.id == id)?;
+        let window = self.windows.remove(position);
+
+        self.layout(self.area);
+        Some(window)
+    }
+
+    /// delete_others() closes every window but `id` and returns them.
+    pub fn delete_others(&mut self, id: WindowId) -> Vec<Window> {
+        if self.get(id).is_none() {
+            return Vec::new();
+        }
+
+        self.layout = Node::Window(id);
+        self.selected = id;
+        let (kept, deleted) = self.windows.drain(..).partition(|window| window.id == id);
+        self.windows = kept;
+
+        self.layout(self.area);
+        deleted
+    }
+
+    /// resize() grows window `id` by `delta` lines or columns in `direction`, or shrinks it for a
+    /// negative `delta`, and returns false if it could not change.
+    pub fn resize(&mut self, id: WindowId, direction: Direction, delta: isize) -> bool {
+        if self.get(id).is_none() {
+            return false;
+        }
+
+        self.layout(self.area);
+        let resized = self.layout.resize(id, direction, delta);
+
+        self.layout(self.area);
+        resized != 0
+    }
+
+    /// balance() makes the windows of each split the same size.
+    pub fn balance(&mut self) {
+        self.layout.balance();
+        self.layout(self.area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffer_manager::BufferManager;
+    use view::View;
+    use window::{Area, Direction, Target, Windows};
+
+    fn windows() -> Windows {
+        let mut buffers = BufferManager::new();
+        let mut windows = Windows::new(buffers.create("*scratch*"), View::new(80, 23));
+        windows.layout(Area { x: 0, y: 0, width: 80, height: 24 });
+
+        windows
+    }
+
+    fn areas(windows: &mut Windows) -> Vec<(usize, usize, usize, usize)> {
+        let area = windows.area();
+        windows
+            .layout(area)
+            .into_iter()
+            .map(|(_, area)| (area.x, area.y, area.width, area.height))
+            .collect()
+    }
+
+    #[test]
+    fn split_windows() {
+        let mut windows = windows();
+        let first = windows.selected_id();
+
+        let below = windows.split(first, Direction::Below).unwrap();
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 12), (0, 12, 80, 12)]);
+        assert_eq!(windows.get(below).unwrap().view().height(), 11);
+
+        let right = windows.split(below, Direction::Right).unwrap();
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 12), (0, 12, 40, 12), (41, 12, 39, 12)]);
+        assert_eq!(windows.ids(), [first, below, right]);
+
+        windows.split(first, Direction::Below).unwrap();
+        assert_eq!(areas(&mut windows)[..2], [(0, 0, 80, 6), (0, 6, 80, 6)]);
+        assert!(windows.split(first, Direction::Below).is_none());
+    }
+
+    #[test]
+    fn resize_and_balance_windows() {
+        let mut windows = windows();
+        let first = windows.selected_id();
+        let second = windows.split(first, Direction::Below).unwrap();
+
+        assert!(windows.resize(first, Direction::Below, 3));
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 15), (0, 15, 80, 9)]);
+        assert!(windows.resize(second, Direction::Below, 2));
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 13), (0, 13, 80, 11)]);
+
+        assert!(windows.resize(first, Direction::Below, 100));
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 20), (0, 20, 80, 4)]);
+        assert!(!windows.resize(first, Direction::Below, 1));
+        assert!(!windows.resize(first, Direction::Right, 1));
+
+        windows.balance();
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 12), (0, 12, 80, 12)]);
+    }
+
+    #[test]
+    fn delete_windows() {
+        let mut windows = windows();
+        let first = windows.selected_id();
+        let second = windows.split(first, Direction::Right).unwrap();
+        let third = windows.split(second, Direction::Below).unwrap();
+        assert_eq!(windows.next(third), first);
+
+        windows.select(second);
+        assert!(windows.delete(second).is_some());
+        assert_eq!(windows.selected_id(), third);
+        assert_eq!(areas(&mut windows), [(0, 0, 40, 24), (41, 0, 39, 24)]);
+
+        assert_eq!(windows.delete_others(first).len(), 1);
+        assert_eq!(areas(&mut windows), [(0, 0, 80, 24)]);
+        assert!(windows.delete(first).is_none());
+    }
+
+    #[test]
+    fn find_what_is_on_screen() {
+        let mut windows = windows();
+        let first = windows.selected_id();
+        let right = windows.split(first, Direction::Right).unwrap();
+
+        let test_cases = [
+            ("text", 3, 5, Some(Target::Text { window: first, column: 3, row: 5 })),
+            ("text on the right", 45, 0, Some(Target::Text { window: right, column: 4, row: 0 })),
+            ("a status line", 45, 23, Some(Target::StatusLine(right))),
+            ("a divider", 40, 10, Some(Target::Divider(first))),
+            ("off the screen", 80, 10, None),
+        ];
+
+        for (name, x, y, expected) in test_cases.iter() {
+            assert_eq!(windows.target_at(*x, *y), *expected, "Test case: \"{}\" failed.", name);
+        }
+        assert!(windows.is_bottom(first));
+    }
+}